@@ -23,6 +23,22 @@ pub enum Selected {
     Dynamic { light: Theme, dark: Theme },
 }
 
+impl Appearance {
+    /// Replaces every loaded copy of a theme with `theme`, matched by name.
+    pub fn replace(&mut self, theme: &Theme) {
+        let themes = self.all.iter_mut().chain(match &mut self.selected {
+            Selected::Static(selected) => vec![selected],
+            Selected::Dynamic { light, dark } => vec![light, dark],
+        });
+
+        for existing in themes {
+            if existing.name == theme.name {
+                *existing = theme.clone();
+            }
+        }
+    }
+}
+
 impl Default for Selected {
     fn default() -> Self {
         Self::Static(Theme::default())
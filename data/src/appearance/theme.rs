@@ -10,6 +10,10 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::fs;
 
+use crate::Config;
+
+pub mod base16;
+
 const DEFAULT_THEME_NAME: &str = "Ferra";
 const DEFAULT_THEME_CONTENT: &str = include_str!("../../../assets/themes/ferra.toml");
 
@@ -32,6 +36,11 @@ impl Theme {
     pub fn new(name: String, colors: Colors) -> Self {
         Theme { name, colors }
     }
+
+    /// Path of the file the theme is loaded from.
+    pub fn path(&self) -> PathBuf {
+        Config::themes_dir().join(format!("{}.toml", self.name))
+    }
 }
 
 // IMPORTANT: Make sure any new components are added to the theme editor
@@ -49,6 +58,12 @@ pub struct Colors {
 }
 
 impl Colors {
+    pub async fn load(path: PathBuf) -> Result<Self, Error> {
+        let content = fs::read_to_string(path).await.map_err(Error::Read)?;
+
+        Ok(toml::from_str(&content)?)
+    }
+
     pub async fn save(self, path: PathBuf) -> Result<(), Error> {
         let content = toml::to_string(&self)?;
 
@@ -74,10 +89,16 @@ impl Colors {
 pub enum Error {
     #[error("Failed to serialize theme to toml: {0}")]
     Encode(#[from] toml::ser::Error),
+    #[error("Failed to parse theme: {0}")]
+    Decode(#[from] toml::de::Error),
+    #[error("Failed to read theme file: {0}")]
+    Read(std::io::Error),
     #[error("Failed to write theme file: {0}")]
     Write(#[from] std::io::Error),
     #[error("Failed to decode base64 theme string: {0}")]
     Base64Decode(#[from] base64::DecodeError),
+    #[error("Invalid base16 scheme: {0}")]
+    Base16(&'static str),
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
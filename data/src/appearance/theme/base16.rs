@@ -0,0 +1,154 @@
+//! Converts [base16](https://github.com/tinted-theming/home) color schemes
+//! into themes.
+use std::path::PathBuf;
+
+use iced_core::Color;
+use tokio::fs;
+
+use super::{
+    alpha, hex_to_color, Buffer, Button, Buttons, Colors, Error, General, ServerMessages, Text,
+    Theme,
+};
+
+/// Reads the scheme at `path` and saves it to the themes directory.
+pub async fn import(path: PathBuf) -> Result<Theme, Error> {
+    let content = fs::read_to_string(path).await.map_err(Error::Read)?;
+
+    let theme = parse(&content)?;
+
+    theme.colors.save(theme.path()).await?;
+
+    Ok(theme)
+}
+
+/// Parses a scheme, in either the original format with its colors at the
+/// top level or the newer one with a `palette` section.
+pub fn parse(content: &str) -> Result<Theme, Error> {
+    let mut name = None;
+    let mut base = [None; 16];
+
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+
+        let value = value.trim();
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+            None => value.split(" #").next().unwrap_or_default().trim(),
+        };
+
+        match key.trim() {
+            "scheme" | "name" => name = Some(value.to_string()),
+            key => {
+                let Some(index) = key
+                    .strip_prefix("base0")
+                    .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                else {
+                    continue;
+                };
+
+                let hex = format!("#{}", value.trim_start_matches('#'));
+
+                base[index as usize] = hex_to_color(&hex);
+            }
+        }
+    }
+
+    let name = name
+        .filter(|name| !name.is_empty())
+        .ok_or(Error::Base16("missing scheme name"))?
+        .replace(['/', '\\'], "-");
+
+    let mut colors = [Color::TRANSPARENT; 16];
+
+    for (color, base) in colors.iter_mut().zip(base) {
+        *color = base.ok_or(Error::Base16("missing color"))?;
+    }
+
+    Ok(Theme::new(name, colors_from(colors)))
+}
+
+/// Maps the scheme following the base16 styling guidelines, `base00` being
+/// the default background and `base05` the default foreground.
+fn colors_from(base: [Color; 16]) -> Colors {
+    Colors {
+        general: General {
+            background: base[0x1],
+            border: base[0x2],
+            horizontal_rule: base[0x2],
+            unread_indicator: base[0x9],
+        },
+        text: Text {
+            primary: base[0x5],
+            secondary: base[0x4],
+            tertiary: base[0xE],
+            success: base[0xB],
+            error: base[0x8],
+        },
+        buffer: Buffer {
+            action: base[0xB],
+            background: base[0x0],
+            background_text_input: base[0x1],
+            background_title_bar: base[0x1],
+            border: base[0x2],
+            border_selected: base[0x3],
+            code: base[0xF],
+            highlight: alpha(base[0xA], 0.2),
+            nickname: base[0xD],
+            selection: base[0x2],
+            server_messages: ServerMessages {
+                default: base[0xA],
+                ..Default::default()
+            },
+            timestamp: base[0x3],
+            topic: base[0x4],
+            url: base[0xC],
+        },
+        buttons: Buttons {
+            primary: Button {
+                background: base[0x1],
+                background_hover: base[0x0],
+                background_selected: base[0x0],
+                background_selected_hover: base[0x2],
+            },
+            secondary: Button {
+                background: base[0x2],
+                background_hover: base[0x3],
+                background_selected: base[0x3],
+                background_selected_hover: base[0x4],
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_schemes() {
+        let legacy = "scheme: \"Test\"\nauthor: \"Someone\"\n".to_string()
+            + &(0..16)
+                .map(|i| format!("base0{i:X}: \"{i:02x}{i:02x}{i:02x}\" # comment\n"))
+                .collect::<String>();
+
+        let theme = parse(&legacy).unwrap();
+        assert_eq!(theme.name, "Test");
+        assert_eq!(theme.colors.text.primary, hex_to_color("#050505").unwrap());
+
+        let palette = "system: \"base16\"\nname: \"Test Two\"\npalette:\n".to_string()
+            + &(0..16)
+                .map(|i| format!("  base0{i:X}: \"#{i:02x}{i:02x}{i:02x}\"\n"))
+                .collect::<String>();
+
+        let theme = parse(&palette).unwrap();
+        assert_eq!(theme.name, "Test Two");
+        assert_eq!(
+            theme.colors.buffer.background,
+            hex_to_color("#000000").unwrap()
+        );
+
+        assert!(parse("scheme: \"Missing\"\nbase00: \"000000\"").is_err());
+    }
+}
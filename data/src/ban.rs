@@ -0,0 +1,158 @@
+//! Ban masks generated from what we know of a user.
+use std::collections::HashMap;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::isupport::{Kind, Parameter};
+use crate::User;
+
+/// The kinds of mask a user can be banned by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mask {
+    /// `nick!*@*`
+    Nick,
+    /// `*!user@host`
+    User,
+    /// `*!*@host`
+    #[default]
+    Host,
+    /// `*!*@*.domain`, or the network of an IPv4 address
+    Domain,
+    /// The account extban, e.g. `$a:account`
+    Account,
+}
+
+impl Mask {
+    pub const ALL: [Mask; 5] = [
+        Mask::Nick,
+        Mask::User,
+        Mask::Host,
+        Mask::Domain,
+        Mask::Account,
+    ];
+
+    /// The mask banning `user`, or none if we don't know enough of them.
+    ///
+    /// `extban` is the prefix of the account extban, e.g. `$a`.
+    pub fn of(&self, user: &User, extban: Option<&str>) -> Option<String> {
+        let host = || user.hostname().filter(|host| !host.is_empty());
+
+        match self {
+            Mask::Nick => Some(format!("{}!*@*", user.nickname())),
+            Mask::User => {
+                let username = user.username()?;
+                // Idents not verified by the server are prefixed with `~`
+                let username = match username.strip_prefix('~') {
+                    Some(username) => format!("*{username}"),
+                    None => username.to_string(),
+                };
+
+                Some(format!("*!{username}@{}", host()?))
+            }
+            Mask::Host => Some(format!("*!*@{}", host()?)),
+            Mask::Domain => Some(format!("*!*@{}", domain(host()?))),
+            Mask::Account => {
+                let account = user.accountname().filter(|account| *account != "*")?;
+
+                Some(format!("{}:{account}", extban?))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Mask::Nick => "nick",
+            Mask::User => "user",
+            Mask::Host => "host",
+            Mask::Domain => "domain",
+            Mask::Account => "account",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Mask {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mask::ALL
+            .into_iter()
+            .find(|mask| mask.to_string().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// The host with its first label left out, so everyone connecting from the
+/// same domain matches. IPv4 addresses keep their first three octets instead,
+/// and hosts too short to have a domain, IPv6 addresses and cloaks are kept
+/// whole.
+fn domain(host: &str) -> String {
+    if let Ok(ip) = host.parse::<Ipv4Addr>() {
+        let [a, b, c, _] = ip.octets();
+
+        return format!("{a}.{b}.{c}.*");
+    }
+
+    match host.split_once('.') {
+        Some((_, domain)) if domain.contains('.') && !host.contains(['/', ':']) => {
+            format!("*.{domain}")
+        }
+        _ => host.to_string(),
+    }
+}
+
+/// The prefix of account extbans advertised by the server, e.g. `$a` or
+/// `~account`.
+pub fn account_extban(isupport: &HashMap<Kind, Parameter>) -> Option<String> {
+    let (prefix, types) = match isupport.get(&Kind::EXTBAN) {
+        Some(Parameter::EXTBAN(prefix, types)) => (*prefix, types.as_str()),
+        _ => return None,
+    };
+    let prefix = prefix.map(String::from).unwrap_or_default();
+
+    match isupport.get(&Kind::ACCOUNTEXTBAN) {
+        Some(Parameter::ACCOUNTEXTBAN(names)) => {
+            names.first().map(|name| format!("{prefix}{name}"))
+        }
+        _ => types.contains('a').then(|| format!("{prefix}a")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks() {
+        let user = User::try_from("halloy!~squidowl@irc.example.org").unwrap();
+        let tests = [
+            (Mask::Nick, Some("halloy!*@*")),
+            (Mask::User, Some("*!*squidowl@irc.example.org")),
+            (Mask::Host, Some("*!*@irc.example.org")),
+            (Mask::Domain, Some("*!*@*.example.org")),
+            (Mask::Account, None),
+        ];
+
+        for (mask, expected) in tests {
+            assert_eq!(mask.of(&user, Some("$a")).as_deref(), expected, "{mask}");
+        }
+
+        let tests = [
+            ("192.168.0.1", "192.168.0.*"),
+            ("example.org", "example.org"),
+            ("2001:db8::1", "2001:db8::1"),
+            ("user/halloy", "user/halloy"),
+        ];
+
+        for (host, expected) in tests {
+            assert_eq!(domain(host), expected);
+        }
+    }
+}
@@ -2,14 +2,24 @@ use core::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use crate::user::Nick;
-use crate::{channel, config, message, Server};
+use crate::user::{Nick, User};
+use crate::{channel, config, message, Config, Server};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Buffer {
     Upstream(Upstream),
     Internal(Internal),
+    /// Bans, quiets and exceptions of a channel. Ahead of `Console`, which
+    /// would otherwise match it when deserializing.
+    ModeLists {
+        server: Server,
+        channel: String,
+    },
+    /// Raw lines sent to and received from a server.
+    Console {
+        server: Server,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -25,6 +35,8 @@ pub enum Internal {
     FileTransfers,
     Logs,
     Highlights,
+    #[strum(serialize = "URLs")]
+    Urls,
 }
 
 impl Buffer {
@@ -86,7 +98,12 @@ impl Upstream {
 }
 
 impl Internal {
-    pub const ALL: &'static [Self] = &[Self::FileTransfers, Self::Logs, Self::Highlights];
+    pub const ALL: &'static [Self] = &[
+        Self::FileTransfers,
+        Self::Logs,
+        Self::Highlights,
+        Self::Urls,
+    ];
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -102,12 +119,131 @@ impl From<config::Buffer> for Settings {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, Deserialize)]
+impl Settings {
+    /// The settings `buffer` opens with, taking its channel's section in the
+    /// server config into account.
+    pub fn new(buffer: &Buffer, config: &Config) -> Self {
+        let mut settings = Self::from(config.buffer.clone());
+
+        if let Some(Upstream::Channel(server, channel)) = buffer.upstream() {
+            if let Some(enabled) = config.servers.channel_settings(server, channel).nicklist {
+                settings.channel.nicklist.enabled = enabled;
+            }
+        }
+
+        settings
+    }
+}
+
+/// Scale of a buffer's content, on top of the configured font size.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Zoom(f32);
+
+impl Zoom {
+    const STEP: f32 = 0.1;
+    const MIN: f32 = 0.5;
+    const MAX: f32 = 3.0;
+
+    fn new(scale: f32) -> Self {
+        // Rounded so repeated steps don't drift
+        Self((scale.clamp(Self::MIN, Self::MAX) * 10.0).round() / 10.0)
+    }
+
+    pub fn scale(self) -> f32 {
+        self.0
+    }
+
+    pub fn zoom_in(self) -> Self {
+        Self::new(self.0 + Self::STEP)
+    }
+
+    pub fn zoom_out(self) -> Self {
+        Self::new(self.0 - Self::STEP)
+    }
+
+    pub fn is_default(self) -> bool {
+        self == Self::default()
+    }
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct TextInput {
     #[serde(default)]
     pub visibility: TextInputVisibility,
     #[serde(default)]
     pub auto_format: AutoFormat,
+    #[serde(default)]
+    pub autocomplete: Autocomplete,
+    #[serde(default)]
+    pub paste: Paste,
+    /// Show buttons inserting formatting codes above the input
+    #[serde(default)]
+    pub formatting_toolbar: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Autocomplete {
+    /// Appended to a completed nickname at the start of the input, and
+    /// anywhere else
+    #[serde(default = "default_completion_suffixes")]
+    pub completion_suffixes: [String; 2],
+}
+
+impl Default for Autocomplete {
+    fn default() -> Self {
+        Self {
+            completion_suffixes: default_completion_suffixes(),
+        }
+    }
+}
+
+impl Autocomplete {
+    pub fn completion_suffix(&self, is_start: bool) -> &str {
+        if is_start {
+            &self.completion_suffixes[0]
+        } else {
+            &self.completion_suffixes[1]
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Paste {
+    /// Pastes of at least this many lines ask how to send them
+    #[serde(default = "default_paste_confirm_lines")]
+    pub confirm_lines: usize,
+    pub pastebin: Option<Pastebin>,
+}
+
+impl Default for Paste {
+    fn default() -> Self {
+        Self {
+            confirm_lines: default_paste_confirm_lines(),
+            pastebin: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pastebin {
+    /// Where pastes are posted, responding with the URL of the paste
+    pub url: String,
+    /// Post the paste as this field of a form, instead of as the body
+    pub form_field: Option<String>,
+}
+
+fn default_paste_confirm_lines() -> usize {
+    2
+}
+
+fn default_completion_suffixes() -> [String; 2] {
+    [": ".to_string(), " ".to_string()]
 }
 
 #[derive(Debug, Clone, Copy, Default, Deserialize)]
@@ -154,6 +290,8 @@ pub struct Nickname {
     pub alignment: Alignment,
     #[serde(default = "default_bool_true")]
     pub show_access_levels: bool,
+    #[serde(default)]
+    pub context_menu: Vec<ContextMenuEntry>,
 }
 
 impl Default for Nickname {
@@ -163,10 +301,31 @@ impl Default for Nickname {
             brackets: Default::default(),
             alignment: Default::default(),
             show_access_levels: default_bool_true(),
+            context_menu: vec![],
         }
     }
 }
 
+/// A custom entry in the context menu of nicknames.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContextMenuEntry {
+    pub label: String,
+    /// Command or message sent from the buffer, e.g. `/kick $channel $nick`.
+    pub command: String,
+}
+
+impl ContextMenuEntry {
+    /// The command with `$nick`, `$account`, `$host` and `$channel` replaced.
+    /// Values which aren't known are replaced with `*`.
+    pub fn command(&self, user: &User, channel: Option<&str>) -> String {
+        self.command
+            .replace("$nick", user.nickname().as_ref())
+            .replace("$account", user.accountname().unwrap_or("*"))
+            .replace("$host", user.hostname().unwrap_or("*"))
+            .replace("$channel", channel.unwrap_or("*"))
+    }
+}
+
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct StatusMessagePrefix {
     #[serde(default)]
@@ -236,3 +395,49 @@ fn default_timestamp() -> String {
 fn default_bool_true() -> bool {
     true
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zoom_steps() {
+        let zoom = Zoom::default().zoom_in().zoom_in().zoom_in();
+        assert_eq!(zoom.scale(), 1.3);
+        assert_eq!(zoom.zoom_out().zoom_out().zoom_out(), Zoom::default());
+
+        let mut zoom = Zoom::default();
+        for _ in 0..10 {
+            zoom = zoom.zoom_out();
+        }
+        assert_eq!(zoom.scale(), 0.5);
+
+        for _ in 0..30 {
+            zoom = zoom.zoom_in();
+        }
+        assert_eq!(zoom.scale(), 3.0);
+    }
+
+    #[test]
+    fn context_menu_command() {
+        let entry = ContextMenuEntry {
+            label: "Kickban".into(),
+            command: "/mode $channel +b *!*@$host".into(),
+        };
+
+        let user = User::try_from("casper!~casper@example.com").unwrap();
+        assert_eq!(
+            entry.command(&user, Some("#halloy")),
+            "/mode #halloy +b *!*@example.com"
+        );
+
+        let entry = ContextMenuEntry {
+            label: "Account".into(),
+            command: "/msg NickServ info $account".into(),
+        };
+        assert_eq!(
+            entry.command(&User::from(Nick::from("casper")), None),
+            "/msg NickServ info *"
+        );
+    }
+}
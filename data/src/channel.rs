@@ -2,10 +2,14 @@ use serde::{Deserialize, Serialize};
 
 use crate::config;
 
+pub mod keys;
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Settings {
     pub nicklist: Nicklist,
     pub topic: Topic,
+    #[serde(default)]
+    pub smart_filter: SmartFilter,
 }
 
 impl From<config::Channel> for Settings {
@@ -13,6 +17,7 @@ impl From<config::Channel> for Settings {
         Self {
             nicklist: Nicklist::from(config.nicklist),
             topic: Topic::from(config.topic),
+            smart_filter: SmartFilter::default(),
         }
     }
 }
@@ -68,3 +73,22 @@ impl Topic {
         self.enabled = !self.enabled
     }
 }
+
+/// Whether server messages are hidden for users who haven't spoken recently,
+/// per `smart` in their config.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct SmartFilter {
+    pub enabled: bool,
+}
+
+impl Default for SmartFilter {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl SmartFilter {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled
+    }
+}
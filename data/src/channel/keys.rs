@@ -0,0 +1,77 @@
+//! Channel keys learned while connected, saved so they're used to join the
+//! channels after a restart.
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::encryption;
+use crate::{compression, environment, history, Server};
+
+/// Keys by server, then by casemapped channel.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Keys(HashMap<String, BTreeMap<String, String>>);
+
+impl Keys {
+    pub fn get(&self, server: &Server) -> BTreeMap<String, String> {
+        self.0.get(server.as_ref()).cloned().unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, server: &Server, keys: BTreeMap<String, String>) {
+        if keys.is_empty() {
+            self.0.remove(server.as_ref());
+        } else {
+            self.0.insert(server.to_string(), keys);
+        }
+    }
+}
+
+/// Keys saved before a restart, or none if they couldn't be read.
+pub fn load() -> Keys {
+    match read() {
+        Ok(keys) => keys,
+        Err(Error::Io(error)) if error.kind() == io::ErrorKind::NotFound => Keys::default(),
+        Err(error) => {
+            log::warn!("failed to load channel keys: {error}");
+            Keys::default()
+        }
+    }
+}
+
+fn read() -> Result<Keys, Error> {
+    let bytes = encryption::decrypt(std::fs::read(path()?)?)?;
+
+    Ok(compression::decompress(&bytes)?)
+}
+
+/// Saves `keys`, encrypted like history when a passphrase is set.
+pub async fn save(keys: Keys) -> Result<(), Error> {
+    let path = path()?;
+
+    let bytes = encryption::encrypt(compression::compress(&keys)?)?;
+
+    tokio::fs::write(path, &bytes).await?;
+
+    Ok(())
+}
+
+fn path() -> Result<PathBuf, Error> {
+    let parent = environment::data_dir();
+
+    if !parent.exists() {
+        std::fs::create_dir_all(&parent)?;
+    }
+
+    Ok(parent.join("channel-keys.json.gz"))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Compression(#[from] compression::Error),
+    #[error(transparent)]
+    History(#[from] history::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
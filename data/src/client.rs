@@ -1,22 +1,43 @@
 use chrono::{DateTime, Utc};
 use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
 use irc::proto::{self, command, Command};
 use itertools::{Either, Itertools};
+use once_cell::sync::Lazy;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Result};
 
+use crate::config::ignore;
 use crate::history::ReadMarker;
 use crate::message::server_time;
+use crate::services::quakenet;
 use crate::time::Posix;
-use crate::user::{Nick, NickRef};
-use crate::{buffer, config, ctcp, dcc, isupport, message, mode, Server, User};
-use crate::{file_transfer, server};
+use crate::user::{AccessLevel, ChannelUsers, Nick, NickRef};
+use crate::{ban, debug, file_transfer, server, services};
+use crate::{
+    buffer, channel, config, console, ctcp, dcc, isupport, message, mode, notify, Server, User,
+};
 
 const HIGHLIGHT_BLACKOUT_INTERVAL: Duration = Duration::from_secs(5);
+const ISON_INITIAL_DELAY: Duration = Duration::from_secs(5);
+const ISON_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Max # older messages to request from the server at once
+const BACKFILL_LIMIT: u16 = 100;
+/// Length assumed for our hostname until the server reports it
+const MAX_HOSTNAME_LEN: usize = 63;
+/// How long channels refused until we're logged in wait for it, and then for
+/// the server to reply to joining them again
+const REGISTRATION_JOIN_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often a nickname in use is asked for again, without MONITOR
+const NICK_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+/// How long to stay in a channel joined again after a kick before the next
+/// kick starts over the attempts
+const REJOIN_RESET_INTERVAL: Duration = Duration::from_secs(300);
 
 #[derive(Debug, Clone, Copy)]
 pub enum Status {
@@ -35,6 +56,13 @@ impl Status {
 pub enum State {
     Disconnected,
     Ready(Client),
+    /// Disconnected with `/disconnect`, until `/reconnect`.
+    Stopped,
+    /// QUIT sent with `/reconnect` or `/disconnect`, until the connection has
+    /// written it.
+    Quitting {
+        reconnect: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -68,6 +96,8 @@ pub enum Broadcast {
         inviter: User,
         channel: String,
         user_channels: Vec<String>,
+        /// The channel is being joined, as the invite came from a trusted source
+        joining: bool,
         sent_time: DateTime<Utc>,
     },
     ChangeHost {
@@ -78,6 +108,30 @@ pub enum Broadcast {
         channels: Vec<String>,
         sent_time: DateTime<Utc>,
     },
+    Rejoin {
+        channel: String,
+        status: RejoinStatus,
+        sent_time: DateTime<Utc>,
+    },
+    /// Replies to CTCP queries stopped during a flood of them
+    CtcpSuppressed {
+        nick: Nick,
+        command: String,
+        sent_time: DateTime<Utc>,
+    },
+}
+
+/// Progress joining a channel again after being kicked from it.
+#[derive(Debug, Clone, Copy)]
+pub enum RejoinStatus {
+    Scheduled {
+        delay: Duration,
+        attempt: usize,
+        max_attempts: usize,
+    },
+    GaveUp {
+        attempts: usize,
+    },
 }
 
 #[derive(Debug)]
@@ -87,8 +141,15 @@ pub enum Event {
     Broadcast(Broadcast),
     Notification(message::Encoded, Nick, Notification),
     FileTransferRequest(file_transfer::ReceiveRequest),
+    FileTransferResume(Nick, dcc::Resume),
+    FileTransferAccept(Nick, dcc::Resume),
+    /// Filename and sha256 of a file the remote finished sending us
+    FileTransferChecksum(Nick, String, String),
+    DccChat(Nick, dcc::Chat),
     UpdateReadMarker(String, ReadMarker),
     JoinedChannel(String),
+    /// Older messages of a target, requested from the server
+    Backfill(String, Vec<message::Encoded>, Nick),
 }
 
 pub struct Client {
@@ -97,12 +158,32 @@ pub struct Client {
     handle: server::Handle,
     alt_nick: Option<usize>,
     resolved_nick: Option<String>,
+    /// Nickname asked for with `/nick` once registered
+    nick_request: Option<NickRequest>,
+    /// Taking back the configured nickname while connected with another
+    regain: Option<Regain>,
+    /// Our host as seen by the server, to discover the address to advertise in DCC offers
+    reported_host: Option<String>,
+    /// Whether we're marked away
+    away: bool,
+    /// Away message set while idle, cleared on input
+    auto_away: Option<String>,
+    /// Last away message shown for each user we've messaged
+    away_replies: HashMap<String, String>,
+    highlights: config::Highlights,
+    repeats: config::buffer::Repeats,
+    /// The last text of each user, to tell when they repeat themselves
+    last_texts: HashMap<String, (String, Instant)>,
+    /// Users whose notifications are held back, until when
+    held_back: HashMap<String, Instant>,
     chanmap: BTreeMap<String, Channel>,
     channels: Vec<String>,
-    users: HashMap<String, Vec<User>>,
     labels: HashMap<String, Context>,
     batches: HashMap<String, Batch>,
     reroute_responses_to: Option<buffer::Upstream>,
+    /// CTCP queries sent to each user, with the buffer they were sent from
+    ctcp_queries: HashMap<(String, String), (buffer::Upstream, Instant)>,
+    ctcp_limiter: ctcp::Limiter,
     registration_step: RegistrationStep,
     listed_caps: Vec<String>,
     supports_labels: bool,
@@ -110,9 +191,44 @@ pub struct Client {
     supports_account_notify: bool,
     supports_extended_join: bool,
     supports_read_marker: bool,
+    supports_pre_away: bool,
+    supports_chathistory: bool,
+    multiline: Option<Multiline>,
+    /// Last message older history was requested before, by target
+    backfill_requests: HashMap<String, String>,
+    /// Targets waiting on older history from the server
+    backfill_pending: HashSet<String>,
     highlight_blackout: HighlightBlackout,
-    registration_required_channels: Vec<String>,
+    registration_required_channels: BTreeMap<String, PendingJoin>,
+    /// Services package, once recognized from NickServ's notices
+    services: Option<services::Implementation>,
+    /// Masks on the server's SILENCE list, ignored here as well
+    silenced: Vec<ignore::Mask>,
+    /// Whether the SILENCE list is being listed to sync it with `ignore`
+    syncing_silence: bool,
+    /// Users we've had a query with, by casemapped nickname
+    queried: HashSet<String>,
+    /// Queries started by users we hadn't talked to, with their first text
+    new_queries: VecDeque<(Instant, User, String)>,
+    /// Users ignored for taking part in a query flood
+    flood_ignored: Vec<ignore::Mask>,
+    /// Channels being joined again after being kicked
+    rejoins: HashMap<String, Rejoin>,
+    /// Keys sent with JOIN, by casemapped channel, until the join succeeds
+    join_keys: HashMap<String, String>,
+    /// Channels open on the previous connection, joined again on registration
+    reconnect_channels: Vec<(String, Option<String>)>,
+    /// Keys learned for channels, by casemapped channel, saved across restarts
+    channel_keys: BTreeMap<String, String>,
+    channel_keys_changed: bool,
     isupport: HashMap<isupport::Kind, isupport::Parameter>,
+    monitor: Vec<String>,
+    /// Changes made to the notify list with `/notify`, saved across restarts
+    notify_changes: notify::Changes,
+    notify_changed: bool,
+    ison: Ison,
+    timers: Vec<Timer>,
+    next_timer_id: usize,
 }
 
 impl fmt::Debug for Client {
@@ -129,16 +245,26 @@ impl Client {
     ) -> Self {
         Self {
             server,
-            config,
             handle: sender,
             resolved_nick: None,
+            reported_host: None,
+            away: false,
+            auto_away: None,
+            highlights: config::Highlights::default(),
+            repeats: config::buffer::Repeats::default(),
+            last_texts: HashMap::new(),
+            held_back: HashMap::new(),
             alt_nick: None,
+            nick_request: None,
+            regain: None,
             chanmap: BTreeMap::default(),
             channels: vec![],
-            users: HashMap::new(),
             labels: HashMap::new(),
             batches: HashMap::new(),
             reroute_responses_to: None,
+            away_replies: HashMap::new(),
+            ctcp_queries: HashMap::new(),
+            ctcp_limiter: ctcp::Limiter::default(),
             registration_step: RegistrationStep::Start,
             listed_caps: vec![],
             supports_labels: false,
@@ -146,9 +272,32 @@ impl Client {
             supports_account_notify: false,
             supports_extended_join: false,
             supports_read_marker: false,
+            supports_pre_away: false,
+            supports_chathistory: false,
+            multiline: None,
+            backfill_requests: HashMap::new(),
+            backfill_pending: HashSet::new(),
             highlight_blackout: HighlightBlackout::Blackout(Instant::now()),
-            registration_required_channels: vec![],
+            registration_required_channels: BTreeMap::new(),
+            services: None,
+            silenced: vec![],
+            syncing_silence: false,
+            queried: HashSet::new(),
+            new_queries: VecDeque::new(),
+            flood_ignored: vec![],
+            rejoins: HashMap::new(),
+            join_keys: HashMap::new(),
+            reconnect_channels: vec![],
+            channel_keys: BTreeMap::new(),
+            channel_keys_changed: false,
             isupport: HashMap::new(),
+            monitor: config.monitor.clone(),
+            notify_changes: notify::Changes::default(),
+            notify_changed: false,
+            ison: Ison::default(),
+            timers: vec![],
+            next_timer_id: 1,
+            config,
         }
     }
 
@@ -171,7 +320,7 @@ impl Client {
     }
 
     fn quit(&mut self, reason: Option<String>) {
-        if let Err(e) = if let Some(reason) = reason {
+        if let Err(e) = if let Some(reason) = reason.or_else(|| self.config.quit_message.clone()) {
             self.handle.try_send(command!("QUIT", reason))
         } else {
             self.handle.try_send(command!("QUIT"))
@@ -181,9 +330,9 @@ impl Client {
     }
 
     fn join(&mut self, channels: &[String]) {
-        let keys = HashMap::new();
+        let keys = self.learned_keys(channels);
 
-        let messages = group_joins(channels, &keys);
+        let messages = group_joins(channels, &keys, self.line_len(), self.target_limit("JOIN"));
 
         for message in messages {
             if let Err(e) = self.handle.try_send(message) {
@@ -192,6 +341,20 @@ impl Client {
         }
     }
 
+    fn part(&mut self, channels: &[String]) {
+        for channel in channels {
+            let message = if let Some(reason) = &self.config.part_message {
+                command!("PART", channel, reason)
+            } else {
+                command!("PART", channel)
+            };
+
+            if let Err(e) = self.handle.try_send(message) {
+                log::warn!("Error sending part: {e}");
+            }
+        }
+    }
+
     fn start_reroute(&self, command: &Command) -> bool {
         use Command::*;
 
@@ -204,6 +367,14 @@ impl Client {
 
 
     fn send(&mut self, buffer: &buffer::Upstream, mut message: message::Encoded) {
+        if let Some(messages) = self.split_targets(&message) {
+            for message in messages {
+                self.send(buffer, message.into());
+            }
+
+            return;
+        }
+
         if self.supports_labels {
             use proto::Tag;
 
@@ -221,11 +392,151 @@ impl Client {
 
         self.reroute_responses_to = self.start_reroute(&message.command).then(|| buffer.clone());
 
+        // Reasons left out are filled in from the config
+        match &mut message.command {
+            Command::PART(_, reason @ None) => reason.clone_from(&self.config.part_message),
+            Command::QUIT(reason @ None) => reason.clone_from(&self.config.quit_message),
+            Command::KICK(_, _, reason @ None) => reason.clone_from(&self.config.kick_reason),
+            _ => {}
+        }
+
+        // Errors changing nickname are reported in the buffer it was asked for in
+        if let Command::NICK(nick) = &message.command {
+            if self.resolved_nick.is_some() {
+                if let Err(e) = self.stop_waiting_for_nick() {
+                    log::warn!("Error sending message: {e}");
+                }
+
+                self.nick_request = Some(NickRequest {
+                    nick: nick.clone(),
+                    buffer: buffer.clone(),
+                    wait: None,
+                });
+            }
+        }
+
+        // Remember keys given to JOIN, so channels can be joined again with them
+        if let Command::JOIN(channels, Some(keys)) = &message.command {
+            for (channel, key) in channels.split(',').zip(keys.split(',')) {
+                self.join_keys
+                    .insert(self.casemapping().normalize(channel), key.to_string());
+            }
+        }
+
+        // Users we message can always start a query with us
+        if let Command::PRIVMSG(targets, _) | Command::NOTICE(targets, _) = &message.command {
+            let users = targets
+                .split(',')
+                .filter(|target| !self.is_channel(target))
+                .map(|target| self.casemapping().normalize(target))
+                .collect::<Vec<_>>();
+
+            self.queried.extend(users);
+        }
+
+        // Replies to ISON sent by the user are shown, not taken for our polls
+        if is_ison(&message.command) {
+            self.ison.requested.push_back(None);
+        }
+
+        // Route the reply of an outgoing CTCP query back to the buffer it was sent from
+        if let Command::PRIVMSG(target, text) = &message.command {
+            if let Some(query) = ctcp::is_query(text)
+                .then(|| ctcp::parse_query(text))
+                .flatten()
+                .filter(|query| !matches!(query.command, ctcp::Command::Action))
+            {
+                let now = Instant::now();
+
+                self.ctcp_queries
+                    .retain(|_, (_, sent)| now.duration_since(*sent) < ctcp::QUERY_TIMEOUT);
+                self.ctcp_queries.insert(
+                    (
+                        self.casemapping().normalize(target),
+                        query.command.to_string(),
+                    ),
+                    (buffer.clone(), now),
+                );
+            }
+        }
+
+        if let Some(command) = self.through_channel(&message.command) {
+            message.command = command;
+        }
+
         if let Err(e) = self.handle.try_send(message.into()) {
             log::warn!("Error sending message: {e}");
         }
     }
 
+    /// A message to a user as CPRIVMSG or CNOTICE, through a channel we share
+    /// with them and are voiced or higher in. These aren't throttled by the
+    /// server's limit on changing targets, e.g. on QuakeNet.
+    fn through_channel(&self, command: &Command) -> Option<Command> {
+        let (channel_command, nick, text): (fn(String, String, String) -> Command, _, _) =
+            match command {
+                Command::PRIVMSG(nick, text)
+                    if self.isupport.contains_key(&isupport::Kind::CPRIVMSG) =>
+                {
+                    (Command::CPRIVMSG, nick, text)
+                }
+                Command::NOTICE(nick, text)
+                    if self.isupport.contains_key(&isupport::Kind::CNOTICE) =>
+                {
+                    (Command::CNOTICE, nick, text)
+                }
+                _ => return None,
+            };
+
+        if nick.contains(',') || self.is_channel(nick) {
+            return None;
+        }
+
+        let user = User::from(Nick::from(nick.as_str()));
+        let us = User::from(Nick::from(self.nickname().as_ref()));
+        let prefixes = self.channel_mode_kinds().prefix;
+
+        let channel = self.chanmap.iter().find_map(|(name, channel)| {
+            let voiced = channel
+                .users
+                .get(&us)
+                .is_some_and(|us| is_voiced(us, &prefixes));
+
+            (voiced && channel.users.get(&user).is_some()).then_some(name)
+        })?;
+
+        Some(channel_command(nick.clone(), channel.clone(), text.clone()))
+    }
+
+    /// Splits a message to more targets than the server takes in one command
+    /// into several.
+    fn split_targets(&self, message: &proto::Message) -> Option<Vec<proto::Message>> {
+        let (command, targets, text): (fn(String, String) -> Command, _, _) = match &message.command
+        {
+            Command::PRIVMSG(targets, text) => (Command::PRIVMSG, targets, text),
+            Command::NOTICE(targets, text) => (Command::NOTICE, targets, text),
+            _ => return None,
+        };
+
+        let limit = self.target_limit(&message.command.command())?;
+        let targets = targets.split(',').collect::<Vec<_>>();
+
+        if targets.len() <= limit {
+            return None;
+        }
+
+        Some(
+            targets
+                .chunks(limit)
+                .map(|targets| proto::Message {
+                    tags: message.tags.clone(),
+                    source: None,
+                    command: command(targets.join(","), text.clone()),
+                })
+                .collect(),
+        )
+    }
+
     fn receive(&mut self, message: message::Encoded) -> Result<Vec<Event>> {
         log::trace!("Message received => {:?}", *message);
 
@@ -271,18 +582,36 @@ impl Client {
         }
 
         match &message.command {
-            Command::BATCH(batch, ..) => {
+            Command::BATCH(batch, params) => {
                 let mut chars = batch.chars();
                 let symbol = ok!(chars.next());
                 let reference = chars.collect::<String>();
 
                 match symbol {
                     '+' => {
-                        let batch = Batch::new(context);
+                        let mut batch = Batch::new(context);
+
+                        // Only history we asked for, bouncers also play back missed messages
+                        if let [kind, target, ..] = params.as_slice() {
+                            if matches!(kind.as_str(), "chathistory" | "znc.in/playback")
+                                && self.backfill_pending.remove(&target.to_lowercase())
+                            {
+                                batch.backfill = Some(target.clone());
+                            }
+                        }
+
                         self.batches.insert(reference, batch);
                     }
                     '-' => {
                         if let Some(finished) = self.batches.remove(&reference) {
+                            if let Some(target) = finished.backfill {
+                                return Ok(vec![Event::Backfill(
+                                    target,
+                                    finished.messages,
+                                    self.nickname().to_owned(),
+                                )]);
+                            }
+
                             // If nested, extend events into parent batch
                             if let Some(parent) = batch_tag
                                 .as_ref()
@@ -300,6 +629,15 @@ impl Client {
                 return Ok(vec![]);
             }
             _ if batch_tag.is_some() => {
+                if let Some(batch) = batch_tag
+                    .as_ref()
+                    .and_then(|batch| self.batches.get_mut(batch))
+                    .filter(|batch| batch.backfill.is_some())
+                {
+                    batch.messages.push(message);
+                    return Ok(vec![]);
+                }
+
                 let events = self.handle(message, context)?;
 
                 if let Some(batch) = self.batches.get_mut(&batch_tag.unwrap()) {
@@ -373,6 +711,9 @@ impl Client {
                     if contains("extended-monitor") {
                         requested.push("extended-monitor");
                     }
+                    if contains("account-tag") {
+                        requested.push("account-tag");
+                    }
                     if contains("account-notify") {
                         requested.push("account-notify");
 
@@ -382,6 +723,19 @@ impl Client {
                     }
                     if contains("batch") {
                         requested.push("batch");
+
+                        // Server history is sent in batches
+                        if contains("draft/chathistory") {
+                            requested.push("draft/chathistory");
+                        }
+                        // Multiline messages are sent in batches
+                        if self
+                            .listed_caps
+                            .iter()
+                            .any(|cap| Multiline::parse(cap).is_some())
+                        {
+                            requested.push("draft/multiline");
+                        }
                     }
                     if contains("labeled-response") {
                         requested.push("labeled-response");
@@ -400,12 +754,15 @@ impl Client {
                     if contains("draft/read-marker") {
                         requested.push("draft/read-marker");
                     }
+                    if contains("draft/pre-away") {
+                        requested.push("draft/pre-away");
+                    }
 
                     if !requested.is_empty() {
                         // Request
                         self.registration_step = RegistrationStep::Req;
 
-                        for message in group_capability_requests(&requested) {
+                        for message in group_capability_requests(&requested, self.line_len()) {
                             self.handle.try_send(message)?;
                         }
                     } else {
@@ -438,6 +795,23 @@ impl Client {
                 if caps.contains(&"draft/read-marker") {
                     self.supports_read_marker = true;
                 }
+                if caps.contains(&"draft/chathistory") {
+                    self.supports_chathistory = true;
+                }
+                if caps.contains(&"draft/multiline") {
+                    self.multiline = self
+                        .listed_caps
+                        .iter()
+                        .find_map(|cap| Multiline::parse(cap));
+                }
+                if caps.contains(&"draft/pre-away") {
+                    self.supports_pre_away = true;
+
+                    // Idle while connecting, so we're away from the start
+                    if let Some(message) = &self.auto_away {
+                        self.handle.try_send(command!("AWAY", message))?;
+                    }
+                }
 
                 let supports_sasl = caps.iter().any(|cap| cap.contains("sasl"));
 
@@ -493,6 +867,9 @@ impl Client {
                 if newly_contains("extended-monitor") {
                     requested.push("extended-monitor");
                 }
+                if newly_contains("account-tag") {
+                    requested.push("account-tag");
+                }
                 if contains("account-notify") || newly_contains("account-notify") {
                     if newly_contains("account-notify") {
                         requested.push("account-notify");
@@ -502,8 +879,14 @@ impl Client {
                         requested.push("extended-join");
                     }
                 }
-                if newly_contains("batch") {
-                    requested.push("batch");
+                if contains("batch") || newly_contains("batch") {
+                    if newly_contains("batch") {
+                        requested.push("batch");
+                    }
+
+                    if newly_contains("draft/chathistory") {
+                        requested.push("draft/chathistory");
+                    }
                 }
                 if contains("labeled-response") || newly_contains("labeled-response") {
                     if newly_contains("labeled-response") {
@@ -523,7 +906,7 @@ impl Client {
                 }
 
                 if !requested.is_empty() {
-                    for message in group_capability_requests(&requested) {
+                    for message in group_capability_requests(&requested, self.line_len()) {
                         self.handle.try_send(message)?;
                     }
                 }
@@ -550,6 +933,12 @@ impl Client {
                 if del_caps.contains(&"draft/read-marker") {
                     self.supports_read_marker = false;
                 }
+                if del_caps.contains(&"draft/chathistory") {
+                    self.supports_chathistory = false;
+                }
+                if del_caps.contains(&"draft/multiline") {
+                    self.multiline = None;
+                }
 
                 self.listed_caps
                     .retain(|cap| !del_caps.iter().any(|del_cap| del_cap == cap));
@@ -566,16 +955,7 @@ impl Client {
             Command::Numeric(RPL_LOGGEDIN, args) => {
                 log::info!("[{}] logged in", self.server);
 
-                if !self.registration_required_channels.is_empty() {
-                    for message in group_joins(
-                        &self.registration_required_channels,
-                        &self.config.channel_keys,
-                    ) {
-                        self.handle.try_send(message)?;
-                    }
-
-                    self.registration_required_channels.clear();
-                }
+                self.retry_registration_required_channels()?;
 
                 if !self.supports_account_notify {
                     let accountname = ok!(args.first());
@@ -602,7 +982,19 @@ impl Client {
                     });
                 }
             }
+            Command::PRIVMSG(_, _) | Command::NOTICE(_, _) | Command::INVITE(_, _)
+                if self.is_ignored(&message) =>
+            {
+                return Ok(vec![]);
+            }
             Command::PRIVMSG(channel, text) | Command::NOTICE(channel, text) => {
+                if let Some(user) = message
+                    .user()
+                    .filter(|_| matches!(&message.command, Command::NOTICE(_, _)))
+                {
+                    self.services_notice(&user, text)?;
+                }
+
                 if let Some(user) = message.user() {
                     if let Some(command) = dcc::decode(text) {
                         match command {
@@ -614,9 +1006,28 @@ impl Client {
                                         dcc_send: request,
                                         server: self.server.clone(),
                                         server_handle: self.handle.clone(),
+                                        reported_host: self.reported_host.clone(),
                                     },
                                 )]);
                             }
+                            dcc::Command::Chat(chat) => {
+                                log::trace!("DCC Chat => {chat:?}");
+                                return Ok(vec![Event::DccChat(user.nickname().to_owned(), chat)]);
+                            }
+                            dcc::Command::Resume(resume) => {
+                                log::trace!("DCC Resume => {resume:?}");
+                                return Ok(vec![Event::FileTransferResume(
+                                    user.nickname().to_owned(),
+                                    resume,
+                                )]);
+                            }
+                            dcc::Command::Accept(accept) => {
+                                log::trace!("DCC Accept => {accept:?}");
+                                return Ok(vec![Event::FileTransferAccept(
+                                    user.nickname().to_owned(),
+                                    accept,
+                                )]);
+                            }
                             dcc::Command::Unsupported(command) => {
                                 bail!("Unsupported DCC command: {command}",);
                             }
@@ -628,46 +1039,95 @@ impl Client {
                             && !message::is_action(text)
                         {
                             if let Some(query) = ctcp::parse_query(text) {
-                                if matches!(&message.command, Command::PRIVMSG(_, _)) {
-                                    match query.command {
-                                        ctcp::Command::Action => (),
+                                let command = query.command.to_string();
+                                let answered = self.config.ctcp.enabled
+                                    && self.config.ctcp.supported().contains(&command.as_str());
+
+                                if answered
+                                    && !matches!(&message.command, Command::NOTICE(_, _))
+                                    && !self
+                                        .ctcp_limiter
+                                        .allows(user.nickname().as_ref(), Instant::now())
+                                {
+                                    log::info!(
+                                        "[{}] CTCP flood, not replying to {command} from {}",
+                                        self.server,
+                                        user.nickname()
+                                    );
+
+                                    // Reported once, until replies are sent again
+                                    if self.ctcp_limiter.suppressed() > 1 {
+                                        return Ok(vec![]);
+                                    }
+
+                                    return Ok(vec![Event::Broadcast(Broadcast::CtcpSuppressed {
+                                        nick: user.nickname().to_owned(),
+                                        command,
+                                        sent_time: server_time(&message),
+                                    })]);
+                                }
+
+                                let ctcp = &self.config.ctcp;
+
+                                if matches!(&message.command, Command::NOTICE(_, _)) {
+                                    if let Some((buffer, _)) = self
+                                        .ctcp_queries
+                                        .remove(&(
+                                            self.casemapping().normalize(user.nickname().as_ref()),
+                                            query.command.to_string(),
+                                        ))
+                                        .filter(|(_, sent)| sent.elapsed() < ctcp::QUERY_TIMEOUT)
+                                    {
+                                        return Ok(vec![Event::WithTarget(
+                                            message.clone(),
+                                            self.nickname().to_owned(),
+                                            buffer.server_message_target(None),
+                                        )]);
+                                    }
+                                } else if ctcp.enabled {
+                                    let reply = match &query.command {
+                                        ctcp::Command::Action => None,
                                         ctcp::Command::ClientInfo => {
-                                            self.handle.try_send(ctcp::response_message(
-                                                &query.command,
-                                                user.nickname().to_string(),
-                                                Some("ACTION CLIENTINFO DCC PING SOURCE VERSION"),
-                                            ))?;
+                                            ctcp.clientinfo.then(|| ctcp.supported().join(" "))
                                         }
-                                        ctcp::Command::DCC => (),
+                                        ctcp::Command::DCC => None,
                                         ctcp::Command::Ping => {
-                                            self.handle.try_send(ctcp::response_message(
-                                                &query.command,
-                                                user.nickname().to_string(),
-                                                query.params,
-                                            ))?;
-                                        }
-                                        ctcp::Command::Source => {
-                                            self.handle.try_send(ctcp::response_message(
-                                                &query.command,
-                                                user.nickname().to_string(),
-                                                Some(crate::environment::SOURCE_WEBSITE),
-                                            ))?;
+                                            if ctcp.ping {
+                                                self.handle.try_send(ctcp::response_message(
+                                                    &query.command,
+                                                    user.nickname().to_string(),
+                                                    query.params,
+                                                ))?;
+                                            }
+
+                                            None
                                         }
-                                        ctcp::Command::Version => {
-                                            self.handle.try_send(ctcp::response_message(
-                                                &query.command,
-                                                user.nickname().to_string(),
-                                                Some(format!(
-                                                    "Halloy {}",
-                                                    crate::environment::VERSION
-                                                )),
-                                            ))?;
+                                        ctcp::Command::Source => ctcp.source.then(|| {
+                                            crate::environment::SOURCE_WEBSITE.to_string()
+                                        }),
+                                        ctcp::Command::Time => {
+                                            ctcp.time.then(|| chrono::Local::now().to_rfc2822())
                                         }
+                                        ctcp::Command::UserInfo => ctcp.userinfo.clone(),
+                                        ctcp::Command::Version => ctcp.version.then(|| {
+                                            ctcp.version_reply.clone().unwrap_or_else(|| {
+                                                format!("Halloy {}", crate::environment::VERSION)
+                                            })
+                                        }),
                                         ctcp::Command::Unknown(command) => {
                                             log::debug!(
                                                 "Ignorning CTCP command {command}: Unknown command"
-                                            )
+                                            );
+                                            None
                                         }
+                                    };
+
+                                    if let Some(reply) = reply {
+                                        self.handle.try_send(ctcp::response_message(
+                                            &query.command,
+                                            user.nickname().to_string(),
+                                            Some(reply),
+                                        ))?;
                                     }
                                 }
 
@@ -675,8 +1135,38 @@ impl Client {
                             }
                         }
 
+                        // Recorded without notifying
+                        let quiet = self.holds_back_notifications(&user, text)
+                            || self.is_masked(
+                                &self.config.soft_ignore,
+                                &user,
+                                account_tag(&message),
+                            );
+
+                        // Queries started during a flood are neither opened nor notified,
+                        // they're recorded in the server buffer instead
+                        if channel == &self.nickname().to_string()
+                            && user.nickname() != self.nickname()
+                            && self.is_query_flood(&user, text)
+                        {
+                            return Ok(vec![Event::WithTarget(
+                                message.clone(),
+                                self.nickname().to_owned(),
+                                message::Target::Server {
+                                    source: message::Source::User(user),
+                                },
+                            )]);
+                        }
+
                         // Highlight notification
-                        if message::references_user_text(user.nickname(), self.nickname(), text) {
+                        if !quiet
+                            && message::is_highlight_text(
+                                user.nickname(),
+                                self.nickname(),
+                                text,
+                                &self.highlights,
+                            )
+                        {
                             return Ok(vec![Event::Notification(
                                 message.clone(),
                                 self.nickname().to_owned(),
@@ -693,11 +1183,28 @@ impl Client {
 
                         // use `channel` to confirm the direct message, then send notification
                         if channel == &self.nickname().to_string() {
-                            return Ok(vec![Event::Notification(
-                                message.clone(),
-                                self.nickname().to_owned(),
-                                Notification::DirectMessage(user),
-                            )]);
+                            // Sent once the remote finishes sending us a file
+                            let checksum = file_transfer::parse_sent_checksum(text).map(
+                                |(filename, sha256)| {
+                                    Event::FileTransferChecksum(
+                                        user.nickname().to_owned(),
+                                        filename,
+                                        sha256,
+                                    )
+                                },
+                            );
+
+                            let event = if quiet {
+                                Event::Single(message.clone(), self.nickname().to_owned())
+                            } else {
+                                Event::Notification(
+                                    message.clone(),
+                                    self.nickname().to_owned(),
+                                    Notification::DirectMessage(user),
+                                )
+                            };
+
+                            return Ok(checksum.into_iter().chain([event]).collect());
                         }
                     }
                 }
@@ -707,10 +1214,18 @@ impl Client {
                 let inviter = ok!(message.user());
                 let user_channels = self.user_channels(user.nickname());
 
+                let joining =
+                    user.nickname() == self.nickname() && self.joins_invite(&inviter, channel);
+
+                if joining {
+                    self.join(&[channel.clone()]);
+                }
+
                 return Ok(vec![Event::Broadcast(Broadcast::Invite {
                     inviter,
                     channel: channel.clone(),
                     user_channels,
+                    joining,
                     sent_time: server_time(&message),
                 })]);
             }
@@ -720,18 +1235,23 @@ impl Client {
 
                 if ourself {
                     self.resolved_nick = Some(nick.clone());
+                    self.stop_waiting_for_nick()?;
+
+                    if self.is_configured_nick(nick) {
+                        self.stop_regaining_nick()?;
+                    }
                 }
 
                 let new_nick = Nick::from(nick.as_str());
 
+                let channels = self.user_channels(old_user.nickname());
+
                 self.chanmap.values_mut().for_each(|channel| {
                     if let Some(user) = channel.users.take(&old_user) {
                         channel.users.insert(user.with_nickname(new_nick.clone()));
                     }
                 });
 
-                let channels = self.user_channels(old_user.nickname());
-
                 return Ok(vec![Event::Broadcast(Broadcast::Nickname {
                     old_user,
                     new_nick,
@@ -760,11 +1280,74 @@ impl Client {
                     self.handle.try_send(command!("NICK", nick))?;
                 }
             }
+            Command::Numeric(numeric @ (ERR_NICKNAMEINUSE | ERR_ERRONEUSNICKNAME), _)
+                if self.nick_request.is_some() =>
+            {
+                let in_use = *numeric == ERR_NICKNAMEINUSE;
+                let supports_monitor = self.isupport.contains_key(&isupport::Kind::MONITOR);
+                let wait_for_nick = self.config.wait_for_nick;
+                let request = ok!(self.nick_request.as_mut());
+
+                // Asking for it again failed, it was already reported
+                if request.wait.is_some() && in_use {
+                    if let Some(NickWait::Retry(next)) = &mut request.wait {
+                        *next = Instant::now() + NICK_RETRY_INTERVAL;
+                    }
+
+                    return Ok(vec![]);
+                }
+
+                let source = request.buffer.clone().server_message_target(None);
+
+                if in_use && wait_for_nick {
+                    if supports_monitor {
+                        self.handle
+                            .try_send(command!("MONITOR", "+", request.nick.clone()))?;
+                        request.wait = Some(NickWait::Monitor);
+                    } else {
+                        request.wait = Some(NickWait::Retry(Instant::now() + NICK_RETRY_INTERVAL));
+                    }
+                } else {
+                    self.nick_request = None;
+                }
+
+                return Ok(vec![Event::WithTarget(
+                    message,
+                    self.nickname().to_owned(),
+                    source,
+                )]);
+            }
+            Command::Numeric(ERR_NICKNAMEINUSE | ERR_ERRONEUSNICKNAME, args)
+                if self.regain.is_some() && self.is_configured_nick(ok!(args.get(1))) =>
+            {
+                // Taken again before we could change to it, keep watching
+                return Ok(vec![]);
+            }
             Command::Numeric(RPL_WELCOME, args) => {
                 // Updated actual nick
                 let nick = ok!(args.first());
                 self.resolved_nick = Some(nick.to_string());
 
+                // Some servers welcome us as `nick!user@host`
+                if let Some((_, host)) = args
+                    .last()
+                    .and_then(|text| text.split_whitespace().last())
+                    .and_then(|mask| mask.split_once('@'))
+                {
+                    self.reported_host = Some(host.to_string());
+                }
+
+                // Poll ISON once ISUPPORT has been received, if MONITOR isn't supported
+                self.ison.next_poll = Some(Instant::now() + ISON_INITIAL_DELAY);
+
+                if self.config.regain_nick && !self.is_configured_nick(nick) {
+                    self.regain = Some(Regain::Pending);
+                }
+
+                if let Some(message) = self.auto_away.as_ref().filter(|_| !self.supports_pre_away) {
+                    self.handle.try_send(command!("AWAY", message))?;
+                }
+
                 // Send nick password & ghost
                 if let Some(nick_pass) = self.config.nick_password.as_ref() {
                     // Try ghost recovery if we couldn't claim our nick
@@ -813,6 +1396,11 @@ impl Client {
                     }
                 }
 
+                if self.config.q_auth.is_some() {
+                    self.handle
+                        .try_send(command!("PRIVMSG", quakenet::Q, "CHALLENGE"))?;
+                }
+
                 // Send user modestring
                 if let Some(modestring) = self.config.umodes.as_ref() {
                     self.handle.try_send(command!("MODE", nick, modestring))?;
@@ -827,8 +1415,28 @@ impl Client {
                     };
                 }
 
-                // Send JOIN
-                for message in group_joins(&self.config.channels, &self.config.channel_keys) {
+                // Send JOIN, for configured channels and ones open before reconnecting
+                let mut channels = self.config.channels.to_vec();
+                let reconnect_channels = std::mem::take(&mut self.reconnect_channels);
+
+                for (channel, _) in &reconnect_channels {
+                    if !channels.contains(channel) {
+                        channels.push(channel.clone());
+                    }
+                }
+
+                // Keys learned later take precedence over configured ones
+                let mut keys = self.config.channel_keys.clone();
+                keys.extend(self.learned_keys(&channels));
+                keys.extend(
+                    reconnect_channels
+                        .into_iter()
+                        .filter_map(|(channel, key)| Some((channel, key?))),
+                );
+
+                for message in
+                    group_joins(&channels, &keys, self.line_len(), self.target_limit("JOIN"))
+                {
                     self.handle.try_send(message)?;
                 }
             }
@@ -836,12 +1444,12 @@ impl Client {
             Command::QUIT(comment) => {
                 let user = ok!(message.user());
 
+                let channels = self.user_channels(user.nickname());
+
                 self.chanmap.values_mut().for_each(|channel| {
                     channel.users.remove(&user);
                 });
 
-                let channels = self.user_channels(user.nickname());
-
                 return Ok(vec![Event::Broadcast(Broadcast::Quit {
                     user,
                     comment: comment.clone(),
@@ -854,6 +1462,7 @@ impl Client {
 
                 if user.nickname() == self.nickname() {
                     self.chanmap.remove(channel);
+                    self.sort_channels();
                 } else if let Some(channel) = self.chanmap.get_mut(channel) {
                     channel.users.remove(&user);
                 }
@@ -862,35 +1471,39 @@ impl Client {
                 let user = ok!(message.user());
 
                 if user.nickname() == self.nickname() {
-                    self.chanmap.insert(channel.clone(), Channel::default());
-
-                    // Sends WHO to get away state on users if WHO poll is enabled.
-                    if self.config.who_poll_enabled {
-                        if let Some(state) = self.chanmap.get_mut(channel) {
-                            if self.isupport.contains_key(&isupport::Kind::WHOX) {
-                                let fields = if self.supports_account_notify {
-                                    "tcnfa"
-                                } else {
-                                    "tcnf"
-                                };
+                    self.registration_required_channels.remove(channel);
 
-                                self.handle.try_send(command!(
-                                    "WHO",
-                                    channel,
-                                    fields,
-                                    isupport::WHO_POLL_TOKEN.to_owned()
-                                ))?;
-
-                                state.last_who = Some(WhoStatus::Requested(
-                                    Instant::now(),
-                                    Some(isupport::WHO_POLL_TOKEN),
-                                ));
-                            } else {
-                                self.handle.try_send(command!("WHO", channel))?;
-                                state.last_who = Some(WhoStatus::Requested(Instant::now(), None));
-                            }
-                            log::debug!("[{}] {channel} - WHO requested", self.server);
-                        }
+                    let key = self
+                        .join_keys
+                        .remove(&self.casemapping().normalize(channel));
+
+                    if key.is_some() {
+                        self.learn_key(channel, key.clone());
+                    }
+
+                    if let Some(rejoin) = self.rejoins.get_mut(channel) {
+                        rejoin.state = RejoinState::Joined(Instant::now());
+                    }
+
+                    self.chanmap.insert(
+                        channel.clone(),
+                        Channel {
+                            users: ChannelUsers::new(self.casemapping()),
+                            key,
+                            ..Channel::default()
+                        },
+                    );
+                    self.sort_channels();
+
+                    if let Some(host) = user.hostname() {
+                        self.reported_host = Some(host.to_string());
+                    }
+
+                    // Request the channel modes, their replies are kept out of history
+                    self.handle.try_send(command!("MODE", channel))?;
+
+                    if let Some(state) = self.chanmap.get_mut(channel) {
+                        state.modes_requested = true;
                     }
 
                     return Ok(vec![Event::JoinedChannel(channel.clone())]);
@@ -908,7 +1521,22 @@ impl Client {
             }
             Command::KICK(channel, victim, _) => {
                 if victim == self.nickname().as_ref() {
-                    self.chanmap.remove(channel);
+                    let state = self.chanmap.remove(channel);
+                    self.sort_channels();
+
+                    if rejoins_on_kick(&self.config, channel) {
+                        let key = state.and_then(|state| state.key().map(String::from));
+                        let status = self.schedule_rejoin(channel, key);
+
+                        return Ok(vec![
+                            Event::Single(message.clone(), self.nickname().to_owned()),
+                            Event::Broadcast(Broadcast::Rejoin {
+                                channel: channel.clone(),
+                                status,
+                                sent_time: server_time(&message),
+                            }),
+                        ]);
+                    }
                 } else if let Some(channel) = self.chanmap.get_mut(channel) {
                     channel
                         .users
@@ -983,6 +1611,11 @@ impl Client {
                 let away = args.is_some();
                 let user = ok!(message.user());
 
+                if !away {
+                    let nick = self.casemapping().normalize(user.nickname().as_ref());
+                    self.away_replies.remove(&nick);
+                }
+
                 for channel in self.chanmap.values_mut() {
                     if let Some(mut user) = channel.users.take(&user) {
                         user.update_away(away);
@@ -990,11 +1623,32 @@ impl Client {
                     }
                 }
             }
+            Command::Numeric(RPL_AWAY, args) => {
+                let nick = self.casemapping().normalize(ok!(args.get(1)));
+                let away_message = args.get(2).cloned().unwrap_or_default();
+
+                let repeated =
+                    self.away_replies.insert(nick, away_message.clone()) == Some(away_message);
+
+                // Replies to messages sent to the user, rather than to a
+                // command such as `/whois`
+                let to_query = self.reroute_responses_to.is_none()
+                    && context.as_ref().is_none_or(|context| {
+                        matches!(context, Context::Buffer(buffer::Upstream::Query(..)))
+                    });
+
+                // Shown once in the query rather than after every message sent
+                if repeated && to_query {
+                    return Ok(vec![]);
+                }
+            }
             Command::Numeric(RPL_UNAWAY, args) => {
                 let nick = ok!(args.first()).as_str();
                 let user = User::try_from(nick)?;
 
                 if user.nickname() == self.nickname() {
+                    self.away = false;
+
                     for channel in self.chanmap.values_mut() {
                         if let Some(mut user) = channel.users.take(&user) {
                             user.update_away(false);
@@ -1008,6 +1662,8 @@ impl Client {
                 let user = User::try_from(nick)?;
 
                 if user.nickname() == self.nickname() {
+                    self.away = true;
+
                     for channel in self.chanmap.values_mut() {
                         if let Some(mut user) = channel.users.take(&user) {
                             user.update_away(true);
@@ -1018,21 +1674,46 @@ impl Client {
             }
             Command::MODE(target, Some(modes), Some(args)) => {
                 if self.is_channel(target) {
-                    let modes = mode::parse::<mode::Channel>(modes, args);
+                    let kinds = self.channel_mode_kinds();
+                    let mut learned_key = None;
 
                     if let Some(channel) = self.chanmap.get_mut(target) {
-                        for mode in modes {
+                        let modes = kinds.parse(modes, args);
+                        let set_by = message.user().map(|user| user.nickname().to_string());
+
+                        channel.modes.apply(&kinds, &modes);
+
+                        if modes.iter().any(|mode| *mode.value() == 'k') {
+                            channel.key = channel.modes.key().map(String::from);
+                            learned_key = Some(channel.key.clone());
+                        }
+
+                        channel
+                            .lists
+                            .apply(&modes, set_by.as_deref(), server_time(&message));
+
+                        for mode in &modes {
+                            let Some(access_level) =
+                                AccessLevel::from_mode(*mode.value(), &kinds.prefix)
+                            else {
+                                continue;
+                            };
+
                             if let Some((op, lookup)) = mode
                                 .operation()
                                 .zip(mode.arg().map(|nick| User::from(Nick::from(nick))))
                             {
                                 if let Some(mut user) = channel.users.take(&lookup) {
-                                    user.update_access_level(op, *mode.value());
+                                    user.update_access_level(op, access_level);
                                     channel.users.insert(user);
                                 }
                             }
                         }
                     }
+
+                    if let Some(key) = learned_key {
+                        self.learn_key(target, key);
+                    }
                 } else {
                     // Only check for being logged in via mode if account-notify is not available,
                     // since it is not standardized across networks.
@@ -1046,22 +1727,17 @@ impl Client {
                         if modes.into_iter().any(|mode| {
                             matches!(mode, mode::Mode::Add(mode::User::Registered, None))
                         }) {
-                            for message in group_joins(
-                                &self.registration_required_channels,
-                                &self.config.channel_keys,
-                            ) {
-                                self.handle.try_send(message)?;
-                            }
-
-                            self.registration_required_channels.clear();
+                            self.retry_registration_required_channels()?;
                         }
                     }
                 }
             }
             Command::Numeric(RPL_NAMREPLY, args) if args.len() > 3 => {
+                let prefixes = self.channel_mode_kinds().prefix;
+
                 if let Some(channel) = self.chanmap.get_mut(&args[2]) {
                     for user in args[3].split(' ') {
-                        if let Ok(user) = User::try_from(user) {
+                        if let Ok(user) = User::parse(user, &prefixes) {
                             channel.users.insert(user);
                         }
                     }
@@ -1076,9 +1752,38 @@ impl Client {
                 let target = ok!(args.get(1));
 
                 if self.is_channel(target) {
-                    if let Some(channel) = self.chanmap.get_mut(target) {
-                        if !channel.names_init {
-                            channel.names_init = true;
+                    if let Some(state) = self.chanmap.get_mut(target) {
+                        if !state.names_init {
+                            state.names_init = true;
+
+                            // Sends WHO to get away state on users, once they're
+                            // listed, if the channel is polled.
+                            if is_who_polled(&self.config, target, state.users.as_slice().len()) {
+                                if self.isupport.contains_key(&isupport::Kind::WHOX) {
+                                    let fields = if self.supports_account_notify {
+                                        "tcnfa"
+                                    } else {
+                                        "tcnf"
+                                    };
+
+                                    self.handle.try_send(command!(
+                                        "WHO",
+                                        target,
+                                        fields,
+                                        isupport::WHO_POLL_TOKEN.to_owned()
+                                    ))?;
+
+                                    state.last_who = Some(WhoStatus::Requested(
+                                        Instant::now(),
+                                        Some(isupport::WHO_POLL_TOKEN),
+                                    ));
+                                } else {
+                                    self.handle.try_send(command!("WHO", target))?;
+                                    state.last_who =
+                                        Some(WhoStatus::Requested(Instant::now(), None));
+                                }
+                                log::debug!("[{}] {target} - WHO requested", self.server);
+                            }
 
                             return Ok(vec![]);
                         }
@@ -1104,24 +1809,126 @@ impl Client {
                 #[cfg(feature = "dev")]
                 return Ok(vec![]);
             }
-            Command::Numeric(RPL_TOPICWHOTIME, args) => {
-                if let Some(channel) = self.chanmap.get_mut(&args[1]) {
-                    channel.topic.who = Some(ok!(args.get(2)).to_string());
-                    let timestamp = Posix::from_seconds(ok!(args.get(3)).parse::<u64>()?);
-                    channel.topic.time =
-                        Some(timestamp.datetime().ok_or_else(|| {
-                            anyhow!("Unable to parse timestamp: {:?}", timestamp)
-                        })?);
+            Command::Numeric(RPL_CHANNELMODEIS, args) => {
+                let kinds = self.channel_mode_kinds();
+
+                if let Some(channel) = self.chanmap.get_mut(ok!(args.get(1))) {
+                    channel.modes = mode::ChannelModes::from_modestring(
+                        &kinds,
+                        ok!(args.get(2)),
+                        args.get(3..).unwrap_or_default(),
+                    );
+
+                    let key = channel.modes.key().map(String::from);
+                    let requested = channel.modes_requested;
+
+                    if key.is_some() {
+                        self.learn_key(ok!(args.get(1)), key);
+                    }
+
+                    if requested {
+                        return Ok(vec![]);
+                    }
                 }
-                // Exclude topic message from history to prevent spam during dev
-                #[cfg(feature = "dev")]
-                return Ok(vec![]);
             }
-            Command::Numeric(ERR_NOCHANMODES, args) => {
-                let channel = ok!(args.get(1));
-
-                // If the channel has not been joined but is in the configured channels,
-                // then interpret this numeric as ERR_NEEDREGGEDNICK (which has the
+            Command::Numeric(RPL_CREATIONTIME, args) => {
+                // Ends the reply to the MODE request sent on join
+                if let Some(channel) = self.chanmap.get_mut(ok!(args.get(1))) {
+                    if channel.modes_requested {
+                        channel.modes_requested = false;
+                        return Ok(vec![]);
+                    }
+                }
+            }
+            Command::Numeric(
+                numeric @ (RPL_BANLIST | RPL_EXCEPTLIST | RPL_INVEXLIST | RPL_QUIETLIST),
+                args,
+            ) => {
+                // Quiet list replies include the mode before the mask
+                let (mode, entry) = match numeric {
+                    RPL_BANLIST => ('b', args.get(2..)),
+                    RPL_EXCEPTLIST => ('e', args.get(2..)),
+                    RPL_INVEXLIST => ('I', args.get(2..)),
+                    _ => ('q', args.get(3..)),
+                };
+                let entry = ok!(entry);
+
+                if let Some(channel) = self.chanmap.get_mut(ok!(args.get(1))) {
+                    let set_at = entry
+                        .get(2)
+                        .and_then(|seconds| seconds.parse::<u64>().ok())
+                        .and_then(|seconds| Posix::from_seconds(seconds).datetime());
+
+                    let requested = channel.lists.insert(
+                        mode,
+                        mode::ListEntry {
+                            mask: ok!(entry.first()).clone(),
+                            set_by: entry.get(1).cloned(),
+                            set_at,
+                        },
+                    );
+
+                    if requested {
+                        return Ok(vec![]);
+                    }
+                }
+            }
+            Command::Numeric(
+                numeric @ (RPL_ENDOFBANLIST | RPL_ENDOFEXCEPTLIST | RPL_ENDOFINVEXLIST
+                | RPL_ENDOFQUIETLIST),
+                args,
+            ) => {
+                let mode = match numeric {
+                    RPL_ENDOFBANLIST => 'b',
+                    RPL_ENDOFEXCEPTLIST => 'e',
+                    RPL_ENDOFINVEXLIST => 'I',
+                    _ => 'q',
+                };
+
+                if let Some(channel) = self.chanmap.get_mut(ok!(args.get(1))) {
+                    if channel.lists.end(mode) {
+                        return Ok(vec![]);
+                    }
+                }
+            }
+            Command::Numeric(RPL_TOPICWHOTIME, args) => {
+                if let Some(channel) = self.chanmap.get_mut(&args[1]) {
+                    channel.topic.who = Some(ok!(args.get(2)).to_string());
+                    let timestamp = Posix::from_seconds(ok!(args.get(3)).parse::<u64>()?);
+                    channel.topic.time =
+                        Some(timestamp.datetime().ok_or_else(|| {
+                            anyhow!("Unable to parse timestamp: {:?}", timestamp)
+                        })?);
+                }
+                // Exclude topic message from history to prevent spam during dev
+                #[cfg(feature = "dev")]
+                return Ok(vec![]);
+            }
+            Command::Numeric(
+                ERR_CHANNELISFULL | ERR_INVITEONLYCHAN | ERR_BANNEDFROMCHAN | ERR_BADCHANNELKEY,
+                args,
+            ) if self
+                .rejoins
+                .get(ok!(args.get(1)))
+                .is_some_and(|rejoin| matches!(rejoin.state, RejoinState::Joining)) =>
+            {
+                let channel = ok!(args.get(1));
+                let status = self.schedule_rejoin(channel, None);
+
+                return Ok(vec![
+                    Event::Single(message.clone(), self.nickname().to_owned()),
+                    Event::Broadcast(Broadcast::Rejoin {
+                        channel: channel.clone(),
+                        status,
+                        sent_time: server_time(&message),
+                    }),
+                ]);
+            }
+            Command::Numeric(ERR_NOCHANMODES, args) => {
+                let channel = ok!(args.get(1));
+
+                // If the channel has not been joined but is in the configured channels,
+                // then interpret this numeric as ERR_NEEDREGGEDNICK (which has the
                 // same number as ERR_NOCHANMODES)
                 if !self.chanmap.contains_key(channel)
                     && self
@@ -1130,7 +1937,20 @@ impl Client {
                         .iter()
                         .any(|config_channel| config_channel == channel)
                 {
-                    self.registration_required_channels.push(channel.clone());
+                    match self.registration_required_channels.get(channel) {
+                        // Still refused once logged in, so there's no point waiting
+                        Some(PendingJoin::Retried(_)) => {
+                            let reason = args.last().cloned().unwrap_or_default();
+
+                            self.registration_required_channels
+                                .insert(channel.clone(), PendingJoin::Failed(reason));
+                        }
+                        Some(_) => {}
+                        None => {
+                            self.registration_required_channels
+                                .insert(channel.clone(), PendingJoin::Login(Instant::now()));
+                        }
+                    }
                 }
             }
             Command::Numeric(RPL_ISUPPORT, args) => {
@@ -1154,8 +1974,12 @@ impl Client {
                                         if let isupport::Parameter::MONITOR(target_limit) =
                                             parameter
                                         {
-                                            let messages =
-                                                group_monitors(&self.config.monitor, target_limit);
+                                            let messages = group_monitors(
+                                                &self.monitor,
+                                                target_limit,
+                                                self.line_len(),
+                                                self.target_limit("MONITOR"),
+                                            );
 
                                             for message in messages {
                                                 self.handle.try_send(message)?;
@@ -1208,18 +2032,8 @@ impl Client {
                     }
                 });
 
-                if old_user.nickname() == self.nickname()
-                    && accountname != "*"
-                    && !self.registration_required_channels.is_empty()
-                {
-                    for message in group_joins(
-                        &self.registration_required_channels,
-                        &self.config.channel_keys,
-                    ) {
-                        self.handle.try_send(message)?;
-                    }
-
-                    self.registration_required_channels.clear();
+                if old_user.nickname() == self.nickname() && accountname != "*" {
+                    self.retry_registration_required_channels()?;
                 }
             }
             Command::CHGHOST(new_username, new_hostname) => {
@@ -1227,6 +2041,10 @@ impl Client {
 
                 let ourself = old_user.nickname() == self.nickname();
 
+                if ourself {
+                    self.reported_host = Some(new_hostname.clone());
+                }
+
                 self.chanmap.values_mut().for_each(|channel| {
                     if let Some(user) = channel.users.take(&old_user) {
                         channel.users.insert(user.with_username_and_hostname(
@@ -1253,6 +2071,13 @@ impl Client {
                     .filter_map(|target| User::try_from(target).ok())
                     .collect::<Vec<_>>();
 
+                if targets.iter().all(|target| {
+                    self.is_waited_for_nick(target.nickname().as_ref())
+                        || self.is_regained_nick(target.nickname().as_ref())
+                }) {
+                    return Ok(vec![]);
+                }
+
                 return Ok(vec![Event::Notification(
                     message.clone(),
                     self.nickname().to_owned(),
@@ -1265,6 +2090,25 @@ impl Client {
                     .map(Nick::from)
                     .collect::<Vec<_>>();
 
+                if let Some(nick) = targets
+                    .iter()
+                    .find(|target| self.is_waited_for_nick(target.as_ref()))
+                {
+                    self.handle.try_send(command!("NICK", nick.to_string()))?;
+                } else if targets
+                    .iter()
+                    .any(|target| self.is_regained_nick(target.as_ref()))
+                {
+                    self.regain_nick()?;
+                }
+
+                if targets.iter().all(|target| {
+                    self.is_waited_for_nick(target.as_ref())
+                        || self.is_regained_nick(target.as_ref())
+                }) {
+                    return Ok(vec![]);
+                }
+
                 return Ok(vec![Event::Notification(
                     message.clone(),
                     self.nickname().to_owned(),
@@ -1274,6 +2118,124 @@ impl Client {
             Command::Numeric(RPL_ENDOFMONLIST, _) => {
                 return Ok(vec![]);
             }
+            Command::Numeric(RPL_ENDOFMOTD | ERR_NOMOTD, _) => {
+                // ISUPPORT has been received, so we know if SILENCE is supported
+                if self.silence_limit().is_some() && !self.syncing_silence {
+                    self.syncing_silence = true;
+                    self.handle.try_send(command!("SILENCE"))?;
+                }
+            }
+            Command::Numeric(RPL_SILELIST, args) => {
+                let mask = ignore::Mask::parse(ok!(args.last()));
+
+                if !self.silenced.contains(&mask) {
+                    self.silenced.push(mask);
+                }
+
+                if self.syncing_silence {
+                    return Ok(vec![]);
+                }
+            }
+            Command::Numeric(RPL_ENDOFSILELIST, _) if self.syncing_silence => {
+                self.syncing_silence = false;
+                self.sync_silence()?;
+
+                return Ok(vec![]);
+            }
+            Command::Numeric(ERR_SILELISTFULL, args) => {
+                let mask = ignore::Mask::parse(ok!(args.get(1)));
+
+                self.silenced.retain(|silenced| *silenced != mask);
+            }
+            Command::Numeric(RPL_ISON, _) if matches!(self.ison.requested.front(), Some(None)) => {
+                self.ison.requested.pop_front();
+            }
+            // An ISON rejected gets no reply, so later replies match the ones after it
+            Command::Numeric(ERR_NEEDMOREPARAMS | ERR_UNKNOWNCOMMAND, args)
+                if args
+                    .get(1)
+                    .is_some_and(|command| command.eq_ignore_ascii_case("ISON")) =>
+            {
+                self.ison.requested.pop_front();
+            }
+            Command::Numeric(RPL_ISON, args) if !self.ison.requested.is_empty() => {
+                let requested = self
+                    .ison
+                    .requested
+                    .pop_front()
+                    .flatten()
+                    .unwrap_or_default();
+                let casemapping = self.casemapping();
+                let online = ok!(args.get(1))
+                    .split_ascii_whitespace()
+                    .map(|nick| casemapping.normalize(nick))
+                    .collect::<HashSet<_>>();
+
+                let (online, offline): (Vec<_>, Vec<_>) = requested
+                    .into_iter()
+                    .partition(|nick| online.contains(&casemapping.normalize(nick)));
+
+                if matches!(self.regain, Some(Regain::Ison))
+                    && offline.iter().any(|nick| self.is_configured_nick(nick))
+                {
+                    self.regain_nick()?;
+                }
+
+                // The configured nickname is only reported if it's also monitored
+                let is_reported = |nick: &String| {
+                    !self.is_configured_nick(nick)
+                        || self.monitor.iter().any(|monitor| {
+                            casemapping.normalize(monitor) == casemapping.normalize(nick)
+                        })
+                };
+                let online = online.into_iter().filter(is_reported).collect::<Vec<_>>();
+                let offline = offline.into_iter().filter(is_reported).collect::<Vec<_>>();
+
+                let online = online
+                    .into_iter()
+                    .filter(|nick| self.ison.online.insert(casemapping.normalize(nick)))
+                    .collect::<Vec<_>>();
+                let offline = offline
+                    .into_iter()
+                    .filter(|nick| self.ison.online.remove(&casemapping.normalize(nick)))
+                    .collect::<Vec<_>>();
+
+                let mut events = vec![];
+
+                // Report changes the same way as MONITOR does
+                if !online.is_empty() {
+                    events.push(Event::Notification(
+                        proto::Message::from(Command::Numeric(
+                            RPL_MONONLINE,
+                            vec![self.nickname().to_string(), online.join(",")],
+                        ))
+                        .into(),
+                        self.nickname().to_owned(),
+                        Notification::MonitoredOnline(
+                            online
+                                .into_iter()
+                                .map(|nick| Nick::from(nick).into())
+                                .collect(),
+                        ),
+                    ));
+                }
+
+                if !offline.is_empty() {
+                    events.push(Event::Notification(
+                        proto::Message::from(Command::Numeric(
+                            RPL_MONOFFLINE,
+                            vec![self.nickname().to_string(), offline.join(",")],
+                        ))
+                        .into(),
+                        self.nickname().to_owned(),
+                        Notification::MonitoredOffline(
+                            offline.into_iter().map(Nick::from).collect(),
+                        ),
+                    ));
+                }
+
+                return Ok(events);
+            }
             Command::MARKREAD(target, Some(timestamp)) => {
                 if let Some(read_marker) = timestamp
                     .strip_prefix("timestamp=")
@@ -1288,6 +2250,57 @@ impl Client {
         Ok(vec![Event::Single(message, self.nickname().to_owned())])
     }
 
+    /// Sends the lines to `target` as one message, in a `draft/multiline` batch.
+    ///
+    /// The stream's flood protection holds the batch back until its end is
+    /// queued, then sends it whole.
+    fn send_multiline(
+        &mut self,
+        buffer: &buffer::Upstream,
+        target: &str,
+        lines: Vec<String>,
+        concat: bool,
+    ) {
+        let reference = generate_label();
+
+        self.send(
+            buffer,
+            command!("BATCH", format!("+{reference}"), "draft/multiline", target).into(),
+        );
+
+        for (index, line) in lines.into_iter().enumerate() {
+            let mut tags = vec![proto::Tag {
+                key: "batch".to_string(),
+                value: Some(reference.clone()),
+            }];
+
+            // Joined to the previous line without a line break
+            if concat && index > 0 {
+                tags.push(proto::Tag {
+                    key: "draft/multiline-concat".to_string(),
+                    value: None,
+                });
+            }
+
+            let message = proto::Message {
+                tags,
+                source: None,
+                command: Command::PRIVMSG(target.to_string(), line),
+            };
+
+            if let Err(e) = self.handle.try_send(message) {
+                log::warn!("Error sending message: {e}");
+            }
+        }
+
+        if let Err(e) = self
+            .handle
+            .try_send(command!("BATCH", format!("-{reference}")))
+        {
+            log::warn!("Error sending message: {e}");
+        }
+    }
+
     pub fn send_markread(&mut self, target: &str, read_marker: ReadMarker) -> Result<()> {
         if self.supports_read_marker {
             self.handle.try_send(command!(
@@ -1299,6 +2312,67 @@ impl Client {
         Ok(())
     }
 
+    /// Requests messages sent to `target` from the server, through CHATHISTORY
+    /// or the ZNC playback module.
+    fn backfill(&mut self, target: &str, backfill: Backfill) -> Result<()> {
+        let key = target.to_lowercase();
+        let request = backfill.to_string();
+
+        // Already requested, or the server had nothing more
+        if self.backfill_requests.get(&key) == Some(&request) {
+            return Ok(());
+        }
+
+        if self.supports_chathistory {
+            let limit = match self.isupport.get(&isupport::Kind::CHATHISTORY) {
+                Some(isupport::Parameter::CHATHISTORY(max)) if *max > 0 => BACKFILL_LIMIT.min(*max),
+                _ => BACKFILL_LIMIT,
+            };
+
+            let params = match &backfill {
+                Backfill::Before { id, server_time } => vec![
+                    "BEFORE".to_string(),
+                    target.to_string(),
+                    match id {
+                        Some(id) => format!("msgid={id}"),
+                        None => format!("timestamp={}", chathistory_timestamp(server_time)),
+                    },
+                    limit.to_string(),
+                ],
+                Backfill::Between(since, until) => vec![
+                    "BETWEEN".to_string(),
+                    target.to_string(),
+                    format!("timestamp={}", chathistory_timestamp(since)),
+                    format!("timestamp={}", chathistory_timestamp(until)),
+                    limit.to_string(),
+                ],
+            };
+
+            self.handle
+                .try_send(proto::command("CHATHISTORY", params))?;
+        } else if self.listed_caps.iter().any(|cap| cap == "znc.in/playback") {
+            let (since, until) = match &backfill {
+                Backfill::Before { server_time, .. } => (0.0, playback_timestamp(server_time)),
+                Backfill::Between(since, until) => {
+                    (playback_timestamp(since), playback_timestamp(until))
+                }
+            };
+
+            self.handle.try_send(command!(
+                "PRIVMSG",
+                "*playback",
+                format!("PLAY {target} {since} {until}"),
+            ))?;
+        } else {
+            return Ok(());
+        }
+
+        self.backfill_requests.insert(key.clone(), request);
+        self.backfill_pending.insert(key);
+
+        Ok(())
+    }
+
     // TODO allow configuring the "sorting method"
     // this function sorts channels together which have similar names when the chantype prefix
     // (sometimes multipled) is removed
@@ -1318,17 +2392,13 @@ impl Client {
         a.cmp(b)
     }
 
-    fn sync(&mut self) {
-        self.channels = self.chanmap.keys().cloned().sorted_by(|a, b| self.compare_channels(a, b)).collect();
-        self.users = self
+    /// Sorts the channels again, after one is joined or left.
+    fn sort_channels(&mut self) {
+        self.channels = self
             .chanmap
-            .iter()
-            .map(|(channel, state)| {
-                (
-                    channel.clone(),
-                    state.users.iter().sorted().cloned().collect(),
-                )
-            })
+            .keys()
+            .cloned()
+            .sorted_by(|a, b| self.compare_channels(a, b))
             .collect();
     }
 
@@ -1336,131 +2406,1060 @@ impl Client {
         &self.channels
     }
 
-    fn topic<'a>(&'a self, channel: &str) -> Option<&'a Topic> {
-        self.chanmap.get(channel).map(|channel| &channel.topic)
-    }
+    /// Keys learned for those of `channels` that have one.
+    fn learned_keys(&self, channels: &[String]) -> HashMap<String, String> {
+        channels
+            .iter()
+            .filter_map(|channel| {
+                let key = self
+                    .channel_keys
+                    .get(&self.casemapping().normalize(channel))?;
 
-    fn resolve_user_attributes<'a>(&'a self, channel: &str, user: &User) -> Option<&'a User> {
-        self.chanmap
-            .get(channel)
-            .and_then(|channel| channel.users.get(user))
+                Some((channel.clone(), key.clone()))
+            })
+            .collect()
     }
 
-    pub fn users<'a>(&'a self, channel: &str) -> &'a [User] {
-        self.users
-            .get(channel)
-            .map(Vec::as_slice)
-            .unwrap_or_default()
+    /// Remembers the key of `channel`, or forgets it when `None`.
+    fn learn_key(&mut self, channel: &str, key: Option<String>) {
+        let channel = self.casemapping().normalize(channel);
+
+        let changed = match key {
+            Some(key) => self.channel_keys.insert(channel, key.clone()) != Some(key),
+            None => self.channel_keys.remove(&channel).is_some(),
+        };
+
+        self.channel_keys_changed |= changed;
     }
 
-    fn user_channels(&self, nick: NickRef) -> Vec<String> {
-        self.channels()
+    /// Channels to join again on a new connection, with their keys. Ones
+    /// inherited from a previous connection are kept until registration.
+    fn open_channels(&self) -> Vec<(String, Option<String>)> {
+        self.chanmap
             .iter()
-            .filter(|channel| {
-                self.users(channel)
-                    .iter()
-                    .any(|user| user.nickname() == nick)
-            })
-            .cloned()
+            .map(|(channel, state)| (channel.clone(), state.key().map(String::from)))
+            .chain(self.reconnect_channels.iter().cloned())
             .collect()
     }
 
-    pub fn nickname(&self) -> NickRef {
-        // TODO: Fallback nicks
-        NickRef::from(
-            self.resolved_nick
-                .as_deref()
-                .unwrap_or(&self.config.nickname),
-        )
+    fn topic<'a>(&'a self, channel: &str) -> Option<&'a Topic> {
+        self.chanmap.get(channel).map(|channel| &channel.topic)
     }
 
-    pub fn tick(&mut self, now: Instant) -> Result<()> {
-        match self.highlight_blackout {
-            HighlightBlackout::Blackout(instant) => {
-                if now.duration_since(instant) >= HIGHLIGHT_BLACKOUT_INTERVAL {
-                    self.highlight_blackout = HighlightBlackout::Receiving;
-                }
-            }
-            HighlightBlackout::Receiving => {}
-        }
+    fn channel_modes<'a>(&'a self, channel: &str) -> Option<&'a mode::ChannelModes> {
+        self.chanmap.get(channel).map(|channel| &channel.modes)
+    }
 
-        for (channel, state) in self.chanmap.iter_mut() {
-            enum Request {
-                Poll,
-                Retry,
-            }
+    fn channel_lists<'a>(&'a self, channel: &str) -> Option<&'a mode::Lists> {
+        self.chanmap.get(channel).map(|channel| &channel.lists)
+    }
 
-            let request = match state.last_who {
-                Some(WhoStatus::Done(last))
-                    if !self.supports_away_notify && self.config.who_poll_enabled =>
-                {
-                    (now.duration_since(last) >= self.config.who_poll_interval)
-                        .then_some(Request::Poll)
-                }
-                Some(WhoStatus::Requested(requested, _)) => (now.duration_since(requested)
-                    >= self.config.who_retry_interval)
-                    .then_some(Request::Retry),
-                _ => None,
-            };
+    /// Parts `channel` and joins it again, with its learned, current or
+    /// configured key.
+    pub fn cycle(&mut self, channel: &str, reason: Option<String>) -> Result<()> {
+        let Some(state) = self.chanmap.get(channel) else {
+            bail!("not in channel {channel}");
+        };
 
-            if let Some(request) = request {
-                if self.isupport.contains_key(&isupport::Kind::WHOX) {
-                    let fields = if self.supports_account_notify {
-                        "tcnfa"
-                    } else {
-                        "tcnf"
-                    };
+        let key = self
+            .learned_keys(&[channel.to_string()])
+            .remove(channel)
+            .or_else(|| state.key().map(String::from))
+            .or_else(|| self.config.channel_keys.get(channel).cloned());
 
-                    self.handle.try_send(command!(
-                        "WHO",
-                        channel,
-                        fields,
-                        isupport::WHO_POLL_TOKEN.to_owned()
-                    ))?;
+        let message = if let Some(reason) = reason.or_else(|| self.config.part_message.clone()) {
+            command!("PART", channel, reason)
+        } else {
+            command!("PART", channel)
+        };
 
-                    state.last_who = Some(WhoStatus::Requested(
-                        Instant::now(),
-                        Some(isupport::WHO_POLL_TOKEN),
-                    ));
-                } else {
-                    self.handle.try_send(command!("WHO", channel))?;
-                    state.last_who = Some(WhoStatus::Requested(Instant::now(), None));
-                }
-                log::debug!(
-                    "[{}] {channel} - WHO {}",
-                    self.server,
-                    match request {
-                        Request::Poll => "poll",
-                        Request::Retry => "retry",
-                    }
-                );
-            }
+        self.handle.try_send(message)?;
+
+        if let Some(key) = key {
+            self.handle.try_send(command!("JOIN", channel, key))?;
+        } else {
+            self.handle.try_send(command!("JOIN", channel))?;
         }
+
         Ok(())
     }
 
-    pub fn chantypes(&self) -> &[char] {
-        self.isupport.get(&isupport::Kind::CHANTYPES).and_then(|chantypes| {
-            let isupport::Parameter::CHANTYPES(types) = chantypes else {
-                unreachable!("Corruption in isupport table.")
-            };
-            types.as_deref()
-        }).unwrap_or(proto::DEFAULT_CHANNEL_PREFIXES)
-    }
+    /// Sets modes on a channel, in as few MODE commands as allowed.
+    pub fn user_modes(&mut self, channel: &str, modes: Vec<mode::Mode<char>>) -> Result<()> {
+        let kinds = self.channel_mode_kinds();
 
-    pub fn statusmsg(&self) -> &[char] {
-        self.isupport.get(&isupport::Kind::STATUSMSG).map(|statusmsg| {
-            let isupport::Parameter::STATUSMSG(prefixes) = statusmsg else {
-                unreachable!("Corruption in isupport table.")
-            };
-            prefixes.as_ref()
-        }).unwrap_or(&[])
+        // +q is the founder prefix on some servers rather than quiet
+        if modes.iter().any(|mode| *mode.value() == 'q')
+            && kinds.class('q') != Some(mode::Class::List)
+        {
+            bail!("quiet (+q) is not supported on this server");
+        }
+
+        let limit = match self.isupport.get(&isupport::Kind::MODES) {
+            Some(isupport::Parameter::MODES(limit)) => limit.map(usize::from),
+            // Default when MODES isn't advertised
+            _ => Some(3),
+        };
+
+        for message in group_modes(channel, &modes, limit, self.line_len()) {
+            self.handle.try_send(message)?;
+        }
+
+        Ok(())
     }
 
-    pub fn is_channel(&self, target: &str) -> bool {
-        proto::is_channel(target, self.chantypes())
+    /// Bans `nick` from `channel` by the kind of `mask` given, or configured.
+    /// A full mask, e.g. `*!*@host`, is banned as is.
+    pub fn ban(&mut self, channel: &str, nick: &str, mask: Option<ban::Mask>) -> Result<()> {
+        let mask = if nick.contains(['!', '@']) {
+            nick.to_string()
+        } else {
+            let user = User::from(Nick::from(nick));
+            let known = self.resolve_user_attributes(channel, &user).or_else(|| {
+                self.chanmap
+                    .values()
+                    .find_map(|channel| channel.users.get(&user))
+            });
+            let user = known.unwrap_or(&user);
+            let kind = mask.unwrap_or(self.config.ban_mask);
+            let extban = ban::account_extban(&self.isupport);
+
+            match kind.of(user, extban.as_deref()) {
+                Some(mask) => mask,
+                None if kind == ban::Mask::Account && extban.is_none() => {
+                    bail!("account bans are not supported on this server")
+                }
+                None if kind == ban::Mask::Account => bail!("{nick} is not logged in"),
+                None => bail!("the host of {nick} is not known"),
+            }
+        };
+
+        self.user_modes(channel, vec![mode::Mode::Add('b', Some(mask))])
     }
-}
+
+    /// Requests the entries of a list mode, e.g. bans with `b`.
+    pub fn request_mode_list(&mut self, channel: &str, mode: char) -> Result<()> {
+        let Some(state) = self.chanmap.get_mut(channel) else {
+            bail!("not in channel {channel}");
+        };
+
+        state.lists.request(mode);
+
+        self.handle
+            .try_send(command!("MODE", channel.to_string(), format!("+{mode}")))?;
+
+        Ok(())
+    }
+
+    pub fn channel_mode_kinds(&self) -> mode::ChannelModeKinds {
+        mode::ChannelModeKinds::from_isupport(&self.isupport)
+    }
+
+    /// Whether an invite from `inviter` to `channel` is joined without asking.
+    fn joins_invite(&self, inviter: &User, channel: &str) -> bool {
+        if self.chanmap.contains_key(channel) {
+            return false;
+        }
+
+        if self.config.join_configured_invites && self.config.channels.contains(channel) {
+            return true;
+        }
+
+        let casemapping = self.casemapping();
+        let nick = casemapping.normalize(inviter.nickname().as_ref());
+        let account = inviter.accountname().or_else(|| {
+            self.chanmap
+                .values()
+                .find_map(|channel| channel.users.get(inviter))
+                .and_then(User::accountname)
+        });
+
+        self.config.join_invites_from.iter().any(|trusted| {
+            casemapping.normalize(trusted) == nick
+                || account.is_some_and(|account| account.eq_ignore_ascii_case(trusted))
+        })
+    }
+
+    /// Whether `message` is from a user matching an ignore mask, or one on
+    /// the server's SILENCE list, e.g. in history played back by a bouncer.
+    fn is_ignored(&self, message: &message::Encoded) -> bool {
+        message.user().is_some_and(|user| {
+            let account = account_tag(message);
+
+            self.is_masked(&self.config.ignore, &user, account)
+                || self.is_masked(&self.silenced, &user, account)
+                || self.is_masked(&self.flood_ignored, &user, account)
+        })
+    }
+
+    /// The number of masks the SILENCE list can hold, if supported.
+    fn silence_limit(&self) -> Option<usize> {
+        match self.isupport.get(&isupport::Kind::SILENCE) {
+            Some(isupport::Parameter::SILENCE(Some(limit))) => Some(usize::from(*limit)),
+            _ => None,
+        }
+    }
+
+    /// Adds the hostmasks in `ignore` missing from the SILENCE list, as many
+    /// as it can hold, so they're dropped while we're away from a bouncer.
+    fn sync_silence(&mut self) -> Result<()> {
+        let Some(limit) = self.silence_limit() else {
+            return Ok(());
+        };
+
+        let missing = self
+            .config
+            .ignore
+            .iter()
+            .filter(|mask| mask.hostmask().is_some() && !self.silenced.contains(mask))
+            .take(limit.saturating_sub(self.silenced.len()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for mask in missing {
+            if let Some(hostmask) = mask.hostmask() {
+                self.handle
+                    .try_send(command!("SILENCE", format!("+{hostmask}")))?;
+            }
+
+            self.silenced.push(mask);
+        }
+
+        Ok(())
+    }
+
+    /// Lists the SILENCE list, or adds (`+mask`) or removes (`-mask`) a mask.
+    pub fn silence(&mut self, mask: Option<&str>) -> Result<()> {
+        let Some(limit) = self.silence_limit() else {
+            bail!("SILENCE is not supported on this server");
+        };
+
+        let Some(mask) = mask else {
+            self.handle.try_send(command!("SILENCE"))?;
+
+            return Ok(());
+        };
+
+        let (add, mask) = match mask.strip_prefix('-') {
+            Some(mask) => (false, mask),
+            None => (true, mask.strip_prefix('+').unwrap_or(mask)),
+        };
+        let mask = ignore::Mask::parse(mask);
+        let Some(hostmask) = mask.hostmask() else {
+            bail!("only nick!user@host masks can be silenced");
+        };
+
+        if add {
+            if self.silenced.contains(&mask) {
+                bail!("{hostmask} is already silenced");
+            } else if self.silenced.len() >= limit {
+                bail!("the silence list is full ({limit} masks)");
+            }
+
+            self.handle
+                .try_send(command!("SILENCE", format!("+{hostmask}")))?;
+            self.silenced.push(mask);
+        } else {
+            self.handle
+                .try_send(command!("SILENCE", format!("-{hostmask}")))?;
+            self.silenced.retain(|silenced| *silenced != mask);
+        }
+
+        Ok(())
+    }
+
+    /// Whether messages from `user` are shown dimmed, without notifying.
+    pub fn is_soft_ignored(&self, user: &User) -> bool {
+        self.is_masked(&self.config.soft_ignore, user, None)
+    }
+
+    /// Whether `user` matches any of `masks`, filling in their host and
+    /// account from the channels we share when missing.
+    fn is_masked(&self, masks: &[ignore::Mask], user: &User, account: Option<&str>) -> bool {
+        if masks.is_empty() || user.nickname() == self.nickname() {
+            return false;
+        }
+
+        let known = self
+            .chanmap
+            .values()
+            .find_map(|channel| channel.users.get(user));
+        let account = account
+            .or_else(|| user.accountname())
+            .or_else(|| known.and_then(User::accountname));
+        let user = match (user.username(), user.hostname(), known) {
+            (None, _, Some(known)) | (_, None, Some(known)) => known,
+            _ => user,
+        };
+
+        masks
+            .iter()
+            .any(|mask| mask.matches(user, account, self.casemapping()))
+    }
+
+    fn resolve_user_attributes<'a>(&'a self, channel: &str, user: &User) -> Option<&'a User> {
+        self.chanmap
+            .get(channel)
+            .and_then(|channel| channel.users.get(user))
+    }
+
+    pub fn users<'a>(&'a self, channel: &str) -> &'a [User] {
+        self.chanmap
+            .get(channel)
+            .map(|channel| channel.users.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn user_channels(&self, nick: NickRef) -> Vec<String> {
+        let user = User::from(Nick::from(nick.as_ref()));
+
+        self.channels()
+            .iter()
+            .filter(|channel| {
+                self.chanmap
+                    .get(channel.as_str())
+                    .is_some_and(|channel| channel.users.get(&user).is_some())
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Max # targets of `command` the server takes at once, from TARGMAX.
+    /// Commands it doesn't list have no limit.
+    fn target_limit(&self, command: &str) -> Option<usize> {
+        let Some(isupport::Parameter::TARGMAX(limits)) =
+            self.isupport.get(&isupport::Kind::TARGMAX)
+        else {
+            return None;
+        };
+
+        limits
+            .iter()
+            .find(|limit| limit.command == command)
+            .and_then(|limit| limit.limit)
+            .filter(|limit| *limit > 0)
+            .map(usize::from)
+    }
+
+    /// Max length of lines sent to the server, from LINELEN if it's given.
+    fn line_len(&self) -> usize {
+        match self.isupport.get(&isupport::Kind::LINELEN) {
+            Some(isupport::Parameter::LINELEN(len)) => usize::from(*len),
+            _ => proto::format::BYTE_LIMIT,
+        }
+    }
+
+    /// Max length of lines sent to the server to be relayed to others, which
+    /// leaves room for the `:nick!user@host ` prefix they see them with.
+    pub fn relayed_line_len(&self) -> usize {
+        let nick = self.nickname().as_ref().len();
+        // Servers mark usernames not verified with ident with a `~`
+        let user = self.config.username.as_ref().map_or(nick, String::len) + 1;
+        let host = self
+            .reported_host
+            .as_ref()
+            .map_or(MAX_HOSTNAME_LEN, String::len);
+
+        self.line_len()
+            .saturating_sub(":!@ ".len() + nick + user + host)
+    }
+
+    pub fn nickname(&self) -> NickRef {
+        // TODO: Fallback nicks
+        NickRef::from(
+            self.resolved_nick
+                .as_deref()
+                .unwrap_or(&self.config.nickname),
+        )
+    }
+
+    /// Updates the list of nicknames to notify about, returning a summary for the user.
+    pub fn notify(&mut self, notify: crate::command::Notify) -> Result<String> {
+        let monitor_limit = self.isupport.get(&isupport::Kind::MONITOR).map(|monitor| {
+            let isupport::Parameter::MONITOR(limit) = monitor else {
+                unreachable!("Corruption in isupport table.")
+            };
+            *limit
+        });
+        let casemapping = self.casemapping();
+
+        match notify {
+            crate::command::Notify::Add(nicks) => {
+                let nicks = nicks
+                    .into_iter()
+                    .unique_by(|nick| casemapping.normalize(nick))
+                    .filter(|nick| {
+                        !self.monitor.iter().any(|monitored| {
+                            casemapping.normalize(monitored) == casemapping.normalize(nick)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                if nicks.is_empty() {
+                    return Ok("already on the notify list".to_string());
+                }
+
+                if let Some(monitor_limit) = monitor_limit {
+                    if let Some(limit) = monitor_limit.map(usize::from) {
+                        if self.monitor.len() + nicks.len() > limit {
+                            bail!("notify list is limited to {limit} nicknames on this server");
+                        }
+                    }
+
+                    for message in
+                        group_monitors(&nicks, None, self.line_len(), self.target_limit("MONITOR"))
+                    {
+                        self.handle.try_send(message)?;
+                    }
+                } else if self.resolved_nick.is_some() {
+                    // Poll ISON straight away for the new nicknames
+                    self.ison.next_poll = Some(Instant::now());
+                }
+
+                let summary = format!("added to notify list: {}", nicks.join(", "));
+
+                for nick in &nicks {
+                    self.notify_changes.add(nick, casemapping);
+                }
+                self.notify_changed = true;
+
+                self.monitor.extend(nicks);
+
+                Ok(summary)
+            }
+            crate::command::Notify::Del(nicks) => {
+                let (removed, monitor): (Vec<_>, Vec<_>) = std::mem::take(&mut self.monitor)
+                    .into_iter()
+                    .partition(|monitored| {
+                        nicks.iter().any(|nick| {
+                            casemapping.normalize(monitored) == casemapping.normalize(nick)
+                        })
+                    });
+
+                self.monitor = monitor;
+
+                if removed.is_empty() {
+                    return Ok("not on the notify list".to_string());
+                }
+
+                for nick in &removed {
+                    self.notify_changes.remove(nick, casemapping);
+                }
+                self.notify_changed = true;
+
+                if monitor_limit.is_some() {
+                    self.handle
+                        .try_send(command!("MONITOR", "-", removed.iter().join(",")))?;
+                } else {
+                    for nick in &removed {
+                        self.ison.online.remove(&casemapping.normalize(nick));
+                    }
+                }
+
+                Ok(format!("removed from notify list: {}", removed.join(", ")))
+            }
+            crate::command::Notify::List => {
+                if self.monitor.is_empty() {
+                    Ok("notify list is empty".to_string())
+                } else {
+                    Ok(format!("notify list: {}", self.monitor.join(", ")))
+                }
+            }
+        }
+    }
+
+    /// Schedules, lists or cancels timers, returning a summary for the user.
+    pub fn timer(&mut self, buffer: &buffer::Upstream, timer: crate::command::Timer) -> String {
+        match timer {
+            crate::command::Timer::Add {
+                delay,
+                repeat,
+                command,
+            } => {
+                let id = self.next_timer_id;
+                self.next_timer_id += 1;
+
+                let timer = Timer {
+                    id,
+                    buffer: buffer.clone(),
+                    command,
+                    interval: Duration::from_secs(delay),
+                    next: Instant::now() + Duration::from_secs(delay),
+                    remaining: (repeat > 0).then_some(repeat),
+                };
+                let summary = format!("timer {id} set: {timer}");
+
+                self.timers.push(timer);
+
+                summary
+            }
+            crate::command::Timer::List => {
+                if self.timers.is_empty() {
+                    "no active timers".to_string()
+                } else {
+                    self.timers
+                        .iter()
+                        .map(|timer| format!("timer {}: {timer}", timer.id))
+                        .join("; ")
+                }
+            }
+            crate::command::Timer::Cancel(id) => {
+                if let Some(index) = self.timers.iter().position(|timer| timer.id == id) {
+                    self.timers.remove(index);
+                    format!("timer {id} cancelled")
+                } else {
+                    format!("no timer with id {id}")
+                }
+            }
+        }
+    }
+
+    /// Takes the commands of timers which are due, along with the buffer they were set in.
+    pub fn expired_timers(&mut self, now: Instant) -> Vec<(buffer::Upstream, String)> {
+        let mut expired = vec![];
+
+        self.timers.retain_mut(|timer| {
+            if now < timer.next {
+                return true;
+            }
+
+            expired.push((timer.buffer.clone(), timer.command.clone()));
+
+            timer.next = now + timer.interval;
+
+            match timer.remaining.as_mut() {
+                Some(remaining) => {
+                    *remaining -= 1;
+                    *remaining > 0
+                }
+                None => true,
+            }
+        });
+
+        expired
+    }
+
+    /// Waits to join `channel` again after being kicked or refused, unless
+    /// it's been attempted too many times in a row.
+    fn schedule_rejoin(&mut self, channel: &str, key: Option<String>) -> RejoinStatus {
+        let max_attempts = self.config.rejoin_max_attempts;
+        let rejoin = self.rejoins.entry(channel.to_string()).or_default();
+
+        if rejoin.is_settled(Instant::now()) {
+            rejoin.attempts = 0;
+        }
+
+        rejoin.attempts += 1;
+        rejoin.key = key.or(rejoin.key.take());
+
+        if rejoin.attempts > max_attempts {
+            self.rejoins.remove(channel);
+
+            return RejoinStatus::GaveUp {
+                attempts: max_attempts,
+            };
+        }
+
+        rejoin.state = RejoinState::Waiting(Instant::now() + self.config.rejoin_delay);
+
+        RejoinStatus::Scheduled {
+            delay: self.config.rejoin_delay,
+            attempt: rejoin.attempts,
+            max_attempts,
+        }
+    }
+
+    /// Whether `nick` is the nickname in use we're waiting for through MONITOR.
+    fn is_waited_for_nick(&self, nick: &str) -> bool {
+        self.nick_request.as_ref().is_some_and(|request| {
+            matches!(request.wait, Some(NickWait::Monitor))
+                && self.casemapping().normalize(&request.nick) == self.casemapping().normalize(nick)
+        })
+    }
+
+    /// Marks us away with `message` while idle, or back once `None`. Away
+    /// set by hand is left as is.
+    fn set_auto_away(&mut self, message: Option<String>) -> Result<()> {
+        let registered = self.resolved_nick.is_some();
+
+        match message {
+            Some(message) => {
+                if self.away || self.auto_away.is_some() {
+                    return Ok(());
+                }
+
+                if registered {
+                    self.handle.try_send(command!("AWAY", &message))?;
+                }
+
+                self.auto_away = Some(message);
+            }
+            None => {
+                if self.auto_away.take().is_some() && (registered || self.supports_pre_away) {
+                    self.handle.try_send(command!("AWAY"))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_configured_nick(&self, nick: &str) -> bool {
+        self.casemapping().normalize(nick) == self.casemapping().normalize(&self.config.nickname)
+    }
+
+    /// Whether `nick` is the configured nickname we're waiting for through MONITOR.
+    fn is_regained_nick(&self, nick: &str) -> bool {
+        matches!(self.regain, Some(Regain::Monitor)) && self.is_configured_nick(nick)
+    }
+
+    /// Changes to the configured nickname now that it's free, recovering it
+    /// from NickServ first if set up to.
+    fn regain_nick(&mut self) -> Result<()> {
+        let nick = &self.config.nickname;
+
+        if let Some(nick_pass) = self.config.nick_password.as_ref() {
+            if self.config.should_ghost {
+                for sequence in &self.config.ghost_sequence {
+                    self.handle.try_send(command!(
+                        "PRIVMSG",
+                        "NickServ",
+                        format!("{sequence} {nick} {nick_pass}")
+                    ))?;
+                }
+            }
+        }
+
+        self.handle.try_send(command!("NICK", nick))?;
+
+        Ok(())
+    }
+
+    /// Stops watching for the configured nickname, no longer monitoring it
+    /// unless it's in the notify list.
+    fn stop_regaining_nick(&mut self) -> Result<()> {
+        if matches!(self.regain.take(), Some(Regain::Monitor))
+            && !self
+                .monitor
+                .iter()
+                .any(|nick| nick.eq_ignore_ascii_case(&self.config.nickname))
+        {
+            self.handle
+                .try_send(command!("MONITOR", "-", self.config.nickname.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the nickname asked for, no longer monitoring it unless it's
+    /// in the notify list.
+    fn stop_waiting_for_nick(&mut self) -> Result<()> {
+        if let Some(request) = self.nick_request.take() {
+            if matches!(request.wait, Some(NickWait::Monitor))
+                && !self
+                    .monitor
+                    .iter()
+                    .any(|nick| nick.eq_ignore_ascii_case(&request.nick))
+            {
+                self.handle
+                    .try_send(command!("MONITOR", "-", request.nick))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Joins the channels refused until we're logged in again, now that we are.
+    fn retry_registration_required_channels(&mut self) -> Result<()> {
+        let channels = self
+            .registration_required_channels
+            .iter()
+            .filter(|(_, pending)| matches!(pending, PendingJoin::Login(_)))
+            .map(|(channel, _)| channel.clone())
+            .collect::<Vec<_>>();
+
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        for message in group_joins(
+            &channels,
+            &self.config.channel_keys,
+            self.line_len(),
+            self.target_limit("JOIN"),
+        ) {
+            self.handle.try_send(message)?;
+        }
+
+        for channel in channels {
+            self.registration_required_channels
+                .insert(channel, PendingJoin::Retried(Instant::now()));
+        }
+
+        Ok(())
+    }
+
+    /// Acts on notices from NickServ and QuakeNet's Q.
+    fn services_notice(&mut self, user: &User, text: &str) -> Result<()> {
+        match services::reply(user, text) {
+            Some(services::Reply::Identified) => {
+                self.retry_registration_required_channels()?;
+            }
+            Some(services::Reply::WrongPassword) => {
+                log::warn!("[{}] identifying with NickServ failed", self.server);
+
+                self.fail_registration_required_channels(
+                    "joining requires a registered nickname, but NickServ rejected the password",
+                );
+            }
+            Some(services::Reply::IdentifyRequested(implementation))
+                if implementation.is_some() && self.services != implementation =>
+            {
+                log::info!("[{}] services: {implementation:?}", self.server);
+
+                self.services = implementation;
+            }
+            Some(services::Reply::IdentifyRequested(_)) | None => {}
+        }
+
+        let Some(q_auth) = &self.config.q_auth else {
+            return Ok(());
+        };
+
+        match quakenet::reply(user, text) {
+            Some(quakenet::Reply::Challenge(Some(challenge))) => {
+                let password = q_auth.password.as_deref().unwrap_or_default();
+
+                self.handle.try_send(command!(
+                    "PRIVMSG",
+                    quakenet::Q,
+                    quakenet::challenge_auth(&q_auth.username, password, &challenge)
+                ))?;
+            }
+            Some(quakenet::Reply::Challenge(None)) => {
+                log::warn!(
+                    "[{}] Q doesn't offer HMAC-SHA-256, not logging in",
+                    self.server
+                );
+            }
+            Some(quakenet::Reply::LoggedIn) => {
+                log::info!("[{}] logged in with Q", self.server);
+
+                if q_auth.hidden_host {
+                    self.handle
+                        .try_send(command!("MODE", self.nickname().to_string(), "+x"))?;
+                }
+
+                self.retry_registration_required_channels()?;
+            }
+            Some(quakenet::Reply::Failed) => {
+                log::warn!("[{}] logging in with Q failed", self.server);
+
+                self.fail_registration_required_channels(
+                    "joining requires being logged in, but Q rejected the password",
+                );
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Whether notifications from `user` are held back, for having repeated
+    /// themselves within the cooldown.
+    fn holds_back_notifications(&mut self, user: &User, text: &str) -> bool {
+        let Some(cooldown) = self.repeats.notification_cooldown else {
+            return false;
+        };
+
+        let now = Instant::now();
+        let nick = self.casemapping().normalize(user.nickname().as_ref());
+        let window = Duration::from_secs(self.repeats.window);
+
+        let repeated = self
+            .last_texts
+            .insert(nick.clone(), (text.to_string(), now))
+            .is_some_and(|(previous, sent)| {
+                now.duration_since(sent) <= window && message::is_repeated_text(&previous, text)
+            });
+
+        if repeated {
+            self.held_back
+                .insert(nick.clone(), now + Duration::from_secs(cooldown));
+        }
+
+        self.held_back.get(&nick).is_some_and(|until| now < *until)
+    }
+
+    /// Whether a direct message from `user` starts one query too many within
+    /// the flood window. Those users are ignored as well if they sent the
+    /// same text as others during it, when configured.
+    fn is_query_flood(&mut self, user: &User, text: &str) -> bool {
+        let Some(flood) = self.config.query_flood.clone() else {
+            return false;
+        };
+
+        let nick = self.casemapping().normalize(user.nickname().as_ref());
+
+        if self.queried.contains(&nick) {
+            return false;
+        }
+
+        let now = Instant::now();
+        self.new_queries
+            .retain(|(received, _, _)| now.duration_since(*received) <= flood.window);
+
+        if self.new_queries.len() < flood.queries {
+            self.new_queries
+                .push_back((now, user.clone(), text.to_string()));
+            self.queried.insert(nick);
+
+            return false;
+        }
+
+        if flood.auto_ignore {
+            let senders = self
+                .new_queries
+                .iter()
+                .filter(|(_, _, first)| message::is_repeated_text(first, text))
+                .map(|(_, sender, _)| sender.clone())
+                .collect::<Vec<_>>();
+
+            if !senders.is_empty() {
+                for sender in senders.iter().chain([user]) {
+                    let hostmask = ban::Mask::Host
+                        .of(sender, None)
+                        .unwrap_or_else(|| sender.nickname().to_string());
+                    let mask = ignore::Mask::parse(&hostmask);
+
+                    if !self.flood_ignored.contains(&mask) {
+                        log::info!("[{}] ignoring {hostmask} for flooding queries", self.server);
+                        self.flood_ignored.push(mask);
+                    }
+                }
+            }
+        }
+
+        log::info!(
+            "[{}] query flood, not opening a query with {}",
+            self.server,
+            user.nickname()
+        );
+
+        self.new_queries
+            .push_back((now, user.clone(), text.to_string()));
+
+        true
+    }
+
+    /// Gives up on the channels waiting for us to be logged in, since we won't be.
+    fn fail_registration_required_channels(&mut self, reason: &str) {
+        for pending in self.registration_required_channels.values_mut() {
+            if matches!(pending, PendingJoin::Login(_)) {
+                *pending = PendingJoin::Failed(reason.to_string());
+            }
+        }
+    }
+
+    /// Takes the channels refused until we're logged in which we've given up
+    /// on joining, along with why.
+    pub fn failed_joins(&mut self, now: Instant) -> Vec<(String, String)> {
+        let mut failed = vec![];
+
+        self.registration_required_channels
+            .retain(|channel, pending| {
+                let reason = match pending {
+                    PendingJoin::Login(since) => {
+                        (now.duration_since(*since) >= REGISTRATION_JOIN_TIMEOUT).then(|| {
+                            "joining requires a registered nickname, but you aren't logged in"
+                                .to_string()
+                        })
+                    }
+                    PendingJoin::Retried(since) => {
+                        (now.duration_since(*since) >= REGISTRATION_JOIN_TIMEOUT).then(|| {
+                            "no reply from the server to joining after logging in".to_string()
+                        })
+                    }
+                    PendingJoin::Failed(reason) => Some(reason.clone()),
+                };
+
+                if let Some(reason) = reason {
+                    log::warn!("[{}] {channel} - join failed: {reason}", self.server);
+                    failed.push((channel.clone(), reason));
+                    false
+                } else {
+                    true
+                }
+            });
+
+        failed
+    }
+
+    pub fn tick(&mut self, now: Instant) -> Result<()> {
+        let window = Duration::from_secs(self.repeats.window);
+        self.last_texts
+            .retain(|_, (_, sent)| now.duration_since(*sent) <= window);
+        self.held_back.retain(|_, until| now < *until);
+
+        match self.highlight_blackout {
+            HighlightBlackout::Blackout(instant) => {
+                if now.duration_since(instant) >= HIGHLIGHT_BLACKOUT_INTERVAL {
+                    self.highlight_blackout = HighlightBlackout::Receiving;
+                }
+            }
+            HighlightBlackout::Receiving => {}
+        }
+
+        // Forget channels joined again a while ago
+        self.rejoins.retain(|_, rejoin| !rejoin.is_settled(now));
+
+        for (channel, rejoin) in &mut self.rejoins {
+            if matches!(rejoin.state, RejoinState::Waiting(at) if now >= at) {
+                let key = rejoin
+                    .key
+                    .as_ref()
+                    .or_else(|| self.config.channel_keys.get(channel));
+
+                if let Some(key) = key {
+                    self.handle
+                        .try_send(command!("JOIN", channel.clone(), key.clone()))?;
+                } else {
+                    self.handle.try_send(command!("JOIN", channel.clone()))?;
+                }
+
+                rejoin.state = RejoinState::Joining;
+            }
+        }
+
+        if let Some(request) = self.nick_request.as_mut() {
+            if let Some(NickWait::Retry(next)) = &mut request.wait {
+                if now >= *next {
+                    *next = now + NICK_RETRY_INTERVAL;
+                    self.handle
+                        .try_send(command!("NICK", request.nick.clone()))?;
+                }
+            }
+        }
+
+        let supports_monitor = self.isupport.contains_key(&isupport::Kind::MONITOR);
+        let poll_ison = self
+            .ison
+            .next_poll
+            .is_some_and(|next_poll| now >= next_poll);
+
+        // Start watching for the configured nickname once ISUPPORT has been received
+        if poll_ison && matches!(self.regain, Some(Regain::Pending)) {
+            if supports_monitor {
+                self.handle
+                    .try_send(command!("MONITOR", "+", self.config.nickname.clone()))?;
+                self.regain = Some(Regain::Monitor);
+            } else {
+                self.regain = Some(Regain::Ison);
+            }
+        }
+
+        let mut ison = self.monitor.clone();
+
+        if matches!(self.regain, Some(Regain::Ison)) {
+            ison.push(self.config.nickname.clone());
+        }
+
+        if poll_ison && !ison.is_empty() && !supports_monitor {
+            for nicks in group_isons(&ison, self.line_len()) {
+                self.handle
+                    .try_send(command!("ISON", nicks.iter().join(" ")))?;
+                self.ison.requested.push_back(Some(nicks));
+            }
+
+            self.ison.next_poll = Some(now + ISON_POLL_INTERVAL);
+        }
+
+        for (channel, state) in self.chanmap.iter_mut() {
+            enum Request {
+                Poll,
+                Retry,
+            }
+
+            if !is_who_polled(&self.config, channel, state.users.as_slice().len()) {
+                continue;
+            }
+
+            let request = match state.last_who {
+                Some(WhoStatus::Done(last)) if !self.supports_away_notify => {
+                    (now.duration_since(last) >= self.config.who_poll_interval)
+                        .then_some(Request::Poll)
+                }
+                Some(WhoStatus::Requested(requested, _)) => (now.duration_since(requested)
+                    >= self.config.who_retry_interval)
+                    .then_some(Request::Retry),
+                _ => None,
+            };
+
+            if let Some(request) = request {
+                if self.isupport.contains_key(&isupport::Kind::WHOX) {
+                    let fields = if self.supports_account_notify {
+                        "tcnfa"
+                    } else {
+                        "tcnf"
+                    };
+
+                    self.handle.try_send(command!(
+                        "WHO",
+                        channel,
+                        fields,
+                        isupport::WHO_POLL_TOKEN.to_owned()
+                    ))?;
+
+                    state.last_who = Some(WhoStatus::Requested(
+                        Instant::now(),
+                        Some(isupport::WHO_POLL_TOKEN),
+                    ));
+                } else {
+                    self.handle.try_send(command!("WHO", channel))?;
+                    state.last_who = Some(WhoStatus::Requested(Instant::now(), None));
+                }
+                log::debug!(
+                    "[{}] {channel} - WHO {}",
+                    self.server,
+                    match request {
+                        Request::Poll => "poll",
+                        Request::Retry => "retry",
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn chantypes(&self) -> &[char] {
+        self.isupport.get(&isupport::Kind::CHANTYPES).and_then(|chantypes| {
+            let isupport::Parameter::CHANTYPES(types) = chantypes else {
+                unreachable!("Corruption in isupport table.")
+            };
+            types.as_deref()
+        }).unwrap_or(proto::DEFAULT_CHANNEL_PREFIXES)
+    }
+
+    pub fn statusmsg(&self) -> &[char] {
+        self.isupport.get(&isupport::Kind::STATUSMSG).map(|statusmsg| {
+            let isupport::Parameter::STATUSMSG(prefixes) = statusmsg else {
+                unreachable!("Corruption in isupport table.")
+            };
+            prefixes.as_ref()
+        }).unwrap_or(&[])
+    }
+
+    pub fn casemapping(&self) -> isupport::CaseMap {
+        match self.isupport.get(&isupport::Kind::CASEMAPPING) {
+            Some(isupport::Parameter::CASEMAPPING(casemap)) => *casemap,
+            _ => isupport::CaseMap::default(),
+        }
+    }
+
+    pub fn is_channel(&self, target: &str) -> bool {
+        proto::is_channel(target, self.chantypes())
+    }
+}
 
 #[derive(Debug)]
 enum HighlightBlackout {
@@ -1478,34 +3477,247 @@ impl HighlightBlackout {
 }
 
 #[derive(Debug, Default)]
-pub struct Map(BTreeMap<Server, State>);
+pub struct Map {
+    clients: BTreeMap<Server, State>,
+    /// Incremented on `/reconnect` to start a fresh connection stream.
+    connections: HashMap<Server, usize>,
+    consoles: HashMap<Server, console::Console>,
+    dcc_chats: dcc::chat::Manager,
+    highlights: config::Highlights,
+    repeats: config::buffer::Repeats,
+    quiet_hours: config::notification::DoNotDisturb,
+    /// Do not disturb turned on or off by hand, until quiet hours start or end.
+    do_not_disturb: Option<DoNotDisturb>,
+    /// Channels open when a connection was lost, joined again on reconnect.
+    open_channels: HashMap<Server, Vec<(String, Option<String>)>>,
+    away: config::Away,
+    last_input: Option<Instant>,
+    /// Marked away on all servers for being idle.
+    idle: bool,
+    channel_keys: channel::keys::Keys,
+    /// Channel keys changed since they were last saved.
+    channel_keys_changed: bool,
+    notify_lists: notify::Lists,
+    /// Notify lists changed since they were last saved.
+    notify_lists_changed: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DoNotDisturb {
+    enabled: bool,
+    scheduled: bool,
+}
 
 impl Map {
+    pub fn new(
+        highlights: config::Highlights,
+        repeats: config::buffer::Repeats,
+        quiet_hours: config::notification::DoNotDisturb,
+        away: config::Away,
+    ) -> Self {
+        Self {
+            highlights,
+            repeats,
+            quiet_hours,
+            away,
+            channel_keys: channel::keys::load(),
+            notify_lists: notify::load(),
+            ..Default::default()
+        }
+    }
+
+    /// Applies highlights to connected clients and ones connecting later.
+    pub fn set_highlights(&mut self, highlights: config::Highlights) {
+        for state in self.clients.values_mut() {
+            if let State::Ready(client) = state {
+                client.highlights = highlights.clone();
+            }
+        }
+
+        self.highlights = highlights;
+    }
+
+    pub fn set_repeats(&mut self, repeats: config::buffer::Repeats) {
+        for state in self.clients.values_mut() {
+            if let State::Ready(client) = state {
+                client.repeats = repeats;
+            }
+        }
+
+        self.repeats = repeats;
+    }
+
+    pub fn set_quiet_hours(&mut self, quiet_hours: config::notification::DoNotDisturb) {
+        self.quiet_hours = quiet_hours;
+    }
+
+    pub fn set_away(&mut self, away: config::Away) {
+        self.away = away;
+    }
+
+    /// Records input from the user, marking us back if we were idle.
+    pub fn input(&mut self, now: Instant) -> Result<()> {
+        self.last_input = Some(now);
+
+        if self.idle {
+            self.idle = false;
+
+            for state in self.clients.values_mut() {
+                if let State::Ready(client) = state {
+                    client.set_auto_away(None)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether notifications should be suppressed right now.
+    pub fn is_do_not_disturb(&self) -> bool {
+        let scheduled = self.is_quiet_hours();
+
+        match self.do_not_disturb {
+            Some(dnd) if dnd.scheduled == scheduled => dnd.enabled,
+            _ => scheduled,
+        }
+    }
+
+    /// Runs a `/dnd` command, returning a summary for the user.
+    pub fn do_not_disturb(&mut self, command: crate::command::DoNotDisturb) -> String {
+        use crate::command::DoNotDisturb as Command;
+
+        let enabled = match command {
+            Command::Toggle => Some(!self.is_do_not_disturb()),
+            Command::On => Some(true),
+            Command::Off => Some(false),
+            Command::Auto => None,
+        };
+
+        self.do_not_disturb = enabled.map(|enabled| DoNotDisturb {
+            enabled,
+            scheduled: self.is_quiet_hours(),
+        });
+
+        match (self.is_do_not_disturb(), enabled.is_some()) {
+            (true, true) => "do not disturb is on".to_string(),
+            (false, true) => "do not disturb is off".to_string(),
+            (true, false) => "do not disturb follows quiet hours, currently on".to_string(),
+            (false, false) => "do not disturb follows quiet hours, currently off".to_string(),
+        }
+    }
+
+    fn is_quiet_hours(&self) -> bool {
+        self.quiet_hours.is_scheduled(chrono::Local::now().time())
+    }
+
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.clients.len()
     }
 
     pub fn disconnected(&mut self, server: Server) {
-        self.0.insert(server, State::Disconnected);
+        // The connection dropped before writing the QUIT, nothing left to wait for
+        if self.quit_sent(&server) {
+            return;
+        }
+
+        // Ignore updates still in flight from a stopped connection
+        if !self.is_stopped(&server) {
+            self.set_state(server, State::Disconnected);
+        }
+    }
+
+    pub fn ready(&mut self, server: Server, mut client: Client) {
+        if !self.is_stopped(&server) && !self.is_quitting(&server) {
+            client.highlights = self.highlights.clone();
+            client.repeats = self.repeats;
+            client.reconnect_channels = self.open_channels.remove(&server).unwrap_or_default();
+            client.channel_keys = self.channel_keys.get(&server);
+            client.notify_changes = self.notify_lists.get(&server);
+            client.monitor = client
+                .notify_changes
+                .apply(&client.config.monitor, client.casemapping());
+            client.auto_away = self.idle.then(|| self.away.message.clone());
+            self.clients.insert(server, State::Ready(client));
+        }
+    }
+
+    /// Replaces the state of `server`, remembering the channels a connected
+    /// client had open.
+    fn set_state(&mut self, server: Server, state: State) {
+        if let Some(State::Ready(client)) = self.clients.insert(server.clone(), state) {
+            if client.channel_keys_changed {
+                self.channel_keys
+                    .insert(&server, client.channel_keys.clone());
+                self.channel_keys_changed = true;
+            }
+
+            if client.notify_changed {
+                self.notify_lists
+                    .insert(&server, client.notify_changes.clone());
+                self.notify_lists_changed = true;
+            }
+
+            self.open_channels.insert(server, client.open_channels());
+        }
     }
 
-    pub fn ready(&mut self, server: Server, client: Client) {
-        self.0.insert(server, State::Ready(client));
+    /// Saves channel keys if clients learned new ones.
+    pub fn save_channel_keys(
+        &mut self,
+    ) -> Option<BoxFuture<'static, Result<(), channel::keys::Error>>> {
+        for (server, state) in &mut self.clients {
+            if let State::Ready(client) = state {
+                if client.channel_keys_changed {
+                    client.channel_keys_changed = false;
+                    self.channel_keys
+                        .insert(server, client.channel_keys.clone());
+                    self.channel_keys_changed = true;
+                }
+            }
+        }
+
+        self.channel_keys_changed.then(|| {
+            self.channel_keys_changed = false;
+
+            channel::keys::save(self.channel_keys.clone()).boxed()
+        })
+    }
+
+    /// Saves notify lists if they were changed with `/notify`.
+    pub fn save_notify_lists(&mut self) -> Option<BoxFuture<'static, Result<(), notify::Error>>> {
+        for (server, state) in &mut self.clients {
+            if let State::Ready(client) = state {
+                if client.notify_changed {
+                    client.notify_changed = false;
+                    self.notify_lists
+                        .insert(server, client.notify_changes.clone());
+                    self.notify_lists_changed = true;
+                }
+            }
+        }
+
+        self.notify_lists_changed.then(|| {
+            self.notify_lists_changed = false;
+
+            notify::save(self.notify_lists.clone()).boxed()
+        })
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.clients.is_empty()
     }
 
     pub fn remove(&mut self, server: &Server) -> Option<Client> {
-        self.0.remove(server).and_then(|state| match state {
-            State::Disconnected => None,
+        self.open_channels.remove(server);
+
+        self.clients.remove(server).and_then(|state| match state {
+            State::Disconnected | State::Stopped | State::Quitting { .. } => None,
             State::Ready(client) => Some(client),
         })
     }
 
     pub fn client(&self, server: &Server) -> Option<&Client> {
-        if let Some(State::Ready(client)) = self.0.get(server) {
+        if let Some(State::Ready(client)) = self.clients.get(server) {
             Some(client)
         } else {
             None
@@ -1513,7 +3725,7 @@ impl Map {
     }
 
     pub fn client_mut(&mut self, server: &Server) -> Option<&mut Client> {
-        if let Some(State::Ready(client)) = self.0.get_mut(server) {
+        if let Some(State::Ready(client)) = self.clients.get_mut(server) {
             Some(client)
         } else {
             None
@@ -1532,18 +3744,59 @@ impl Map {
         }
     }
 
-    pub fn sync(&mut self, server: &Server) {
-        if let Some(State::Ready(client)) = self.0.get_mut(server) {
-            client.sync();
+    pub fn send(&mut self, buffer: &buffer::Upstream, message: message::Encoded) {
+        // Chats over DCC don't go through the server
+        if let buffer::Upstream::Query(server, nick) = buffer {
+            if let Some(remote) = dcc::chat::remote(nick.as_ref()) {
+                if let Command::PRIVMSG(_, text) = &message.command {
+                    if let Err(error) = self.dcc_chats.send(server, &remote, text.clone()) {
+                        log::warn!("Error sending DCC chat message: {error}");
+                    }
+                }
+                return;
+            }
         }
-    }
 
-    pub fn send(&mut self, buffer: &buffer::Upstream, message: message::Encoded) {
         if let Some(client) = self.client_mut(buffer.server()) {
             client.send(buffer, message);
         }
     }
 
+    /// Sends the lines as one message when the server supports `draft/multiline`,
+    /// returning whether they were sent. With `concat`, they're the parts of
+    /// a single line split to fit.
+    pub fn send_multiline(
+        &mut self,
+        buffer: &buffer::Upstream,
+        lines: Vec<String>,
+        concat: bool,
+    ) -> bool {
+        // Chats over DCC don't go through the server
+        let Some(target) = buffer
+            .target()
+            .filter(|target| dcc::chat::remote(target).is_none())
+        else {
+            return false;
+        };
+
+        match self.client_mut(buffer.server()) {
+            Some(client)
+                if client
+                    .multiline
+                    .is_some_and(|multiline| multiline.fits(&lines)) =>
+            {
+                client.send_multiline(buffer, &target, lines, concat);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Limits of multiline messages, when the server supports them.
+    pub fn get_multiline(&self, server: &Server) -> Option<Multiline> {
+        self.client(server).and_then(|client| client.multiline)
+    }
+
     pub fn send_markread(&mut self, server: &Server, target: &str, read_marker: ReadMarker) -> Result<()> {
         if let Some(client) = self.client_mut(server) {
             client.send_markread(target, read_marker)?;
@@ -1551,20 +3804,124 @@ impl Map {
         Ok(())
     }
 
-    pub fn join(&mut self, server: &Server, channels: &[String]) {
-        if let Some(client) = self.client_mut(server) {
-            client.join(channels);
-        }
+    pub fn join(&mut self, server: &Server, channels: &[String]) {
+        if let Some(client) = self.client_mut(server) {
+            client.join(channels);
+        }
+    }
+
+    pub fn part(&mut self, server: &Server, channels: &[String]) {
+        if let Some(client) = self.client_mut(server) {
+            client.part(channels);
+        }
+    }
+
+    pub fn quit(&mut self, server: &Server, reason: Option<String>) {
+        if let Some(client) = self.client_mut(server) {
+            client.quit(reason);
+        }
+    }
+
+    pub fn disconnect(&mut self, server: &Server, reason: Option<String>) -> Result<String> {
+        let Some(client) = self.client_mut(server) else {
+            bail!("not connected");
+        };
+
+        client.quit(reason);
+        // Stopped once the connection has written the QUIT, see `quit_sent`
+        self.set_state(server.clone(), State::Quitting { reconnect: false });
+
+        Ok(format!("disconnected from {server}"))
+    }
+
+    /// Runs a `/dcc` command, returning a summary for the user.
+    pub fn dcc(&mut self, server: &Server, dcc: crate::command::Dcc) -> Result<String> {
+        match dcc {
+            crate::command::Dcc::Chat(nick) => {
+                let Some(client) = self.client(server) else {
+                    bail!("not connected");
+                };
+
+                let handle = client.handle.clone();
+                let reported_host = client.reported_host.clone();
+
+                self.dcc_chats
+                    .open(server, Nick::from(nick), handle, reported_host)
+            }
+            crate::command::Dcc::Close(nick) => self.dcc_chats.close(server, &Nick::from(nick)),
+        }
+    }
+
+    pub fn dcc_chats(&self) -> &dcc::chat::Manager {
+        &self.dcc_chats
+    }
+
+    pub fn dcc_chats_mut(&mut self) -> &mut dcc::chat::Manager {
+        &mut self.dcc_chats
+    }
+
+    pub fn reconnect(&mut self, server: &Server) -> Result<String> {
+        match self.clients.get_mut(server) {
+            Some(State::Ready(client)) => {
+                client.quit(Some("Reconnecting".to_string()));
+                // Replaced once the connection has written the QUIT, see `quit_sent`
+                self.set_state(server.clone(), State::Quitting { reconnect: true });
+            }
+            Some(State::Quitting { reconnect }) => *reconnect = true,
+            Some(State::Disconnected | State::Stopped) => {
+                *self.connections.entry(server.clone()).or_default() += 1;
+                self.set_state(server.clone(), State::Disconnected);
+            }
+            None => bail!("not connected"),
+        }
+
+        Ok(format!("reconnecting to {server}"))
+    }
+
+    /// Finishes a `/reconnect` or `/disconnect` once the connection has
+    /// written its QUIT or dropped, returning whether one was pending.
+    pub fn quit_sent(&mut self, server: &Server) -> bool {
+        let Some(State::Quitting { reconnect }) = self.clients.get(server) else {
+            return false;
+        };
+
+        if *reconnect {
+            // Drops the connection that quit for a new one
+            *self.connections.entry(server.clone()).or_default() += 1;
+            self.clients.insert(server.clone(), State::Disconnected);
+        } else {
+            self.clients.insert(server.clone(), State::Stopped);
+        }
+
+        true
+    }
+
+    pub fn record_console(&mut self, server: &Server, lines: Vec<console::Line>) {
+        self.consoles
+            .entry(server.clone())
+            .or_default()
+            .extend(lines);
+    }
+
+    pub fn get_console(&self, server: &Server) -> Option<&console::Console> {
+        self.consoles.get(server)
     }
 
-    pub fn quit(&mut self, server: &Server, reason: Option<String>) {
-        if let Some(client) = self.client_mut(server) {
-            client.quit(reason);
-        }
+    pub fn is_stopped(&self, server: &Server) -> bool {
+        matches!(self.clients.get(server), Some(State::Stopped))
+    }
+
+    pub fn is_quitting(&self, server: &Server) -> bool {
+        matches!(self.clients.get(server), Some(State::Quitting { .. }))
+    }
+
+    /// Identifies the current connection stream of a server.
+    pub fn connection(&self, server: &Server) -> usize {
+        self.connections.get(server).copied().unwrap_or_default()
     }
 
     pub fn exit(&mut self) -> HashSet<Server> {
-        self.0
+        self.clients
             .iter_mut()
             .filter_map(|(server, state)| {
                 if let State::Ready(client) = state {
@@ -1587,6 +3944,11 @@ impl Map {
             .and_then(|client| client.resolve_user_attributes(channel, user))
     }
 
+    pub fn is_soft_ignored(&self, server: &Server, user: &User) -> bool {
+        self.client(server)
+            .is_some_and(|client| client.is_soft_ignored(user))
+    }
+
     pub fn get_channel_users<'a>(&'a self, server: &Server, channel: &str) -> &'a [User] {
         self.client(server)
             .map(|client| client.users(channel))
@@ -1605,16 +3967,69 @@ impl Map {
             .unwrap_or_default()
     }
 
+    pub fn get_channel_modes<'a>(
+        &'a self,
+        server: &Server,
+        channel: &str,
+    ) -> Option<&'a mode::ChannelModes> {
+        self.client(server)
+            .map(|client| client.channel_modes(channel))
+            .unwrap_or_default()
+    }
+
+    pub fn get_channel_lists<'a>(
+        &'a self,
+        server: &Server,
+        channel: &str,
+    ) -> Option<&'a mode::Lists> {
+        self.client(server)
+            .map(|client| client.channel_lists(channel))
+            .unwrap_or_default()
+    }
+
+    pub fn get_channel_mode_kinds(&self, server: &Server) -> mode::ChannelModeKinds {
+        self.client(server)
+            .map(Client::channel_mode_kinds)
+            .unwrap_or_default()
+    }
+
     pub fn get_channels<'a>(&'a self, server: &Server) -> &'a [String] {
         self.client(server)
             .map(|client| client.channels())
             .unwrap_or_default()
     }
 
-    pub fn get_isupport(&self, server: &Server) -> HashMap<isupport::Kind, isupport::Parameter> {
+    pub fn backfill(&mut self, server: &Server, target: &str, backfill: Backfill) -> Result<()> {
+        // The server has no history of chats over DCC
+        if dcc::chat::remote(target).is_some() {
+            return Ok(());
+        }
+
+        if let Some(client) = self.client_mut(server) {
+            client.backfill(target, backfill)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_isupport<'a>(
+        &'a self,
+        server: &Server,
+    ) -> &'a HashMap<isupport::Kind, isupport::Parameter> {
+        static EMPTY: Lazy<HashMap<isupport::Kind, isupport::Parameter>> = Lazy::new(HashMap::new);
+
         self.client(server)
-            .map(|client| client.isupport.clone())
-            .unwrap_or_default()
+            .map(|client| &client.isupport)
+            .unwrap_or(&EMPTY)
+    }
+
+    pub fn get_isupport_param<'a>(
+        &'a self,
+        server: &Server,
+        kind: isupport::Kind,
+    ) -> Option<&'a isupport::Parameter> {
+        self.client(server)
+            .and_then(|client| client.isupport.get(&kind))
     }
 
     pub fn get_chantypes<'a>(&'a self, server: &Server) -> &'a [char] {
@@ -1633,8 +4048,23 @@ impl Map {
         self.client(server).map(|client| &client.handle)
     }
 
+    pub fn is_away(&self, server: &Server) -> bool {
+        self.client(server).is_some_and(|client| client.away)
+    }
+
+    /// Max length of input sent to `server`, see [`Client::relayed_line_len`].
+    pub fn get_line_len(&self, server: &Server) -> usize {
+        self.client(server)
+            .map_or(proto::format::BYTE_LIMIT, Client::relayed_line_len)
+    }
+
+    pub fn get_reported_host(&self, server: &Server) -> Option<&str> {
+        self.client(server)
+            .and_then(|client| client.reported_host.as_deref())
+    }
+
     pub fn connected_servers(&self) -> impl Iterator<Item = &Server> {
-        self.0.iter().filter_map(|(server, state)| {
+        self.clients.iter().filter_map(|(server, state)| {
             if let State::Ready(_) = state {
                 Some(server)
             } else {
@@ -1644,21 +4074,178 @@ impl Map {
     }
 
     pub fn iter(&self) -> std::collections::btree_map::Iter<Server, State> {
-        self.0.iter()
+        self.clients.iter()
     }
 
     pub fn status(&self, server: &Server) -> Status {
-        self.0
+        self.clients
             .get(server)
             .map(|s| match s {
-                State::Disconnected => Status::Disconnected,
+                State::Disconnected | State::Stopped | State::Quitting { .. } => {
+                    Status::Disconnected
+                }
                 State::Ready(_) => Status::Connected,
             })
             .unwrap_or(Status::Unavailable)
     }
 
+    pub fn notify(&mut self, server: &Server, notify: crate::command::Notify) -> Result<String> {
+        if let Some(client) = self.client_mut(server) {
+            client.notify(notify)
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    pub fn cycle(&mut self, server: &Server, channel: &str, reason: Option<String>) -> Result<()> {
+        if let Some(client) = self.client_mut(server) {
+            client.cycle(channel, reason)
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    pub fn user_modes(
+        &mut self,
+        server: &Server,
+        channel: &str,
+        modes: Vec<mode::Mode<char>>,
+    ) -> Result<()> {
+        if let Some(client) = self.client_mut(server) {
+            client.user_modes(channel, modes)
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    pub fn ban(
+        &mut self,
+        server: &Server,
+        channel: &str,
+        nick: &str,
+        mask: Option<ban::Mask>,
+    ) -> Result<()> {
+        if let Some(client) = self.client_mut(server) {
+            client.ban(channel, nick, mask)
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    /// What each server supports and its recent console lines, for a bug
+    /// report.
+    pub fn debug_servers(&self) -> Vec<debug::Server> {
+        self.clients
+            .iter()
+            .map(|(server, state)| {
+                let status = match state {
+                    State::Disconnected => "disconnected",
+                    State::Ready(_) => "connected",
+                    State::Stopped => "stopped",
+                    State::Quitting { .. } => "quitting",
+                };
+                let mut report = debug::Server::new(
+                    server.to_string(),
+                    status.to_string(),
+                    self.consoles.get(server),
+                );
+
+                if let State::Ready(client) = state {
+                    report.caps = client.listed_caps.clone();
+                    report.isupport = client
+                        .isupport
+                        .values()
+                        .map(|parameter| format!("{parameter:?}"))
+                        .sorted()
+                        .collect();
+                }
+
+                report
+            })
+            .collect()
+    }
+
+    /// The ignore masks and filters in effect on `server`.
+    pub fn ignore_list(&self, server: &Server) -> Option<ignore::list::List> {
+        self.client(server)
+            .map(|client| ignore::list::List::of(&client.config))
+    }
+
+    pub fn silence(&mut self, server: &Server, mask: Option<&str>) -> Result<()> {
+        if let Some(client) = self.client_mut(server) {
+            client.silence(mask)
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    pub fn request_mode_list(&mut self, server: &Server, channel: &str, mode: char) -> Result<()> {
+        if let Some(client) = self.client_mut(server) {
+            client.request_mode_list(channel, mode)
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    pub fn timer(
+        &mut self,
+        buffer: &buffer::Upstream,
+        timer: crate::command::Timer,
+    ) -> Result<String> {
+        if let Some(client) = self.client_mut(buffer.server()) {
+            Ok(client.timer(buffer, timer))
+        } else {
+            bail!("not connected")
+        }
+    }
+
+    pub fn expired_timers(&mut self, now: Instant) -> Vec<(buffer::Upstream, String)> {
+        self.clients
+            .values_mut()
+            .filter_map(|state| match state {
+                State::Ready(client) => Some(client.expired_timers(now)),
+                State::Disconnected | State::Stopped | State::Quitting { .. } => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Takes the channels given up on joining on each server, along with why.
+    pub fn failed_joins(&mut self, now: Instant) -> Vec<(Server, String, String)> {
+        self.clients
+            .iter_mut()
+            .filter_map(|(server, state)| match state {
+                State::Ready(client) => Some(
+                    client
+                        .failed_joins(now)
+                        .into_iter()
+                        .map(|(channel, reason)| (server.clone(), channel, reason)),
+                ),
+                State::Disconnected | State::Stopped | State::Quitting { .. } => None,
+            })
+            .flatten()
+            .collect()
+    }
+
     pub fn tick(&mut self, now: Instant) -> Result<()> {
-        for client in self.0.values_mut() {
+        let last_input = *self.last_input.get_or_insert(now);
+
+        if !self.idle
+            && self
+                .away
+                .idle()
+                .is_some_and(|idle| now.duration_since(last_input) >= idle)
+        {
+            self.idle = true;
+
+            for state in self.clients.values_mut() {
+                if let State::Ready(client) = state {
+                    client.set_auto_away(Some(self.away.message.clone()))?;
+                }
+            }
+        }
+
+        for client in self.clients.values_mut() {
             if let State::Ready(client) = client {
                 client.tick(now)?;
             }
@@ -1694,10 +4281,53 @@ impl Context {
     }
 }
 
+/// Messages to request from the server's history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backfill {
+    /// Messages older than the given one
+    Before {
+        id: Option<String>,
+        server_time: DateTime<Utc>,
+    },
+    /// Messages sent while disconnected
+    Between(DateTime<Utc>, DateTime<Utc>),
+}
+
+impl Backfill {
+    pub fn before(message: &message::Message) -> Self {
+        Self::Before {
+            id: message.id.clone(),
+            server_time: message.server_time,
+        }
+    }
+}
+
+impl fmt::Display for Backfill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backfill::Before { id: Some(id), .. } => write!(f, "before {id}"),
+            Backfill::Before { server_time, .. } => write!(f, "before {server_time}"),
+            Backfill::Between(since, until) => write!(f, "between {since} and {until}"),
+        }
+    }
+}
+
+fn chathistory_timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Seconds since the epoch, as taken by the ZNC playback module.
+fn playback_timestamp(time: &DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 1000.0
+}
+
 #[derive(Debug)]
 pub struct Batch {
     context: Option<Context>,
     events: Vec<Event>,
+    /// Target of requested history, whose messages are kept as is
+    backfill: Option<String>,
+    messages: Vec<message::Encoded>,
 }
 
 impl Batch {
@@ -1705,7 +4335,49 @@ impl Batch {
         Self {
             context,
             events: vec![],
+            backfill: None,
+            messages: vec![],
+        }
+    }
+}
+
+/// Limits of `draft/multiline` messages, advertised by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Multiline {
+    pub max_bytes: usize,
+    pub max_lines: Option<usize>,
+}
+
+impl Multiline {
+    fn parse(cap: &str) -> Option<Self> {
+        let value = cap.strip_prefix("draft/multiline=")?;
+
+        let mut max_bytes = None;
+        let mut max_lines = None;
+
+        for token in value.split(',') {
+            match token.split_once('=') {
+                Some(("max-bytes", value)) => max_bytes = value.parse().ok(),
+                Some(("max-lines", value)) => max_lines = value.parse().ok(),
+                _ => {}
+            }
         }
+
+        Some(Self {
+            max_bytes: max_bytes?,
+            max_lines,
+        })
+    }
+
+    /// Whether the lines fit in one multiline message.
+    pub fn fits(&self, lines: &[String]) -> bool {
+        // Lines are counted as joined by line feeds
+        let bytes = lines.iter().map(String::len).sum::<usize>() + lines.len().saturating_sub(1);
+
+        bytes <= self.max_bytes
+            && self
+                .max_lines
+                .is_none_or(|max_lines| lines.len() <= max_lines)
     }
 }
 
@@ -1713,6 +4385,15 @@ fn generate_label() -> String {
     Posix::now().as_nanos().to_string()
 }
 
+/// The services account of the sender, from `account-tag`.
+fn account_tag(message: &message::Encoded) -> Option<&str> {
+    message
+        .tags
+        .iter()
+        .find(|tag| tag.key == "account")
+        .and_then(|tag| tag.value.as_deref())
+}
+
 fn remove_tag(key: &str, tags: &mut Vec<irc::proto::Tag>) -> Option<String> {
     tags.remove(tags.iter().position(|tag| tag.key == key)?)
         .value
@@ -1749,15 +4430,61 @@ enum RegistrationStep {
     End,
 }
 
+#[derive(Debug)]
+struct Timer {
+    id: usize,
+    buffer: buffer::Upstream,
+    command: String,
+    interval: Duration,
+    next: Instant,
+    remaining: Option<u32>,
+}
+
+impl fmt::Display for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let interval = self.interval.as_secs();
+
+        match self.remaining {
+            Some(1) => write!(f, "{} (in {interval}s)", self.command),
+            Some(remaining) => write!(
+                f,
+                "{} (every {interval}s, {remaining} times remaining)",
+                self.command
+            ),
+            None => write!(f, "{} (every {interval}s)", self.command),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ison {
+    next_poll: Option<Instant>,
+    /// ISON sent, in order, with the nicknames polled, or none if sent by
+    /// the user, so its reply is shown
+    requested: VecDeque<Option<Vec<String>>>,
+    online: HashSet<String>,
+}
+
 #[derive(Debug, Default)]
 pub struct Channel {
-    pub users: HashSet<User>,
+    pub users: ChannelUsers,
     pub last_who: Option<WhoStatus>,
     pub topic: Topic,
+    pub modes: mode::ChannelModes,
+    /// Modes were requested on join, so their replies aren't shown
+    pub modes_requested: bool,
+    pub lists: mode::Lists,
     pub names_init: bool,
+    /// Key the channel was joined with, or last set with MODE
+    pub key: Option<String>,
 }
 
 impl Channel {
+    /// The channel's key, to join it again with.
+    pub fn key(&self) -> Option<&str> {
+        self.modes.key().or(self.key.as_deref())
+    }
+
     pub fn update_user_away(&mut self, user: &str, flags: &str) {
         let user = User::from(Nick::from(user));
 
@@ -1792,6 +4519,78 @@ pub struct Topic {
     pub time: Option<DateTime<Utc>>,
 }
 
+/// A channel being joined again after a kick.
+#[derive(Debug, Default)]
+struct Rejoin {
+    /// Attempts in a row, counting ones refused by the server
+    attempts: usize,
+    /// The channel's key when we were kicked
+    key: Option<String>,
+    state: RejoinState,
+}
+
+impl Rejoin {
+    /// Whether we've stayed in the channel long enough for the next kick to
+    /// count as a new one, rather than another attempt.
+    fn is_settled(&self, now: Instant) -> bool {
+        match self.state {
+            RejoinState::Joined(joined) => now.duration_since(joined) >= REJOIN_RESET_INTERVAL,
+            RejoinState::Joining | RejoinState::Waiting(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+enum RejoinState {
+    #[default]
+    Joining,
+    /// Waiting to join until the given time
+    Waiting(Instant),
+    /// Joined at the given time
+    Joined(Instant),
+}
+
+/// A nickname asked for with `/nick` once registered.
+#[derive(Debug)]
+struct NickRequest {
+    nick: String,
+    /// Where it was asked for, to report errors in
+    buffer: buffer::Upstream,
+    /// Waiting for it to be free, when it was in use
+    wait: Option<NickWait>,
+}
+
+#[derive(Debug)]
+enum NickWait {
+    /// Told by MONITOR when it's free
+    Monitor,
+    /// Asked for again at the given time
+    Retry(Instant),
+}
+
+/// Watching for the configured nickname to be free.
+#[derive(Debug)]
+enum Regain {
+    /// Until ISUPPORT tells whether MONITOR is supported
+    Pending,
+    /// Told by MONITOR when it's free
+    Monitor,
+    /// Polled with ISON along with the notify list
+    Ison,
+}
+
+/// A configured channel the server refused to let us join until our
+/// nickname is registered (ERR_NEEDREGGEDNICK).
+#[derive(Debug, Clone)]
+enum PendingJoin {
+    /// Waiting for us to be logged in
+    Login(Instant),
+    /// Joined again once logged in, waiting for the server
+    Retried(Instant),
+    /// Refused again once logged in, with the server's reason
+    Failed(String),
+}
+
 #[derive(Debug, Clone)]
 pub enum WhoStatus {
     Requested(Instant, Option<isupport::WhoToken>),
@@ -1801,30 +4600,67 @@ pub enum WhoStatus {
 
 fn group_capability_requests<'a>(
     capabilities: &'a [&'a str],
+    line_len: usize,
 ) -> impl Iterator<Item = proto::Message> + 'a {
-    const MAX_LEN: usize = proto::format::BYTE_LIMIT - b"CAP REQ :\r\n".len();
+    let max_len = line_len.saturating_sub(b"CAP REQ :\r\n".len());
 
-    capabilities
-        .iter()
-        .scan(0, |count, capability| {
-            // Capability + a space
-            *count += capability.len() + 1;
+    // Capability + a space
+    chunk_by_len(capabilities, max_len, None, |capability| {
+        capability.len() + 1
+    })
+    .into_iter()
+    .map(|capabilities| command!("CAP", "REQ", capabilities.into_iter().join(" ")))
+}
 
-            let chunk = *count / MAX_LEN;
+/// Group modes together into as few MODE messages as `limit` (the MODES
+/// ISUPPORT parameter) and the line length allow
+fn group_modes(
+    channel: &str,
+    modes: &[mode::Mode<char>],
+    limit: Option<usize>,
+    line_len: usize,
+) -> Vec<proto::Message> {
+    let max_len = line_len.saturating_sub(b"MODE  \r\n".len() + channel.len());
+
+    let mut chunks: Vec<Vec<mode::Mode<char>>> = vec![];
+    let mut len = 0;
+
+    for mode in modes {
+        // Sign + mode + argument with a space
+        let mode_len = 3 + mode.arg().map_or(0, str::len);
+
+        match chunks.last_mut() {
+            Some(chunk)
+                if !matches!(limit, Some(limit) if chunk.len() >= limit)
+                    && len + mode_len <= max_len =>
+            {
+                chunk.push(mode.clone());
+                len += mode_len;
+            }
+            _ => {
+                chunks.push(vec![mode.clone()]);
+                len = mode_len;
+            }
+        }
+    }
 
-            Some((chunk, capability))
+    chunks
+        .iter()
+        .filter_map(|chunk| mode::encode(chunk))
+        .map(|(modestring, args)| {
+            proto::Command::MODE(channel.to_string(), Some(modestring), Some(args)).into()
         })
-        .into_group_map()
-        .into_values()
-        .map(|capabilities| command!("CAP", "REQ", capabilities.into_iter().join(" ")))
+        .collect()
 }
 
 /// Group channels together into as few JOIN messages as possible
 fn group_joins<'a>(
     channels: &'a [String],
     keys: &'a HashMap<String, String>,
+    line_len: usize,
+    target_limit: Option<usize>,
 ) -> impl Iterator<Item = proto::Message> + 'a {
-    const MAX_LEN: usize = proto::format::BYTE_LIMIT - b"JOIN \r\n".len();
+    let max_len = line_len.saturating_sub(b"JOIN \r\n".len());
 
     let (without_keys, with_keys): (Vec<_>, Vec<_>) = channels.iter().partition_map(|channel| {
         keys.get(channel)
@@ -1832,64 +4668,228 @@ fn group_joins<'a>(
             .unwrap_or(Either::Left(channel))
     });
 
-    let joins_without_keys = without_keys
-        .into_iter()
-        .scan(0, |count, channel| {
-            // Channel + a comma
-            *count += channel.len() + 1;
+    // Channel + a comma
+    let joins_without_keys = chunk_by_len(without_keys, max_len, target_limit, |channel| {
+        channel.len() + 1
+    })
+    .into_iter()
+    .map(|channels| command!("JOIN", channels.into_iter().join(",")));
 
-            let chunk = *count / MAX_LEN;
+    // Channel + key + a comma for each
+    let joins_with_keys = chunk_by_len(with_keys, max_len, target_limit, |(channel, key)| {
+        channel.len() + key.len() + 2
+    })
+    .into_iter()
+    .map(|values| {
+        command!(
+            "JOIN",
+            values.iter().map(|(c, _)| c).join(","),
+            values.iter().map(|(_, k)| k).join(",")
+        )
+    });
 
-            Some((chunk, channel))
-        })
-        .into_group_map()
-        .into_values()
-        .map(|channels| command!("JOIN", channels.into_iter().join(",")));
+    joins_without_keys.chain(joins_with_keys)
+}
 
-    let joins_with_keys = with_keys
-        .into_iter()
-        .scan(0, |count, (channel, key)| {
-            // Channel + key + a comma for each
-            *count += channel.len() + key.len() + 2;
+/// Splits `items` into as few chunks as possible, each of at most `max_len`
+/// bytes as measured by `len` and `max_count` items. Items longer than that
+/// get a chunk of their own.
+fn chunk_by_len<T>(
+    items: impl IntoIterator<Item = T>,
+    max_len: usize,
+    max_count: Option<usize>,
+    len: impl Fn(&T) -> usize,
+) -> Vec<Vec<T>> {
+    let mut chunks: Vec<Vec<T>> = vec![];
+    let mut chunk_len = 0;
+
+    for item in items {
+        let item_len = len(&item);
+
+        match chunks.last_mut() {
+            Some(chunk)
+                if chunk_len + item_len <= max_len
+                    && max_count.is_none_or(|max_count| chunk.len() < max_count) =>
+            {
+                chunk.push(item);
+                chunk_len += item_len;
+            }
+            _ => {
+                chunks.push(vec![item]);
+                chunk_len = item_len;
+            }
+        }
+    }
+
+    chunks
+}
 
-            let chunk = *count / MAX_LEN;
+fn rejoins_on_kick(config: &config::Server, channel: &str) -> bool {
+    config
+        .channels
+        .settings(channel)
+        .rejoin_on_kick
+        .unwrap_or(config.rejoin_on_kick)
+}
 
-            Some((chunk, (channel, key)))
-        })
-        .into_group_map()
-        .into_values()
-        .map(|values| {
-            command!(
-                "JOIN",
-                values.iter().map(|(c, _)| c).join(","),
-                values.iter().map(|(_, k)| k).join(",")
-            )
-        });
+/// Whether users of `channel` are polled with WHO for their away state. Only
+/// NAMES lists those of channels above `who_poll_max_users`, as WHO on them
+/// floods the server with replies.
+fn is_who_polled(config: &config::Server, channel: &str, users: usize) -> bool {
+    config.who_poll_enabled
+        && config.channels.settings(channel).who_poll
+        && users <= config.who_poll_max_users
+}
 
-    joins_without_keys.chain(joins_with_keys)
+/// Whether `user` has voice or a membership prefix ranked above it, e.g.
+/// halfop or owner where the server's PREFIX has them.
+fn is_voiced(user: &User, prefixes: &[isupport::PrefixMap]) -> bool {
+    let voice = AccessLevel::from_mode('v', prefixes);
+
+    user.highest_access_level()
+        .is_some_and(|level| voice.is_none_or(|voice| level >= voice))
+}
+
+fn is_ison(command: &Command) -> bool {
+    match command {
+        Command::Raw(raw) => raw
+            .split_ascii_whitespace()
+            .next()
+            .is_some_and(|command| command.eq_ignore_ascii_case("ISON")),
+        command => command.command().eq_ignore_ascii_case("ISON"),
+    }
+}
+
+fn group_isons(nicks: &[String], line_len: usize) -> impl Iterator<Item = Vec<String>> + '_ {
+    let max_len = line_len.saturating_sub(b"ISON \r\n".len());
+
+    // Nick + a space
+    chunk_by_len(nicks.iter().cloned(), max_len, None, |nick| nick.len() + 1).into_iter()
 }
 
 fn group_monitors(
     targets: &[String],
     target_limit: Option<u16>,
+    line_len: usize,
+    command_limit: Option<usize>,
 ) -> impl Iterator<Item = proto::Message> + '_ {
-    const MAX_LEN: usize = proto::format::BYTE_LIMIT - b"MONITOR + \r\n".len();
+    let max_len = line_len.saturating_sub(b"MONITOR + \r\n".len());
 
-    if let Some(target_limit) = target_limit.map(usize::from) {
+    let targets = if let Some(target_limit) = target_limit.map(usize::from) {
         &targets[0..std::cmp::min(target_limit, targets.len())]
     } else {
         targets
+    };
+
+    // Target + a comma
+    chunk_by_len(targets, max_len, command_limit, |target| target.len() + 1)
+        .into_iter()
+        .map(|targets| command!("MONITOR", "+", targets.into_iter().join(",")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grouped_joins() {
+        let channels = ["#a", "#b", "#c", "#d", "#e"].map(String::from);
+        let keys = HashMap::from([("#e".to_string(), "key".to_string())]);
+
+        let joins = |line_len, target_limit| {
+            group_joins(&channels, &keys, line_len, target_limit)
+                .map(|message| proto::format::message(message).trim_end().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(joins(512, None), ["JOIN #a,#b,#c,#d", "JOIN #e key"]);
+        assert_eq!(
+            joins(512, Some(3)),
+            ["JOIN #a,#b,#c", "JOIN #d", "JOIN #e key"]
+        );
+        assert_eq!(
+            joins(b"JOIN #a,#b,\r\n".len(), None),
+            ["JOIN #a,#b", "JOIN #c,#d", "JOIN #e key"]
+        );
+        assert_eq!(joins(512, Some(1)).len(), 5);
     }
-    .iter()
-    .scan(0, |count, target| {
-        // Target + a comma
-        *count += target.len() + 1;
 
-        let chunk = *count / MAX_LEN;
+    #[test]
+    fn grouped_modes() {
+        let modes = [
+            mode::Mode::Add('o', Some("alice".into())),
+            mode::Mode::Add('o', Some("bob".into())),
+            mode::Mode::Add('v', Some("carol".into())),
+            mode::Mode::Remove('v', Some("dan".into())),
+            mode::Mode::Add('b', Some("*!*@spam".into())),
+        ];
+
+        let lines = |limit, line_len| {
+            group_modes("#c", &modes, limit, line_len)
+                .into_iter()
+                .map(|message| proto::format::message(message).trim_end().to_string())
+                .collect::<Vec<_>>()
+        };
 
-        Some((chunk, target))
-    })
-    .into_group_map()
-    .into_values()
-    .map(|targets| command!("MONITOR", "+", targets.into_iter().join(",")))
+        assert_eq!(
+            lines(None, 512),
+            ["MODE #c +oov-v+b alice bob carol dan *!*@spam"]
+        );
+        assert_eq!(
+            lines(Some(3), 512),
+            ["MODE #c +oov alice bob carol", "MODE #c -v+b dan *!*@spam"]
+        );
+        assert_eq!(
+            lines(None, b"MODE #c +oo alice bob \r\n".len()),
+            [
+                "MODE #c +oo alice bob",
+                "MODE #c +v-v carol dan",
+                "MODE #c +b *!*@spam"
+            ]
+        );
+        assert_eq!(lines(Some(1), 512).len(), 5);
+    }
+
+    #[test]
+    fn grouped_isons() {
+        let nicks = ["alice", "bob", "carol"].map(String::from);
+
+        let isons = |line_len| group_isons(&nicks, line_len).collect::<Vec<_>>();
+
+        assert_eq!(isons(512), [vec!["alice", "bob", "carol"]]);
+        assert_eq!(
+            isons(b"ISON alice bob \r\n".len()),
+            [vec!["alice", "bob"], vec!["carol"]]
+        );
+    }
+
+    #[test]
+    fn voiced_by_prefix() {
+        let prefixes = [('~', 'q'), ('&', 'a'), ('@', 'o'), ('%', 'h'), ('+', 'v')]
+            .map(|(prefix, mode)| isupport::PrefixMap { prefix, mode });
+        let voiced = |user| is_voiced(&User::parse(user, &prefixes).unwrap(), &prefixes);
+
+        assert!(voiced("~owner"));
+        assert!(voiced("&admin"));
+        assert!(voiced("%halfop"));
+        assert!(voiced("+voice"));
+        assert!(!voiced("user"));
+
+        // Without voice, any prefix will do
+        let prefixes = [isupport::PrefixMap {
+            prefix: '@',
+            mode: 'o',
+        }];
+        assert!(is_voiced(
+            &User::parse("@op", &prefixes).unwrap(),
+            &prefixes
+        ));
+    }
+
+    #[test]
+    fn ison_sent_by_user() {
+        assert!(is_ison(&command!("ISON", "alice bob").command));
+        assert!(is_ison(&Command::Raw("ison alice".into())));
+        assert!(!is_ison(&command!("PRIVMSG", "alice", "ISON").command));
+    }
 }
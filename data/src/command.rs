@@ -1,10 +1,15 @@
+use std::path::PathBuf;
 use std::str::FromStr;
 
+use chrono::NaiveDate;
 use irc::proto;
 use itertools::Itertools;
 use regex::Regex;
 
-use crate::{buffer, ctcp, message::formatting};
+use crate::history::export::{self, Export};
+use crate::history::import;
+use crate::services::{self, Service};
+use crate::{ban, buffer, ctcp, message::formatting, mode};
 
 #[derive(Debug, Clone, Copy)]
 pub enum Kind {
@@ -13,15 +18,43 @@ pub enum Kind {
     Nick,
     Quit,
     Msg,
+    Opmsg,
     Me,
     Whois,
     Part,
+    Cycle,
     Topic,
     Kick,
+    Ban,
+    Silence,
     Mode,
     Format,
     Away,
+    Ctcp,
+    Notify,
     Raw,
+    Timer,
+    Amsg,
+    Anotice,
+    Disconnect,
+    Reconnect,
+    Op,
+    Deop,
+    Voice,
+    Devoice,
+    Quiet,
+    Export,
+    Import,
+    Dcc,
+    Dnd,
+    Layout,
+    Ignores,
+    Debug,
+    LogLevel,
+    Ns,
+    Cs,
+    Identify,
+    Register,
 }
 
 impl FromStr for Kind {
@@ -34,15 +67,43 @@ impl FromStr for Kind {
             "nick" => Ok(Kind::Nick),
             "quit" => Ok(Kind::Quit),
             "msg" => Ok(Kind::Msg),
+            "opmsg" => Ok(Kind::Opmsg),
             "me" | "describe" => Ok(Kind::Me),
             "whois" => Ok(Kind::Whois),
             "part" | "leave" => Ok(Kind::Part),
+            "cycle" => Ok(Kind::Cycle),
             "topic" | "t" => Ok(Kind::Topic),
             "kick" => Ok(Kind::Kick),
+            "ban" => Ok(Kind::Ban),
+            "silence" => Ok(Kind::Silence),
             "mode" | "m" => Ok(Kind::Mode),
             "format" | "f" => Ok(Kind::Format),
             "away" => Ok(Kind::Away),
-            "raw" => Ok(Kind::Raw),
+            "ctcp" => Ok(Kind::Ctcp),
+            "notify" => Ok(Kind::Notify),
+            "raw" | "quote" => Ok(Kind::Raw),
+            "timer" => Ok(Kind::Timer),
+            "amsg" => Ok(Kind::Amsg),
+            "anotice" => Ok(Kind::Anotice),
+            "disconnect" => Ok(Kind::Disconnect),
+            "reconnect" => Ok(Kind::Reconnect),
+            "op" => Ok(Kind::Op),
+            "deop" => Ok(Kind::Deop),
+            "voice" => Ok(Kind::Voice),
+            "devoice" => Ok(Kind::Devoice),
+            "quiet" => Ok(Kind::Quiet),
+            "export" => Ok(Kind::Export),
+            "import" => Ok(Kind::Import),
+            "dcc" => Ok(Kind::Dcc),
+            "dnd" => Ok(Kind::Dnd),
+            "layout" => Ok(Kind::Layout),
+            "ignores" => Ok(Kind::Ignores),
+            "debug" => Ok(Kind::Debug),
+            "loglevel" => Ok(Kind::LogLevel),
+            "ns" | "nickserv" => Ok(Kind::Ns),
+            "cs" | "chanserv" => Ok(Kind::Cs),
+            "identify" | "id" => Ok(Kind::Identify),
+            "register" => Ok(Kind::Register),
             _ => Err(()),
         }
     }
@@ -58,11 +119,31 @@ pub enum Command {
     Me(String, String),
     Whois(Option<String>, String),
     Part(String, Option<String>),
+    /// Parts a channel and joins it again with its key
+    Cycle(String, Option<String>),
     Topic(String, Option<String>),
     Kick(String, String, Option<String>),
+    Ban(String, String, Option<ban::Mask>),
+    Silence(Option<String>),
     Mode(String, Option<String>, Option<Vec<String>>),
     Away(Option<String>),
+    Ctcp(ctcp::Command, String, Option<String>),
+    Notify(Notify),
     Raw(String),
+    Timer(Timer),
+    Broadcast(Broadcast),
+    Disconnect(Option<String>),
+    Reconnect,
+    UserModes(String, Vec<mode::Mode<char>>),
+    Export(Export),
+    Import(import::Format, PathBuf),
+    Dcc(Dcc),
+    DoNotDisturb(DoNotDisturb),
+    Layout(Layout),
+    Ignores(Ignores),
+    /// Writes a report of the configuration and the state of every server
+    DebugExport,
+    LogLevel(LogLevel),
     Unknown(String, Vec<String>),
 }
 
@@ -102,6 +183,14 @@ pub fn parse(s: &str, buffer: Option<&buffer::Upstream>) -> Result<Command, Erro
             Kind::Msg => {
                 validated::<2, 0, true>(args, |[target, msg], []| Command::Msg(target, msg))
             }
+            Kind::Opmsg => {
+                let channel = buffer
+                    .and_then(|b| b.channel())
+                    .ok_or(Error::NotInChannel)?;
+
+                // Only seen by the channel's operators, through STATUSMSG
+                validated::<1, 0, true>(args, |[text], _| Command::Msg(format!("@{channel}"), text))
+            }
             Kind::Me => {
                 if let Some(target) = buffer.and_then(|b| b.target()) {
                     validated::<1, 0, true>(args, |[text], _| Command::Me(target, text))
@@ -116,12 +205,47 @@ pub fn parse(s: &str, buffer: Option<&buffer::Upstream>) -> Result<Command, Erro
             Kind::Part => validated::<1, 1, true>(args, |[chanlist], [reason]| {
                 Command::Part(chanlist, reason)
             }),
+            Kind::Cycle => {
+                let channel = buffer
+                    .and_then(|b| b.channel())
+                    .ok_or(Error::NotInChannel)?;
+
+                validated::<0, 1, true>(args, |_, [reason]| {
+                    Command::Cycle(channel.to_string(), reason)
+                })
+            }
             Kind::Topic => {
                 validated::<1, 1, true>(args, |[channel], [topic]| Command::Topic(channel, topic))
             }
             Kind::Kick => validated::<2, 1, true>(args, |[channel, user], [comment]| {
                 Command::Kick(channel, user, comment)
             }),
+            Kind::Ban => {
+                let channel = buffer
+                    .and_then(|b| b.channel())
+                    .ok_or(Error::NotInChannel)?;
+
+                match args.as_slice() {
+                    [nick] => Ok(Command::Ban(channel.to_string(), nick.to_string(), None)),
+                    [nick, mask] => {
+                        let mask = mask
+                            .parse()
+                            .map_err(|_| Error::InvalidBanMask(mask.to_string()))?;
+
+                        Ok(Command::Ban(
+                            channel.to_string(),
+                            nick.to_string(),
+                            Some(mask),
+                        ))
+                    }
+                    _ => Err(Error::IncorrectArgCount {
+                        min: 1,
+                        max: 2,
+                        actual: args.len(),
+                    }),
+                }
+            }
+            Kind::Silence => validated::<0, 1, false>(args, |_, [mask]| Command::Silence(mask)),
             Kind::Mode => {
                 if let Some((target, rest)) = args.split_first() {
                     if let Some((mode_string, mode_arguments)) = rest.split_first() {
@@ -145,7 +269,178 @@ pub fn parse(s: &str, buffer: Option<&buffer::Upstream>) -> Result<Command, Erro
                 }
             }
             Kind::Away => validated::<0, 1, true>(args, |_, [comment]| Command::Away(comment)),
+            Kind::Ctcp => validated::<2, 1, true>(args, |[target, command], [params]| {
+                Command::Ctcp(ctcp::Command::from(command.as_str()), target, params)
+            }),
+            Kind::Notify => {
+                let nicks = || {
+                    args.iter()
+                        .skip(1)
+                        .flat_map(|nicks| nicks.split(','))
+                        .filter(|nick| !nick.is_empty())
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                };
+
+                match args.first().map(|s| s.to_lowercase()).as_deref() {
+                    None | Some("list" | "l") => Ok(Command::Notify(Notify::List)),
+                    Some("add" | "+") if !nicks().is_empty() => {
+                        Ok(Command::Notify(Notify::Add(nicks())))
+                    }
+                    Some("del" | "-") if !nicks().is_empty() => {
+                        Ok(Command::Notify(Notify::Del(nicks())))
+                    }
+                    Some("add" | "+" | "del" | "-") => Err(Error::MissingArgs),
+                    Some(_) => Err(Error::InvalidSubcommand),
+                }
+            }
             Kind::Raw => Ok(Command::Raw(raw.to_string())),
+            Kind::Timer => parse_timer(raw).map(Command::Timer),
+            Kind::Disconnect => {
+                validated::<0, 1, true>(args, |_, [reason]| Command::Disconnect(reason))
+            }
+            Kind::Reconnect => validated::<0, 0, false>(args, |_, _| Command::Reconnect),
+            Kind::Op | Kind::Deop | Kind::Voice | Kind::Devoice | Kind::Quiet => {
+                let channel = buffer
+                    .and_then(|b| b.channel())
+                    .ok_or(Error::NotInChannel)?;
+
+                if args.is_empty() {
+                    return Err(Error::MissingArgs);
+                }
+
+                let modes = args
+                    .iter()
+                    .map(|nick| {
+                        let nick = Some(nick.to_string());
+
+                        match kind {
+                            Kind::Op => mode::Mode::Add('o', nick),
+                            Kind::Deop => mode::Mode::Remove('o', nick),
+                            Kind::Voice => mode::Mode::Add('v', nick),
+                            Kind::Devoice => mode::Mode::Remove('v', nick),
+                            _ => mode::Mode::Add('q', nick),
+                        }
+                    })
+                    .collect();
+
+                Ok(Command::UserModes(channel.to_string(), modes))
+            }
+            Kind::Export => parse_export(args).map(Command::Export),
+            Kind::Import => {
+                // Keep the path as written, it may contain spaces
+                let Some((format, path)) = raw.trim().split_once(char::is_whitespace) else {
+                    return Err(Error::MissingArgs);
+                };
+
+                let format = format
+                    .parse()
+                    .map_err(|_| Error::InvalidImportFormat(format.to_string()))?;
+
+                Ok(Command::Import(format, PathBuf::from(path.trim())))
+            }
+            Kind::Dcc => match args.first().map(|s| s.to_lowercase()).as_deref() {
+                Some("chat") => validated::<1, 0, false>(args[1..].to_vec(), |[nick], _| {
+                    Command::Dcc(Dcc::Chat(nick))
+                }),
+                Some("close") => validated::<1, 0, false>(args[1..].to_vec(), |[nick], _| {
+                    Command::Dcc(Dcc::Close(nick))
+                }),
+                Some(_) => Err(Error::InvalidSubcommand),
+                None => Err(Error::MissingArgs),
+            },
+            Kind::Dnd => match args.first().map(|s| s.to_lowercase()).as_deref() {
+                None => Ok(Command::DoNotDisturb(DoNotDisturb::Toggle)),
+                Some("on") => Ok(Command::DoNotDisturb(DoNotDisturb::On)),
+                Some("off") => Ok(Command::DoNotDisturb(DoNotDisturb::Off)),
+                Some("auto") => Ok(Command::DoNotDisturb(DoNotDisturb::Auto)),
+                Some(_) => Err(Error::InvalidSubcommand),
+            },
+            Kind::Layout => {
+                // Names may contain spaces
+                let name = args.get(1..).unwrap_or_default().join(" ");
+
+                match args.first().map(|s| s.to_lowercase()).as_deref() {
+                    None | Some("list") => Ok(Command::Layout(Layout::List)),
+                    Some("save" | "load" | "delete" | "del") if name.is_empty() => {
+                        Err(Error::MissingArgs)
+                    }
+                    Some("save") => Ok(Command::Layout(Layout::Save(name))),
+                    Some("load") => Ok(Command::Layout(Layout::Load(name))),
+                    Some("delete" | "del") => Ok(Command::Layout(Layout::Delete(name))),
+                    Some(_) => Err(Error::InvalidSubcommand),
+                }
+            }
+            Kind::Ignores => {
+                // Keep the path as written, it may contain spaces
+                let (subcommand, path) = raw
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or(Error::MissingArgs)?;
+                let path = PathBuf::from(path.trim());
+
+                match subcommand.to_lowercase().as_str() {
+                    "export" => Ok(Command::Ignores(Ignores::Export(path))),
+                    "import" => Ok(Command::Ignores(Ignores::Import(path))),
+                    _ => Err(Error::InvalidSubcommand),
+                }
+            }
+            Kind::Debug => match args.first().map(|s| s.to_lowercase()).as_deref() {
+                Some("export") => Ok(Command::DebugExport),
+                Some(_) => Err(Error::InvalidSubcommand),
+                None => Err(Error::MissingArgs),
+            },
+            Kind::LogLevel => {
+                let module = args.get(1).map(|module| module.to_string());
+
+                match args.first().map(|s| s.to_lowercase()).as_deref() {
+                    _ if args.len() > 2 => Err(Error::IncorrectArgCount {
+                        min: 0,
+                        max: 2,
+                        actual: args.len(),
+                    }),
+                    None => Ok(Command::LogLevel(LogLevel::Show)),
+                    Some("reset") => Ok(Command::LogLevel(LogLevel::Reset(module))),
+                    Some(level) => level
+                        .parse()
+                        .map(|level| Command::LogLevel(LogLevel::Set(level, module)))
+                        .map_err(|_| Error::InvalidLogLevel(level.to_string())),
+                }
+            }
+            Kind::Ns => validated::<1, 0, true>(args, |[text], _| {
+                Command::Msg(Service::NickServ.nick().to_string(), text)
+            }),
+            Kind::Cs => validated::<1, 0, true>(args, |[text], _| {
+                Command::Msg(Service::ChanServ.nick().to_string(), text)
+            }),
+            Kind::Identify => validated::<1, 1, false>(args, |[first], [second]| {
+                let text = match second {
+                    Some(password) => services::identify(Some(&first), &password),
+                    None => services::identify(None, &first),
+                };
+
+                Command::Msg(Service::NickServ.nick().to_string(), text)
+            }),
+            Kind::Register => validated::<2, 0, false>(args, |[password, email], _| {
+                Command::Msg(
+                    Service::NickServ.nick().to_string(),
+                    services::register(&password, &email),
+                )
+            }),
+            Kind::Amsg | Kind::Anotice => {
+                let (all_servers, args) = match args.split_first() {
+                    Some((&"-all", rest)) => (true, rest.to_vec()),
+                    _ => (false, args),
+                };
+
+                validated::<1, 0, true>(args, |[text], _| {
+                    Command::Broadcast(Broadcast {
+                        text,
+                        notice: matches!(kind, Kind::Anotice),
+                        all_servers,
+                    })
+                })
+            }
             Kind::Format => {
                 if let Some(target) = buffer.and_then(|b| b.target()) {
                     Ok(Command::Msg(target, formatting::encode(raw, false)))
@@ -158,6 +453,87 @@ pub fn parse(s: &str, buffer: Option<&buffer::Upstream>) -> Result<Command, Erro
     }
 }
 
+/// Longest delay of a timer, in seconds (30 days)
+const MAX_TIMER_DELAY: u64 = 30 * 24 * 60 * 60;
+
+fn parse_timer(raw: &str) -> Result<Timer, Error> {
+    fn next_word(s: &str) -> (&str, &str) {
+        let s = s.trim_start();
+        s.split_once(char::is_whitespace).unwrap_or((s, ""))
+    }
+
+    fn parse_number<T: FromStr>(s: &str) -> Result<T, Error> {
+        s.parse().map_err(|_| Error::InvalidNumber(s.to_string()))
+    }
+
+    let (first, rest) = next_word(raw);
+
+    match first.to_lowercase().as_str() {
+        "" | "list" => Ok(Timer::List),
+        "cancel" | "del" => match next_word(rest) {
+            ("", _) => Err(Error::MissingArgs),
+            (id, _) => Ok(Timer::Cancel(parse_number(id)?)),
+        },
+        delay => {
+            let delay = parse_number(delay)
+                .ok()
+                .filter(|delay| *delay <= MAX_TIMER_DELAY)
+                .ok_or_else(|| Error::InvalidNumber(delay.to_string()))?;
+
+            let (repeat, command) = match next_word(rest) {
+                ("-repeat", rest) => {
+                    let (repeat, command) = next_word(rest);
+                    (parse_number(repeat)?, command)
+                }
+                _ => (1, rest),
+            };
+
+            let command = command.trim();
+
+            if command.is_empty() {
+                Err(Error::MissingArgs)
+            } else if delay == 0 && repeat != 1 {
+                // Would run on every tick
+                Err(Error::RepeatWithoutDelay)
+            } else {
+                Ok(Timer::Add {
+                    delay,
+                    repeat,
+                    command: command.to_string(),
+                })
+            }
+        }
+    }
+}
+
+fn parse_export(args: Vec<&str>) -> Result<Export, Error> {
+    if args.len() > 3 {
+        return Err(Error::IncorrectArgCount {
+            min: 0,
+            max: 3,
+            actual: args.len(),
+        });
+    }
+
+    let parse_date = |date: &&str| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| Error::InvalidDate(date.to_string()))
+    };
+
+    let format = match args.first() {
+        Some(format) => format
+            .parse()
+            .map_err(|_| Error::InvalidExportFormat(format.to_string()))?,
+        None => export::Format::Text,
+    };
+
+    Ok(Export {
+        format,
+        since: args.get(1).map(parse_date).transpose()?,
+        until: args.get(2).map(parse_date).transpose()?,
+    })
+}
+
 // TODO: Expand `validated` so we can better indicate which parameters is optional.
 fn validated<const EXACT: usize, const OPT: usize, const TEXT: bool>(
     args: Vec<&str>,
@@ -218,12 +594,109 @@ impl TryFrom<Command> for proto::Command {
                 proto::Command::MODE(target, modestring, modearguments)
             }
             Command::Away(comment) => proto::Command::AWAY(comment),
+            Command::Ctcp(command, target, params) => {
+                // Without an argument, PING is sent the time to tell the round trip from
+                let params = params
+                    .or_else(|| matches!(command, ctcp::Command::Ping).then(ctcp::ping_param));
+
+                ctcp::query_command(&command, target, params)
+            }
+            Command::Notify(_) => return Err(()),
             Command::Raw(raw) => proto::Command::Raw(raw),
+            Command::Timer(_) => return Err(()),
+            Command::Broadcast(_) => return Err(()),
+            Command::Disconnect(_) => return Err(()),
+            Command::Reconnect => return Err(()),
+            Command::Cycle(..) => return Err(()),
+            Command::UserModes(..) => return Err(()),
+            Command::Ban(..) => return Err(()),
+            Command::Silence(_) => return Err(()),
+            Command::Export(_) => return Err(()),
+            Command::Import(..) => return Err(()),
+            Command::Dcc(_) => return Err(()),
+            Command::DoNotDisturb(_) => return Err(()),
+            Command::Layout(_) => return Err(()),
+            Command::Ignores(_) => return Err(()),
+            Command::DebugExport => return Err(()),
+            Command::LogLevel(_) => return Err(()),
             Command::Unknown(command, args) => proto::Command::new(&command, args),
         })
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum Notify {
+    Add(Vec<String>),
+    Del(Vec<String>),
+    List,
+}
+
+#[derive(Debug, Clone)]
+pub enum Timer {
+    /// Runs `command` after `delay` seconds, `repeat` times (forever if 0).
+    Add {
+        delay: u64,
+        repeat: u32,
+        command: String,
+    },
+    List,
+    Cancel(usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum Dcc {
+    /// Offers to chat with a user, or accepts their offer
+    Chat(String),
+    /// Closes a chat, or declines an offer
+    Close(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DoNotDisturb {
+    /// Turns do not disturb on if it's off, or off if it's on
+    Toggle,
+    On,
+    Off,
+    /// Follows the configured quiet hours again
+    Auto,
+}
+
+#[derive(Debug, Clone)]
+pub enum Layout {
+    /// Saves the panes of the main window under a name
+    Save(String),
+    /// Replaces the panes of the main window with a saved layout
+    Load(String),
+    Delete(String),
+    List,
+}
+
+#[derive(Debug, Clone)]
+pub enum Ignores {
+    /// Writes the server's ignore masks and filters to a file
+    Export(PathBuf),
+    /// Merges the ignore masks and filters in a file into the server's
+    Import(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub enum LogLevel {
+    /// Shows what is logged
+    Show,
+    /// Sets the level of a module, or of everything else without one
+    Set(crate::log::Verbosity, Option<String>),
+    /// Goes back to the configured level of a module, or of everything
+    Reset(Option<String>),
+}
+
+/// A message or notice sent to every joined channel.
+#[derive(Debug, Clone)]
+pub struct Broadcast {
+    pub text: String,
+    pub notice: bool,
+    pub all_servers: bool,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("{}", fmt_incorrect_arg_count(*min, *max, *actual))]
@@ -240,6 +713,24 @@ pub enum Error {
     MissingArgs,
     #[error("invalid modestring")]
     InvalidModeString,
+    #[error("invalid subcommand")]
+    InvalidSubcommand,
+    #[error("invalid number: {0}")]
+    InvalidNumber(String),
+    #[error("not in a channel")]
+    NotInChannel,
+    #[error("invalid date: {0}, expected YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("invalid export format: {0}, expected text, json or html")]
+    InvalidExportFormat(String),
+    #[error("invalid import format: {0}, expected weechat, irssi or znc")]
+    InvalidImportFormat(String),
+    #[error("invalid ban type: {0}, expected nick, user, host, domain or account")]
+    InvalidBanMask(String),
+    #[error("invalid log level: {0}, expected off, error, warn, info, debug, trace or reset")]
+    InvalidLogLevel(String),
+    #[error("repeating timers need a delay of at least 1 second")]
+    RepeatWithoutDelay,
 }
 
 fn fmt_incorrect_arg_count(min: usize, max: usize, actual: usize) -> String {
@@ -251,3 +742,40 @@ fn fmt_incorrect_arg_count(min: usize, max: usize, actual: usize) -> String {
         format!("expected {min} to {max} arguments, recevied {actual}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timer() {
+        assert!(matches!(
+            parse_timer("5 -repeat 3 /msg #halloy hi"),
+            Ok(Timer::Add { delay: 5, repeat: 3, command }) if command == "/msg #halloy hi"
+        ));
+        assert!(matches!(
+            parse_timer("0 /away"),
+            Ok(Timer::Add {
+                delay: 0,
+                repeat: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_timer("0 -repeat 0 /msg #halloy hi"),
+            Err(Error::RepeatWithoutDelay)
+        ));
+        assert!(matches!(
+            parse_timer("0 -repeat 2 /msg #halloy hi"),
+            Err(Error::RepeatWithoutDelay)
+        ));
+        assert!(matches!(
+            parse_timer("18446744073709551615 /away"),
+            Err(Error::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            parse_timer("2592000 /away"),
+            Ok(Timer::Add { delay: 2592000, .. })
+        ));
+    }
+}
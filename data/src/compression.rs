@@ -23,6 +23,24 @@ pub fn decompress<T: DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
     serde_json::from_slice(&bytes).map_err(Error::Decode)
 }
 
+/// Compresses already compressed `data` again, as small as possible.
+pub fn recompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![];
+    GzDecoder::new(data)
+        .read_to_end(&mut bytes)
+        .map_err(Error::Decompression)?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&bytes).map_err(Error::Compression)?;
+    encoder.finish().map_err(Error::Compression)
+}
+
+/// Whether `data` is already compressed as small as possible, going by its gzip header.
+pub fn is_recompressed(data: &[u8]) -> bool {
+    // Extra flags are set to 2 for maximum compression
+    data.get(8) == Some(&2)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("compression failed")]
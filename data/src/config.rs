@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
 use std::{str, string};
 
 use tokio_stream::wrappers::ReadDirStream;
@@ -10,12 +12,20 @@ use rand_chacha::ChaCha8Rng;
 use serde::Deserialize;
 use thiserror::Error;
 
+pub use self::away::Away;
 pub use self::buffer::Buffer;
 pub use self::channel::Channel;
+pub use self::check::Problem;
+pub use self::dcc::Dcc;
 pub use self::file_transfer::FileTransfer;
+pub use self::highlights::Highlights;
+pub use self::history::History;
 pub use self::keys::Keyboard;
+pub use self::logs::Logs;
 pub use self::notification::Notifications;
+pub use self::preview::Preview;
 pub use self::proxy::Proxy;
+pub use self::reasons::Reasons;
 pub use self::server::Server;
 pub use self::sidebar::Sidebar;
 
@@ -26,18 +36,45 @@ use crate::environment::config_dir;
 use crate::server::Map as ServerMap;
 use crate::{environment, Theme};
 
+pub mod away;
 pub mod buffer;
 pub mod channel;
+mod check;
+pub mod dcc;
 pub mod file_transfer;
+pub mod highlights;
+pub mod history;
+pub mod ignore;
+mod include;
 pub mod keys;
+pub mod logs;
 pub mod notification;
+pub mod preview;
 pub mod proxy;
+pub mod reasons;
 pub mod server;
 pub mod sidebar;
 
 const CONFIG_TEMPLATE: &str = include_str!("../../config.toml");
 const DEFAULT_THEME_NAME: &str = "ferra";
 
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Settings given on the command line, taking precedence over the file.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    /// Read instead of `config.toml` in the config directory
+    pub path: Option<PathBuf>,
+    /// Used on every server
+    pub nickname: Option<String>,
+}
+
+/// Sets the overrides applied each time the config is loaded. Only the
+/// first call has an effect.
+pub fn set_overrides(overrides: Overrides) {
+    let _ = OVERRIDES.set(overrides);
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Config {
     pub appearance: Appearance,
@@ -50,6 +87,12 @@ pub struct Config {
     pub keyboard: Keyboard,
     pub notifications: Notifications<Sound>,
     pub file_transfer: FileTransfer,
+    pub dcc: Dcc,
+    pub highlights: Highlights,
+    pub history: History,
+    pub preview: Preview,
+    pub away: Away,
+    pub logs: Logs,
     pub tooltips: bool,
 }
 
@@ -74,7 +117,7 @@ impl From<ScaleFactor> for f64 {
     }
 }
 
-#[derive(Debug, Clone, Default, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
 pub struct Font {
     pub family: Option<String>,
     pub size: Option<u8>,
@@ -114,104 +157,175 @@ impl Config {
         dir
     }
 
-    fn path() -> PathBuf {
-        Self::config_dir().join(environment::CONFIG_FILE_NAME)
+    /// The configuration file, `--config` taking precedence.
+    pub fn path() -> PathBuf {
+        OVERRIDES
+            .get()
+            .and_then(|overrides| overrides.path.clone())
+            .unwrap_or_else(|| Self::config_dir().join(environment::CONFIG_FILE_NAME))
     }
 
-    pub async fn load() -> Result<Self, Error> {
-        use tokio::fs;
+    /// When the configuration file, the files it includes, or the imported
+    /// ignore lists merged into it, were last modified.
+    pub fn modified() -> Option<SystemTime> {
+        let modified = std::fs::metadata(Self::path())
+            .and_then(|metadata| metadata.modified())
+            .ok();
 
-        #[derive(Deserialize, Debug)]
-        #[serde(untagged)]
-        pub enum ThemeKeys {
-            Static(String),
-            Dynamic { light: String, dark: String },
+        modified
+            .max(include::modified())
+            .max(ignore::list::modified())
+    }
+
+    /// Settings changed in `updated` which aren't applied while running.
+    pub fn restart_required(&self, updated: &Config) -> Vec<String> {
+        let mut changed = vec![];
+
+        if self.font != updated.font {
+            changed.push("font".to_string());
         }
 
-        impl Default for ThemeKeys {
-            fn default() -> Self {
-                Self::Static(Default::default())
-            }
+        if self.proxy != updated.proxy {
+            changed.push("proxy".to_string());
         }
 
-        impl ThemeKeys {
-            pub fn keys(&self) -> (&str, Option<&str>) {
-                match self {
-                    ThemeKeys::Static(manual) => (manual, None),
-                    ThemeKeys::Dynamic { light, dark } => (light, Some(dark)),
-                }
+        for entry in updated.servers.entries() {
+            let Some(previous) = self.servers.get(&entry.server) else {
+                continue;
+            };
+
+            // Channels are joined and parted as they change
+            let mut previous = previous.clone();
+            previous.channels.clone_from(&entry.config.channels);
+
+            if previous != entry.config {
+                changed.push(format!("servers.{}", entry.server));
             }
         }
 
-        #[derive(Deserialize)]
-        pub struct Configuration {
-            #[serde(default)]
-            pub theme: ThemeKeys,
-            pub servers: ServerMap,
-            pub proxy: Option<Proxy>,
-            #[serde(default)]
-            pub font: Font,
-            #[serde(default)]
-            pub scale_factor: ScaleFactor,
-            #[serde(default)]
-            pub buffer: Buffer,
-            #[serde(default)]
-            pub sidebar: Sidebar,
-            #[serde(default)]
-            pub keyboard: Keyboard,
-            #[serde(default)]
-            pub notifications: Notifications,
-            #[serde(default)]
-            pub file_transfer: FileTransfer,
-            #[serde(default = "default_tooltip")]
-            pub tooltips: bool,
+        changed
+    }
+
+    pub async fn load() -> Result<Self, Error> {
+        let (config, unknown) = Self::check().await?;
+
+        for problem in unknown {
+            log::warn!("{problem}");
         }
 
+        Ok(config)
+    }
+
+    /// Loads the configuration, along with the keys in it which aren't
+    /// known settings.
+    pub async fn check() -> Result<(Self, Vec<Problem>), Error> {
+        let (config, sources) = Self::read().await?;
+
+        let (
+            Configuration {
+                theme,
+                mut servers,
+                font,
+                proxy,
+                scale_factor,
+                buffer,
+                sidebar,
+                keyboard,
+                notifications,
+                mut file_transfer,
+                dcc,
+                highlights,
+                mut history,
+                preview,
+                away,
+                reasons,
+                logs,
+                tooltips,
+            },
+            unknown,
+        ) = check::deserialize(config, &sources)
+            .map_err(|problem| Error::Parse(problem.to_string()))?;
+
+        if let Some(nickname) = OVERRIDES
+            .get()
+            .and_then(|overrides| overrides.nickname.as_deref())
+        {
+            servers.set_nickname(nickname);
+        }
+
+        servers.set_default_reasons(&reasons);
+        servers.apply_imported(&ignore::list::imported().await);
+
+        servers.read_passwords().await?;
+        history.read_passphrase().await?;
+        dcc.apply(&mut file_transfer);
+
+        let loaded_notifications = notifications.load_sounds()?;
+
+        let appearance = Self::load_appearance(theme.keys())
+            .await
+            .unwrap_or_default();
+
+        Ok((
+            Config {
+                appearance,
+                servers,
+                font,
+                proxy,
+                scale_factor,
+                buffer,
+                sidebar,
+                keyboard,
+                notifications: loaded_notifications,
+                file_transfer,
+                dcc,
+                highlights,
+                history,
+                preview,
+                away,
+                logs,
+                tooltips,
+            },
+            unknown,
+        ))
+    }
+
+    /// Reads the configuration file with the files it includes merged in,
+    /// along with the files read.
+    async fn read() -> Result<(toml::Table, Vec<check::Source>), Error> {
+        use tokio::fs;
+
         let path = Self::path();
         if !path.try_exists()? {
             return Err(Error::ConfigMissing {
                 has_yaml_config: has_yaml_config()?,
             });
         }
-        let content = fs::read_to_string(path)
+        let content = fs::read_to_string(&path)
             .await
             .map_err(|e| Error::LoadConfigFile(e.to_string()))?;
 
-        let Configuration {
-            theme,
-            mut servers,
-            font,
-            proxy,
-            scale_factor,
-            buffer,
-            sidebar,
-            keyboard,
-            notifications,
-            file_transfer,
-            tooltips,
-        } = toml::from_str(content.as_ref()).map_err(|e| Error::Parse(e.to_string()))?;
+        let config = toml::from_str(content.as_ref())
+            .map_err(|e| Error::Parse(format!("{}: {e}", path.display())))?;
 
-        servers.read_passwords().await?;
+        // Included files are relative to the one including them
+        let dir = path
+            .parent()
+            .map_or_else(Self::config_dir, Path::to_path_buf);
 
-        let loaded_notifications = notifications.load_sounds()?;
+        let mut sources = vec![check::Source {
+            path: path.clone(),
+            content,
+        }];
 
-        let appearance = Self::load_appearance(theme.keys())
-            .await
-            .unwrap_or_default();
+        let config = include::resolve(config, &dir, &mut sources).await?;
+
+        Ok((config, sources))
+    }
 
-        Ok(Config {
-            appearance,
-            servers,
-            font,
-            proxy,
-            scale_factor,
-            buffer,
-            sidebar,
-            keyboard,
-            notifications: loaded_notifications,
-            file_transfer,
-            tooltips,
-        })
+    /// The configuration as written, with the files it includes merged in.
+    pub async fn merged() -> Result<toml::Table, Error> {
+        Ok(Self::read().await?.0)
     }
 
     async fn load_appearance(theme_keys: (&str, Option<&str>)) -> Result<Appearance, Error> {
@@ -324,6 +438,66 @@ fn has_yaml_config() -> Result<bool, Error> {
     Ok(config_dir().join("config.yaml").try_exists()?)
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ThemeKeys {
+    Static(String),
+    Dynamic { light: String, dark: String },
+}
+
+impl Default for ThemeKeys {
+    fn default() -> Self {
+        Self::Static(Default::default())
+    }
+}
+
+impl ThemeKeys {
+    fn keys(&self) -> (&str, Option<&str>) {
+        match self {
+            ThemeKeys::Static(manual) => (manual, None),
+            ThemeKeys::Dynamic { light, dark } => (light, Some(dark)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    #[serde(default)]
+    theme: ThemeKeys,
+    servers: ServerMap,
+    proxy: Option<Proxy>,
+    #[serde(default)]
+    font: Font,
+    #[serde(default)]
+    scale_factor: ScaleFactor,
+    #[serde(default)]
+    buffer: Buffer,
+    #[serde(default)]
+    sidebar: Sidebar,
+    #[serde(default)]
+    keyboard: Keyboard,
+    #[serde(default)]
+    notifications: Notifications,
+    #[serde(default)]
+    file_transfer: FileTransfer,
+    #[serde(default)]
+    dcc: Dcc,
+    #[serde(default)]
+    highlights: Highlights,
+    #[serde(default)]
+    history: History,
+    #[serde(default)]
+    preview: Preview,
+    #[serde(default)]
+    away: Away,
+    #[serde(default)]
+    reasons: Reasons,
+    #[serde(default)]
+    logs: Logs,
+    #[serde(default = "default_tooltip")]
+    tooltips: bool,
+}
+
 fn default_tooltip() -> bool {
     true
 }
@@ -344,12 +518,18 @@ pub enum Error {
     StringUtf8Error(#[from] string::FromUtf8Error),
     #[error(transparent)]
     LoadSounds(#[from] audio::LoadError),
-    #[error("Only one of password, password_file and password_command can be set.")]
+    #[error(
+        "Only one of password, password_file, password_command and password_prompt can be set."
+    )]
     DuplicatePassword,
-    #[error("Only one of nick_password, nick_password_file and nick_password_command can be set.")]
+    #[error("Only one of nick_password, nick_password_file, nick_password_command and nick_password_prompt can be set.")]
     DuplicateNickPassword,
-    #[error("Exactly one of sasl.plain.password, sasl.plain.password_file or sasl.plain.password_command must be set.")]
+    #[error("Exactly one of sasl.plain.password, sasl.plain.password_file, sasl.plain.password_command or sasl.plain.password_prompt must be set.")]
     DuplicateSaslPassword,
+    #[error("Exactly one of q_auth.password, q_auth.password_file or q_auth.password_command must be set.")]
+    DuplicateQAuthPassword,
+    #[error("Only one of history.passphrase, history.passphrase_file and history.passphrase_command can be set.")]
+    DuplicatePassphrase,
     #[error("Config does not exist")]
     ConfigMissing { has_yaml_config: bool },
 }
@@ -359,3 +539,102 @@ impl From<std::io::Error> for Error {
         Self::Io(error.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    fn unknown(path: &Path, table: toml::Table, content: &str) -> Result<Vec<Problem>, Problem> {
+        let sources = [check::Source {
+            path: path.to_path_buf(),
+            content: content.to_string(),
+        }];
+
+        check::deserialize::<Configuration>(table, &sources).map(|(_, unknown)| unknown)
+    }
+
+    #[test]
+    fn example_config() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../config.toml");
+        let content = fs::read_to_string(&path).unwrap();
+
+        let unknown = unknown(&path, toml::from_str(&content).unwrap(), &content).unwrap();
+
+        assert!(unknown.is_empty(), "{unknown:#?}");
+    }
+
+    #[test]
+    fn documented_settings() {
+        fn markdown(dir: &Path, files: &mut Vec<PathBuf>) {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+
+                if path.is_dir() {
+                    markdown(&path, files);
+                } else if path.extension().is_some_and(|ext| ext == "md") {
+                    files.push(path);
+                }
+            }
+        }
+
+        let mut files = vec![];
+        markdown(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("../book/src/configuration"),
+            &mut files,
+        );
+
+        let mut problems = vec![];
+
+        for path in files {
+            let text = fs::read_to_string(&path).unwrap();
+
+            for block in text.split("```toml\n").skip(1) {
+                let Some((example, _)) = block.split_once("```") else {
+                    continue;
+                };
+
+                // Some examples show alternatives for the same key.
+                let Ok(mut table) = toml::from_str::<toml::Table>(example) else {
+                    continue;
+                };
+
+                // Files written by `/ignores export` aren't configuration.
+                if table.contains_key("soft_ignore") {
+                    continue;
+                }
+
+                table.remove("include");
+
+                // Examples only show the settings they're about.
+                let servers = table
+                    .entry("servers")
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+
+                if let Some(servers) = servers.as_table_mut() {
+                    if servers.is_empty() {
+                        servers.insert("liberachat".into(), toml::Value::Table(toml::Table::new()));
+                    }
+
+                    for (_, server) in servers.iter_mut() {
+                        let Some(server) = server.as_table_mut() else {
+                            continue;
+                        };
+
+                        server.entry("nickname").or_insert("halloy".into());
+                        server.entry("server").or_insert("irc.libera.chat".into());
+                    }
+                }
+
+                // Examples with placeholder values can't be loaded.
+                if let Ok(unknown) = unknown(&path, table, example) {
+                    problems.extend(unknown);
+                }
+            }
+        }
+
+        assert!(problems.is_empty(), "{problems:#?}");
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Away {
+    /// Seconds without input before being marked away on all servers
+    pub idle: Option<u64>,
+    /// Away message set when idle
+    #[serde(default = "default_message")]
+    pub message: String,
+}
+
+impl Default for Away {
+    fn default() -> Self {
+        Self {
+            idle: None,
+            message: default_message(),
+        }
+    }
+}
+
+impl Away {
+    pub fn idle(&self) -> Option<Duration> {
+        self.idle.map(|idle| Duration::from_secs(idle.max(60)))
+    }
+}
+
+fn default_message() -> String {
+    "Away".to_string()
+}
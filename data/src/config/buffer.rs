@@ -1,10 +1,12 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Local, Utc};
 use serde::Deserialize;
 
 use super::Channel;
 use crate::{
-    buffer::{Nickname, StatusMessagePrefix, TextInput, Timestamp},
-    message::source,
+    buffer::{self, Nickname, StatusMessagePrefix, TextInput, Timestamp},
+    message::{self, source},
 };
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -23,6 +25,186 @@ pub struct Buffer {
     pub internal_messages: InternalMessages,
     #[serde(default)]
     pub status_message_prefix: StatusMessagePrefix,
+    #[serde(default)]
+    pub formatting: Formatting,
+    #[serde(default)]
+    pub unread_marker: UnreadMarker,
+    #[serde(default)]
+    pub grouping: Grouping,
+    #[serde(default)]
+    pub date_separators: DateSeparators,
+    #[serde(default)]
+    pub repeats: Repeats,
+}
+
+/// Separators shown where the day changes between messages.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DateSeparators {
+    #[serde(default = "default_bool_true")]
+    pub show: bool,
+    #[serde(default = "default_date_separator_format")]
+    pub format: String,
+}
+
+impl Default for DateSeparators {
+    fn default() -> Self {
+        Self {
+            show: default_bool_true(),
+            format: default_date_separator_format(),
+        }
+    }
+}
+
+impl DateSeparators {
+    pub fn format(&self, date_time: &DateTime<Utc>) -> String {
+        date_time
+            .with_timezone(&Local)
+            .format(&self.format)
+            .to_string()
+    }
+}
+
+fn default_date_separator_format() -> String {
+    "%A, %-d %B".to_string()
+}
+
+/// Consecutive messages from the same user shown under a single nickname and
+/// timestamp.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Grouping {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Longest time in seconds between two messages of a group
+    #[serde(default = "default_grouping_window")]
+    pub window: u64,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: default_grouping_window(),
+        }
+    }
+}
+
+impl Grouping {
+    /// The longest time between two messages of a group, if enabled.
+    pub fn window(&self) -> Option<chrono::TimeDelta> {
+        self.enabled
+            .then(|| chrono::TimeDelta::seconds(self.window as i64))
+    }
+}
+
+fn default_grouping_window() -> u64 {
+    300
+}
+
+/// Messages a user sends again shortly after, collapsed into the first with
+/// a counter.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Repeats {
+    #[serde(default)]
+    pub collapse: bool,
+    /// Longest time in seconds between two repeats
+    #[serde(default = "default_repeats_window")]
+    pub window: u64,
+    /// Seconds notifications from a user are held back for after they
+    /// repeat themselves
+    pub notification_cooldown: Option<u64>,
+}
+
+impl Default for Repeats {
+    fn default() -> Self {
+        Self {
+            collapse: false,
+            window: default_repeats_window(),
+            notification_cooldown: None,
+        }
+    }
+}
+
+impl Repeats {
+    /// The longest time between two collapsed repeats, if enabled.
+    pub fn window(&self) -> Option<chrono::TimeDelta> {
+        self.collapse
+            .then(|| chrono::TimeDelta::seconds(self.window as i64))
+    }
+}
+
+fn default_repeats_window() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct UnreadMarker {
+    #[serde(default)]
+    pub clear: ClearUnreadMarker,
+}
+
+/// When messages above the unread marker count as read while the buffer is
+/// open. They always do once it's closed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClearUnreadMarker {
+    #[default]
+    BufferClosed,
+    FocusLost,
+    MessageSent,
+}
+
+/// How formatting of received messages is shown, overridable per server and
+/// per channel.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Formatting {
+    #[serde(default)]
+    pub render: Render,
+    #[serde(default)]
+    pub servers: HashMap<String, Render>,
+    #[serde(default)]
+    pub channels: HashMap<String, Render>,
+}
+
+impl Formatting {
+    /// A channel's own setting takes precedence over its server's.
+    pub fn render(&self, buffer: &buffer::Upstream) -> Render {
+        let channel = buffer.channel().and_then(|channel| {
+            self.channels
+                .iter()
+                .find_map(|(name, render)| name.eq_ignore_ascii_case(channel).then_some(*render))
+        });
+
+        channel
+            .or_else(|| self.servers.get(buffer.server().as_ref()).copied())
+            .unwrap_or(self.render)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Render {
+    /// Colors and styles, as sent
+    #[default]
+    Full,
+    /// Styles, without colors
+    Monochrome,
+    /// Plain text
+    Strip,
+}
+
+impl Render {
+    /// The formatting to show for `formatting`, if any.
+    pub fn apply(self, formatting: message::Formatting) -> Option<message::Formatting> {
+        match self {
+            Render::Full => Some(formatting),
+            Render::Monochrome => Some(message::Formatting {
+                fg: None,
+                bg: None,
+                ..formatting
+            }),
+            Render::Strip => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -38,12 +220,29 @@ pub struct ServerMessages {
     #[serde(default)]
     pub change_host: ServerMessage,
     #[serde(default)]
+    pub change_nick: ServerMessage,
+    #[serde(default)]
     pub monitored_online: ServerMessage,
     #[serde(default)]
     pub monitored_offline: ServerMessage,
 }
 
 impl ServerMessages {
+    pub fn has_smart(&self) -> bool {
+        [
+            &self.topic,
+            &self.join,
+            &self.part,
+            &self.quit,
+            &self.change_host,
+            &self.change_nick,
+            &self.monitored_online,
+            &self.monitored_offline,
+        ]
+        .iter()
+        .any(|server_message| server_message.smart.is_some())
+    }
+
     pub fn get(&self, server: &source::Server) -> Option<&ServerMessage> {
         match server.kind() {
             source::server::Kind::ReplyTopic => Some(&self.topic),
@@ -51,6 +250,7 @@ impl ServerMessages {
             source::server::Kind::Part => Some(&self.part),
             source::server::Kind::Quit => Some(&self.quit),
             source::server::Kind::ChangeHost => Some(&self.change_host),
+            source::server::Kind::ChangeNick => Some(&self.change_nick),
             source::server::Kind::MonitoredOnline => Some(&self.monitored_online),
             source::server::Kind::MonitoredOffline => Some(&self.monitored_offline),
         }
@@ -167,3 +367,38 @@ impl Buffer {
 fn default_bool_true() -> bool {
     true
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formatting_render() {
+        let formatting = Formatting {
+            render: Render::Monochrome,
+            servers: HashMap::from([("libera".into(), Render::Strip)]),
+            channels: HashMap::from([("#Bots".into(), Render::Full)]),
+        };
+
+        let tests = [
+            (buffer::Upstream::Server("oftc".into()), Render::Monochrome),
+            (buffer::Upstream::Server("libera".into()), Render::Strip),
+            (
+                buffer::Upstream::Channel("libera".into(), "#halloy".into()),
+                Render::Strip,
+            ),
+            (
+                buffer::Upstream::Channel("libera".into(), "#bots".into()),
+                Render::Full,
+            ),
+            (
+                buffer::Upstream::Query("oftc".into(), "nick".into()),
+                Render::Monochrome,
+            ),
+        ];
+
+        for (buffer, expected) in tests {
+            assert_eq!(formatting.render(&buffer), expected, "{buffer:?}");
+        }
+    }
+}
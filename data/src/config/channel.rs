@@ -1,7 +1,13 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
 use crate::buffer::Color;
 use crate::channel::Position;
+use crate::user::Nick;
+use crate::User;
 
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct Channel {
@@ -33,6 +39,12 @@ pub struct Nicklist {
     pub alignment: Alignment,
     #[serde(default = "default_bool_true")]
     pub show_access_levels: bool,
+    #[serde(default)]
+    pub sort: Sort,
+    #[serde(default)]
+    pub group_away: bool,
+    #[serde(default)]
+    pub channels: HashMap<String, NicklistOverride>,
 }
 
 impl Default for Nicklist {
@@ -44,10 +56,70 @@ impl Default for Nicklist {
             width: Default::default(),
             alignment: Default::default(),
             show_access_levels: default_bool_true(),
+            sort: Sort::default(),
+            group_away: false,
+            channels: HashMap::default(),
+        }
+    }
+}
+
+impl Nicklist {
+    /// Orders the channel's users, which come sorted by access level.
+    pub fn sort<'a>(
+        &self,
+        channel: &str,
+        users: &'a [User],
+        last_seen: impl FnOnce() -> HashMap<Nick, DateTime<Utc>>,
+    ) -> Vec<&'a User> {
+        let channel_override = self.channels.get(channel);
+        let sort = channel_override
+            .and_then(|channel| channel.sort)
+            .unwrap_or(self.sort);
+        let group_away = channel_override
+            .and_then(|channel| channel.group_away)
+            .unwrap_or(self.group_away);
+
+        let mut users = users.iter().collect::<Vec<_>>();
+
+        match sort {
+            Sort::AccessLevel => {}
+            Sort::Alphabetical => users.sort_by(|a, b| a.nickname().cmp(&b.nickname())),
+            // Most recent first, and those not seen last
+            Sort::Activity => {
+                let last_seen = last_seen();
+
+                users.sort_by_cached_key(|user| {
+                    Reverse(last_seen.get(&user.nickname().to_owned()).copied())
+                });
+            }
+        }
+
+        if group_away {
+            users.sort_by_key(|user| user.is_away());
         }
+
+        users
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sort {
+    #[default]
+    AccessLevel,
+    Alphabetical,
+    Activity,
+}
+
+/// Overrides the nicklist sorting of a channel.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct NicklistOverride {
+    #[serde(default)]
+    pub sort: Option<Sort>,
+    #[serde(default)]
+    pub group_away: Option<bool>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Alignment {
@@ -80,3 +152,51 @@ fn default_topic_banner_max_lines() -> u16 {
 fn default_bool_true() -> bool {
     true
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sort() {
+        let nicklist: Nicklist = toml::from_str(
+            r##"
+            sort = "alphabetical"
+
+            [channels."#halloy"]
+            sort = "activity"
+            group_away = true
+            "##,
+        )
+        .unwrap();
+
+        let mut users = ["@casper", "+bob", "alice", "dave"]
+            .into_iter()
+            .map(|user| User::try_from(user).unwrap())
+            .collect::<Vec<_>>();
+        users.sort();
+        users[3].update_away(true);
+
+        let now = Utc::now();
+        let last_seen = HashMap::from([
+            (Nick::from("alice"), now),
+            (Nick::from("dave"), now - chrono::Duration::minutes(5)),
+        ]);
+
+        let nicks = |users: Vec<&User>| {
+            users
+                .into_iter()
+                .map(|user| user.nickname().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            nicks(nicklist.sort("#rust", &users, || last_seen.clone())),
+            ["alice", "bob", "casper", "dave"]
+        );
+        assert_eq!(
+            nicks(nicklist.sort("#halloy", &users, || last_seen.clone())),
+            ["alice", "casper", "bob", "dave"]
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! Deserializes the configuration while keeping track of where problems are,
+//! so they can be reported at the line of the file they come from.
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::Deserialize;
+use toml::{Spanned, Table, Value};
+
+/// A file the configuration was read from, the main one first and included
+/// ones in order.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// A problem with a key, at the line of the file which set it if known.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub key: String,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Location { path, line, column }) = &self.location {
+            write!(f, "{}:{line}:{column}: ", path.display())?;
+        }
+
+        if self.key.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "`{}` {}", self.key, self.message)
+        }
+    }
+}
+
+/// Deserializes `table`, along with the keys which went unused.
+pub fn deserialize<T: DeserializeOwned>(
+    table: Table,
+    sources: &[Source],
+) -> Result<(T, Vec<Problem>), Problem> {
+    let unused = RefCell::new(vec![]);
+
+    let tracked = Tracked {
+        value: Value::Table(table),
+        path: vec![],
+        unused: &unused,
+    };
+
+    match T::deserialize(tracked) {
+        Ok(value) => {
+            let unknown = unused
+                .into_inner()
+                .into_iter()
+                .map(|path| problem(sources, path, "is not a known setting".into()))
+                .collect();
+
+            Ok((value, unknown))
+        }
+        Err(error) => Err(problem(
+            sources,
+            error.path.unwrap_or_default(),
+            error.message,
+        )),
+    }
+}
+
+fn problem(sources: &[Source], path: Vec<String>, message: String) -> Problem {
+    Problem {
+        key: path.join("."),
+        location: locate(sources, &path),
+        message,
+    }
+}
+
+/// Where `path` is set. The main file takes precedence over included ones,
+/// and later includes over earlier ones.
+fn locate(sources: &[Source], path: &[String]) -> Option<Location> {
+    let (main, includes) = sources.split_first()?;
+
+    std::iter::once(main)
+        .chain(includes.iter().rev())
+        .find_map(|source| {
+            let root = toml::from_str::<Node>(&source.content).ok()?;
+            let offset = find(&root, path)?;
+            let before = &source.content[..offset];
+
+            Some(Location {
+                path: source.path.clone(),
+                line: before.matches('\n').count() + 1,
+                column: before
+                    .rsplit('\n')
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .count()
+                    + 1,
+            })
+        })
+}
+
+/// A value of the file, with the spans of its keys. Values themselves
+/// don't always have one, as with tables only named in a header.
+enum Node {
+    Table(Vec<(Spanned<String>, Node)>),
+    Array(Vec<Node>),
+    Leaf,
+}
+
+/// Offset of the last key of `path` found in `node`.
+fn find(node: &Node, path: &[String]) -> Option<usize> {
+    let (key, rest) = path.split_first()?;
+
+    match node {
+        Node::Table(entries) => {
+            let (name, value) = entries.iter().find(|(name, _)| name.get_ref() == key)?;
+
+            Some(find(value, rest).unwrap_or(name.span().start))
+        }
+        Node::Array(values) => find(values.get(key.parse::<usize>().ok()?)?, rest),
+        Node::Leaf => None,
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("any value")
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+                let mut entries = vec![];
+
+                while let Some(key) = map.next_key()? {
+                    entries.push((key, map.next_value()?));
+                }
+
+                Ok(Node::Table(entries))
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+                let mut values = vec![];
+
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+
+                Ok(Node::Array(values))
+            }
+
+            fn visit_bool<E>(self, _: bool) -> Result<Node, E> {
+                Ok(Node::Leaf)
+            }
+
+            fn visit_i64<E>(self, _: i64) -> Result<Node, E> {
+                Ok(Node::Leaf)
+            }
+
+            fn visit_u64<E>(self, _: u64) -> Result<Node, E> {
+                Ok(Node::Leaf)
+            }
+
+            fn visit_f64<E>(self, _: f64) -> Result<Node, E> {
+                Ok(Node::Leaf)
+            }
+
+            fn visit_str<E>(self, _: &str) -> Result<Node, E> {
+                Ok(Node::Leaf)
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+#[derive(Debug)]
+struct Error {
+    /// Key path of the value with the problem, filled in while unwinding
+    path: Option<Vec<String>>,
+    message: String,
+}
+
+impl Error {
+    fn at(mut self, path: &[String]) -> Self {
+        self.path.get_or_insert_with(|| path.to_vec());
+        self
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error {
+            path: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Deserializes a value, recording the keys which are skipped over as
+/// unknown and the path of those which fail.
+struct Tracked<'a> {
+    value: Value,
+    path: Vec<String>,
+    unused: &'a RefCell<Vec<Vec<String>>>,
+}
+
+impl<'a> Tracked<'a> {
+    fn child(&self, key: String, value: Value) -> Self {
+        let mut path = self.path.clone();
+        path.push(key);
+
+        Tracked {
+            value,
+            path,
+            unused: self.unused,
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Tracked<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+
+        let result = match self.value {
+            Value::String(value) => visitor.visit_string(value),
+            Value::Integer(value) => visitor.visit_i64(value),
+            Value::Float(value) => visitor.visit_f64(value),
+            Value::Boolean(value) => visitor.visit_bool(value),
+            Value::Datetime(_) => self
+                .value
+                .deserialize_any(visitor)
+                .map_err(de::Error::custom),
+            Value::Array(ref values) => visitor.visit_seq(Seq {
+                values: values
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| self.child(index.to_string(), value.clone()))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            }),
+            Value::Table(ref table) => visitor.visit_map(Map {
+                entries: table
+                    .iter()
+                    .map(|(key, value)| (key.clone(), self.child(key.clone(), value.clone())))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+                pending: None,
+            }),
+        };
+
+        result.map_err(|error| error.at(&path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+
+        visitor.visit_some(self).map_err(|error| error.at(&path))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+
+        visitor
+            .visit_newtype_struct(self)
+            .map_err(|error| error.at(&path))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let path = self.path.clone();
+
+        let result = match self.value {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            // Variants with data are a table with a single key
+            Value::Table(ref table) if table.len() == 1 => {
+                let (variant, value) = table.iter().next().expect("single entry");
+
+                visitor.visit_enum(Enum {
+                    variant: variant.clone(),
+                    value: self.child(variant.clone(), value.clone()),
+                })
+            }
+            _ => Err(de::Error::custom(
+                "expected a string or a table with a single key",
+            )),
+        };
+
+        result.map_err(|error| error.at(&path))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.unused.borrow_mut().push(self.path);
+
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct Seq<'a> {
+    values: std::vec::IntoIter<Tracked<'a>>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for Seq<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.values
+            .next()
+            .map(|value| seed.deserialize(value))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct Map<'a> {
+    entries: std::vec::IntoIter<(String, Tracked<'a>)>,
+    pending: Option<Tracked<'a>>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Map<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        self.pending = Some(value);
+
+        seed.deserialize(Key(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .pending
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before its key"))?;
+
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// A table key, which unlike a plain string can be a newtype, as with
+/// server names.
+struct Key(String);
+
+impl<'de> de::Deserializer<'de> for Key {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct Enum<'a> {
+    variant: String,
+    value: Tracked<'a>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for Enum<'a> {
+    type Error = Error;
+    type Variant = Tracked<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Tracked<'a>), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+
+        Ok((variant, self.value))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for Tracked<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Err(de::Error::custom("expected a string"))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        servers: HashMap<String, Server>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        nickname: String,
+        #[serde(default)]
+        port: u16,
+    }
+
+    fn check(content: &str) -> Result<(Config, Vec<Problem>), Problem> {
+        let sources = [Source {
+            path: PathBuf::from("config.toml"),
+            content: content.to_string(),
+        }];
+
+        deserialize(toml::from_str(content).unwrap(), &sources)
+    }
+
+    #[test]
+    fn located_problems() {
+        let content = "[servers.libera]\nnickname = \"halloy\"\nport = \"6697\"\n";
+        let error = check(content).unwrap_err();
+
+        assert_eq!(error.key, "servers.libera.port");
+        assert_eq!(
+            error.to_string(),
+            "config.toml:3:1: `servers.libera.port` invalid type: string \"6697\", expected u16"
+        );
+
+        let content = "[servers.libera]\nnickname = \"halloy\"\n  nick_pasword = \"secret\"\n";
+        let (_, unknown) = check(content).unwrap();
+
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(
+            unknown[0].to_string(),
+            "config.toml:3:3: `servers.libera.nick_pasword` is not a known setting"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::config::file_transfer::{FileTransfer, Server};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Dcc {
+    /// Address advertised to the remote user in outgoing offers, discovered if not set
+    pub public_address: Option<IpAddr>,
+    /// Ports to listen on for the remote user to connect, such as "5000-5010"
+    #[serde(default, deserialize_with = "deserialize_port_range")]
+    pub port_range: Option<RangeInclusive<u16>>,
+}
+
+impl Dcc {
+    /// Applies these settings over `[file_transfer.server]`, setting it up to
+    /// listen on all interfaces if only `[dcc]` is configured.
+    pub fn apply(&self, file_transfer: &mut FileTransfer) {
+        match &mut file_transfer.server {
+            Some(server) => {
+                if let Some(public_address) = self.public_address {
+                    server.public_address = Some(public_address);
+                }
+                if let Some(port_range) = &self.port_range {
+                    server.bind_ports = port_range.clone();
+                }
+            }
+            None => {
+                if let Some(port_range) = &self.port_range {
+                    file_transfer.server = Some(Server {
+                        public_address: self.public_address,
+                        bind_address: match self.public_address {
+                            Some(IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                            _ => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                        },
+                        bind_ports: port_range.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn deserialize_port_range<'de, D>(deserializer: D) -> Result<Option<RangeInclusive<u16>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(range) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    parse_port_range(&range).map(Some).ok_or_else(|| {
+        serde::de::Error::custom(format!(
+            "invalid `port_range` \"{range}\", expected a port or ports such as \"5000-5010\""
+        ))
+    })
+}
+
+/// A single port, or the first and last ports separated by a dash.
+fn parse_port_range(range: &str) -> Option<RangeInclusive<u16>> {
+    let (first, last) = range.split_once('-').unwrap_or((range, range));
+
+    let first = first.trim().parse::<u16>().ok().filter(|port| *port != 0)?;
+    let last = last
+        .trim()
+        .parse::<u16>()
+        .ok()
+        .filter(|port| *port >= first)?;
+
+    Some(first..=last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn port_range() {
+        assert_eq!(parse_port_range("5000-5010"), Some(5000..=5010));
+        assert_eq!(parse_port_range("5000 - 5010"), Some(5000..=5010));
+        assert_eq!(parse_port_range("5000"), Some(5000..=5000));
+        assert_eq!(parse_port_range("5010-5000"), None);
+        assert_eq!(parse_port_range("0-10"), None);
+        assert_eq!(parse_port_range("5000-70000"), None);
+    }
+}
@@ -13,7 +13,13 @@ pub struct FileTransfer {
     /// Time in seconds to wait before timing out a transfer waiting to be accepted.
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    /// If true, compare received files against the checksum given by the sender
+    /// or a `.sha256` file saved next to them
+    #[serde(default = "default_verify_checksum")]
+    pub verify_checksum: bool,
     pub server: Option<Server>,
+    #[serde(default)]
+    pub rate_limit: RateLimit,
 }
 
 impl Default for FileTransfer {
@@ -22,7 +28,9 @@ impl Default for FileTransfer {
             save_directory: default_save_directory(),
             passive: default_passive(),
             timeout: default_timeout(),
+            verify_checksum: default_verify_checksum(),
             server: None,
+            rate_limit: RateLimit::default(),
         }
     }
 }
@@ -35,14 +43,30 @@ fn default_timeout() -> u64 {
     60 * 5
 }
 
+fn default_verify_checksum() -> bool {
+    true
+}
+
 fn default_save_directory() -> PathBuf {
     dirs_next::download_dir().unwrap_or(PathBuf::from("/tmp/"))
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct RateLimit {
+    /// Max rate of all files being sent, in KiB/s
+    pub upload: Option<u64>,
+    /// Max rate of all files being received, in KiB/s
+    pub download: Option<u64>,
+    /// Max rate of each file being sent, in KiB/s
+    pub upload_per_transfer: Option<u64>,
+    /// Max rate of each file being received, in KiB/s
+    pub download_per_transfer: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Server {
-    /// Address advertised to the remote user to connect to
-    pub public_address: IpAddr,
+    /// Address advertised to the remote user to connect to, discovered if not set
+    pub public_address: Option<IpAddr>,
     /// Address to bind to when accepting connections
     pub bind_address: IpAddr,
     /// Port range used to bind with
@@ -56,7 +80,7 @@ impl<'de> Deserialize<'de> for Server {
     {
         #[derive(Deserialize)]
         struct Data {
-            public_address: IpAddr,
+            public_address: Option<IpAddr>,
             bind_address: IpAddr,
             bind_port_first: NonZeroU16,
             bind_port_last: NonZeroU16,
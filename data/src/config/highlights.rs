@@ -0,0 +1,110 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer};
+
+/// Words and patterns highlighting messages, besides our nickname.
+#[derive(Debug, Clone, Default)]
+pub struct Highlights {
+    matches: Vec<Regex>,
+}
+
+impl Highlights {
+    pub fn matches(&self, text: &str) -> bool {
+        self.matches.iter().any(|regex| regex.is_match(text))
+    }
+}
+
+impl<'de> Deserialize<'de> for Highlights {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Data {
+            #[serde(default)]
+            matches: Vec<Match>,
+        }
+
+        #[derive(Deserialize)]
+        struct Match {
+            #[serde(default)]
+            words: Vec<String>,
+            regex: Option<String>,
+            #[serde(default)]
+            case_sensitive: bool,
+            #[serde(default = "default_whole_word")]
+            whole_word: bool,
+        }
+
+        let Data { matches } = Data::deserialize(deserializer)?;
+
+        let mut regexes = vec![];
+
+        for Match {
+            words,
+            regex,
+            case_sensitive,
+            whole_word,
+        } in matches
+        {
+            let words = (!words.is_empty()).then(|| {
+                words
+                    .iter()
+                    .map(|word| regex::escape(word))
+                    .collect::<Vec<_>>()
+                    .join("|")
+            });
+
+            for pattern in words.into_iter().chain(regex) {
+                // Rather than `\b`, so words can start or end with symbols
+                let pattern = if whole_word {
+                    format!(r"(?:^|\W)(?:{pattern})(?:\W|$)")
+                } else {
+                    pattern
+                };
+
+                let regex = RegexBuilder::new(&pattern)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|error| {
+                        serde::de::Error::custom(format!("invalid highlight regex: {error}"))
+                    })?;
+
+                regexes.push(regex);
+            }
+        }
+
+        Ok(Highlights { matches: regexes })
+    }
+}
+
+fn default_whole_word() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches() {
+        let highlights: Highlights = toml::from_str(
+            r#"
+            [[matches]]
+            words = ["halloy", "c++"]
+
+            [[matches]]
+            regex = "deploy(ed|ing)?"
+            case_sensitive = true
+            whole_word = false
+            "#,
+        )
+        .unwrap();
+
+        assert!(highlights.matches("Halloy is out"));
+        assert!(highlights.matches("written in c++ or rust"));
+        assert!(!highlights.matches("halloyed"));
+        assert!(highlights.matches("redeployed it"));
+        assert!(!highlights.matches("Deploy it"));
+        assert!(!Highlights::default().matches("halloy"));
+    }
+}
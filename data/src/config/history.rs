@@ -0,0 +1,38 @@
+use serde::Deserialize;
+use tokio::fs;
+
+use crate::config::Error;
+use crate::server::read_from_command;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct History {
+    /// Passphrase to encrypt history saved to disk with
+    pub passphrase: Option<String>,
+    /// File to read the passphrase from
+    pub passphrase_file: Option<String>,
+    /// Command whose output is the passphrase
+    pub passphrase_command: Option<String>,
+    /// Max size of history saved to disk, in megabytes
+    pub max_size: Option<u64>,
+}
+
+impl History {
+    pub async fn read_passphrase(&mut self) -> Result<(), Error> {
+        if let Some(passphrase_file) = &self.passphrase_file {
+            if self.passphrase.is_some() || self.passphrase_command.is_some() {
+                return Err(Error::DuplicatePassphrase);
+            }
+            // Without the trailing newline most editors add
+            let passphrase = fs::read_to_string(passphrase_file).await?;
+            self.passphrase = Some(passphrase.trim_end_matches(['\r', '\n']).to_string());
+        }
+        if let Some(passphrase_command) = &self.passphrase_command {
+            if self.passphrase.is_some() {
+                return Err(Error::DuplicatePassphrase);
+            }
+            self.passphrase = Some(read_from_command(passphrase_command).await?);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Users whose messages are dropped, by hostmask or services account.
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+
+use super::include::matches;
+use crate::isupport::CaseMap;
+use crate::User;
+
+pub mod list;
+
+/// A `nick!user@host` mask, or a services account as `$a:account`. Both
+/// may contain `*` and `?` wildcards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mask {
+    Hostmask(String),
+    Account(String),
+}
+
+impl Mask {
+    pub fn parse(mask: &str) -> Self {
+        if let Some(account) = mask.strip_prefix("$a:") {
+            return Mask::Account(account.to_string());
+        }
+
+        // Fill in the parts left out, so "nick" and "*@host" are full masks
+        let (nick, rest) = mask.split_once('!').unwrap_or(match mask.split_once('@') {
+            Some(_) => ("*", mask),
+            None => (mask, "*"),
+        });
+        let (user, host) = rest.split_once('@').unwrap_or((rest, "*"));
+
+        Mask::Hostmask(format!("{nick}!{user}@{host}"))
+    }
+
+    /// The `nick!user@host` mask, unless it's an account.
+    pub fn hostmask(&self) -> Option<&str> {
+        match self {
+            Mask::Hostmask(mask) => Some(mask),
+            Mask::Account(_) => None,
+        }
+    }
+
+    /// Whether `user`, logged in as `account` if known, is masked.
+    pub fn matches(&self, user: &User, account: Option<&str>, casemapping: CaseMap) -> bool {
+        match self {
+            Mask::Hostmask(mask) => {
+                let hostmask = format!(
+                    "{}!{}@{}",
+                    user.nickname(),
+                    user.username().unwrap_or("*"),
+                    user.hostname().unwrap_or("*"),
+                );
+
+                matches(
+                    &casemapping.normalize(mask),
+                    &casemapping.normalize(&hostmask),
+                )
+            }
+            Mask::Account(mask) => account.is_some_and(|account| {
+                account != "*" && matches(&mask.to_ascii_lowercase(), &account.to_ascii_lowercase())
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mask::Hostmask(mask) => write!(f, "{mask}"),
+            Mask::Account(account) => write!(f, "$a:{account}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Mask {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Mask::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks() {
+        let user = User::try_from("Troll[1]!~troll@spam.example.org").unwrap();
+        let tests = [
+            ("troll{1}", true),
+            ("Troll*", true),
+            ("Troll", false),
+            ("*!*@*.example.org", true),
+            ("*@spam.example.org", true),
+            ("~troll@*", true),
+            ("*!*@*.example.net", false),
+            ("Troll[?]!~troll", true),
+        ];
+
+        for (mask, expected) in tests {
+            assert_eq!(
+                Mask::parse(mask).matches(&user, None, CaseMap::RFC1459),
+                expected,
+                "{mask}"
+            );
+        }
+    }
+
+    #[test]
+    fn accounts() {
+        let user = User::try_from("renamed!~troll@other.example.org").unwrap();
+        let mask = Mask::parse("$a:troll*");
+
+        assert!(mask.matches(&user, Some("Trolling"), CaseMap::RFC1459));
+        assert!(!mask.matches(&user, Some("*"), CaseMap::RFC1459));
+        assert!(!mask.matches(&user, None, CaseMap::RFC1459));
+    }
+}
@@ -0,0 +1,254 @@
+//! Ignore masks and filters shared as files, e.g. by channel operators with
+//! their members. Imported lists are kept per server and merged into the
+//! configuration when it's loaded.
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::Mask;
+use crate::{config, environment, Server};
+
+/// The masks and filters of a server, as written to a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct List {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub soft_ignore: Vec<String>,
+    /// Regular expressions hiding messages, by channel
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub filters: BTreeMap<String, Vec<String>>,
+}
+
+/// How the entries of an imported list were merged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Merged {
+    pub added: usize,
+    /// Entries which were already in the list
+    pub duplicates: usize,
+    /// Masks moved between `ignore` and `soft_ignore`
+    pub conflicts: usize,
+}
+
+impl List {
+    /// The masks and filters of `config`.
+    pub fn of(config: &config::Server) -> Self {
+        Self {
+            ignore: config.ignore.iter().map(ToString::to_string).collect(),
+            soft_ignore: config.soft_ignore.iter().map(ToString::to_string).collect(),
+            filters: config
+                .channels
+                .sections()
+                .filter(|(_, settings)| !settings.filters.is_empty())
+                .map(|(channel, settings)| {
+                    (
+                        channel.to_string(),
+                        settings.filters.patterns().map(String::from).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Merges `other` into the list. A mask in both lists is kept in the
+    /// one `other` puts it in.
+    pub fn merge(&mut self, other: List) -> Merged {
+        let mut merged = Merged::default();
+
+        merge_masks(
+            other.ignore,
+            &mut self.ignore,
+            &mut self.soft_ignore,
+            &mut merged,
+        );
+        merge_masks(
+            other.soft_ignore,
+            &mut self.soft_ignore,
+            &mut self.ignore,
+            &mut merged,
+        );
+
+        for (channel, patterns) in other.filters {
+            let existing = match self
+                .filters
+                .keys()
+                .find(|existing| existing.eq_ignore_ascii_case(&channel))
+                .cloned()
+            {
+                Some(existing) => self.filters.entry(existing).or_default(),
+                None => self.filters.entry(channel).or_default(),
+            };
+
+            for pattern in patterns {
+                if existing.contains(&pattern) {
+                    merged.duplicates += 1;
+                } else {
+                    existing.push(pattern);
+                    merged.added += 1;
+                }
+            }
+        }
+
+        merged
+    }
+
+    /// Adds the masks and filters to `config`, unless it has them already.
+    /// Masks in either of its lists are kept where they are.
+    pub fn apply(&self, config: &mut config::Server) {
+        for (masks, into) in [
+            (&self.ignore, &mut config.ignore),
+            (&self.soft_ignore, &mut config.soft_ignore),
+        ] {
+            for mask in masks.iter().map(|mask| Mask::parse(mask)) {
+                if !into.contains(&mask) {
+                    into.push(mask);
+                }
+            }
+        }
+
+        let ignored = config.ignore.clone();
+        config.soft_ignore.retain(|mask| !ignored.contains(mask));
+
+        for (channel, patterns) in &self.filters {
+            let regexes = patterns
+                .iter()
+                .filter_map(|pattern| Regex::new(pattern).ok())
+                .collect();
+
+            config.channels.add_filters(channel, regexes);
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        for pattern in self.filters.values().flatten() {
+            if let Err(error) = Regex::new(pattern) {
+                return Err(Error::InvalidFilter(pattern.clone(), error.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds `masks` to `into`, taking them out of `from`.
+fn merge_masks(
+    masks: Vec<String>,
+    into: &mut Vec<String>,
+    from: &mut Vec<String>,
+    merged: &mut Merged,
+) {
+    for mask in masks {
+        let parsed = Mask::parse(&mask);
+        let is_mask = |existing: &String| Mask::parse(existing) == parsed;
+
+        if into.iter().any(is_mask) {
+            merged.duplicates += 1;
+        } else if from.iter().any(is_mask) {
+            from.retain(|existing| !is_mask(existing));
+            into.push(mask);
+            merged.conflicts += 1;
+        } else {
+            into.push(mask);
+            merged.added += 1;
+        }
+    }
+}
+
+/// Writes `list` to `path`.
+pub async fn export(list: List, path: PathBuf) -> Result<PathBuf, Error> {
+    tokio::fs::write(&path, toml::to_string(&list)?).await?;
+
+    Ok(path)
+}
+
+/// Merges the list at `path` into the ones imported for `server`.
+pub async fn import(server: Server, path: PathBuf) -> Result<Merged, Error> {
+    let list: List = toml::from_str(&tokio::fs::read_to_string(&path).await?)?;
+    list.validate()?;
+
+    let mut imported = read(&imported_path()).await?;
+    let merged = imported.entry(server.to_string()).or_default().merge(list);
+
+    tokio::fs::create_dir_all(environment::data_dir()).await?;
+    tokio::fs::write(imported_path(), toml::to_string(&imported)?).await?;
+
+    Ok(merged)
+}
+
+/// The lists imported for each server, or none if they couldn't be read.
+pub async fn imported() -> BTreeMap<String, List> {
+    match read(&imported_path()).await {
+        Ok(imported) => imported,
+        Err(error) => {
+            log::warn!("failed to load imported ignore lists: {error}");
+            BTreeMap::new()
+        }
+    }
+}
+
+/// When a list was last imported, so the configuration is loaded again.
+pub fn modified() -> Option<SystemTime> {
+    std::fs::metadata(imported_path())
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+async fn read(path: &Path) -> Result<BTreeMap<String, List>, Error> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(toml::from_str(&content)?),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn imported_path() -> PathBuf {
+    environment::data_dir().join("imported-ignores.toml")
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] toml::de::Error),
+    #[error(transparent)]
+    Serialize(#[from] toml::ser::Error),
+    #[error("invalid filter \"{0}\": {1}")]
+    InvalidFilter(String, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged() {
+        let mut list = List {
+            ignore: vec!["troll".into(), "spam!*@*".into()],
+            soft_ignore: vec!["*bot*".into()],
+            filters: BTreeMap::from([("#halloy".into(), vec!["^!\\w+".into()])]),
+        };
+
+        let merged = list.merge(List {
+            ignore: vec!["troll!*@*".into(), "*bot*".into()],
+            soft_ignore: vec!["spam".into(), "chatty".into()],
+            filters: BTreeMap::from([("#Halloy".into(), vec!["^!\\w+".into(), "buy now".into()])]),
+        });
+
+        assert_eq!(
+            merged,
+            Merged {
+                added: 2,
+                duplicates: 2,
+                conflicts: 2,
+            }
+        );
+        assert_eq!(list.ignore, vec!["troll", "*bot*"]);
+        assert_eq!(list.soft_ignore, vec!["spam", "chatty"]);
+        assert_eq!(list.filters["#halloy"], vec!["^!\\w+", "buy now"]);
+    }
+}
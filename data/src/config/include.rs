@@ -0,0 +1,205 @@
+//! Configuration split across files with `include = ["servers/*.toml"]`.
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use tokio::fs;
+use toml::{Table, Value};
+
+use super::check::Source;
+use super::Error;
+
+/// Files included the last time the configuration was read, along with the
+/// directories searched for wildcard includes
+static WATCHED: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+
+/// When a file included by the configuration was last modified, or a file
+/// was added to or removed from a directory searched for includes.
+pub fn modified() -> Option<SystemTime> {
+    WATCHED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .filter_map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .max()
+}
+
+fn watch(path: &Path) {
+    WATCHED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(path.to_path_buf());
+}
+
+/// Merges the files included by `config` under it, settings from `config`
+/// taking precedence over included ones. Paths are relative to `dir`, and
+/// the files read are added to `sources`.
+pub async fn resolve(
+    mut config: Table,
+    dir: &Path,
+    sources: &mut Vec<Source>,
+) -> Result<Table, Error> {
+    let patterns = match config.remove("include") {
+        None => vec![],
+        Some(Value::String(pattern)) => vec![pattern],
+        Some(Value::Array(patterns)) => patterns
+            .into_iter()
+            .map(|pattern| match pattern {
+                Value::String(pattern) => Ok(pattern),
+                _ => Err(Error::Parse("include must be a list of paths".into())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err(Error::Parse("include must be a list of paths".into())),
+    };
+
+    let mut merged = Table::new();
+
+    WATCHED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+
+    for pattern in patterns {
+        for path in paths(dir, &pattern).await? {
+            // Watched before reading, so fixing a file that fails reloads it
+            watch(&path);
+
+            let content = fs::read_to_string(&path)
+                .await
+                .map_err(|e| Error::LoadConfigFile(format!("{}: {e}", path.display())))?;
+
+            let mut included: Table = toml::from_str(&content)
+                .map_err(|e| Error::Parse(format!("{}: {e}", path.display())))?;
+
+            // Included files can't include others
+            included.remove("include");
+
+            merge(&mut merged, included);
+
+            sources.push(Source { path, content });
+        }
+    }
+
+    merge(&mut merged, config);
+
+    Ok(merged)
+}
+
+/// Files matching `pattern`, sorted by name. Only the file name may hold
+/// the `*` and `?` wildcards.
+async fn paths(dir: &Path, pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    let path = dir.join(pattern);
+
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Err(Error::Parse(format!("invalid include: {pattern}")));
+    };
+
+    if !file_name.contains(['*', '?']) {
+        return Ok(vec![path]);
+    }
+
+    let parent = path.parent().unwrap_or(dir);
+
+    watch(parent);
+
+    let mut entries = fs::read_dir(parent)
+        .await
+        .map_err(|e| Error::LoadConfigFile(format!("{}: {e}", parent.display())))?;
+
+    let mut paths = vec![];
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+
+        if name.to_str().is_some_and(|name| matches(file_name, name)) {
+            paths.push(entry.path());
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+/// and `?` any single one.
+pub(super) fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.chars().next() {
+        None => name.is_empty(),
+        Some('*') => {
+            let rest = &pattern[1..];
+
+            name.char_indices()
+                .map(|(index, _)| index)
+                .chain(Some(name.len()))
+                .any(|index| matches(rest, &name[index..]))
+        }
+        Some(c) => name.chars().next().is_some_and(|first| {
+            (c == '?' || c == first) && matches(&pattern[c.len_utf8()..], &name[first.len_utf8()..])
+        }),
+    }
+}
+
+/// Merges `other` into `base`, recursing into tables present in both.
+fn merge(base: &mut Table, other: Table) {
+    for (key, value) in other {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(other)) => merge(base, other),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.toml", "libera.toml"));
+        assert!(matches("*.toml", ".toml"));
+        assert!(matches("net?.toml", "net1.toml"));
+        assert!(!matches("*.toml", "libera.yaml"));
+        assert!(!matches("net?.toml", "net.toml"));
+    }
+
+    #[test]
+    fn merged_tables() {
+        let mut base: Table = toml::from_str(
+            r#"
+            [servers.libera]
+            nickname = "a"
+            server = "irc.libera.chat"
+
+            [buffer]
+            timestamp = { format = "%R" }
+            "#,
+        )
+        .unwrap();
+
+        let other: Table = toml::from_str(
+            r#"
+            [servers.libera]
+            nickname = "b"
+
+            [servers.oftc]
+            nickname = "c"
+            "#,
+        )
+        .unwrap();
+
+        merge(&mut base, other);
+
+        let libera = base["servers"]["libera"].as_table().unwrap();
+        assert_eq!(libera["nickname"].as_str(), Some("b"));
+        assert_eq!(libera["server"].as_str(), Some("irc.libera.chat"));
+        assert!(base["servers"].get("oftc").is_some());
+        assert!(base.contains_key("buffer"));
+    }
+}
@@ -42,6 +42,26 @@ pub struct Keyboard {
     pub theme_editor: KeyBind,
     #[serde(default = "KeyBind::highlight")]
     pub highlight: KeyBind,
+    #[serde(default = "KeyBind::search")]
+    pub search: KeyBind,
+    #[serde(default = "KeyBind::format_bold")]
+    pub format_bold: KeyBind,
+    #[serde(default = "KeyBind::format_italics")]
+    pub format_italics: KeyBind,
+    #[serde(default = "KeyBind::format_underline")]
+    pub format_underline: KeyBind,
+    #[serde(default = "KeyBind::format_color")]
+    pub format_color: KeyBind,
+    #[serde(default = "KeyBind::format_reset")]
+    pub format_reset: KeyBind,
+    #[serde(default = "KeyBind::zoom_in")]
+    pub zoom_in: KeyBind,
+    #[serde(default = "KeyBind::zoom_out")]
+    pub zoom_out: KeyBind,
+    #[serde(default = "KeyBind::zoom_reset")]
+    pub zoom_reset: KeyBind,
+    #[serde(default)]
+    pub vim_mode: bool,
 }
 
 impl Default for Keyboard {
@@ -66,6 +86,16 @@ impl Default for Keyboard {
             logs: KeyBind::logs(),
             theme_editor: KeyBind::theme_editor(),
             highlight: KeyBind::highlight(),
+            search: KeyBind::search(),
+            format_bold: KeyBind::format_bold(),
+            format_italics: KeyBind::format_italics(),
+            format_underline: KeyBind::format_underline(),
+            format_color: KeyBind::format_color(),
+            format_reset: KeyBind::format_reset(),
+            zoom_in: KeyBind::zoom_in(),
+            zoom_out: KeyBind::zoom_out(),
+            zoom_reset: KeyBind::zoom_reset(),
+            vim_mode: false,
         }
     }
 }
@@ -94,6 +124,15 @@ impl Keyboard {
             shortcut(self.logs.clone(), Logs),
             shortcut(self.theme_editor.clone(), ThemeEditor),
             shortcut(self.highlight.clone(), Highlight),
+            shortcut(self.search.clone(), Search),
+            shortcut(self.format_bold.clone(), FormatBold),
+            shortcut(self.format_italics.clone(), FormatItalics),
+            shortcut(self.format_underline.clone(), FormatUnderline),
+            shortcut(self.format_color.clone(), FormatColor),
+            shortcut(self.format_reset.clone(), FormatReset),
+            shortcut(self.zoom_in.clone(), ZoomIn),
+            shortcut(self.zoom_out.clone(), ZoomOut),
+            shortcut(self.zoom_reset.clone(), ZoomReset),
         ]
     }
 }
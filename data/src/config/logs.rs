@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::log::{Filter, Verbosity};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Logs {
+    /// What Halloy logs, unless `RUST_LOG` is set
+    #[serde(default)]
+    pub level: Verbosity,
+    /// Modules logging more or less than the rest, e.g. `data::client`
+    #[serde(default)]
+    pub modules: BTreeMap<String, Verbosity>,
+}
+
+impl Logs {
+    pub fn filter(&self) -> Filter {
+        Filter {
+            verbosity: self.level,
+            modules: self.modules.clone(),
+        }
+    }
+}
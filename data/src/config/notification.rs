@@ -1,3 +1,9 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::NaiveTime;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
 use serde::Deserialize;
 
 use crate::audio::{self, Sound};
@@ -9,6 +15,8 @@ pub struct Notification<T = String> {
     #[serde(default)]
     pub show_toast: bool,
     pub sound: Option<T>,
+    /// Program and arguments to run, such as `["notify-send", "{nick}", "{message}"]`
+    pub command: Option<Vec<String>>,
 }
 
 impl<T> Default for Notification<T> {
@@ -16,10 +24,93 @@ impl<T> Default for Notification<T> {
         Self {
             show_toast: false,
             sound: None,
+            command: None,
         }
     }
 }
 
+impl<T> Notification<T> {
+    /// The program and arguments to run, with `context` substituted into each.
+    pub fn command(&self, context: Context) -> Option<Vec<String>> {
+        let command = self
+            .command
+            .as_ref()
+            .filter(|command| !command.is_empty())?;
+
+        Some(command.iter().map(|arg| context.substitute(arg)).collect())
+    }
+}
+
+/// Values substituted for `{server}`, `{channel}`, `{nick}` and `{message}`
+/// in notification commands and webhooks, empty when they don't apply.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Context<'a> {
+    pub server: &'a str,
+    pub channel: &'a str,
+    pub nick: &'a str,
+    pub message: &'a str,
+}
+
+impl Context<'_> {
+    pub fn substitute(&self, template: &str) -> String {
+        static PLACEHOLDER: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"\{(server|channel|nick|message)\}").unwrap());
+
+        PLACEHOLDER
+            .replace_all(template, |captures: &Captures| match &captures[1] {
+                "server" => self.server,
+                "channel" => self.channel,
+                "nick" => self.nick,
+                _ => self.message,
+            })
+            .into_owned()
+    }
+}
+
+/// An HTTP endpoint, such as ntfy, Gotify or Pushover, that highlights and
+/// direct messages are posted to.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<WebhookEvent>,
+    /// Headers, with the context substituted into their values
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Plain text body, the message if neither this nor `json` are set
+    pub body: Option<String>,
+    /// JSON body, with the context substituted into its strings
+    pub json: Option<serde_json::Value>,
+    /// Only post while marked away on the server, or idle
+    #[serde(default)]
+    pub only_when_away: bool,
+    /// Seconds Halloy has to be unfocused for to be considered idle
+    pub idle_after: Option<u64>,
+}
+
+impl Webhook {
+    /// Whether to post `event`, given whether we're `away` and how long
+    /// Halloy has been unfocused for.
+    pub fn is_enabled(&self, event: WebhookEvent, away: bool, unfocused: Option<Duration>) -> bool {
+        let idle = self.idle_after.is_some_and(|idle_after| {
+            unfocused.is_some_and(|unfocused| unfocused >= Duration::from_secs(idle_after))
+        });
+
+        self.events.contains(&event) && (!self.only_when_away || away || idle)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Highlight,
+    DirectMessage,
+}
+
+fn default_webhook_events() -> Vec<WebhookEvent> {
+    vec![WebhookEvent::Highlight, WebhookEvent::DirectMessage]
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Notifications<T = String> {
     #[serde(default)]
@@ -38,6 +129,10 @@ pub struct Notifications<T = String> {
     pub monitored_online: Notification<T>,
     #[serde(default)]
     pub monitored_offline: Notification<T>,
+    #[serde(default)]
+    pub do_not_disturb: DoNotDisturb,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
 }
 
 impl<T> Default for Notifications<T> {
@@ -51,6 +146,8 @@ impl<T> Default for Notifications<T> {
             file_transfer_request: Notification::default(),
             monitored_online: Notification::default(),
             monitored_offline: Notification::default(),
+            do_not_disturb: DoNotDisturb::default(),
+            webhooks: vec![],
         }
     }
 }
@@ -61,6 +158,7 @@ impl Notifications {
             Ok(Notification {
                 show_toast: notification.show_toast,
                 sound: notification.sound.as_deref().map(Sound::load).transpose()?,
+                command: notification.command.clone(),
             })
         };
 
@@ -73,6 +171,93 @@ impl Notifications {
             file_transfer_request: load(&self.file_transfer_request)?,
             monitored_online: load(&self.monitored_online)?,
             monitored_offline: load(&self.monitored_offline)?,
+            do_not_disturb: self.do_not_disturb.clone(),
+            webhooks: self.webhooks.clone(),
         })
     }
 }
+
+/// Quiet hours during which notifications are suppressed.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DoNotDisturb {
+    #[serde(default)]
+    pub schedule: Vec<QuietHours>,
+}
+
+impl DoNotDisturb {
+    pub fn is_scheduled(&self, time: NaiveTime) -> bool {
+        self.schedule.iter().any(|hours| hours.contains(time))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct QuietHours {
+    /// Local time, such as "22:00"
+    pub start: NaiveTime,
+    /// Local time, before `start` if the quiet hours run past midnight
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quiet_hours() {
+        let do_not_disturb: DoNotDisturb = toml::from_str(
+            r#"
+            [[schedule]]
+            start = "22:00"
+            end = "08:00"
+
+            [[schedule]]
+            start = "12:30"
+            end = "13:00"
+            "#,
+        )
+        .unwrap();
+
+        let time = |time: &str| time.parse::<NaiveTime>().unwrap();
+
+        assert!(do_not_disturb.is_scheduled(time("23:15")));
+        assert!(do_not_disturb.is_scheduled(time("00:00")));
+        assert!(do_not_disturb.is_scheduled(time("07:59")));
+        assert!(!do_not_disturb.is_scheduled(time("08:00")));
+        assert!(do_not_disturb.is_scheduled(time("12:45")));
+        assert!(!do_not_disturb.is_scheduled(time("13:00")));
+        assert!(!DoNotDisturb::default().is_scheduled(time("23:15")));
+    }
+
+    #[test]
+    fn command() {
+        let notification: Notification = toml::from_str(
+            r#"command = ["notify-send", "{nick} in {channel}", "{message}", "{unknown}"]"#,
+        )
+        .unwrap();
+
+        let command = notification.command(Context {
+            server: "libera",
+            channel: "#halloy",
+            nick: "casper",
+            message: "hi {nick}",
+        });
+
+        assert_eq!(
+            command.unwrap(),
+            ["notify-send", "casper in #halloy", "hi {nick}", "{unknown}"]
+        );
+        assert!(Notification::<String>::default()
+            .command(Context::default())
+            .is_none());
+    }
+}
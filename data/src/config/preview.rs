@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Preview {
+    /// Fetch titles of links posted in channels and queries
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds to wait for a page before giving up
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Only preview links to these domains, and their subdomains
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Never preview links to these domains, and their subdomains
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: default_timeout(),
+            allow: vec![],
+            deny: vec![],
+        }
+    }
+}
+
+impl Preview {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.clamp(1, 30))
+    }
+
+    /// Whether `url` may be fetched. Denied domains take precedence over
+    /// allowed ones, and an empty allow list allows every domain.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        let matches = |domain: &String| {
+            let domain = domain.trim_start_matches("*.");
+
+            host.eq_ignore_ascii_case(domain)
+                || host
+                    .len()
+                    .checked_sub(domain.len() + 1)
+                    .is_some_and(|index| {
+                        host.as_bytes()[index] == b'.'
+                            && host[index + 1..].eq_ignore_ascii_case(domain)
+                    })
+        };
+
+        !self.deny.iter().any(matches) && (self.allow.is_empty() || self.allow.iter().any(matches))
+    }
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn is_allowed() {
+        let preview = Preview {
+            allow: vec!["example.com".into(), "*.github.io".into()],
+            deny: vec!["private.example.com".into()],
+            ..Preview::default()
+        };
+
+        let tests = [
+            ("https://example.com/page", true),
+            ("http://www.example.com", true),
+            ("https://EXAMPLE.com", true),
+            ("https://halloy.github.io/docs", true),
+            ("https://private.example.com", false),
+            ("https://a.private.example.com", false),
+            ("https://notexample.com", false),
+            ("https://example.org", false),
+            ("ftp://example.com", false),
+        ];
+
+        for (url, expected) in tests {
+            assert_eq!(preview.is_allowed(&url.parse().unwrap()), expected, "{url}");
+        }
+
+        assert!(Preview::default().is_allowed(&"https://example.org".parse().unwrap()));
+    }
+}
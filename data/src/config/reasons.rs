@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+/// Reasons sent when none is given, for servers without their own.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Reasons {
+    /// Reason for leaving a channel
+    pub part: Option<String>,
+    /// Reason for quitting
+    pub quit: Option<String>,
+    /// Reason for kicking a user
+    pub kick: Option<String>,
+}
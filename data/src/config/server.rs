@@ -1,11 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Deref;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use irc::codec::Encoding;
 use irc::connection;
+use regex::Regex;
 use serde::{Deserialize, Deserializer};
 
+use crate::ban;
 use crate::config;
+use crate::config::ignore;
+use crate::message::Source;
+use crate::Message;
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct Server {
@@ -17,6 +25,9 @@ pub struct Server {
     pub nick_password_file: Option<String>,
     /// The client's NICKSERV password command.
     pub nick_password_command: Option<String>,
+    /// Whether to ask for the client's NICKSERV password when connecting.
+    #[serde(default)]
+    pub nick_password_prompt: bool,
     /// The server's NICKSERV IDENTIFY syntax.
     pub nick_identify_syntax: Option<IdentifySyntax>,
     /// Alternative nicknames for the client, if the default is taken.
@@ -37,9 +48,12 @@ pub struct Server {
     pub password_file: Option<String>,
     /// The command which outputs a password to connect to the server.
     pub password_command: Option<String>,
-    /// A list of channels to join on connection.
+    /// Whether to ask for the password to connect to the server when connecting.
+    #[serde(default)]
+    pub password_prompt: bool,
+    /// A list of channels to join on connection, or sections with settings per channel.
     #[serde(default)]
-    pub channels: Vec<String>,
+    pub channels: Channels,
     /// A mapping of channel names to keys for join-on-connect.
     #[serde(default)]
     pub channel_keys: HashMap<String, String>,
@@ -62,8 +76,31 @@ pub struct Server {
     /// in that order.
     #[serde(default = "default_ghost_sequence")]
     pub ghost_sequence: Vec<String>,
+    /// Whether to change to a nickname asked for with `/nick` once it's free
+    /// again, if it was in use.
+    #[serde(default)]
+    pub wait_for_nick: bool,
+    /// Whether to take back `nickname` once it's free, when connected with
+    /// another one.
+    #[serde(default)]
+    pub regain_nick: bool,
     /// User modestring to set on connect. Example: "+RB-x"
     pub umodes: Option<String>,
+    /// Reason sent when leaving a channel without giving one.
+    pub part_message: Option<String>,
+    /// Reason sent when quitting without giving one.
+    pub quit_message: Option<String>,
+    /// Reason sent when kicking a user without giving one.
+    pub kick_reason: Option<String>,
+    /// Encoding of text sent to and received from the server.
+    #[serde(
+        default = "default_encoding",
+        deserialize_with = "deserialize_encoding"
+    )]
+    pub encoding: Encoding,
+    /// Encoding of received text that isn't valid UTF-8, when `encoding` is UTF-8.
+    #[serde(default, deserialize_with = "deserialize_encoding_fallback")]
+    pub encoding_fallback: Option<Encoding>,
     /// Whether or not to use TLS.
     /// Clients will automatically panic if this is enabled without TLS support.
     #[serde(default = "default_use_tls")]
@@ -75,6 +112,8 @@ pub struct Server {
     root_cert_path: Option<PathBuf>,
     /// Sasl authentication
     pub sasl: Option<Sasl>,
+    /// Logging in with QuakeNet's Q, which has no SASL
+    pub q_auth: Option<QAuth>,
     /// Commands which are executed once connected.
     #[serde(default)]
     pub on_connect: Vec<String>,
@@ -93,9 +132,44 @@ pub struct Server {
         deserialize_with = "deserialize_duration_from_u64"
     )]
     pub who_retry_interval: Duration,
+    /// Channels with more users than this aren't polled with WHO.
+    #[serde(default = "default_who_poll_max_users")]
+    pub who_poll_max_users: usize,
+    /// Whether to join channels again after being kicked from them.
+    #[serde(default)]
+    pub rejoin_on_kick: bool,
+    /// How long to wait before joining again after being kicked.
+    #[serde(
+        default = "default_rejoin_delay",
+        deserialize_with = "deserialize_duration_from_u64"
+    )]
+    pub rejoin_delay: Duration,
+    /// How many times in a row to join again, before giving up.
+    #[serde(default = "default_rejoin_max_attempts")]
+    pub rejoin_max_attempts: usize,
+    /// Nicknames or accounts whose invites are joined without asking.
+    #[serde(default)]
+    pub join_invites_from: Vec<String>,
+    /// Whether invites to channels in `channels` are joined without asking.
+    #[serde(default)]
+    pub join_configured_invites: bool,
+    /// Users whose messages, notices and invites are dropped.
+    #[serde(default)]
+    pub ignore: Vec<ignore::Mask>,
+    /// Users whose messages are dimmed and never notify.
+    #[serde(default)]
+    pub soft_ignore: Vec<ignore::Mask>,
+    /// The kind of mask users are banned by, unless another is chosen.
+    #[serde(default)]
+    pub ban_mask: ban::Mask,
+    /// Protection against waves of unsolicited direct messages.
+    pub query_flood: Option<QueryFlood>,
     /// A list of nicknames to monitor (if MONITOR is supported by the server).
     #[serde(default)]
     pub monitor: Vec<String>,
+    /// Replies to CTCP queries.
+    #[serde(default)]
+    pub ctcp: Ctcp,
 }
 
 impl Server {
@@ -114,13 +188,72 @@ impl Server {
             } else {
                 default_port()
             }),
-            channels,
+            channels: Channels::from(channels),
             use_tls,
             dangerously_accept_invalid_certs: false,
             ..Default::default()
         }
     }
 
+    /// The next credential to ask for before connecting, if any.
+    pub fn prompt(&self) -> Option<Prompt> {
+        if self.password_prompt && self.password.is_none() {
+            Some(Prompt::Password)
+        } else if self.nick_password_prompt && self.nick_password.is_none() {
+            Some(Prompt::NickPassword)
+        } else if let Some(Sasl::Plain {
+            password: None,
+            password_prompt: true,
+            ..
+        }) = &self.sasl
+        {
+            Some(Prompt::SaslPassword)
+        } else {
+            None
+        }
+    }
+
+    /// Fills in a credential asked for when connecting.
+    pub fn set_prompted(&mut self, prompt: Prompt, secret: String) {
+        match prompt {
+            Prompt::Password => self.password = Some(secret),
+            Prompt::NickPassword => self.nick_password = Some(secret),
+            Prompt::SaslPassword => {
+                if let Some(Sasl::Plain { password, .. }) = &mut self.sasl {
+                    *password = Some(secret);
+                }
+            }
+        }
+    }
+
+    /// Keeps the credentials asked for in `previous`, so they aren't asked
+    /// for again after reloading the configuration.
+    pub fn keep_prompted(&mut self, previous: &Server) {
+        if self.password_prompt && previous.password_prompt {
+            self.password.clone_from(&previous.password);
+        }
+
+        if self.nick_password_prompt && previous.nick_password_prompt {
+            self.nick_password.clone_from(&previous.nick_password);
+        }
+
+        if let (
+            Some(Sasl::Plain {
+                password,
+                password_prompt: true,
+                ..
+            }),
+            Some(Sasl::Plain {
+                password: previous,
+                password_prompt: true,
+                ..
+            }),
+        ) = (&mut self.sasl, &previous.sasl)
+        {
+            password.clone_from(previous);
+        }
+    }
+
     pub fn connection(&self, proxy: Option<config::Proxy>) -> connection::Config {
         let security = if self.use_tls {
             connection::Security::Secured {
@@ -140,6 +273,10 @@ impl Server {
             proxy: proxy.map(From::from),
         }
     }
+
+    pub fn codec(&self) -> irc::Codec {
+        irc::Codec::new(self.encoding, self.encoding_fallback)
+    }
 }
 
 impl Default for Server {
@@ -149,6 +286,7 @@ impl Default for Server {
             nick_password: Default::default(),
             nick_password_file: Default::default(),
             nick_password_command: Default::default(),
+            nick_password_prompt: Default::default(),
             nick_identify_syntax: Default::default(),
             alt_nicks: Default::default(),
             username: Default::default(),
@@ -158,6 +296,7 @@ impl Default for Server {
             password: Default::default(),
             password_file: Default::default(),
             password_command: Default::default(),
+            password_prompt: Default::default(),
             channels: Default::default(),
             channel_keys: Default::default(),
             ping_time: default_ping_time(),
@@ -165,16 +304,312 @@ impl Default for Server {
             reconnect_delay: default_reconnect_delay(),
             should_ghost: Default::default(),
             ghost_sequence: default_ghost_sequence(),
+            wait_for_nick: Default::default(),
+            regain_nick: Default::default(),
             umodes: Default::default(),
+            part_message: Default::default(),
+            quit_message: Default::default(),
+            kick_reason: Default::default(),
+            encoding: default_encoding(),
+            encoding_fallback: Default::default(),
             use_tls: default_use_tls(),
             dangerously_accept_invalid_certs: Default::default(),
             root_cert_path: Default::default(),
             sasl: Default::default(),
+            q_auth: Default::default(),
             on_connect: Default::default(),
             who_poll_enabled: default_who_poll_enabled(),
             who_poll_interval: default_who_poll_interval(),
             who_retry_interval: default_who_retry_interval(),
+            who_poll_max_users: default_who_poll_max_users(),
+            rejoin_on_kick: Default::default(),
+            rejoin_delay: default_rejoin_delay(),
+            rejoin_max_attempts: default_rejoin_max_attempts(),
+            join_invites_from: Default::default(),
+            join_configured_invites: Default::default(),
+            ignore: Default::default(),
+            soft_ignore: Default::default(),
+            ban_mask: Default::default(),
+            query_flood: Default::default(),
             monitor: Default::default(),
+            ctcp: Default::default(),
+        }
+    }
+}
+
+/// The channels to join on connection, along with the settings of those
+/// given their own section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Channels {
+    join: Vec<String>,
+    settings: BTreeMap<String, ChannelSettings>,
+}
+
+impl Channels {
+    /// The settings of `channel`, or the defaults if it has no section.
+    pub fn settings(&self, channel: &str) -> ChannelSettings {
+        self.settings
+            .iter()
+            .find_map(|(name, settings)| name.eq_ignore_ascii_case(channel).then_some(settings))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Whether `channel` is joined on connection or has its own section.
+    pub fn contains(&self, channel: &str) -> bool {
+        self.join
+            .iter()
+            .chain(self.settings.keys())
+            .any(|name| name.eq_ignore_ascii_case(channel))
+    }
+
+    /// Adds `filters` to the section of `channel`, given one that isn't
+    /// joined on connection if it has none.
+    pub fn add_filters(&mut self, channel: &str, filters: Vec<Regex>) {
+        let settings = match self
+            .settings
+            .keys()
+            .find(|name| name.eq_ignore_ascii_case(channel))
+            .cloned()
+        {
+            Some(name) => self.settings.entry(name).or_default(),
+            None => self
+                .settings
+                .entry(channel.to_string())
+                .or_insert_with(|| ChannelSettings {
+                    join: false,
+                    ..ChannelSettings::default()
+                }),
+        };
+
+        for regex in filters {
+            if !settings
+                .filters
+                .patterns()
+                .any(|pattern| pattern == regex.as_str())
+            {
+                settings.filters.0.push(regex);
+            }
+        }
+    }
+
+    /// The channels given their own section, with their settings.
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &ChannelSettings)> {
+        self.settings
+            .iter()
+            .map(|(channel, settings)| (channel.as_str(), settings))
+    }
+}
+
+impl From<Vec<String>> for Channels {
+    fn from(join: Vec<String>) -> Self {
+        Self {
+            join,
+            settings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Deref for Channels {
+    type Target = [String];
+
+    fn deref(&self) -> &Self::Target {
+        &self.join
+    }
+}
+
+impl<'de> Deserialize<'de> for Channels {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Data {
+            List(Vec<String>),
+            Sections(BTreeMap<String, ChannelSettings>),
+        }
+
+        Ok(match Data::deserialize(deserializer)? {
+            Data::List(join) => Channels::from(join),
+            Data::Sections(settings) => Channels {
+                join: settings
+                    .iter()
+                    .filter(|(_, settings)| settings.join)
+                    .map(|(channel, _)| channel.clone())
+                    .collect(),
+                settings,
+            },
+        })
+    }
+}
+
+/// Settings of a channel, from its `[servers.<name>.channels."<channel>"]`
+/// section. They take precedence over the global ones.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    /// Whether to join the channel on connection.
+    pub join: bool,
+    /// Whether highlights in the channel send notifications.
+    pub notifications: bool,
+    /// Whether join, part and quit messages are shown in the channel.
+    pub join_part: bool,
+    /// Whether the channel's history is saved to disk.
+    pub logging: bool,
+    /// Whether users in the channel are polled with WHO for their away
+    /// state, if `who_poll_enabled`.
+    pub who_poll: bool,
+    /// Whether the nicklist is shown when opening the channel, overriding
+    /// `buffer.channel.nicklist.enabled`.
+    pub nicklist: Option<bool>,
+    /// Whether to join the channel again after being kicked, overriding
+    /// `rejoin_on_kick`.
+    pub rejoin_on_kick: Option<bool>,
+    /// Messages hidden from the channel.
+    pub filters: Filters,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self {
+            join: true,
+            notifications: true,
+            join_part: true,
+            logging: true,
+            who_poll: true,
+            nicklist: None,
+            rejoin_on_kick: None,
+            filters: Filters::default(),
+        }
+    }
+}
+
+/// Regular expressions of messages hidden from a channel, matched against
+/// the text of messages from users.
+#[derive(Debug, Clone, Default)]
+pub struct Filters(Vec<Regex>);
+
+impl Filters {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(Regex::as_str)
+    }
+
+    pub fn hides(&self, message: &Message) -> bool {
+        if self.0.is_empty() || !matches!(message.target.source(), Source::User(_) | Source::Action)
+        {
+            return false;
+        }
+
+        let text = message.content.text();
+
+        self.0.iter().any(|regex| regex.is_match(&text))
+    }
+}
+
+impl PartialEq for Filters {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|(a, b)| a.as_str() == b.as_str())
+    }
+}
+
+impl Eq for Filters {}
+
+impl<'de> Deserialize<'de> for Filters {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|error| {
+                    serde::de::Error::custom(format!("invalid filter \"{pattern}\": {error}"))
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Filters)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Ctcp {
+    /// Whether or not to reply to CTCP queries at all.
+    pub enabled: bool,
+    /// Reply to CLIENTINFO queries.
+    pub clientinfo: bool,
+    /// Reply to PING queries.
+    pub ping: bool,
+    /// Reply to SOURCE queries.
+    pub source: bool,
+    /// Reply to TIME queries.
+    pub time: bool,
+    /// Reply to VERSION queries.
+    pub version: bool,
+    /// Custom VERSION reply. Defaults to the client name and version.
+    pub version_reply: Option<String>,
+    /// USERINFO reply. USERINFO queries are not answered if unset.
+    pub userinfo: Option<String>,
+}
+
+impl Default for Ctcp {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            clientinfo: true,
+            ping: true,
+            source: true,
+            time: true,
+            version: true,
+            version_reply: None,
+            userinfo: None,
+        }
+    }
+}
+
+impl Ctcp {
+    /// The CTCP commands which will be answered, in CLIENTINFO order.
+    pub fn supported(&self) -> Vec<&'static str> {
+        [
+            ("ACTION", true),
+            ("CLIENTINFO", self.clientinfo),
+            ("DCC", true),
+            ("PING", self.ping),
+            ("SOURCE", self.source),
+            ("TIME", self.time),
+            ("USERINFO", self.userinfo.is_some()),
+            ("VERSION", self.version),
+        ]
+        .into_iter()
+        .filter_map(|(command, enabled)| enabled.then_some(command))
+        .collect()
+    }
+}
+
+/// A credential asked for when connecting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Password,
+    NickPassword,
+    SaslPassword,
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Prompt::Password => write!(f, "server password"),
+            Prompt::NickPassword => write!(f, "NickServ password"),
+            Prompt::SaslPassword => write!(f, "SASL password"),
         }
     }
 }
@@ -198,6 +633,9 @@ pub enum Sasl {
         password_file: Option<String>,
         /// Account password command
         password_command: Option<String>,
+        /// Whether to ask for the account password when connecting
+        #[serde(default)]
+        password_prompt: bool,
     },
     External {
         /// The path to PEM encoded X509 user certificate for external auth
@@ -250,6 +688,36 @@ impl Sasl {
     }
 }
 
+#[derive(PartialEq, Eq, Debug, Clone, Deserialize)]
+pub struct QAuth {
+    /// Account name
+    pub username: String,
+    /// Account password
+    pub password: Option<String>,
+    /// Account password file
+    pub password_file: Option<String>,
+    /// Account password command
+    pub password_command: Option<String>,
+    /// Whether to hide our host behind the account once logged in, with +x
+    #[serde(default = "default_hidden_host")]
+    pub hidden_host: bool,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Deserialize)]
+pub struct QueryFlood {
+    /// How many users we haven't talked to may start a query within `window`
+    #[serde(default = "default_query_flood_queries")]
+    pub queries: usize,
+    #[serde(
+        default = "default_query_flood_window",
+        deserialize_with = "deserialize_duration_from_u64"
+    )]
+    pub window: Duration,
+    /// Whether users sending the same text as others during a flood are ignored
+    #[serde(default)]
+    pub auto_ignore: bool,
+}
+
 fn deserialize_duration_from_u64<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>,
@@ -258,6 +726,27 @@ where
     Ok(Duration::from_secs(seconds.clamp(5, 3600)))
 }
 
+fn deserialize_encoding<'de, D>(deserializer: D) -> Result<Encoding, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let label = String::deserialize(deserializer)?;
+
+    Encoding::for_label(&label)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown encoding \"{label}\"")))
+}
+
+fn deserialize_encoding_fallback<'de, D>(deserializer: D) -> Result<Option<Encoding>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_encoding(deserializer).map(Some)
+}
+
+fn default_encoding() -> Encoding {
+    Encoding::UTF_8
+}
+
 fn default_use_tls() -> bool {
     true
 }
@@ -297,3 +786,27 @@ fn default_who_poll_interval() -> Duration {
 fn default_who_retry_interval() -> Duration {
     Duration::from_secs(10)
 }
+
+fn default_who_poll_max_users() -> usize {
+    1000
+}
+
+fn default_rejoin_delay() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_rejoin_max_attempts() -> usize {
+    3
+}
+
+fn default_hidden_host() -> bool {
+    true
+}
+
+fn default_query_flood_queries() -> usize {
+    5
+}
+
+fn default_query_flood_window() -> Duration {
+    Duration::from_secs(60)
+}
@@ -10,7 +10,7 @@ pub struct Sidebar {
     pub buffer_focused_action: Option<BufferFocusedAction>,
     #[serde(default = "default_sidebar_width")]
     pub width: u16,
-    #[serde(default)]
+    #[serde(default, alias = "unread_indicators")]
     pub unread_indicator: UnreadIndicator,
     #[serde(default)]
     pub position: Position,
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use irc::proto::{self, format};
+use itertools::Itertools;
+
+/// Max # lines kept per server
+const MAX_LINES: usize = 1_000;
+
+/// How long a labeled command waits for its reply before it's forgotten
+const LABEL_TIMEOUT: TimeDelta = TimeDelta::minutes(5);
+
+/// Commands whose first parameter is the channel or user they're about
+const TARGETED: &[&str] = &[
+    "PRIVMSG", "NOTICE", "TAGMSG", "JOIN", "PART", "KICK", "MODE", "TOPIC", "NAMES", "WHO",
+    "WHOIS", "WHOWAS", "MARKREAD",
+];
+
+const HIDDEN: &str = "********";
+
+/// Raw lines sent to and received from a server, for debugging.
+#[derive(Debug, Clone, Default)]
+pub struct Console {
+    lines: VecDeque<Line>,
+    /// When labeled commands waiting for their reply were sent
+    labels: HashMap<String, DateTime<Utc>>,
+}
+
+impl Console {
+    pub fn extend(&mut self, lines: impl IntoIterator<Item = Line>) {
+        for mut line in lines {
+            if let Some(label) = line.tag("label").map(String::from) {
+                match line.direction {
+                    Direction::Sent => {
+                        self.labels
+                            .retain(|_, sent| line.time - *sent < LABEL_TIMEOUT);
+                        self.labels.insert(label, line.time);
+                    }
+                    Direction::Received => {
+                        line.round_trip = self.labels.remove(&label).map(|sent| line.time - sent);
+                    }
+                }
+            }
+
+            self.lines.push_back(line);
+        }
+
+        let overflow = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..overflow);
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub time: DateTime<Utc>,
+    pub direction: Direction,
+    pub text: String,
+    /// The command, or the numeric as three digits
+    pub command: String,
+    /// Parameters after the command, with credentials hidden
+    pub params: Vec<String>,
+    pub tags: Vec<proto::Tag>,
+    /// Time since the labeled command this line replies to was sent
+    pub round_trip: Option<TimeDelta>,
+}
+
+impl Line {
+    pub fn sent(message: &proto::Message) -> Self {
+        Self::new(Direction::Sent, message)
+    }
+
+    pub fn received(message: &proto::Message) -> Self {
+        Self::new(Direction::Received, message)
+    }
+
+    fn new(direction: Direction, message: &proto::Message) -> Self {
+        let text = redact(format::message(message.clone()).trim_end());
+        // Parsed again from the redacted text, so credentials stay hidden
+        let parsed = proto::parse::message(&format!("{text}\r\n")).ok();
+
+        Self {
+            time: Utc::now(),
+            direction,
+            command: message.command.command(),
+            params: parsed
+                .map(|message| message.command.parameters())
+                .unwrap_or_default(),
+            tags: message.tags.clone(),
+            round_trip: None,
+            text,
+        }
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.key == key)
+            .and_then(|tag| tag.value.as_deref())
+    }
+
+    /// The channel or user the line is about, if any.
+    pub fn target(&self) -> Option<&str> {
+        let target = if self.command.parse::<u16>().is_ok() {
+            // Numerics are addressed to us first
+            self.params.get(1)?
+        } else if TARGETED.contains(&self.command.as_str()) {
+            self.params.first()?
+        } else {
+            return None;
+        };
+
+        (!target.contains(char::is_whitespace)).then_some(target.as_str())
+    }
+
+    /// Whether the command or target of the line is any of the
+    /// space-separated words of `filter`. Every line matches an empty filter.
+    pub fn matches(&self, filter: &str) -> bool {
+        let mut words = filter.split_whitespace().peekable();
+
+        words.peek().is_none()
+            || words.any(|word| {
+                self.command.eq_ignore_ascii_case(word)
+                    || self.target().is_some_and(|target| {
+                        target
+                            .split(',')
+                            .any(|target| target.eq_ignore_ascii_case(word))
+                    })
+            })
+    }
+}
+
+/// Hides credentials such as passwords and SASL payloads.
+fn redact(line: &str) -> String {
+    let mut rest = line;
+
+    // Skip tags and source
+    for marker in ['@', ':'] {
+        if rest.starts_with(marker) {
+            let end = rest.find(' ').map_or(rest.len(), |index| index + 1);
+            rest = &rest[end..];
+        }
+    }
+
+    let start = &line[..line.len() - rest.len()];
+    let (command, params) = rest.split_once(' ').unwrap_or((rest, ""));
+    let words = params.split(' ').collect::<Vec<_>>();
+
+    let is_service = |target: &str| {
+        let nick = target.split('@').next().unwrap_or(target);
+
+        ["nickserv", "chanserv", "q"]
+            .iter()
+            .any(|service| nick.eq_ignore_ascii_case(service))
+    };
+    // Parameters shown of a services command at `index`, if it has credentials
+    let service_command = |index: usize| {
+        let command = words.get(index)?.trim_start_matches(':').to_uppercase();
+
+        match command.as_str() {
+            "IDENTIFY" | "REGISTER" => Some(index + 1),
+            // The nickname or username comes before the password
+            "GHOST" | "RECOVER" | "REGAIN" | "RELEASE" | "AUTH" | "CHALLENGEAUTH" | "SETPASS" => {
+                Some(index + 2)
+            }
+            // SET PASSWORD, or SET #channel PASSWORD on ChanServ
+            "SET" => (index + 1..=index + 2).find_map(|at| {
+                words
+                    .get(at)?
+                    .eq_ignore_ascii_case("PASSWORD")
+                    .then_some(at + 1)
+            }),
+            _ => None,
+        }
+    };
+
+    // Number of parameters shown before the hidden part
+    let shown = match command.to_uppercase().as_str() {
+        "PASS" => Some(0),
+        "OPER" => Some(1),
+        "AUTHENTICATE" => match params {
+            "+" | "*" | "PLAIN" | "EXTERNAL" => None,
+            _ => Some(0),
+        },
+        "PRIVMSG" if is_service(words[0]) => service_command(1),
+        "NICKSERV" | "NS" | "CHANSERV" | "CS" => service_command(0),
+        _ => None,
+    };
+    let Some(shown) = shown else {
+        return line.to_string();
+    };
+
+    let shown = words.iter().take(shown).chain(Some(&HIDDEN)).join(" ");
+
+    format!("{start}{command} {shown}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redact_credentials() {
+        let tests = [
+            ("PASS hunter2", "PASS ********"),
+            ("OPER admin hunter2", "OPER admin ********"),
+            ("AUTHENTICATE PLAIN", "AUTHENTICATE PLAIN"),
+            ("AUTHENTICATE +", "AUTHENTICATE +"),
+            ("AUTHENTICATE Zm9vAGZvbwBiYXI=", "AUTHENTICATE ********"),
+            (
+                "PRIVMSG NickServ :IDENTIFY foo hunter2",
+                "PRIVMSG NickServ :IDENTIFY ********",
+            ),
+            ("NS identify hunter2", "NS identify ********"),
+            (
+                "PRIVMSG NickServ :REGAIN halloy hunter2",
+                "PRIVMSG NickServ :REGAIN halloy ********",
+            ),
+            (
+                "PRIVMSG NickServ :REGISTER hunter2 halloy@example.org",
+                "PRIVMSG NickServ :REGISTER ********",
+            ),
+            (
+                "PRIVMSG NickServ :SET PASSWORD hunter2",
+                "PRIVMSG NickServ :SET PASSWORD ********",
+            ),
+            (
+                "NS SETPASS halloy key hunter2",
+                "NS SETPASS halloy ********",
+            ),
+            (
+                "PRIVMSG ChanServ :SET #halloy PASSWORD hunter2",
+                "PRIVMSG ChanServ :SET #halloy PASSWORD ********",
+            ),
+            ("CS identify #halloy hunter2", "CS identify ********"),
+            (
+                "PRIVMSG NickServ :SET EMAIL halloy@example.org",
+                "PRIVMSG NickServ :SET EMAIL halloy@example.org",
+            ),
+            (
+                "PRIVMSG Q@CServe.quakenet.org :AUTH halloy hunter2",
+                "PRIVMSG Q@CServe.quakenet.org :AUTH halloy ********",
+            ),
+            (
+                "@label=1 :nick!user@host PASS hunter2",
+                "@label=1 :nick!user@host PASS ********",
+            ),
+            ("PRIVMSG #halloy :hunter2", "PRIVMSG #halloy :hunter2"),
+            (
+                "PRIVMSG NickServ :info hunter2",
+                "PRIVMSG NickServ :info hunter2",
+            ),
+        ];
+
+        for (line, expected) in tests {
+            assert_eq!(redact(line), expected);
+        }
+    }
+
+    #[test]
+    fn inspect() {
+        let parse = |line: &str| proto::parse::message(&format!("{line}\r\n")).unwrap();
+
+        let line = Line::sent(&parse("PRIVMSG NickServ :IDENTIFY hunter2"));
+        assert_eq!(line.command, "PRIVMSG");
+        assert_eq!(line.params, vec!["NickServ", "IDENTIFY ********"]);
+        assert!(line.matches("privmsg"));
+        assert!(line.matches("join nickserv"));
+        assert!(!line.matches("JOIN"));
+        assert!(line.matches(""));
+
+        let line = Line::received(&parse(":irc.example.org 366 halloy #halloy :End of /NAMES"));
+        assert_eq!(line.target(), Some("#halloy"));
+
+        let mut console = Console::default();
+        let mut sent = Line::sent(&parse("@label=1 WHO #halloy"));
+        sent.time -= TimeDelta::seconds(2);
+        let reply = Line::received(&parse(
+            "@label=1 :irc.example.org BATCH +a labeled-response",
+        ));
+        console.extend([sent, reply]);
+
+        let round_trip = console.lines().last().unwrap().round_trip.unwrap();
+        assert!(round_trip >= TimeDelta::seconds(2));
+    }
+}
@@ -1,15 +1,20 @@
+use chrono::{DateTime, TimeDelta, Utc};
 use irc::proto;
+use std::collections::VecDeque;
 use std::fmt;
+use std::time::{Duration, Instant};
 
 // Reference: https://rawgit.com/DanielOaks/irc-rfcs/master/dist/draft-oakley-irc-ctcp-latest.html
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Command {
     Action,
     ClientInfo,
     DCC,
     Ping,
     Source,
+    Time,
+    UserInfo,
     Version,
     Unknown(String),
 }
@@ -20,6 +25,40 @@ pub struct Query<'a> {
     pub params: Option<&'a str>,
 }
 
+impl From<&str> for Command {
+    fn from(command: &str) -> Self {
+        match command.to_uppercase().as_ref() {
+            "ACTION" => Command::Action,
+            "CLIENTINFO" => Command::ClientInfo,
+            "DCC" => Command::DCC,
+            "PING" => Command::Ping,
+            "SOURCE" => Command::Source,
+            "TIME" => Command::Time,
+            "USERINFO" => Command::UserInfo,
+            "VERSION" => Command::Version,
+            command => Command::Unknown(command.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let command = match self {
+            Command::Action => "ACTION",
+            Command::ClientInfo => "CLIENTINFO",
+            Command::DCC => "DCC",
+            Command::Ping => "PING",
+            Command::Source => "SOURCE",
+            Command::Time => "TIME",
+            Command::UserInfo => "USERINFO",
+            Command::Version => "VERSION",
+            Command::Unknown(command) => command.as_ref(),
+        };
+
+        write!(f, "{command}")
+    }
+}
+
 pub fn is_query(text: &str) -> bool {
     text.starts_with('\u{1}')
 }
@@ -31,35 +70,15 @@ pub fn parse_query(text: &str) -> Option<Query> {
         .strip_prefix('\u{1}')?;
 
     let (command, params) = if let Some((command, params)) = query.split_once(char::is_whitespace) {
-        (command.to_uppercase(), Some(params))
+        (Command::from(command), Some(params))
     } else {
-        (query.to_uppercase(), None)
-    };
-
-    let command = match command.as_ref() {
-        "ACTION" => Command::Action,
-        "CLIENTINFO" => Command::ClientInfo,
-        "DCC" => Command::DCC,
-        "PING" => Command::Ping,
-        "SOURCE" => Command::Source,
-        "VERSION" => Command::Version,
-        _ => Command::Unknown(command),
+        (Command::from(query), None)
     };
 
     Some(Query { command, params })
 }
 
 pub fn format(command: &Command, params: Option<impl fmt::Display>) -> String {
-    let command = match command {
-        Command::Action => "ACTION",
-        Command::ClientInfo => "CLIENTINFO",
-        Command::DCC => "DCC",
-        Command::Ping => "PING",
-        Command::Source => "SOURCE",
-        Command::Version => "VERSION",
-        Command::Unknown(command) => command.as_ref(),
-    };
-
     if let Some(params) = params {
         format!("\u{1}{command} {params}\u{1}")
     } else {
@@ -67,6 +86,26 @@ pub fn format(command: &Command, params: Option<impl fmt::Display>) -> String {
     }
 }
 
+/// How long the reply to a query we sent is waited for
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Parameter of a PING query we send, the time it's sent in milliseconds, so
+/// the round-trip time can be told from the reply.
+pub fn ping_param() -> String {
+    Utc::now().timestamp_millis().to_string()
+}
+
+/// Round-trip time of a PING query sent with [`ping_param`], replied to at
+/// `received`. Parameters other clients send are left alone.
+pub fn ping_round_trip(params: &str, received: DateTime<Utc>) -> Option<TimeDelta> {
+    let sent = DateTime::from_timestamp_millis(params.trim().parse().ok()?)?;
+    let round_trip = received - sent;
+
+    TimeDelta::from_std(QUERY_TIMEOUT)
+        .is_ok_and(|timeout| round_trip >= TimeDelta::zero() && round_trip <= timeout)
+        .then_some(round_trip)
+}
+
 pub fn query_command(
     command: &Command,
     target: String,
@@ -90,3 +129,89 @@ pub fn response_message(
 ) -> proto::Message {
     proto::command!("NOTICE", target, format(command, params))
 }
+
+/// How long replies count towards the limits
+const REPLY_WINDOW: Duration = Duration::from_secs(10);
+/// Replies sent to all users within the window
+const MAX_REPLIES: usize = 5;
+/// Replies sent to a single user within the window
+const MAX_REPLIES_PER_USER: usize = 2;
+
+/// Limits automatic replies to queries, so a flood of queries can't make us
+/// flood ourselves off the server.
+#[derive(Debug, Default)]
+pub struct Limiter {
+    replies: VecDeque<(Instant, String)>,
+    suppressed: usize,
+}
+
+impl Limiter {
+    /// Whether a reply to `nick` can be sent, recording it if so.
+    pub fn allows(&mut self, nick: &str, now: Instant) -> bool {
+        while self
+            .replies
+            .front()
+            .is_some_and(|(sent, _)| now.duration_since(*sent) >= REPLY_WINDOW)
+        {
+            self.replies.pop_front();
+        }
+
+        let nick = nick.to_lowercase();
+        let to_user = self.replies.iter().filter(|(_, to)| *to == nick).count();
+
+        if self.replies.len() >= MAX_REPLIES || to_user >= MAX_REPLIES_PER_USER {
+            self.suppressed += 1;
+            return false;
+        }
+
+        self.replies.push_back((now, nick));
+        self.suppressed = 0;
+
+        true
+    }
+
+    /// Queries left unanswered since the last reply.
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiter() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+
+        assert!(limiter.allows("troll", now));
+        assert!(limiter.allows("Troll", now));
+        assert!(!limiter.allows("troll", now));
+        assert_eq!(limiter.suppressed(), 1);
+
+        for nick in ["a", "b", "c"] {
+            assert!(limiter.allows(nick, now));
+        }
+        assert!(!limiter.allows("d", now));
+        assert!(!limiter.allows("e", now));
+        assert_eq!(limiter.suppressed(), 2);
+
+        assert!(limiter.allows("troll", now + REPLY_WINDOW));
+        assert_eq!(limiter.suppressed(), 0);
+    }
+
+    #[test]
+    fn ping_round_trip() {
+        let sent = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let received = sent + TimeDelta::milliseconds(250);
+
+        assert_eq!(
+            super::ping_round_trip("1700000000000", received),
+            Some(TimeDelta::milliseconds(250))
+        );
+        // Seconds and microseconds, as sent by other clients
+        assert_eq!(super::ping_round_trip("1700000000 123456", received), None);
+        assert_eq!(super::ping_round_trip("1600000000000", received), None);
+    }
+}
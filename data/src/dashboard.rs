@@ -1,8 +1,10 @@
+use std::collections::BTreeMap;
 use std::io;
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use crate::buffer::{self, Buffer};
 use crate::pane::Pane;
 use crate::{compression, environment};
 
@@ -11,6 +13,12 @@ pub struct Dashboard {
     pub pane: Pane,
     #[serde(default)]
     pub popout_panes: Vec<Pane>,
+    /// Named layouts of the main window, saved with `/layout save`
+    #[serde(default)]
+    pub layouts: BTreeMap<String, Pane>,
+    /// Buffers zoomed in or out
+    #[serde(default)]
+    pub zoom: Vec<(Buffer, buffer::Zoom)>,
 }
 
 #[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
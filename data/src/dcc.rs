@@ -7,6 +7,8 @@ use crate::ctcp;
 use irc::proto;
 use itertools::Itertools;
 
+pub mod chat;
+
 pub fn decode(content: &str) -> Option<Command> {
     let query = ctcp::parse_query(content)?;
 
@@ -18,6 +20,9 @@ pub fn decode(content: &str) -> Option<Command> {
 
     match args.next()?.to_lowercase().as_str() {
         "send" => Send::decode(args).map(Command::Send),
+        "chat" => Chat::decode(args).map(Command::Chat),
+        "resume" => Resume::decode(args).map(Command::Resume),
+        "accept" => Resume::decode(args).map(Command::Accept),
         cmd => Some(Command::Unsupported(cmd.to_string())),
     }
 }
@@ -25,6 +30,9 @@ pub fn decode(content: &str) -> Option<Command> {
 #[derive(Debug, Clone)]
 pub enum Command {
     Send(Send),
+    Chat(Chat),
+    Resume(Resume),
+    Accept(Resume),
     Unsupported(String),
 }
 
@@ -154,6 +162,186 @@ impl Send {
     }
 }
 
+/// Offer to chat over a direct connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chat {
+    pub host: IpAddr,
+    /// `None` for reverse chats, where the remote waits for us to listen
+    pub port: Option<NonZeroU16>,
+    pub token: Option<String>,
+}
+
+impl Chat {
+    fn decode<'a>(mut args: impl Iterator<Item = &'a str>) -> Option<Self> {
+        // Only the plain text protocol is supported
+        if !args.next()?.eq_ignore_ascii_case("chat") {
+            return None;
+        }
+
+        let host = decode_host(args.next()?)?;
+        let port = NonZeroU16::new(args.next()?.parse().ok()?);
+        let token = args.next().map(String::from);
+
+        if port.is_none() && token.is_none() {
+            return None;
+        }
+
+        Some(Self { host, port, token })
+    }
+
+    pub fn encode(self, target: &dyn ToString) -> proto::Message {
+        let host = encode_host(self.host);
+        let port = self.port.map(NonZeroU16::get).unwrap_or(0);
+
+        let params = match self.token {
+            Some(token) => format!("CHAT chat {host} {port} {token}"),
+            None => format!("CHAT chat {host} {port}"),
+        };
+
+        ctcp::query_message(&ctcp::Command::DCC, target.to_string(), Some(params))
+    }
+}
+
+/// Position to continue an interrupted transfer from, requested by the
+/// receiver with `RESUME` and confirmed by the sender with `ACCEPT`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resume {
+    pub filename: String,
+    /// Port of the offer, `None` for reverse transfers
+    pub port: Option<NonZeroU16>,
+    pub position: u64,
+    pub token: Option<String>,
+}
+
+impl Resume {
+    fn decode<'a>(args: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let args = args.collect::<Vec<_>>();
+
+        if args.len() < 3 {
+            return None;
+        }
+
+        // Reverse transfers are resumed on port 0, followed by their token
+        let (args, token) = if args.len() > 3 && args[args.len() - 3] == "0" {
+            let (token, args) = args.split_last()?;
+            (args, Some(token.to_string()))
+        } else {
+            (args.as_slice(), None)
+        };
+
+        let (position, args) = args.split_last()?;
+        let (port, filename) = args.split_last()?;
+
+        let port = NonZeroU16::new(port.parse().ok()?);
+        let position = position.parse().ok()?;
+        let filename = filename.iter().join(" ").trim_matches('\"').to_string();
+
+        if port.is_none() && token.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            filename,
+            port,
+            position,
+            token,
+        })
+    }
+
+    pub fn resume(self, target: &dyn ToString) -> proto::Message {
+        self.encode("RESUME", target)
+    }
+
+    pub fn accept(self, target: &dyn ToString) -> proto::Message {
+        self.encode("ACCEPT", target)
+    }
+
+    fn encode(self, command: &str, target: &dyn ToString) -> proto::Message {
+        let Self {
+            filename,
+            port,
+            position,
+            token,
+        } = self;
+
+        let port = port.map(NonZeroU16::get).unwrap_or(0);
+
+        let params = match token {
+            Some(token) => format!("{command} {filename} {port} {position} {token}"),
+            None => format!("{command} {filename} {port} {position}"),
+        };
+
+        ctcp::query_message(&ctcp::Command::DCC, target.to_string(), Some(params))
+    }
+}
+
+/// Address to advertise in offers, preferring a configured address reachable
+/// from the internet over the host the server reports for us.
+pub async fn public_address(configured: Option<IpAddr>, reported: Option<&str>) -> Option<IpAddr> {
+    if let Some(address) = configured.filter(is_global) {
+        return Some(address);
+    }
+
+    let discovered = match reported {
+        Some(host) => resolve(host).await,
+        None => None,
+    };
+
+    // A LAN address still works with someone on the same network
+    discovered.or(configured)
+}
+
+async fn resolve(host: &str) -> Option<IpAddr> {
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return Some(address).filter(is_global);
+    }
+
+    // Cloaks such as `user/halloy` can't be resolved
+    if host.contains('/') || !host.contains('.') {
+        return None;
+    }
+
+    let addresses = tokio::net::lookup_host((host, 0))
+        .await
+        .ok()?
+        .map(|address| address.ip())
+        .filter(is_global)
+        .collect::<Vec<_>>();
+
+    // IPv4 is more widely supported by other clients
+    addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or(addresses.first())
+        .copied()
+}
+
+/// Whether `address` could be reachable from outside of the local network.
+fn is_global(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                // Carrier-grade NAT
+                || (a == 100 && (b & 0b1100_0000) == 64))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                // Unique local
+                || (first & 0xfe00) == 0xfc00
+                // Link local
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
 fn decode_host(host: &str) -> Option<IpAddr> {
     match host.parse::<u32>() {
         Ok(n) => Some(IpAddr::V4(Ipv4Addr::from(n))),
@@ -172,6 +360,20 @@ fn encode_host(host: IpAddr) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn global_addresses() {
+        let global = |address: &str| is_global(&address.parse().unwrap());
+
+        assert!(global("203.0.113.7"));
+        assert!(global("2001:db8::1"));
+        assert!(!global("192.168.1.20"));
+        assert!(!global("10.0.0.2"));
+        assert!(!global("100.64.0.1"));
+        assert!(!global("127.0.0.1"));
+        assert!(!global("fd00::1"));
+        assert!(!global("fe80::1"));
+    }
+
     #[test]
     fn send_decode() {
         let args = "my_file_name 1402301083 12350 1453953495";
@@ -294,4 +496,68 @@ mod tests {
         let send = Send::decode(args.split_whitespace());
         assert_eq!(send, None);
     }
+
+    #[test]
+    fn resume_decode() {
+        let args = "\"my file name\" 12345 1024";
+        let resume = Resume::decode(args.split_whitespace());
+        assert_eq!(
+            resume,
+            Some(Resume {
+                filename: "my file name".to_string(),
+                port: NonZeroU16::new(12345),
+                position: 1024,
+                token: None,
+            })
+        );
+
+        let args = "file.ext 0 1024 token";
+        let resume = Resume::decode(args.split_whitespace());
+        assert_eq!(
+            resume,
+            Some(Resume {
+                filename: "file.ext".to_string(),
+                port: None,
+                position: 1024,
+                token: Some("token".to_string()),
+            })
+        );
+
+        // Port 0 is only valid with a token
+        let args = "file.ext 0 1024";
+        let resume = Resume::decode(args.split_whitespace());
+        assert_eq!(resume, None);
+    }
+
+    #[test]
+    fn chat_decode() {
+        let args = "chat 1402301083 12345";
+        let chat = Chat::decode(args.split_whitespace());
+        assert_eq!(
+            chat,
+            Some(Chat {
+                host: IpAddr::V4(Ipv4Addr::from(1402301083)),
+                port: NonZeroU16::new(12345),
+                token: None,
+            })
+        );
+
+        let args = "CHAT 1402301083 0 token";
+        let chat = Chat::decode(args.split_whitespace());
+        assert_eq!(
+            chat,
+            Some(Chat {
+                host: IpAddr::V4(Ipv4Addr::from(1402301083)),
+                port: None,
+                token: Some("token".to_string()),
+            })
+        );
+
+        // Other protocols, and port 0 without a token
+        assert_eq!(
+            Chat::decode("wboard 1402301083 12345".split_whitespace()),
+            None
+        );
+        assert_eq!(Chat::decode("chat 1402301083 0".split_whitespace()), None);
+    }
 }
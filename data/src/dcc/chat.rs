@@ -0,0 +1,485 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::num::NonZeroU16;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use bytes::{Bytes, BytesMut};
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::{stream, SinkExt, Stream, StreamExt};
+use irc::connection::{self, Listener};
+use irc::{BytesCodec, Connection};
+use thiserror::Error;
+use tokio::time;
+
+use crate::user::Nick;
+use crate::{config, dcc, server, Server};
+
+/// Queries with a nick starting with this are chats over DCC
+pub const PREFIX: char = '=';
+
+/// Offers kept per server, further ones are ignored
+const MAX_OFFERS: usize = 20;
+/// How long an offer can be accepted
+const OFFER_EXPIRY: Duration = Duration::from_secs(10 * 60);
+/// Bytes received without a newline, passed on as a line once reached
+const MAX_LINE_LEN: usize = 8 * 1024;
+
+pub type Id = usize;
+
+/// Query target of a chat with `remote`.
+pub fn target(remote: &Nick) -> Nick {
+    Nick::from(format!("{PREFIX}{remote}"))
+}
+
+/// Remote user of a chat, if `target` is one.
+pub fn remote(target: &str) -> Option<Nick> {
+    target
+        .strip_prefix(PREFIX)
+        .filter(|nick| !nick.is_empty())
+        .map(Nick::from)
+}
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    /// Accepting an offer from the remote user
+    Accept(dcc::Chat),
+    /// Offering to chat with the remote user
+    Offer,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chat {
+    pub id: Id,
+    pub server: Server,
+    pub remote: Nick,
+    pub request: Request,
+    server_handle: server::Handle,
+    reported_host: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Action {
+    Send(String),
+    /// Remote is listening for our reverse offer
+    Confirmed {
+        host: IpAddr,
+        port: NonZeroU16,
+    },
+}
+
+#[derive(Debug)]
+pub enum Update {
+    Ready(Id, mpsc::Sender<Action>),
+    Connected(Id),
+    Received(Id, String),
+    Closed(Id, Option<String>),
+}
+
+#[derive(Debug)]
+struct Open {
+    chat: Chat,
+    sender: Option<mpsc::Sender<Action>>,
+    connected: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Manager {
+    /// Offers from other users, until accepted with `/dcc chat`
+    offers: HashMap<(Server, Nick), (dcc::Chat, Instant)>,
+    chats: BTreeMap<Id, Open>,
+    next_id: Id,
+}
+
+impl Manager {
+    /// Returns `true` for a new offer, rather than the confirmation of one we
+    /// sent or an offer ignored for there being too many.
+    pub fn received(&mut self, server: &Server, from: Nick, offer: dcc::Chat) -> bool {
+        if let (Some(port), Some(token)) = (offer.port, &offer.token) {
+            if let Some(sender) = self
+                .chats
+                .values_mut()
+                .find(|open| {
+                    open.chat.server == *server
+                        && open.chat.remote == from
+                        && matches!(open.chat.request, Request::Offer)
+                        && open.chat.id.to_string() == *token
+                })
+                .and_then(|open| open.sender.as_mut())
+            {
+                let _ = sender.try_send(Action::Confirmed {
+                    host: offer.host,
+                    port,
+                });
+                return false;
+            }
+        }
+
+        let now = Instant::now();
+        self.offers
+            .retain(|_, (_, received)| now.duration_since(*received) < OFFER_EXPIRY);
+
+        let key = (server.clone(), from);
+
+        if !self.offers.contains_key(&key)
+            && self.offers.keys().filter(|(s, _)| s == server).count() >= MAX_OFFERS
+        {
+            log::debug!(
+                "[{server}] ignoring DCC chat offer from {}: too many offers",
+                key.1
+            );
+            return false;
+        }
+
+        self.offers.insert(key, (offer, now));
+
+        true
+    }
+
+    /// Accepts the offer from `remote`, or offers to chat if there is none.
+    pub fn open(
+        &mut self,
+        server: &Server,
+        remote: Nick,
+        server_handle: server::Handle,
+        reported_host: Option<String>,
+    ) -> Result<String> {
+        if self.find(server, &remote).is_some() {
+            bail!("already chatting with {remote}");
+        }
+
+        let offer = self
+            .offers
+            .remove(&(server.clone(), remote.clone()))
+            .filter(|(_, received)| received.elapsed() < OFFER_EXPIRY);
+
+        let (request, feedback) = match offer {
+            Some((offer, _)) => (
+                Request::Accept(offer),
+                format!("accepting DCC chat with {remote}"),
+            ),
+            None => (Request::Offer, format!("offering DCC chat to {remote}")),
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.chats.insert(
+            id,
+            Open {
+                chat: Chat {
+                    id,
+                    server: server.clone(),
+                    remote,
+                    request,
+                    server_handle,
+                    reported_host,
+                },
+                sender: None,
+                connected: false,
+            },
+        );
+
+        Ok(feedback)
+    }
+
+    /// Closes the chat with `remote`, or declines their offer.
+    pub fn close(&mut self, server: &Server, remote: &Nick) -> Result<String> {
+        if let Some(id) = self.find(server, remote) {
+            // Dropping the sender ends the chat
+            self.chats.remove(&id);
+
+            Ok(format!("closed DCC chat with {remote}"))
+        } else if self
+            .offers
+            .remove(&(server.clone(), remote.clone()))
+            .is_some()
+        {
+            Ok(format!("declined DCC chat with {remote}"))
+        } else {
+            bail!("no DCC chat with {remote}");
+        }
+    }
+
+    pub fn send(&mut self, server: &Server, remote: &Nick, text: String) -> Result<()> {
+        let Some(open) = self
+            .find(server, remote)
+            .and_then(|id| self.chats.get_mut(&id))
+            .filter(|open| open.connected)
+        else {
+            bail!("not connected to {remote}");
+        };
+
+        if let Some(sender) = open.sender.as_mut() {
+            sender.try_send(Action::Send(text))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn ready(&mut self, id: Id, sender: mpsc::Sender<Action>) {
+        if let Some(open) = self.chats.get_mut(&id) {
+            open.sender = Some(sender);
+        }
+    }
+
+    pub fn connected(&mut self, id: Id) -> Option<&Chat> {
+        let open = self.chats.get_mut(&id)?;
+        open.connected = true;
+
+        Some(&open.chat)
+    }
+
+    pub fn get(&self, id: Id) -> Option<&Chat> {
+        self.chats.get(&id).map(|open| &open.chat)
+    }
+
+    pub fn remove(&mut self, id: Id) -> Option<Chat> {
+        self.chats.remove(&id).map(|open| open.chat)
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = &Chat> {
+        self.chats.values().map(|open| &open.chat)
+    }
+
+    fn find(&self, server: &Server, remote: &Nick) -> Option<Id> {
+        self.chats
+            .values()
+            .find(|open| open.chat.server == *server && open.chat.remote == *remote)
+            .map(|open| open.chat.id)
+    }
+}
+
+pub fn run(
+    chat: Chat,
+    config: config::FileTransfer,
+    proxy: Option<config::Proxy>,
+) -> impl Stream<Item = Update> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    // Spawn to unblock backend from iced stream which has backpressure
+    let runner = stream::once(async { tokio::spawn(_run(chat, config, proxy, sender)).await })
+        .filter_map(|_| future::ready(None));
+
+    stream::select(receiver, runner)
+}
+
+async fn _run(
+    chat: Chat,
+    config: config::FileTransfer,
+    proxy: Option<config::Proxy>,
+    updates: mpsc::UnboundedSender<Update>,
+) {
+    let id = chat.id;
+    let (sender, mut actions) = mpsc::channel(100);
+
+    let _ = updates.unbounded_send(Update::Ready(id, sender));
+
+    let connection = match connect(chat, config, proxy, &mut actions).await {
+        Ok(Some(connection)) => connection,
+        // Closed before the remote connected
+        Ok(None) => return,
+        Err(error) => {
+            let _ = updates.unbounded_send(Update::Closed(id, Some(error.to_string())));
+            return;
+        }
+    };
+
+    let _ = updates.unbounded_send(Update::Connected(id));
+
+    let (mut writer, mut reader) = connection.split();
+    let mut buffer = BytesMut::new();
+
+    let error = loop {
+        match future::select(reader.next(), actions.next()).await {
+            Either::Left((Some(Ok(bytes)), _)) => {
+                buffer.extend_from_slice(&bytes);
+
+                while let Some(pos) = buffer.iter().position(|byte| *byte == b'\n') {
+                    let line = buffer.split_to(pos + 1);
+                    let text = String::from_utf8_lossy(&line)
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string();
+
+                    if !text.is_empty() {
+                        let _ = updates.unbounded_send(Update::Received(id, text));
+                    }
+                }
+
+                // Don't hold onto text without newlines from the remote forever
+                if buffer.len() >= MAX_LINE_LEN {
+                    let line = buffer.split();
+                    let text = String::from_utf8_lossy(&line).into_owned();
+
+                    let _ = updates.unbounded_send(Update::Received(id, text));
+                }
+            }
+            Either::Left((Some(Err(error)), _)) => break Some(error.to_string()),
+            Either::Left((None, _)) => break None,
+            Either::Right((Some(Action::Send(text)), _)) => {
+                if let Err(error) = writer.send(Bytes::from(format!("{text}\n"))).await {
+                    break Some(error.to_string());
+                }
+            }
+            Either::Right((Some(Action::Confirmed { .. }), _)) => {}
+            // Closed with `/dcc close`
+            Either::Right((None, _)) => break None,
+        }
+    };
+
+    let _ = writer.close().await;
+    let _ = updates.unbounded_send(Update::Closed(id, error));
+}
+
+/// Returns `None` if the chat is closed before the remote connects.
+async fn connect(
+    chat: Chat,
+    config: config::FileTransfer,
+    proxy: Option<config::Proxy>,
+    actions: &mut mpsc::Receiver<Action>,
+) -> Result<Option<Connection<BytesCodec>>, Error> {
+    let Chat {
+        id,
+        remote,
+        request,
+        mut server_handle,
+        reported_host,
+        ..
+    } = chat;
+
+    let timeout = Duration::from_secs(config.timeout);
+
+    let (host, port) = match request {
+        Request::Accept(dcc::Chat {
+            host,
+            port: Some(port),
+            ..
+        }) => (host, port),
+        Request::Accept(dcc::Chat { token, .. }) => {
+            let server = config.server.ok_or(Error::ReverseNoServerConfig)?;
+            let public_address =
+                dcc::public_address(server.public_address, reported_host.as_deref())
+                    .await
+                    .ok_or(Error::NoPublicAddress)?;
+
+            let (port, listener) =
+                Listener::bind(server.bind_address, server.bind_ports, BytesCodec::new()).await?;
+
+            let _ = server_handle
+                .send(
+                    dcc::Chat {
+                        host: public_address,
+                        port: NonZeroU16::new(port),
+                        token,
+                    }
+                    .encode(&remote),
+                )
+                .await;
+
+            return accept(listener, timeout, actions).await;
+        }
+        Request::Offer if config.passive => {
+            // Host doesn't matter for reverse connection
+            let host = IpAddr::V4([127, 0, 0, 1].into());
+
+            let _ = server_handle
+                .send(
+                    dcc::Chat {
+                        host,
+                        port: None,
+                        token: Some(id.to_string()),
+                    }
+                    .encode(&remote),
+                )
+                .await;
+
+            let confirmed = time::timeout(timeout, async {
+                loop {
+                    match actions.next().await {
+                        Some(Action::Confirmed { host, port }) => return Some((host, port)),
+                        Some(Action::Send(_)) => {}
+                        None => return None,
+                    }
+                }
+            })
+            .await
+            .map_err(|_| Error::TimeoutPassive)?;
+
+            let Some(confirmed) = confirmed else {
+                return Ok(None);
+            };
+
+            confirmed
+        }
+        Request::Offer => {
+            let server = config.server.ok_or(Error::NonPassiveNoServerConfig)?;
+            let public_address =
+                dcc::public_address(server.public_address, reported_host.as_deref())
+                    .await
+                    .ok_or(Error::NoPublicAddress)?;
+
+            let (port, listener) =
+                Listener::bind(server.bind_address, server.bind_ports, BytesCodec::new()).await?;
+
+            let _ = server_handle
+                .send(
+                    dcc::Chat {
+                        host: public_address,
+                        port: NonZeroU16::new(port),
+                        token: None,
+                    }
+                    .encode(&remote),
+                )
+                .await;
+
+            return accept(listener, timeout, actions).await;
+        }
+    };
+
+    let connection = Connection::new(
+        connection::Config {
+            server: &host.to_string(),
+            port: port.get(),
+            security: connection::Security::Unsecured,
+            proxy: proxy.map(From::from),
+        },
+        BytesCodec::new(),
+    )
+    .await?;
+
+    Ok(Some(connection))
+}
+
+/// Waits for the remote to connect, unless the chat is closed first.
+async fn accept(
+    listener: Listener<BytesCodec>,
+    timeout: Duration,
+    actions: &mut mpsc::Receiver<Action>,
+) -> Result<Option<Connection<BytesCodec>>, Error> {
+    let accept = std::pin::pin!(time::timeout(timeout, listener.accept()));
+    let closed = std::pin::pin!(async { while actions.next().await.is_some() {} });
+
+    match future::select(accept, closed).await {
+        Either::Left((connection, _)) => {
+            Ok(Some(connection.map_err(|_| Error::TimeoutConnection)??))
+        }
+        Either::Right(_) => Ok(None),
+    }
+}
+
+#[derive(Debug, Error)]
+enum Error {
+    #[error("[dcc] or [file_transfer.server] must be configured to accept a passive chat")]
+    ReverseNoServerConfig,
+    #[error("[dcc] or [file_transfer.server] must be configured to offer a chat when passive is disabled")]
+    NonPassiveNoServerConfig,
+    #[error("couldn't discover our public address, set `public_address` in [dcc]")]
+    NoPublicAddress,
+    #[error("connection error: {0}")]
+    Connection(#[from] connection::Error),
+    #[error("timed out waiting for remote to connect")]
+    TimeoutConnection,
+    #[error("timed out waiting for remote to accept")]
+    TimeoutPassive,
+}
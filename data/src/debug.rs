@@ -0,0 +1,201 @@
+//! Diagnostics written to a single file, to attach to bug reports.
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use chrono::Local;
+use tokio::fs;
+use toml::Value;
+
+use crate::console::{self, Direction};
+use crate::{environment, history, Config};
+
+/// Console lines kept in the report, per server
+const CONSOLE_LINES: usize = 200;
+
+const HIDDEN: &str = "********";
+
+/// Settings hidden from the report wherever they are, holding passwords or
+/// the commands and files revealing them
+const SECRETS: &[&str] = &[
+    "password",
+    "password_file",
+    "password_command",
+    "nick_password",
+    "nick_password_file",
+    "nick_password_command",
+    "passphrase",
+    "passphrase_file",
+    "passphrase_command",
+    "channel_keys",
+    "on_connect",
+];
+
+/// What a server told us it supports, and what was last sent and received.
+#[derive(Debug, Clone)]
+pub struct Server {
+    pub name: String,
+    pub status: String,
+    pub caps: Vec<String>,
+    pub isupport: Vec<String>,
+    pub console: Vec<console::Line>,
+}
+
+impl Server {
+    pub fn new(name: String, status: String, console: Option<&console::Console>) -> Self {
+        let mut console = console
+            .map(|console| console.lines().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        console.drain(..console.len().saturating_sub(CONSOLE_LINES));
+
+        Self {
+            name,
+            status,
+            caps: vec![],
+            isupport: vec![],
+            console,
+        }
+    }
+}
+
+/// Writes the report to a new file in the exports directory, returning its
+/// path.
+pub async fn export(servers: Vec<Server>) -> Result<PathBuf, Error> {
+    let config = match Config::merged().await {
+        Ok(config) => sanitize(config).unwrap_or_else(|error| error.to_string()),
+        Err(error) => format!("couldn't read the configuration: {error}"),
+    };
+
+    let mut report = String::new();
+
+    let _ = writeln!(report, "# Halloy {}", environment::formatted_version());
+    let _ = writeln!(
+        report,
+        "{} {}, {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        Local::now().to_rfc3339()
+    );
+
+    let _ = writeln!(report, "\n# Configuration\n\n{}", config.trim_end());
+
+    for server in servers {
+        let _ = writeln!(report, "\n# Server {} ({})", server.name, server.status);
+        let _ = writeln!(report, "\n## Capabilities\n\n{}", server.caps.join(" "));
+        let _ = writeln!(report, "\n## ISUPPORT\n");
+        for parameter in &server.isupport {
+            let _ = writeln!(report, "{parameter}");
+        }
+
+        let _ = writeln!(report, "\n## Console\n");
+        for line in &server.console {
+            let direction = match line.direction {
+                Direction::Sent => "->",
+                Direction::Received => "<-",
+            };
+
+            let _ = writeln!(
+                report,
+                "{} {direction} {}",
+                line.time.to_rfc3339(),
+                line.text
+            );
+        }
+    }
+
+    let path = history::export::dir_path().await?.join(format!(
+        "debug-{}.txt",
+        Local::now().format("%Y-%m-%d_%H-%M-%S")
+    ));
+
+    fs::write(&path, report).await?;
+
+    Ok(path)
+}
+
+/// The configuration with passwords, passphrases, channel keys and the
+/// tokens of webhooks and pastebins hidden.
+fn sanitize(config: toml::Table) -> Result<String, Error> {
+    let mut value = Value::Table(config);
+
+    hide_secrets(&mut value, None, false);
+
+    Ok(toml::to_string_pretty(&value)?)
+}
+
+/// Hides the strings in `value`, a setting of the `parent` table, if
+/// `secret`.
+fn hide_secrets(value: &mut Value, parent: Option<&str>, secret: bool) {
+    match value {
+        Value::String(text) if secret => *text = HIDDEN.to_string(),
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                let secret = secret || is_secret(parent, key);
+
+                hide_secrets(value, Some(key), secret);
+            }
+        }
+        // Tables in arrays, like webhooks, are settings of the parent table
+        Value::Array(values) => {
+            for value in values {
+                hide_secrets(value, parent, secret);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_secret(parent: Option<&str>, key: &str) -> bool {
+    SECRETS.contains(&key)
+        || matches!(
+            (parent, key),
+            // Webhook and pastebin URLs often hold tokens
+            (Some("webhooks"), "url" | "headers") | (Some("pastebin"), _)
+        )
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Export(#[from] history::Error),
+    #[error(transparent)]
+    Serialize(#[from] toml::ser::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitized() {
+        let config = r##"
+[servers.liberachat]
+nickname = "halloy"
+nick_password = "hunter2"
+password_prompt = true
+channel_keys = { "#secret" = "hunter2" }
+
+[servers.liberachat.sasl.plain]
+username = "halloy"
+password_command = "pass show liberachat"
+
+[history]
+passphrase_command = "pass show halloy"
+
+[[notifications.webhooks]]
+url = "https://ntfy.sh/hunter2"
+headers = { Authorization = "Bearer hunter2" }
+
+[buffer.text_input.paste.pastebin]
+url = "https://paste.example.com/?token=hunter2"
+"##;
+
+        let sanitized = sanitize(toml::from_str(config).unwrap()).unwrap();
+
+        assert!(!sanitized.contains("hunter2"));
+        assert!(!sanitized.contains("pass show"));
+        assert!(sanitized.contains("nickname = \"halloy\""));
+        assert!(sanitized.contains("password_prompt = true"));
+    }
+}
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 use crate::user::Nick;
 use crate::{dcc, server, Server};
@@ -10,9 +11,11 @@ pub use self::manager::Manager;
 pub use self::task::Task;
 
 pub mod manager;
+pub mod saved;
 pub mod task;
+pub mod throttle;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Id(u16);
 
 impl From<u16> for Id {
@@ -27,7 +30,7 @@ impl From<Id> for u16 {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileTransfer {
     pub id: Id,
     pub server: Server,
@@ -37,12 +40,17 @@ pub struct FileTransfer {
     pub filename: String,
     pub size: u64,
     pub status: Status,
+    /// File being sent, or where a received file is saved once approved
+    #[serde(default)]
+    pub path: Option<PathBuf>,
 }
 
 impl FileTransfer {
     pub fn progress(&self) -> f64 {
         match self.status {
-            Status::Active { transferred, .. } => transferred as f64 / self.size as f64,
+            Status::Active { transferred, .. } | Status::Interrupted { transferred } => {
+                transferred as f64 / self.size as f64
+            }
             Status::Completed { .. } => 1.0,
             _ => 0.0,
         }
@@ -66,13 +74,13 @@ impl Ord for FileTransfer {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Direction {
     Sent,
     Received,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
     /// Pending appoval
     PendingApproval,
@@ -85,9 +93,59 @@ pub enum Status {
     /// Transfer is actively sending / receiving
     Active { transferred: u64, elapsed: Duration },
     /// Transfer is complete
-    Completed { elapsed: Duration, sha256: String },
+    Completed {
+        elapsed: Duration,
+        sha256: String,
+        checksum: Checksum,
+    },
     /// An error occured
     Failed { error: String },
+    /// Halloy closed before the transfer completed
+    Interrupted { transferred: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Checksum {
+    /// Nothing to verify the file against
+    Unverified,
+    /// Matches the checksum given for the file
+    Verified,
+    /// Doesn't match the checksum given for the file, which may be corrupt
+    Mismatch { expected: String },
+}
+
+impl Checksum {
+    pub fn verify(sha256: &str, expected: Option<&str>) -> Self {
+        match expected {
+            Some(expected) if expected.eq_ignore_ascii_case(sha256) => Checksum::Verified,
+            Some(expected) => Checksum::Mismatch {
+                expected: expected.to_lowercase(),
+            },
+            None => Checksum::Unverified,
+        }
+    }
+}
+
+/// Message sent to the remote user once a transfer finishes.
+pub fn finished_message(direction: Direction, filename: &str, sha256: &str) -> String {
+    match direction {
+        Direction::Sent => format!("Finished sending \"{filename}\", sha256: {sha256}"),
+        Direction::Received => format!("Finished receiving \"{filename}\", sha256: {sha256}"),
+    }
+}
+
+/// Filename and checksum from the message sent by the remote user once they
+/// finish sending us a file.
+pub fn parse_sent_checksum(text: &str) -> Option<(String, String)> {
+    let (filename, sha256) = text
+        .strip_prefix("Finished sending \"")?
+        .rsplit_once("\", sha256: ")?;
+
+    is_sha256(sha256).then(|| (filename.to_string(), sha256.to_lowercase()))
+}
+
+pub fn is_sha256(text: &str) -> bool {
+    text.len() == 64 && text.chars().all(|c| c.is_ascii_hexdigit())
 }
 
 #[derive(Debug, Clone)]
@@ -96,6 +154,8 @@ pub struct ReceiveRequest {
     pub dcc_send: dcc::Send,
     pub server: Server,
     pub server_handle: server::Handle,
+    /// Our host as reported by the IRC server
+    pub reported_host: Option<String>,
 }
 
 #[derive(Debug)]
@@ -104,4 +164,33 @@ pub struct SendRequest {
     pub path: PathBuf,
     pub server: Server,
     pub server_handle: server::Handle,
+    /// Our host as reported by the IRC server
+    pub reported_host: Option<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sent_checksum() {
+        let sha256 = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+        assert_eq!(
+            parse_sent_checksum(&finished_message(
+                Direction::Sent,
+                "my_\"file\".txt",
+                sha256
+            )),
+            Some(("my_\"file\".txt".to_string(), sha256.to_string()))
+        );
+        assert_eq!(
+            parse_sent_checksum(&finished_message(Direction::Received, "file.txt", sha256)),
+            None
+        );
+        assert_eq!(
+            parse_sent_checksum("Finished sending \"file.txt\", sha256: 1234"),
+            None
+        );
+    }
 }
@@ -2,16 +2,24 @@ use std::{
     collections::{HashMap, VecDeque},
     num::NonZeroU16,
     path::PathBuf,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use chrono::Utc;
-use futures::{stream::BoxStream, StreamExt};
+use futures::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
 use itertools::Itertools;
 use rand::Rng;
 
-use super::{task, Direction, FileTransfer, Id, ReceiveRequest, SendRequest, Status, Task};
-use crate::{config, dcc};
+use super::throttle::{Limit, Throttle};
+use super::{
+    saved, task, Checksum, Direction, FileTransfer, Id, ReceiveRequest, SendRequest, Status, Task,
+};
+use crate::{config, dcc, user::Nick};
+
+/// Time between saves while transfers change
+const SAVE_EVERY: Duration = Duration::from_secs(5);
+/// Number of recent transfers listed after a restart
+const MAX_SAVED: usize = 100;
 
 enum Item {
     Working {
@@ -47,18 +55,71 @@ pub struct Manager {
     /// Queued = waiting for port assignment
     queued: VecDeque<Id>,
     used_ports: HashMap<Id, NonZeroU16>,
+    /// Received transfers waiting for the sender to accept a resume
+    resuming: HashMap<Id, dcc::Resume>,
+    /// Checksums given by the sender before their transfer finished
+    checksums: HashMap<Id, String>,
+    /// Shared by all sent transfers
+    upload: Limit,
+    /// Shared by all received transfers
+    download: Limit,
+    changed: bool,
+    last_saved: Instant,
 }
 
 impl Manager {
     pub fn new(config: config::FileTransfer) -> Self {
         Self {
+            upload: Limit::new(config.rate_limit.upload),
+            download: Limit::new(config.rate_limit.download),
+            changed: false,
+            last_saved: Instant::now(),
             config,
             items: HashMap::new(),
             queued: VecDeque::new(),
             used_ports: HashMap::new(),
+            resuming: HashMap::new(),
+            checksums: HashMap::new(),
         }
     }
 
+    /// Lists transfers saved before a restart, with ones still running marked as interrupted.
+    pub fn load(config: config::FileTransfer) -> Self {
+        let mut manager = Self::new(config);
+
+        let file_transfers = match saved::load() {
+            Ok(file_transfers) => file_transfers,
+            Err(saved::Error::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                vec![]
+            }
+            Err(error) => {
+                log::warn!("failed to load file transfers: {error}");
+                vec![]
+            }
+        };
+
+        for mut file_transfer in file_transfers {
+            match file_transfer.status {
+                Status::Completed { .. } | Status::Failed { .. } | Status::Interrupted { .. } => {}
+                Status::Active { transferred, .. } => {
+                    file_transfer.status = Status::Interrupted { transferred };
+                }
+                Status::PendingApproval
+                | Status::PendingReverseConfirmation
+                | Status::Queued
+                | Status::Ready => {
+                    file_transfer.status = Status::Interrupted { transferred: 0 };
+                }
+            }
+
+            manager
+                .items
+                .insert(file_transfer.id, Item::Finished(file_transfer));
+        }
+
+        manager
+    }
+
     fn get_random_id(&self) -> Id {
         let mut rng = rand::thread_rng();
 
@@ -71,23 +132,63 @@ impl Manager {
         }
     }
 
-    fn server(&self) -> Option<task::Server> {
+    fn server(&self, reported_host: Option<String>) -> Option<task::Server> {
         self.config.server.as_ref().map(|server| task::Server {
             public_address: server.public_address,
+            reported_host,
             bind_address: server.bind_address,
         })
     }
 
+    fn throttle(&self, direction: Direction) -> Throttle {
+        let rate_limit = &self.config.rate_limit;
+
+        match direction {
+            Direction::Sent => Throttle {
+                transfer: Limit::new(rate_limit.upload_per_transfer),
+                global: self.upload.clone(),
+            },
+            Direction::Received => Throttle {
+                transfer: Limit::new(rate_limit.download_per_transfer),
+                global: self.download.clone(),
+            },
+        }
+    }
+
+    /// Applies rate limits to new and running transfers.
+    pub fn set_rate_limit(&mut self, rate_limit: config::file_transfer::RateLimit) {
+        self.upload.set(rate_limit.upload);
+        self.download.set(rate_limit.download);
+
+        for item in self.items.values() {
+            if let Item::Working {
+                file_transfer,
+                task,
+            } = item
+            {
+                task.set_limit(match file_transfer.direction {
+                    Direction::Sent => rate_limit.upload_per_transfer,
+                    Direction::Received => rate_limit.download_per_transfer,
+                });
+            }
+        }
+
+        self.config.rate_limit = rate_limit;
+    }
+
     pub fn send(&mut self, request: SendRequest, proxy: Option<config::Proxy>) -> Option<Event> {
         let SendRequest {
             to,
             path,
             server,
             server_handle,
+            reported_host,
         } = request;
 
         let reverse = self.config.passive;
 
+        self.changed = true;
+
         let filename = path
             .file_name()
             .and_then(|n| n.to_str())
@@ -114,13 +215,15 @@ impl Manager {
                 // Task will trigger queued update
                 Status::Queued
             },
+            path: Some(path.clone()),
         };
 
         let task = Task::send(id, path, filename, to, reverse, server_handle);
         let (handle, stream) = task.spawn(
-            self.server(),
+            self.server(reported_host),
             Duration::from_secs(self.config.timeout),
             proxy,
+            self.throttle(Direction::Sent),
         );
 
         self.items.insert(
@@ -144,6 +247,7 @@ impl Manager {
             dcc_send,
             server,
             server_handle,
+            reported_host,
         } = request;
 
         // Check if this is the response to a reverse send we sent
@@ -177,8 +281,32 @@ impl Manager {
             dcc_send.filename()
         );
 
+        self.changed = true;
+
         let id = self.get_random_id();
 
+        // Offered again after being interrupted, so it can be resumed where it was saved
+        let interrupted = self.items.iter().find_map(|(id, item)| match item {
+            Item::Finished(
+                file_transfer @ FileTransfer {
+                    direction: Direction::Received,
+                    status: Status::Interrupted { .. },
+                    path: Some(_),
+                    ..
+                },
+            ) if file_transfer.server == server
+                && file_transfer.remote_user == from
+                && file_transfer.filename == dcc_send.filename()
+                && file_transfer.size == dcc_send.size() =>
+            {
+                Some(*id)
+            }
+            _ => None,
+        });
+        let path = interrupted
+            .and_then(|id| self.items.remove(&id))
+            .and_then(|item| item.file_transfer().path.clone());
+
         // Otherwise this must be a new request
         let file_transfer = FileTransfer {
             id,
@@ -189,13 +317,15 @@ impl Manager {
             filename: dcc_send.filename().to_string(),
             size: dcc_send.size(),
             status: Status::PendingApproval,
+            path,
         };
 
         let task = Task::receive(id, dcc_send, from, server_handle);
         let (handle, stream) = task.spawn(
-            self.server(),
+            self.server(reported_host),
             Duration::from_secs(self.config.timeout),
             proxy.cloned(),
+            self.throttle(Direction::Received),
         );
 
         self.items.insert(
@@ -209,14 +339,118 @@ impl Manager {
         Some(Event::NewTransfer(file_transfer, stream.boxed()))
     }
 
+    /// Resumes a sent transfer from where the remote's partial file ends.
+    pub fn resume(&mut self, from: &Nick, resume: dcc::Resume) {
+        let id = match resume.port {
+            Some(port) => self
+                .used_ports
+                .iter()
+                .find_map(|(id, used)| (*used == port).then_some(*id)),
+            None => resume
+                .token
+                .as_deref()
+                .and_then(|token| token.parse().ok().map(Id)),
+        };
+
+        if let Some(Item::Working {
+            file_transfer,
+            task,
+        }) = id.and_then(|id| self.items.get_mut(&id))
+        {
+            if file_transfer.direction == Direction::Sent && file_transfer.remote_user == *from {
+                log::debug!(
+                    "File transfer resume requested by {from} for {:?} at {}",
+                    file_transfer.filename,
+                    resume.position,
+                );
+                task.resume(resume.position);
+            }
+        }
+    }
+
+    /// Continues a received transfer once the sender accepts to resume it.
+    pub fn accept(&mut self, from: &Nick, accept: dcc::Resume) {
+        let Some(id) = self.resuming.iter().find_map(|(id, resume)| {
+            (resume.port == accept.port && resume.token == accept.token).then_some(*id)
+        }) else {
+            return;
+        };
+
+        if let Some(Item::Working {
+            file_transfer,
+            task,
+        }) = self.items.get_mut(&id)
+        {
+            if file_transfer.remote_user == *from {
+                log::debug!(
+                    "File transfer resume accepted by {from} for {:?} at {}",
+                    file_transfer.filename,
+                    accept.position,
+                );
+                self.resuming.remove(&id);
+                task.accept(accept.position);
+            }
+        }
+    }
+
+    /// Verifies the latest file received from `from` named `filename` against
+    /// the checksum they sent, or once it finishes.
+    pub fn checksum(&mut self, from: &Nick, filename: &str, sha256: String) {
+        if !self.config.verify_checksum {
+            return;
+        }
+
+        let Some(item) = self
+            .items
+            .values_mut()
+            .filter(|item| {
+                let file_transfer = item.file_transfer();
+
+                file_transfer.direction == Direction::Received
+                    && file_transfer.remote_user == *from
+                    && file_transfer.filename == filename
+            })
+            .max_by_key(|item| item.file_transfer().created_at)
+        else {
+            return;
+        };
+
+        self.changed = true;
+
+        match item {
+            Item::Working { file_transfer, .. } => {
+                self.checksums.insert(file_transfer.id, sha256);
+            }
+            Item::Finished(file_transfer) => {
+                if let Status::Completed {
+                    sha256: received,
+                    checksum,
+                    ..
+                } = &mut file_transfer.status
+                {
+                    *checksum = Checksum::verify(received, Some(&sha256));
+                }
+
+                log_checksum(file_transfer);
+            }
+        }
+    }
+
     pub fn update(&mut self, update: task::Update) {
+        self.changed = true;
+
         match update {
             task::Update::Metadata(id, size) => {
                 if let Some(item) = self.items.get_mut(&id) {
                     item.file_transfer_mut().size = size;
                 }
             }
+            task::Update::Resuming(id, resume) => {
+                self.resuming.insert(id, resume);
+            }
             task::Update::Queued(id) => {
+                self.resuming.remove(&id);
+
                 let available_port = self.get_available_port();
 
                 if let Some(Item::Working {
@@ -236,6 +470,8 @@ impl Manager {
                 }
             }
             task::Update::Ready(id) => {
+                self.resuming.remove(&id);
+
                 if let Some(item) = self.items.get_mut(&id) {
                     item.file_transfer_mut().status = Status::Ready;
                 }
@@ -267,7 +503,10 @@ impl Manager {
                 id,
                 elapsed,
                 sha256,
+                expected,
             } => {
+                let expected = self.checksums.remove(&id).or(expected);
+
                 if let Some(Item::Working { file_transfer, .. }) = self.items.remove(&id) {
                     log::debug!(
                         "File transfer completed {} {} for {:?} in {:.2}s",
@@ -280,13 +519,22 @@ impl Manager {
                         elapsed.as_secs_f32()
                     );
 
-                    self.items.insert(
-                        id,
-                        Item::Finished(FileTransfer {
-                            status: Status::Completed { elapsed, sha256 },
-                            ..file_transfer
-                        }),
-                    );
+                    let checksum = if self.config.verify_checksum {
+                        Checksum::verify(&sha256, expected.as_deref())
+                    } else {
+                        Checksum::Unverified
+                    };
+                    let file_transfer = FileTransfer {
+                        status: Status::Completed {
+                            elapsed,
+                            sha256,
+                            checksum,
+                        },
+                        ..file_transfer
+                    };
+                    log_checksum(&file_transfer);
+
+                    self.items.insert(id, Item::Finished(file_transfer));
 
                     self.recycle_port(id);
                 }
@@ -305,6 +553,8 @@ impl Manager {
                     );
                     file_transfer.status = Status::Failed { error };
 
+                    self.resuming.remove(&id);
+                    self.checksums.remove(&id);
                     self.recycle_port(id);
                 }
             }
@@ -338,13 +588,24 @@ impl Manager {
     }
 
     pub fn remove(&mut self, id: &Id) {
+        self.changed = true;
+
         let _ = self.items.remove(id);
         self.queued.retain(|i| i != id);
+        self.resuming.remove(id);
+        self.checksums.remove(id);
         self.recycle_port(*id);
     }
 
     pub fn approve(&mut self, id: &Id, save_to: PathBuf) {
-        if let Some(Item::Working { task, .. }) = self.items.get_mut(id) {
+        if let Some(Item::Working {
+            file_transfer,
+            task,
+        }) = self.items.get_mut(id)
+        {
+            self.changed = true;
+
+            file_transfer.path = Some(save_to.clone());
             task.approve(save_to);
         }
     }
@@ -357,7 +618,45 @@ impl Manager {
         self.items.values().map(Item::file_transfer).sorted()
     }
 
+    /// Saves transfers if they changed since last saved a while ago.
+    pub fn tick(&mut self, now: Instant) -> Option<BoxFuture<'static, Result<(), saved::Error>>> {
+        (self.changed && now.duration_since(self.last_saved) >= SAVE_EVERY).then(|| self.save(now))
+    }
+
+    pub fn exit(&mut self) -> Option<BoxFuture<'static, Result<(), saved::Error>>> {
+        self.changed.then(|| self.save(Instant::now()))
+    }
+
+    fn save(&mut self, now: Instant) -> BoxFuture<'static, Result<(), saved::Error>> {
+        self.changed = false;
+        self.last_saved = now;
+
+        let file_transfers = self.list().take(MAX_SAVED).cloned().collect();
+
+        saved::save(file_transfers).boxed()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.items.values().len() == 0
     }
 }
+
+fn log_checksum(file_transfer: &FileTransfer) {
+    let Status::Completed { checksum, .. } = &file_transfer.status else {
+        return;
+    };
+
+    match checksum {
+        Checksum::Unverified => {}
+        Checksum::Verified => log::debug!(
+            "File transfer checksum verified from {} for {:?}",
+            file_transfer.remote_user,
+            file_transfer.filename,
+        ),
+        Checksum::Mismatch { expected } => log::warn!(
+            "File transfer checksum mismatch from {} for {:?}, expected sha256: {expected}",
+            file_transfer.remote_user,
+            file_transfer.filename,
+        ),
+    }
+}
@@ -0,0 +1,40 @@
+use std::io;
+use std::path::PathBuf;
+
+use super::FileTransfer;
+use crate::{compression, environment};
+
+/// Transfers listed after a restart, most recent first.
+pub fn load() -> Result<Vec<FileTransfer>, Error> {
+    let bytes = std::fs::read(path()?)?;
+
+    Ok(compression::decompress(&bytes)?)
+}
+
+pub async fn save(file_transfers: Vec<FileTransfer>) -> Result<(), Error> {
+    let path = path()?;
+
+    let bytes = compression::compress(&file_transfers)?;
+
+    tokio::fs::write(path, &bytes).await?;
+
+    Ok(())
+}
+
+fn path() -> Result<PathBuf, Error> {
+    let parent = environment::data_dir();
+
+    if !parent.exists() {
+        std::fs::create_dir_all(&parent)?;
+    }
+
+    Ok(parent.join("file-transfers.json.gz"))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Compression(#[from] compression::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
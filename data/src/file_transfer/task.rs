@@ -2,35 +2,40 @@ use std::{
     io,
     net::IpAddr,
     num::NonZeroU16,
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
 use bytes::{Bytes, BytesMut};
 use futures::{
     channel::mpsc::{self, Receiver, Sender},
+    future::{self, Either},
     SinkExt, Stream,
 };
 use irc::{connection, proto::command, BytesCodec, Connection};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 use tokio::{
-    fs::File,
+    fs::{self, File},
     io::{AsyncReadExt, AsyncWriteExt},
     task::JoinHandle,
     time,
 };
 use tokio_stream::StreamExt;
 
-use super::Id;
+use super::throttle::{Limit, Throttle};
+use super::{finished_message, is_sha256, Direction, Id};
 use crate::{config, dcc, server, user::Nick};
 
 /// 16 KiB
 pub const BUFFER_SIZE: usize = 16 * 1024;
+/// Time to wait for the sender to accept a resume before starting over
+const RESUME_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct Handle {
     sender: Sender<Action>,
     task: JoinHandle<()>,
+    limit: Limit,
 }
 
 impl Handle {
@@ -47,6 +52,19 @@ impl Handle {
     pub fn port_available(&mut self, port: NonZeroU16) {
         let _ = self.sender.try_send(Action::PortAvailable { port });
     }
+
+    pub fn resume(&mut self, position: u64) {
+        let _ = self.sender.try_send(Action::Resume { position });
+    }
+
+    pub fn accept(&mut self, position: u64) {
+        let _ = self.sender.try_send(Action::Accepted { position });
+    }
+
+    /// Changes the max rate of this transfer, in KiB/s.
+    pub fn set_limit(&self, kibibytes: Option<u64>) {
+        self.limit.set(kibibytes);
+    }
 }
 
 impl Drop for Handle {
@@ -110,7 +128,9 @@ impl Task {
         server: Option<Server>,
         timeout: Duration,
         proxy: Option<config::Proxy>,
+        throttle: Throttle,
     ) -> (Handle, impl Stream<Item = Update>) {
+        let limit = throttle.transfer.clone();
         let (action_sender, action_receiver) = mpsc::channel(1);
         let (update_sender, update_receiver) = mpsc::channel(100);
 
@@ -134,6 +154,7 @@ impl Task {
                         server,
                         timeout,
                         proxy,
+                        throttle,
                     )
                     .await
                     {
@@ -160,6 +181,7 @@ impl Task {
                         server,
                         timeout,
                         proxy,
+                        throttle,
                     )
                     .await
                     {
@@ -173,6 +195,7 @@ impl Task {
             Handle {
                 sender: action_sender,
                 task,
+                limit,
             },
             update_receiver,
         )
@@ -183,6 +206,8 @@ pub enum Action {
     Approve { save_to: PathBuf },
     ReverseConfirmed { host: IpAddr, port: NonZeroU16 },
     PortAvailable { port: NonZeroU16 },
+    Resume { position: u64 },
+    Accepted { position: u64 },
 }
 
 #[derive(Debug)]
@@ -190,6 +215,7 @@ pub enum Update {
     Metadata(Id, u64),
     Queued(Id),
     Ready(Id),
+    Resuming(Id, dcc::Resume),
     Progress {
         id: Id,
         transferred: u64,
@@ -199,15 +225,27 @@ pub enum Update {
         id: Id,
         elapsed: Duration,
         sha256: String,
+        /// Checksum of a `.sha256` file saved next to a received file
+        expected: Option<String>,
     },
     Failed(Id, String),
 }
 
 pub struct Server {
-    pub public_address: IpAddr,
+    pub public_address: Option<IpAddr>,
+    /// Our host as reported by the IRC server, to discover the public address from
+    pub reported_host: Option<String>,
     pub bind_address: IpAddr,
 }
 
+impl Server {
+    async fn public_address(&self) -> Result<IpAddr, Error> {
+        dcc::public_address(self.public_address, self.reported_host.as_deref())
+            .await
+            .ok_or(Error::NoPublicAddress)
+    }
+}
+
 async fn receive(
     id: Id,
     dcc_send: dcc::Send,
@@ -218,12 +256,51 @@ async fn receive(
     server: Option<Server>,
     timeout: Duration,
     proxy: Option<config::Proxy>,
+    throttle: Throttle,
 ) -> Result<(), Error> {
     // Wait for approval
     let Some(Action::Approve { save_to }) = action.next().await else {
         return Ok(());
     };
 
+    // Continue from a partial file left by an interrupted transfer
+    let partial = fs::metadata(&save_to)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+
+    let position = if partial > 0 && partial < dcc_send.size() {
+        let resume = dcc::Resume {
+            filename: dcc_send.filename().to_string(),
+            port: match &dcc_send {
+                dcc::Send::Direct { port, .. } => Some(*port),
+                dcc::Send::Reverse { .. } => None,
+            },
+            position: partial,
+            token: dcc_send.token().map(ToString::to_string),
+        };
+
+        let _ = server_handle
+            .send(resume.clone().resume(&remote_user))
+            .await;
+        let _ = update.send(Update::Resuming(id, resume)).await;
+
+        let accepted = time::timeout(RESUME_TIMEOUT, async {
+            loop {
+                match action.next().await {
+                    Some(Action::Accepted { position }) => return Some(position),
+                    Some(_) => {}
+                    None => return None,
+                }
+            }
+        })
+        .await;
+
+        accepted.ok().flatten().unwrap_or_default().min(partial)
+    } else {
+        0
+    };
+
     let (host, port, filename, size, reverse) = match dcc_send {
         dcc::Send::Direct {
             host,
@@ -239,18 +316,24 @@ async fn receive(
             ..
         } => {
             let server = server.ok_or(Error::ReverseReceiveNoServerConfig)?;
+            let public_address = server.public_address().await?;
 
             let _ = update.send(Update::Queued(id)).await;
 
-            let Some(Action::PortAvailable { port }) = action.next().await else {
-                unreachable!();
+            let port = loop {
+                match action.next().await {
+                    Some(Action::PortAvailable { port }) => break port,
+                    // Accepted after we gave up on resuming
+                    Some(_) => {}
+                    None => return Ok(()),
+                }
             };
 
             let _ = server_handle
                 .send(
                     dcc::Send::Reverse {
                         filename: filename.clone(),
-                        host: server.public_address,
+                        host: public_address,
                         port: Some(port),
                         size,
                         token,
@@ -292,10 +375,24 @@ async fn receive(
         .await?
     };
 
-    let mut file = File::create(&save_to).await?;
     let mut hasher = Sha256::new();
 
-    let mut transferred = 0;
+    let mut file = if position > 0 {
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&save_to)
+            .await?;
+
+        file.set_len(position).await?;
+        hash_prefix(&mut file, position, &mut hasher).await?;
+
+        file
+    } else {
+        File::create(&save_to).await?
+    };
+
+    let mut transferred = position;
     let mut last_progress = started_at;
 
     while transferred < size {
@@ -313,6 +410,9 @@ async fn receive(
             let ack = Bytes::from_iter(((transferred & 0xFFFFFFFF) as u32).to_be_bytes());
             let _ = connection.send(ack).await;
 
+            // Slows the sender down as we stop reading
+            throttle.wait(bytes.len()).await;
+
             // Send progress at 60fps
             if last_progress.elapsed() >= Duration::from_millis(16) {
                 let _ = update
@@ -335,7 +435,7 @@ async fn receive(
         .send(command!(
             "PRIVMSG",
             remote_user.to_string(),
-            format!("Finished receiving \"{filename}\", sha256: {sha256}")
+            finished_message(Direction::Received, &filename, &sha256)
         ))
         .await;
 
@@ -344,6 +444,7 @@ async fn receive(
             id,
             elapsed: started_at.elapsed(),
             sha256,
+            expected: sidecar_checksum(&save_to).await,
         })
         .await;
 
@@ -362,10 +463,14 @@ async fn send(
     server: Option<Server>,
     timeout: Duration,
     proxy: Option<config::Proxy>,
+    throttle: Throttle,
 ) -> Result<(), Error> {
     let mut file = File::open(path).await?;
     let size = file.metadata().await?.len();
 
+    // Set once the remote accepts to resume
+    let mut position = 0;
+
     let _ = update.send(Update::Metadata(id, size)).await;
 
     let mut connection = if reverse {
@@ -380,17 +485,44 @@ async fn send(
                     host,
                     port: None,
                     size,
-                    token,
+                    token: token.clone(),
                 }
                 .encode(&remote_user),
             )
             .await;
 
-        let Some(Action::ReverseConfirmed { host, port }) = time::timeout(timeout, action.next())
-            .await
-            .map_err(|_| Error::TimeoutPassive)?
-        else {
-            unreachable!();
+        // Remote may ask to resume before confirming
+        let confirmed = time::timeout(timeout, async {
+            loop {
+                match action.next().await {
+                    Some(Action::ReverseConfirmed { host, port }) => return Some((host, port)),
+                    Some(Action::Resume {
+                        position: requested,
+                    }) if requested < size => {
+                        let _ = server_handle
+                            .send(
+                                dcc::Resume {
+                                    filename: sanitized_filename.clone(),
+                                    port: None,
+                                    position: requested,
+                                    token: Some(token.clone()),
+                                }
+                                .accept(&remote_user),
+                            )
+                            .await;
+
+                        position = requested;
+                    }
+                    Some(_) => {}
+                    None => return None,
+                }
+            }
+        })
+        .await
+        .map_err(|_| Error::TimeoutPassive)?;
+
+        let Some((host, port)) = confirmed else {
+            return Ok(());
         };
 
         let _ = update.send(Update::Ready(id)).await;
@@ -407,6 +539,7 @@ async fn send(
         .await?
     } else {
         let server = server.ok_or(Error::NonPassiveSendNoServerConfig)?;
+        let public_address = server.public_address().await?;
 
         let _ = update.send(Update::Queued(id)).await;
 
@@ -418,7 +551,7 @@ async fn send(
             .send(
                 dcc::Send::Direct {
                     filename: sanitized_filename.clone(),
-                    host: server.public_address,
+                    host: public_address,
                     port,
                     size,
                 }
@@ -428,7 +561,7 @@ async fn send(
 
         let _ = update.send(Update::Ready(id)).await;
 
-        time::timeout(
+        let mut listen = std::pin::pin!(time::timeout(
             timeout,
             Connection::listen_and_accept(
                 server.bind_address,
@@ -436,9 +569,38 @@ async fn send(
                 connection::Security::Unsecured,
                 BytesCodec::new(),
             ),
-        )
-        .await
-        .map_err(|_| Error::TimeoutConnection)??
+        ));
+
+        // Remote may ask to resume before connecting
+        loop {
+            match future::select(&mut listen, std::pin::pin!(action.next())).await {
+                Either::Left((connection, _)) => {
+                    break connection.map_err(|_| Error::TimeoutConnection)??;
+                }
+                Either::Right((
+                    Some(Action::Resume {
+                        position: requested,
+                    }),
+                    _,
+                )) if requested < size => {
+                    let _ = server_handle
+                        .send(
+                            dcc::Resume {
+                                filename: sanitized_filename.clone(),
+                                port: Some(port),
+                                position: requested,
+                                token: None,
+                            }
+                            .accept(&remote_user),
+                        )
+                        .await;
+
+                    position = requested;
+                }
+                Either::Right((Some(_), _)) => {}
+                Either::Right((None, _)) => return Ok(()),
+            }
+        }
     };
 
     let started_at = Instant::now();
@@ -446,7 +608,9 @@ async fn send(
     let mut buffer = BytesMut::with_capacity(BUFFER_SIZE);
     let mut hasher = Sha256::new();
 
-    let mut transferred = 0;
+    hash_prefix(&mut file, position, &mut hasher).await?;
+
+    let mut transferred = position;
     let mut last_progress = started_at;
 
     while transferred < size {
@@ -463,6 +627,8 @@ async fn send(
 
         buffer.reserve(BUFFER_SIZE);
 
+        throttle.wait(n).await;
+
         // Send progress at 60fps
         if last_progress.elapsed() >= Duration::from_millis(16) {
             let _ = update
@@ -497,7 +663,7 @@ async fn send(
         .send(command!(
             "PRIVMSG",
             remote_user.to_string(),
-            format!("Finished sending \"{sanitized_filename}\", sha256: {sha256}")
+            finished_message(Direction::Sent, &sanitized_filename, &sha256)
         ))
         .await;
 
@@ -506,18 +672,53 @@ async fn send(
             id,
             elapsed: started_at.elapsed(),
             sha256,
+            expected: None,
         })
         .await;
 
     Ok(())
 }
 
+/// Checksum in a `.sha256` file saved next to `path`, as written by `sha256sum`.
+async fn sidecar_checksum(path: &Path) -> Option<String> {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".sha256");
+
+    let contents = fs::read_to_string(sidecar).await.ok()?;
+
+    contents
+        .split_whitespace()
+        .next()
+        .filter(|sha256| is_sha256(sha256))
+        .map(str::to_lowercase)
+}
+
+/// Hashes the first `len` bytes of `file`, leaving it positioned right after them.
+async fn hash_prefix(file: &mut File, len: u64, hasher: &mut Sha256) -> io::Result<()> {
+    let mut prefix = file.take(len);
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let n = prefix.read(&mut buffer).await?;
+
+        if n == 0 {
+            return Ok(());
+        }
+
+        hasher.update(&buffer[..n]);
+    }
+}
+
 #[derive(Debug, Error)]
 enum Error {
-    #[error("sender requested passive send but [file_transfer.server] is not configured")]
+    #[error(
+        "sender requested passive send but neither [dcc] nor [file_transfer.server] is configured"
+    )]
     ReverseReceiveNoServerConfig,
-    #[error("[file_transfer.server] must be configured to send a file when passive is disabled")]
+    #[error("[dcc] or [file_transfer.server] must be configured to send a file when passive is disabled")]
     NonPassiveSendNoServerConfig,
+    #[error("couldn't discover our public address, set `public_address` in [dcc]")]
+    NoPublicAddress,
     #[error("connection error: {0}")]
     Connection(#[from] connection::Error),
     #[error("io error: {0}")]
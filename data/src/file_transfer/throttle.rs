@@ -0,0 +1,110 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::time;
+
+const BYTES_PER_KIBIBYTE: f64 = 1024.0;
+
+/// Max transfer rate, shared with running transfers so it can be changed while they run.
+#[derive(Debug, Clone)]
+pub struct Limit(Arc<Mutex<Bucket>>);
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes per second, unlimited if not set
+    rate: Option<f64>,
+    /// Bytes that can be transferred without waiting, negative when over the limit
+    available: f64,
+    updated: Instant,
+}
+
+impl Limit {
+    /// Limits the rate to `kibibytes` per second, or nothing if not set.
+    pub fn new(kibibytes: Option<u64>) -> Self {
+        let limit = Self(Arc::new(Mutex::new(Bucket {
+            rate: None,
+            available: 0.0,
+            updated: Instant::now(),
+        })));
+        limit.set(kibibytes);
+        limit
+    }
+
+    pub fn set(&self, kibibytes: Option<u64>) {
+        let mut bucket = self.0.lock().unwrap();
+
+        let rate = kibibytes
+            .filter(|kibibytes| *kibibytes > 0)
+            .map(|kibibytes| kibibytes as f64 * BYTES_PER_KIBIBYTE);
+
+        if bucket.rate.is_none() {
+            bucket.available = 0.0;
+            bucket.updated = Instant::now();
+        }
+        if let Some(rate) = rate {
+            bucket.available = bucket.available.min(rate);
+        }
+
+        bucket.rate = rate;
+    }
+
+    /// Takes `bytes` transferred from the limit, returning how long to wait
+    /// before transferring more.
+    fn take(&self, bytes: usize) -> Duration {
+        let mut bucket = self.0.lock().unwrap();
+
+        let Some(rate) = bucket.rate else {
+            return Duration::ZERO;
+        };
+
+        // Allow bursts of up to a second
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * rate;
+        bucket.available = (bucket.available + refill).min(rate) - bytes as f64;
+        bucket.updated = now;
+
+        if bucket.available < 0.0 {
+            Duration::from_secs_f64(-bucket.available / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// Limits of a single transfer and of all transfers in the same direction.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    pub transfer: Limit,
+    pub global: Limit,
+}
+
+impl Throttle {
+    /// Waits until transferring more keeps within both limits.
+    pub async fn wait(&self, bytes: usize) {
+        let delay = self.transfer.take(bytes).max(self.global.take(bytes));
+
+        if !delay.is_zero() {
+            time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn take() {
+        let limit = Limit::new(Some(1));
+
+        let delay = limit.take(512);
+        assert!(delay > Duration::from_millis(400) && delay <= Duration::from_millis(500));
+        assert!(limit.take(512) > Duration::from_millis(900));
+
+        limit.set(None);
+        assert_eq!(limit.take(usize::MAX), Duration::ZERO);
+
+        limit.set(Some(2));
+        assert!(limit.take(1024) > Duration::from_millis(400));
+    }
+}
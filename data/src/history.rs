@@ -1,20 +1,35 @@
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
 use std::time::Duration;
 use std::{fmt, io};
 
-use chrono::{DateTime, Utc};
-use futures::future::BoxFuture;
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, Shared};
 use futures::{Future, FutureExt};
 use irc::proto;
+use once_cell::sync::Lazy;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::value::RawValue;
 use tokio::fs;
 use tokio::time::Instant;
 
 use crate::user::Nick;
 use crate::{buffer, compression, environment, message, Buffer, Message, Server};
 
+pub use self::link::Link;
 pub use self::manager::{Manager, Resource};
 pub use self::metadata::{Metadata, ReadMarker};
 
+pub mod backfill;
+pub mod compaction;
+pub mod encryption;
+pub mod export;
+pub mod import;
+pub mod link;
 pub mod manager;
 pub mod metadata;
 
@@ -23,10 +38,16 @@ pub mod metadata;
 const MAX_MESSAGES: usize = 10_000;
 /// # messages to tuncate after hitting [`MAX_MESSAGES`]
 const TRUNC_COUNT: usize = 500;
+/// # messages decoded at a time when opening a buffer or scrolling back
+const PAGE_SIZE: usize = 500;
+/// Max # messages kept in memory for an open buffer, older ones are left on disk
+const MAX_LOADED: usize = 2_000;
 /// Duration to wait after receiving last message before flushing
 const FLUSH_AFTER_LAST_RECEIVED: Duration = Duration::from_secs(5);
+/// How far back from the latest message to look for one played back again
+const DUPLICATE_WINDOW: TimeDelta = TimeDelta::minutes(1);
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Kind {
     Server(Server),
     Channel(Server, String),
@@ -116,39 +137,140 @@ impl From<Kind> for Buffer {
 #[derive(Debug)]
 pub struct Loaded {
     pub messages: Vec<Message>,
+    /// # messages saved before `messages`, left on disk
+    pub spilled: usize,
     pub metadata: Metadata,
 }
 
+/// Reads the whole history of `kind`.
 pub async fn load(kind: Kind) -> Result<Loaded, Error> {
     let path = path(&kind).await?;
 
-    let messages = read_all(&path).await.unwrap_or_default();
+    let messages = match read_raw(&path).await {
+        Ok(raw) => decode(&raw),
+        // Don't treat history we can't decrypt as empty, it'd be overwritten
+        Err(error) if error.is_encryption() => return Err(error),
+        Err(_) => vec![],
+    };
     let metadata = metadata::load(kind).await.unwrap_or_default();
 
-    Ok(Loaded { messages, metadata })
+    Ok(Loaded {
+        messages,
+        spilled: 0,
+        metadata,
+    })
 }
 
+/// Reads the history of `kind`, only decoding its latest page of messages.
+pub async fn load_recent(kind: Kind) -> Result<Loaded, Error> {
+    let path = path(&kind).await?;
+
+    let raw = match read_raw(&path).await {
+        Ok(raw) => raw,
+        // Don't treat history we can't decrypt as empty, it'd be overwritten
+        Err(error) if error.is_encryption() => return Err(error),
+        Err(_) => vec![],
+    };
+    let spilled = raw.len().saturating_sub(PAGE_SIZE);
+    let messages = decode(&raw[spilled..]);
+    let metadata = metadata::load(kind).await.unwrap_or_default();
+
+    Ok(Loaded {
+        messages,
+        spilled,
+        metadata,
+    })
+}
+
+/// Messages read back from the part of a history left on disk.
+#[derive(Debug)]
+pub struct Spilled {
+    /// Their position among the messages saved
+    pub range: Range<usize>,
+    pub messages: Vec<Message>,
+}
+
+/// Reads the messages in `range` of those saved for `kind`, once the writes
+/// started before are done.
+pub fn load_spilled(
+    kind: Kind,
+    range: Range<usize>,
+) -> impl Future<Output = Result<Spilled, Error>> {
+    in_order(&kind.clone(), async move {
+        let path = path(&kind).await?;
+        let raw = read_raw(&path).await?;
+
+        let messages = decode(&raw[range.start.min(raw.len())..range.end.min(raw.len())]);
+
+        Ok(Spilled { range, messages })
+    })
+}
+
+/// Saves `messages` after the `spilled` ones already saved for `kind`, which
+/// are kept as they are.
 pub async fn overwrite(
     kind: &Kind,
+    spilled: Range<usize>,
     messages: &[Message],
     read_marker: Option<ReadMarker>,
 ) -> Result<(), Error> {
-    if messages.is_empty() {
+    let path = path(kind).await?;
+
+    let raw = if spilled.is_empty() {
+        vec![]
+    } else {
+        let mut raw = read_raw(&path).await?;
+        raw.truncate(spilled.end);
+        raw.drain(..spilled.start.min(raw.len()));
+        raw
+    };
+
+    if messages.is_empty() && raw.is_empty() {
         return metadata::save(kind, messages, read_marker).await;
     }
 
-    let latest = &messages[messages.len().saturating_sub(MAX_MESSAGES)..];
+    let skip = (raw.len() + messages.len()).saturating_sub(MAX_MESSAGES);
+    let spilled = &raw[skip.min(raw.len())..];
+    let latest = &messages[skip.saturating_sub(raw.len())..];
 
-    let path = path(kind).await?;
-    let compressed = compression::compress(&latest)?;
+    let compressed = compression::compress(&Stored { spilled, latest })?;
 
-    fs::write(path, &compressed).await?;
+    // Written aside then renamed, so it's never read half written
+    let written = path.with_extension("gz.tmp");
+    fs::write(&written, encryption::encrypt(compressed)?).await?;
+    fs::rename(&written, &path).await?;
 
     metadata::save(kind, latest, read_marker).await?;
 
     Ok(())
 }
 
+/// Saves `messages` after the `spilled` ones already saved for `kind`, with
+/// `older` messages merged in order among all of them.
+async fn overwrite_with_older(
+    kind: &Kind,
+    spilled: usize,
+    messages: Vec<Message>,
+    older: Vec<Message>,
+    read_marker: Option<ReadMarker>,
+) -> Result<(), Error> {
+    if older.is_empty() {
+        return overwrite(kind, 0..spilled, &messages, read_marker).await;
+    }
+
+    let mut all = if spilled == 0 {
+        vec![]
+    } else {
+        let raw = read_raw(&path(kind).await?).await?;
+        decode(&raw[..spilled.min(raw.len())])
+    };
+    all.extend(messages);
+
+    let (merged, _) = backfill::merge(all, older);
+
+    overwrite(kind, 0..0, &merged, read_marker).await
+}
+
 pub async fn append(
     kind: &Kind,
     messages: Vec<Message>,
@@ -157,16 +279,81 @@ pub async fn append(
     let loaded = load(kind.clone()).await?;
 
     let mut all_messages = loaded.messages;
-    all_messages.extend(messages);
 
-    overwrite(kind, &all_messages, read_marker).await
+    for message in messages {
+        if !is_duplicate(&all_messages, &message) {
+            all_messages.push(message);
+        }
+    }
+
+    overwrite(kind, 0..0, &all_messages, read_marker).await
 }
 
-async fn read_all(path: &PathBuf) -> Result<Vec<Message>, Error> {
-    let bytes = fs::read(path).await?;
+/// Runs `task` once those started before it on the history of `kind` are
+/// done, as they read and rewrite the same file at positions counted before
+/// they started.
+fn in_order<T>(kind: &Kind, task: impl Future<Output = T>) -> impl Future<Output = T> {
+    static TASKS: Lazy<Mutex<HashMap<Kind, Shared<oneshot::Receiver<()>>>>> =
+        Lazy::new(Mutex::default);
+
+    let (done, receiver) = oneshot::channel();
+    let previous = TASKS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(kind.clone(), receiver.shared());
+
+    async move {
+        // Cancelled if the previous task was dropped, nothing left to wait for
+        if let Some(previous) = previous {
+            let _ = previous.await;
+        }
+
+        let output = task.await;
+        let _ = done.send(());
+
+        output
+    }
+}
+
+async fn read_raw(path: &PathBuf) -> Result<Vec<Box<RawValue>>, Error> {
+    let bytes = encryption::decrypt(fs::read(path).await?)?;
     Ok(compression::decompress(&bytes)?)
 }
 
+fn decode(raw: &[Box<RawValue>]) -> Vec<Message> {
+    raw.iter()
+        .filter_map(|raw| match serde_json::from_str(raw.get()) {
+            Ok(message) => Some(message),
+            Err(error) => {
+                log::warn!("failed to decode message from history: {error}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Messages as written to disk, those left on disk never decoded.
+struct Stored<'a> {
+    spilled: &'a [Box<RawValue>],
+    latest: &'a [Message],
+}
+
+impl Serialize for Stored<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.spilled.len() + self.latest.len()))?;
+
+        for raw in self.spilled {
+            seq.serialize_element(raw)?;
+        }
+
+        for message in self.latest {
+            seq.serialize_element(message)?;
+        }
+
+        seq.end()
+    }
+}
+
 pub async fn dir_path() -> Result<PathBuf, Error> {
     let data_dir = environment::data_dir();
 
@@ -203,12 +390,17 @@ pub enum History {
         last_updated_at: Option<Instant>,
         max_triggers_unread: Option<DateTime<Utc>>,
         read_marker: Option<ReadMarker>,
+        unread: Unread,
     },
     Full {
         kind: Kind,
         messages: Vec<Message>,
+        /// # messages saved before `messages`, left on disk
+        spilled: usize,
         last_updated_at: Option<Instant>,
         read_marker: Option<ReadMarker>,
+        /// Latest message when the buffer last lost focus
+        seen: Option<ReadMarker>,
     },
 }
 
@@ -220,6 +412,7 @@ impl History {
             last_updated_at: None,
             max_triggers_unread: None,
             read_marker: None,
+            unread: Unread::default(),
         }
     }
 
@@ -227,11 +420,13 @@ impl History {
         if let Self::Partial {
             max_triggers_unread,
             read_marker,
+            unread,
             ..
         } = self
         {
             *read_marker = (*read_marker).max(metadata.read_marker);
             *max_triggers_unread = (*max_triggers_unread).max(metadata.last_triggers_unread);
+            unread.read(*read_marker);
         }
     }
 
@@ -255,14 +450,27 @@ impl History {
         }
     }
 
-    fn add_message(&mut self, message: Message) {
+    /// Returns whether the message was added, rather than a duplicate.
+    fn add_message(&mut self, message: Message) -> bool {
+        let (History::Partial { messages, .. } | History::Full { messages, .. }) = self;
+
+        if is_duplicate(messages, &message) {
+            return false;
+        }
+
         if message.triggers_unread() {
             if let History::Partial {
                 max_triggers_unread,
+                read_marker,
+                unread,
                 ..
             } = self
             {
                 *max_triggers_unread = (*max_triggers_unread).max(Some(message.server_time));
+
+                if is_after(message.server_time, *read_marker) {
+                    unread.messages.push(message.server_time);
+                }
             }
         }
 
@@ -282,9 +490,17 @@ impl History {
                 messages.push(message);
             }
         }
+
+        true
     }
 
-    fn flush(&mut self, now: Instant) -> Option<BoxFuture<'static, Result<(), Error>>> {
+    /// Writes the messages received since the last flush, unless the history
+    /// isn't `logged`.
+    fn flush(
+        &mut self,
+        now: Instant,
+        logged: bool,
+    ) -> Option<BoxFuture<'static, Result<(), Error>>> {
         match self {
             History::Partial {
                 kind,
@@ -303,9 +519,12 @@ impl History {
 
                         *last_updated_at = None;
 
-                        return Some(
-                            async move { append(&kind, messages, read_marker).await }.boxed(),
-                        );
+                        return logged.then(|| {
+                            in_order(&kind.clone(), async move {
+                                append(&kind, messages, read_marker).await
+                            })
+                            .boxed()
+                        });
                     }
                 }
 
@@ -314,6 +533,7 @@ impl History {
             History::Full {
                 kind,
                 messages,
+                spilled,
                 last_updated_at,
                 read_marker,
                 ..
@@ -326,14 +546,33 @@ impl History {
                         let read_marker = *read_marker;
                         *last_updated_at = None;
 
-                        if messages.len() > MAX_MESSAGES {
-                            messages.drain(0..messages.len() - (MAX_MESSAGES - TRUNC_COUNT));
+                        let mut kept = 0..*spilled;
+                        let total = *spilled + messages.len();
+
+                        if total > MAX_MESSAGES {
+                            let excess = total - (MAX_MESSAGES - TRUNC_COUNT);
+
+                            kept.start = excess.min(*spilled);
+                            messages.drain(0..excess - kept.start);
                         }
 
-                        let messages = messages.clone();
+                        let written = messages.clone();
+
+                        if !logged {
+                            return None;
+                        }
+
+                        // Once written, the oldest messages can be dropped
+                        // from memory and read back from disk when needed
+                        let spill = messages.len().saturating_sub(MAX_LOADED);
+                        messages.drain(0..spill);
+                        *spilled = kept.len() + spill;
 
                         return Some(
-                            async move { overwrite(&kind, &messages, read_marker).await }.boxed(),
+                            in_order(&kind.clone(), async move {
+                                overwrite(&kind, kept, &written, read_marker).await
+                            })
+                            .boxed(),
                         );
                     }
                 }
@@ -343,17 +582,25 @@ impl History {
         }
     }
 
-    fn make_partial(&mut self) -> Option<impl Future<Output = Result<Option<ReadMarker>, Error>>> {
+    /// Keeps only the metadata of a full history, saving its messages along
+    /// with the `older` ones held for it.
+    fn make_partial(
+        &mut self,
+        logged: bool,
+        older: Vec<Message>,
+    ) -> Option<impl Future<Output = Result<Option<ReadMarker>, Error>>> {
         match self {
             History::Partial { .. } => None,
             History::Full {
                 kind,
                 messages,
+                spilled,
                 read_marker,
                 ..
             } => {
                 let kind = kind.clone();
                 let messages = std::mem::take(messages);
+                let spilled = *spilled;
 
                 let read_marker = ReadMarker::latest(&messages).max(*read_marker);
                 let max_triggers_unread = metadata::latest_triggers_unread(&messages);
@@ -364,42 +611,61 @@ impl History {
                     last_updated_at: None,
                     read_marker,
                     max_triggers_unread,
+                    unread: Unread::default(),
                 };
 
-                Some(async move {
-                    overwrite(&kind, &messages, read_marker)
-                        .await
-                        .map(|_| read_marker)
-                })
+                Some(in_order(&kind.clone(), async move {
+                    if logged {
+                        overwrite_with_older(&kind, spilled, messages, older, read_marker).await?;
+                    }
+
+                    Ok(read_marker)
+                }))
             }
         }
     }
 
-    async fn close(self) -> Result<Option<ReadMarker>, Error> {
-        match self {
-            History::Partial {
-                kind,
-                messages,
-                read_marker,
-                ..
-            } => {
-                append(&kind, messages, read_marker).await?;
+    /// Saves the history, along with the `older` messages held for a full one.
+    fn close(
+        self,
+        logged: bool,
+        older: Vec<Message>,
+    ) -> impl Future<Output = Result<Option<ReadMarker>, Error>> {
+        let kind = match &self {
+            History::Partial { kind, .. } | History::Full { kind, .. } => kind.clone(),
+        };
 
-                Ok(None)
-            }
-            History::Full {
-                kind,
-                messages,
-                read_marker,
-                ..
-            } => {
-                let read_marker = ReadMarker::latest(&messages).max(read_marker);
+        in_order(&kind, async move {
+            match self {
+                History::Partial {
+                    kind,
+                    messages,
+                    read_marker,
+                    ..
+                } => {
+                    if logged {
+                        append(&kind, messages, read_marker).await?;
+                    }
+
+                    Ok(None)
+                }
+                History::Full {
+                    kind,
+                    messages,
+                    spilled,
+                    read_marker,
+                    ..
+                } => {
+                    let read_marker = ReadMarker::latest(&messages).max(read_marker);
 
-                overwrite(&kind, &messages, read_marker).await?;
+                    if logged {
+                        overwrite_with_older(&kind, spilled, messages, older, read_marker).await?;
+                    }
 
-                Ok(read_marker)
+                    Ok(read_marker)
+                }
             }
-        }
+        })
     }
 
     pub fn update_read_marker(&mut self, read_marker: ReadMarker) {
@@ -409,6 +675,37 @@ impl History {
         };
 
         *stored = (*stored).max(Some(read_marker));
+
+        if let History::Partial {
+            read_marker,
+            unread,
+            ..
+        } = self
+        {
+            unread.read(*read_marker);
+        }
+    }
+
+    /// Counts a highlight sent at `server_time`, unless the buffer is open or
+    /// already read past it.
+    fn add_highlight(&mut self, server_time: DateTime<Utc>) {
+        if let History::Partial {
+            read_marker,
+            unread,
+            ..
+        } = self
+        {
+            if is_after(server_time, *read_marker) {
+                unread.highlights.push(server_time);
+            }
+        }
+    }
+
+    fn unread(&self) -> Option<&Unread> {
+        match self {
+            History::Partial { unread, .. } => Some(unread),
+            History::Full { .. } => None,
+        }
     }
 
     pub fn read_marker(&self) -> Option<ReadMarker> {
@@ -420,13 +717,72 @@ impl History {
     }
 }
 
+/// Messages and highlights received in a buffer that isn't open, since it
+/// was last read.
+#[derive(Debug, Clone, Default)]
+pub struct Unread {
+    messages: Vec<DateTime<Utc>>,
+    highlights: Vec<DateTime<Utc>>,
+}
+
+impl Unread {
+    pub fn messages(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn highlights(&self) -> usize {
+        self.highlights.len()
+    }
+
+    fn first_highlight(&self) -> Option<DateTime<Utc>> {
+        self.highlights.iter().min().copied()
+    }
+
+    fn read(&mut self, read_marker: Option<ReadMarker>) {
+        self.messages
+            .retain(|server_time| is_after(*server_time, read_marker));
+        self.highlights
+            .retain(|server_time| is_after(*server_time, read_marker));
+    }
+}
+
+fn is_after(server_time: DateTime<Utc>, read_marker: Option<ReadMarker>) -> bool {
+    read_marker.is_none_or(|read_marker| server_time > read_marker.date_time())
+}
+
+/// Whether `message` is already in `messages`, such as when a bouncer plays
+/// back messages logged before reconnecting.
+fn is_duplicate(messages: &[Message], message: &Message) -> bool {
+    messages
+        .iter()
+        .rev()
+        .take_while(|existing| existing.server_time + DUPLICATE_WINDOW >= message.server_time)
+        .any(|existing| match (&existing.id, &message.id) {
+            (Some(existing), Some(id)) => existing == id,
+            // Without a msgid, the same sender and text at the same time. Server
+            // messages such as the MOTD can repeat lines in a single burst.
+            _ => {
+                matches!(
+                    message.target.source(),
+                    message::Source::User(_) | message::Source::Action
+                ) && existing.server_time == message.server_time
+                    && existing.target.source() == message.target.source()
+                    && existing.content.text() == message.content.text()
+            }
+        })
+}
+
 #[derive(Debug)]
 pub struct View<'a> {
     pub total: usize,
+    /// Messages newer than those in the view, left out by its limit
+    pub newer: usize,
     pub old_messages: Vec<&'a Message>,
     pub new_messages: Vec<&'a Message>,
     pub max_nick_chars: Option<usize>,
     pub max_prefix_chars: Option<usize>,
+    /// How many times messages were repeated, collapsed into them
+    pub repeats: HashMap<message::Hash, usize>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -437,4 +793,21 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
+    #[error("history is encrypted, but no passphrase is set")]
+    MissingPassphrase,
+    #[error("wrong history passphrase")]
+    WrongPassphrase,
+    #[error("history could not be encrypted")]
+    Encryption,
+    #[error("history could not be decrypted")]
+    Decryption,
+}
+
+impl Error {
+    fn is_encryption(&self) -> bool {
+        matches!(
+            self,
+            Error::MissingPassphrase | Error::WrongPassphrase | Error::Decryption
+        )
+    }
 }
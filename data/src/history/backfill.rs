@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+
+use crate::Message;
+
+/// Adds messages fetched from the server to the `existing` ones, skipping any
+/// already in history, matched by msgid or by server time and text.
+pub fn merge(mut existing: Vec<Message>, backfilled: Vec<Message>) -> (Vec<Message>, usize) {
+    let ids = existing
+        .iter()
+        .filter_map(|message| message.id.as_deref())
+        .collect::<HashSet<_>>();
+    let seen = existing.iter().map(key).collect::<HashSet<_>>();
+
+    let mut backfilled = backfilled
+        .into_iter()
+        .filter(|message| {
+            !message.id.as_deref().is_some_and(|id| ids.contains(id))
+                && !seen.contains(&key(message))
+        })
+        .collect::<Vec<_>>();
+
+    // Messages in a batch share a time of receipt, order them as they were sent
+    backfilled.sort_by_key(|message| message.server_time);
+
+    let count = backfilled.len();
+
+    // Requested before the loaded history or within a gap, so they all belong together
+    let index = backfilled.first().map_or(0, |first| {
+        existing.partition_point(|message| message.server_time <= first.server_time)
+    });
+    existing.splice(index..index, backfilled);
+
+    (existing, count)
+}
+
+fn key(message: &Message) -> (DateTime<Utc>, String) {
+    (message.server_time, message.content.text().into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::message::{self, Source, Target};
+
+    fn message(seconds: i64, text: &str, id: Option<&str>) -> Message {
+        let mut message = Message::sent(
+            Target::Channel {
+                channel: "#halloy".to_string(),
+                source: Source::Action,
+                prefixes: vec![],
+            },
+            message::plain(text.to_string()),
+        );
+        message.server_time = Utc.timestamp_opt(seconds, 0).unwrap();
+        message.id = id.map(String::from);
+        message
+    }
+
+    #[test]
+    fn merge_skips_known_messages() {
+        let existing = vec![message(10, "local", Some("b")), message(20, "latest", None)];
+        let backfilled = vec![
+            message(5, "older", Some("a")),
+            message(10, "same id", Some("b")),
+            message(20, "latest", Some("c")),
+            message(1, "oldest", None),
+        ];
+
+        let (merged, count) = merge(existing, backfilled);
+        let texts = merged
+            .iter()
+            .map(|message| message.content.text())
+            .collect::<Vec<_>>();
+
+        assert_eq!(count, 2);
+        assert_eq!(texts, ["oldest", "older", "local", "latest"]);
+
+        let (merged, count) = merge(merged, vec![message(15, "missed", Some("d"))]);
+        let texts = merged
+            .iter()
+            .map(|message| message.content.text())
+            .collect::<Vec<_>>();
+
+        assert_eq!(count, 1);
+        assert_eq!(texts, ["oldest", "older", "local", "missed", "latest"]);
+    }
+}
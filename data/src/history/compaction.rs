@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use tokio::fs;
+
+use crate::compression;
+use crate::history::{self, dir_path, encryption, metadata, Error, Kind};
+
+/// Time since history was last written before it's compressed again, as small as possible
+const COMPACT_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const BYTES_PER_MEGABYTE: u64 = 1_000_000;
+
+/// Disk space used by history.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} MB in {} files",
+            self.bytes as f64 / BYTES_PER_MEGABYTE as f64,
+            self.files
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Compacted {
+    /// # files compressed again
+    pub compressed: usize,
+    pub usage: Usage,
+    pub rotation: Rotation,
+}
+
+/// History to remove to get back under the max size.
+#[derive(Debug, Clone, Default)]
+pub struct Rotation {
+    /// Least recently written first, with their size on disk
+    histories: Vec<(Kind, u64)>,
+    /// Bytes over the max size
+    excess: u64,
+}
+
+impl Rotation {
+    pub fn is_empty(&self) -> bool {
+        self.excess == 0
+    }
+
+    /// The least recently written history to remove, skipping history `in_use`.
+    pub fn select(self, in_use: impl Fn(&Kind) -> bool) -> Vec<Kind> {
+        let mut excess = self.excess;
+
+        self.histories
+            .into_iter()
+            .filter(|(kind, _)| !in_use(kind))
+            .take_while(|(_, bytes)| {
+                let over = excess > 0;
+                excess = excess.saturating_sub(*bytes);
+                over
+            })
+            .map(|(kind, _)| kind)
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct File {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+pub async fn usage() -> Result<Usage, Error> {
+    let mut entries = fs::read_dir(dir_path().await?).await?;
+    let mut usage = Usage::default();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let metadata = entry.metadata().await?;
+
+        if metadata.is_file() {
+            usage.files += 1;
+            usage.bytes += metadata.len();
+        }
+    }
+
+    Ok(usage)
+}
+
+/// Compresses history that hasn't been written to in a while again, and
+/// finds the least recently written history to remove once over `max_size`
+/// megabytes.
+pub async fn compact(max_size: Option<u64>) -> Result<Compacted, Error> {
+    let mut files = history_files().await?;
+    let mut compressed = 0;
+
+    for file in &mut files {
+        if file.modified.elapsed().unwrap_or_default() < COMPACT_AFTER {
+            continue;
+        }
+
+        if let Some(bytes) = recompress(file).await? {
+            file.bytes = bytes;
+            compressed += 1;
+        }
+    }
+
+    let mut rotation = Rotation::default();
+
+    if let Some(max_size) = max_size {
+        let max_bytes = max_size.saturating_mul(BYTES_PER_MEGABYTE);
+        let total = files.iter().map(|file| file.bytes).sum::<u64>();
+
+        if total > max_bytes {
+            // History last saved by older versions has no kind in its
+            // metadata to tell whether it's in use, so it's kept
+            let mut kinds = HashMap::new();
+
+            for kind in metadata::list().await? {
+                kinds.insert(history::path(&kind).await?, kind);
+            }
+
+            files.sort_by_key(|file| file.modified);
+
+            rotation = Rotation {
+                histories: files
+                    .into_iter()
+                    .filter_map(|file| Some((kinds.remove(&file.path)?, file.bytes)))
+                    .collect(),
+                excess: total - max_bytes,
+            };
+        }
+    }
+
+    Ok(Compacted {
+        compressed,
+        usage: usage().await?,
+        rotation,
+    })
+}
+
+/// Removes history along with its metadata, returning the kinds removed.
+pub async fn remove(kinds: Vec<Kind>) -> Result<Vec<Kind>, Error> {
+    for kind in &kinds {
+        history::in_order(kind, remove_files(kind)).await?;
+    }
+
+    Ok(kinds)
+}
+
+async fn remove_files(kind: &Kind) -> Result<(), Error> {
+    fs::remove_file(history::path(kind).await?).await?;
+
+    // History saved by older versions may have no metadata
+    match fs::remove_file(metadata::path(kind).await?).await {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+async fn history_files() -> Result<Vec<File>, Error> {
+    let mut entries = fs::read_dir(dir_path().await?).await?;
+    let mut files = vec![];
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+
+        // Metadata is tiny and kept alongside
+        if !path.to_string_lossy().ends_with(".json.gz") {
+            continue;
+        }
+
+        let metadata = entry.metadata().await?;
+
+        files.push(File {
+            path,
+            bytes: metadata.len(),
+            modified: metadata.modified()?,
+        });
+    }
+
+    Ok(files)
+}
+
+/// Returns the new size of the file, if compressed again.
+async fn recompress(file: &File) -> Result<Option<u64>, Error> {
+    // History we can't read is left untouched
+    let Ok(bytes) = encryption::decrypt(fs::read(&file.path).await?) else {
+        return Ok(None);
+    };
+
+    if compression::is_recompressed(&bytes) {
+        return Ok(None);
+    }
+
+    let bytes = encryption::encrypt(compression::recompress(&bytes)?)?;
+
+    // Written to since we read it
+    if fs::metadata(&file.path).await?.modified()? != file.modified {
+        return Ok(None);
+    }
+
+    fs::write(&file.path, &bytes).await?;
+
+    // Keep when it was last written, so rotation removes the right history
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&file.path)
+        .await?
+        .into_std()
+        .await
+        .set_modified(file.modified)?;
+
+    Ok(Some(bytes.len() as u64))
+}
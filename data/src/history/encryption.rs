@@ -0,0 +1,128 @@
+use std::num::NonZeroU32;
+use std::sync::OnceLock;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::fs;
+
+use crate::history::{dir_path, Error};
+
+/// Marks files written encrypted, anything else is read as is
+const MAGIC: &[u8] = b"halloy-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const ITERATIONS: NonZeroU32 = NonZeroU32::new(600_000).unwrap();
+/// Sealed next to the salt to tell a wrong passphrase apart from a corrupt file
+const CHECK: &[u8] = b"halloy";
+
+static KEY: OnceLock<LessSafeKey> = OnceLock::new();
+
+/// Derives the key used to encrypt history from `passphrase`.
+///
+/// History written afterwards is encrypted, and history written before is
+/// encrypted the next time it is saved.
+pub async fn init(passphrase: &str) -> Result<(), Error> {
+    let path = dir_path().await?.join("encryption");
+
+    let key = match fs::read(&path).await {
+        Ok(bytes) => {
+            let (salt, sealed) = bytes.split_at_checked(SALT_LEN).ok_or(Error::Decryption)?;
+            let key = derive(passphrase, salt)?;
+
+            if open(&key, sealed).ok().as_deref() != Some(CHECK) {
+                return Err(Error::WrongPassphrase);
+            }
+
+            key
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            let mut salt = [0; SALT_LEN];
+            SystemRandom::new()
+                .fill(&mut salt)
+                .map_err(|_| Error::Encryption)?;
+
+            let key = derive(passphrase, &salt)?;
+
+            let mut bytes = salt.to_vec();
+            bytes.extend(seal(&key, CHECK.to_vec())?);
+            fs::write(&path, bytes).await?;
+
+            key
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    let _ = KEY.set(key);
+
+    Ok(())
+}
+
+pub fn encrypt(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let Some(key) = KEY.get() else {
+        return Ok(bytes);
+    };
+
+    let mut encrypted = MAGIC.to_vec();
+    encrypted.extend(seal(key, bytes)?);
+
+    Ok(encrypted)
+}
+
+pub fn decrypt(bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let Some(sealed) = bytes.strip_prefix(MAGIC) else {
+        return Ok(bytes);
+    };
+
+    let key = KEY.get().ok_or(Error::MissingPassphrase)?;
+
+    open(key, sealed)
+}
+
+fn derive(passphrase: &str, salt: &[u8]) -> Result<LessSafeKey, Error> {
+    let mut key = [0; KEY_LEN];
+
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        ITERATIONS,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| Error::Encryption)?;
+
+    Ok(LessSafeKey::new(key))
+}
+
+/// Encrypts `bytes`, prefixed by a random nonce.
+fn seal(key: &LessSafeKey, mut bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| Error::Encryption)?;
+
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::empty(),
+        &mut bytes,
+    )
+    .map_err(|_| Error::Encryption)?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(bytes);
+
+    Ok(sealed)
+}
+
+fn open(key: &LessSafeKey, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let (nonce, sealed) = bytes.split_at_checked(NONCE_LEN).ok_or(Error::Decryption)?;
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| Error::Decryption)?;
+
+    let mut sealed = sealed.to_vec();
+    let opened = key
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .map_err(|_| Error::Decryption)?;
+
+    Ok(opened.to_vec())
+}
@@ -0,0 +1,277 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use tokio::fs;
+
+use crate::appearance::theme;
+use crate::history::{Error, Kind};
+use crate::message::{Content, Fragment, Source};
+use crate::{environment, Message};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Plain text, in the style of irssi logs
+    Text,
+    /// One JSON object per message
+    Json,
+    /// Standalone HTML page, keeping message formatting
+    Html,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Json => "jsonl",
+            Format::Html => "html",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "txt" => Ok(Format::Text),
+            "json" | "jsonl" => Ok(Format::Json),
+            "html" => Ok(Format::Html),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub format: Format,
+    /// First day to include, in local time
+    pub since: Option<NaiveDate>,
+    /// Last day to include, in local time
+    pub until: Option<NaiveDate>,
+}
+
+impl Export {
+    fn includes(&self, message: &Message) -> bool {
+        let date = message.server_time.with_timezone(&Local).date_naive();
+
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+    }
+}
+
+/// Writes the messages of `kind` to a new file in the exports directory,
+/// returning its path.
+pub async fn write(kind: &Kind, export: &Export, messages: &[Message]) -> Result<PathBuf, Error> {
+    let messages = messages
+        .iter()
+        .filter(|message| export.includes(message))
+        .collect::<Vec<_>>();
+
+    let contents = match export.format {
+        Format::Text => text(kind, &messages),
+        Format::Json => json(&messages)?,
+        Format::Html => html(kind, &messages),
+    };
+
+    let path = dir_path()
+        .await?
+        .join(file_name(kind, export.format, Local::now()));
+
+    fs::write(&path, contents).await?;
+
+    Ok(path)
+}
+
+pub async fn dir_path() -> Result<PathBuf, Error> {
+    let dir = environment::data_dir().join("exports");
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir).await?;
+    }
+
+    Ok(dir)
+}
+
+fn file_name(kind: &Kind, format: Format, now: DateTime<Local>) -> String {
+    let name = match (kind.server(), kind.target()) {
+        (Some(server), Some(target)) => format!("{server}-{target}"),
+        (Some(server), None) => server.to_string(),
+        _ => kind.to_string(),
+    };
+
+    // Keep file names valid on every platform
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '#' | '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    format!(
+        "{name}-{}.{}",
+        now.format("%Y%m%d-%H%M%S"),
+        format.extension()
+    )
+}
+
+fn local(time: &DateTime<Utc>) -> DateTime<Local> {
+    time.with_timezone(&Local)
+}
+
+fn text(kind: &Kind, messages: &[&Message]) -> String {
+    let mut output = String::new();
+    let mut date = None;
+
+    let _ = writeln!(
+        output,
+        "--- Log opened for {kind} {}",
+        Local::now().format("%a %b %d %H:%M:%S %Y")
+    );
+
+    for message in messages {
+        let time = local(&message.server_time);
+
+        if date != Some(time.date_naive()) {
+            if date.is_some() {
+                let _ = writeln!(output, "--- Day changed {}", time.format("%a %b %d %Y"));
+            }
+            date = Some(time.date_naive());
+        }
+
+        let text = message.content.text();
+        let timestamp = time.format("%H:%M:%S");
+
+        let _ = match message.target.source() {
+            Source::User(user) => writeln!(output, "{timestamp} <{}> {text}", user.nickname()),
+            Source::Action => writeln!(output, "{timestamp}  * {text}"),
+            Source::Server(_) | Source::Internal(_) => {
+                writeln!(output, "{timestamp} -!- {text}")
+            }
+        };
+    }
+
+    output
+}
+
+fn json(messages: &[&Message]) -> Result<String, Error> {
+    let mut output = String::new();
+
+    for message in messages {
+        let (kind, nick) = match message.target.source() {
+            Source::User(user) => ("message", Some(user.nickname().to_string())),
+            Source::Action => ("action", None),
+            Source::Server(_) => ("server", None),
+            Source::Internal(_) => ("internal", None),
+        };
+
+        let line = serde_json::to_string(&serde_json::json!({
+            "time": message.server_time,
+            "kind": kind,
+            "nick": nick,
+            "text": message.content.text(),
+        }))?;
+
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+fn html(kind: &Kind, messages: &[&Message]) -> String {
+    let mut output = String::new();
+    let title = escape(&kind.to_string());
+
+    let _ = writeln!(
+        output,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n\
+         body {{ background: #1e1e1e; color: #e0e0e0; font-family: monospace; }}\n\
+         .time {{ color: #808080; }}\n\
+         .nick {{ font-weight: bold; }}\n\
+         .server {{ color: #a0a0a0; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>"
+    );
+
+    for message in messages {
+        let time = local(&message.server_time).format("%Y-%m-%d %H:%M:%S");
+        let content = html_content(&message.content);
+
+        let line = match message.target.source() {
+            Source::User(user) => format!(
+                "<span class=\"nick\">&lt;{}&gt;</span> {content}",
+                escape(user.nickname().as_ref())
+            ),
+            Source::Action => format!("* {content}"),
+            Source::Server(_) | Source::Internal(_) => {
+                format!("<span class=\"server\">-!- {content}</span>")
+            }
+        };
+
+        let _ = writeln!(
+            output,
+            "<div><span class=\"time\">{time}</span> {line}</div>"
+        );
+    }
+
+    output.push_str("</body>\n</html>\n");
+
+    output
+}
+
+fn html_content(content: &Content) -> String {
+    let Content::Fragments(fragments) = content else {
+        return escape(&content.text());
+    };
+
+    fragments
+        .iter()
+        .map(|fragment| match fragment {
+            Fragment::Url(url) => {
+                let url = escape(url.as_str());
+                format!("<a href=\"{url}\">{url}</a>")
+            }
+            Fragment::Formatted { text, formatting } => {
+                let colors = theme::Colors::default();
+                let mut style = String::new();
+
+                if formatting.bold {
+                    style.push_str("font-weight: bold;");
+                }
+                if formatting.italics {
+                    style.push_str("font-style: italic;");
+                }
+                match (formatting.underline, formatting.strikethrough) {
+                    (true, true) => style.push_str("text-decoration: underline line-through;"),
+                    (true, false) => style.push_str("text-decoration: underline;"),
+                    (false, true) => style.push_str("text-decoration: line-through;"),
+                    (false, false) => {}
+                }
+                if formatting.monospace {
+                    style.push_str("font-family: monospace;");
+                }
+                if let Some(fg) = formatting.fg.and_then(|fg| fg.into_iced(&colors)) {
+                    let _ = write!(style, "color: {};", theme::color_to_hex(fg));
+                }
+                if let Some(bg) = formatting.bg.and_then(|bg| bg.into_iced(&colors)) {
+                    let _ = write!(style, "background: {};", theme::color_to_hex(bg));
+                }
+
+                format!("<span style=\"{style}\">{}</span>", escape(text))
+            }
+            fragment => escape(fragment.as_str()),
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,382 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use futures::Future;
+use tokio::fs;
+
+use crate::history::{self, Error, Kind, ReadMarker};
+use crate::message::{self, Direction, Hash, Source, Target};
+use crate::time::Posix;
+use crate::{Message, User};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `2024-01-31 12:00:00\t@nick\ttext`
+    WeeChat,
+    /// `12:00 <@nick> text`, with dates from `--- Day changed` lines
+    Irssi,
+    /// `[12:00:00] <nick> text`, with dates from file names
+    Znc,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "weechat" => Ok(Format::WeeChat),
+            "irssi" => Ok(Format::Irssi),
+            "znc" => Ok(Format::Znc),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Reads the logs at `path`, a file or a directory of files, as messages of `kind`.
+pub async fn read(kind: &Kind, format: Format, path: &Path) -> Result<Vec<Message>, Error> {
+    let mut paths = vec![];
+
+    if fs::metadata(path).await?.is_dir() {
+        let mut entries = fs::read_dir(path).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+
+        // Oldest first for logs rotated by date
+        paths.sort();
+    } else {
+        paths.push(path.to_path_buf());
+    }
+
+    let mut messages = vec![];
+
+    for path in paths {
+        let bytes = fs::read(&path).await?;
+        let contents = String::from_utf8_lossy(&bytes);
+
+        messages.extend(parse(kind, format, &contents, date_from_path(&path)));
+    }
+
+    messages.sort_by_key(|message| message.server_time);
+
+    Ok(messages)
+}
+
+/// How many imported messages weren't in history yet, and how many of those
+/// are kept within the messages history holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Count {
+    pub new: usize,
+    pub kept: usize,
+}
+
+impl Count {
+    /// Counts the `new` messages among the latest `limit` of `merged`.
+    pub fn new(merged: &[Message], new: &HashSet<Hash>, limit: usize) -> Self {
+        let kept = merged[merged.len().saturating_sub(limit)..]
+            .iter()
+            .filter(|message| new.contains(&message.hash))
+            .count();
+
+        Self {
+            new: new.len(),
+            kept,
+        }
+    }
+}
+
+/// Merges imported messages into the history of `kind` saved to disk.
+pub fn write(kind: Kind, messages: Vec<Message>) -> impl Future<Output = Result<Count, Error>> {
+    history::in_order(&kind.clone(), async move {
+        let loaded = history::load(kind.clone()).await?;
+
+        // Imported messages are already read
+        let read_marker = loaded
+            .metadata
+            .read_marker
+            .max(ReadMarker::latest(&messages));
+        let (messages, new) = merge(loaded.messages, messages);
+        let count = Count::new(&messages, &new, history::MAX_MESSAGES);
+
+        history::overwrite(&kind, 0..0, &messages, read_marker).await?;
+
+        Ok(count)
+    })
+}
+
+/// Adds imported messages to `existing` in order, skipping any imported before,
+/// along with the hashes of those added.
+pub fn merge(mut existing: Vec<Message>, imported: Vec<Message>) -> (Vec<Message>, HashSet<Hash>) {
+    let hashes = existing
+        .iter()
+        .map(|message| message.hash)
+        .collect::<HashSet<_>>();
+
+    let mut new = HashSet::new();
+
+    existing.extend(
+        imported
+            .into_iter()
+            .filter(|message| !hashes.contains(&message.hash) && new.insert(message.hash)),
+    );
+    existing.sort_by_key(|message| message.server_time);
+
+    (existing, new)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Entry<'a> {
+    Message(&'a str, &'a str),
+    Action(&'a str),
+    Status(&'a str),
+}
+
+fn parse(kind: &Kind, format: Format, contents: &str, date: Option<NaiveDate>) -> Vec<Message> {
+    let mut date = date;
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (time, entry) = match format {
+                Format::WeeChat => weechat(line)?,
+                Format::Irssi | Format::Znc => {
+                    if let Some(changed) = day_changed(line) {
+                        date = Some(changed);
+                        return None;
+                    }
+
+                    let (time, entry) = timestamped(line)?;
+
+                    (date?.and_time(time), entry)
+                }
+            };
+
+            imported_message(kind, time, entry)
+        })
+        .collect()
+}
+
+fn weechat(line: &str) -> Option<(NaiveDateTime, Entry<'_>)> {
+    let mut split = line.splitn(3, '\t');
+
+    let time = NaiveDateTime::parse_from_str(split.next()?, "%Y-%m-%d %H:%M:%S").ok()?;
+    let prefix = split.next()?.trim();
+    let text = split.next().unwrap_or_default();
+
+    let entry = match prefix {
+        "*" => Entry::Action(text),
+        "" | "-->" | "<--" | "--" | "=!=" => Entry::Status(text),
+        nick => Entry::Message(nick, text),
+    };
+
+    Some((time, entry))
+}
+
+/// `--- Log opened` and `--- Day changed` lines written by irssi.
+fn day_changed(line: &str) -> Option<NaiveDate> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+
+    if line.starts_with("--- Log opened") && words.len() >= 5 {
+        let date = words[words.len() - 5..].join(" ");
+
+        NaiveDateTime::parse_from_str(&date, "%a %b %d %H:%M:%S %Y")
+            .ok()
+            .map(|date_time| date_time.date())
+    } else if line.starts_with("--- Day changed") && words.len() >= 4 {
+        let date = words[words.len() - 4..].join(" ");
+
+        NaiveDate::parse_from_str(&date, "%a %b %d %Y").ok()
+    } else {
+        None
+    }
+}
+
+/// Lines starting with a time, as written by irssi (`12:00`) and ZNC (`[12:00:00]`).
+fn timestamped(line: &str) -> Option<(NaiveTime, Entry<'_>)> {
+    let (time, rest) = line.split_once(' ')?;
+    let time = time.trim_start_matches('[').trim_end_matches(']');
+
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .ok()?;
+
+    let rest = rest.trim_start();
+
+    let entry = if let Some(rest) = rest.strip_prefix('<') {
+        let (nick, text) = rest
+            .split_once("> ")
+            .unwrap_or((rest.trim_end_matches('>'), ""));
+
+        Entry::Message(nick.trim(), text)
+    } else if let Some(text) = rest
+        .strip_prefix("-!- ")
+        .or_else(|| rest.strip_prefix("*** "))
+    {
+        Entry::Status(text)
+    } else if let Some(text) = rest.strip_prefix("* ") {
+        Entry::Action(text)
+    } else {
+        Entry::Status(rest)
+    };
+
+    Some((time, entry))
+}
+
+/// Date in a file name, such as `#halloy_20240131.log` or `2024-01-31.log`.
+fn date_from_path(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_stem()?.to_str()?;
+
+    (0..name.len()).find_map(|start| {
+        let dashed = name
+            .get(start..start + 10)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+
+        dashed.or_else(|| {
+            name.get(start..start + 8)
+                .filter(|date| date.bytes().all(|byte| byte.is_ascii_digit()))
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        })
+    })
+}
+
+fn imported_message(kind: &Kind, time: NaiveDateTime, entry: Entry) -> Option<Message> {
+    // Logs are written in local time
+    let server_time = Local
+        .from_local_datetime(&time)
+        .earliest()?
+        .with_timezone(&Utc);
+
+    let (source, content) = match entry {
+        Entry::Message(nick, text) => (
+            Source::User(User::try_from(nick).ok()?),
+            message::parse_fragments(text.to_string(), &[]),
+        ),
+        Entry::Action(text) => (
+            Source::Action,
+            message::parse_fragments(text.to_string(), &[]),
+        ),
+        Entry::Status(text) => (Source::Server(None), message::plain(text.to_string())),
+    };
+
+    let target = match kind {
+        Kind::Server(_) => Target::Server { source },
+        Kind::Channel(_, channel) => Target::Channel {
+            channel: channel.clone(),
+            source,
+            prefixes: vec![],
+        },
+        Kind::Query(_, nick) => Target::Query {
+            nick: nick.clone(),
+            source,
+        },
+        Kind::Logs | Kind::Highlights => return None,
+    };
+
+    // Derived from the time so importing the same logs again is a no-op
+    let received_at = Posix::from_seconds(server_time.timestamp().max(0) as u64);
+    let hash = Hash::new(&received_at, &content);
+
+    Some(Message {
+        received_at,
+        server_time,
+        direction: Direction::Received,
+        target,
+        content,
+        id: None,
+        hash,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        let time = |s| NaiveTime::parse_from_str(s, "%H:%M:%S").unwrap();
+
+        assert_eq!(
+            timestamped("12:00 <@halloy> hello there"),
+            Some((time("12:00:00"), Entry::Message("@halloy", "hello there")))
+        );
+        assert_eq!(
+            timestamped("12:00 < halloy> hello"),
+            Some((time("12:00:00"), Entry::Message("halloy", "hello")))
+        );
+        assert_eq!(
+            timestamped("12:00  * halloy waves"),
+            Some((time("12:00:00"), Entry::Action("halloy waves")))
+        );
+        assert_eq!(
+            timestamped("12:00 -!- halloy [~h@host] has joined #halloy"),
+            Some((
+                time("12:00:00"),
+                Entry::Status("halloy [~h@host] has joined #halloy")
+            ))
+        );
+        assert_eq!(
+            timestamped("[12:00:01] *** Joins: halloy (~h@host)"),
+            Some((time("12:00:01"), Entry::Status("Joins: halloy (~h@host)")))
+        );
+        assert_eq!(
+            timestamped("[12:00:01] * halloy waves"),
+            Some((time("12:00:01"), Entry::Action("halloy waves")))
+        );
+
+        let (_, entry) = weechat("2024-01-31 12:00:00\t@halloy\thello").unwrap();
+        assert_eq!(entry, Entry::Message("@halloy", "hello"));
+        let (_, entry) = weechat("2024-01-31 12:00:00\t *\thalloy waves").unwrap();
+        assert_eq!(entry, Entry::Action("halloy waves"));
+        let (_, entry) = weechat("2024-01-31 12:00:00\t-->\thalloy has joined").unwrap();
+        assert_eq!(entry, Entry::Status("halloy has joined"));
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31);
+        assert_eq!(day_changed("--- Day changed Wed Jan 31 2024"), date);
+        assert_eq!(day_changed("--- Log opened Wed Jan 31 09:15:00 2024"), date);
+        assert_eq!(
+            date_from_path(Path::new("logs/libera_#halloy_20240131.log")),
+            date
+        );
+        assert_eq!(date_from_path(Path::new("#halloy/2024-01-31.log")), date);
+        assert_eq!(date_from_path(Path::new("#halloy.log")), None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Server;
+
+    #[test]
+    fn count_kept() {
+        let kind = Kind::Channel(Server::from("libera"), "#halloy".to_string());
+        let log = |lines: &[&str]| {
+            let contents = lines
+                .iter()
+                .map(|line| format!("2024-01-31 {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            parse(&kind, Format::WeeChat, &contents, None)
+        };
+
+        let existing = log(&["12:00:02\tnick\tthree", "12:00:03\tnick\tfour"]);
+        let imported = log(&[
+            "12:00:00\tnick\tone",
+            "12:00:01\tnick\ttwo",
+            "12:00:03\tnick\tfour",
+        ]);
+
+        let (merged, new) = merge(existing, imported);
+
+        assert_eq!(merged.len(), 4);
+        assert_eq!(Count::new(&merged, &new, 10), Count { new: 2, kept: 2 });
+        // Only the latest three are kept, dropping the oldest imported one
+        assert_eq!(Count::new(&merged, &new, 3), Count { new: 2, kept: 1 });
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use url::Url;
+
+use crate::buffer;
+use crate::message::Source;
+use crate::user::Nick;
+use crate::Message;
+
+/// A URL sent by a user in a channel or query.
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub url: Url,
+    pub nick: Nick,
+    pub buffer: buffer::Upstream,
+    pub server_time: DateTime<Utc>,
+}
+
+/// URLs of the message, if sent by a user.
+pub fn from_message(buffer: &buffer::Upstream, message: &Message) -> Vec<Link> {
+    let Source::User(user) = message.target.source() else {
+        return vec![];
+    };
+
+    message
+        .content
+        .urls()
+        .map(|url| Link {
+            url: url.clone(),
+            nick: user.nickname().to_owned(),
+            buffer: buffer.clone(),
+            server_time: message.server_time,
+        })
+        .collect()
+}
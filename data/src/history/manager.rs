@@ -1,15 +1,26 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use futures::future::BoxFuture;
 use futures::{future, Future, FutureExt};
 use tokio::time::Instant;
+use url::Url;
 
+use crate::config::buffer::ClearUnreadMarker;
 use crate::history::{self, History};
 use crate::message::{self, Limit};
+use crate::preview::{self, Preview};
 use crate::user::Nick;
-use crate::{buffer, config, input};
-use crate::{server, Config, Input, Server, User};
+use crate::{buffer, client, config, input};
+use crate::{server, Buffer, Config, Input, Server, User};
+
+/// URLs kept for the URL list
+const MAX_LINKS: usize = 1000;
+/// Wait before reading back history that failed to be read, to merge the
+/// messages held for it
+const READ_BACK_RETRY: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Resource {
@@ -44,6 +55,14 @@ pub enum Message {
         Result<Option<history::ReadMarker>, history::Error>,
     ),
     Flushed(history::Kind, Result<(), history::Error>),
+    Exported(history::Kind, Result<PathBuf, history::Error>),
+    Imported(history::Kind, Result<Imported, history::Error>),
+    LoadedOlder(history::Kind, Result<history::Spilled, history::Error>),
+    /// Everything left on disk, read back to merge older messages in order
+    ReadBack(history::Kind, Result<history::Spilled, history::Error>),
+    /// Text reported back in the buffer of `kind`
+    Feedback(history::Kind, String),
+    Previewed(Url, Result<Preview, preview::Error>),
     Exited(
         Vec<(
             history::Kind,
@@ -52,9 +71,20 @@ pub enum Message {
     ),
 }
 
+/// Imported messages, written to disk for a closed buffer, or to merge into
+/// an open one along with the messages it left on disk.
+#[derive(Debug)]
+pub enum Imported {
+    Written(history::import::Count),
+    Loaded(Vec<crate::Message>, Option<history::Spilled>),
+}
+
 pub enum Event {
     Loaded(history::Kind),
     Closed(history::Kind, Option<history::ReadMarker>),
+    Exported(history::Kind, Result<PathBuf, String>),
+    Imported(history::Kind, Result<history::import::Count, String>),
+    Feedback(history::Kind, String),
     Exited(Vec<(history::Kind, Option<history::ReadMarker>)>),
 }
 
@@ -62,6 +92,15 @@ pub enum Event {
 pub struct Manager {
     resources: HashSet<Resource>,
     data: Data,
+    /// When the connection to each server was lost, to mark the gap once restored
+    disconnected_at: HashMap<Server, DateTime<Utc>>,
+    /// URLs sent this session, most recent last
+    links: VecDeque<history::Link>,
+    previews: HashMap<Url, preview::State>,
+    /// Histories with older messages being read back from disk
+    loading_older: HashSet<history::Kind>,
+    /// Channels showing the messages their filters hide
+    revealed: HashSet<history::Kind>,
 }
 
 impl Manager {
@@ -71,7 +110,7 @@ impl Manager {
 
         let added = added.into_iter().map(|resource| {
             async move {
-                history::load(resource.kind.clone())
+                history::load_recent(resource.kind.clone())
                     .map(move |result| Message::LoadFull(resource.kind, result))
                     .await
             }
@@ -134,6 +173,88 @@ impl Manager {
             Message::UpdateReadMarker(kind, read_marker, Err(error)) => {
                 log::warn!("failed to update read marker for {kind} to {read_marker}: {error}");
             }
+            Message::Previewed(url, Ok(preview)) => {
+                log::debug!("fetched preview for {url}");
+                self.previews.insert(url, preview::State::Loaded(preview));
+            }
+            Message::Previewed(url, Err(error)) => {
+                log::debug!("failed to fetch preview for {url}: {error}");
+                self.previews.insert(url, preview::State::Error);
+            }
+            Message::Exported(kind, Ok(path)) => {
+                log::debug!("exported history for {kind} to {}", path.display());
+                return Some(Event::Exported(kind, Ok(path)));
+            }
+            Message::Exported(kind, Err(error)) => {
+                log::warn!("failed to export history for {kind}: {error}");
+                return Some(Event::Exported(kind, Err(error.to_string())));
+            }
+            Message::Imported(kind, Ok(imported)) => {
+                let count = match imported {
+                    Imported::Written(count) => count,
+                    Imported::Loaded(messages, spilled) => {
+                        if let Some(spilled) = spilled {
+                            self.data.restore(&kind, spilled);
+                        }
+                        self.data.import(&kind, messages)
+                    }
+                };
+
+                if count.kept < count.new {
+                    log::warn!(
+                        "{} messages imported into {kind} didn't fit in history",
+                        count.new - count.kept
+                    );
+                }
+                log::debug!("imported {} messages into {kind}", count.kept);
+
+                return Some(Event::Imported(kind, Ok(count)));
+            }
+            Message::Imported(kind, Err(error)) => {
+                log::warn!("failed to import history into {kind}: {error}");
+                return Some(Event::Imported(kind, Err(error.to_string())));
+            }
+            Message::Feedback(kind, text) => {
+                return Some(Event::Feedback(kind, text));
+            }
+            Message::LoadedOlder(kind, Ok(spilled)) => {
+                self.loading_older.remove(&kind);
+
+                // Everything left on disk is being read back, this page included
+                if self.data.held.contains_key(&kind) {
+                    return None;
+                }
+
+                log::debug!(
+                    "read {} older messages back into {kind}",
+                    spilled.messages.len()
+                );
+                self.data.restore(&kind, spilled);
+            }
+            Message::LoadedOlder(kind, Err(error)) => {
+                self.loading_older.remove(&kind);
+                log::warn!("failed to read older messages for {kind}: {error}");
+            }
+            Message::ReadBack(kind, Ok(spilled)) => {
+                // Read again if the history changed since, keeping the
+                // messages held until then
+                if !self.data.restore(&kind, spilled) {
+                    self.data.read_back_retries.insert(kind, Instant::now());
+                    return None;
+                }
+
+                let held = self.data.held.remove(&kind).unwrap_or_default();
+                let count = self.data.backfill(&kind, held);
+
+                log::debug!("merged {count} older messages into {kind}");
+            }
+            Message::ReadBack(kind, Err(error)) => {
+                log::warn!("failed to read back history for {kind}: {error}");
+
+                self.data
+                    .read_back_retries
+                    .insert(kind, Instant::now() + READ_BACK_RETRY);
+            }
             Message::Exited(results) => {
                 let mut output = vec![];
 
@@ -157,25 +278,156 @@ impl Manager {
         None
     }
 
+    /// Stops saving the history of channels with logging turned off in
+    /// their server's config.
+    pub fn update_logging(&mut self, servers: &server::Map) {
+        self.data.unlogged = servers
+            .entries()
+            .flat_map(|entry| {
+                entry
+                    .config
+                    .channels
+                    .sections()
+                    .filter(|(_, settings)| !settings.logging)
+                    .map(|(channel, _)| (entry.server.clone(), channel.to_lowercase()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+
+    /// Hides messages matching the filters of channels in their server's
+    /// config, counting those hidden in the loaded history.
+    pub fn update_filters(&mut self, servers: &server::Map) {
+        self.data.filters = servers
+            .entries()
+            .flat_map(|entry| {
+                entry
+                    .config
+                    .channels
+                    .sections()
+                    .filter(|(_, settings)| !settings.filters.is_empty())
+                    .map(|(channel, settings)| {
+                        (
+                            (entry.server.clone(), channel.to_lowercase()),
+                            settings.filters.clone(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        let kinds = self.data.map.keys().cloned().collect::<Vec<_>>();
+
+        for kind in kinds {
+            self.data.count_hidden(&kind);
+        }
+    }
+
     pub fn tick(&mut self, now: Instant) -> Vec<BoxFuture<'static, Message>> {
-        self.data.flush_all(now)
+        let mut tasks = self.data.retry_read_backs(now);
+        tasks.extend(self.data.flush_all(now));
+
+        tasks
     }
 
     pub fn close(&mut self, kind: history::Kind) -> Option<impl Future<Output = Message>> {
+        let logged = is_logged(&self.data.unlogged, &kind);
         let history = self.data.map.remove(&kind)?;
-
-        Some(history.close().map(|result| Message::Closed(kind, result)))
+        self.data.hidden.remove(&kind);
+        let held = self.data.release(&kind);
+
+        Some(
+            history
+                .close(logged, held)
+                .map(|result| Message::Closed(kind, result)),
+        )
     }
 
     pub fn exit(&mut self) -> impl Future<Output = Message> {
-        let map = std::mem::take(&mut self.data).map;
+        let mut data = std::mem::take(&mut self.data);
+
+        let tasks = std::mem::take(&mut data.map)
+            .into_iter()
+            .map(|(kind, state)| {
+                let logged = is_logged(&data.unlogged, &kind);
+                let held = data.release(&kind);
+
+                state.close(logged, held).map(move |result| (kind, result))
+            })
+            .collect::<Vec<_>>();
+
+        async move { Message::Exited(future::join_all(tasks).await) }
+    }
+
+    /// Writes the full history of `kind`, including messages not yet flushed, to a file.
+    pub fn export(
+        &self,
+        kind: history::Kind,
+        export: history::export::Export,
+    ) -> impl Future<Output = Message> {
+        // Full history holds every message not left on disk
+        let (pending, spilled) = match self.data.map.get(&kind) {
+            Some(History::Full {
+                messages, spilled, ..
+            }) => (messages.clone(), Some(*spilled)),
+            Some(History::Partial { messages, .. }) => (messages.clone(), None),
+            None => (vec![], None),
+        };
 
         async move {
-            let tasks = map
-                .into_iter()
-                .map(|(kind, state)| state.close().map(move |result| (kind, result)));
+            let result = async {
+                let mut messages = match spilled {
+                    Some(0) => vec![],
+                    Some(spilled) => {
+                        history::load_spilled(kind.clone(), 0..spilled)
+                            .await?
+                            .messages
+                    }
+                    None => history::load(kind.clone()).await?.messages,
+                };
+                messages.extend(pending);
+
+                history::export::write(&kind, &export, &messages).await
+            }
+            .await;
 
-            Message::Exited(future::join_all(tasks).await)
+            Message::Exported(kind, result)
+        }
+    }
+
+    /// Imports the logs at `path` into the history of `kind`.
+    pub fn import(
+        &self,
+        kind: history::Kind,
+        format: history::import::Format,
+        path: PathBuf,
+    ) -> impl Future<Output = Message> {
+        // Full history is merged in memory and flushed as usual, along with
+        // the messages left on disk as imported ones can be older
+        let spilled = match self.data.map.get(&kind) {
+            Some(History::Full { spilled, .. }) => Some(*spilled),
+            _ => None,
+        };
+
+        async move {
+            let result = async {
+                let messages = history::import::read(&kind, format, &path).await?;
+
+                let spilled = match spilled {
+                    None => {
+                        return Ok(Imported::Written(
+                            history::import::write(kind.clone(), messages).await?,
+                        ));
+                    }
+                    Some(0) => None,
+                    Some(spilled) => Some(history::load_spilled(kind.clone(), 0..spilled).await?),
+                };
+
+                Ok(Imported::Loaded(messages, spilled))
+            }
+            .await;
+
+            Message::Imported(kind, result)
         }
     }
 
@@ -211,22 +463,108 @@ impl Manager {
         server: &Server,
         message: crate::Message,
     ) -> Option<impl Future<Output = Message>> {
-        history::Kind::from_server_message(server.clone(), &message)
-            .and_then(|kind| self.data.add_message(kind, message))
+        let kind = history::Kind::from_server_message(server.clone(), &message)?;
+
+        if let Some(buffer) = Buffer::from(kind.clone()).upstream() {
+            self.links
+                .extend(history::link::from_message(buffer, &message));
+
+            let excess = self.links.len().saturating_sub(MAX_LINKS);
+            self.links.drain(..excess);
+        }
+
+        self.data.add_message(kind, message)
+    }
+
+    /// URLs sent this session, most recent first.
+    pub fn links(&self) -> impl Iterator<Item = &history::Link> {
+        self.links.iter().rev()
+    }
+
+    /// Fetches previews of URLs sent by a user in the message, unless
+    /// already fetched.
+    pub fn request_previews(
+        &mut self,
+        message: &crate::Message,
+        config: &config::Preview,
+    ) -> Vec<impl Future<Output = Message>> {
+        if !config.enabled || !matches!(message.target.source(), message::Source::User(_)) {
+            return vec![];
+        }
+
+        let urls = message
+            .content
+            .urls()
+            .filter(|url| config.is_allowed(url) && !self.previews.contains_key(*url))
+            .cloned()
+            .collect::<HashSet<_>>();
+
+        urls.into_iter()
+            .map(|url| {
+                self.previews.insert(url.clone(), preview::State::Loading);
+
+                preview::fetch(url.clone(), config.clone())
+                    .map(move |result| Message::Previewed(url, result))
+            })
+            .collect()
+    }
+
+    pub fn preview(&self, url: &Url) -> Option<&Preview> {
+        match self.previews.get(url)? {
+            preview::State::Loaded(preview) => Some(preview),
+            preview::State::Loading | preview::State::Error => None,
+        }
     }
 
     pub fn record_log(
         &mut self,
         record: crate::log::Record,
     ) -> Option<impl Future<Output = Message>> {
-        self.data
-            .add_message(history::Kind::Logs, crate::Message::log(record))
+        let server_time = record.timestamp;
+        // Warnings and errors stand out like highlights until the logs are read
+        let is_warning = record.level <= crate::log::Level::Warn;
+
+        let task = self
+            .data
+            .add_message(history::Kind::Logs, crate::Message::log(record));
+
+        if is_warning {
+            if let Some(history) = self.data.map.get_mut(&history::Kind::Logs) {
+                history.add_highlight(server_time);
+            }
+        }
+
+        task
+    }
+
+    /// Warnings and errors logged since the logs were last read.
+    pub fn unread_log_warnings(&self) -> usize {
+        self.unread(&history::Kind::Logs)
+            .map_or(0, history::Unread::highlights)
     }
 
     pub fn record_highlight(
         &mut self,
         message: crate::Message,
     ) -> Option<impl Future<Output = Message>> {
+        if let message::Target::Highlights {
+            server,
+            channel,
+            query,
+            ..
+        } = &message.target
+        {
+            let kind = if *query {
+                history::Kind::Query(server.clone(), Nick::from(channel.as_str()))
+            } else {
+                history::Kind::Channel(server.clone(), channel.clone())
+            };
+
+            if let Some(history) = self.data.map.get_mut(&kind) {
+                history.add_highlight(message.server_time);
+            }
+        }
+
         self.data.add_message(history::Kind::Highlights, message)
     }
 
@@ -238,6 +576,58 @@ impl Manager {
         self.data.update_read_marker(kind, read_marker)
     }
 
+    /// Adds older messages fetched from the server to the loaded history of `kind`.
+    pub fn backfill(
+        &mut self,
+        kind: &history::Kind,
+        messages: Vec<crate::Message>,
+    ) -> Option<impl Future<Output = Message>> {
+        self.data.merge_older(kind, messages)
+    }
+
+    /// Whether `kind` has older messages left on disk, to read back with
+    /// [`Manager::load_older`] before requesting them from the server.
+    pub fn has_older(&self, kind: &history::Kind) -> bool {
+        matches!(
+            self.data.map.get(kind),
+            Some(History::Full { spilled, .. }) if *spilled > 0
+        )
+    }
+
+    /// Reads the next page of older messages left on disk for `kind` back
+    /// into memory, unless it's already being read.
+    pub fn load_older(&mut self, kind: &history::Kind) -> Option<impl Future<Output = Message>> {
+        let Some(History::Full { spilled, .. }) = self.data.map.get(kind) else {
+            return None;
+        };
+
+        // Everything left on disk is already being read back
+        if *spilled == 0 || self.data.held.contains_key(kind) {
+            return None;
+        }
+
+        if !self.loading_older.insert(kind.clone()) {
+            return None;
+        }
+
+        let range = spilled.saturating_sub(history::PAGE_SIZE)..*spilled;
+        let kind = kind.clone();
+
+        Some(async move {
+            history::load_spilled(kind.clone(), range)
+                .map(move |result| Message::LoadedOlder(kind, result))
+                .await
+        })
+    }
+
+    /// Oldest message loaded for `kind`, to request older messages from the server.
+    pub fn oldest(&self, kind: &history::Kind) -> Option<&crate::Message> {
+        match self.data.map.get(kind)? {
+            History::Full { messages, .. } => messages.first(),
+            History::Partial { .. } => None,
+        }
+    }
+
     pub fn channel_joined(
         &mut self,
         server: Server,
@@ -250,9 +640,28 @@ impl Manager {
         &self,
         kind: &history::Kind,
         limit: Option<Limit>,
-        buffer_config: &config::Buffer,
+        config: &Config,
+        smart_filter: bool,
     ) -> Option<history::View<'_>> {
-        self.data.history_view(kind, limit, buffer_config)
+        let reveal = self.revealed.contains(kind);
+
+        self.data
+            .history_view(kind, limit, config, smart_filter, reveal)
+    }
+
+    /// How many messages of a channel its filters hide, and whether they're
+    /// shown anyway.
+    pub fn filtered(&self, kind: &history::Kind) -> (usize, bool) {
+        let count = self.data.hidden.get(kind).copied().unwrap_or_default();
+
+        (count, self.revealed.contains(kind))
+    }
+
+    /// Shows the messages hidden by the filters of a channel, or hides them again.
+    pub fn toggle_filtered(&mut self, kind: history::Kind) {
+        if !self.revealed.remove(&kind) {
+            self.revealed.insert(kind);
+        }
     }
 
     pub fn get_unique_queries(&self, server: &Server) -> Vec<&Nick> {
@@ -269,6 +678,12 @@ impl Manager {
         queries
     }
 
+    /// Whether history is open or has messages waiting to be saved.
+    pub fn is_in_use(&self, kind: &history::Kind) -> bool {
+        self.data.map.contains_key(kind)
+            || self.resources.contains(&Resource { kind: kind.clone() })
+    }
+
     pub fn has_unread(&self, kind: &history::Kind) -> bool {
         self.data
             .map
@@ -277,6 +692,60 @@ impl Manager {
             .unwrap_or_default()
     }
 
+    /// Unread messages and highlights of `kind`, if it isn't open.
+    pub fn unread(&self, kind: &history::Kind) -> Option<&history::Unread> {
+        self.data.map.get(kind).and_then(History::unread)
+    }
+
+    /// Unread messages and highlights across every server, channel and query.
+    pub fn unread_counts(&self) -> (usize, usize) {
+        self.data
+            .map
+            .iter()
+            .filter(|(kind, _)| !matches!(kind, history::Kind::Logs | history::Kind::Highlights))
+            .filter_map(|(_, history)| history.unread())
+            .fold((0, 0), |(messages, highlights), unread| {
+                (
+                    messages + unread.messages(),
+                    highlights + unread.highlights(),
+                )
+            })
+    }
+
+    /// The buffer with the oldest unread highlight.
+    pub fn next_highlight(&self) -> Option<&history::Kind> {
+        self.data
+            .map
+            .iter()
+            .filter_map(|(kind, history)| Some((kind, history.unread()?.first_highlight()?)))
+            .min_by_key(|(_, first_highlight)| *first_highlight)
+            .map(|(kind, _)| kind)
+    }
+
+    /// When each user last sent a message, among the loaded messages of `kind`.
+    pub fn last_seen(&self, kind: &history::Kind) -> HashMap<Nick, DateTime<Utc>> {
+        let Some(History::Full { messages, .. }) = self.data.map.get(kind) else {
+            return HashMap::new();
+        };
+
+        messages
+            .iter()
+            .filter_map(|message| match message.target.source() {
+                message::Source::User(user) => {
+                    Some((user.nickname().to_owned(), message.server_time))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Remembers the latest message of `kind` as seen, as its buffer loses focus.
+    pub fn unfocused(&mut self, kind: &history::Kind) {
+        if let Some(History::Full { messages, seen, .. }) = self.data.map.get_mut(kind) {
+            *seen = history::ReadMarker::latest(messages).max(*seen);
+        }
+    }
+
     pub fn read_marker(&self, kind: &history::Kind) -> Option<history::ReadMarker> {
         self.data
             .map
@@ -324,9 +793,35 @@ impl Manager {
                 message::broadcast::connection_failed(error, sent_time)
             }
             Broadcast::Disconnected { error } => {
+                self.disconnected_at
+                    .entry(server.clone())
+                    .or_insert(sent_time);
+
                 message::broadcast::disconnected(channels, queries, error, sent_time)
             }
-            Broadcast::Reconnected => message::broadcast::reconnected(channels, queries, sent_time),
+            Broadcast::Reconnected => {
+                let channels = channels.collect::<Vec<_>>();
+                let queries = queries.collect::<Vec<_>>();
+
+                let mut messages = self
+                    .disconnected_at
+                    .remove(server)
+                    .map(|disconnected_at| {
+                        message::broadcast::gap(
+                            channels.clone(),
+                            queries.clone(),
+                            disconnected_at,
+                            sent_time,
+                        )
+                    })
+                    .unwrap_or_default();
+
+                messages.extend(message::broadcast::reconnected(
+                    channels, queries, sent_time,
+                ));
+
+                messages
+            }
             Broadcast::Quit {
                 user,
                 comment,
@@ -376,7 +871,17 @@ impl Manager {
                 inviter,
                 channel,
                 user_channels,
-            } => message::broadcast::invite(inviter, channel, user_channels, sent_time),
+                joining,
+            } => message::broadcast::invite(inviter, channel, user_channels, joining, sent_time),
+            Broadcast::JoinFailed { channel, reason } => {
+                message::broadcast::join_failed(channel, &reason, sent_time)
+            }
+            Broadcast::Rejoin { channel, status } => {
+                message::broadcast::rejoin(channel, status, sent_time)
+            }
+            Broadcast::CtcpSuppressed { nick, command } => {
+                message::broadcast::ctcp_suppressed(&nick, &command, sent_time)
+            }
             Broadcast::ChangeHost {
                 old_user,
                 new_username,
@@ -436,6 +941,18 @@ fn with_limit<'a>(
         Some(Limit::Since(timestamp)) => messages
             .skip_while(|message| message.received_at < timestamp)
             .collect(),
+        Some(Limit::Ending(timestamp, n)) => {
+            let collected = messages.collect::<Vec<_>>();
+            let end = collected
+                .iter()
+                .rposition(|message| message.received_at <= timestamp)
+                .map_or(0, |index| index + 1);
+            collected[end.saturating_sub(n)..end].to_vec()
+        }
+        Some(Limit::Starting(timestamp, n)) => messages
+            .skip_while(|message| message.received_at < timestamp)
+            .take(n)
+            .collect(),
         None => messages.collect(),
     }
 }
@@ -444,6 +961,17 @@ fn with_limit<'a>(
 struct Data {
     map: HashMap<history::Kind, History>,
     input: input::Storage,
+    /// Channels, lowercased, whose history isn't saved to disk
+    unlogged: HashSet<(Server, String)>,
+    /// Messages older than those loaded, held until the ones left on disk are
+    /// read back so they're saved in order
+    held: HashMap<history::Kind, Vec<crate::Message>>,
+    /// When to read back history again, for messages still held
+    read_back_retries: HashMap<history::Kind, Instant>,
+    /// Filters of channels, lowercased, hiding messages
+    filters: HashMap<(Server, String), config::server::Filters>,
+    /// How many loaded messages the filters of each channel hide
+    hidden: HashMap<history::Kind, usize>,
 }
 
 impl Data {
@@ -452,9 +980,12 @@ impl Data {
 
         let history::Loaded {
             mut messages,
+            spilled,
             metadata,
         } = data;
 
+        let loaded = kind.clone();
+
         match self.map.entry(kind.clone()) {
             hash_map::Entry::Occupied(mut entry) => match entry.get_mut() {
                 History::Partial {
@@ -470,16 +1001,20 @@ impl Data {
                     entry.insert(History::Full {
                         kind,
                         messages,
+                        spilled,
                         last_updated_at,
                         read_marker,
+                        seen: None,
                     });
                 }
                 _ => {
                     entry.insert(History::Full {
                         kind,
                         messages,
+                        spilled,
                         last_updated_at: None,
                         read_marker: metadata.read_marker,
+                        seen: None,
                     });
                 }
             },
@@ -487,11 +1022,188 @@ impl Data {
                 entry.insert(History::Full {
                     kind,
                     messages,
+                    spilled,
                     last_updated_at: None,
                     read_marker: metadata.read_marker,
+                    seen: None,
                 });
             }
         }
+
+        self.count_hidden(&loaded);
+    }
+
+    fn filters(&self, kind: &history::Kind) -> Option<&config::server::Filters> {
+        match kind {
+            history::Kind::Channel(server, channel) => {
+                self.filters.get(&(server.clone(), channel.to_lowercase()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Counts the loaded messages of `kind` its filters hide.
+    fn count_hidden(&mut self, kind: &history::Kind) {
+        let count = match (self.filters(kind), self.map.get(kind)) {
+            (Some(filters), Some(History::Full { messages, .. })) => messages
+                .iter()
+                .filter(|message| filters.hides(message))
+                .count(),
+            _ => 0,
+        };
+
+        if count > 0 {
+            self.hidden.insert(kind.clone(), count);
+        } else {
+            self.hidden.remove(kind);
+        }
+    }
+
+    fn import(
+        &mut self,
+        kind: &history::Kind,
+        imported: Vec<crate::Message>,
+    ) -> history::import::Count {
+        let Some(History::Full {
+            messages,
+            last_updated_at,
+            ..
+        }) = self.map.get_mut(kind)
+        else {
+            return history::import::Count::default();
+        };
+
+        let (merged, new) = history::import::merge(std::mem::take(messages), imported);
+
+        // Flushing truncates history further once it's over the limit
+        let limit = if merged.len() > history::MAX_MESSAGES {
+            history::MAX_MESSAGES - history::TRUNC_COUNT
+        } else {
+            merged.len()
+        };
+
+        let count = history::import::Count::new(&merged, &new, limit);
+        *messages = merged;
+        *last_updated_at = Some(Instant::now());
+
+        self.count_hidden(kind);
+
+        count
+    }
+
+    /// Puts messages read back from disk before those loaded for `kind`,
+    /// unless the history was saved again since they were read. Returns
+    /// whether they were.
+    fn restore(&mut self, kind: &history::Kind, restored: history::Spilled) -> bool {
+        let Some(History::Full {
+            messages, spilled, ..
+        }) = self.map.get_mut(kind)
+        else {
+            return false;
+        };
+
+        if *spilled != restored.range.end {
+            return false;
+        }
+
+        let mut all = restored.messages;
+        all.append(messages);
+
+        *messages = all;
+        *spilled = restored.range.start;
+
+        self.count_hidden(kind);
+
+        true
+    }
+
+    /// Reads back the history of those holding messages whose read back
+    /// failed, once it's time to try again.
+    fn retry_read_backs(&mut self, now: Instant) -> Vec<BoxFuture<'static, Message>> {
+        let due = self
+            .read_back_retries
+            .iter()
+            .filter(|(_, at)| now >= **at)
+            .map(|(kind, _)| kind.clone())
+            .collect::<Vec<_>>();
+
+        due.into_iter()
+            .filter_map(|kind| {
+                self.read_back_retries.remove(&kind);
+
+                let held = self.held.remove(&kind)?;
+
+                self.merge_older(&kind, held)
+            })
+            .collect()
+    }
+
+    /// Takes the messages held for `kind`, to save along with its history
+    /// as it's closed.
+    fn release(&mut self, kind: &history::Kind) -> Vec<crate::Message> {
+        self.read_back_retries.remove(kind);
+        self.held.remove(kind).unwrap_or_default()
+    }
+
+    /// Merges older messages into the loaded history of `kind` once the
+    /// messages it left on disk are read back, returning the task reading
+    /// them unless one is pending.
+    fn merge_older(
+        &mut self,
+        kind: &history::Kind,
+        messages: Vec<crate::Message>,
+    ) -> Option<BoxFuture<'static, Message>> {
+        use std::collections::hash_map;
+
+        let spilled = match self.map.get(kind) {
+            Some(History::Full { spilled, .. }) if *spilled > 0 => *spilled,
+            _ => {
+                let count = self.backfill(kind, messages);
+                log::debug!("backfilled {count} messages into {kind}");
+
+                return None;
+            }
+        };
+
+        match self.held.entry(kind.clone()) {
+            hash_map::Entry::Occupied(mut entry) => {
+                entry.get_mut().extend(messages);
+
+                None
+            }
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(messages);
+
+                let kind = kind.clone();
+
+                Some(
+                    history::load_spilled(kind.clone(), 0..spilled)
+                        .map(move |result| Message::ReadBack(kind, result))
+                        .boxed(),
+                )
+            }
+        }
+    }
+
+    fn backfill(&mut self, kind: &history::Kind, backfilled: Vec<crate::Message>) -> usize {
+        let Some(History::Full {
+            messages,
+            last_updated_at,
+            ..
+        }) = self.map.get_mut(kind)
+        else {
+            return 0;
+        };
+
+        let (merged, count) = history::backfill::merge(std::mem::take(messages), backfilled);
+        *messages = merged;
+
+        if count > 0 {
+            *last_updated_at = Some(Instant::now());
+            self.count_hidden(kind);
+        }
+
+        count
     }
 
     fn update_partial(&mut self, kind: history::Kind, data: history::Metadata) {
@@ -504,23 +1216,56 @@ impl Data {
         &self,
         kind: &history::Kind,
         limit: Option<Limit>,
-        buffer_config: &config::Buffer,
+        config: &Config,
+        smart_filter: bool,
+        reveal: bool,
     ) -> Option<history::View> {
         let History::Full {
             messages,
             read_marker,
+            seen,
             ..
         } = self.map.get(kind)?
         else {
             return None;
         };
 
+        let buffer_config = &config.buffer;
+
+        let channel_settings = match kind {
+            history::Kind::Channel(server, channel) => {
+                config.servers.channel_settings(server, channel)
+            }
+            _ => config::server::ChannelSettings::default(),
+        };
+
+        // Where unread messages start, moved past what was seen per the config
+        let read_marker = match buffer_config.unread_marker.clear {
+            ClearUnreadMarker::BufferClosed => *read_marker,
+            ClearUnreadMarker::FocusLost => (*read_marker).max(*seen),
+            ClearUnreadMarker::MessageSent => {
+                (*read_marker).max(history::ReadMarker::latest_sent(messages))
+            }
+        };
+
         let mut most_recent_messages = HashMap::<Nick, DateTime<Utc>>::new();
 
         let filtered = messages
             .iter()
+            .filter(|message| reveal || !channel_settings.filters.hides(message))
             .filter(|message| match message.target.source() {
                 message::Source::Server(Some(source)) => {
+                    if !channel_settings.join_part
+                        && matches!(
+                            source.kind(),
+                            message::source::server::Kind::Join
+                                | message::source::server::Kind::Part
+                                | message::source::server::Kind::Quit
+                        )
+                    {
+                        return false;
+                    }
+
                     if let Some(server_message) = buffer_config.server_messages.get(source) {
                         // Check if target is a channel, and if included/excluded.
                         if let message::Target::Channel { channel, .. } = &message.target {
@@ -529,7 +1274,7 @@ impl Data {
                             }
                         }
 
-                        if let Some(seconds) = server_message.smart {
+                        if let Some(seconds) = server_message.smart.filter(|_| smart_filter) {
                             let nick = match source.nick() {
                                 Some(nick) => nick.clone(),
                                 None => {
@@ -576,6 +1321,28 @@ impl Data {
             })
             .collect::<Vec<_>>();
 
+        let mut repeats = HashMap::<message::Hash, usize>::new();
+
+        let filtered = match buffer_config.repeats.window() {
+            Some(window) => {
+                let mut collapsed = Vec::<&crate::Message>::with_capacity(filtered.len());
+
+                for (index, message) in filtered.iter().enumerate() {
+                    let repeat = index.checked_sub(1).is_some_and(|previous| {
+                        message::is_repeat(filtered[previous], message, window)
+                    });
+
+                    match collapsed.last() {
+                        Some(first) if repeat => *repeats.entry(first.hash).or_insert(1) += 1,
+                        _ => collapsed.push(message),
+                    }
+                }
+
+                collapsed
+            }
+            None => filtered,
+        };
+
         let total = filtered.len();
         let with_access_levels = buffer_config.nickname.show_access_levels;
 
@@ -630,7 +1397,16 @@ impl Data {
             })
             .unwrap_or_default();
 
-        let limited = with_limit(limit, filtered.into_iter());
+        let limited = with_limit(limit, filtered.iter().copied());
+
+        let newer = limited
+            .last()
+            .and_then(|last| {
+                filtered
+                    .iter()
+                    .rposition(|message| std::ptr::eq(*message, *last))
+            })
+            .map_or(0, |index| total - index - 1);
 
         let split_at = read_marker.map_or(0, |read_marker| {
             limited
@@ -654,10 +1430,12 @@ impl Data {
 
         Some(history::View {
             total,
+            newer,
             old_messages: old.to_vec(),
             new_messages: new.to_vec(),
             max_nick_chars,
             max_prefix_chars,
+            repeats,
         })
     }
 
@@ -668,9 +1446,33 @@ impl Data {
     ) -> Option<impl Future<Output = Message>> {
         use std::collections::hash_map;
 
+        let hides = self
+            .filters(&kind)
+            .is_some_and(|filters| filters.hides(&message));
+
         match self.map.entry(kind.clone()) {
             hash_map::Entry::Occupied(mut entry) => {
-                entry.get_mut().add_message(message);
+                // Older than the messages loaded, such as played back after a
+                // gap, so merged in order with those left on disk
+                if let History::Full {
+                    messages, spilled, ..
+                } = entry.get()
+                {
+                    if *spilled > 0
+                        && messages
+                            .first()
+                            .is_some_and(|first| message.server_time < first.server_time)
+                    {
+                        return self.merge_older(&kind, vec![message]);
+                    }
+                }
+
+                let history = entry.get_mut();
+
+                if history.add_message(message) && hides && matches!(history, History::Full { .. })
+                {
+                    *self.hidden.entry(kind).or_default() += 1;
+                }
 
                 None
             }
@@ -747,21 +1549,51 @@ impl Data {
         &mut self,
         kind: &history::Kind,
     ) -> Option<impl Future<Output = Result<Option<history::ReadMarker>, history::Error>>> {
-        self.map.get_mut(kind).and_then(History::make_partial)
+        let logged = is_logged(&self.unlogged, kind);
+        let held = self.release(kind);
+        self.hidden.remove(kind);
+
+        self.map
+            .get_mut(kind)
+            .and_then(|history| history.make_partial(logged, held))
     }
 
     fn flush_all(&mut self, now: Instant) -> Vec<BoxFuture<'static, Message>> {
-        self.map
+        let mut flushed = vec![];
+
+        let tasks = self
+            .map
             .iter_mut()
+            // Flushing would spill messages while the others are read back
+            .filter(|(kind, _)| !self.held.contains_key(kind))
             .filter_map(|(kind, state)| {
+                let logged = is_logged(&self.unlogged, kind);
                 let kind = kind.clone();
 
-                state.flush(now).map(move |task| {
+                state.flush(now, logged).map(|task| {
+                    flushed.push(kind.clone());
+
                     task.map(move |result| Message::Flushed(kind, result))
                         .boxed()
                 })
             })
-            .collect()
+            .collect();
+
+        // Flushing drops the oldest messages from memory
+        for kind in flushed {
+            self.count_hidden(&kind);
+        }
+
+        tasks
+    }
+}
+
+fn is_logged(unlogged: &HashSet<(Server, String)>, kind: &history::Kind) -> bool {
+    match kind {
+        history::Kind::Channel(server, channel) => {
+            !unlogged.contains(&(server.clone(), channel.to_lowercase()))
+        }
+        _ => true,
     }
 }
 
@@ -818,6 +1650,19 @@ pub enum Broadcast {
         inviter: Nick,
         channel: String,
         user_channels: Vec<String>,
+        joining: bool,
+    },
+    JoinFailed {
+        channel: String,
+        reason: String,
+    },
+    Rejoin {
+        channel: String,
+        status: client::RejoinStatus,
+    },
+    CtcpSuppressed {
+        nick: Nick,
+        command: String,
     },
     ChangeHost {
         old_user: User,
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -6,8 +7,8 @@ use chrono::{format::SecondsFormat, DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 
-use crate::history::{dir_path, Error, Kind};
-use crate::message::source;
+use crate::history::{dir_path, encryption, Error, Kind};
+use crate::message::{self, source};
 use crate::Message;
 
 #[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
@@ -16,6 +17,15 @@ pub struct Metadata {
     pub last_triggers_unread: Option<DateTime<Utc>>,
 }
 
+/// Metadata as written to disk, along with the kind of history it belongs to
+#[derive(Debug, Deserialize, Serialize)]
+struct Stored<'a> {
+    #[serde(flatten)]
+    metadata: Metadata,
+    #[serde(default)]
+    kind: Option<Cow<'a, Kind>>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
 pub struct ReadMarker(DateTime<Utc>);
 
@@ -26,7 +36,7 @@ impl ReadMarker {
             .rev()
             .find(|message| match message.target.source() {
                 source::Source::Internal(source) => match source {
-                    source::Internal::Status(_) => false,
+                    source::Internal::Status(_) | source::Internal::Gap(_) => false,
                     // Logs are in their own buffer and this gives us backlog support there
                     source::Internal::Logs => true,
                 },
@@ -36,6 +46,15 @@ impl ReadMarker {
             .map(Self)
     }
 
+    /// Latest message we sent.
+    pub fn latest_sent(messages: &[Message]) -> Option<Self> {
+        messages
+            .iter()
+            .rev()
+            .find(|message| matches!(message.direction, message::Direction::Sent))
+            .map(|message| Self(message.server_time))
+    }
+
     pub fn date_time(self) -> DateTime<Utc> {
         self.0
     }
@@ -69,6 +88,8 @@ pub async fn load(kind: Kind) -> Result<Metadata, Error> {
     let path = path(&kind).await?;
 
     if let Ok(bytes) = fs::read(path).await {
+        let bytes = encryption::decrypt(bytes)?;
+
         Ok(serde_json::from_slice(&bytes).unwrap_or_default())
     } else {
         Ok(Metadata::default())
@@ -80,14 +101,17 @@ pub async fn save(
     messages: &[Message],
     read_marker: Option<ReadMarker>,
 ) -> Result<(), Error> {
-    let bytes = serde_json::to_vec(&Metadata {
-        read_marker,
-        last_triggers_unread: latest_triggers_unread(messages),
+    let bytes = serde_json::to_vec(&Stored {
+        metadata: Metadata {
+            read_marker,
+            last_triggers_unread: latest_triggers_unread(messages),
+        },
+        kind: Some(Cow::Borrowed(kind)),
     })?;
 
     let path = path(kind).await?;
 
-    fs::write(path, &bytes).await?;
+    fs::write(path, encryption::encrypt(bytes)?).await?;
 
     Ok(())
 }
@@ -102,19 +126,56 @@ pub async fn update(kind: &Kind, read_marker: &ReadMarker) -> Result<(), Error>
         return Ok(());
     }
 
-    let bytes = serde_json::to_vec(&Metadata {
-        read_marker: Some(*read_marker),
-        last_triggers_unread: metadata.last_triggers_unread,
+    let bytes = serde_json::to_vec(&Stored {
+        metadata: Metadata {
+            read_marker: Some(*read_marker),
+            last_triggers_unread: metadata.last_triggers_unread,
+        },
+        kind: Some(Cow::Borrowed(kind)),
     })?;
 
     let path = path(kind).await?;
 
-    fs::write(path, &bytes).await?;
+    fs::write(path, encryption::encrypt(bytes)?).await?;
 
     Ok(())
 }
 
-async fn path(kind: &Kind) -> Result<PathBuf, Error> {
+/// Kinds of all history saved to disk.
+///
+/// History last saved by older versions is missing until it's saved again.
+pub async fn list() -> Result<Vec<Kind>, Error> {
+    let dir = dir_path().await?;
+    let mut entries = fs::read_dir(dir).await?;
+    let mut kinds = vec![];
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+
+        let Ok(bytes) = fs::read(path)
+            .await
+            .map_err(Error::from)
+            .and_then(encryption::decrypt)
+        else {
+            continue;
+        };
+
+        if let Ok(Stored {
+            kind: Some(kind), ..
+        }) = serde_json::from_slice(&bytes)
+        {
+            kinds.push(kind.into_owned());
+        }
+    }
+
+    Ok(kinds)
+}
+
+pub(super) async fn path(kind: &Kind) -> Result<PathBuf, Error> {
     let dir = dir_path().await?;
 
     let name = match kind {
@@ -5,35 +5,38 @@ use irc::proto::format;
 
 use crate::buffer::{self, AutoFormat};
 use crate::message::formatting;
-use crate::{command, message, Command, Message, Server, User};
+use crate::{command, dcc, message, Command, Message, Server, User};
 
 const INPUT_HISTORY_LENGTH: usize = 100;
 
+/// Parses `input` typed in `buffer`, which must fit in `line_len` bytes once
+/// encoded unless it's a message which can be split with [`Input::split`].
+/// Messages only operators see need `@` in the server's `statusmsg`.
 pub fn parse(
     buffer: buffer::Upstream,
     auto_format: AutoFormat,
     input: &str,
+    line_len: usize,
+    statusmsg: &[char],
 ) -> Result<Input, Error> {
     let content = match command::parse(input, Some(&buffer)) {
         Ok(command) => Content::Command(command),
-        Err(command::Error::MissingSlash) => {
-            let text = match auto_format {
-                AutoFormat::Disabled => input.to_string(),
-                AutoFormat::Markdown => formatting::encode(input, true),
-                AutoFormat::All => formatting::encode(input, false),
-            };
-
-            Content::Text(text)
-        }
+        Err(command::Error::MissingSlash) => Content::Text(encode(auto_format, input)),
         Err(error) => return Err(Error::Command(error)),
     };
 
-    if content
-        .proto(&buffer)
-        .map(exceeds_byte_limit)
-        .unwrap_or_default()
+    if let Content::Command(Command::Msg(targets, _)) = &content {
+        if !statusmsg.contains(&'@') && targets.split(',').any(|target| target.starts_with('@')) {
+            return Err(Error::OperatorsUnsupported);
+        }
+    }
+
+    if content.text().is_none()
+        && content
+            .proto(&buffer)
+            .is_some_and(|message| format::message(message).len() > line_len)
     {
-        return Err(Error::ExceedsByteLimit);
+        return Err(Error::ExceedsByteLimit(line_len));
     }
 
     Ok(Input {
@@ -43,6 +46,32 @@ pub fn parse(
     })
 }
 
+/// The formatting recipients will see once `input` is sent as a message, if
+/// it has any.
+pub fn formatting(auto_format: AutoFormat, input: &str) -> Option<Vec<formatting::Fragment>> {
+    if !matches!(
+        command::parse(input, None),
+        Err(command::Error::MissingSlash)
+    ) {
+        return None;
+    }
+
+    let fragments = formatting::parse(&encode(auto_format, input))?;
+
+    fragments
+        .iter()
+        .any(|fragment| matches!(fragment, formatting::Fragment::Formatted(..)))
+        .then_some(fragments)
+}
+
+fn encode(auto_format: AutoFormat, input: &str) -> String {
+    match auto_format {
+        AutoFormat::Disabled => input.to_string(),
+        AutoFormat::Markdown => formatting::encode(input, true),
+        AutoFormat::All => formatting::encode(input, false),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Input {
     pub buffer: buffer::Upstream,
@@ -71,6 +100,11 @@ impl Input {
                     source,
                     prefixes,
                 })
+            } else if let Some(remote) = dcc::chat::remote(target) {
+                Some(message::Target::Query {
+                    nick: dcc::chat::target(&remote),
+                    source,
+                })
             } else if let Ok(user) = User::try_from(target) {
                 Some(message::Target::Query {
                     nick: user.nickname().to_owned(),
@@ -104,6 +138,13 @@ impl Input {
         }
     }
 
+    /// The command to run in the client, if it isn't sent to the server.
+    pub fn local_command(&self) -> Option<Command> {
+        self.content
+            .command(&self.buffer)
+            .filter(|command| proto::Command::try_from(command.clone()).is_err())
+    }
+
     pub fn encoded(&self) -> Option<message::Encoded> {
         self.content.proto(&self.buffer).map(message::Encoded::from)
     }
@@ -111,6 +152,76 @@ impl Input {
     pub fn raw(&self) -> Option<&str> {
         self.raw.as_deref()
     }
+
+    /// The text typed in the buffer, if it isn't a command.
+    pub fn text(&self) -> Option<&str> {
+        match &self.content {
+            Content::Text(text) => Some(text),
+            Content::Command(_) => None,
+        }
+    }
+
+    /// Splits a message too long to send in lines of `line_len` bytes into
+    /// as many messages as it takes, rather than have the server cut it off.
+    /// Returns `None` if it fits in one.
+    pub fn split(&self, line_len: usize) -> Option<Vec<Input>> {
+        let message = self.content.proto(&self.buffer)?;
+        let text = self.content.text()?;
+
+        let len = format::message(message).len();
+        let max_len = line_len.saturating_sub(len - text.len());
+
+        if len <= line_len || max_len == 0 {
+            return None;
+        }
+
+        Some(
+            split_text(text, max_len)
+                .into_iter()
+                .enumerate()
+                .map(|(index, part)| Input {
+                    buffer: self.buffer.clone(),
+                    content: self.content.with_text(part),
+                    // Recorded in the input history once
+                    raw: self.raw.clone().filter(|_| index == 0),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Splits `text` into parts of at most `max_len` bytes, after the last space
+/// which fits when there is one so words aren't cut in two. Spaces are kept,
+/// for the parts joined back together to give the text.
+fn split_text(text: &str, max_len: usize) -> Vec<String> {
+    let mut parts = vec![];
+    let mut rest = text;
+
+    while rest.len() > max_len {
+        let mut end = max_len;
+
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let end = rest[..end]
+            .rfind(' ')
+            .filter(|space| *space > 0)
+            .map(|space| space + 1)
+            .or((end > 0).then_some(end))
+            .unwrap_or_else(|| rest.chars().next().map_or(1, char::len_utf8));
+
+        let (part, remaining) = rest.split_at(end);
+
+        parts.push(part.to_string());
+        rest = remaining;
+    }
+
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+
+    parts
 }
 
 #[derive(Debug, Clone)]
@@ -130,6 +241,28 @@ impl Content {
         }
     }
 
+    /// The text of a message, which can be split across several.
+    fn text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text)
+            | Self::Command(Command::Msg(_, text))
+            | Self::Command(Command::Me(_, text)) => Some(text),
+            Self::Command(_) => None,
+        }
+    }
+
+    fn with_text(&self, text: String) -> Self {
+        match self {
+            Self::Command(Command::Msg(target, _)) => {
+                Self::Command(Command::Msg(target.clone(), text))
+            }
+            Self::Command(Command::Me(target, _)) => {
+                Self::Command(Command::Me(target.clone(), text))
+            }
+            Self::Text(_) | Self::Command(_) => Self::Text(text),
+        }
+    }
+
     fn proto(&self, buffer: &buffer::Upstream) -> Option<proto::Message> {
         self.command(buffer)
             .and_then(|command| proto::Command::try_from(command).ok())
@@ -182,15 +315,97 @@ pub struct Cache<'a> {
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error(
-        "message exceeds maximum encoded length of {} bytes",
-        format::BYTE_LIMIT
-    )]
-    ExceedsByteLimit,
+    #[error("message exceeds maximum encoded length of {0} bytes")]
+    ExceedsByteLimit(usize),
+    #[error("server doesn't support messages only operators see (STATUSMSG)")]
+    OperatorsUnsupported,
     #[error(transparent)]
     Command(#[from] command::Error),
 }
 
-fn exceeds_byte_limit(message: proto::Message) -> bool {
-    format::message(message).len() > format::BYTE_LIMIT
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formatting_preview() {
+        let formatted = |auto_format, input| {
+            formatting(auto_format, input).map(|fragments| {
+                fragments
+                    .into_iter()
+                    .map(|fragment| match fragment {
+                        formatting::Fragment::Unformatted(text) => (text, false),
+                        formatting::Fragment::Formatted(text, formatting) => {
+                            (text, formatting.bold)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            formatted(AutoFormat::Disabled, "hi \u{2}there"),
+            Some(vec![("hi ".into(), false), ("there".into(), true)])
+        );
+        assert_eq!(
+            formatted(AutoFormat::Markdown, "hi **there**"),
+            Some(vec![("hi ".into(), false), ("there".into(), true)])
+        );
+        assert_eq!(formatted(AutoFormat::Disabled, "hi **there**"), None);
+        assert_eq!(formatted(AutoFormat::Disabled, "/me \u{2}waves"), None);
+        assert_eq!(formatted(AutoFormat::Disabled, "plain"), None);
+    }
+
+    #[test]
+    fn split_long_messages() {
+        assert_eq!(
+            split_text("hello there world", 12),
+            ["hello there ", "world"]
+        );
+        assert_eq!(split_text("abcdefgh", 3), ["abc", "def", "gh"]);
+        assert_eq!(split_text("héllo", 2), ["h", "é", "ll", "o"]);
+        assert_eq!(split_text("short", 10), ["short"]);
+
+        let buffer = buffer::Upstream::Channel(Server::from("libera"), "#halloy".into());
+        let text = "word ".repeat(200);
+        let input = parse(buffer, AutoFormat::Disabled, &text, 512, &[]).unwrap();
+
+        let parts = input.split(512).unwrap();
+
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| format::message(
+            part.content.proto(&part.buffer).unwrap()
+        )
+        .len()
+            <= 512));
+        assert_eq!(
+            parts.iter().filter_map(Input::text).collect::<String>(),
+            text
+        );
+        assert!(parts[0].raw().is_some() && parts[1].raw().is_none());
+    }
+
+    #[test]
+    fn operators_only() {
+        let buffer = buffer::Upstream::Channel(Server::from("libera"), "#halloy".into());
+        let opmsg = |statusmsg: &[char]| {
+            parse(
+                buffer.clone(),
+                AutoFormat::Disabled,
+                "/opmsg hi",
+                512,
+                statusmsg,
+            )
+        };
+
+        assert!(matches!(opmsg(&[]), Err(Error::OperatorsUnsupported)));
+        assert!(matches!(opmsg(&['+']), Err(Error::OperatorsUnsupported)));
+        assert_eq!(
+            opmsg(&['@', '+'])
+                .unwrap()
+                .encoded()
+                .map(|encoded| encoded.command.clone()),
+            Some(proto::Command::PRIVMSG("@#halloy".into(), "hi".into()))
+        );
+    }
 }
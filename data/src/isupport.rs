@@ -5,19 +5,28 @@ use std::str::FromStr;
 #[allow(non_camel_case_types)]
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Kind {
+    ACCOUNTEXTBAN,
     AWAYLEN,
+    CASEMAPPING,
     CHANLIMIT,
+    CHANMODES,
     CHANNELLEN,
     CHANTYPES,
+    CHATHISTORY,
     CNOTICE,
     CPRIVMSG,
     ELIST,
+    EXTBAN,
     KEYLEN,
     KICKLEN,
     KNOCK,
+    LINELEN,
+    MODES,
     MONITOR,
     NICKLEN,
+    PREFIX,
     SAFELIST,
+    SILENCE,
     STATUSMSG,
     TARGMAX,
     TOPICLEN,
@@ -469,19 +478,28 @@ impl Operation {
         match self {
             Operation::Add(parameter) => parameter.kind(),
             Operation::Remove(parameter) => match parameter.as_ref() {
+                "ACCOUNTEXTBAN" => Some(Kind::ACCOUNTEXTBAN),
                 "AWAYLEN" => Some(Kind::AWAYLEN),
+                "CASEMAPPING" => Some(Kind::CASEMAPPING),
                 "CHANLIMIT" => Some(Kind::CHANLIMIT),
+                "CHANMODES" => Some(Kind::CHANMODES),
                 "CHANNELLEN" => Some(Kind::CHANNELLEN),
                 "CHANTYPES" => Some(Kind::CHANTYPES),
+                "CHATHISTORY" => Some(Kind::CHATHISTORY),
                 "CNOTICE" => Some(Kind::CNOTICE),
                 "CPRIVMSG" => Some(Kind::CPRIVMSG),
                 "ELIST" => Some(Kind::ELIST),
+                "EXTBAN" => Some(Kind::EXTBAN),
                 "KEYLEN" => Some(Kind::KEYLEN),
                 "KICKLEN" => Some(Kind::KICKLEN),
                 "KNOCK" => Some(Kind::KNOCK),
+                "LINELEN" => Some(Kind::LINELEN),
+                "MODES" => Some(Kind::MODES),
                 "MONITOR" => Some(Kind::MONITOR),
                 "NICKLEN" => Some(Kind::NICKLEN),
+                "PREFIX" => Some(Kind::PREFIX),
                 "SAFELIST" => Some(Kind::SAFELIST),
+                "SILENCE" => Some(Kind::SILENCE),
                 "STATUSMSG" => Some(Kind::STATUSMSG),
                 "TARGMAX" => Some(Kind::TARGMAX),
                 "TOPICLEN" => Some(Kind::TOPICLEN),
@@ -566,18 +584,27 @@ pub enum Parameter {
 impl Parameter {
     pub fn kind(&self) -> Option<Kind> {
         match self {
+            Parameter::ACCOUNTEXTBAN(_) => Some(Kind::ACCOUNTEXTBAN),
             Parameter::AWAYLEN(_) => Some(Kind::AWAYLEN),
+            Parameter::CASEMAPPING(_) => Some(Kind::CASEMAPPING),
             Parameter::CHANLIMIT(_) => Some(Kind::CHANLIMIT),
+            Parameter::CHANMODES(_) => Some(Kind::CHANMODES),
             Parameter::CHANNELLEN(_) => Some(Kind::CHANNELLEN),
+            Parameter::CHATHISTORY(_) => Some(Kind::CHATHISTORY),
             Parameter::CNOTICE => Some(Kind::CNOTICE),
             Parameter::CPRIVMSG => Some(Kind::CPRIVMSG),
             Parameter::ELIST(_) => Some(Kind::ELIST),
+            Parameter::EXTBAN(..) => Some(Kind::EXTBAN),
             Parameter::KEYLEN(_) => Some(Kind::KEYLEN),
             Parameter::KICKLEN(_) => Some(Kind::KICKLEN),
             Parameter::KNOCK => Some(Kind::KNOCK),
+            Parameter::LINELEN(_) => Some(Kind::LINELEN),
+            Parameter::MODES(_) => Some(Kind::MODES),
             Parameter::MONITOR(_) => Some(Kind::MONITOR),
             Parameter::NICKLEN(_) => Some(Kind::NICKLEN),
+            Parameter::PREFIX(_) => Some(Kind::PREFIX),
             Parameter::SAFELIST => Some(Kind::SAFELIST),
+            Parameter::SILENCE(_) => Some(Kind::SILENCE),
             Parameter::STATUSMSG(_) => Some(Kind::STATUSMSG),
             Parameter::TARGMAX(_) => Some(Kind::TARGMAX),
             Parameter::TOPICLEN(_) => Some(Kind::TOPICLEN),
@@ -590,14 +617,39 @@ impl Parameter {
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum CaseMap {
     ASCII,
+    #[default]
     RFC1459,
     RFC1459_STRICT,
     RFC7613,
 }
 
+impl CaseMap {
+    /// Lowercases `name` so nicknames and channels the server considers
+    /// equal compare equal.
+    pub fn normalize(&self, name: &str) -> String {
+        match self {
+            CaseMap::ASCII => name.to_ascii_lowercase(),
+            CaseMap::RFC1459 | CaseMap::RFC1459_STRICT => {
+                let strict = matches!(self, CaseMap::RFC1459_STRICT);
+
+                name.chars()
+                    .map(|c| match c {
+                        '[' => '{',
+                        ']' => '}',
+                        '\\' => '|',
+                        '~' if !strict => '^',
+                        c => c.to_ascii_lowercase(),
+                    })
+                    .collect()
+            }
+            CaseMap::RFC7613 => name.to_lowercase(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ChannelLimit {
     pub prefix: char,
@@ -635,7 +687,7 @@ pub struct ModesLimit {
     pub limit: u16,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PrefixMap {
     pub prefix: char,
     pub mode: char,
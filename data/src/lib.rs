@@ -18,15 +18,18 @@ pub use self::window::Window;
 
 pub mod appearance;
 pub mod audio;
+pub mod ban;
 pub mod buffer;
 pub mod channel;
 pub mod client;
 pub mod command;
 mod compression;
 pub mod config;
+pub mod console;
 pub mod ctcp;
 pub mod dashboard;
 pub mod dcc;
+pub mod debug;
 pub mod environment;
 pub mod file_transfer;
 pub mod history;
@@ -35,12 +38,17 @@ pub mod isupport;
 pub mod log;
 pub mod message;
 pub mod mode;
+pub mod notify;
 pub mod pane;
+pub mod pastebin;
+pub mod preview;
 pub mod server;
+pub mod services;
 pub mod shortcut;
 pub mod stream;
 pub mod time;
 pub mod url;
 pub mod user;
 pub mod version;
+pub mod webhook;
 pub mod window;
@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::RwLock;
 use std::{fs, io};
 
 use chrono::{DateTime, Utc};
@@ -7,6 +9,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::environment;
 
+/// Modules whose logging can be changed, Halloy's own
+const MODULES: &[&str] = &["data", "halloy"];
+
+/// The filter in effect, and the one set by the configuration.
+static FILTER: RwLock<(Filter, Filter)> = RwLock::new((Filter::new(), Filter::new()));
+
 pub fn file() -> Result<fs::File, Error> {
     let path = path()?;
 
@@ -59,6 +67,168 @@ impl From<log::Level> for Level {
     }
 }
 
+/// How much is logged, overall or by a module.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    Deserialize,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Verbosity {
+    Off,
+    Error,
+    Warn,
+    Info,
+    #[default]
+    Debug,
+    Trace,
+}
+
+impl From<Verbosity> for log::LevelFilter {
+    fn from(verbosity: Verbosity) -> Self {
+        match verbosity {
+            Verbosity::Off => log::LevelFilter::Off,
+            Verbosity::Error => log::LevelFilter::Error,
+            Verbosity::Warn => log::LevelFilter::Warn,
+            Verbosity::Info => log::LevelFilter::Info,
+            Verbosity::Debug => log::LevelFilter::Debug,
+            Verbosity::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<log::LevelFilter> for Verbosity {
+    fn from(filter: log::LevelFilter) -> Self {
+        match filter {
+            log::LevelFilter::Off => Verbosity::Off,
+            log::LevelFilter::Error => Verbosity::Error,
+            log::LevelFilter::Warn => Verbosity::Warn,
+            log::LevelFilter::Info => Verbosity::Info,
+            log::LevelFilter::Debug => Verbosity::Debug,
+            log::LevelFilter::Trace => Verbosity::Trace,
+        }
+    }
+}
+
+/// What is logged, with modules such as `data::client` logging more or less
+/// than the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub verbosity: Verbosity,
+    pub modules: BTreeMap<String, Verbosity>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filter {
+    const fn new() -> Self {
+        Self {
+            verbosity: Verbosity::Debug,
+            modules: BTreeMap::new(),
+        }
+    }
+
+    /// The verbosity of the most specific module `target` is in.
+    pub fn verbosity(&self, target: &str) -> Verbosity {
+        self.modules
+            .iter()
+            .filter(|(module, _)| is_in(target, module))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.verbosity, |(_, verbosity)| *verbosity)
+    }
+
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::LevelFilter::from(self.verbosity(metadata.target()))
+    }
+
+    fn max(&self) -> Verbosity {
+        self.modules
+            .values()
+            .copied()
+            .fold(self.verbosity, Verbosity::max)
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "log level is {}", self.verbosity)?;
+
+        for (module, verbosity) in &self.modules {
+            write!(f, ", {module} is {verbosity}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a record is logged, for Halloy's own modules.
+pub fn enabled(metadata: &log::Metadata) -> bool {
+    if !MODULES
+        .iter()
+        .any(|module| is_in(metadata.target(), module))
+    {
+        return true;
+    }
+
+    FILTER
+        .read()
+        .map_or(true, |filters| filters.0.enabled(metadata))
+}
+
+/// The filter in effect.
+pub fn filter() -> Filter {
+    FILTER
+        .read()
+        .map(|filters| filters.0.clone())
+        .unwrap_or_default()
+}
+
+/// Changes what is logged, until the configuration is loaded again.
+pub fn set_filter(filter: Filter) {
+    // Other crates log up to info
+    log::set_max_level(filter.max().max(Verbosity::Info).into());
+
+    if let Ok(mut filters) = FILTER.write() {
+        filters.0 = filter;
+    }
+}
+
+/// Logs what the configuration sets, taking over changes made at runtime.
+pub fn configure(filter: Filter) {
+    if let Ok(mut filters) = FILTER.write() {
+        filters.1 = filter.clone();
+    }
+
+    set_filter(filter);
+}
+
+/// The filter set by the configuration.
+pub fn configured() -> Filter {
+    FILTER
+        .read()
+        .map(|filters| filters.1.clone())
+        .unwrap_or_default()
+}
+
+fn is_in(target: &str, module: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
@@ -68,3 +238,28 @@ pub enum Error {
     #[error(transparent)]
     ParseLevel(#[from] log::ParseLevelError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_verbosity() {
+        let filter = Filter {
+            verbosity: Verbosity::Warn,
+            modules: BTreeMap::from([
+                ("data".to_string(), Verbosity::Info),
+                ("data::client".to_string(), Verbosity::Trace),
+            ]),
+        };
+
+        assert_eq!(
+            filter.verbosity("data::client::on_connect"),
+            Verbosity::Trace
+        );
+        assert_eq!(filter.verbosity("data::stream"), Verbosity::Info);
+        assert_eq!(filter.verbosity("database"), Verbosity::Warn);
+        assert_eq!(filter.verbosity("halloy"), Verbosity::Warn);
+        assert_eq!(filter.max(), Verbosity::Trace);
+    }
+}
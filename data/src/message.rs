@@ -18,7 +18,7 @@ pub use self::source::Source;
 use crate::config::buffer::UsernameFormat;
 use crate::time::{self, Posix};
 use crate::user::{Nick, NickRef};
-use crate::{ctcp, Config, Server, User};
+use crate::{buffer, config, ctcp, dcc, Config, Server, User};
 
 // References:
 // - https://datatracker.ietf.org/doc/html/rfc1738#section-5
@@ -122,6 +122,9 @@ pub enum Target {
         server: Server,
         channel: Channel,
         source: Source,
+        /// A direct message, `channel` being the query with the sender
+        #[serde(default)]
+        query: bool,
     },
 }
 
@@ -269,6 +272,59 @@ impl Message {
         }
     }
 
+    pub fn dcc_chat_offer_received(from: &Nick) -> Message {
+        Message::dcc_chat_status(
+            from,
+            format!("{from} wants to chat over DCC, use /dcc chat {from} to accept"),
+        )
+    }
+
+    /// Status of the chat over DCC with `remote`, shown in its query.
+    pub fn dcc_chat_status(remote: &Nick, text: String) -> Message {
+        let received_at = Posix::now();
+        let content = plain(text);
+        let hash = Hash::new(&received_at, &content);
+
+        Message {
+            received_at,
+            server_time: Utc::now(),
+            direction: Direction::Received,
+            target: Target::Query {
+                nick: dcc::chat::target(remote),
+                source: Source::Action,
+            },
+            content,
+            id: None,
+            hash,
+        }
+    }
+
+    pub fn dcc_chat_received(from: &Nick, text: &str) -> Message {
+        let received_at = Posix::now();
+
+        let (source, content) = match parse_action(NickRef::from(from.as_ref()), text) {
+            Some(content) if is_action(text) => (Source::Action, content),
+            _ => (
+                Source::User(User::from(from.clone())),
+                parse_fragments(text.to_string(), &[]),
+            ),
+        };
+        let hash = Hash::new(&received_at, &content);
+
+        Message {
+            received_at,
+            server_time: Utc::now(),
+            direction: Direction::Received,
+            target: Target::Query {
+                nick: dcc::chat::target(from),
+                source,
+            },
+            content,
+            id: None,
+            hash,
+        }
+    }
+
     pub fn with_target(self, target: Target) -> Self {
         Self { target, ..self }
     }
@@ -308,6 +364,16 @@ impl Message {
                 server,
                 channel,
                 source: Source::User(user),
+                query: false,
+            },
+            Target::Query {
+                nick,
+                source: Source::User(user),
+            } => Target::Highlights {
+                server,
+                channel: nick.to_string(),
+                source: Source::User(user),
+                query: true,
             },
             _ => return None,
         };
@@ -398,7 +464,7 @@ impl<'de> Deserialize<'de> for Message {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Hash(u64);
 
 impl Hash {
@@ -565,13 +631,26 @@ pub enum Content {
 }
 
 impl Content {
-    fn text(&self) -> Cow<str> {
+    pub fn text(&self) -> Cow<str> {
         match self {
             Content::Plain(s) => s.into(),
             Content::Fragments(fragments) => fragments.iter().map(Fragment::as_str).join("").into(),
             Content::Log(record) => (&record.message).into(),
         }
     }
+
+    /// URLs linked in the content.
+    pub fn urls(&self) -> impl Iterator<Item = &Url> {
+        let fragments = match self {
+            Content::Fragments(fragments) => fragments.as_slice(),
+            Content::Plain(_) | Content::Log(_) => &[],
+        };
+
+        fragments.iter().filter_map(|fragment| match fragment {
+            Fragment::Url(url) => Some(url),
+            _ => None,
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -944,7 +1023,16 @@ fn content<'a>(
             let channel_users = channel_users(target);
             Some(parse_fragments(text.clone(), channel_users))
         }
-        Command::NOTICE(_, text) => Some(parse_fragments(text.clone(), &[])),
+        Command::NOTICE(_, text) => {
+            // Check if a reply to a CTCP query
+            if let Some(nick) = message.user().as_ref().map(User::nickname) {
+                if let Some(reply) = parse_ctcp_reply(nick, text) {
+                    return Some(reply);
+                }
+            }
+
+            Some(parse_fragments(text.clone(), &[]))
+        }
         Command::Numeric(RPL_TOPIC, params) => {
             let topic = params.get(2)?;
 
@@ -1065,7 +1153,7 @@ fn content<'a>(
             let user = params.get(1)?;
             let away_message = params
                 .get(2)
-                .map(|away| format!(" ({away})"))
+                .map(|away| format!(": {away}"))
                 .unwrap_or_default();
 
             Some(parse_fragments(
@@ -1114,11 +1202,18 @@ pub enum Limit {
     Top(usize),
     Bottom(usize),
     Since(time::Posix),
+    /// Messages up to the last one received at or before the time
+    Ending(time::Posix, usize),
+    /// Messages from the first one received at or after the time
+    Starting(time::Posix, usize),
 }
 
 impl Limit {
     pub const DEFAULT_STEP: usize = 50;
     pub const DEFAULT_COUNT: usize = 500;
+    /// Most messages shown at once, past which a window of them is moved
+    /// through history instead
+    pub const MAX_COUNT: usize = 1000;
 
     pub fn top() -> Self {
         Self::Top(Self::DEFAULT_COUNT)
@@ -1143,6 +1238,30 @@ fn parse_action(nick: NickRef, text: &str) -> Option<Content> {
     Some(action_text(nick, query.params))
 }
 
+fn parse_ctcp_reply(nick: NickRef, text: &str) -> Option<Content> {
+    let query = ctcp::is_query(text)
+        .then(|| ctcp::parse_query(text))
+        .flatten()
+        .filter(|query| !matches!(query.command, ctcp::Command::Action))?;
+
+    let round_trip = query
+        .params
+        .filter(|_| matches!(query.command, ctcp::Command::Ping))
+        .and_then(|params| ctcp::ping_round_trip(params, Utc::now()));
+
+    Some(plain(if let Some(round_trip) = round_trip {
+        format!(
+            "CTCP {} reply from {nick}: {:.3}s",
+            query.command,
+            round_trip.num_milliseconds() as f64 / 1000.0
+        )
+    } else if let Some(params) = query.params {
+        format!("CTCP {} reply from {nick}: {params}", query.command)
+    } else {
+        format!("CTCP {} reply from {nick}", query.command)
+    }))
+}
+
 pub fn action_text(nick: NickRef, action: Option<&str>) -> Content {
     if let Some(action) = action {
         parse_fragments(format!("{nick} {action}"), &[])
@@ -1176,6 +1295,85 @@ pub fn references_user(sender: NickRef, own_nick: NickRef, message: &Message) ->
     }
 }
 
+/// Whether `message` continues the group of consecutive messages sent by the
+/// same user before it, sent no more than `window` after `previous` and on the
+/// same day.
+pub fn continues_group(previous: &Message, message: &Message, window: chrono::TimeDelta) -> bool {
+    let (Source::User(previous_user), Source::User(user)) =
+        (previous.target.source(), message.target.source())
+    else {
+        return false;
+    };
+
+    let elapsed = message.server_time - previous.server_time;
+
+    previous_user.nickname() == user.nickname()
+        && previous.target.prefixes() == message.target.prefixes()
+        && elapsed >= chrono::TimeDelta::zero()
+        && elapsed <= window
+        && is_same_day(previous, message)
+}
+
+/// Whether `message` repeats `previous`, sent by the same user no more than
+/// `window` after it.
+pub fn is_repeat(previous: &Message, message: &Message, window: chrono::TimeDelta) -> bool {
+    let (Source::User(previous_user), Source::User(user)) =
+        (previous.target.source(), message.target.source())
+    else {
+        return false;
+    };
+
+    let elapsed = message.server_time - previous.server_time;
+
+    previous_user.nickname() == user.nickname()
+        && elapsed >= chrono::TimeDelta::zero()
+        && elapsed <= window
+        && is_repeated_text(&previous.content.text(), &message.content.text())
+}
+
+/// Whether two texts are the same, but for case and spacing.
+pub fn is_repeated_text(previous: &str, text: &str) -> bool {
+    let words = |text: &str| {
+        text.split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+
+    words(previous) == words(text)
+}
+
+/// Whether both messages were sent on the same day, in the local timezone.
+pub fn is_same_day(a: &Message, b: &Message) -> bool {
+    let date = |message: &Message| {
+        message
+            .server_time
+            .with_timezone(&chrono::Local)
+            .date_naive()
+    };
+
+    date(a) == date(b)
+}
+
+/// Whether `message` references our nick or matches our configured highlights.
+pub fn is_highlight(
+    sender: NickRef,
+    own_nick: NickRef,
+    message: &Message,
+    highlights: &config::Highlights,
+) -> bool {
+    references_user(sender, own_nick, message)
+        || (sender != own_nick && highlights.matches(&message.content.text()))
+}
+
+pub fn is_highlight_text(
+    sender: NickRef,
+    own_nick: NickRef,
+    text: &str,
+    highlights: &config::Highlights,
+) -> bool {
+    references_user_text(sender, own_nick, text) || (sender != own_nick && highlights.matches(text))
+}
+
 pub fn references_user_text(sender: NickRef, own_nick: NickRef, text: &str) -> bool {
     sender != own_nick
         && text
@@ -1197,7 +1395,7 @@ pub enum Link {
     Channel(String),
     Url(String),
     User(User),
-    GoToMessage(Server, String, Hash),
+    GoToMessage(buffer::Upstream, Hash),
 }
 
 fn fail_as_none<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
@@ -2,6 +2,7 @@
 use chrono::{DateTime, Utc};
 
 use super::{parse_fragments, plain, source, Content, Direction, Message, Source, Target};
+use crate::client::RejoinStatus;
 use crate::config::buffer::UsernameFormat;
 use crate::time::Posix;
 use crate::user::Nick;
@@ -10,6 +11,7 @@ use crate::{message, Config, User};
 enum Cause {
     Server(Option<source::Server>),
     Status(source::Status),
+    Gap(DateTime<Utc>),
 }
 
 fn expand(
@@ -38,6 +40,7 @@ fn expand(
     let source = match cause {
         Cause::Server(server) => Source::Server(server),
         Cause::Status(status) => Source::Internal(source::Internal::Status(status)),
+        Cause::Gap(disconnected_at) => Source::Internal(source::Internal::Gap(disconnected_at)),
     };
 
     channels
@@ -126,6 +129,23 @@ pub fn disconnected(
     )
 }
 
+pub fn gap(
+    channels: impl IntoIterator<Item = String>,
+    queries: impl IntoIterator<Item = Nick>,
+    disconnected_at: DateTime<Utc>,
+    sent_time: DateTime<Utc>,
+) -> Vec<Message> {
+    let content = plain("disconnected — messages may be missing".into());
+    expand(
+        channels,
+        queries,
+        false,
+        Cause::Gap(disconnected_at),
+        content,
+        sent_time,
+    )
+}
+
 pub fn reconnected(
     channels: impl IntoIterator<Item = String>,
     queries: impl IntoIterator<Item = Nick>,
@@ -184,33 +204,85 @@ pub fn nickname(
     ourself: bool,
     sent_time: DateTime<Utc>,
 ) -> Vec<Message> {
-    let content = if ourself {
-        plain(format!("You're now known as {new_nick}"))
+    let (content, cause) = if ourself {
+        (
+            plain(format!("You're now known as {new_nick}")),
+            Cause::Server(None),
+        )
     } else {
-        plain(format!("{old_nick} is now known as {new_nick}"))
+        (
+            plain(format!("{old_nick} is now known as {new_nick}")),
+            Cause::Server(Some(source::Server::new(
+                source::server::Kind::ChangeNick,
+                Some(old_nick.clone()),
+            ))),
+        )
     };
 
-    expand(
-        channels,
-        queries,
-        false,
-        Cause::Server(None),
-        content,
-        sent_time,
-    )
+    expand(channels, queries, false, cause, content, sent_time)
 }
 
 pub fn invite(
     inviter: Nick,
     channel: String,
     channels: impl IntoIterator<Item = String>,
+    joining: bool,
     sent_time: DateTime<Utc>,
 ) -> Vec<Message> {
-    let content = plain(format!("{inviter} invited you to join {channel}"));
+    let content = if joining {
+        plain(format!("{inviter} invited you to join {channel}, joining"))
+    } else {
+        plain(format!("{inviter} invited you to join {channel}"))
+    };
 
     expand(channels, [], false, Cause::Server(None), content, sent_time)
 }
 
+pub fn join_failed(channel: String, reason: &str, sent_time: DateTime<Utc>) -> Vec<Message> {
+    let content = plain(format!("couldn't join {channel} ({reason})"));
+
+    expand(
+        [channel],
+        [],
+        false,
+        Cause::Status(source::Status::Error),
+        content,
+        sent_time,
+    )
+}
+
+pub fn rejoin(channel: String, status: RejoinStatus, sent_time: DateTime<Utc>) -> Vec<Message> {
+    let (content, cause) = match status {
+        RejoinStatus::Scheduled {
+            delay,
+            attempt,
+            max_attempts,
+        } => (
+            plain(format!(
+                "rejoining {channel} in {}s (attempt {attempt} of {max_attempts})",
+                delay.as_secs()
+            )),
+            Cause::Server(None),
+        ),
+        RejoinStatus::GaveUp { attempts } => (
+            plain(format!(
+                "not rejoining {channel}, gave up after {attempts} attempts"
+            )),
+            Cause::Status(source::Status::Error),
+        ),
+    };
+
+    expand([channel], [], false, cause, content, sent_time)
+}
+
+pub fn ctcp_suppressed(nick: &Nick, command: &str, sent_time: DateTime<Utc>) -> Vec<Message> {
+    let content = plain(format!(
+        "too many CTCP queries, not replying to {command} from {nick} and others for a while"
+    ));
+
+    expand([], [], true, Cause::Server(None), content, sent_time)
+}
+
 pub fn change_host(
     channels: impl IntoIterator<Item = String>,
     queries: impl IntoIterator<Item = Nick>,
@@ -191,7 +191,7 @@ pub enum Fragment {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
-enum Modifier {
+pub enum Modifier {
     Bold = 0x02,
     Italics = 0x1D,
     Underline = 0x1F,
@@ -204,7 +204,7 @@ enum Modifier {
 }
 
 impl Modifier {
-    fn char(&self) -> char {
+    pub fn char(&self) -> char {
         *self as u8 as char
     }
 }
@@ -340,7 +340,7 @@ pub enum Color {
 }
 
 impl Color {
-    fn code(code: u8) -> Option<Self> {
+    pub fn code(code: u8) -> Option<Self> {
         match code {
             0 => Some(Self::White),
             1 => Some(Self::Black),
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::User;
@@ -16,6 +17,8 @@ pub enum Source {
 pub enum Internal {
     Status(Status),
     Logs,
+    /// Messages may be missing from when the connection was lost until this message
+    Gap(DateTime<Utc>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -66,6 +69,7 @@ pub mod server {
         Quit,
         ReplyTopic,
         ChangeHost,
+        ChangeNick,
         MonitoredOnline,
         MonitoredOffline,
     }
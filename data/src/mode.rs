@@ -1,3 +1,10 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::isupport;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Mode<T> {
     Add(T, Option<String>),
@@ -227,6 +234,23 @@ pub fn parse<T>(encoded: &str, args: &[String]) -> Vec<Mode<T>>
 where
     T: Parser,
 {
+    parse_with(encoded, args, |c, _| T::from_char(c).takes_arg())
+        .into_iter()
+        .map(|mode| match mode {
+            Mode::Add(c, arg) => Mode::Add(T::from_char(c), arg),
+            Mode::Remove(c, arg) => Mode::Remove(T::from_char(c), arg),
+            Mode::NoPrefix(c) => Mode::NoPrefix(T::from_char(c)),
+        })
+        .collect()
+}
+
+/// Parses a modestring, using `takes_arg` to decide whether a mode consumes an argument
+/// (called with the mode and whether it is being added).
+fn parse_with(
+    encoded: &str,
+    args: &[String],
+    takes_arg: impl Fn(char, bool) -> bool,
+) -> Vec<Mode<char>> {
     enum Mod<'a> {
         Plus(&'a str),
         Minus(&'a str),
@@ -259,16 +283,15 @@ where
         };
 
         for c in modes.chars() {
-            let value = T::from_char(c);
-            let arg = if value.takes_arg() {
+            let arg = if takes_arg(c, !matches!(_mod, Mod::Minus(_))) {
                 args.next().cloned()
             } else {
                 None
             };
             let mode = match _mod {
-                Mod::Plus(_) => Mode::Add(value, arg),
-                Mod::Minus(_) => Mode::Remove(value, arg),
-                Mod::None(_) => Mode::NoPrefix(value),
+                Mod::Plus(_) => Mode::Add(c, arg),
+                Mod::Minus(_) => Mode::Remove(c, arg),
+                Mod::None(_) => Mode::NoPrefix(c),
             };
             parsed.push(mode);
         }
@@ -277,6 +300,404 @@ where
     parsed
 }
 
+// Reference: https://modern.ircdocs.horse/#chanmodes-parameter
+
+/// How a channel mode behaves, as advertised by the server in CHANMODES and PREFIX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Type A: adds or removes an entry to or from a list (e.g. bans).
+    List,
+    /// Type B: always takes a parameter (e.g. key).
+    AlwaysArg,
+    /// Type C: takes a parameter only when set (e.g. limit).
+    SetArg,
+    /// Type D: never takes a parameter (e.g. moderated).
+    Flag,
+    /// Membership prefix given to a user (e.g. op, voice).
+    Prefix,
+}
+
+/// The channel modes supported by a server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelModeKinds {
+    pub list: Vec<char>,
+    pub always_arg: Vec<char>,
+    pub set_arg: Vec<char>,
+    pub flag: Vec<char>,
+    pub prefix: Vec<isupport::PrefixMap>,
+}
+
+impl Default for ChannelModeKinds {
+    fn default() -> Self {
+        Self {
+            list: vec!['b', 'e', 'I'],
+            always_arg: vec!['k'],
+            set_arg: vec!['l'],
+            flag: vec!['i', 'm', 'n', 'p', 's', 't'],
+            prefix: vec![
+                isupport::PrefixMap {
+                    prefix: '@',
+                    mode: 'o',
+                },
+                isupport::PrefixMap {
+                    prefix: '+',
+                    mode: 'v',
+                },
+            ],
+        }
+    }
+}
+
+impl ChannelModeKinds {
+    pub fn from_isupport(isupport: &HashMap<isupport::Kind, isupport::Parameter>) -> Self {
+        let mut kinds = Self::default();
+
+        if let Some(isupport::Parameter::CHANMODES(channel_modes)) =
+            isupport.get(&isupport::Kind::CHANMODES)
+        {
+            let group = |letter| {
+                channel_modes
+                    .iter()
+                    .find(|channel_mode| channel_mode.letter == letter)
+                    .map(|channel_mode| channel_mode.modes.chars().collect())
+                    .unwrap_or_default()
+            };
+
+            kinds.list = group('A');
+            kinds.always_arg = group('B');
+            kinds.set_arg = group('C');
+            kinds.flag = group('D');
+        }
+
+        if let Some(isupport::Parameter::PREFIX(prefix)) = isupport.get(&isupport::Kind::PREFIX) {
+            kinds.prefix.clone_from(prefix);
+        }
+
+        kinds
+    }
+
+    pub fn class(&self, mode: char) -> Option<Class> {
+        if self.prefix.iter().any(|prefix_map| prefix_map.mode == mode) {
+            Some(Class::Prefix)
+        } else if self.list.contains(&mode) {
+            Some(Class::List)
+        } else if self.always_arg.contains(&mode) {
+            Some(Class::AlwaysArg)
+        } else if self.set_arg.contains(&mode) {
+            Some(Class::SetArg)
+        } else if self.flag.contains(&mode) {
+            Some(Class::Flag)
+        } else {
+            None
+        }
+    }
+
+    pub fn takes_arg(&self, mode: char, adding: bool) -> bool {
+        match self.class(mode) {
+            Some(Class::List | Class::AlwaysArg | Class::Prefix) => true,
+            Some(Class::SetArg) => adding,
+            Some(Class::Flag) | None => false,
+        }
+    }
+
+    pub fn parse(&self, encoded: &str, args: &[String]) -> Vec<Mode<char>> {
+        parse_with(encoded, args, |c, adding| self.takes_arg(c, adding))
+    }
+}
+
+/// The modes currently set on a channel, excluding list and membership modes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelModes {
+    flags: BTreeSet<char>,
+    params: BTreeMap<char, String>,
+}
+
+impl ChannelModes {
+    /// Builds the channel modes from a full modestring, e.g. from RPL_CHANNELMODEIS.
+    pub fn from_modestring(kinds: &ChannelModeKinds, encoded: &str, args: &[String]) -> Self {
+        let mut modes = Self::default();
+        modes.apply(kinds, &kinds.parse(encoded, args));
+        modes
+    }
+
+    pub fn apply(&mut self, kinds: &ChannelModeKinds, modes: &[Mode<char>]) {
+        for mode in modes {
+            let c = *mode.value();
+
+            match (kinds.class(c), mode) {
+                (Some(Class::AlwaysArg | Class::SetArg), Mode::Add(_, arg)) => {
+                    self.params.insert(c, arg.clone().unwrap_or_default());
+                }
+                (Some(Class::AlwaysArg | Class::SetArg), Mode::Remove(_, _)) => {
+                    self.params.remove(&c);
+                }
+                (Some(Class::Flag), Mode::Add(_, _)) => {
+                    self.flags.insert(c);
+                }
+                (Some(Class::Flag), Mode::Remove(_, _)) => {
+                    self.flags.remove(&c);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_set(&self, mode: char) -> bool {
+        self.flags.contains(&mode) || self.params.contains_key(&mode)
+    }
+
+    pub fn param(&self, mode: char) -> Option<&str> {
+        self.params.get(&mode).map(String::as_str)
+    }
+
+    pub fn moderated(&self) -> bool {
+        self.is_set('m')
+    }
+
+    pub fn invite_only(&self) -> bool {
+        self.is_set('i')
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.param('k')
+    }
+
+    pub fn limit(&self) -> Option<u16> {
+        self.param('l').and_then(|limit| limit.parse().ok())
+    }
+
+    /// Starts editing these modes. Changes are collected as MODE command parameters.
+    pub fn edit(&self) -> ChannelModesEdit {
+        ChannelModesEdit {
+            current: self.clone(),
+            desired: self.clone(),
+        }
+    }
+
+    fn modes(&self) -> Vec<Mode<char>> {
+        self.flags
+            .iter()
+            .map(|c| Mode::Add(*c, None))
+            .chain(
+                self.params
+                    .iter()
+                    .map(|(c, arg)| Mode::Add(*c, Some(arg.clone()))),
+            )
+            .collect()
+    }
+}
+
+impl fmt::Display for ChannelModes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match encode(&self.modes()) {
+            Some((modestring, args)) if args.is_empty() => write!(f, "{modestring}"),
+            Some((modestring, args)) => write!(f, "{modestring} {}", args.join(" ")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Pending changes to a channel's modes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelModesEdit {
+    current: ChannelModes,
+    desired: ChannelModes,
+}
+
+impl ChannelModesEdit {
+    pub fn modes(&self) -> &ChannelModes {
+        &self.desired
+    }
+
+    pub fn set_flag(&mut self, mode: char, enabled: bool) {
+        if enabled {
+            self.desired.flags.insert(mode);
+        } else {
+            self.desired.flags.remove(&mode);
+        }
+    }
+
+    pub fn set_param(&mut self, mode: char, param: Option<String>) {
+        if let Some(param) = param.filter(|param| !param.is_empty()) {
+            self.desired.params.insert(mode, param);
+        } else {
+            self.desired.params.remove(&mode);
+        }
+    }
+
+    pub fn set_moderated(&mut self, moderated: bool) {
+        self.set_flag('m', moderated);
+    }
+
+    pub fn set_invite_only(&mut self, invite_only: bool) {
+        self.set_flag('i', invite_only);
+    }
+
+    pub fn set_key(&mut self, key: Option<String>) {
+        self.set_param('k', key);
+    }
+
+    pub fn set_limit(&mut self, limit: Option<u16>) {
+        self.set_param('l', limit.map(|limit| limit.to_string()));
+    }
+
+    /// The mode changes needed to go from the current to the desired modes.
+    pub fn changes(&self, kinds: &ChannelModeKinds) -> Vec<Mode<char>> {
+        let current = &self.current;
+        let desired = &self.desired;
+
+        let removed_flags = current
+            .flags
+            .difference(&desired.flags)
+            .map(|c| Mode::Remove(*c, None));
+        let added_flags = desired
+            .flags
+            .difference(&current.flags)
+            .map(|c| Mode::Add(*c, None));
+        let removed_params = current
+            .params
+            .iter()
+            .filter(|(c, _)| !desired.params.contains_key(c))
+            .map(|(c, arg)| Mode::Remove(*c, kinds.takes_arg(*c, false).then(|| arg.clone())));
+        let changed_params = desired
+            .params
+            .iter()
+            .filter(|(c, arg)| current.params.get(c) != Some(arg))
+            .map(|(c, arg)| Mode::Add(*c, Some(arg.clone())));
+
+        removed_flags
+            .chain(removed_params)
+            .chain(added_flags)
+            .chain(changed_params)
+            .collect()
+    }
+}
+
+/// An entry of a list mode, e.g. a ban mask.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListEntry {
+    pub mask: String,
+    pub set_by: Option<String>,
+    pub set_at: Option<DateTime<Utc>>,
+}
+
+/// The entries of a list mode, as received from the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct List {
+    pub entries: Vec<ListEntry>,
+    /// Entries are still being received
+    pub loading: bool,
+    /// Replies were asked for with [`Lists::request`] rather than by the user,
+    /// so they aren't shown as messages
+    requested: bool,
+}
+
+/// The list modes (bans, quiets, exceptions, invite exceptions) of a channel
+/// which have been received, kept up to date with later MODE changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lists(BTreeMap<char, List>);
+
+impl Lists {
+    pub fn get(&self, mode: char) -> Option<&List> {
+        self.0.get(&mode)
+    }
+
+    /// Clears a list ahead of requesting it again.
+    pub fn request(&mut self, mode: char) {
+        self.0.insert(
+            mode,
+            List {
+                entries: vec![],
+                loading: true,
+                requested: true,
+            },
+        );
+    }
+
+    /// Adds an entry from a list reply. Returns whether the reply was
+    /// requested with [`Lists::request`].
+    pub fn insert(&mut self, mode: char, entry: ListEntry) -> bool {
+        let list = self.0.entry(mode).or_default();
+
+        // The server always sends the full list, so a reply that wasn't
+        // requested replaces a list received before.
+        if !list.loading {
+            list.entries.clear();
+            list.loading = true;
+        }
+
+        list.entries.push(entry);
+        list.requested
+    }
+
+    /// Marks the end of a list reply. Returns whether the reply was
+    /// requested with [`Lists::request`].
+    pub fn end(&mut self, mode: char) -> bool {
+        let list = self.0.entry(mode).or_default();
+
+        if !list.loading {
+            list.entries.clear();
+        }
+
+        list.loading = false;
+        std::mem::take(&mut list.requested)
+    }
+
+    /// Applies MODE changes to the lists already received.
+    pub fn apply(&mut self, modes: &[Mode<char>], set_by: Option<&str>, set_at: DateTime<Utc>) {
+        for mode in modes {
+            let Some(list) = self.0.get_mut(mode.value()) else {
+                continue;
+            };
+
+            match mode {
+                Mode::Add(_, Some(mask)) => {
+                    if !list.entries.iter().any(|entry| entry.mask == *mask) {
+                        list.entries.push(ListEntry {
+                            mask: mask.clone(),
+                            set_by: set_by.map(ToString::to_string),
+                            set_at: Some(set_at),
+                        });
+                    }
+                }
+                Mode::Remove(_, Some(mask)) => {
+                    list.entries.retain(|entry| entry.mask != *mask);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Encodes modes into a modestring and its arguments.
+pub fn encode(modes: &[Mode<char>]) -> Option<(String, Vec<String>)> {
+    if modes.is_empty() {
+        return None;
+    }
+
+    let mut modestring = String::new();
+    let mut args = vec![];
+    let mut last = None;
+
+    for mode in modes {
+        let sign = match mode {
+            Mode::Add(..) => Some('+'),
+            Mode::Remove(..) => Some('-'),
+            Mode::NoPrefix(_) => None,
+        };
+
+        if sign.is_some() && sign != last {
+            modestring.extend(sign);
+            last = sign;
+        }
+
+        modestring.push(*mode.value());
+        args.extend(mode.arg().map(String::from));
+    }
+
+    Some((modestring, args))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -303,4 +724,68 @@ mod test {
             assert_eq!(modes, expected);
         }
     }
+
+    #[test]
+    fn channel_modes() {
+        let kinds = ChannelModeKinds::default();
+        let mut modes =
+            ChannelModes::from_modestring(&kinds, "+ntkl", &["secret".into(), "10".into()]);
+
+        assert!(modes.is_set('n'));
+        assert_eq!(modes.key(), Some("secret"));
+        assert_eq!(modes.limit(), Some(10));
+
+        modes.apply(&kinds, &kinds.parse("-l+m", &["nick".into()]));
+
+        assert!(modes.moderated());
+        assert_eq!(modes.limit(), None);
+        assert_eq!(modes.to_string(), "+mntk secret");
+
+        let mut edit = modes.edit();
+        edit.set_moderated(false);
+        edit.set_invite_only(true);
+        edit.set_key(None);
+        edit.set_limit(Some(25));
+
+        assert_eq!(
+            encode(&edit.changes(&kinds)),
+            Some(("-mk+il".into(), vec!["secret".into(), "25".into()]))
+        );
+    }
+
+    #[test]
+    fn lists() {
+        let entry = |mask: &str| ListEntry {
+            mask: mask.into(),
+            set_by: None,
+            set_at: None,
+        };
+        let mut lists = Lists::default();
+
+        lists.request('b');
+        assert!(lists.insert('b', entry("*!*@a")));
+        assert!(lists.end('b'));
+
+        let set_at = DateTime::default();
+        lists.apply(
+            &[
+                Mode::Add('b', Some("*!*@b".into())),
+                Mode::Remove('b', Some("*!*@a".into())),
+                Mode::Add('e', Some("*!*@c".into())),
+            ],
+            Some("op"),
+            set_at,
+        );
+
+        let bans = lists.get('b').unwrap();
+        assert_eq!(bans.entries.len(), 1);
+        assert_eq!(bans.entries[0].mask, "*!*@b");
+        assert_eq!(bans.entries[0].set_by.as_deref(), Some("op"));
+        assert!(lists.get('e').is_none());
+
+        // Replies the user asked for replace the list, and are shown
+        assert!(!lists.insert('b', entry("*!*@d")));
+        assert!(!lists.end('b'));
+        assert_eq!(lists.get('b').unwrap().entries, vec![entry("*!*@d")]);
+    }
 }
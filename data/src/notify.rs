@@ -0,0 +1,164 @@
+//! Changes to the notify list made with `/notify`, saved so they're kept
+//! over reconnects and restarts.
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::encryption;
+use crate::isupport::CaseMap;
+use crate::{compression, environment, history, Server};
+
+/// Nicknames added to and removed from the configured `monitor` list of a
+/// server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Changes {
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn add(&mut self, nick: &str, casemapping: CaseMap) {
+        let nick_eq = |other: &String| casemapping.normalize(other) == casemapping.normalize(nick);
+
+        self.removed.retain(|removed| !nick_eq(removed));
+
+        if !self.added.iter().any(nick_eq) {
+            self.added.push(nick.to_string());
+        }
+    }
+
+    pub fn remove(&mut self, nick: &str, casemapping: CaseMap) {
+        let nick_eq = |other: &String| casemapping.normalize(other) == casemapping.normalize(nick);
+
+        self.added.retain(|added| !nick_eq(added));
+
+        if !self.removed.iter().any(nick_eq) {
+            self.removed.push(nick.to_string());
+        }
+    }
+
+    /// The notify list, from the configured `monitor` list.
+    pub fn apply(&self, monitor: &[String], casemapping: CaseMap) -> Vec<String> {
+        let contains = |nicks: &[String], nick: &str| {
+            nicks
+                .iter()
+                .any(|other| casemapping.normalize(other) == casemapping.normalize(nick))
+        };
+
+        let mut list = monitor
+            .iter()
+            .filter(|nick| !contains(&self.removed, nick))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for nick in &self.added {
+            if !contains(&list, nick) {
+                list.push(nick.clone());
+            }
+        }
+
+        list
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Changes by server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Lists(HashMap<String, Changes>);
+
+impl Lists {
+    pub fn get(&self, server: &Server) -> Changes {
+        self.0.get(server.as_ref()).cloned().unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, server: &Server, changes: Changes) {
+        if changes.is_empty() {
+            self.0.remove(server.as_ref());
+        } else {
+            self.0.insert(server.to_string(), changes);
+        }
+    }
+}
+
+/// Changes saved before a restart, or none if they couldn't be read.
+pub fn load() -> Lists {
+    match read() {
+        Ok(lists) => lists,
+        Err(Error::Io(error)) if error.kind() == io::ErrorKind::NotFound => Lists::default(),
+        Err(error) => {
+            log::warn!("failed to load notify lists: {error}");
+            Lists::default()
+        }
+    }
+}
+
+fn read() -> Result<Lists, Error> {
+    let bytes = encryption::decrypt(std::fs::read(path()?)?)?;
+
+    Ok(compression::decompress(&bytes)?)
+}
+
+/// Saves `lists`, encrypted like history when a passphrase is set.
+pub async fn save(lists: Lists) -> Result<(), Error> {
+    let path = path()?;
+
+    let bytes = encryption::encrypt(compression::compress(&lists)?)?;
+
+    tokio::fs::write(path, &bytes).await?;
+
+    Ok(())
+}
+
+fn path() -> Result<PathBuf, Error> {
+    let parent = environment::data_dir();
+
+    if !parent.exists() {
+        std::fs::create_dir_all(&parent)?;
+    }
+
+    Ok(parent.join("notify-lists.json.gz"))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Compression(#[from] compression::Error),
+    #[error(transparent)]
+    History(#[from] history::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let casemapping = CaseMap::RFC1459;
+        let monitor = vec!["alice".to_string(), "bob".to_string(), "[dan]".to_string()];
+        let mut changes = Changes::default();
+
+        changes.add("carol", casemapping);
+        changes.remove("Bob", casemapping);
+        changes.remove("{DAN}", casemapping);
+        assert_eq!(changes.apply(&monitor, casemapping), vec!["alice", "carol"]);
+
+        changes.add("bob", casemapping);
+        changes.remove("CAROL", casemapping);
+        assert_eq!(changes.apply(&monitor, casemapping), vec!["alice", "bob"]);
+        assert_eq!(changes.added, vec!["bob"]);
+        assert_eq!(changes.removed, vec!["{DAN}", "CAROL"]);
+
+        // Only ASCII letters are equal under the ASCII casemapping
+        assert_eq!(
+            changes.apply(&monitor, CaseMap::ASCII),
+            vec!["alice", "bob", "[dan]"]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::buffer::Pastebin;
+
+/// Uploads the text to the pastebin, returning the URL of the paste.
+pub async fn upload(pastebin: Pastebin, text: String) -> Result<String, Error> {
+    let client = reqwest::Client::builder()
+        .user_agent("halloy")
+        .timeout(Duration::from_secs(30))
+        .build()?;
+
+    let request = client.post(&pastebin.url);
+
+    let request = match &pastebin.form_field {
+        Some(field) => request.form(&[(field.as_str(), text.as_str())]),
+        None => request.body(text),
+    };
+
+    let response = request.send().await?.error_for_status()?.text().await?;
+
+    let url = response.trim();
+
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(url.to_string())
+    } else {
+        Err(Error::InvalidResponse)
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Request(String),
+    #[error("pastebin didn't respond with a URL")]
+    InvalidResponse,
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error.to_string())
+    }
+}
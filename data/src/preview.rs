@@ -0,0 +1,331 @@
+use std::fmt;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use url::Url;
+
+use crate::config;
+
+/// Bytes of a page read while looking for its title
+const MAX_BODY_SIZE: usize = 256 * 1024;
+/// Bytes of oEmbed metadata read
+const MAX_OEMBED_SIZE: usize = 64 * 1024;
+/// Redirects followed to reach a page
+const MAX_REDIRECTS: usize = 3;
+/// Characters of a title shown
+const MAX_TITLE_CHARS: usize = 200;
+
+static TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<(meta|link)\s[^>]*>").unwrap());
+static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)([a-z:_-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>/]+))"#).unwrap()
+});
+
+/// Title of a linked page, shown under the message linking it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub title: String,
+    pub site: Option<String>,
+}
+
+impl fmt::Display for Preview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.site {
+            Some(site) => write!(f, "{site} — {}", self.title),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum State {
+    Loading,
+    Loaded(Preview),
+    Error,
+}
+
+/// Fetches the preview of `url`, preferring its oEmbed metadata over the
+/// page title when the page advertises it.
+pub async fn fetch(url: Url, config: config::Preview) -> Result<Preview, Error> {
+    let redirects = config.clone();
+
+    let client = reqwest::Client::builder()
+        .user_agent("halloy")
+        .timeout(config.timeout())
+        // Redirects may lead to a domain that isn't allowed, checked before
+        // following them
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > MAX_REDIRECTS || !redirects.is_allowed(attempt.url()) {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()?;
+
+    let mut response = client.get(url).send().await?.error_for_status()?;
+
+    if response.status().is_redirection() || !config.is_allowed(response.url()) {
+        return Err(Error::Denied);
+    }
+
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+
+    if !is_html {
+        return Err(Error::NotHtml);
+    }
+
+    let body = read_body(&mut response, MAX_BODY_SIZE).await?;
+
+    let html = String::from_utf8_lossy(&body);
+    let page = parse(&html);
+
+    let oembed = page
+        .oembed
+        .as_deref()
+        .and_then(|href| response.url().join(href).ok())
+        .filter(|url| config.is_allowed(url));
+
+    if let Some(oembed) = oembed {
+        if let Ok(preview) = fetch_oembed(&client, oembed).await {
+            return Ok(preview);
+        }
+    }
+
+    page.preview.ok_or(Error::NoTitle)
+}
+
+async fn fetch_oembed(client: &reqwest::Client, url: Url) -> Result<Preview, Error> {
+    #[derive(Deserialize)]
+    struct OEmbed {
+        title: Option<String>,
+        author_name: Option<String>,
+        provider_name: Option<String>,
+    }
+
+    let mut response = client.get(url).send().await?.error_for_status()?;
+
+    let body = read_body(&mut response, MAX_OEMBED_SIZE + 1).await?;
+
+    if body.len() > MAX_OEMBED_SIZE {
+        return Err(Error::TooLarge);
+    }
+
+    let oembed = serde_json::from_slice::<OEmbed>(&body)?;
+
+    let title = oembed
+        .title
+        .and_then(|title| clean(&title))
+        .ok_or(Error::NoTitle)?;
+
+    Ok(Preview {
+        title: match oembed.author_name.and_then(|author| clean(&author)) {
+            Some(author) => format!("{title} by {author}"),
+            None => title,
+        },
+        site: oembed.provider_name.and_then(|provider| clean(&provider)),
+    })
+}
+
+/// Reads the body of `response`, stopping once `limit` bytes are read.
+async fn read_body(response: &mut reqwest::Response, limit: usize) -> Result<Vec<u8>, Error> {
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+
+        if body.len() >= limit {
+            body.truncate(limit);
+            break;
+        }
+    }
+
+    Ok(body)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Page {
+    preview: Option<Preview>,
+    /// Location of the oEmbed metadata, relative to the page
+    oembed: Option<String>,
+}
+
+fn parse(html: &str) -> Page {
+    let mut og_title = None;
+    let mut site = None;
+    let mut oembed = None;
+
+    for tag in TAG_REGEX.captures_iter(html) {
+        let attributes = ATTRIBUTE_REGEX
+            .captures_iter(&tag[0])
+            .filter_map(|captures| {
+                let name = captures.get(1)?.as_str().to_ascii_lowercase();
+                let value = captures
+                    .get(2)
+                    .or_else(|| captures.get(3))
+                    .or_else(|| captures.get(4))?
+                    .as_str();
+
+                Some((name, value))
+            })
+            .collect::<Vec<_>>();
+
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find_map(|(key, value)| (key == name).then_some(*value))
+        };
+
+        if tag[1].eq_ignore_ascii_case("meta") {
+            match attribute("property").or_else(|| attribute("name")) {
+                Some("og:title") => og_title = attribute("content").and_then(clean),
+                Some("og:site_name") => site = attribute("content").and_then(clean),
+                _ => {}
+            }
+        } else if attribute("type")
+            .is_some_and(|kind| kind.eq_ignore_ascii_case("application/json+oembed"))
+        {
+            oembed = attribute("href").map(decode_entities);
+        }
+    }
+
+    let title = og_title.or_else(|| {
+        TITLE_REGEX
+            .captures(html)
+            .and_then(|captures| clean(&captures[1]))
+    });
+
+    Page {
+        preview: title.map(|title| Preview { title, site }),
+        oembed,
+    }
+}
+
+/// Decodes entities and collapses whitespace, truncating long text.
+fn clean(text: &str) -> Option<String> {
+    let text = decode_entities(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if text.is_empty() {
+        return None;
+    }
+
+    if text.chars().count() > MAX_TITLE_CHARS {
+        Some(format!(
+            "{}…",
+            text.chars().take(MAX_TITLE_CHARS).collect::<String>()
+        ))
+    } else {
+        Some(text)
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let name = &rest[1..end];
+
+            let char = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => name
+                    .strip_prefix("#x")
+                    .or_else(|| name.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| name.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+
+            char.map(|char| (char, end))
+        });
+
+        match entity {
+            Some((char, end)) => {
+                decoded.push(char);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("domain isn't allowed")]
+    Denied,
+    #[error("not an html page")]
+    NotHtml,
+    #[error("page has no title")]
+    NoTitle,
+    #[error("oEmbed metadata is too large")]
+    TooLarge,
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_page() {
+        let tests = [
+            (
+                "<html><head><title>\n  Halloy &amp; friends\n</title></head></html>",
+                Page {
+                    preview: Some(Preview {
+                        title: "Halloy & friends".into(),
+                        site: None,
+                    }),
+                    oembed: None,
+                },
+            ),
+            (
+                r#"<head>
+                <title>Ignored</title>
+                <meta property="og:title" content="It&#39;s a &quot;title&quot;">
+                <meta property='og:site_name' content='Example'>
+                <link rel="alternate" type="application/json+oembed" href="/oembed?url=a&amp;format=json">
+                </head>"#,
+                Page {
+                    preview: Some(Preview {
+                        title: "It's a \"title\"".into(),
+                        site: Some("Example".into()),
+                    }),
+                    oembed: Some("/oembed?url=a&format=json".into()),
+                },
+            ),
+            ("<p>no title</p>", Page::default()),
+        ];
+
+        for (html, expected) in tests {
+            assert_eq!(parse(html), expected);
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::{fmt, str};
 use tokio::fs;
 use tokio::process::Command;
 
 use futures::channel::mpsc::Sender;
 use irc::proto;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::config;
 use crate::config::server::Sasl;
@@ -13,12 +14,25 @@ use crate::config::Error;
 
 pub type Handle = Sender<proto::Message>;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-pub struct Server(String);
+/// A server's name, shared by everything referring to it.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Server(Arc<str>);
 
 impl From<&str> for Server {
     fn from(value: &str) -> Self {
-        Server(value.to_string())
+        Server(Arc::from(value))
+    }
+}
+
+impl Serialize for Server {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Server {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|server| Server(Arc::from(server)))
     }
 }
 
@@ -52,7 +66,7 @@ impl<'a> From<(&'a Server, &'a config::Server)> for Entry {
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct Map(BTreeMap<Server, config::Server>);
 
-async fn read_from_command(pass_command: &str) -> Result<String, Error> {
+pub(crate) async fn read_from_command(pass_command: &str) -> Result<String, Error> {
     let output = if cfg!(target_os = "windows") {
         Command::new("cmd")
             .arg("/C")
@@ -86,6 +100,10 @@ impl Map {
         self.0.remove(server);
     }
 
+    pub fn get(&self, server: &Server) -> Option<&config::Server> {
+        self.0.get(server)
+    }
+
     pub fn contains(&self, server: &Server) -> bool {
         self.0.contains_key(server)
     }
@@ -98,8 +116,83 @@ impl Map {
         self.0.iter().map(Entry::from)
     }
 
+    /// The entry named `server`, or connecting to the same address and port
+    /// as `config`.
+    pub fn find(&self, server: &Server, config: &config::Server) -> Option<Entry> {
+        self.entries().find(|entry| {
+            entry.server == *server
+                || (entry.config.server.eq_ignore_ascii_case(&config.server)
+                    && entry.config.port == config.port)
+        })
+    }
+
+    /// The settings of `channel` on `server`, the defaults if it has none.
+    pub fn channel_settings(
+        &self,
+        server: &Server,
+        channel: &str,
+    ) -> config::server::ChannelSettings {
+        self.get(server)
+            .map(|config| config.channels.settings(channel))
+            .unwrap_or_default()
+    }
+
+    /// Uses `nickname` on every server.
+    pub fn set_nickname(&mut self, nickname: &str) {
+        for config in self.0.values_mut() {
+            config.nickname = nickname.to_string();
+        }
+    }
+
+    /// Uses the reasons in `[reasons]` on servers without their own.
+    pub fn set_default_reasons(&mut self, reasons: &config::Reasons) {
+        for config in self.0.values_mut() {
+            if config.part_message.is_none() {
+                config.part_message.clone_from(&reasons.part);
+            }
+            if config.quit_message.is_none() {
+                config.quit_message.clone_from(&reasons.quit);
+            }
+            if config.kick_reason.is_none() {
+                config.kick_reason.clone_from(&reasons.kick);
+            }
+        }
+    }
+
+    /// Adds the imported ignore lists to the servers they were imported for.
+    pub fn apply_imported(&mut self, imported: &BTreeMap<String, config::ignore::list::List>) {
+        for (server, config) in self.0.iter_mut() {
+            if let Some(list) = imported.get(server.as_ref()) {
+                list.apply(config);
+            }
+        }
+    }
+
+    /// Keeps the credentials asked for in `previous`.
+    pub fn keep_prompted(&mut self, previous: &Map) {
+        for (server, config) in self.0.iter_mut() {
+            if let Some(previous) = previous.get(server) {
+                config.keep_prompted(previous);
+            }
+        }
+    }
+
     pub async fn read_passwords(&mut self) -> Result<(), Error> {
         for (_, config) in self.0.iter_mut() {
+            if config.password_prompt
+                && (config.password.is_some()
+                    || config.password_file.is_some()
+                    || config.password_command.is_some())
+            {
+                return Err(Error::DuplicatePassword);
+            }
+            if config.nick_password_prompt
+                && (config.nick_password.is_some()
+                    || config.nick_password_file.is_some()
+                    || config.nick_password_command.is_some())
+            {
+                return Err(Error::DuplicateNickPassword);
+            }
             if let Some(pass_file) = &config.password_file {
                 if config.password.is_some() || config.password_command.is_some() {
                     return Err(Error::DuplicatePassword);
@@ -132,12 +225,21 @@ impl Map {
                         password: Some(_),
                         password_file: None,
                         password_command: None,
+                        password_prompt: false,
+                        ..
+                    }
+                    | Sasl::Plain {
+                        password: None,
+                        password_file: None,
+                        password_command: None,
+                        password_prompt: true,
                         ..
                     } => {}
                     Sasl::Plain {
                         password: password @ None,
                         password_file: Some(pass_file),
                         password_command: None,
+                        password_prompt: false,
                         ..
                     } => {
                         let pass = fs::read_to_string(pass_file).await?;
@@ -147,6 +249,7 @@ impl Map {
                         password: password @ None,
                         password_file: None,
                         password_command: Some(pass_command),
+                        password_prompt: false,
                         ..
                     } => {
                         let pass = read_from_command(pass_command).await?;
@@ -160,6 +263,22 @@ impl Map {
                     }
                 }
             }
+            if let Some(q_auth) = &mut config.q_auth {
+                match (
+                    &q_auth.password,
+                    &q_auth.password_file,
+                    &q_auth.password_command,
+                ) {
+                    (Some(_), None, None) => {}
+                    (None, Some(pass_file), None) => {
+                        q_auth.password = Some(fs::read_to_string(pass_file).await?);
+                    }
+                    (None, None, Some(pass_command)) => {
+                        q_auth.password = Some(read_from_command(pass_command).await?);
+                    }
+                    _ => return Err(Error::DuplicateQAuthPassword),
+                }
+            }
         }
         Ok(())
     }
@@ -0,0 +1,131 @@
+//! NickServ and ChanServ, as run by Atheme and Anope.
+use crate::User;
+
+pub mod quakenet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    NickServ,
+    ChanServ,
+}
+
+impl Service {
+    pub fn nick(&self) -> &'static str {
+        match self {
+            Service::NickServ => "NickServ",
+            Service::ChanServ => "ChanServ",
+        }
+    }
+
+    fn sent(&self, user: &User) -> bool {
+        user.nickname().as_ref().eq_ignore_ascii_case(self.nick())
+    }
+}
+
+/// Services packages, told apart by how they word their replies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Implementation {
+    Atheme,
+    Anope,
+}
+
+/// A reply from NickServ we act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply {
+    /// We're identified, for networks which don't tell us we're logged in
+    /// otherwise
+    Identified,
+    /// Identifying failed, so we won't be logged in
+    WrongPassword,
+    /// Our nickname is registered and we should identify
+    IdentifyRequested(Option<Implementation>),
+}
+
+/// Reads a notice from `user`, if it's a NickServ reply we know.
+pub fn reply(user: &User, text: &str) -> Option<Reply> {
+    if !Service::NickServ.sent(user) {
+        return None;
+    }
+
+    let text = text.to_lowercase();
+    let contains = |phrases: &[&str]| phrases.iter().any(|phrase| text.contains(phrase));
+
+    if contains(&[
+        "you are now identified",
+        "you are now logged in",
+        "password accepted",
+    ]) {
+        Some(Reply::Identified)
+    } else if contains(&[
+        "invalid password",
+        "password incorrect",
+        "incorrect password",
+    ]) {
+        Some(Reply::WrongPassword)
+    } else if text.contains("this nickname is registered and protected") {
+        Some(Reply::IdentifyRequested(Some(Implementation::Anope)))
+    } else if text.contains("this nickname is registered") {
+        Some(Reply::IdentifyRequested(
+            text.contains("identify via")
+                .then_some(Implementation::Atheme),
+        ))
+    } else {
+        None
+    }
+}
+
+/// The text of an IDENTIFY command, with `account` when it isn't our nickname.
+pub fn identify(account: Option<&str>, password: &str) -> String {
+    match account {
+        Some(account) => format!("IDENTIFY {account} {password}"),
+        None => format!("IDENTIFY {password}"),
+    }
+}
+
+pub fn register(password: &str, email: &str) -> String {
+    format!("REGISTER {password} {email}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::Nick;
+
+    #[test]
+    fn replies() {
+        let nickserv = User::from(Nick::from("NickServ"));
+        let tests = [
+            (
+                "You are now identified for \x02halloy\x02.",
+                Some(Reply::Identified),
+            ),
+            (
+                "Password accepted - you are now recognized.",
+                Some(Reply::Identified),
+            ),
+            (
+                "Invalid password for \x02halloy\x02.",
+                Some(Reply::WrongPassword),
+            ),
+            ("Password incorrect.", Some(Reply::WrongPassword)),
+            (
+                "This nickname is registered. Please choose a different nickname, or \
+                 identify via \x02/msg NickServ IDENTIFY halloy <password>\x02",
+                Some(Reply::IdentifyRequested(Some(Implementation::Atheme))),
+            ),
+            (
+                "This nickname is registered and protected. If it is your nick, type \
+                 \x02/msg NickServ IDENTIFY \x1fpassword\x1f\x02.",
+                Some(Reply::IdentifyRequested(Some(Implementation::Anope))),
+            ),
+            ("Last login from: halloy@example.org", None),
+        ];
+
+        for (text, expected) in tests {
+            assert_eq!(reply(&nickserv, text), expected, "{text}");
+        }
+
+        let impostor = User::from(Nick::from("NickServer"));
+        assert_eq!(reply(&impostor, "You are now identified"), None);
+    }
+}
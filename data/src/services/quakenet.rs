@@ -0,0 +1,129 @@
+//! QuakeNet's Q, which has no SASL, so we log in with CHALLENGEAUTH and the
+//! password never crosses the connection.
+use ring::{digest, hmac};
+
+use crate::User;
+
+/// Where Q takes logins, addressed in full so no one else receives them.
+pub const Q: &str = "Q@CServe.quakenet.org";
+
+/// The only algorithm we answer challenges with.
+const ALGORITHM: &str = "HMAC-SHA-256";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// A challenge to answer, if it can be answered with HMAC-SHA-256
+    Challenge(Option<String>),
+    LoggedIn,
+    Failed,
+}
+
+/// Reads a notice from `user`, if it's a reply from Q we know.
+pub fn reply(user: &User, text: &str) -> Option<Reply> {
+    if user.nickname().as_ref() != "Q" {
+        return None;
+    }
+
+    if let Some(rest) = text.strip_prefix("CHALLENGE ") {
+        let mut words = rest.split_ascii_whitespace();
+        let challenge = words.next()?;
+
+        Some(Reply::Challenge(
+            words
+                .any(|algorithm| algorithm == ALGORITHM)
+                .then(|| challenge.to_string()),
+        ))
+    } else if text.starts_with("You are now logged in as") {
+        Some(Reply::LoggedIn)
+    } else if text.starts_with("Username or password incorrect") {
+        Some(Reply::Failed)
+    } else {
+        None
+    }
+}
+
+/// The text of a CHALLENGEAUTH answering `challenge`.
+pub fn challenge_auth(username: &str, password: &str, challenge: &str) -> String {
+    let username = lower(username);
+    // Q only uses the first 10 characters of a password
+    let password = password.chars().take(10).collect::<String>();
+
+    let key = sha256(&format!("{username}:{}", sha256(&password)));
+    let response = hmac::sign(
+        &hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()),
+        challenge.as_bytes(),
+    );
+
+    format!(
+        "CHALLENGEAUTH {username} {} {ALGORITHM}",
+        hex::encode(response.as_ref())
+    )
+}
+
+fn sha256(text: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, text.as_bytes()))
+}
+
+/// Lowercases like QuakeNet, with rfc1459 casemapping.
+fn lower(username: &str) -> String {
+    username
+        .chars()
+        .map(|c| match c {
+            '[' => '{',
+            ']' => '}',
+            '\\' => '|',
+            '^' => '~',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::Nick;
+
+    #[test]
+    fn answers_challenge() {
+        assert_eq!(
+            challenge_auth("Halloy[]", "hunter2isverylong", "3afabede5c2859fd821e315f889d9a6c"),
+            "CHALLENGEAUTH halloy{} cf09e74f7888112feb7dcc99a55ba93e6fcd637afa84aa20c206696f53656577 \
+             HMAC-SHA-256"
+        );
+    }
+
+    #[test]
+    fn replies() {
+        let q = User::from(Nick::from("Q"));
+
+        assert_eq!(
+            reply(
+                &q,
+                "CHALLENGE 3afabede5c2859fd821e315f889d9a6c HMAC-MD5 HMAC-SHA-1 HMAC-SHA-256 \
+                 LEGACY-MD5"
+            ),
+            Some(Reply::Challenge(Some(
+                "3afabede5c2859fd821e315f889d9a6c".to_string()
+            )))
+        );
+        assert_eq!(
+            reply(&q, "CHALLENGE 3afabede5c2859fd821e315f889d9a6c LEGACY-MD5"),
+            Some(Reply::Challenge(None))
+        );
+        assert_eq!(
+            reply(&q, "You are now logged in as halloy."),
+            Some(Reply::LoggedIn)
+        );
+        assert_eq!(
+            reply(&q, "Username or password incorrect."),
+            Some(Reply::Failed)
+        );
+        assert_eq!(
+            reply(
+                &User::from(Nick::from("Quser")),
+                "You are now logged in as halloy."
+            ),
+            None
+        );
+    }
+}
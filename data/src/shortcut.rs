@@ -42,6 +42,67 @@ pub enum Command {
     Logs,
     ThemeEditor,
     Highlight,
+    Search,
+    FormatBold,
+    FormatItalics,
+    FormatUnderline,
+    FormatColor,
+    FormatReset,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    ScrollUp,
+    ScrollDown,
+    ScrollToStart,
+    ScrollToEnd,
+    Yank,
+    InsertMode,
+    NormalMode,
+}
+
+/// Mode of the vim-style keybinding scheme.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Keys are typed into the focused input.
+    #[default]
+    Insert,
+    /// Unmodified keys navigate buffers instead.
+    Normal,
+}
+
+/// Outcome of a key pressed in normal mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normal {
+    Command(Command),
+    /// The key starts a sequence, e.g. the first `g` of `gg`.
+    Pending(char),
+    Ignored,
+}
+
+/// Maps a key typed in normal mode, following any `pending` key.
+pub fn normal(pending: Option<char>, key: &str) -> Normal {
+    use Command::*;
+
+    let command = match (pending, key) {
+        (None, "j") => ScrollDown,
+        (None, "k") => ScrollUp,
+        (None, "G") => ScrollToEnd,
+        (None, "H") => MoveLeft,
+        (None, "J") => MoveDown,
+        (None, "K") => MoveUp,
+        (None, "L") => MoveRight,
+        (None, "/") => Search,
+        (None, "i" | "a") => InsertMode,
+        (None, "g") => return Normal::Pending('g'),
+        (None, "y") => return Normal::Pending('y'),
+        (Some('g'), "g") => ScrollToStart,
+        (Some('g'), "t") => CycleNextBuffer,
+        (Some('g'), "T") => CyclePreviousBuffer,
+        (Some('y'), "y") => Yank,
+        _ => return Normal::Ignored,
+    };
+
+    Normal::Command(command)
 }
 
 macro_rules! default {
@@ -132,6 +193,15 @@ impl KeyBind {
     default!(logs, "l", COMMAND);
     default!(theme_editor, "t", COMMAND);
     default!(highlight, "i", COMMAND);
+    default!(search, "f", COMMAND);
+    default!(format_bold, "b", COMMAND);
+    default!(format_italics, "i", COMMAND | SHIFT);
+    default!(format_underline, "u", COMMAND);
+    default!(format_color, "k", COMMAND | SHIFT);
+    default!(format_reset, "o", COMMAND);
+    default!(zoom_in, "=", COMMAND);
+    default!(zoom_out, "-", COMMAND);
+    default!(zoom_reset, "0", COMMAND);
 
     pub fn is_pressed(
         &self,
@@ -412,3 +482,25 @@ pub enum ParseError {
     #[error("invalid modifier: {0}")]
     InvalidModifier(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_sequences() {
+        assert_eq!(normal(None, "j"), Normal::Command(Command::ScrollDown));
+        assert_eq!(normal(None, "g"), Normal::Pending('g'));
+        assert_eq!(
+            normal(Some('g'), "g"),
+            Normal::Command(Command::ScrollToStart)
+        );
+        assert_eq!(
+            normal(Some('g'), "T"),
+            Normal::Command(Command::CyclePreviousBuffer)
+        );
+        assert_eq!(normal(Some('y'), "y"), Normal::Command(Command::Yank));
+        assert_eq!(normal(Some('y'), "g"), Normal::Ignored);
+        assert_eq!(normal(None, "x"), Normal::Ignored);
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use futures::never::Never;
+use std::collections::VecDeque;
 use std::time::Duration;
 
 use futures::channel::mpsc;
@@ -11,7 +12,7 @@ use tokio::time::{self, Instant, Interval};
 use crate::client::Client;
 use crate::server::Server;
 use crate::time::Posix;
-use crate::{config, message, server};
+use crate::{config, console, message, server};
 
 pub type Result<T = Update, E = Error> = std::result::Result<T, E>;
 
@@ -40,6 +41,7 @@ pub enum Update {
         sent_time: DateTime<Utc>,
     },
     MessagesReceived(Server, Vec<message::Encoded>),
+    Console(Server, Vec<console::Line>),
     Quit(Server, Option<String>),
 }
 
@@ -50,6 +52,7 @@ enum State {
     Connected {
         stream: Stream,
         batch: Batch,
+        queue: Queue,
         ping_time: Interval,
         ping_timeout: Option<Interval>,
     },
@@ -58,8 +61,9 @@ enum State {
 
 enum Input {
     IrcMessage(Result<codec::ParseResult, codec::Error>),
-    Batch(Vec<message::Encoded>),
+    Batch(Vec<message::Encoded>, Vec<console::Line>),
     Send(proto::Message),
+    Throttled(Vec<proto::Message>),
     Ping,
     PingTimeout,
 }
@@ -105,6 +109,12 @@ async fn _run(
     loop {
         match &mut state {
             State::Disconnected { last_retry } => {
+                // Stop retrying once the frontend has dropped this stream
+                if sender.is_closed() {
+                    state = State::Quit;
+                    continue;
+                }
+
                 if let Some(last_retry) = last_retry.as_ref() {
                     let remaining = reconnect_delay.saturating_sub(last_retry.elapsed());
 
@@ -114,21 +124,29 @@ async fn _run(
                 }
 
                 match connect(server.clone(), config.clone(), proxy.clone()).await {
-                    Ok((stream, client)) => {
+                    Ok((mut stream, client)) => {
                         log::info!("[{server}] connected");
 
-                        let _ = sender.unbounded_send(Update::Connected {
-                            server: server.clone(),
-                            client,
-                            is_initial,
-                            sent_time: Utc::now(),
-                        });
+                        if sender
+                            .unbounded_send(Update::Connected {
+                                server: server.clone(),
+                                client,
+                                is_initial,
+                                sent_time: Utc::now(),
+                            })
+                            .is_err()
+                        {
+                            let _ = stream.connection.send(command!("QUIT")).await;
+                            state = State::Quit;
+                            continue;
+                        }
 
                         is_initial = false;
 
                         state = State::Connected {
                             stream,
                             batch: Batch::new(),
+                            queue: Queue::new(),
                             ping_timeout: None,
                             ping_time: ping_time_interval(config.ping_time),
                         };
@@ -155,6 +173,7 @@ async fn _run(
             State::Connected {
                 stream,
                 batch,
+                queue,
                 ping_time,
                 ping_timeout,
             } => {
@@ -163,7 +182,10 @@ async fn _run(
                         (&mut stream.connection).map(Input::IrcMessage).boxed(),
                         (&mut stream.receiver).map(Input::Send).boxed(),
                         ping_time.tick().into_stream().map(|_| Input::Ping).boxed(),
-                        batch.map(Input::Batch).boxed(),
+                        batch
+                            .map(|(messages, lines)| Input::Batch(messages, lines))
+                            .boxed(),
+                        queue.map(Input::Throttled).boxed(),
                     ]);
 
                     if let Some(timeout) = ping_timeout.as_mut() {
@@ -179,10 +201,17 @@ async fn _run(
                     select.next().await.expect("stream input")
                 };
 
+                if let Input::IrcMessage(Ok(Ok(message))) = &input {
+                    batch.console.push(console::Line::received(message));
+                }
+
                 match input {
                     Input::IrcMessage(Ok(Ok(message))) => match message.command {
                         proto::Command::PING(token) => {
-                            let _ = stream.connection.send(command!("PONG", token)).await;
+                            let pong = command!("PONG", token);
+
+                            batch.console.push(console::Line::sent(&pong));
+                            let _ = stream.connection.send(pong).await;
                         }
                         proto::Command::PONG(_, token) => {
                             let token = token.unwrap_or_default();
@@ -203,6 +232,11 @@ async fn _run(
                             };
                         }
                         _ => {
+                            // Text is sent as UTF-8 from now on, whatever the encoding
+                            if is_utf8_only(&message) {
+                                stream.connection.codec_mut().set_utf8_only();
+                            }
+
                             batch.messages.push(message.into());
                         }
                     },
@@ -221,21 +255,45 @@ async fn _run(
                             last_retry: Some(Instant::now()),
                         };
                     }
-                    Input::Batch(messages) => {
-                        let _ = sender
-                            .unbounded_send(Update::MessagesReceived(server.clone(), messages));
+                    Input::Batch(messages, lines) => {
+                        if !messages.is_empty() {
+                            let _ = sender
+                                .unbounded_send(Update::MessagesReceived(server.clone(), messages));
+                        }
+                        if !lines.is_empty() {
+                            let _ = sender.unbounded_send(Update::Console(server.clone(), lines));
+                        }
                     }
                     Input::Send(message) => {
                         if let Command::QUIT(reason) = &message.command {
                             let reason = reason.clone();
 
+                            // Whatever the user sent before quitting goes out first
+                            let queued = queue.pending.flush();
+
+                            // Batch is dropped on quit, so report the lines right away
+                            let mut lines = std::mem::take(&mut batch.console);
+                            lines.extend(queued.iter().map(console::Line::sent));
+                            lines.push(console::Line::sent(&message));
+                            let _ = sender.unbounded_send(Update::Console(server.clone(), lines));
+
+                            for queued in queued {
+                                let _ = stream.connection.send(queued).await;
+                            }
                             let _ = stream.connection.send(message).await;
                             let _ = sender.unbounded_send(Update::Quit(server.clone(), reason));
 
                             log::info!("[{server}] quit");
 
                             state = State::Quit;
-                        } else {
+                        } else if let Some(message) = queue.pending.push(message) {
+                            batch.console.push(console::Line::sent(&message));
+                            let _ = stream.connection.send(message).await;
+                        }
+                    }
+                    Input::Throttled(messages) => {
+                        for message in messages {
+                            batch.console.push(console::Line::sent(&message));
                             let _ = stream.connection.send(message).await;
                         }
                     }
@@ -243,7 +301,10 @@ async fn _run(
                         let now = Posix::now().as_nanos().to_string();
                         log::trace!("[{server}] ping sent: {now}");
 
-                        let _ = stream.connection.send(command!("PING", now)).await;
+                        let ping = command!("PING", now);
+
+                        batch.console.push(console::Line::sent(&ping));
+                        let _ = stream.connection.send(ping).await;
 
                         if ping_timeout.is_none() {
                             *ping_timeout = Some(ping_timeout_interval(config.ping_timeout));
@@ -276,7 +337,7 @@ async fn connect(
     config: config::Server,
     proxy: Option<config::Proxy>,
 ) -> Result<(Stream, Client), connection::Error> {
-    let connection = Connection::new(config.connection(proxy), irc::Codec).await?;
+    let connection = Connection::new(config.connection(proxy), config.codec()).await?;
 
     let (sender, receiver) = mpsc::channel(100);
 
@@ -297,6 +358,7 @@ async fn connect(
 struct Batch {
     interval: Interval,
     messages: Vec<message::Encoded>,
+    console: Vec<console::Line>,
 }
 
 impl Batch {
@@ -309,12 +371,13 @@ impl Batch {
                 Duration::from_millis(Self::INTERVAL_MILLIS),
             ),
             messages: vec![],
+            console: vec![],
         }
     }
 }
 
 impl futures::Stream for Batch {
-    type Item = Vec<message::Encoded>;
+    type Item = (Vec<message::Encoded>, Vec<console::Line>);
 
     fn poll_next(
         self: std::pin::Pin<&mut Self>,
@@ -325,11 +388,12 @@ impl futures::Stream for Batch {
         match batch.interval.poll_tick(cx) {
             std::task::Poll::Ready(_) => {
                 let messages = std::mem::take(&mut batch.messages);
+                let console = std::mem::take(&mut batch.console);
 
-                if messages.is_empty() {
+                if messages.is_empty() && console.is_empty() {
                     std::task::Poll::Pending
                 } else {
-                    std::task::Poll::Ready(Some(messages))
+                    std::task::Poll::Ready(Some((messages, console)))
                 }
             }
             std::task::Poll::Pending => std::task::Poll::Pending,
@@ -337,6 +401,135 @@ impl futures::Stream for Batch {
     }
 }
 
+/// Flood protection for outgoing messages, allowing a burst of messages before
+/// sending them at a steady rate.
+struct Queue {
+    interval: Interval,
+    pending: Pending,
+}
+
+impl Queue {
+    const INTERVAL_MILLIS: u64 = 500;
+
+    fn new() -> Self {
+        let mut interval = time::interval_at(
+            Instant::now() + Duration::from_millis(Self::INTERVAL_MILLIS),
+            Duration::from_millis(Self::INTERVAL_MILLIS),
+        );
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+        Self {
+            interval,
+            pending: Pending::default(),
+        }
+    }
+}
+
+impl futures::Stream for Queue {
+    type Item = Vec<proto::Message>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let queue = self.get_mut();
+
+        while queue.interval.poll_tick(cx).is_ready() {
+            queue.pending.tokens = (queue.pending.tokens + 1).min(Pending::BURST);
+        }
+
+        match queue.pending.pop() {
+            Some(messages) => std::task::Poll::Ready(Some(messages)),
+            None => std::task::Poll::Pending,
+        }
+    }
+}
+
+/// Messages held back by the flood protection, sent in the order they were
+/// sent by the client.
+struct Pending {
+    tokens: usize,
+    messages: VecDeque<proto::Message>,
+}
+
+impl Default for Pending {
+    fn default() -> Self {
+        Self {
+            tokens: Self::BURST,
+            messages: VecDeque::new(),
+        }
+    }
+}
+
+impl Pending {
+    const BURST: usize = 5;
+
+    /// Queues `message`, or gives it back when it can be sent right away:
+    /// when it isn't throttled and nothing sent before it is waiting.
+    fn push(&mut self, message: proto::Message) -> Option<proto::Message> {
+        if self.messages.is_empty() && !is_throttled(&message) {
+            Some(message)
+        } else {
+            self.messages.push_back(message);
+            None
+        }
+    }
+
+    /// Takes the next message that can be sent, or a whole `draft/multiline`
+    /// batch once its end is queued, which costs a single token.
+    fn pop(&mut self) -> Option<Vec<proto::Message>> {
+        let front = self.messages.front()?;
+
+        let len = match batch_start(front) {
+            Some(reference) => {
+                1 + self
+                    .messages
+                    .iter()
+                    .position(|message| is_batch_end(message, reference))?
+            }
+            None => 1,
+        };
+
+        if is_throttled(front) {
+            if self.tokens == 0 {
+                return None;
+            }
+
+            self.tokens -= 1;
+        }
+
+        Some(self.messages.drain(..len).collect())
+    }
+
+    /// Takes everything queued, whatever the tokens left.
+    fn flush(&mut self) -> Vec<proto::Message> {
+        self.messages.drain(..).collect()
+    }
+}
+
+/// Only messages sent on behalf of the user are throttled, along with the
+/// batches they're sent in.
+fn is_throttled(message: &proto::Message) -> bool {
+    matches!(
+        message.command,
+        Command::PRIVMSG(..) | Command::NOTICE(..) | Command::BATCH(..)
+    )
+}
+
+fn batch_start(message: &proto::Message) -> Option<&str> {
+    match &message.command {
+        Command::BATCH(reference, _) => reference.strip_prefix('+'),
+        _ => None,
+    }
+}
+
+fn is_batch_end(message: &proto::Message, reference: &str) -> bool {
+    matches!(
+        &message.command,
+        Command::BATCH(end, _) if end.strip_prefix('-') == Some(reference)
+    )
+}
+
 fn ping_time_interval(secs: u64) -> Interval {
     time::interval_at(
         Instant::now() + Duration::from_secs(secs),
@@ -350,3 +543,79 @@ fn ping_timeout_interval(secs: u64) -> Interval {
         Duration::from_secs(secs),
     )
 }
+
+/// Whether `message` is an ISUPPORT reply with the UTF8ONLY token.
+fn is_utf8_only(message: &proto::Message) -> bool {
+    matches!(
+        &message.command,
+        Command::Numeric(proto::command::Numeric::RPL_ISUPPORT, args)
+            if args.iter().any(|arg| arg == "UTF8ONLY")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(pending: &mut Pending) -> Vec<String> {
+        std::iter::from_fn(|| pending.pop())
+            .flatten()
+            .map(|message| message.command.command())
+            .collect()
+    }
+
+    #[test]
+    fn sent_in_order() {
+        let mut pending = Pending {
+            tokens: 1,
+            ..Pending::default()
+        };
+
+        assert!(pending.push(command!("PRIVMSG", "#halloy", "hi")).is_none());
+        assert!(pending.push(command!("PRIVMSG", "#halloy", "o/")).is_none());
+        // Held back until the messages sent before it are
+        assert!(pending.push(command!("PART", "#halloy")).is_none());
+
+        assert_eq!(sent(&mut pending), vec!["PRIVMSG"]);
+
+        pending.tokens = 1;
+        assert_eq!(sent(&mut pending), vec!["PRIVMSG", "PART"]);
+
+        // Nothing waiting anymore
+        assert!(pending.push(command!("JOIN", "#halloy")).is_some());
+    }
+
+    #[test]
+    fn multiline_batch() {
+        let mut pending = Pending {
+            tokens: 1,
+            ..Pending::default()
+        };
+
+        pending.push(command!("BATCH", "+ref", "draft/multiline", "#halloy"));
+        pending.push(command!("PRIVMSG", "#halloy", "hi"));
+        pending.push(command!("PRIVMSG", "#halloy", "o/"));
+
+        // Held back until the batch ends
+        assert!(pending.pop().is_none());
+
+        pending.push(command!("BATCH", "-ref"));
+        pending.push(command!("PART", "#halloy"));
+
+        let batch = pending.pop().unwrap();
+        assert_eq!(
+            batch
+                .into_iter()
+                .map(|message| message.command.parameters().join(" "))
+                .collect::<Vec<_>>(),
+            vec![
+                "+ref draft/multiline #halloy",
+                "#halloy hi",
+                "#halloy o/",
+                "-ref"
+            ]
+        );
+
+        assert_eq!(sent(&mut pending), vec!["PART"]);
+    }
+}
@@ -35,12 +35,6 @@ pub fn theme(colors: &theme::Colors) -> String {
     format!("halloy:///theme?e={}", colors.encode_base64())
 }
 
-impl Url {
-    pub fn find_in(mut args: impl Iterator<Item = String>) -> Option<Self> {
-        args.find_map(|arg| arg.parse().ok())
-    }
-}
-
 impl FromStr for Url {
     type Err = ();
 
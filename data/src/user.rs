@@ -1,14 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::Hash;
+use std::sync::{Arc, Mutex, PoisonError};
 
 use thiserror::Error;
 
 use irc::proto;
 use itertools::sorted;
-use serde::{Deserialize, Serialize};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{appearance::theme::Colors, buffer, config::buffer::UsernameFormat, mode};
+use crate::{appearance::theme::Colors, buffer, config::buffer::UsernameFormat, isupport, mode};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(into = "String")]
@@ -71,21 +73,30 @@ impl<'a> TryFrom<&'a str> for User {
     type Error = TryFromUserError;
 
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        Self::parse(value, DEFAULT_PREFIXES)
+    }
+}
+
+impl User {
+    /// Parses a user led by the membership prefixes of the server's PREFIX,
+    /// e.g. from a NAMES reply.
+    pub fn parse(value: &str, prefixes: &[isupport::PrefixMap]) -> Result<Self, TryFromUserError> {
         if value.is_empty() {
-            return Err(Self::Error::NicknameEmpty);
+            return Err(TryFromUserError::NicknameEmpty);
         }
 
-        let Some(index) =
-            value.find(|c: char| c.is_alphabetic() || "[\\]^_`{|}*".find(c).is_some())
-        else {
-            return Err(Self::Error::NicknameInvalidCharacter);
+        let Some(index) = value.find(|c: char| {
+            !prefixes.iter().any(|prefix_map| prefix_map.prefix == c)
+                && (c.is_alphabetic() || "[\\]^_`{|}*".find(c).is_some())
+        }) else {
+            return Err(TryFromUserError::NicknameInvalidCharacter);
         };
 
         let (access_levels, rest) = (&value[..index], &value[index..]);
 
         let access_levels = access_levels
             .chars()
-            .filter_map(|c| AccessLevel::try_from(c).ok())
+            .filter_map(|c| AccessLevel::from_prefix(c, prefixes))
             .collect::<HashSet<_>>();
 
         let (nickname, username, hostname) = match (rest.find('!'), rest.find('@')) {
@@ -167,9 +178,11 @@ impl User {
     }
 
     pub fn display(&self, with_access_levels: bool) -> String {
-        match with_access_levels {
-            true => format!("{}{}", self.highest_access_level(), self.nickname()),
-            false => self.nickname().to_string(),
+        match self.highest_access_level() {
+            Some(access_level) if with_access_levels => {
+                format!("{access_level}{}", self.nickname())
+            }
+            _ => self.nickname().to_string(),
         }
     }
 
@@ -222,27 +235,25 @@ impl User {
         }
     }
 
-    pub fn highest_access_level(&self) -> AccessLevel {
-        self.access_levels
-            .iter()
-            .max()
-            .copied()
-            .unwrap_or(AccessLevel::Member)
+    /// The most privileged membership prefix, if any.
+    pub fn highest_access_level(&self) -> Option<AccessLevel> {
+        self.access_levels.iter().max().copied()
     }
 
-    pub fn has_access_level(&self, access_level: AccessLevel) -> bool {
-        self.access_levels.contains(&access_level)
+    /// Whether the user holds the membership given by `mode`, e.g. `o` for op.
+    pub fn has_access_mode(&self, mode: char) -> bool {
+        self.access_levels
+            .iter()
+            .any(|access_level| access_level.mode() == mode)
     }
 
-    pub fn update_access_level(&mut self, operation: mode::Operation, mode: mode::Channel) {
-        if let Ok(level) = AccessLevel::try_from(mode) {
-            match operation {
-                mode::Operation::Add => {
-                    self.access_levels.insert(level);
-                }
-                mode::Operation::Remove => {
-                    self.access_levels.remove(&level);
-                }
+    pub fn update_access_level(&mut self, operation: mode::Operation, access_level: AccessLevel) {
+        match operation {
+            mode::Operation::Add => {
+                self.access_levels.insert(access_level);
+            }
+            mode::Operation::Remove => {
+                self.access_levels.remove(&access_level);
             }
         }
     }
@@ -281,14 +292,78 @@ impl From<proto::User> for User {
     }
 }
 
+/// The users of a channel, kept sorted as they join, leave or change so
+/// large channels aren't sorted over again after every message. Users are
+/// looked up by their nickname under the server's casemapping.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelUsers {
+    casemap: isupport::CaseMap,
+    lookup: HashMap<String, User>,
+    sorted: Vec<User>,
+}
+
+impl ChannelUsers {
+    pub fn new(casemap: isupport::CaseMap) -> Self {
+        Self {
+            casemap,
+            ..Self::default()
+        }
+    }
+
+    fn key(&self, user: &User) -> String {
+        self.casemap.normalize(user.nickname.as_ref())
+    }
+
+    /// Adds `user`, unless a user with the same nickname is already in the
+    /// channel.
+    pub fn insert(&mut self, user: User) -> bool {
+        let key = self.key(&user);
+
+        if self.lookup.contains_key(&key) {
+            return false;
+        }
+
+        let index = self.sorted.partition_point(|other| *other < user);
+        self.sorted.insert(index, user.clone());
+        self.lookup.insert(key, user);
+
+        true
+    }
+
+    pub fn take(&mut self, user: &User) -> Option<User> {
+        let user = self.lookup.remove(&self.key(user))?;
+
+        if let Ok(index) = self.sorted.binary_search(&user) {
+            self.sorted.remove(index);
+        }
+
+        Some(user)
+    }
+
+    pub fn remove(&mut self, user: &User) -> bool {
+        self.take(user).is_some()
+    }
+
+    pub fn get(&self, user: &User) -> Option<&User> {
+        self.lookup.get(&self.key(user))
+    }
+
+    /// Users sorted by access level, then nickname.
+    pub fn as_slice(&self) -> &[User] {
+        &self.sorted
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NickColor {
     pub seed: Option<String>,
     pub color: iced_core::Color,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct Nick(String);
+/// A nickname, shared with every other copy of it so the users and messages
+/// holding it don't each need their own.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Nick(Arc<str>);
 
 impl fmt::Display for Nick {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -298,19 +373,80 @@ impl fmt::Display for Nick {
 
 impl AsRef<str> for Nick {
     fn as_ref(&self) -> &str {
-        self.0.as_ref()
+        &self.0
     }
 }
 
 impl From<String> for Nick {
     fn from(nick: String) -> Self {
-        Nick(nick)
+        Nick::from(nick.as_str())
     }
 }
 
 impl<'a> From<&'a str> for Nick {
     fn from(nick: &'a str) -> Self {
-        Nick(nick.to_string())
+        Nick(
+            NICKS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .intern(nick),
+        )
+    }
+}
+
+impl Serialize for Nick {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Nick {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Nick;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a nickname")
+            }
+
+            // Looked up as borrowed, only allocated if not already in use
+            fn visit_str<E: serde::de::Error>(self, nick: &str) -> Result<Nick, E> {
+                Ok(Nick::from(nick))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+static NICKS: Lazy<Mutex<Pool>> = Lazy::new(Mutex::default);
+
+/// # nicknames held before those no longer in use are dropped
+const MIN_POOL_SIZE: usize = 1024;
+
+#[derive(Debug, Default)]
+struct Pool {
+    nicks: HashSet<Arc<str>>,
+    prune_at: usize,
+}
+
+impl Pool {
+    fn intern(&mut self, nick: &str) -> Arc<str> {
+        if let Some(nick) = self.nicks.get(nick) {
+            return nick.clone();
+        }
+
+        if self.nicks.len() >= self.prune_at {
+            self.nicks.retain(|nick| Arc::strong_count(nick) > 1);
+            self.prune_at = (self.nicks.len() * 2).max(MIN_POOL_SIZE);
+        }
+
+        let nick = Arc::<str>::from(nick);
+        self.nicks.insert(nick.clone());
+
+        nick
     }
 }
 
@@ -325,7 +461,7 @@ impl<'a> From<&'a str> for NickRef<'a> {
 
 impl<'a> NickRef<'a> {
     pub fn to_owned(self) -> Nick {
-        Nick(self.0.to_string())
+        Nick::from(self.0)
     }
 }
 
@@ -355,62 +491,78 @@ impl<'a> Ord for NickRef<'a> {
 
 impl<'a> PartialEq<Nick> for NickRef<'a> {
     fn eq(&self, other: &Nick) -> bool {
-        self.0.eq(other.0.as_str())
+        self.0.eq(other.as_ref())
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub enum AccessLevel {
-    Member,
-    Voice,
-    HalfOp,
-    Oper,
-    Admin,
-    Owner,
+/// Membership prefixes assumed when the server's PREFIX isn't known, e.g.
+/// for users read back from history.
+const DEFAULT_PREFIXES: &[isupport::PrefixMap] = &[
+    isupport::PrefixMap {
+        prefix: '~',
+        mode: 'q',
+    },
+    isupport::PrefixMap {
+        prefix: '&',
+        mode: 'a',
+    },
+    isupport::PrefixMap {
+        prefix: '@',
+        mode: 'o',
+    },
+    isupport::PrefixMap {
+        prefix: '%',
+        mode: 'h',
+    },
+    isupport::PrefixMap {
+        prefix: '+',
+        mode: 'v',
+    },
+];
+
+/// A channel membership prefix (e.g. `@` for op), ranked by its place in the
+/// server's PREFIX, which lists them from most to least privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccessLevel {
+    rank: usize,
+    prefix: char,
+    mode: char,
 }
 
-impl std::fmt::Display for AccessLevel {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let access_level = match self {
-            AccessLevel::Owner => "~",
-            AccessLevel::Admin => "&",
-            AccessLevel::Oper => "@",
-            AccessLevel::HalfOp => "%",
-            AccessLevel::Voice => "+",
-            AccessLevel::Member => "",
-        };
+impl AccessLevel {
+    pub fn from_prefix(prefix: char, prefixes: &[isupport::PrefixMap]) -> Option<Self> {
+        Self::find(prefixes, |prefix_map| prefix_map.prefix == prefix)
+    }
 
-        write!(f, "{}", access_level)
+    pub fn from_mode(mode: char, prefixes: &[isupport::PrefixMap]) -> Option<Self> {
+        Self::find(prefixes, |prefix_map| prefix_map.mode == mode)
     }
-}
 
-impl TryFrom<char> for AccessLevel {
-    type Error = ();
+    fn find(
+        prefixes: &[isupport::PrefixMap],
+        f: impl Fn(&isupport::PrefixMap) -> bool,
+    ) -> Option<Self> {
+        let index = prefixes.iter().position(f)?;
 
-    fn try_from(c: char) -> Result<AccessLevel, ()> {
-        match c {
-            '~' => Ok(AccessLevel::Owner),
-            '&' => Ok(AccessLevel::Admin),
-            '@' => Ok(AccessLevel::Oper),
-            '%' => Ok(AccessLevel::HalfOp),
-            '+' => Ok(AccessLevel::Voice),
-            _ => Err(()),
-        }
+        Some(Self {
+            rank: prefixes.len() - index,
+            prefix: prefixes[index].prefix,
+            mode: prefixes[index].mode,
+        })
     }
-}
 
-impl TryFrom<mode::Channel> for AccessLevel {
-    type Error = ();
+    pub fn prefix(&self) -> char {
+        self.prefix
+    }
 
-    fn try_from(mode: mode::Channel) -> Result<Self, Self::Error> {
-        Ok(match mode {
-            mode::Channel::Founder => Self::Owner,
-            mode::Channel::Admin => Self::Admin,
-            mode::Channel::Oper => Self::Oper,
-            mode::Channel::Halfop => Self::HalfOp,
-            mode::Channel::Voice => Self::Voice,
-            _ => return Err(()),
-        })
+    pub fn mode(&self) -> char {
+        self.mode
+    }
+}
+
+impl std::fmt::Display for AccessLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.prefix)
     }
 }
 
@@ -418,6 +570,10 @@ impl TryFrom<mode::Channel> for AccessLevel {
 mod tests {
     use super::*;
 
+    fn level(prefix: char) -> AccessLevel {
+        AccessLevel::from_prefix(prefix, DEFAULT_PREFIXES).unwrap()
+    }
+
     #[test]
     fn string_try_from() {
         let tests = [
@@ -427,10 +583,7 @@ mod tests {
                     username: None,
                     hostname: None,
                     accountname: None,
-                    access_levels: HashSet::<AccessLevel>::from([
-                        AccessLevel::Oper,
-                        AccessLevel::Voice,
-                    ]),
+                    access_levels: HashSet::<AccessLevel>::from([level('@'), level('+')]),
                     away: false,
                 },
                 "+@dan",
@@ -441,7 +594,7 @@ mod tests {
                     username: Some("d".into()),
                     hostname: Some("localhost".into()),
                     accountname: None,
-                    access_levels: HashSet::<AccessLevel>::from([AccessLevel::Oper]),
+                    access_levels: HashSet::<AccessLevel>::from([level('@')]),
                     away: false,
                 },
                 "@d@n!d@localhost",
@@ -474,10 +627,7 @@ mod tests {
                     username: Some("~foobar".into()),
                     hostname: Some("12.521.212.521".into()),
                     accountname: None,
-                    access_levels: HashSet::<AccessLevel>::from([
-                        AccessLevel::Oper,
-                        AccessLevel::Voice,
-                    ]),
+                    access_levels: HashSet::<AccessLevel>::from([level('@'), level('+')]),
                     away: false,
                 },
                 "+@foobar!~foobar@12.521.212.521",
@@ -511,7 +661,7 @@ mod tests {
                     username: Some("the.flu".into()),
                     hostname: Some("in.you".into()),
                     accountname: None,
-                    access_levels: HashSet::<AccessLevel>::from([AccessLevel::Oper]),
+                    access_levels: HashSet::<AccessLevel>::from([level('@')]),
                     away: false,
                 },
             ),
@@ -569,4 +719,94 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn channel_users_sorted() {
+        let mut users = ChannelUsers::default();
+
+        for nick in ["@carol", "bob", "+dave", "alice"] {
+            assert!(users.insert(User::try_from(nick).unwrap()));
+        }
+        assert!(!users.insert(User::try_from("bob").unwrap()));
+
+        let nicks = |users: &ChannelUsers| {
+            users
+                .as_slice()
+                .iter()
+                .map(|user| user.nickname().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(nicks(&users), ["carol", "dave", "alice", "bob"]);
+
+        let mut bob = users.take(&User::try_from("bob").unwrap()).unwrap();
+        bob.update_access_level(mode::Operation::Add, level('@'));
+        users.insert(bob);
+
+        assert!(users.remove(&User::try_from("dave").unwrap()));
+        assert!(!users.remove(&User::try_from("dave").unwrap()));
+
+        assert_eq!(nicks(&users), ["bob", "carol", "alice"]);
+
+        let mut users = ChannelUsers::new(isupport::CaseMap::RFC1459);
+        users.insert(User::try_from("Dan[away]").unwrap());
+
+        assert!(users.get(&User::try_from("dan{AWAY}").unwrap()).is_some());
+        assert!(users.remove(&User::try_from("DAN{away}").unwrap()));
+        assert!(users.as_slice().is_empty());
+    }
+
+    #[test]
+    fn custom_prefixes() {
+        // e.g. IRCnet's channel creator, and a `*` prefix
+        let prefixes = [('!', 'O'), ('*', 'Y'), ('@', 'o'), ('+', 'v')]
+            .map(|(prefix, mode)| isupport::PrefixMap { prefix, mode });
+
+        let user = User::parse("*@dan", &prefixes).unwrap();
+        assert_eq!(user.nickname(), Nick::from("dan"));
+        assert_eq!(user.display(true), "*dan");
+        assert!(user.has_access_mode('o'));
+
+        let mut users = ChannelUsers::default();
+
+        for nick in ["+carol", "@bob", "!alice"] {
+            users.insert(User::parse(nick, &prefixes).unwrap());
+        }
+
+        let mut carol = users.take(&User::try_from("carol").unwrap()).unwrap();
+        carol.update_access_level(
+            mode::Operation::Add,
+            AccessLevel::from_mode('Y', &prefixes).unwrap(),
+        );
+        users.insert(carol);
+
+        let nicks = users
+            .as_slice()
+            .iter()
+            .map(|user| user.display(true))
+            .collect::<Vec<_>>();
+
+        assert_eq!(nicks, ["!alice", "*carol", "@bob"]);
+    }
+
+    #[test]
+    fn nicks_shared() {
+        let nick = Nick::from("dan");
+        let decoded: Nick = serde_json::from_str(r#""dan""#).unwrap();
+
+        assert!(Arc::ptr_eq(&nick.0, &decoded.0));
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), r#""dan""#);
+
+        let mut pool = Pool {
+            nicks: HashSet::new(),
+            prune_at: 2,
+        };
+        let kept = pool.intern("kept");
+        pool.intern("dropped");
+        pool.intern("new");
+
+        assert!(pool.nicks.contains("kept"));
+        assert!(!pool.nicks.contains("dropped"));
+        assert!(Arc::ptr_eq(&kept, &pool.intern("kept")));
+    }
 }
@@ -0,0 +1,107 @@
+use std::future::Future;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::config::notification::{Context, Webhook};
+
+/// How long to wait for the webhook to respond
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Posts a notification to the webhook, with `context` substituted into its
+/// headers and body.
+pub fn send(webhook: &Webhook, context: Context) -> impl Future<Output = Result<(), Error>> {
+    let client = reqwest::Client::builder()
+        .user_agent("halloy")
+        .timeout(TIMEOUT)
+        .build();
+
+    let request = client.map(|client| {
+        let mut request = client.post(&webhook.url);
+
+        for (name, value) in &webhook.headers {
+            request = request.header(name, context.substitute(value));
+        }
+
+        if let Some(json) = &webhook.json {
+            request.json(&substitute_json(json, &context))
+        } else if let Some(body) = &webhook.body {
+            request.body(context.substitute(body))
+        } else {
+            request.body(context.message.to_string())
+        }
+    });
+
+    async move {
+        request?.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+fn substitute_json(value: &Value, context: &Context) -> Value {
+    match value {
+        Value::String(string) => Value::String(context.substitute(string)),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| substitute_json(value, context))
+                .collect(),
+        ),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), substitute_json(value, context)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Request(reqwest::Error),
+}
+
+// The url often holds a token, keep it out of the logs
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Request(error.without_url())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let webhook: Webhook = toml::from_str(
+            r#"
+            url = "https://gotify.example.com/message"
+            json = { title = "{nick} in {channel}", message = "{message}", priority = 5 }
+            "#,
+        )
+        .unwrap();
+
+        let json = substitute_json(
+            webhook.json.as_ref().unwrap(),
+            &Context {
+                server: "libera",
+                channel: "#halloy",
+                nick: "casper",
+                message: "say \"hi\"",
+            },
+        );
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "title": "casper in #halloy",
+                "message": "say \"hi\"",
+                "priority": 5,
+            })
+        );
+    }
+}
@@ -365,10 +365,13 @@ pub enum Numeric {
     RPL_TRYAGAIN = 263,
     RPL_LOCALUSERS = 265,
     RPL_GLOBALUSERS = 266,
+    RPL_SILELIST = 271,
+    RPL_ENDOFSILELIST = 272,
     RPL_WHOISCERTFP = 276,
     RPL_NONE = 300,
     RPL_AWAY = 301,
     RPL_USERHOST = 302,
+    RPL_ISON = 303,
     RPL_UNAWAY = 305,
     RPL_NOWAWAY = 306,
     RPL_WHOREPLY = 352,
@@ -456,6 +459,7 @@ pub enum Numeric {
     ERR_NOOPERHOST = 491,
     ERR_UMODEUNKNOWNFLAG = 501,
     ERR_USERSDONTMATCH = 502,
+    ERR_SILELISTFULL = 511,
     ERR_HELPNOTFOUND = 524,
     ERR_INVALIDKEY = 525,
     RPL_STARTTLS = 670,
@@ -466,6 +470,8 @@ pub enum Numeric {
     RPL_HELPTXT = 705,
     RPL_ENDOFHELP = 706,
     ERR_NOPRIVS = 723,
+    RPL_QUIETLIST = 728,
+    RPL_ENDOFQUIETLIST = 729,
     RPL_MONONLINE = 730,
     RPL_MONOFFLINE = 731,
     RPL_MONLIST = 732,
@@ -511,10 +517,13 @@ impl TryFrom<u16> for Numeric {
             263 => RPL_TRYAGAIN,
             265 => RPL_LOCALUSERS,
             266 => RPL_GLOBALUSERS,
+            271 => RPL_SILELIST,
+            272 => RPL_ENDOFSILELIST,
             276 => RPL_WHOISCERTFP,
             300 => RPL_NONE,
             301 => RPL_AWAY,
             302 => RPL_USERHOST,
+            303 => RPL_ISON,
             305 => RPL_UNAWAY,
             306 => RPL_NOWAWAY,
             352 => RPL_WHOREPLY,
@@ -602,6 +611,7 @@ impl TryFrom<u16> for Numeric {
             491 => ERR_NOOPERHOST,
             501 => ERR_UMODEUNKNOWNFLAG,
             502 => ERR_USERSDONTMATCH,
+            511 => ERR_SILELISTFULL,
             524 => ERR_HELPNOTFOUND,
             525 => ERR_INVALIDKEY,
             670 => RPL_STARTTLS,
@@ -612,6 +622,8 @@ impl TryFrom<u16> for Numeric {
             705 => RPL_HELPTXT,
             706 => RPL_ENDOFHELP,
             723 => ERR_NOPRIVS,
+            728 => RPL_QUIETLIST,
+            729 => RPL_ENDOFQUIETLIST,
             730 => RPL_MONONLINE,
             731 => RPL_MONOFFLINE,
             732 => RPL_MONLIST,
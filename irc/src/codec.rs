@@ -6,7 +6,47 @@ use tokio_util::codec::{Decoder, Encoder};
 
 pub type ParseResult<T = Message, E = parse::Error> = std::result::Result<T, E>;
 
-pub struct Codec;
+/// A text encoding, by its label such as "utf-8" or "windows-1252".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding(&'static encoding_rs::Encoding);
+
+impl Encoding {
+    pub const UTF_8: Self = Self(encoding_rs::UTF_8);
+
+    pub fn for_label(label: &str) -> Option<Self> {
+        encoding_rs::Encoding::for_label(label.as_bytes()).map(Self)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Codec {
+    /// Encoding of lines sent and received
+    encoding: Encoding,
+    /// Encoding of received lines that aren't valid UTF-8, when that's the encoding
+    fallback: Option<Encoding>,
+}
+
+impl Codec {
+    pub fn new(encoding: Encoding, fallback: Option<Encoding>) -> Self {
+        Self { encoding, fallback }
+    }
+
+    /// Uses UTF-8 only, for servers that require it (UTF8ONLY).
+    pub fn set_utf8_only(&mut self) {
+        self.encoding = Encoding::UTF_8;
+        self.fallback = None;
+    }
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::new(Encoding::UTF_8, None)
+    }
+}
 
 impl Decoder for Codec {
     type Item = ParseResult;
@@ -19,7 +59,21 @@ impl Decoder for Codec {
 
         let bytes = Vec::from(src.split_to(pos + 2));
 
-        Ok(Some(parse::message_bytes(bytes)))
+        if self.encoding != Encoding::UTF_8 {
+            let (text, _) = self.encoding.0.decode_without_bom_handling(&bytes);
+
+            return Ok(Some(parse::message(&text)));
+        }
+
+        match (String::from_utf8(bytes), self.fallback) {
+            (Ok(text), _) => Ok(Some(parse::message(&text))),
+            (Err(error), Some(fallback)) => {
+                let (text, _) = fallback.0.decode_without_bom_handling(error.as_bytes());
+
+                Ok(Some(parse::message(&text)))
+            }
+            (Err(error), None) => Ok(Some(parse::message_bytes(error.into_bytes()))),
+        }
     }
 }
 
@@ -29,7 +83,13 @@ impl Encoder<Message> for Codec {
     fn encode(&mut self, message: Message, dst: &mut BytesMut) -> Result<(), Self::Error> {
         let encoded = format::message(message);
 
-        dst.extend(encoded.into_bytes());
+        if self.encoding == Encoding::UTF_8 {
+            dst.extend(encoded.into_bytes());
+        } else {
+            let (bytes, _, _) = self.encoding.0.encode(&encoded);
+
+            dst.extend_from_slice(&bytes);
+        }
 
         Ok(())
     }
@@ -40,3 +100,99 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] io::Error),
 }
+
+#[cfg(test)]
+mod test {
+    use proto::{command, Command};
+
+    use super::*;
+
+    fn windows_1252() -> Encoding {
+        Encoding::for_label("windows-1252").unwrap()
+    }
+
+    fn decode(codec: &mut Codec, bytes: &[u8]) -> ParseResult {
+        codec.decode(&mut BytesMut::from(bytes)).unwrap().unwrap()
+    }
+
+    fn encode(codec: &mut Codec, message: Message) -> BytesMut {
+        let mut bytes = BytesMut::new();
+        codec.encode(message, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip_windows_1252() {
+        let mut codec = Codec::new(windows_1252(), None);
+
+        let bytes = encode(
+            &mut codec,
+            command("PRIVMSG", vec!["#halloy".into(), "café €5".into()]),
+        );
+        assert_eq!(&bytes[..], b"PRIVMSG #halloy :caf\xe9 \x805\r\n");
+
+        let message = decode(&mut codec, &bytes).unwrap();
+        assert_eq!(
+            message.command,
+            Command::PRIVMSG("#halloy".into(), "café €5".into())
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let line = b":dan!d@host PRIVMSG #halloy :caf\xe9\r\n";
+
+        let mut codec = Codec::default();
+        assert!(matches!(
+            decode(&mut codec, line),
+            Err(parse::Error::InvalidUtf8(_))
+        ));
+
+        let mut codec = Codec::new(Encoding::UTF_8, Some(windows_1252()));
+        assert_eq!(
+            decode(&mut codec, line).unwrap().command,
+            Command::PRIVMSG("#halloy".into(), "café".into())
+        );
+
+        // Valid UTF-8 is left to UTF-8 when there's a fallback
+        assert_eq!(
+            decode(
+                &mut codec,
+                ":dan!d@host PRIVMSG #halloy :café\r\n".as_bytes()
+            )
+            .unwrap()
+            .command,
+            Command::PRIVMSG("#halloy".into(), "café".into())
+        );
+    }
+
+    #[test]
+    fn utf8_only() {
+        let mut codec = Codec::new(windows_1252(), Some(windows_1252()));
+        codec.set_utf8_only();
+
+        let bytes = encode(
+            &mut codec,
+            command("PRIVMSG", vec!["#halloy".into(), "café".into()]),
+        );
+        assert_eq!(&bytes[..], "PRIVMSG #halloy café\r\n".as_bytes());
+
+        assert!(matches!(
+            decode(&mut codec, b"PRIVMSG #halloy :caf\xe9\r\n"),
+            Err(parse::Error::InvalidUtf8(_))
+        ));
+    }
+
+    #[test]
+    fn partial_line() {
+        let mut codec = Codec::default();
+        let mut bytes = BytesMut::from(&b"PING :halloy\r\nPI"[..]);
+
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap().unwrap().unwrap().command,
+            Command::PING("halloy".into())
+        );
+        assert!(codec.decode(&mut bytes).unwrap().is_none());
+        assert_eq!(&bytes[..], b"PI");
+    }
+}
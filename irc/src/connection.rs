@@ -87,6 +87,13 @@ impl<Codec> Connection<Codec> {
         }
     }
 
+    pub fn codec_mut(&mut self) -> &mut Codec {
+        match self {
+            Connection::Tls(framed) => framed.codec_mut(),
+            Connection::Unsecured(framed) => framed.codec_mut(),
+        }
+    }
+
     pub async fn shutdown(self) -> Result<(), Error> {
         match self {
             Connection::Tls(framed) => {
@@ -100,6 +107,41 @@ impl<Codec> Connection<Codec> {
     }
 }
 
+/// Listener bound to the first available port of a range, for DCC flows
+/// where the port is advertised before the connection is accepted.
+pub struct Listener<Codec> {
+    listener: TcpListener,
+    codec: Codec,
+}
+
+impl<Codec> Listener<Codec> {
+    pub async fn bind(
+        address: IpAddr,
+        ports: impl IntoIterator<Item = u16>,
+        codec: Codec,
+    ) -> Result<(u16, Self), Error> {
+        let mut error = None;
+
+        for port in ports {
+            match TcpListener::bind((address, port)).await {
+                Ok(listener) => return Ok((port, Self { listener, codec })),
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(error
+            .unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrNotAvailable))
+            .into())
+    }
+
+    /// Returns a single connection once accepted.
+    pub async fn accept(self) -> Result<Connection<Codec>, Error> {
+        let (tcp, _remote) = self.listener.accept().await?;
+
+        Ok(Connection::Unsecured(Framed::new(tcp, self.codec)))
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("tls error: {0}")]
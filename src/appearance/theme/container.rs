@@ -96,6 +96,30 @@ pub fn highlight(theme: &Theme) -> Style {
     }
 }
 
+pub fn search_match(theme: &Theme, current: bool) -> Style {
+    Style {
+        background: Some(Background::Color(theme.colors().buffer.selection)),
+        border: Border {
+            color: theme.colors().buffer.border_selected,
+            width: if current { 1.0 } else { 0.0 },
+            radius: 0.0.into(),
+        },
+        ..Default::default()
+    }
+}
+
+pub fn color_swatch(theme: &Theme, color: Color) -> Style {
+    Style {
+        background: Some(Background::Color(color)),
+        border: Border {
+            radius: 2.0.into(),
+            width: 1.0,
+            color: theme.colors().general.border,
+        },
+        ..Default::default()
+    }
+}
+
 pub fn general(theme: &Theme) -> Style {
     Style {
         background: Some(Background::Color(theme.colors().general.background)),
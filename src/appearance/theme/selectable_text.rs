@@ -35,6 +35,16 @@ pub fn action(theme: &Theme) -> Style {
     }
 }
 
+/// Text of soft ignored users.
+pub fn dimmed(theme: &Theme) -> Style {
+    let color = text::secondary(theme).color;
+
+    Style {
+        color,
+        selection_color: theme.colors().buffer.selection,
+    }
+}
+
 pub fn tertiary(theme: &Theme) -> Style {
     let color = text::tertiary(theme).color;
 
@@ -71,6 +81,7 @@ pub fn server(theme: &Theme, server: Option<&message::source::Server>) -> Style
             message::source::server::Kind::Quit => colors.quit,
             message::source::server::Kind::ReplyTopic => colors.reply_topic,
             message::source::server::Kind::ChangeHost => colors.change_host,
+            message::source::server::Kind::ChangeNick => None,
             message::source::server::Kind::MonitoredOnline => colors.monitored_online,
             message::source::server::Kind::MonitoredOffline => colors.monitored_offline,
         })
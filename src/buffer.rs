@@ -1,27 +1,33 @@
 pub use data::buffer::{Internal, Settings, Upstream};
 use data::user::Nick;
-use data::{buffer, file_transfer, history, message, Config};
+use data::{buffer, command, file_transfer, history, message, Config};
 use iced::Task;
 
 pub use self::channel::Channel;
+pub use self::console::Console;
 pub use self::file_transfers::FileTransfers;
 pub use self::highlights::Highlights;
 pub use self::logs::Logs;
+pub use self::mode_lists::ModeLists;
 pub use self::query::Query;
 pub use self::server::Server;
+pub use self::urls::Urls;
 use crate::screen::dashboard::sidebar;
 use crate::widget::Element;
 use crate::Theme;
 
 pub mod channel;
+pub mod console;
 pub mod empty;
 pub mod file_transfers;
 pub mod highlights;
-mod input_view;
+pub mod input_view;
 pub mod logs;
+pub mod mode_lists;
 pub mod query;
 mod scroll_view;
 pub mod server;
+pub mod urls;
 pub mod user_context;
 
 #[derive(Clone)]
@@ -33,6 +39,9 @@ pub enum Buffer {
     FileTransfers(FileTransfers),
     Logs(Logs),
     Highlights(Highlights),
+    Urls(Urls),
+    ModeLists(ModeLists),
+    Console(Console),
 }
 
 #[derive(Debug, Clone)]
@@ -43,13 +52,18 @@ pub enum Message {
     FileTransfers(file_transfers::Message),
     Logs(logs::Message),
     Highlights(highlights::Message),
+    Urls(urls::Message),
+    ModeLists(mode_lists::Message),
+    Console(console::Message),
 }
 
 pub enum Event {
     UserContext(user_context::Event),
     OpenChannel(String),
-    GoToMessage(data::Server, String, message::Hash),
+    GoToMessage(buffer::Upstream, message::Hash),
     History(Task<history::manager::Message>),
+    Layout(command::Layout, Task<history::manager::Message>),
+    ResendFile(file_transfer::Id),
 }
 
 impl Buffer {
@@ -62,18 +76,28 @@ impl Buffer {
             Buffer::Channel(state) => Some(&state.buffer),
             Buffer::Server(state) => Some(&state.buffer),
             Buffer::Query(state) => Some(&state.buffer),
-            Buffer::Empty | Buffer::FileTransfers(_) | Buffer::Logs(_) | Buffer::Highlights(_) => {
-                None
-            }
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Logs(_)
+            | Buffer::Highlights(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => None,
         }
     }
 
     pub fn internal(&self) -> Option<buffer::Internal> {
         match self {
-            Buffer::Empty | Buffer::Channel(_) | Buffer::Server(_) | Buffer::Query(_) => None,
+            Buffer::Empty
+            | Buffer::Channel(_)
+            | Buffer::Server(_)
+            | Buffer::Query(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => None,
             Buffer::FileTransfers(_) => Some(buffer::Internal::FileTransfers),
             Buffer::Logs(_) => Some(buffer::Internal::Logs),
             Buffer::Highlights(_) => Some(buffer::Internal::Highlights),
+            Buffer::Urls(_) => Some(buffer::Internal::Urls),
         }
     }
 
@@ -88,6 +112,14 @@ impl Buffer {
             }
             Buffer::Logs(_) => Some(data::Buffer::Internal(buffer::Internal::Logs)),
             Buffer::Highlights(_) => Some(data::Buffer::Internal(buffer::Internal::Highlights)),
+            Buffer::Urls(_) => Some(data::Buffer::Internal(buffer::Internal::Urls)),
+            Buffer::ModeLists(state) => Some(data::Buffer::ModeLists {
+                server: state.server.clone(),
+                channel: state.channel.clone(),
+            }),
+            Buffer::Console(state) => Some(data::Buffer::Console {
+                server: state.server.clone(),
+            }),
         }
     }
 
@@ -97,16 +129,19 @@ impl Buffer {
         clients: &mut data::client::Map,
         history: &mut history::Manager,
         file_transfers: &mut file_transfer::Manager,
+        settings: &Settings,
         config: &Config,
     ) -> (Task<Message>, Option<Event>) {
         match (self, message) {
             (Buffer::Channel(state), Message::Channel(message)) => {
-                let (command, event) = state.update(message, clients, history, config);
+                let (command, event) =
+                    state.update(message, clients, history, &settings.channel, config);
 
                 let event = event.map(|event| match event {
                     channel::Event::UserContext(event) => Event::UserContext(event),
                     channel::Event::OpenChannel(channel) => Event::OpenChannel(channel),
                     channel::Event::History(task) => Event::History(task),
+                    channel::Event::Layout(layout, task) => Event::Layout(layout, task),
                 });
 
                 (command.map(Message::Channel), event)
@@ -118,6 +153,7 @@ impl Buffer {
                     server::Event::UserContext(event) => Event::UserContext(event),
                     server::Event::OpenChannel(channel) => Event::OpenChannel(channel),
                     server::Event::History(task) => Event::History(task),
+                    server::Event::Layout(layout, task) => Event::Layout(layout, task),
                 });
 
                 (command.map(Message::Server), event)
@@ -129,17 +165,22 @@ impl Buffer {
                     query::Event::UserContext(event) => Event::UserContext(event),
                     query::Event::OpenChannel(channel) => Event::OpenChannel(channel),
                     query::Event::History(task) => Event::History(task),
+                    query::Event::Layout(layout, task) => Event::Layout(layout, task),
                 });
 
                 (command.map(Message::Query), event)
             }
             (Buffer::FileTransfers(state), Message::FileTransfers(message)) => {
-                let command = state.update(message, file_transfers, config);
+                let (command, event) = state.update(message, file_transfers, config);
 
-                (command.map(Message::FileTransfers), None)
+                let event = event.map(|event| match event {
+                    file_transfers::Event::Resend(id) => Event::ResendFile(id),
+                });
+
+                (command.map(Message::FileTransfers), event)
             }
             (Buffer::Logs(state), Message::Logs(message)) => {
-                let (command, event) = state.update(message);
+                let (command, event) = state.update(message, history);
 
                 let event = event.map(|event| match event {
                     logs::Event::UserContext(event) => Event::UserContext(event),
@@ -150,19 +191,30 @@ impl Buffer {
                 (command.map(Message::Logs), event)
             }
             (Buffer::Highlights(state), Message::Highlights(message)) => {
-                let (command, event) = state.update(message);
+                let (command, event) = state.update(message, history);
 
                 let event = event.map(|event| match event {
                     highlights::Event::UserContext(event) => Event::UserContext(event),
                     highlights::Event::OpenChannel(channel) => Event::OpenChannel(channel),
-                    highlights::Event::GoToMessage(server, channel, message) => {
-                        Event::GoToMessage(server, channel, message)
+                    highlights::Event::GoToMessage(buffer, message) => {
+                        Event::GoToMessage(buffer, message)
                     }
                     highlights::Event::History(task) => Event::History(task),
                 });
 
                 (command.map(Message::Highlights), event)
             }
+            (Buffer::Urls(state), Message::Urls(message)) => {
+                (state.update(message).map(Message::Urls), None)
+            }
+            (Buffer::ModeLists(state), Message::ModeLists(message)) => {
+                (state.update(message, clients).map(Message::ModeLists), None)
+            }
+            (Buffer::Console(state), Message::Console(message)) => {
+                state.update(message);
+
+                (Task::none(), None)
+            }
             _ => (Task::none(), None),
         }
     }
@@ -204,6 +256,9 @@ impl Buffer {
             Buffer::Highlights(state) => {
                 highlights::view(state, clients, history, config, theme).map(Message::Highlights)
             }
+            Buffer::Urls(state) => urls::view(state, history, config).map(Message::Urls),
+            Buffer::ModeLists(state) => mode_lists::view(state, clients).map(Message::ModeLists),
+            Buffer::Console(state) => console::view(state, clients, config).map(Message::Console),
         }
     }
 
@@ -229,9 +284,13 @@ impl Buffer {
 
     pub fn focus(&self) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) | Buffer::Logs(_) | Buffer::Highlights(_) => {
-                Task::none()
-            }
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Logs(_)
+            | Buffer::Highlights(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
             Buffer::Channel(channel) => channel.focus().map(Message::Channel),
             Buffer::Server(server) => server.focus().map(Message::Server),
             Buffer::Query(query) => query.focus().map(Message::Query),
@@ -240,7 +299,13 @@ impl Buffer {
 
     pub fn reset(&mut self) {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) | Buffer::Logs(_) | Buffer::Highlights(_) => {}
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Logs(_)
+            | Buffer::Highlights(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => {}
             Buffer::Channel(channel) => channel.reset(),
             Buffer::Server(server) => server.reset(),
             Buffer::Query(query) => query.reset(),
@@ -257,7 +322,10 @@ impl Buffer {
             | Buffer::Server(_)
             | Buffer::FileTransfers(_)
             | Buffer::Logs(_)
-            | Buffer::Highlights(_) => Task::none(),
+            | Buffer::Highlights(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
             Buffer::Channel(state) => state
                 .input_view
                 .insert_user(nick, state.buffer.clone(), history)
@@ -269,9 +337,41 @@ impl Buffer {
         }
     }
 
+    pub fn insert_formatting_to_input(
+        &mut self,
+        code: String,
+        history: &mut history::Manager,
+    ) -> Task<Message> {
+        match self {
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Logs(_)
+            | Buffer::Highlights(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
+            Buffer::Channel(state) => state
+                .input_view
+                .insert_formatting(code, state.buffer.clone(), history)
+                .map(|message| Message::Channel(channel::Message::InputView(message))),
+            Buffer::Server(state) => state
+                .input_view
+                .insert_formatting(code, state.buffer.clone(), history)
+                .map(|message| Message::Server(server::Message::InputView(message))),
+            Buffer::Query(state) => state
+                .input_view
+                .insert_formatting(code, state.buffer.clone(), history)
+                .map(|message| Message::Query(query::Message::InputView(message))),
+        }
+    }
+
     pub fn scroll_to_start(&mut self) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
             Buffer::Channel(channel) => channel
                 .scroll_view
                 .scroll_to_start()
@@ -297,7 +397,11 @@ impl Buffer {
 
     pub fn scroll_to_end(&mut self) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
             Buffer::Channel(channel) => channel
                 .scroll_view
                 .scroll_to_end()
@@ -321,14 +425,49 @@ impl Buffer {
         }
     }
 
+    pub fn scroll_by(&mut self, delta: f32) -> Task<Message> {
+        match self {
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
+            Buffer::Channel(channel) => channel
+                .scroll_view
+                .scroll_by(delta)
+                .map(|message| Message::Channel(channel::Message::ScrollView(message))),
+            Buffer::Server(server) => server
+                .scroll_view
+                .scroll_by(delta)
+                .map(|message| Message::Server(server::Message::ScrollView(message))),
+            Buffer::Query(query) => query
+                .scroll_view
+                .scroll_by(delta)
+                .map(|message| Message::Query(query::Message::ScrollView(message))),
+            Buffer::Logs(log) => log
+                .scroll_view
+                .scroll_by(delta)
+                .map(|message| Message::Logs(logs::Message::ScrollView(message))),
+            Buffer::Highlights(highlights) => highlights
+                .scroll_view
+                .scroll_by(delta)
+                .map(|message| Message::Highlights(highlights::Message::ScrollView(message))),
+        }
+    }
+
     pub fn scroll_to_message(
         &mut self,
         message: message::Hash,
         history: &history::Manager,
+        settings: &Settings,
         config: &Config,
     ) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
             Buffer::Channel(state) => state
                 .scroll_view
                 .scroll_to_message(
@@ -336,6 +475,7 @@ impl Buffer {
                     scroll_view::Kind::Channel(&state.server, &state.channel),
                     history,
                     config,
+                    settings.channel.smart_filter.enabled,
                 )
                 .map(|message| Message::Channel(channel::Message::ScrollView(message))),
             Buffer::Server(state) => state
@@ -345,6 +485,7 @@ impl Buffer {
                     scroll_view::Kind::Server(&state.server),
                     history,
                     config,
+                    true,
                 )
                 .map(|message| Message::Server(server::Message::ScrollView(message))),
             Buffer::Query(state) => state
@@ -354,15 +495,22 @@ impl Buffer {
                     scroll_view::Kind::Query(&state.server, &state.nick),
                     history,
                     config,
+                    true,
                 )
                 .map(|message| Message::Query(query::Message::ScrollView(message))),
             Buffer::Logs(state) => state
                 .scroll_view
-                .scroll_to_message(message, scroll_view::Kind::Logs, history, config)
+                .scroll_to_message(message, scroll_view::Kind::Logs, history, config, true)
                 .map(|message| Message::Logs(logs::Message::ScrollView(message))),
             Buffer::Highlights(state) => state
                 .scroll_view
-                .scroll_to_message(message, scroll_view::Kind::Highlights, history, config)
+                .scroll_to_message(
+                    message,
+                    scroll_view::Kind::Highlights,
+                    history,
+                    config,
+                    true,
+                )
                 .map(|message| Message::Highlights(highlights::Message::ScrollView(message))),
         }
     }
@@ -370,21 +518,32 @@ impl Buffer {
     pub fn scroll_to_backlog(
         &mut self,
         history: &history::Manager,
+        settings: &Settings,
         config: &Config,
     ) -> Task<Message> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => Task::none(),
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
             Buffer::Channel(state) => state
                 .scroll_view
                 .scroll_to_backlog(
                     scroll_view::Kind::Channel(&state.server, &state.channel),
                     history,
                     config,
+                    settings.channel.smart_filter.enabled,
                 )
                 .map(|message| Message::Channel(channel::Message::ScrollView(message))),
             Buffer::Server(state) => state
                 .scroll_view
-                .scroll_to_backlog(scroll_view::Kind::Server(&state.server), history, config)
+                .scroll_to_backlog(
+                    scroll_view::Kind::Server(&state.server),
+                    history,
+                    config,
+                    true,
+                )
                 .map(|message| Message::Server(server::Message::ScrollView(message))),
             Buffer::Query(state) => state
                 .scroll_view
@@ -392,22 +551,51 @@ impl Buffer {
                     scroll_view::Kind::Query(&state.server, &state.nick),
                     history,
                     config,
+                    true,
                 )
                 .map(|message| Message::Query(query::Message::ScrollView(message))),
             Buffer::Logs(state) => state
                 .scroll_view
-                .scroll_to_backlog(scroll_view::Kind::Logs, history, config)
+                .scroll_to_backlog(scroll_view::Kind::Logs, history, config, true)
                 .map(|message| Message::Logs(logs::Message::ScrollView(message))),
             Buffer::Highlights(state) => state
                 .scroll_view
-                .scroll_to_backlog(scroll_view::Kind::Highlights, history, config)
+                .scroll_to_backlog(scroll_view::Kind::Highlights, history, config, true)
                 .map(|message| Message::Highlights(highlights::Message::ScrollView(message))),
         }
     }
 
+    pub fn toggle_search(&mut self) -> Task<Message> {
+        match self {
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Logs(_)
+            | Buffer::Highlights(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => Task::none(),
+            Buffer::Channel(state) => state
+                .scroll_view
+                .toggle_search()
+                .map(|message| Message::Channel(channel::Message::ScrollView(message))),
+            Buffer::Server(state) => state
+                .scroll_view
+                .toggle_search()
+                .map(|message| Message::Server(server::Message::ScrollView(message))),
+            Buffer::Query(state) => state
+                .scroll_view
+                .toggle_search()
+                .map(|message| Message::Query(query::Message::ScrollView(message))),
+        }
+    }
+
     pub fn is_scrolled_to_bottom(&self) -> Option<bool> {
         match self {
-            Buffer::Empty | Buffer::FileTransfers(_) => None,
+            Buffer::Empty
+            | Buffer::FileTransfers(_)
+            | Buffer::Urls(_)
+            | Buffer::ModeLists(_)
+            | Buffer::Console(_) => None,
             Buffer::Channel(channel) => Some(channel.scroll_view.is_scrolled_to_bottom()),
             Buffer::Server(server) => Some(server.scroll_view.is_scrolled_to_bottom()),
             Buffer::Query(query) => Some(query.scroll_view.is_scrolled_to_bottom()),
@@ -431,7 +619,12 @@ impl From<data::Buffer> for Buffer {
                 buffer::Internal::FileTransfers => Self::FileTransfers(FileTransfers::new()),
                 buffer::Internal::Logs => Self::Logs(Logs::new()),
                 buffer::Internal::Highlights => Self::Highlights(Highlights::new()),
+                buffer::Internal::Urls => Self::Urls(Urls::new()),
             },
+            data::Buffer::ModeLists { server, channel } => {
+                Self::ModeLists(ModeLists::new(server, channel))
+            }
+            data::Buffer::Console { server } => Self::Console(Console::new(server)),
         }
     }
 }
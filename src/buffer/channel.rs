@@ -1,8 +1,9 @@
+use data::client::Backfill;
 use data::server::Server;
 use data::user::Nick;
 use data::{buffer, User};
-use data::{channel, history, message, Config};
-use iced::widget::{column, container, row};
+use data::{channel, command, history, message, mode, Config};
+use iced::widget::{column, container, row, Space};
 use iced::{alignment, padding, Length, Task};
 
 use super::{input_view, scroll_view, user_context};
@@ -23,6 +24,7 @@ pub enum Event {
     UserContext(user_context::Event),
     OpenChannel(String),
     History(Task<history::manager::Message>),
+    Layout(command::Layout, Task<history::manager::Message>),
 }
 
 pub fn view<'a>(
@@ -38,6 +40,7 @@ pub fn view<'a>(
     let channel = &state.channel;
     let buffer = &state.buffer;
     let input = history.input(buffer);
+    let render = config.buffer.formatting.render(buffer);
     let our_nick = clients.nickname(&state.server);
 
     let our_user = our_nick
@@ -52,7 +55,8 @@ pub fn view<'a>(
             scroll_view::Kind::Channel(&state.server, &state.channel),
             history,
             config,
-            move |message, max_nick_width, max_prefix_width| {
+            settings.smart_filter.enabled,
+            move |message, max_nick_width, max_prefix_width, grouped| {
                 let timestamp =
                     config
                         .buffer
@@ -93,6 +97,7 @@ pub fn view<'a>(
                 match message.target.source() {
                     message::Source::User(user) => {
                         let current_user = users.iter().find(|current_user| *current_user == user);
+                        let soft_ignored = clients.is_soft_ignored(server, user);
 
                         let mut text = selectable_text(
                             config
@@ -105,7 +110,7 @@ pub fn view<'a>(
                             theme::selectable_text::nickname(
                                 theme,
                                 user.nick_color(theme.colors(), config.buffer.nickname.color),
-                                user.is_away(),
+                                user.is_away() || soft_ignored,
                             )
                         });
 
@@ -122,6 +127,7 @@ pub fn view<'a>(
                             user,
                             current_user,
                             our_user,
+                            config,
                         )
                         .map(scroll_view::Message::UserContext);
 
@@ -129,36 +135,80 @@ pub fn view<'a>(
                             &message.content,
                             theme,
                             scroll_view::Message::Link,
-                            theme::selectable_text::default,
+                            move |theme: &Theme| {
+                                if soft_ignored {
+                                    theme::selectable_text::dimmed(theme)
+                                } else {
+                                    theme::selectable_text::default(theme)
+                                }
+                            },
                             move |link| match link {
-                                message::Link::User(_) => user_context::Entry::list(true, our_user),
+                                message::Link::User(_) => user_context::Entry::list(
+                                    true,
+                                    our_user,
+                                    config.buffer.nickname.context_menu.len(),
+                                ),
                                 _ => vec![],
                             },
                             move |link, entry, length| match link {
                                 message::Link::User(user) => entry
-                                    .view(server, Some(channel), user, current_user, length)
+                                    .view(
+                                        server,
+                                        Some(channel),
+                                        user,
+                                        current_user,
+                                        &config.buffer.nickname.context_menu,
+                                        length,
+                                    )
                                     .map(scroll_view::Message::UserContext),
                                 _ => row![].into(),
                             },
+                            render,
                             config,
                         );
 
-                        let timestamp_nickname_row = row![]
-                            .push_maybe(timestamp)
-                            .push_maybe(prefixes)
-                            .push(nick)
-                            .push(space);
-
                         let text_container =
                             container(message_content).style(move |theme| match our_nick {
                                 Some(nick)
-                                    if message::references_user(user.nickname(), nick, message) =>
+                                    if !soft_ignored
+                                        && message::is_highlight(
+                                            user.nickname(),
+                                            nick,
+                                            message,
+                                            &config.highlights,
+                                        ) =>
                                 {
                                     theme::container::highlight(theme)
                                 }
                                 _ => Default::default(),
                             });
 
+                        if grouped {
+                            let (timestamp_width, nick_width) =
+                                scroll_view::group_indent(message, user, max_nick_width, config);
+
+                            return match &config.buffer.nickname.alignment {
+                                data::buffer::Alignment::Left | data::buffer::Alignment::Right => {
+                                    Some(
+                                        row![]
+                                            .push_maybe(timestamp_width.map(Space::with_width))
+                                            .push_maybe(prefixes)
+                                            .push(Space::with_width(nick_width))
+                                            .push(space)
+                                            .push(text_container)
+                                            .into(),
+                                    )
+                                }
+                                data::buffer::Alignment::Top => Some(text_container.into()),
+                            };
+                        }
+
+                        let timestamp_nickname_row = row![]
+                            .push_maybe(timestamp)
+                            .push_maybe(prefixes)
+                            .push(nick)
+                            .push(space);
+
                         match &config.buffer.nickname.alignment {
                             data::buffer::Alignment::Left | data::buffer::Alignment::Right => Some(
                                 row![]
@@ -186,6 +236,7 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             message_style,
+                            render,
                             config,
                         );
 
@@ -209,6 +260,7 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             theme::selectable_text::action,
+                            render,
                             config,
                         );
 
@@ -236,6 +288,7 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             message_style,
+                            render,
                             config,
                         );
 
@@ -251,6 +304,13 @@ pub fn view<'a>(
                             .into(),
                         )
                     }
+                    message::Source::Internal(message::source::Internal::Gap(disconnected_at)) => {
+                        Some(scroll_view::gap(
+                            *disconnected_at,
+                            message.server_time,
+                            config,
+                        ))
+                    }
                     message::Source::Internal(message::source::Internal::Logs) => None,
                 }
             },
@@ -260,8 +320,8 @@ pub fn view<'a>(
     .width(Length::FillPortion(2))
     .height(Length::Fill);
 
-    let nick_list =
-        nick_list::view(server, channel, users, our_user, config).map(Message::UserContext);
+    let nick_list = nick_list::view(server, channel, users, our_user, history, config)
+        .map(Message::UserContext);
 
     // If topic toggles from None to Some then it messes with messages' scroll state,
     // so produce a zero-height placeholder when topic is None.
@@ -276,12 +336,27 @@ pub fn view<'a>(
     let channels = clients.get_channels(&state.server);
     let is_connected_to_channel = channels.iter().any(|c| c == &state.channel);
 
+    // Without voice or above, messages to a moderated channel are dropped by the server
+    let moderated = clients
+        .get_channel_modes(&state.server, &state.channel)
+        .is_some_and(mode::ChannelModes::moderated);
+    let voiced = our_user.is_some_and(|user| user.highest_access_level().is_some());
+
+    let placeholder = if moderated && !voiced {
+        "Channel is moderated, your messages may be dropped..."
+    } else {
+        input_view::PLACEHOLDER
+    };
+
     let text_input = show_text_input.then(move || {
         input_view::view(
             &state.input_view,
             input,
             is_focused,
             !is_connected_to_channel,
+            placeholder,
+            config,
+            theme,
         )
         .map(Message::InputView)
     });
@@ -332,21 +407,69 @@ impl Channel {
         }
     }
 
+    fn backfill(&self, clients: &mut data::client::Map, backfill: Backfill) {
+        if let Err(error) = clients.backfill(&self.server, &self.channel, backfill) {
+            log::warn!(
+                "[{}] failed to request history for {}: {error}",
+                self.server,
+                self.channel
+            );
+        }
+    }
+
     pub fn update(
         &mut self,
         message: Message,
         clients: &mut data::client::Map,
         history: &mut history::Manager,
+        settings: &channel::Settings,
         config: &Config,
     ) -> (Task<Message>, Option<Event>) {
         match message {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
 
+                if let Some(scroll_view::Event::Search(direction)) = event {
+                    let search = self.scroll_view.search(
+                        direction,
+                        scroll_view::Kind::Channel(&self.server, &self.channel),
+                        history,
+                        config,
+                        settings.smart_filter.enabled,
+                    );
+
+                    return (
+                        Task::batch(vec![command, search]).map(Message::ScrollView),
+                        None,
+                    );
+                }
+
                 let event = event.and_then(|event| match event {
                     scroll_view::Event::UserContext(event) => Some(Event::UserContext(event)),
                     scroll_view::Event::OpenChannel(channel) => Some(Event::OpenChannel(channel)),
-                    scroll_view::Event::GoToMessage(..) => None,
+                    scroll_view::Event::GoToMessage(..) | scroll_view::Event::Search(_) => None,
+                    scroll_view::Event::RequestOlder => {
+                        let kind =
+                            history::Kind::Channel(self.server.clone(), self.channel.clone());
+
+                        // History saved to disk comes first, then the server's
+                        if history.has_older(&kind) {
+                            return Some(Event::History(Task::batch(
+                                history.load_older(&kind).map(Task::future),
+                            )));
+                        }
+
+                        if let Some(oldest) = history.oldest(&kind) {
+                            self.backfill(clients, Backfill::before(oldest));
+                        }
+
+                        None
+                    }
+                    scroll_view::Event::RequestMissing(since, until) => {
+                        self.backfill(clients, Backfill::Between(since, until));
+
+                        None
+                    }
                 });
 
                 (command.map(Message::ScrollView), event)
@@ -366,6 +489,10 @@ impl Channel {
 
                         (command, Some(Event::History(history_task)))
                     }
+                    Some(input_view::Event::Layout {
+                        layout,
+                        history_task,
+                    }) => (command, Some(Event::Layout(layout, history_task))),
                     None => (command, None),
                 }
             }
@@ -406,17 +533,27 @@ fn topic<'a>(
     }
 
     let topic = clients.get_channel_topic(&state.server, &state.channel)?;
+    let modes = clients
+        .get_channel_modes(&state.server, &state.channel)
+        .map(ToString::to_string)
+        .filter(|modes| !modes.is_empty());
+
+    if topic.content.is_none() && modes.is_none() {
+        return None;
+    }
 
     Some(
         topic::view(
             &state.server,
             &state.channel,
-            topic.content.as_ref()?,
+            topic.content.as_ref(),
             topic.who.as_deref(),
             topic.time.as_ref(),
+            modes,
             config.buffer.channel.topic.max_lines,
             users,
             our_user,
+            config.buffer.formatting.render(&state.buffer),
             config,
             theme,
         )
@@ -425,7 +562,7 @@ fn topic<'a>(
 }
 
 mod nick_list {
-    use data::{config, Config, Server, User};
+    use data::{config, history, Config, Server, User};
     use iced::widget::{column, scrollable, Scrollable};
     use iced::{alignment, Length};
     use user_context::Message;
@@ -439,10 +576,15 @@ mod nick_list {
         channel: &'a str,
         users: &'a [User],
         our_user: Option<&'a User>,
+        history: &'a history::Manager,
         config: &'a Config,
     ) -> Element<'a, Message> {
         let nicklist_config = &config.buffer.channel.nicklist;
 
+        let users = nicklist_config.sort(channel, users, || {
+            history.last_seen(&history::Kind::Channel(server.clone(), channel.to_string()))
+        });
+
         let width = match nicklist_config.width {
             Some(width) => width,
             None => {
@@ -460,7 +602,7 @@ mod nick_list {
             }
         };
 
-        let content = column(users.iter().map(|user| {
+        let content = column(users.into_iter().map(|user| {
             let content = selectable_text(user.display(nicklist_config.show_access_levels))
                 .style(|theme| {
                     theme::selectable_text::nickname(
@@ -475,7 +617,15 @@ mod nick_list {
                 })
                 .width(Length::Fixed(width));
 
-            user_context::view(content, server, Some(channel), user, Some(user), our_user)
+            user_context::view(
+                content,
+                server,
+                Some(channel),
+                user,
+                Some(user),
+                our_user,
+                config,
+            )
         }));
 
         Scrollable::new(content)
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use data::config::buffer::Render;
 use data::user::Nick;
 use data::{message, Config, Server, User};
 use iced::widget::{column, container, horizontal_rule, row, scrollable, Scrollable};
@@ -38,12 +39,14 @@ pub fn update(message: Message) -> Option<Event> {
 pub fn view<'a>(
     server: &'a Server,
     channel: &'a String,
-    content: &'a message::Content,
+    content: Option<&'a message::Content>,
     who: Option<&'a str>,
     time: Option<&'a DateTime<Utc>>,
+    modes: Option<String>,
     max_lines: u16,
     users: &'a [User],
     our_user: Option<&'a User>,
+    render: Render,
     config: &'a Config,
     theme: &'a Theme,
 ) -> Element<'a, Message> {
@@ -65,6 +68,7 @@ pub fn view<'a>(
                 user,
                 Some(user),
                 our_user,
+                config,
             )
         } else {
             selectable_text(who)
@@ -83,14 +87,23 @@ pub fn view<'a>(
         )
     });
 
-    let content = column![message_content(
-        content,
-        theme,
-        Message::Link,
-        theme::selectable_text::topic,
-        config,
-    )]
-    .push_maybe(set_by);
+    let modes = modes.map(|modes| {
+        selectable_text(format!("modes {modes}")).style(theme::selectable_text::topic)
+    });
+
+    let content = column![]
+        .push_maybe(content.map(|content| {
+            message_content(
+                content,
+                theme,
+                Message::Link,
+                theme::selectable_text::topic,
+                render,
+                config,
+            )
+        }))
+        .push_maybe(set_by)
+        .push_maybe(modes);
 
     let scrollable = Scrollable::new(container(content).width(Length::Fill).padding(padding()))
         .direction(scrollable::Direction::Vertical(
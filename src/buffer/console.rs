@@ -0,0 +1,159 @@
+use data::console::{Direction, Line};
+use data::{Config, Server};
+use iced::widget::{
+    button, center, column, container, row, scrollable, text, text_input, Scrollable,
+};
+use iced::Length;
+
+use crate::widget::{selectable_text, Element, Row};
+use crate::{font, theme};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Filter(String),
+    Structured(bool),
+}
+
+pub fn view<'a>(
+    state: &'a Console,
+    clients: &'a data::client::Map,
+    config: &'a Config,
+) -> Element<'a, Message> {
+    let Some(console) = clients.get_console(&state.server) else {
+        return center(text("No lines sent or received yet").style(theme::text::secondary)).into();
+    };
+
+    let lines = console
+        .lines()
+        .filter(|line| line.matches(&state.filter))
+        .map(|line| {
+            let timestamp = config.buffer.format_timestamp(&line.time).map(|timestamp| {
+                selectable_text(timestamp).style(theme::selectable_text::timestamp)
+            });
+
+            let direction = match line.direction {
+                Direction::Sent => "→ ",
+                Direction::Received => "← ",
+            };
+
+            let header = row![]
+                .push_maybe(timestamp)
+                .push(selectable_text(direction).style(theme::selectable_text::tertiary));
+
+            if state.structured {
+                Element::from(structured(header, line))
+            } else {
+                Element::from(header.push(selectable_text(line.text.as_str())))
+            }
+        });
+
+    let mode = if state.structured {
+        "Raw"
+    } else {
+        "Structured"
+    };
+
+    let toolbar = container(
+        row![
+            text_input("Filter by command or target...", &state.filter)
+                .on_input(Message::Filter)
+                .padding(4)
+                .style(theme::text_input::primary),
+            button(text(mode))
+                .padding([4, 6])
+                .on_press(Message::Structured(!state.structured))
+                .style(|theme, status| theme::button::secondary(theme, status, false)),
+        ]
+        .spacing(4)
+        .align_y(iced::Alignment::Center),
+    )
+    .padding([0, 8]);
+
+    container(
+        column![
+            toolbar,
+            Scrollable::new(
+                column(lines)
+                    .spacing(if state.structured { 4 } else { 0 })
+                    .padding([0, 8])
+            )
+            .direction(scrollable::Direction::Vertical(
+                scrollable::Scrollbar::default()
+                    .anchor(scrollable::Anchor::End)
+                    .width(5)
+                    .scroller_width(5),
+            ))
+            .height(Length::Fill),
+        ]
+        .spacing(8),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(8)
+    .into()
+}
+
+/// The line parsed into its command, target, parameters and tags, with the
+/// round-trip time of the labeled command it replies to.
+fn structured<'a>(header: Row<'a, Message>, line: &'a Line) -> Element<'a, Message> {
+    let round_trip = line.round_trip.map(|round_trip| {
+        selectable_text(format!(" ({} ms)", round_trip.num_milliseconds()))
+            .style(theme::selectable_text::tertiary)
+    });
+
+    let header = header
+        .push(selectable_text(line.command.as_str()).font(font::MONO_BOLD.clone()))
+        .push_maybe(line.target().map(|target| {
+            selectable_text(format!(" {target}")).style(theme::selectable_text::action)
+        }))
+        .push_maybe(round_trip);
+
+    let params = line.params.iter().enumerate().map(|(index, param)| {
+        Element::from(row![
+            selectable_text(format!("  {index:>2} ")).style(theme::selectable_text::timestamp),
+            selectable_text(param.as_str()),
+        ])
+    });
+
+    let tags = (!line.tags.is_empty()).then(|| {
+        let tags = line
+            .tags
+            .iter()
+            .map(|tag| match &tag.value {
+                Some(value) => format!("{}={value}", tag.key),
+                None => tag.key.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        selectable_text(format!("  @ {tags}")).style(theme::selectable_text::dimmed)
+    });
+
+    column![header].extend(params).push_maybe(tags).into()
+}
+
+#[derive(Debug, Clone)]
+pub struct Console {
+    pub server: Server,
+    /// Commands and targets of the lines shown
+    filter: String,
+    /// Whether lines are shown parsed rather than raw
+    structured: bool,
+}
+
+impl Console {
+    pub fn new(server: Server) -> Self {
+        Self {
+            server,
+            filter: String::new(),
+            structured: false,
+        }
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::Filter(filter) => self.filter = filter,
+            Message::Structured(structured) => self.structured = structured,
+        }
+    }
+}
@@ -12,6 +12,13 @@ pub enum Message {
     Approve(file_transfer::Id),
     SavePathSelected(file_transfer::Id, Option<PathBuf>),
     Clear(file_transfer::Id),
+    Resume(file_transfer::Id),
+    Resend(file_transfer::Id),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Resend(file_transfer::Id),
 }
 
 pub fn view<'a>(
@@ -66,21 +73,24 @@ impl FileTransfers {
         message: Message,
         file_transfers: &mut file_transfer::Manager,
         config: &Config,
-    ) -> Task<Message> {
+    ) -> (Task<Message>, Option<Event>) {
         match message {
             Message::Approve(id) => {
                 if let Some(transfer) = file_transfers.get(&id).cloned() {
                     let save_directory = config.file_transfer.save_directory.clone();
-                    return Task::perform(
-                        async move {
-                            rfd::AsyncFileDialog::new()
-                                .set_directory(save_directory)
-                                .set_file_name(transfer.filename)
-                                .save_file()
-                                .await
-                                .map(|handle| handle.path().to_path_buf())
-                        },
-                        move |path| Message::SavePathSelected(id, path),
+                    return (
+                        Task::perform(
+                            async move {
+                                rfd::AsyncFileDialog::new()
+                                    .set_directory(save_directory)
+                                    .set_file_name(transfer.filename)
+                                    .save_file()
+                                    .await
+                                    .map(|handle| handle.path().to_path_buf())
+                            },
+                            move |path| Message::SavePathSelected(id, path),
+                        ),
+                        None,
                     );
                 }
             }
@@ -92,9 +102,20 @@ impl FileTransfers {
             Message::Clear(id) => {
                 file_transfers.remove(&id);
             }
+            Message::Resume(id) => {
+                if let Some(path) = file_transfers
+                    .get(&id)
+                    .and_then(|transfer| transfer.path.clone())
+                {
+                    file_transfers.approve(&id, path);
+                }
+            }
+            Message::Resend(id) => {
+                return (Task::none(), Some(Event::Resend(id)));
+            }
         }
 
-        Task::none()
+        (Task::none(), None)
     }
 }
 
@@ -120,6 +141,13 @@ mod transfer_row {
                     ))
                     .style(theme::text::secondary),
                 ),
+                file_transfer::Direction::Received if transfer.path.is_some() => container(
+                    text(format!(
+                        "Transfer from {}. Resume where it was interrupted, or accept to begin again.",
+                        transfer.remote_user
+                    ))
+                    .style(theme::text::secondary),
+                ),
                 file_transfer::Direction::Received => container(
                     text(format!(
                         "Transfer from {}. Accept to begin.",
@@ -185,7 +213,11 @@ mod transfer_row {
                     .spacing(0),
                 )
             }
-            file_transfer::Status::Completed { elapsed, sha256 } => {
+            file_transfer::Status::Completed {
+                elapsed,
+                sha256,
+                checksum,
+            } => {
                 let mut formatter = timeago::Formatter::new();
                 formatter
                     .ago("")
@@ -198,17 +230,57 @@ mod transfer_row {
                     file_transfer::Direction::Received => "from",
                 };
 
+                let verified = if *checksum == file_transfer::Checksum::Verified {
+                    " (verified)"
+                } else {
+                    ""
+                };
+
+                let completed = text(format!(
+                    "Completed {} {} in {elapsed}. sha256: {sha256}{verified}",
+                    direction, transfer.remote_user,
+                ))
+                .style(theme::text::secondary);
+
+                if let file_transfer::Checksum::Mismatch { expected } = checksum {
+                    container(
+                        column![
+                            completed,
+                            text(format!(
+                                "Checksum mismatch, the file may be corrupt. Expected sha256: {expected}"
+                            ))
+                            .style(theme::text::error),
+                        ]
+                        .spacing(0),
+                    )
+                } else {
+                    container(completed)
+                }
+            }
+            file_transfer::Status::Failed { error } => {
+                container(text(format!("Failed: {error}")).style(theme::text::error))
+            }
+            file_transfer::Status::Interrupted { transferred } => {
+                let transferred = ByteSize::b(*transferred);
+                let file_size = ByteSize::b(transfer.size);
+
+                let resume = match transfer.direction {
+                    file_transfer::Direction::Sent if transfer.path.is_some() => {
+                        "Send again to resume.".to_string()
+                    }
+                    file_transfer::Direction::Sent => String::default(),
+                    file_transfer::Direction::Received => {
+                        format!("Ask {} to send it again to resume.", transfer.remote_user)
+                    }
+                };
+
                 container(
                     text(format!(
-                        "Completed {} {} in {elapsed}. sha256: {sha256}",
-                        direction, transfer.remote_user,
+                        "Interrupted at {transferred} of {file_size}. {resume}"
                     ))
                     .style(theme::text::secondary),
                 )
             }
-            file_transfer::Status::Failed { error } => {
-                container(text(format!("Failed: {error}")).style(theme::text::error))
-            }
         };
 
         let file_size = ByteSize::b(transfer.size);
@@ -224,6 +296,10 @@ mod transfer_row {
 
         match &transfer.status {
             file_transfer::Status::PendingApproval => {
+                if transfer.path.is_some() {
+                    buttons =
+                        buttons.push(row_button(icon::refresh(), Message::Resume(transfer.id)));
+                }
                 buttons =
                     buttons.push(row_button(icon::checkmark(), Message::Approve(transfer.id)));
                 buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
@@ -239,6 +315,13 @@ mod transfer_row {
             file_transfer::Status::Failed { .. } => {
                 buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
             }
+            file_transfer::Status::Interrupted { .. } => {
+                if transfer.direction == file_transfer::Direction::Sent && transfer.path.is_some() {
+                    buttons =
+                        buttons.push(row_button(icon::refresh(), Message::Resend(transfer.id)));
+                }
+                buttons = buttons.push(row_button(icon::cancel(), Message::Clear(transfer.id)));
+            }
         }
 
         let row = row![content, buttons]
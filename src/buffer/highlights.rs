@@ -1,4 +1,5 @@
-use data::{history, message, Config, Server};
+use data::user::Nick;
+use data::{buffer, history, message, Config};
 use iced::widget::{container, row, span};
 use iced::{Length, Task};
 
@@ -14,7 +15,7 @@ pub enum Message {
 pub enum Event {
     UserContext(user_context::Event),
     OpenChannel(String),
-    GoToMessage(Server, String, message::Hash),
+    GoToMessage(buffer::Upstream, message::Hash),
     History(Task<history::manager::Message>),
 }
 
@@ -31,13 +32,23 @@ pub fn view<'a>(
             scroll_view::Kind::Highlights,
             history,
             config,
-            move |message, _, _| match &message.target {
+            true,
+            move |message, _, _, _| match &message.target {
                 message::Target::Highlights {
                     server,
                     channel,
                     source: message::Source::User(user),
+                    query,
                 } => {
                     let users = clients.get_channel_users(server, channel);
+                    let context_channel = (!query).then_some(channel.as_str());
+
+                    let buffer = if *query {
+                        buffer::Upstream::Query(server.clone(), Nick::from(channel.as_str()))
+                    } else {
+                        buffer::Upstream::Channel(server.clone(), channel.clone())
+                    };
+                    let render = config.buffer.formatting.render(&buffer);
 
                     let timestamp =
                         config
@@ -48,13 +59,9 @@ pub fn view<'a>(
                             });
 
                     let channel_text = selectable_rich_text::<_, _, (), _, _>(vec![
-                        span(channel).color(theme.colors().buffer.url).link(
-                            message::Link::GoToMessage(
-                                server.clone(),
-                                channel.to_string(),
-                                message.hash,
-                            ),
-                        ),
+                        span(channel)
+                            .color(theme.colors().buffer.url)
+                            .link(message::Link::GoToMessage(buffer, message.hash)),
                         span(" "),
                     ])
                     .on_link(scroll_view::Message::Link);
@@ -78,9 +85,16 @@ pub fn view<'a>(
                         )
                     });
 
-                    let nick =
-                        user_context::view(text, server, Some(channel), user, current_user, None)
-                            .map(scroll_view::Message::UserContext);
+                    let nick = user_context::view(
+                        text,
+                        server,
+                        context_channel,
+                        user,
+                        current_user,
+                        None,
+                        config,
+                    )
+                    .map(scroll_view::Message::UserContext);
 
                     let text = message_content::with_context(
                         &message.content,
@@ -88,15 +102,27 @@ pub fn view<'a>(
                         scroll_view::Message::Link,
                         theme::selectable_text::default,
                         move |link| match link {
-                            message::Link::User(_) => user_context::Entry::list(true, None),
+                            message::Link::User(_) => user_context::Entry::list(
+                                context_channel.is_some(),
+                                None,
+                                config.buffer.nickname.context_menu.len(),
+                            ),
                             _ => vec![],
                         },
                         move |link, entry, length| match link {
                             message::Link::User(user) => entry
-                                .view(server, Some(channel), user, current_user, length)
+                                .view(
+                                    server,
+                                    context_channel,
+                                    user,
+                                    current_user,
+                                    &config.buffer.nickname.context_menu,
+                                    length,
+                                )
                                 .map(scroll_view::Message::UserContext),
                             _ => row![].into(),
                         },
+                        render,
                         config,
                     );
 
@@ -136,17 +162,27 @@ impl Highlights {
         Self::default()
     }
 
-    pub fn update(&mut self, message: Message) -> (Task<Message>, Option<Event>) {
+    pub fn update(
+        &mut self,
+        message: Message,
+        history: &mut history::Manager,
+    ) -> (Task<Message>, Option<Event>) {
         match message {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
 
-                let event = event.map(|event| match event {
-                    scroll_view::Event::UserContext(event) => Event::UserContext(event),
-                    scroll_view::Event::OpenChannel(channel) => Event::OpenChannel(channel),
-                    scroll_view::Event::GoToMessage(server, channel, message) => {
-                        Event::GoToMessage(server, channel, message)
+                let event = event.and_then(|event| match event {
+                    scroll_view::Event::UserContext(event) => Some(Event::UserContext(event)),
+                    scroll_view::Event::OpenChannel(channel) => Some(Event::OpenChannel(channel)),
+                    scroll_view::Event::GoToMessage(buffer, message) => {
+                        Some(Event::GoToMessage(buffer, message))
+                    }
+                    scroll_view::Event::RequestOlder => {
+                        let task = history.load_older(&history::Kind::Highlights);
+
+                        Some(Event::History(Task::batch(task.map(Task::future))))
                     }
+                    scroll_view::Event::RequestMissing(..) | scroll_view::Event::Search(_) => None,
                 });
 
                 (command.map(Message::ScrollView), event)
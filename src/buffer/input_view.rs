@@ -1,19 +1,30 @@
+use data::config::ignore;
 use data::input::{self, Cache, Draft};
 use data::user::Nick;
-use data::{buffer, client, history, Config};
-use iced::widget::{container, row, text, text_input};
+use data::{buffer, client, command, history, message, pastebin, Command, Config};
+use iced::widget::{column, container, row, text, text_input};
 use iced::Task;
+use itertools::Itertools;
 
 use self::completion::Completion;
-use crate::theme;
-use crate::widget::{anchored_overlay, key_press, Element};
+use self::paste::Paste;
+use crate::widget::{anchored_overlay, key_press, paste, Element};
+use crate::{theme, Theme};
 
 mod completion;
+mod formatting;
+mod paste;
+
+pub const PLACEHOLDER: &str = "Send message...";
 
 pub enum Event {
     InputSent {
         history_task: Task<history::manager::Message>,
     },
+    Layout {
+        layout: command::Layout,
+        history_task: Task<history::manager::Message>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +34,12 @@ pub enum Message {
     Tab(bool),
     Up,
     Down,
+    Paste(String),
+    SendPaste(paste::Mode),
+    UploadPaste,
+    PasteUploaded(Result<String, pastebin::Error>),
+    CancelPaste,
+    Format(String),
 }
 
 pub fn view<'a>(
@@ -30,6 +47,9 @@ pub fn view<'a>(
     cache: Cache<'a>,
     buffer_focused: bool,
     disabled: bool,
+    placeholder: &'a str,
+    config: &'a Config,
+    theme: &'a Theme,
 ) -> Element<'a, Message> {
     let style = if state.error.is_some() {
         theme::text_input::error
@@ -37,7 +57,7 @@ pub fn view<'a>(
         theme::text_input::primary
     };
 
-    let mut text_input = text_input("Send message...", cache.draft)
+    let mut text_input = text_input(placeholder, cache.draft)
         .on_submit(Message::Send)
         .id(state.input_id.clone())
         .padding(8)
@@ -75,6 +95,16 @@ pub fn view<'a>(
         );
     }
 
+    // Ask how to send pastes of many lines
+    if buffer_focused && !disabled {
+        let confirm_lines = config.buffer.text_input.paste.confirm_lines;
+
+        input = paste(input, move |text| {
+            (text.trim_end().lines().count() >= confirm_lines.max(2))
+                .then_some(Message::Paste(text))
+        });
+    }
+
     let overlay = state
         .error
         .as_deref()
@@ -82,7 +112,18 @@ pub fn view<'a>(
         .or_else(|| state.completion.view(cache.draft))
         .unwrap_or_else(|| row![].into());
 
-    anchored_overlay(input, overlay, anchored_overlay::Anchor::AboveTop, 4.0)
+    let input = anchored_overlay(input, overlay, anchored_overlay::Anchor::AboveTop, 4.0);
+
+    let toolbar = (config.buffer.text_input.formatting_toolbar && buffer_focused && !disabled)
+        .then(|| formatting::toolbar(config, theme));
+
+    column![]
+        .push_maybe(state.paste.as_ref().map(|paste| paste.view(config)))
+        .push_maybe(formatting::preview(cache.draft, config, theme))
+        .push_maybe(toolbar)
+        .push(input)
+        .spacing(4)
+        .into()
 }
 
 fn error<'a, 'b, Message: 'a>(error: &'b str) -> Element<'a, Message> {
@@ -92,12 +133,294 @@ fn error<'a, 'b, Message: 'a>(error: &'b str) -> Element<'a, Message> {
         .into()
 }
 
+/// Sends the parsed input to the server, or runs it in the client, and records it in history.
+pub fn send(
+    input: data::Input,
+    clients: &mut client::Map,
+    history: &mut history::Manager,
+) -> Task<history::manager::Message> {
+    let buffer = input.buffer.clone();
+
+    if let Some(parts) = input.split(clients.get_line_len(buffer.server())) {
+        // Text typed in the buffer goes in one multiline message when the
+        // server supports it, recipients seeing it whole as it's recorded
+        if input.text().is_some() {
+            let lines = parts
+                .iter()
+                .filter_map(|part| part.text().map(String::from))
+                .collect();
+
+            if clients.send_multiline(&buffer, lines, true) {
+                return record(input, clients, history);
+            }
+        }
+
+        return Task::batch(parts.into_iter().map(|part| send(part, clients, history)));
+    }
+
+    if let Some(encoded) = input.encoded() {
+        clients.send(&buffer, encoded);
+    }
+
+    // Broadcasts are sent on to each joined channel as their own input
+    let broadcast_task = if let Some(Command::Broadcast(broadcast)) = input.local_command() {
+        let tasks = broadcast_inputs(broadcast, &buffer, clients)
+            .into_iter()
+            .map(|input| send(input, clients, history))
+            .collect::<Vec<_>>();
+
+        Task::batch(tasks)
+    } else {
+        Task::none()
+    };
+
+    // Exports and imports report back in the buffer once done
+    let kind = history::Kind::from_input_buffer(buffer.clone());
+    let history_command_task = match input.local_command() {
+        Some(Command::Export(export)) => Task::future(history.export(kind, export)),
+        Some(Command::Import(format, path)) => Task::future(history.import(kind, format, path)),
+        Some(Command::Ignores(ignores)) => ignores_task(kind, ignores, clients),
+        Some(Command::DebugExport) => {
+            let servers = clients.debug_servers();
+
+            Task::future(async move {
+                let text = match data::debug::export(servers).await {
+                    Ok(path) => format!("exported debug report to {}", path.display()),
+                    Err(error) => format!("failed to export debug report: {error}"),
+                };
+
+                history::manager::Message::Feedback(kind, text)
+            })
+        }
+        _ => Task::none(),
+    };
+
+    // Commands handled by the client itself report back in the buffer
+    let local_task = input
+        .local_command()
+        .and_then(|command| local_command(command, &buffer, clients))
+        .and_then(|text| {
+            history.record_message(
+                buffer.server(),
+                data::Message::sent(
+                    buffer.clone().server_message_target(None),
+                    message::plain(text),
+                ),
+            )
+        })
+        .map(Task::future)
+        .unwrap_or_else(Task::none);
+
+    Task::batch([
+        broadcast_task,
+        history_command_task,
+        local_task,
+        record(input, clients, history),
+    ])
+}
+
+/// Exports or imports the ignore masks and filters of the buffer's server,
+/// reporting back in the buffer once done.
+fn ignores_task(
+    kind: history::Kind,
+    ignores: command::Ignores,
+    clients: &client::Map,
+) -> Task<history::manager::Message> {
+    let Some(server) = kind.server().cloned() else {
+        return Task::none();
+    };
+
+    match ignores {
+        command::Ignores::Export(path) => {
+            let Some(list) = clients.ignore_list(&server) else {
+                return Task::done(history::manager::Message::Feedback(
+                    kind,
+                    "failed to export: not connected".to_string(),
+                ));
+            };
+
+            Task::future(async move {
+                let text = match ignore::list::export(list, path).await {
+                    Ok(path) => format!("exported ignore masks and filters to {}", path.display()),
+                    Err(error) => format!("failed to export: {error}"),
+                };
+
+                history::manager::Message::Feedback(kind, text)
+            })
+        }
+        command::Ignores::Import(path) => Task::future(async move {
+            let text = match ignore::list::import(server, path).await {
+                Ok(merged) => format!(
+                    "imported {} ignore masks and filters, {} already present and {} moved \
+                     between ignore and soft_ignore. Use /reconnect to apply them",
+                    merged.added, merged.duplicates, merged.conflicts
+                ),
+                Err(error) => format!("failed to import: {error}"),
+            };
+
+            history::manager::Message::Feedback(kind, text)
+        }),
+    }
+}
+
+/// Records the input in history, as sent by us.
+fn record(
+    input: data::Input,
+    clients: &client::Map,
+    history: &mut history::Manager,
+) -> Task<history::manager::Message> {
+    let buffer = input.buffer.clone();
+    let mut history_task = Task::none();
+
+    if let Some(nick) = clients.nickname(buffer.server()) {
+        let mut user = nick.to_owned().into();
+        let mut channel_users = &[][..];
+        let chantypes = clients.get_chantypes(buffer.server());
+        let statusmsg = clients.get_statusmsg(buffer.server());
+
+        // Resolve our attributes if sending this message in a channel
+        if let buffer::Upstream::Channel(server, channel) = &buffer {
+            channel_users = clients.get_channel_users(server, channel);
+
+            if let Some(user_with_attributes) =
+                clients.resolve_user_attributes(server, channel, &user)
+            {
+                user = user_with_attributes.clone();
+            }
+        }
+
+        history_task = Task::batch(
+            history
+                .record_input(input, user, channel_users, chantypes, statusmsg)
+                .into_iter()
+                .map(Task::future),
+        );
+    }
+
+    history_task
+}
+
+/// Splits a broadcast into an input for every joined channel.
+fn broadcast_inputs(
+    broadcast: command::Broadcast,
+    buffer: &buffer::Upstream,
+    clients: &client::Map,
+) -> Vec<data::Input> {
+    let servers: Vec<_> = if broadcast.all_servers {
+        clients.connected_servers().cloned().collect()
+    } else {
+        vec![buffer.server().clone()]
+    };
+
+    let mut inputs = vec![];
+
+    for server in servers {
+        for channel in clients.get_channels(&server) {
+            let command = if broadcast.notice {
+                Command::Unknown(
+                    "NOTICE".to_string(),
+                    vec![channel.clone(), broadcast.text.clone()],
+                )
+            } else {
+                Command::Msg(channel.clone(), broadcast.text.clone())
+            };
+
+            inputs.push(data::Input::command(
+                buffer::Upstream::Channel(server.clone(), channel.clone()),
+                command,
+            ));
+        }
+    }
+
+    inputs
+}
+
+/// Runs a command handled by the client, returning feedback to show in the buffer.
+fn local_command(
+    command: Command,
+    buffer: &buffer::Upstream,
+    clients: &mut client::Map,
+) -> Option<String> {
+    match command {
+        Command::Notify(notify) => Some(
+            clients
+                .notify(buffer.server(), notify)
+                .unwrap_or_else(|error| error.to_string()),
+        ),
+        Command::Timer(timer) => Some(
+            clients
+                .timer(buffer, timer)
+                .unwrap_or_else(|error| error.to_string()),
+        ),
+        Command::Disconnect(reason) => Some(
+            clients
+                .disconnect(buffer.server(), reason)
+                .unwrap_or_else(|error| error.to_string()),
+        ),
+        Command::UserModes(channel, modes) => clients
+            .user_modes(buffer.server(), &channel, modes)
+            .err()
+            .map(|error| error.to_string()),
+        Command::Ban(channel, nick, mask) => clients
+            .ban(buffer.server(), &channel, &nick, mask)
+            .err()
+            .map(|error| error.to_string()),
+        Command::Silence(mask) => clients
+            .silence(buffer.server(), mask.as_deref())
+            .err()
+            .map(|error| error.to_string()),
+        Command::Dcc(dcc) => Some(
+            clients
+                .dcc(buffer.server(), dcc)
+                .unwrap_or_else(|error| error.to_string()),
+        ),
+        Command::Cycle(channel, reason) => clients
+            .cycle(buffer.server(), &channel, reason)
+            .err()
+            .map(|error| error.to_string()),
+        Command::Reconnect => Some(
+            clients
+                .reconnect(buffer.server())
+                .unwrap_or_else(|error| error.to_string()),
+        ),
+        Command::DoNotDisturb(dnd) => Some(clients.do_not_disturb(dnd)),
+        Command::LogLevel(level) => Some(log_level(level)),
+        _ => None,
+    }
+}
+
+/// Changes what is logged until the configuration is loaded again,
+/// returning what is logged now.
+fn log_level(level: command::LogLevel) -> String {
+    let mut filter = data::log::filter();
+
+    match level {
+        command::LogLevel::Show => return filter.to_string(),
+        command::LogLevel::Set(verbosity, None) => filter.verbosity = verbosity,
+        command::LogLevel::Set(verbosity, Some(module)) => {
+            filter.modules.insert(module, verbosity);
+        }
+        command::LogLevel::Reset(None) => filter = data::log::configured(),
+        command::LogLevel::Reset(Some(module)) => {
+            match data::log::configured().modules.get(&module) {
+                Some(verbosity) => filter.modules.insert(module, *verbosity),
+                None => filter.modules.remove(&module),
+            };
+        }
+    }
+
+    data::log::set_filter(filter.clone());
+
+    filter.to_string()
+}
+
 #[derive(Debug, Clone)]
 pub struct State {
     input_id: text_input::Id,
     error: Option<String>,
     completion: Completion,
     selected_history: Option<usize>,
+    paste: Option<Paste>,
 }
 
 impl Default for State {
@@ -113,6 +436,7 @@ impl State {
             error: None,
             completion: Completion::default(),
             selected_history: None,
+            paste: None,
         }
     }
 
@@ -131,14 +455,7 @@ impl State {
                 // Reset selected history
                 self.selected_history = None;
 
-                let users = buffer
-                    .channel()
-                    .map(|channel| clients.get_channel_users(buffer.server(), channel))
-                    .unwrap_or_default();
-                let channels = clients.get_channels(buffer.server());
-                let isupport = clients.get_isupport(buffer.server());
-
-                self.completion.process(&input, users, channels, &isupport);
+                self.process_completion(&input, buffer, clients, history);
 
                 history.record_draft(Draft {
                     buffer: buffer.clone(),
@@ -156,7 +473,8 @@ impl State {
                 self.selected_history = None;
 
                 if let Some(entry) = self.completion.select() {
-                    let new_input = entry.complete_input(input);
+                    let new_input =
+                        entry.complete_input(input, &config.buffer.text_input.autocomplete);
 
                     self.on_completion(buffer, history, new_input)
                 } else if !input.is_empty() {
@@ -167,6 +485,8 @@ impl State {
                         buffer.clone(),
                         config.buffer.text_input.auto_format,
                         input,
+                        clients.get_line_len(buffer.server()),
+                        clients.get_statusmsg(buffer.server()),
                     ) {
                         Ok(input) => input,
                         Err(error) => {
@@ -175,38 +495,24 @@ impl State {
                         }
                     };
 
-                    if let Some(encoded) = input.encoded() {
-                        clients.send(buffer, encoded);
-                    }
-
-                    let mut history_task = Task::none();
-
-                    if let Some(nick) = clients.nickname(buffer.server()) {
-                        let mut user = nick.to_owned().into();
-                        let mut channel_users = &[][..];
-                        let chantypes = clients.get_chantypes(buffer.server());
-                        let statusmsg = clients.get_statusmsg(buffer.server());
-
-                        // Resolve our attributes if sending this message in a channel
-                        if let buffer::Upstream::Channel(server, channel) = &buffer {
-                            channel_users = clients.get_channel_users(server, channel);
-
-                            if let Some(user_with_attributes) =
-                                clients.resolve_user_attributes(server, channel, &user)
-                            {
-                                user = user_with_attributes.clone();
-                            }
-                        }
+                    // Layouts are managed by the dashboard
+                    let layout = match input.local_command() {
+                        Some(Command::Layout(layout)) => Some(layout),
+                        _ => None,
+                    };
 
-                        history_task = Task::batch(
-                            history
-                                .record_input(input, user, channel_users, chantypes, statusmsg)
-                                .into_iter()
-                                .map(Task::future),
-                        );
+                    let history_task = send(input, clients, history);
+
+                    match layout {
+                        Some(layout) => (
+                            Task::none(),
+                            Some(Event::Layout {
+                                layout,
+                                history_task,
+                            }),
+                        ),
+                        None => (Task::none(), Some(Event::InputSent { history_task })),
                     }
-
-                    (Task::none(), Some(Event::InputSent { history_task }))
                 } else {
                     (Task::none(), None)
                 }
@@ -215,7 +521,8 @@ impl State {
                 let input = history.input(buffer).draft;
 
                 if let Some(entry) = self.completion.tab(reverse) {
-                    let new_input = entry.complete_input(input);
+                    let new_input =
+                        entry.complete_input(input, &config.buffer.text_input.autocomplete);
 
                     self.on_completion(buffer, history, new_input)
                 } else {
@@ -240,15 +547,7 @@ impl State {
                         .unwrap()
                         .clone();
 
-                    let users = buffer
-                        .channel()
-                        .map(|channel| clients.get_channel_users(buffer.server(), channel))
-                        .unwrap_or_default();
-                    let channels = clients.get_channels(buffer.server());
-                    let isupport = clients.get_isupport(buffer.server());
-
-                    self.completion
-                        .process(&new_input, users, channels, &isupport);
+                    self.process_completion(&new_input, buffer, clients, history);
 
                     return self.on_completion(buffer, history, new_input);
                 }
@@ -268,26 +567,179 @@ impl State {
                         *index -= 1;
                         let new_input = cache.history.get(*index).unwrap().clone();
 
-                        let users = buffer
-                            .channel()
-                            .map(|channel| clients.get_channel_users(buffer.server(), channel))
-                            .unwrap_or_default();
-                        let channels = clients.get_channels(buffer.server());
-                        let isupport = clients.get_isupport(buffer.server());
+                        self.process_completion(&new_input, buffer, clients, history);
 
-                        self.completion
-                            .process(&new_input, users, channels, &isupport);
                         new_input
                     };
 
                     return self.on_completion(buffer, history, new_input);
                 }
 
+                (Task::none(), None)
+            }
+            Message::Paste(text) => {
+                let mut paste = Paste::new(&text);
+
+                paste.multiline = buffer.target().is_some()
+                    && clients
+                        .get_multiline(buffer.server())
+                        .is_some_and(|multiline| multiline.fits(&paste.lines));
+
+                self.error = None;
+                self.paste = Some(paste);
+
+                (Task::none(), None)
+            }
+            Message::SendPaste(mode) => self.send_paste(mode, buffer, clients, history, config),
+            Message::UploadPaste => {
+                let (Some(paste), Some(pastebin)) = (
+                    self.paste.as_mut(),
+                    config.buffer.text_input.paste.pastebin.clone(),
+                ) else {
+                    return (Task::none(), None);
+                };
+
+                paste.uploading = true;
+
+                (
+                    Task::perform(
+                        pastebin::upload(pastebin, paste.lines.join("\n")),
+                        Message::PasteUploaded,
+                    ),
+                    None,
+                )
+            }
+            Message::PasteUploaded(Ok(url)) => {
+                if self.paste.take().is_none() {
+                    return (Task::none(), None);
+                }
+
+                let draft = history.input(buffer).draft;
+
+                let text = if draft.is_empty() {
+                    url
+                } else {
+                    format!("{} {url}", draft.trim_end())
+                };
+
+                self.on_completion(buffer, history, text)
+            }
+            Message::PasteUploaded(Err(error)) => {
+                if let Some(paste) = self.paste.as_mut() {
+                    paste.uploading = false;
+                    self.error = Some(format!("failed to upload paste: {error}"));
+                }
+
+                (Task::none(), None)
+            }
+            Message::CancelPaste => {
+                self.paste = None;
+
+                (Task::none(), None)
+            }
+            Message::Format(code) => (self.insert_formatting(code, buffer.clone(), history), None),
+        }
+    }
+
+    fn send_paste(
+        &mut self,
+        mode: paste::Mode,
+        buffer: &buffer::Upstream,
+        clients: &mut client::Map,
+        history: &mut history::Manager,
+        config: &Config,
+    ) -> (Task<Message>, Option<Event>) {
+        let Some(paste) = self.paste.take() else {
+            return (Task::none(), None);
+        };
+
+        let lines = paste
+            .lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if let (paste::Mode::Multiline, Some(target)) = (mode, buffer.target()) {
+            if clients.send_multiline(buffer, lines.clone(), false) {
+                let history_task = Task::batch(lines.into_iter().map(|line| {
+                    record(
+                        data::Input::command(buffer.clone(), Command::Msg(target.clone(), line)),
+                        clients,
+                        history,
+                    )
+                }));
+
+                return (Task::none(), Some(Event::InputSent { history_task }));
+            }
+        }
+
+        // Each line is sent as if typed, throttled by flood protection
+        let inputs = lines
+            .iter()
+            .map(|line| {
+                input::parse(
+                    buffer.clone(),
+                    config.buffer.text_input.auto_format,
+                    line,
+                    clients.get_line_len(buffer.server()),
+                    clients.get_statusmsg(buffer.server()),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>();
+
+        match inputs {
+            Ok(inputs) => {
+                let history_task = Task::batch(
+                    inputs
+                        .into_iter()
+                        .map(|input| send(input, clients, history)),
+                );
+
+                (Task::none(), Some(Event::InputSent { history_task }))
+            }
+            Err(error) => {
+                self.error = Some(error.to_string());
+                self.paste = Some(paste);
+
                 (Task::none(), None)
             }
         }
     }
 
+    fn process_completion(
+        &mut self,
+        input: &str,
+        buffer: &buffer::Upstream,
+        clients: &client::Map,
+        history: &history::Manager,
+    ) {
+        let server = buffer.server();
+
+        let users = buffer
+            .channel()
+            .map(|channel| clients.get_channel_users(server, channel))
+            .unwrap_or_default();
+        let last_seen = || history.last_seen(&history::Kind::from(buffer.clone()));
+        // Channels of other servers follow those of this one
+        let channels = clients
+            .get_channels(server)
+            .iter()
+            .chain(
+                clients
+                    .connected_servers()
+                    .filter(|other| *other != server)
+                    .flat_map(|other| clients.get_channels(other)),
+            )
+            .unique()
+            .cloned()
+            .collect::<Vec<_>>();
+        let isupport = clients.get_isupport(server);
+
+        self.completion
+            .process(input, users, last_seen, &channels, isupport);
+    }
+
     fn on_completion(
         &self,
         buffer: &buffer::Upstream,
@@ -310,6 +762,24 @@ impl State {
         self.error = None;
         self.completion = Completion::default();
         self.selected_history = None;
+        self.paste = None;
+    }
+
+    /// Appends a formatting code to the draft, to format what's typed next.
+    pub fn insert_formatting(
+        &mut self,
+        code: String,
+        buffer: buffer::Upstream,
+        history: &mut history::Manager,
+    ) -> Task<Message> {
+        let text = format!("{}{code}", history.input(&buffer).draft);
+
+        history.record_draft(Draft { buffer, text });
+
+        Task::batch([
+            text_input::focus(self.input_id.clone()),
+            text_input::move_cursor_to_end(self.input_id.clone()),
+        ])
     }
 
     pub fn insert_user(
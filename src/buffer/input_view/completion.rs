@@ -1,8 +1,11 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fmt;
 
+use chrono::{DateTime, Utc};
+use data::buffer::Autocomplete;
 use data::isupport;
-use data::user::User;
+use data::user::{Nick, User};
 use iced::widget::{column, container, row, text, tooltip};
 use iced::Length;
 use once_cell::sync::Lazy;
@@ -28,6 +31,7 @@ impl Completion {
         &mut self,
         input: &str,
         users: &[User],
+        last_seen: impl FnOnce() -> HashMap<Nick, DateTime<Utc>>,
         channels: &[String],
         isupport: &HashMap<isupport::Kind, isupport::Parameter>,
     ) {
@@ -40,10 +44,12 @@ impl Completion {
             if matches!(self.commands, Commands::Selecting { .. }) {
                 self.text = Text::default();
             } else {
-                self.text.process(input, users, channels);
+                let arg = self.commands.arg_kind(input);
+
+                self.text.process(input, arg, users, last_seen, channels);
             }
         } else {
-            self.text.process(input, users, channels);
+            self.text.process(input, None, users, last_seen, channels);
             self.commands = Commands::default();
         }
     }
@@ -53,10 +59,10 @@ impl Completion {
     }
 
     pub fn tab(&mut self, reverse: bool) -> Option<Entry> {
-        if !self.commands.tab(reverse) {
-            self.text.tab(reverse).map(Entry::Text)
+        if self.commands.tab(reverse) {
+            self.commands.highlighted().map(Entry::Command)
         } else {
-            None
+            self.text.tab(reverse).map(Entry::Text)
         }
     }
 
@@ -72,14 +78,14 @@ pub enum Entry {
 }
 
 impl Entry {
-    pub fn complete_input(&self, input: &str) -> String {
+    pub fn complete_input(&self, input: &str, autocomplete: &Autocomplete) -> String {
         match self {
             Entry::Command(command) => format!("/{}", command.title.to_lowercase()),
             Entry::Text(next) => {
                 let is_channel = next.starts_with('#');
-                let colon_space = ": ";
-
-                let trimmed_input = input.trim_end_matches(colon_space);
+                let trimmed_input = input
+                    .trim_end_matches(autocomplete.completion_suffix(true))
+                    .trim_end_matches(autocomplete.completion_suffix(false));
                 let mut words: Vec<_> = trimmed_input.split_whitespace().collect();
 
                 // Replace the last word with the next word
@@ -91,13 +97,9 @@ impl Entry {
 
                 let mut new_input = words.join(" ");
 
-                if words.len() == 1 && !is_channel {
-                    // If completed at the beginning of the input line, ': ' (colon space) is appended.
-                    new_input.push_str(colon_space);
-                } else {
-                    // Otherwise, a space is appended to the completion.
-                    new_input.push(' ');
-                }
+                // A nickname completed at the beginning of the input line is
+                // addressed, e.g. with ': ' (colon space) appended.
+                new_input.push_str(autocomplete.completion_suffix(words.len() == 1 && !is_channel));
 
                 new_input
             }
@@ -334,6 +336,27 @@ impl Commands {
         }
     }
 
+    fn highlighted(&self) -> Option<Command> {
+        match self {
+            Self::Selecting {
+                highlighted: Some(index),
+                filtered,
+            } => filtered.get(*index).cloned(),
+            _ => None,
+        }
+    }
+
+    /// What the argument being typed refers to, to complete it accordingly.
+    fn arg_kind(&self, input: &str) -> Option<ArgKind> {
+        match self {
+            Self::Selected {
+                command,
+                subcommand,
+            } => subcommand.as_ref().unwrap_or(command).arg_kind(input),
+            Self::Idle | Self::Selecting { .. } => None,
+        }
+    }
+
     fn select(&mut self) -> Option<Command> {
         if let Self::Selecting {
             highlighted: Some(index),
@@ -460,8 +483,35 @@ pub struct Command {
 impl Command {
     fn description(&self) -> Option<&'static str> {
         Some(match self.title.to_lowercase().as_str() {
+            "amsg" => "Send a message to all joined channels",
+            "anotice" => "Send a notice to all joined channels",
             "away" => "Mark yourself as away. If already away, the status is removed",
+            "ban" => "Ban a user from the channel",
+            "cs" => "Send a command to ChanServ",
+            "ctcp" => "Send a client-to-client query to a user",
+            "cycle" => "Leave the channel and join it again with its key",
+            "dcc" => "Chat with a user over a direct connection",
+            "dcc chat" => "Offer to chat with a user, or accept their offer",
+            "dcc close" => "Close a chat, or decline an offer",
+            "debug" => "Write a report of the configuration and servers for bug reports",
+            "debug export" => "Write a report of the configuration and servers for bug reports",
+            "deop" => "Remove operator status from user(s) in the channel",
+            "devoice" => "Remove voice from user(s) in the channel",
+            "disconnect" => "Disconnect from the current server with an optional reason",
+            "dnd" => "Toggle do not disturb, suppressing notifications",
+            "export" => "Export the history of the buffer to a text, JSON or HTML file",
+            "identify" => "Identify with NickServ",
+            "ignores" => "Export or import the ignore masks and filters of the server",
+            "ignores export" => "Write the ignore masks and filters of the server to a file",
+            "ignores import" => "Merge the ignore masks and filters in a file into the server's",
+            "import" => "Import WeeChat, irssi or ZNC logs into the history of the buffer",
             "join" => "Join channel(s) with optional key(s)",
+            "layout" => "Save, load or delete named layouts of the main window",
+            "layout delete" => "Delete a saved layout",
+            "layout list" => "Show the saved layouts",
+            "layout load" => "Replace the open buffers with a saved layout",
+            "layout save" => "Save the open buffers and their arrangement as a layout",
+            "loglevel" => "Show or change what is logged, overall or by module",
             "me" => "Send an action message to the channel",
             "mode" => "Set mode(s) on a target or retrieve the current mode(s) set. A target can be a channel or an user",
             "monitor" => "System to notify when users become online/offline",
@@ -472,10 +522,23 @@ impl Command {
             "monitor s" => "For each user in the list being monitored, get the current status",
             "msg" => "Open a query with a nickname and send an optional message",
             "nick" => "Change your nickname on the current server",
+            "ns" => "Send a command to NickServ",
+            "notify" => "Get notified when users become online/offline",
+            "notify add" => "Add user(s) to the notify list",
+            "notify del" => "Remove user(s) from the notify list",
+            "notify list" => "Show the notify list",
+            "op" => "Give operator status to user(s) in the channel",
+            "opmsg" => "Send a message only the operators of the channel will see",
             "part" => "Leave channel(s) with an optional reason",
             "quit" => "Disconnect from the server with an optional reason",
+            "quiet" => "Quiet user(s) in the channel",
             "raw" => "Send data to the server without modifying it",
+            "reconnect" => "Reconnect to the current server",
+            "register" => "Register your nickname with NickServ",
+            "silence" => "List, add or remove masks on the server's silence list",
+            "timer" => "Run a message or command after a delay, optionally repeating it",
             "topic" => "Retrieve the topic of a channel or set a new topic",
+            "voice" => "Give voice to user(s) in the channel",
             "whois" => "Retrieve information about user(s)",
             "format" => "Format text using markdown or $ sequences",
 
@@ -485,16 +548,44 @@ impl Command {
 
     fn alias(&self) -> Vec<&str> {
         match self.title.to_lowercase().as_str() {
+            "amsg" => vec![],
+            "anotice" => vec![],
             "away" => vec![],
+            "ban" => vec![],
+            "cs" => vec!["chanserv"],
+            "ctcp" => vec![],
+            "cycle" => vec![],
+            "dcc" => vec![],
+            "debug" => vec![],
+            "deop" => vec![],
+            "devoice" => vec![],
+            "disconnect" => vec![],
+            "dnd" => vec![],
+            "export" => vec![],
+            "identify" => vec!["id"],
+            "ignores" => vec![],
+            "import" => vec![],
             "join" => vec!["j"],
+            "layout" => vec![],
+            "loglevel" => vec![],
             "me" => vec!["describe"],
             "mode" => vec!["m"],
             "msg" => vec![],
             "nick" => vec![],
+            "notify" => vec![],
+            "ns" => vec!["nickserv"],
+            "op" => vec![],
+            "opmsg" => vec![],
             "part" => vec!["leave"],
             "quit" => vec![""],
-            "raw" => vec![],
+            "quiet" => vec![],
+            "raw" => vec!["quote"],
+            "reconnect" => vec![],
+            "register" => vec![],
+            "silence" => vec![],
+            "timer" => vec![],
             "topic" => vec!["t"],
+            "voice" => vec![],
             "whois" => vec![],
             "format" => vec!["f"],
 
@@ -502,6 +593,21 @@ impl Command {
         }
     }
 
+    fn arg_kind(&self, input: &str) -> Option<ArgKind> {
+        let index = [input, "_"]
+            .concat()
+            .split_ascii_whitespace()
+            .count()
+            .checked_sub(1 + self.title.split_ascii_whitespace().count())?;
+
+        match self.args.get(index)?.text {
+            "channel" | "channels" => Some(ArgKind::Channel),
+            "nick" | "nicks" | "user" | "users" => Some(ArgKind::Nick),
+            "target" | "targets" => Some(ArgKind::Target),
+            _ => None,
+        }
+    }
+
     fn view<'a, Message: 'a>(&self, input: &str) -> Element<'a, Message> {
         let active_arg = [input, "_"]
             .concat()
@@ -585,6 +691,14 @@ impl fmt::Display for Arg {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgKind {
+    Channel,
+    Nick,
+    /// A channel or a nick
+    Target,
+}
+
 #[derive(Debug, Clone, Default)]
 struct Text {
     prompt: String,
@@ -593,51 +707,88 @@ struct Text {
 }
 
 impl Text {
-    fn process(&mut self, input: &str, users: &[User], channels: &[String]) {
-        if !self.process_channels(input, channels) {
-            self.process_users(input, users);
+    fn process(
+        &mut self,
+        input: &str,
+        arg: Option<ArgKind>,
+        users: &[User],
+        last_seen: impl FnOnce() -> HashMap<Nick, DateTime<Utc>>,
+        channels: &[String],
+    ) {
+        match arg {
+            Some(ArgKind::Channel) => self.process_channels(input, channels, true),
+            Some(ArgKind::Nick) => self.process_users(input, users, last_seen, true),
+            Some(ArgKind::Target) | None => {
+                if !self.process_channels(input, channels, false) {
+                    self.process_users(input, users, last_seen, arg.is_some());
+                }
+            }
         }
     }
 
-    fn process_users(&mut self, input: &str, users: &[User]) {
+    /// Completes nicks, most recently seen speaking first.
+    fn process_users(
+        &mut self,
+        input: &str,
+        users: &[User],
+        last_seen: impl FnOnce() -> HashMap<Nick, DateTime<Utc>>,
+        allow_empty: bool,
+    ) {
         let (_, rest) = input.rsplit_once(' ').unwrap_or(("", input));
 
-        if rest.is_empty() {
+        if rest.is_empty() && !allow_empty {
             *self = Self::default();
             return;
         }
 
         let nick = rest.to_lowercase();
 
+        let mut filtered = users
+            .iter()
+            .filter(|user| user.nickname().as_ref().to_lowercase().starts_with(&nick))
+            .collect::<Vec<_>>();
+
+        if filtered.len() > 1 {
+            let last_seen = last_seen();
+
+            filtered.sort_by_cached_key(|user| {
+                Reverse(last_seen.get(&user.nickname().to_owned()).copied())
+            });
+        }
+
         self.selected = None;
         self.prompt = rest.to_string();
-        self.filtered = users
-            .iter()
-            .filter_map(|user| {
-                let lower_nick = user.nickname().as_ref().to_lowercase();
-                lower_nick
-                    .starts_with(&nick)
-                    .then(|| user.nickname().to_string())
-            })
+        self.filtered = filtered
+            .into_iter()
+            .map(|user| user.nickname().to_string())
             .collect();
     }
 
-    fn process_channels(&mut self, input: &str, channels: &[String]) -> bool {
+    /// Completes channels after a `#`, or anywhere when `is_arg` is set.
+    fn process_channels(&mut self, input: &str, channels: &[String], is_arg: bool) -> bool {
         let (_, last) = input.rsplit_once(' ').unwrap_or(("", input));
-        let Some((_, rest)) = last.split_once('#') else {
-            *self = Self::default();
-            return false;
-        };
 
-        let channel = format!("#{}", rest.to_lowercase());
+        let (prompt, channel) = match last.split_once('#') {
+            Some((_, rest)) => (format!("#{rest}"), format!("#{}", rest.to_lowercase())),
+            None if is_arg => (last.to_string(), last.to_lowercase()),
+            None => {
+                *self = Self::default();
+                return false;
+            }
+        };
 
         self.selected = None;
-        self.prompt = format!("#{rest}");
+        self.prompt = prompt;
         self.filtered = channels
             .iter()
             .filter_map(|c| {
                 let lower_channel = c.to_lowercase();
-                lower_channel.starts_with(&channel).then(|| c.to_string())
+
+                (lower_channel.starts_with(&channel)
+                    || lower_channel
+                        .trim_start_matches(|c: char| !c.is_alphanumeric())
+                        .starts_with(&channel))
+                .then(|| c.to_string())
             })
             .collect();
 
@@ -752,6 +903,29 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             }],
             subcommands: None,
         },
+        Command {
+            title: "CTCP",
+            args: vec![
+                Arg {
+                    text: "nick",
+                    optional: false,
+                    tooltip: None,
+                },
+                Arg {
+                    text: "command",
+                    optional: false,
+                    tooltip: Some(String::from(
+                        "CLIENTINFO, PING, SOURCE, TIME, USERINFO, VERSION, ...",
+                    )),
+                },
+                Arg {
+                    text: "args",
+                    optional: true,
+                    tooltip: None,
+                },
+            ],
+            subcommands: None,
+        },
         Command {
             title: "ME",
             args: vec![Arg {
@@ -782,6 +956,74 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
             subcommands: None,
         },
+        Command {
+            title: "NOTIFY",
+            args: vec![Arg {
+                text: "subcommand",
+                optional: true,
+                tooltip: Some(String::from(
+                    "add: Add user(s) to the notify list\n\
+                     del: Remove user(s) from the notify list\n\
+                     list: Show the notify list",
+                )),
+            }],
+            subcommands: Some(vec![
+                Command {
+                    title: "NOTIFY ADD",
+                    args: vec![Arg {
+                        text: "users",
+                        optional: false,
+                        tooltip: Some(String::from("comma or space-separated")),
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "NOTIFY DEL",
+                    args: vec![Arg {
+                        text: "users",
+                        optional: false,
+                        tooltip: Some(String::from("comma or space-separated")),
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "NOTIFY LIST",
+                    args: vec![],
+                    subcommands: None,
+                },
+            ]),
+        },
+        Command {
+            title: "DCC",
+            args: vec![Arg {
+                text: "subcommand",
+                optional: false,
+                tooltip: Some(String::from(
+                    "chat: Offer to chat with a user, or accept their offer\n\
+                     close: Close a chat, or decline an offer",
+                )),
+            }],
+            subcommands: Some(vec![
+                Command {
+                    title: "DCC CHAT",
+                    args: vec![Arg {
+                        text: "nick",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "DCC CLOSE",
+                    args: vec![Arg {
+                        text: "nick",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+            ]),
+        },
         Command {
             title: "PART",
             args: vec![
@@ -798,6 +1040,15 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
             subcommands: None,
         },
+        Command {
+            title: "CYCLE",
+            args: vec![Arg {
+                text: "reason",
+                optional: true,
+                tooltip: None,
+            }],
+            subcommands: None,
+        },
         Command {
             title: "TOPIC",
             args: vec![
@@ -834,6 +1085,35 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
             subcommands: None,
         },
+        Command {
+            title: "BAN",
+            args: vec![
+                Arg {
+                    text: "nick",
+                    optional: false,
+                    tooltip: Some(String::from("or a full mask, e.g. *!*@host")),
+                },
+                Arg {
+                    text: "type",
+                    optional: true,
+                    tooltip: Some(String::from(
+                        "nick, user, host, domain or account\n(default: ban_mask of the server)",
+                    )),
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "SILENCE",
+            args: vec![Arg {
+                text: "mask",
+                optional: true,
+                tooltip: Some(String::from(
+                    "+mask to add, -mask to remove\nomit to list the silence list",
+                )),
+            }],
+            subcommands: None,
+        },
         Command {
             title: "KICK",
             args: vec![
@@ -871,6 +1151,342 @@ static COMMAND_LIST: Lazy<Vec<Command>> = Lazy::new(|| {
             ],
             subcommands: None,
         },
+        Command {
+            title: "TIMER",
+            args: vec![
+                Arg {
+                    text: "seconds",
+                    optional: false,
+                    tooltip: Some(String::from(
+                        "list: Show active timers\n\
+                         cancel {id}: Cancel a timer",
+                    )),
+                },
+                Arg {
+                    text: "-repeat N",
+                    optional: true,
+                    tooltip: Some(String::from("0 repeats until cancelled")),
+                },
+                Arg {
+                    text: "command",
+                    optional: false,
+                    tooltip: None,
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "AMSG",
+            args: vec![
+                Arg {
+                    text: "-all",
+                    optional: true,
+                    tooltip: Some(String::from("Send to channels on all servers")),
+                },
+                Arg {
+                    text: "text",
+                    optional: false,
+                    tooltip: None,
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "ANOTICE",
+            args: vec![
+                Arg {
+                    text: "-all",
+                    optional: true,
+                    tooltip: Some(String::from("Send to channels on all servers")),
+                },
+                Arg {
+                    text: "text",
+                    optional: false,
+                    tooltip: None,
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "DISCONNECT",
+            args: vec![Arg {
+                text: "reason",
+                optional: true,
+                tooltip: None,
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "RECONNECT",
+            args: vec![],
+            subcommands: None,
+        },
+        Command {
+            title: "DND",
+            args: vec![Arg {
+                text: "state",
+                optional: true,
+                tooltip: Some(String::from(
+                    "on: Suppress notifications\n\
+                     off: Show notifications, even during quiet hours\n\
+                     auto: Suppress notifications during quiet hours",
+                )),
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "LOGLEVEL",
+            args: vec![
+                Arg {
+                    text: "level",
+                    optional: true,
+                    tooltip: Some(String::from(
+                        "off, error, warn, info, debug or trace
+                         reset: Go back to the configured level",
+                    )),
+                },
+                Arg {
+                    text: "module",
+                    optional: true,
+                    tooltip: Some(String::from("e.g. data::client")),
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "LAYOUT",
+            args: vec![Arg {
+                text: "subcommand",
+                optional: true,
+                tooltip: Some(String::from(
+                    "save: Save the open buffers and their arrangement as a layout\n\
+                     load: Replace the open buffers with a saved layout\n\
+                     delete: Delete a saved layout\n\
+                     list: Show the saved layouts",
+                )),
+            }],
+            subcommands: Some(vec![
+                Command {
+                    title: "LAYOUT SAVE",
+                    args: vec![Arg {
+                        text: "name",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "LAYOUT LOAD",
+                    args: vec![Arg {
+                        text: "name",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "LAYOUT DELETE",
+                    args: vec![Arg {
+                        text: "name",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "LAYOUT LIST",
+                    args: vec![],
+                    subcommands: None,
+                },
+            ]),
+        },
+        Command {
+            title: "DEBUG",
+            args: vec![Arg {
+                text: "subcommand",
+                optional: false,
+                tooltip: Some(String::from(
+                    "export: Write a report of the configuration and servers for bug reports",
+                )),
+            }],
+            subcommands: Some(vec![Command {
+                title: "DEBUG EXPORT",
+                args: vec![],
+                subcommands: None,
+            }]),
+        },
+        Command {
+            title: "IGNORES",
+            args: vec![Arg {
+                text: "subcommand",
+                optional: false,
+                tooltip: Some(String::from(
+                    "export: Write the ignore masks and filters of the server to a file\n\
+                     import: Merge the ignore masks and filters in a file into the server's",
+                )),
+            }],
+            subcommands: Some(vec![
+                Command {
+                    title: "IGNORES EXPORT",
+                    args: vec![Arg {
+                        text: "path",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+                Command {
+                    title: "IGNORES IMPORT",
+                    args: vec![Arg {
+                        text: "path",
+                        optional: false,
+                        tooltip: None,
+                    }],
+                    subcommands: None,
+                },
+            ]),
+        },
+        Command {
+            title: "OP",
+            args: vec![Arg {
+                text: "nicks",
+                optional: false,
+                tooltip: Some(String::from("space-separated")),
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "OPMSG",
+            args: vec![Arg {
+                text: "text",
+                optional: false,
+                tooltip: None,
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "DEOP",
+            args: vec![Arg {
+                text: "nicks",
+                optional: false,
+                tooltip: Some(String::from("space-separated")),
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "VOICE",
+            args: vec![Arg {
+                text: "nicks",
+                optional: false,
+                tooltip: Some(String::from("space-separated")),
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "DEVOICE",
+            args: vec![Arg {
+                text: "nicks",
+                optional: false,
+                tooltip: Some(String::from("space-separated")),
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "QUIET",
+            args: vec![Arg {
+                text: "nicks",
+                optional: false,
+                tooltip: Some(String::from("space-separated")),
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "EXPORT",
+            args: vec![
+                Arg {
+                    text: "format",
+                    optional: true,
+                    tooltip: Some(String::from("text, json or html")),
+                },
+                Arg {
+                    text: "since",
+                    optional: true,
+                    tooltip: Some(String::from("YYYY-MM-DD")),
+                },
+                Arg {
+                    text: "until",
+                    optional: true,
+                    tooltip: Some(String::from("YYYY-MM-DD")),
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "IMPORT",
+            args: vec![
+                Arg {
+                    text: "format",
+                    optional: false,
+                    tooltip: Some(String::from("weechat, irssi or znc")),
+                },
+                Arg {
+                    text: "path",
+                    optional: false,
+                    tooltip: Some(String::from("log file or directory of log files")),
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "NS",
+            args: vec![Arg {
+                text: "command",
+                optional: false,
+                tooltip: None,
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "CS",
+            args: vec![Arg {
+                text: "command",
+                optional: false,
+                tooltip: None,
+            }],
+            subcommands: None,
+        },
+        Command {
+            title: "IDENTIFY",
+            args: vec![
+                Arg {
+                    text: "account",
+                    optional: true,
+                    tooltip: Some(String::from("Defaults to your nickname")),
+                },
+                Arg {
+                    text: "password",
+                    optional: false,
+                    tooltip: None,
+                },
+            ],
+            subcommands: None,
+        },
+        Command {
+            title: "REGISTER",
+            args: vec![
+                Arg {
+                    text: "password",
+                    optional: false,
+                    tooltip: None,
+                },
+                Arg {
+                    text: "email",
+                    optional: false,
+                    tooltip: None,
+                },
+            ],
+            subcommands: None,
+        },
         Command {
             title: "FORMAT",
             args: vec![
@@ -0,0 +1,98 @@
+use data::message::formatting::{self, Color, Modifier};
+use data::{input, message, Config};
+use iced::widget::{button, center, column, container, row, span, text, Space};
+use iced::Length;
+
+use super::Message;
+use crate::widget::message_content::formatted_span;
+use crate::widget::{selectable_rich_text, tooltip, Element};
+use crate::{font, icon, theme, Theme};
+
+/// Colors offered by the toolbar, the ones every client agrees on
+const COLORS: u8 = 16;
+
+pub fn toolbar<'a>(config: &Config, theme: &Theme) -> Element<'a, Message> {
+    let show_tooltips = config.tooltips;
+
+    let control = |content: Element<'a, Message>, code: String, name: &'a str| {
+        tooltip(
+            button(center(content))
+                .padding(0)
+                .width(22)
+                .height(22)
+                .on_press(Message::Format(code))
+                .style(|theme, status| theme::button::secondary(theme, status, false)),
+            show_tooltips.then_some(name),
+            tooltip::Position::Top,
+        )
+    };
+
+    let modifiers = [
+        ("B", &font::MONO_BOLD, Modifier::Bold, "Bold"),
+        ("I", &font::MONO_ITALICS, Modifier::Italics, "Italics"),
+        ("U", &font::MONO, Modifier::Underline, "Underline"),
+        ("S", &font::MONO, Modifier::Strikethrough, "Strikethrough"),
+        ("M", &font::MONO, Modifier::Monospace, "Monospace"),
+    ]
+    .into_iter()
+    .map(|(label, font, modifier, name)| {
+        control(
+            text(label).font(font.clone()).into(),
+            modifier.char().to_string(),
+            name,
+        )
+    });
+
+    let colors = (0..COLORS).filter_map(|code| {
+        let color = Color::code(code)?.into_iced(theme.colors())?;
+
+        // Always two digits, so digits typed after it aren't read as part of the color
+        Some(control(
+            container(Space::new(12, 12))
+                .style(move |theme| theme::container::color_swatch(theme, color))
+                .into(),
+            format!("{}{code:02}", Modifier::Color.char()),
+            "Color",
+        ))
+    });
+
+    let reset = control(
+        icon::cancel().into(),
+        Modifier::Reset.char().to_string(),
+        "Reset",
+    );
+
+    row(modifiers.chain(colors).chain([reset]))
+        .spacing(2)
+        .align_y(iced::Alignment::Center)
+        .into()
+}
+
+/// The draft as recipients will see it, if it has any formatting.
+pub fn preview<'a>(draft: &str, config: &Config, theme: &Theme) -> Option<Element<'a, Message>> {
+    let fragments = input::formatting(config.buffer.text_input.auto_format, draft)?;
+
+    let spans = fragments
+        .into_iter()
+        .map(|fragment| match fragment {
+            formatting::Fragment::Unformatted(text) => span(text),
+            formatting::Fragment::Formatted(text, formatting) => {
+                formatted_span(text, &formatting, theme)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Some(
+        container(
+            column![
+                text("Preview").style(theme::text::secondary),
+                selectable_rich_text::<_, message::Link, (), _, _>(spans),
+            ]
+            .spacing(4),
+        )
+        .width(Length::Fill)
+        .padding(8)
+        .style(theme::container::tooltip)
+        .into(),
+    )
+}
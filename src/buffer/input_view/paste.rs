@@ -0,0 +1,101 @@
+use data::Config;
+use iced::widget::{button, column, container, row, scrollable, text, Scrollable};
+use iced::Length;
+
+use super::Message;
+use crate::theme;
+use crate::widget::Element;
+
+/// Height of the preview of a paste waiting to be sent
+const PREVIEW_HEIGHT: f32 = 120.0;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Mode {
+    /// Each line as its own message
+    Lines,
+    /// All lines as one `draft/multiline` message
+    Multiline,
+}
+
+/// A paste of many lines, waiting to be confirmed before it's sent.
+#[derive(Debug, Clone)]
+pub struct Paste {
+    pub lines: Vec<String>,
+    /// Whether the lines can be sent as one message
+    pub multiline: bool,
+    pub uploading: bool,
+}
+
+impl Paste {
+    pub fn new(text: &str) -> Self {
+        Self {
+            lines: text.trim_end().lines().map(String::from).collect(),
+            multiline: false,
+            uploading: false,
+        }
+    }
+
+    pub fn view<'a>(&self, config: &Config) -> Element<'a, Message> {
+        let count = self.lines.len();
+
+        let preview = Scrollable::new(
+            column(
+                self.lines
+                    .iter()
+                    .map(|line| text(line.clone()).style(theme::text::secondary).into()),
+            )
+            .width(Length::Fill),
+        )
+        .direction(scrollable::Direction::Vertical(
+            scrollable::Scrollbar::new().width(1).scroller_width(1),
+        ))
+        .style(theme::scrollable::hidden);
+
+        let action = |label: String, message: Message| {
+            button(text(label))
+                .padding([4, 8])
+                .on_press_maybe((!self.uploading).then_some(message))
+                .style(|theme, status| theme::button::secondary(theme, status, false))
+        };
+
+        let actions = row![action(
+            format!("Send {count} lines"),
+            Message::SendPaste(Mode::Lines)
+        )]
+        .push_maybe(self.multiline.then(|| {
+            action(
+                "Send as one message".to_string(),
+                Message::SendPaste(Mode::Multiline),
+            )
+        }))
+        .push_maybe(config.buffer.text_input.paste.pastebin.is_some().then(|| {
+            action(
+                if self.uploading {
+                    "Uploading...".to_string()
+                } else {
+                    "Upload to pastebin".to_string()
+                },
+                Message::UploadPaste,
+            )
+        }))
+        .push(
+            button(text("Cancel"))
+                .padding([4, 8])
+                .on_press(Message::CancelPaste)
+                .style(|theme, status| theme::button::secondary(theme, status, false)),
+        )
+        .spacing(4);
+
+        container(
+            column![
+                text(format!("Pasting {count} lines")),
+                container(preview).max_height(PREVIEW_HEIGHT),
+                actions,
+            ]
+            .spacing(6),
+        )
+        .padding(8)
+        .style(theme::container::tooltip)
+        .into()
+    }
+}
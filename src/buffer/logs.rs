@@ -1,3 +1,4 @@
+use data::config::buffer::Render;
 use data::{history, message, Config};
 use iced::widget::container;
 use iced::{Length, Task};
@@ -29,13 +30,15 @@ pub fn view<'a>(
             scroll_view::Kind::Logs,
             history,
             config,
-            move |message, _, _| match message.target.source() {
+            true,
+            move |message, _, _, _| match message.target.source() {
                 message::Source::Internal(message::source::Internal::Logs) => Some(
                     container(message_content(
                         &message.content,
                         theme,
                         scroll_view::Message::Link,
                         theme::selectable_text::default,
+                        Render::Full,
                         config,
                     ))
                     .into(),
@@ -64,7 +67,11 @@ impl Logs {
         Self::default()
     }
 
-    pub fn update(&mut self, message: Message) -> (Task<Message>, Option<Event>) {
+    pub fn update(
+        &mut self,
+        message: Message,
+        history: &mut history::Manager,
+    ) -> (Task<Message>, Option<Event>) {
         match message {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
@@ -72,7 +79,13 @@ impl Logs {
                 let event = event.and_then(|event| match event {
                     scroll_view::Event::UserContext(event) => Some(Event::UserContext(event)),
                     scroll_view::Event::OpenChannel(channel) => Some(Event::OpenChannel(channel)),
-                    scroll_view::Event::GoToMessage(_, _, _) => None,
+                    scroll_view::Event::GoToMessage(..) => None,
+                    scroll_view::Event::RequestOlder => {
+                        let task = history.load_older(&history::Kind::Logs);
+
+                        Some(Event::History(Task::batch(task.map(Task::future))))
+                    }
+                    scroll_view::Event::RequestMissing(..) | scroll_view::Event::Search(_) => None,
                 });
 
                 (command.map(Message::ScrollView), event)
@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use chrono::Local;
+use data::{mode, Server};
+use iced::widget::{
+    button, center, checkbox, column, container, horizontal_space, row, scrollable, text,
+    text_input, Scrollable,
+};
+use iced::{Length, Task};
+
+use crate::widget::{Element, Text};
+use crate::{icon, theme};
+
+/// List modes shown, in order, when the server supports them.
+const MODES: &[(char, &str)] = &[
+    ('b', "Bans"),
+    ('q', "Quiets"),
+    ('e', "Exceptions"),
+    ('I', "Invite exceptions"),
+];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Select(char),
+    SelectModes,
+    Refresh,
+    Toggle(String, bool),
+    Remove(Vec<String>),
+    SetModerated(bool),
+    SetInviteOnly(bool),
+    SetKey(String),
+    SetLimit(String),
+    ApplyModes,
+    DiscardModes,
+}
+
+/// What the buffer shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    /// Channel modes, e.g. moderated or the key
+    Modes,
+    List(char),
+}
+
+pub fn view<'a>(state: &'a ModeLists, clients: &'a data::client::Map) -> Element<'a, Message> {
+    let kinds = clients.get_channel_mode_kinds(&state.server);
+    let lists = clients.get_channel_lists(&state.server, &state.channel);
+
+    let modes_selected = state.tab == Tab::Modes;
+    let modes_tab = button(text("Modes"))
+        .padding([2, 6])
+        .on_press(Message::SelectModes)
+        .style(move |theme, status| theme::button::secondary(theme, status, modes_selected));
+
+    let tabs = MODES
+        .iter()
+        .filter(|(mode, _)| kinds.class(*mode) == Some(mode::Class::List))
+        .fold(row![modes_tab].spacing(4), |tabs, (mode, name)| {
+            let selected = state.tab == Tab::List(*mode);
+
+            tabs.push(
+                button(text(*name))
+                    .padding([2, 6])
+                    .on_press(Message::Select(*mode))
+                    .style(move |theme, status| theme::button::secondary(theme, status, selected)),
+            )
+        });
+
+    let remove_selected =
+        (matches!(state.tab, Tab::List(_)) && !state.selected.is_empty()).then(|| {
+            button(text(format!("Remove selected ({})", state.selected.len())))
+                .padding([2, 6])
+                .on_press(Message::Remove(state.selected.iter().cloned().collect()))
+                .style(|theme, status| theme::button::secondary(theme, status, false))
+        });
+
+    let header = container(
+        row![tabs, horizontal_space()]
+            .push_maybe(remove_selected)
+            .push_maybe(
+                matches!(state.tab, Tab::List(_))
+                    .then(|| row_button(icon::refresh(), Message::Refresh)),
+            )
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+    )
+    .padding([4, 8]);
+
+    let error = state
+        .error
+        .as_ref()
+        .map(|error| container(text(error).style(theme::text::error)).padding([0, 8]));
+
+    let body: Element<'a, Message> = match state.tab {
+        Tab::Modes => modes(state, clients, &kinds),
+        Tab::List(mode) => list(state, lists.map(|lists| lists.get(mode))),
+    };
+
+    column![header].push_maybe(error).push(body).into()
+}
+
+/// Editor for the modes set on the channel which the server supports.
+fn modes<'a>(
+    state: &'a ModeLists,
+    clients: &'a data::client::Map,
+    kinds: &mode::ChannelModeKinds,
+) -> Element<'a, Message> {
+    let Some(current) = clients.get_channel_modes(&state.server, &state.channel) else {
+        return center(text("Not in channel").style(theme::text::secondary)).into();
+    };
+
+    let shown = state
+        .edit
+        .as_ref()
+        .map_or(current, mode::ChannelModesEdit::modes);
+    let supports = |mode, class| kinds.class(mode) == Some(class);
+
+    let moderated = supports('m', mode::Class::Flag).then(|| {
+        checkbox("Moderated, only voiced users may talk", shown.moderated())
+            .on_toggle(Message::SetModerated)
+    });
+
+    let invite_only = supports('i', mode::Class::Flag)
+        .then(|| checkbox("Invite only", shown.invite_only()).on_toggle(Message::SetInviteOnly));
+
+    let key = supports('k', mode::Class::AlwaysArg).then(|| {
+        row![
+            text("Key").width(60),
+            text_input("No key", shown.key().unwrap_or_default())
+                .on_input(Message::SetKey)
+                .padding(4)
+                .style(theme::text_input::primary),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center)
+    });
+
+    let limit = supports('l', mode::Class::SetArg).then(|| {
+        row![
+            text("Limit").width(60),
+            text_input(
+                "No limit",
+                &shown
+                    .limit()
+                    .map(|limit| limit.to_string())
+                    .unwrap_or_default()
+            )
+            .on_input(Message::SetLimit)
+            .padding(4)
+            .style(theme::text_input::primary),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center)
+    });
+
+    let changed = state
+        .edit
+        .as_ref()
+        .is_some_and(|edit| !edit.changes(kinds).is_empty());
+
+    let actions = row![
+        button(text("Apply"))
+            .padding([2, 6])
+            .on_press_maybe(changed.then_some(Message::ApplyModes))
+            .style(|theme, status| theme::button::secondary(theme, status, false)),
+        button(text("Discard"))
+            .padding([2, 6])
+            .on_press_maybe(changed.then_some(Message::DiscardModes))
+            .style(|theme, status| theme::button::secondary(theme, status, false)),
+    ]
+    .spacing(4);
+
+    column![]
+        .push_maybe(moderated)
+        .push_maybe(invite_only)
+        .push_maybe(key)
+        .push_maybe(limit)
+        .push(actions)
+        .spacing(8)
+        .padding([4, 8])
+        .max_width(400)
+        .into()
+}
+
+fn list<'a>(state: &'a ModeLists, list: Option<Option<&'a mode::List>>) -> Element<'a, Message> {
+    match list {
+        None => center(text("Not in channel").style(theme::text::secondary)).into(),
+        Some(None) => center(text("Not loaded").style(theme::text::secondary)).into(),
+        Some(Some(list)) if list.loading => {
+            center(text("Loading...").style(theme::text::secondary)).into()
+        }
+        Some(Some(list)) if list.entries.is_empty() => {
+            center(text("No entries").style(theme::text::secondary)).into()
+        }
+        Some(Some(list)) => {
+            let rows = column(list.entries.iter().enumerate().map(|(idx, entry)| {
+                let mask = entry.mask.clone();
+                let selected = state.selected.contains(&entry.mask);
+
+                let set = match (&entry.set_by, entry.set_at) {
+                    (Some(set_by), Some(set_at)) => Some(format!(
+                        "set by {set_by} on {}",
+                        set_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    )),
+                    (Some(set_by), None) => Some(format!("set by {set_by}")),
+                    (None, Some(set_at)) => Some(format!(
+                        "set on {}",
+                        set_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    )),
+                    (None, None) => None,
+                };
+
+                container(
+                    row![
+                        checkbox("", selected)
+                            .on_toggle(move |checked| Message::Toggle(mask.clone(), checked)),
+                        column![text(entry.mask.as_str()).style(theme::text::primary)]
+                            .push_maybe(set.map(|set| text(set).style(theme::text::secondary)))
+                            .width(Length::Fill),
+                        row_button(icon::cancel(), Message::Remove(vec![entry.mask.clone()])),
+                    ]
+                    .spacing(4)
+                    .align_y(iced::Alignment::Center),
+                )
+                .padding(6)
+                .style(move |theme| theme::container::table(theme, idx))
+                .into()
+            }))
+            .spacing(1)
+            .padding([0, 2]);
+
+            container(
+                Scrollable::new(rows)
+                    .direction(scrollable::Direction::Vertical(
+                        scrollable::Scrollbar::new().width(1).scroller_width(1),
+                    ))
+                    .style(theme::scrollable::hidden),
+            )
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModeLists {
+    pub server: Server,
+    pub channel: String,
+    tab: Tab,
+    /// Masks selected for removal
+    selected: HashSet<String>,
+    /// Changes to the channel modes not applied yet
+    edit: Option<mode::ChannelModesEdit>,
+    error: Option<String>,
+}
+
+impl ModeLists {
+    pub fn new(server: Server, channel: String) -> Self {
+        Self {
+            server,
+            channel,
+            tab: Tab::List('b'),
+            selected: HashSet::new(),
+            edit: None,
+            error: None,
+        }
+    }
+
+    pub fn update(&mut self, message: Message, clients: &mut data::client::Map) -> Task<Message> {
+        match message {
+            Message::Select(mode) => {
+                self.tab = Tab::List(mode);
+                self.selected.clear();
+
+                let loaded = clients
+                    .get_channel_lists(&self.server, &self.channel)
+                    .is_some_and(|lists| lists.get(mode).is_some());
+
+                if !loaded {
+                    self.request(clients);
+                }
+            }
+            Message::SelectModes => {
+                self.tab = Tab::Modes;
+                self.selected.clear();
+            }
+            Message::Refresh => {
+                self.selected.clear();
+                self.request(clients);
+            }
+            Message::Toggle(mask, checked) => {
+                if checked {
+                    self.selected.insert(mask);
+                } else {
+                    self.selected.remove(&mask);
+                }
+            }
+            Message::Remove(masks) => {
+                for mask in &masks {
+                    self.selected.remove(mask);
+                }
+
+                let Tab::List(list) = self.tab else {
+                    return Task::none();
+                };
+
+                let modes = masks
+                    .into_iter()
+                    .map(|mask| mode::Mode::Remove(list, Some(mask)))
+                    .collect();
+
+                self.error = clients
+                    .user_modes(&self.server, &self.channel, modes)
+                    .err()
+                    .map(|error| error.to_string());
+            }
+            Message::SetModerated(moderated) => {
+                self.edit_modes(clients).set_moderated(moderated);
+            }
+            Message::SetInviteOnly(invite_only) => {
+                self.edit_modes(clients).set_invite_only(invite_only);
+            }
+            Message::SetKey(key) => {
+                // Keys can't hold spaces
+                if !key.contains(' ') {
+                    self.edit_modes(clients).set_key(Some(key));
+                }
+            }
+            Message::SetLimit(limit) => {
+                if limit.is_empty() {
+                    self.edit_modes(clients).set_limit(None);
+                } else if let Ok(limit) = limit.parse() {
+                    self.edit_modes(clients).set_limit(Some(limit));
+                }
+            }
+            Message::ApplyModes => {
+                let Some(edit) = self.edit.take() else {
+                    return Task::none();
+                };
+
+                let changes = edit.changes(&clients.get_channel_mode_kinds(&self.server));
+
+                self.error = clients
+                    .user_modes(&self.server, &self.channel, changes)
+                    .err()
+                    .map(|error| error.to_string());
+            }
+            Message::DiscardModes => {
+                self.edit = None;
+            }
+        }
+
+        Task::none()
+    }
+
+    /// Requests the list being shown from the server.
+    pub fn request(&mut self, clients: &mut data::client::Map) {
+        let Tab::List(list) = self.tab else {
+            return;
+        };
+
+        self.error = clients
+            .request_mode_list(&self.server, &self.channel, list)
+            .err()
+            .map(|error| error.to_string());
+    }
+
+    /// Changes to the channel modes, starting from those currently set.
+    fn edit_modes(&mut self, clients: &data::client::Map) -> &mut mode::ChannelModesEdit {
+        self.edit.get_or_insert_with(|| {
+            clients
+                .get_channel_modes(&self.server, &self.channel)
+                .cloned()
+                .unwrap_or_default()
+                .edit()
+        })
+    }
+}
+
+fn row_button(icon: Text, message: Message) -> Element<Message> {
+    button(center(icon))
+        .on_press(message)
+        .padding(5)
+        .width(22)
+        .height(22)
+        .style(|theme, status| theme::button::secondary(theme, status, false))
+        .into()
+}
@@ -1,6 +1,7 @@
+use data::client::Backfill;
 use data::user::Nick;
-use data::{buffer, history, message, Config, Server};
-use iced::widget::{column, container, row, vertical_space};
+use data::{buffer, command, history, message, Config, Server};
+use iced::widget::{column, container, row, vertical_space, Space};
 use iced::{alignment, Length, Task};
 
 use super::{input_view, scroll_view, user_context};
@@ -17,6 +18,7 @@ pub enum Event {
     UserContext(user_context::Event),
     OpenChannel(String),
     History(Task<history::manager::Message>),
+    Layout(command::Layout, Task<history::manager::Message>),
 }
 
 pub fn view<'a>(
@@ -31,6 +33,7 @@ pub fn view<'a>(
     let status = clients.status(server);
     let buffer = &state.buffer;
     let input = history.input(buffer);
+    let render = config.buffer.formatting.render(buffer);
 
     let messages = container(
         scroll_view::view(
@@ -38,7 +41,8 @@ pub fn view<'a>(
             scroll_view::Kind::Query(server, &state.nick),
             history,
             config,
-            move |message, max_nick_width, _| {
+            true,
+            move |message, max_nick_width, _, grouped| {
                 let timestamp =
                     config
                         .buffer
@@ -52,6 +56,7 @@ pub fn view<'a>(
                 match message.target.source() {
                     message::Source::User(user) => {
                         let with_access_levels = config.buffer.nickname.show_access_levels;
+                        let soft_ignored = clients.is_soft_ignored(server, user);
                         let mut text = selectable_text(
                             config
                                 .buffer
@@ -63,7 +68,7 @@ pub fn view<'a>(
                             theme::selectable_text::nickname(
                                 theme,
                                 user.nick_color(theme.colors(), config.buffer.nickname.color),
-                                false,
+                                soft_ignored,
                             )
                         });
 
@@ -73,27 +78,66 @@ pub fn view<'a>(
                                 .horizontal_alignment(alignment::Horizontal::Right);
                         }
 
-                        let nick = user_context::view(text, server, None, user, None, None)
+                        // Computed before `message` is shadowed by its content
+                        let indent = grouped.then(|| {
+                            scroll_view::group_indent(message, user, max_nick_width, config)
+                        });
+
+                        let nick = user_context::view(text, server, None, user, None, None, config)
                             .map(scroll_view::Message::UserContext);
 
                         let message = message_content::with_context(
                             &message.content,
                             theme,
                             scroll_view::Message::Link,
-                            theme::selectable_text::default,
+                            move |theme: &Theme| {
+                                if soft_ignored {
+                                    theme::selectable_text::dimmed(theme)
+                                } else {
+                                    theme::selectable_text::default(theme)
+                                }
+                            },
                             move |link| match link {
-                                message::Link::User(_) => user_context::Entry::list(false, None),
+                                message::Link::User(_) => user_context::Entry::list(
+                                    false,
+                                    None,
+                                    config.buffer.nickname.context_menu.len(),
+                                ),
                                 _ => vec![],
                             },
                             move |link, entry, length| match link {
                                 message::Link::User(user) => entry
-                                    .view(server, None, user, None, length)
+                                    .view(
+                                        server,
+                                        None,
+                                        user,
+                                        None,
+                                        &config.buffer.nickname.context_menu,
+                                        length,
+                                    )
                                     .map(scroll_view::Message::UserContext),
                                 _ => row![].into(),
                             },
+                            render,
                             config,
                         );
 
+                        if let Some((timestamp_width, nick_width)) = indent {
+                            return match &config.buffer.nickname.alignment {
+                                data::buffer::Alignment::Left | data::buffer::Alignment::Right => {
+                                    Some(
+                                        row![]
+                                            .push_maybe(timestamp_width.map(Space::with_width))
+                                            .push(Space::with_width(nick_width))
+                                            .push(space)
+                                            .push(message)
+                                            .into(),
+                                    )
+                                }
+                                data::buffer::Alignment::Top => Some(message.into()),
+                            };
+                        }
+
                         let timestamp_nickname_row =
                             row![].push_maybe(timestamp).push(nick).push(space);
 
@@ -118,6 +162,7 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             message_style,
+                            render,
                             config,
                         );
 
@@ -140,6 +185,7 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             theme::selectable_text::action,
+                            render,
                             config,
                         );
 
@@ -166,6 +212,7 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             message_style,
+                            render,
                             config,
                         );
 
@@ -180,6 +227,13 @@ pub fn view<'a>(
                             .into(),
                         )
                     }
+                    message::Source::Internal(message::source::Internal::Gap(disconnected_at)) => {
+                        Some(scroll_view::gap(
+                            *disconnected_at,
+                            message.server_time,
+                            config,
+                        ))
+                    }
                     message::Source::Internal(message::source::Internal::Logs) => None,
                 }
             },
@@ -196,8 +250,16 @@ pub fn view<'a>(
     let text_input = show_text_input.then(|| {
         column![
             vertical_space().height(4),
-            input_view::view(&state.input_view, input, is_focused, !status.connected())
-                .map(Message::InputView)
+            input_view::view(
+                &state.input_view,
+                input,
+                is_focused,
+                !status.connected(),
+                input_view::PLACEHOLDER,
+                config,
+                theme,
+            )
+            .map(Message::InputView)
         ]
         .width(Length::Fill)
     });
@@ -233,6 +295,16 @@ impl Query {
         }
     }
 
+    fn backfill(&self, clients: &mut data::client::Map, backfill: Backfill) {
+        if let Err(error) = clients.backfill(&self.server, self.nick.as_ref(), backfill) {
+            log::warn!(
+                "[{}] failed to request history for {}: {error}",
+                self.server,
+                self.nick
+            );
+        }
+    }
+
     pub fn update(
         &mut self,
         message: Message,
@@ -244,10 +316,46 @@ impl Query {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
 
+                if let Some(scroll_view::Event::Search(direction)) = event {
+                    let search = self.scroll_view.search(
+                        direction,
+                        scroll_view::Kind::Query(&self.server, &self.nick),
+                        history,
+                        config,
+                        true,
+                    );
+
+                    return (
+                        Task::batch(vec![command, search]).map(Message::ScrollView),
+                        None,
+                    );
+                }
+
                 let event = event.and_then(|event| match event {
                     scroll_view::Event::UserContext(event) => Some(Event::UserContext(event)),
                     scroll_view::Event::OpenChannel(channel) => Some(Event::OpenChannel(channel)),
-                    scroll_view::Event::GoToMessage(_, _, _) => None,
+                    scroll_view::Event::GoToMessage(..) | scroll_view::Event::Search(_) => None,
+                    scroll_view::Event::RequestOlder => {
+                        let kind = history::Kind::Query(self.server.clone(), self.nick.clone());
+
+                        // History saved to disk comes first, then the server's
+                        if history.has_older(&kind) {
+                            return Some(Event::History(Task::batch(
+                                history.load_older(&kind).map(Task::future),
+                            )));
+                        }
+
+                        if let Some(oldest) = history.oldest(&kind) {
+                            self.backfill(clients, Backfill::before(oldest));
+                        }
+
+                        None
+                    }
+                    scroll_view::Event::RequestMissing(since, until) => {
+                        self.backfill(clients, Backfill::Between(since, until));
+
+                        None
+                    }
                 });
 
                 (command.map(Message::ScrollView), event)
@@ -267,6 +375,10 @@ impl Query {
 
                         (command, Some(Event::History(history_task)))
                     }
+                    Some(input_view::Event::Layout {
+                        layout,
+                        history_task,
+                    }) => (command, Some(Event::Layout(layout, history_task))),
                     None => (command, None),
                 }
             }
@@ -1,34 +1,52 @@
+use std::iter;
+
+use chrono::{DateTime, Utc};
 use data::message::{self, Limit};
 use data::server::Server;
 use data::user::Nick;
-use data::{history, time, Config};
-use iced::widget::{column, container, horizontal_rule, row, scrollable, text, Scrollable};
+use data::{buffer, history, time, Config, User};
+use iced::widget::{button, column, container, horizontal_rule, row, scrollable, text, Scrollable};
 use iced::{padding, Length, Task};
 
 use self::keyed::keyed;
+pub use self::search::Direction;
+use self::search::Search;
 use super::user_context;
-use crate::widget::{Element, MESSAGE_MARKER_TEXT};
+use crate::widget::{Element, Row, MESSAGE_MARKER_TEXT};
 use crate::{font, theme};
 
+mod search;
+
 #[derive(Debug, Clone)]
 pub enum Message {
     Scrolled {
-        count: usize,
-        remaining: bool,
-        oldest: time::Posix,
+        rendered: Rendered,
         status: Status,
         viewport: scrollable::Viewport,
     },
     UserContext(user_context::Message),
     Link(message::Link),
     ScrollTo(keyed::Bounds),
+    /// Keeps a message at the same edge of the viewport after the window of
+    /// messages shown moved
+    KeepInView(keyed::Bounds, Anchor),
+    RequestMissing(DateTime<Utc>, DateTime<Utc>),
+    SearchInput(String),
+    Search(Direction),
+    CloseSearch,
 }
 
 #[derive(Debug, Clone)]
 pub enum Event {
     UserContext(user_context::Event),
     OpenChannel(String),
-    GoToMessage(Server, String, message::Hash),
+    GoToMessage(buffer::Upstream, message::Hash),
+    /// Scrolled past the oldest message in history
+    RequestOlder,
+    /// Clicked a gap left while disconnected
+    RequestMissing(DateTime<Utc>, DateTime<Utc>),
+    /// Requested the next search match
+    Search(Direction),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -54,32 +72,55 @@ impl From<Kind<'_>> for history::Kind {
     }
 }
 
+/// Widths of the timestamp and nickname left blank in front of a message
+/// continuing a group, keeping its text aligned with the first message.
+pub fn group_indent(
+    message: &data::Message,
+    user: &User,
+    max_nick_width: Option<f32>,
+    config: &Config,
+) -> (Option<f32>, f32) {
+    let timestamp = config
+        .buffer
+        .format_timestamp(&message.server_time)
+        .map(|timestamp| font::width_from_chars(timestamp.chars().count(), &config.font));
+
+    let nickname = max_nick_width.unwrap_or_else(|| {
+        let nickname = config
+            .buffer
+            .nickname
+            .brackets
+            .format(user.display(config.buffer.nickname.show_access_levels));
+
+        font::width_from_chars(nickname.chars().count(), &config.font)
+    });
+
+    (timestamp, nickname)
+}
+
 pub fn view<'a>(
     state: &State,
     kind: Kind,
     history: &'a history::Manager,
     config: &'a Config,
-    format: impl Fn(&'a data::Message, Option<f32>, Option<f32>) -> Option<Element<'a, Message>> + 'a,
+    smart_filter: bool,
+    format: impl Fn(&'a data::Message, Option<f32>, Option<f32>, bool) -> Option<Element<'a, Message>>
+        + 'a,
 ) -> Element<'a, Message> {
     let Some(history::View {
         total,
+        newer,
         old_messages,
         new_messages,
         max_nick_chars,
         max_prefix_chars,
-    }) = history.get_messages(&kind.into(), Some(state.limit), &config.buffer)
+        repeats,
+    }) = history.get_messages(&kind.into(), Some(state.limit), config, smart_filter)
     else {
         return column![].into();
     };
 
-    let count = old_messages.len() + new_messages.len();
-    let remaining = count < total;
-    let oldest = old_messages
-        .iter()
-        .chain(&new_messages)
-        .next()
-        .map(|message| message.received_at)
-        .unwrap_or_else(time::Posix::now);
+    let rendered = Rendered::new(total, newer, &old_messages, &new_messages);
     let status = state.status;
 
     let max_nick_width = max_nick_chars.map(|len| {
@@ -91,40 +132,88 @@ pub fn view<'a>(
 
     let max_prefix_width = max_prefix_chars.map(|len| font::width_from_chars(len, &config.font));
 
-    let old = old_messages
-        .into_iter()
-        .filter_map(|message| {
-            format(message, max_nick_width, max_prefix_width)
-                .map(|element| keyed(keyed::Key::message(message), element))
-        })
+    let search = state.search.as_ref();
+
+    let group_window = config.buffer.grouping.window();
+
+    let message_element =
+        |previous: Option<&'a data::Message>, message: &'a data::Message, after_divider: bool| {
+            // Whether to show the message under the nickname of the one before it.
+            // Groups don't continue past the divider.
+            let grouped = !after_divider
+                && group_window
+                    .zip(previous)
+                    .is_some_and(|(window, previous)| {
+                        message::continues_group(previous, message, window)
+                    });
+
+            let date_separator = config.buffer.date_separators.show
+                && previous.is_none_or(|previous| !message::is_same_day(previous, message));
+
+            format(message, max_nick_width, max_prefix_width, grouped).map(|element| {
+                let element = match repeats.get(&message.hash) {
+                    Some(count) => row![
+                        element,
+                        text(format!("×{count}"))
+                            .shaping(text::Shaping::Advanced)
+                            .size(divider_font_size(config))
+                            .style(theme::text::secondary)
+                    ]
+                    .spacing(4)
+                    .align_y(iced::Alignment::Center)
+                    .into(),
+                    None => element,
+                };
+                let element = with_previews(element, message, history, config);
+                let element = match search.filter(|search| search.is_match(message)) {
+                    Some(search) => {
+                        let is_current = search.is_current(message);
+
+                        container(element)
+                            .style(move |theme| theme::container::search_match(theme, is_current))
+                            .into()
+                    }
+                    None => element,
+                };
+                let element = if date_separator {
+                    column![
+                        divider(
+                            text(config.buffer.date_separators.format(&message.server_time))
+                                .size(divider_font_size(config))
+                                .style(theme::text::secondary),
+                        ),
+                        element
+                    ]
+                    .into()
+                } else {
+                    element
+                };
+
+                keyed(keyed::Key::message(message), element)
+            })
+        };
+
+    let old = iter::once(None)
+        .chain(old_messages.iter().copied().map(Some))
+        .zip(old_messages.iter().copied())
+        .filter_map(|(previous, message)| message_element(previous, message, false))
         .collect::<Vec<_>>();
-    let new = new_messages
-        .into_iter()
-        .filter_map(|message| {
-            format(message, max_nick_width, max_prefix_width)
-                .map(|element| keyed(keyed::Key::message(message), element))
-        })
+    let new = iter::once(old_messages.last().copied())
+        .chain(new_messages.iter().copied().map(Some))
+        .zip(new_messages.iter().copied())
+        .enumerate()
+        .filter_map(|(index, (previous, message))| message_element(previous, message, index == 0))
         .collect::<Vec<_>>();
 
-    let show_divider =
-        !new.is_empty() || matches!(status, Status::Idle(Anchor::Bottom) | Status::ScrollTo);
+    // Marks where unread messages start, until they're read
+    let show_divider = !new.is_empty() || matches!(status, Status::ScrollTo);
 
     let divider = if show_divider {
-        let font_size = config.font.size.map(f32::from).unwrap_or(theme::TEXT_SIZE) - 1.0;
-
-        row![
-            container(horizontal_rule(1))
-                .width(Length::Fill)
-                .padding(padding::right(6)),
+        divider(
             text("backlog")
-                .size(font_size)
+                .size(divider_font_size(config))
                 .style(theme::text::secondary),
-            container(horizontal_rule(1))
-                .width(Length::Fill)
-                .padding(padding::left(6))
-        ]
-        .padding(2)
-        .align_y(iced::Alignment::Center)
+        )
     } else {
         row![]
     };
@@ -135,7 +224,7 @@ pub fn view<'a>(
         column(new)
     ];
 
-    Scrollable::new(container(content).width(Length::Fill).padding([0, 8]))
+    let messages = Scrollable::new(container(content).width(Length::Fill).padding([0, 8]))
         .direction(scrollable::Direction::Vertical(
             scrollable::Scrollbar::default()
                 .anchor(status.alignment())
@@ -143,14 +232,146 @@ pub fn view<'a>(
                 .scroller_width(5),
         ))
         .on_scroll(move |viewport| Message::Scrolled {
-            count,
-            remaining,
-            oldest,
+            rendered,
             status,
             viewport,
         })
-        .id(state.scrollable.clone())
-        .into()
+        .id(state.scrollable.clone());
+
+    if let Some(search) = search {
+        column![search.view(), messages].into()
+    } else {
+        messages.into()
+    }
+}
+
+/// Shows the titles of pages linked in the message under it.
+fn with_previews<'a>(
+    element: Element<'a, Message>,
+    message: &'a data::Message,
+    history: &'a history::Manager,
+    config: &Config,
+) -> Element<'a, Message> {
+    if !config.preview.enabled {
+        return element;
+    }
+
+    let previews = message
+        .content
+        .urls()
+        .filter_map(|url| {
+            let preview = history.preview(url)?;
+
+            Some(
+                button(
+                    text(preview.to_string())
+                        .size(divider_font_size(config))
+                        .style(theme::text::secondary),
+                )
+                .padding(padding::left(16))
+                .style(theme::button::bare)
+                .on_press(Message::Link(message::Link::Url(url.to_string())))
+                .into(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if previews.is_empty() {
+        element
+    } else {
+        column![element, column(previews)].into()
+    }
+}
+
+/// Marks where messages may be missing while disconnected, requesting them
+/// from the server when clicked.
+pub fn gap<'a>(
+    disconnected_at: DateTime<Utc>,
+    reconnected_at: DateTime<Utc>,
+    config: &Config,
+) -> Element<'a, Message> {
+    divider(
+        button(
+            text("disconnected — messages may be missing")
+                .size(divider_font_size(config))
+                .style(theme::text::error),
+        )
+        .padding(0)
+        .style(theme::button::bare)
+        .on_press(Message::RequestMissing(disconnected_at, reconnected_at)),
+    )
+    .into()
+}
+
+fn divider<'a>(label: impl Into<Element<'a, Message>>) -> Row<'a, Message> {
+    row![
+        container(horizontal_rule(1))
+            .width(Length::Fill)
+            .padding(padding::right(6)),
+        label.into(),
+        container(horizontal_rule(1))
+            .width(Length::Fill)
+            .padding(padding::left(6))
+    ]
+    .padding(2)
+    .align_y(iced::Alignment::Center)
+}
+
+fn divider_font_size(config: &Config) -> f32 {
+    config.font.size.map(f32::from).unwrap_or(theme::TEXT_SIZE) - 1.0
+}
+
+/// The messages shown by the view, to decide what to show next as it's
+/// scrolled.
+#[derive(Debug, Clone, Copy)]
+pub struct Rendered {
+    count: usize,
+    /// Messages left out before those shown
+    older: usize,
+    /// Messages left out after those shown
+    newer: usize,
+    oldest: time::Posix,
+    first: Option<message::Hash>,
+    last: Option<message::Hash>,
+    /// Where the window ends once moved a step towards older messages
+    older_end: Option<time::Posix>,
+    /// Where the window starts once moved a step towards newer messages
+    newer_start: Option<time::Posix>,
+}
+
+impl Rendered {
+    fn new(
+        total: usize,
+        newer: usize,
+        old_messages: &[&data::Message],
+        new_messages: &[&data::Message],
+    ) -> Self {
+        let messages = || old_messages.iter().chain(new_messages);
+        let received_at = |index: usize| messages().nth(index).map(|message| message.received_at);
+
+        let count = old_messages.len() + new_messages.len();
+
+        Self {
+            count,
+            older: total - count - newer,
+            newer,
+            oldest: received_at(0).unwrap_or_else(time::Posix::now),
+            first: messages().next().map(|message| message.hash),
+            last: messages().last().map(|message| message.hash),
+            older_end: count
+                .checked_sub(Limit::DEFAULT_STEP + 1)
+                .and_then(received_at),
+            newer_start: received_at(Limit::DEFAULT_STEP),
+        }
+    }
+
+    /// Whether there are messages left out past the end away from `anchor`.
+    fn is_past_end(&self, anchor: Anchor) -> bool {
+        match anchor {
+            Anchor::Top => self.newer > 0,
+            Anchor::Bottom => self.older > 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -159,6 +380,7 @@ pub struct State {
     limit: Limit,
     status: Status,
     pending_scroll_to: Option<message::Hash>,
+    search: Option<Search>,
 }
 
 impl Default for State {
@@ -168,6 +390,7 @@ impl Default for State {
             limit: Limit::bottom(),
             status: Status::default(),
             pending_scroll_to: None,
+            search: None,
         }
     }
 }
@@ -180,14 +403,34 @@ impl State {
     pub fn update(&mut self, message: Message) -> (Task<Message>, Option<Event>) {
         match message {
             Message::Scrolled {
-                count,
-                remaining,
-                oldest,
+                rendered,
                 status: old_status,
                 viewport,
             } => {
+                let Rendered { count, oldest, .. } = rendered;
+                let remaining = rendered.older + rendered.newer > 0;
                 let relative_offset = viewport.relative_offset().y;
 
+                let request_older = rendered.older == 0
+                    && !matches!(old_status, Status::Loading(_))
+                    && old_status.is_top(relative_offset);
+                let event = request_older.then_some(Event::RequestOlder);
+
+                if !matches!(old_status, Status::ScrollTo | Status::Loading(_)) {
+                    if let Some((limit, key, edge)) =
+                        self.next_window(&rendered, old_status, relative_offset)
+                    {
+                        self.limit = limit;
+                        self.status = Status::Unlocked(Anchor::Bottom);
+
+                        return (
+                            keyed::find_bounds(self.scrollable.clone(), keyed::Key::Message(key))
+                                .map(move |bounds| Message::KeepInView(bounds, edge)),
+                            event,
+                        );
+                    }
+                }
+
                 match old_status {
                     Status::ScrollTo => {
                         return (Task::none(), None);
@@ -210,7 +453,9 @@ impl State {
                             }
                         }
                     }
-                    _ if old_status.is_end(relative_offset) && remaining => {
+                    _ if old_status.is_end(relative_offset)
+                        && rendered.is_past_end(old_status.anchor()) =>
+                    {
                         match old_status.anchor() {
                             Anchor::Top => {
                                 self.status = Status::Loading(Anchor::Top);
@@ -233,7 +478,7 @@ impl State {
                     Status::Idle(anchor) if !old_status.is_start(relative_offset) => {
                         self.status = Status::Unlocked(anchor);
 
-                        if matches!(anchor, Anchor::Bottom) {
+                        if matches!(anchor, Anchor::Bottom) && !self.is_windowed() {
                             self.limit = Limit::Since(oldest);
                         }
                     }
@@ -243,9 +488,11 @@ impl State {
                 if let Some(new_offset) = self.status.new_offset(old_status, viewport) {
                     return (
                         scrollable::scroll_to(self.scrollable.clone(), new_offset),
-                        None,
+                        event,
                     );
                 }
+
+                return (Task::none(), event);
             }
             Message::UserContext(message) => {
                 return (
@@ -267,11 +514,53 @@ impl State {
                     ))),
                 )
             }
-            Message::Link(message::Link::GoToMessage(server, channel, message)) => {
+            Message::Link(message::Link::GoToMessage(buffer, message)) => {
+                return (Task::none(), Some(Event::GoToMessage(buffer, message)))
+            }
+            Message::RequestMissing(since, until) => {
+                return (Task::none(), Some(Event::RequestMissing(since, until)));
+            }
+            Message::SearchInput(query) => {
+                if let Some(search) = &mut self.search {
+                    search.set_query(query);
+
+                    return (Task::none(), Some(Event::Search(Direction::Older)));
+                }
+            }
+            Message::Search(direction) => {
+                return (Task::none(), Some(Event::Search(direction)));
+            }
+            Message::CloseSearch => {
+                self.search = None;
+            }
+            Message::KeepInView(
+                keyed::Bounds {
+                    scrollable_bounds,
+                    hit_bounds,
+                    ..
+                },
+                edge,
+            ) => {
+                let absolute = hit_bounds.y - scrollable_bounds.content.y;
+
+                // Windows are shown unlocked, with offsets relative to top
+                let y = match edge {
+                    Anchor::Top => absolute,
+                    Anchor::Bottom => {
+                        absolute + hit_bounds.height - scrollable_bounds.viewport.height
+                    }
+                };
+
                 return (
-                    Task::none(),
-                    Some(Event::GoToMessage(server, channel, message)),
-                )
+                    scrollable::scroll_to(
+                        self.scrollable.clone(),
+                        scrollable::AbsoluteOffset {
+                            x: 0.0,
+                            y: y.max(0.0),
+                        },
+                    ),
+                    None,
+                );
             }
             Message::ScrollTo(keyed::Bounds {
                 scrollable_bounds,
@@ -284,23 +573,28 @@ impl State {
                 let absolute = hit_bounds.y - scrollable_bounds.content.y;
                 let relative = (absolute / total_offset).min(1.0);
 
-                self.status = Status::Idle(Anchor::Bottom);
+                // If a prev element exists, put scrollable halfway over prev
+                // element so it's obvious user can scroll up
+                let target = prev_bounds.map_or(absolute, |bounds| {
+                    (bounds.y - scrollable_bounds.content.y) + bounds.height / 2.0
+                });
+
+                // Windows of long buffers don't reach the bottom, so aren't
+                // anchored to it
+                let offset = if self.is_windowed() {
+                    self.status = Status::Unlocked(Anchor::Bottom);
 
-                // Offsets are given relative to top,
-                // and we must scroll to offsets relative to
-                // the bottom
-                let offset = if relative == 1.0 {
-                    0.0
+                    target
                 } else {
-                    // If a prev element exists, put scrollable halfway over prev
-                    // element so it's obvious user can scroll up
-                    if let Some(bounds) = prev_bounds {
-                        let absolute =
-                            (bounds.y - scrollable_bounds.content.y) + bounds.height / 2.0;
+                    self.status = Status::Idle(Anchor::Bottom);
 
-                        total_offset - absolute
+                    // Offsets are given relative to top,
+                    // and we must scroll to offsets relative to
+                    // the bottom
+                    if relative == 1.0 {
+                        0.0
                     } else {
-                        total_offset - absolute
+                        total_offset - target
                     }
                 };
 
@@ -317,6 +611,55 @@ impl State {
         (Task::none(), None)
     }
 
+    fn is_windowed(&self) -> bool {
+        matches!(self.limit, Limit::Ending(..) | Limit::Starting(..))
+    }
+
+    /// Moves the messages shown a step through long buffers once either end
+    /// is scrolled to, rather than showing ever more of them. Returns the
+    /// limit along with the message to keep in view at the edge reached.
+    fn next_window(
+        &self,
+        rendered: &Rendered,
+        status: Status,
+        relative_offset: f32,
+    ) -> Option<(Limit, message::Hash, Anchor)> {
+        if !self.is_windowed() && rendered.count + Limit::DEFAULT_STEP <= Limit::MAX_COUNT {
+            return None;
+        }
+
+        if status.is_top(relative_offset) && rendered.older > 0 {
+            Some((
+                Limit::Ending(rendered.older_end?, Limit::MAX_COUNT),
+                rendered.first?,
+                Anchor::Top,
+            ))
+        } else if status.is_bottom(relative_offset) && rendered.newer > 0 {
+            Some((
+                Limit::Starting(rendered.newer_start?, Limit::MAX_COUNT),
+                rendered.last?,
+                Anchor::Bottom,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Shows the messages from `position` to the bottom, or a window around
+    /// the message at `position` when there are too many of them.
+    fn limit_to(&mut self, position: usize, messages: &[&data::Message]) {
+        // Get all messages from bottom until 1 before position
+        let offset = messages.len() - position + 1;
+
+        self.limit = if offset > Limit::MAX_COUNT {
+            let end = (position + Limit::MAX_COUNT / 2).min(messages.len() - 1);
+
+            Limit::Ending(messages[end].received_at, Limit::MAX_COUNT)
+        } else {
+            Limit::Bottom(offset.max(Limit::DEFAULT_COUNT))
+        };
+    }
+
     pub fn scroll_to_start(&mut self) -> Task<Message> {
         self.status = Status::Idle(Anchor::Top);
         self.limit = Limit::top();
@@ -335,6 +678,63 @@ impl State {
         )
     }
 
+    /// Scrolls by `delta` pixels, towards newer messages when positive.
+    pub fn scroll_by(&mut self, delta: f32) -> Task<Message> {
+        // Offsets are relative to the anchored edge
+        let y = match self.status.anchor() {
+            Anchor::Top => delta,
+            Anchor::Bottom => -delta,
+        };
+
+        scrollable::scroll_by(
+            self.scrollable.clone(),
+            scrollable::AbsoluteOffset { x: 0.0, y },
+        )
+    }
+
+    /// Opens the search bar, or closes it if already open.
+    pub fn toggle_search(&mut self) -> Task<Message> {
+        if self.search.take().is_some() {
+            return Task::none();
+        }
+
+        let search = Search::new();
+        let task = search.focus();
+        self.search = Some(search);
+
+        task
+    }
+
+    /// Scrolls to the next message matching the search, in `direction`.
+    pub fn search(
+        &mut self,
+        direction: Direction,
+        kind: Kind,
+        history: &history::Manager,
+        config: &Config,
+        smart_filter: bool,
+    ) -> Task<Message> {
+        let Some(search) = &mut self.search else {
+            return Task::none();
+        };
+
+        let Some(history::View {
+            old_messages,
+            new_messages,
+            ..
+        }) = history.get_messages(&kind.into(), None, config, smart_filter)
+        else {
+            return Task::none();
+        };
+
+        let Some(message) = search.next(direction, old_messages.into_iter().chain(new_messages))
+        else {
+            return Task::none();
+        };
+
+        self.scroll_to_message(message, kind, history, config, smart_filter)
+    }
+
     pub fn is_scrolled_to_bottom(&self) -> bool {
         matches!(self.status, Status::Idle(Anchor::Bottom))
     }
@@ -345,13 +745,13 @@ impl State {
         kind: Kind,
         history: &history::Manager,
         config: &Config,
+        smart_filter: bool,
     ) -> Task<Message> {
         let Some(history::View {
-            total,
             old_messages,
             new_messages,
             ..
-        }) = history.get_messages(&kind.into(), None, &config.buffer)
+        }) = history.get_messages(&kind.into(), None, config, smart_filter)
         else {
             // We're still loading history, which will trigger
             // scroll_to_backlog after loading. If this is set,
@@ -361,18 +761,16 @@ impl State {
             return Task::none();
         };
 
-        let Some(pos) = old_messages
-            .iter()
-            .chain(&new_messages)
-            .position(|m| m.hash == message)
-        else {
+        let messages = old_messages
+            .into_iter()
+            .chain(new_messages)
+            .collect::<Vec<_>>();
+
+        let Some(pos) = messages.iter().position(|m| m.hash == message) else {
             return Task::none();
         };
 
-        // Get all messages from bottom until 1 before message
-        let offset = total - pos + 1;
-
-        self.limit = Limit::Bottom(offset.max(Limit::DEFAULT_COUNT));
+        self.limit_to(pos, &messages);
         self.status = Status::ScrollTo;
 
         keyed::find_bounds(self.scrollable.clone(), keyed::Key::Message(message))
@@ -384,9 +782,10 @@ impl State {
         kind: Kind,
         history: &history::Manager,
         config: &Config,
+        smart_filter: bool,
     ) -> Task<Message> {
         if let Some(message) = self.pending_scroll_to.take() {
-            return self.scroll_to_message(message, kind, history, config);
+            return self.scroll_to_message(message, kind, history, config, smart_filter);
         }
 
         if history.read_marker(&kind.into()).is_none() {
@@ -394,18 +793,21 @@ impl State {
         }
 
         let Some(history::View {
-            total,
             old_messages,
+            new_messages,
             ..
-        }) = history.get_messages(&kind.into(), None, &config.buffer)
+        }) = history.get_messages(&kind.into(), None, config, smart_filter)
         else {
             return Task::none();
         };
 
-        // Get all messages from bottom until 1 before backlog
-        let offset = total - old_messages.len() + 1;
+        let pos = old_messages.len();
+        let messages = old_messages
+            .into_iter()
+            .chain(new_messages)
+            .collect::<Vec<_>>();
 
-        self.limit = Limit::Bottom(offset.max(Limit::DEFAULT_COUNT));
+        self.limit_to(pos, &messages);
         self.status = Status::ScrollTo;
 
         keyed::find_bounds(self.scrollable.clone(), keyed::Key::Divider).map(Message::ScrollTo)
@@ -0,0 +1,132 @@
+use data::message;
+use iced::widget::{button, center, container, row, text, text_input};
+use iced::{Length, Task};
+
+use super::Message;
+use crate::widget::Element;
+use crate::{icon, theme};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    Older,
+    Newer,
+}
+
+#[derive(Debug, Clone)]
+pub struct Search {
+    input: text_input::Id,
+    query: String,
+    /// Lowercased query, matched against lowercased message text
+    needle: String,
+    current: Option<message::Hash>,
+    /// Position of the current match among all matches
+    position: Option<usize>,
+    matches: Option<usize>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            input: text_input::Id::unique(),
+            query: String::new(),
+            needle: String::new(),
+            current: None,
+            position: None,
+            matches: None,
+        }
+    }
+
+    pub fn focus(&self) -> Task<Message> {
+        text_input::focus(self.input.clone())
+    }
+
+    pub fn set_query(&mut self, query: String) {
+        self.needle = query.to_lowercase();
+        self.query = query;
+        self.current = None;
+        self.position = None;
+        self.matches = None;
+    }
+
+    pub fn is_match(&self, message: &data::Message) -> bool {
+        !self.needle.is_empty() && message.content.text().to_lowercase().contains(&self.needle)
+    }
+
+    pub fn is_current(&self, message: &data::Message) -> bool {
+        self.current == Some(message.hash)
+    }
+
+    /// Moves to the next match in `direction`, starting from the most recent
+    /// one. Wraps around at either end.
+    pub fn next<'a>(
+        &mut self,
+        direction: Direction,
+        messages: impl Iterator<Item = &'a data::Message>,
+    ) -> Option<message::Hash> {
+        let matches = messages
+            .filter(|message| self.is_match(message))
+            .map(|message| message.hash)
+            .collect::<Vec<_>>();
+
+        let current = self
+            .current
+            .and_then(|current| matches.iter().position(|hash| *hash == current));
+
+        let position = match (direction, current) {
+            (_, None) => matches.len().checked_sub(1),
+            (Direction::Older, Some(0)) => Some(matches.len() - 1),
+            (Direction::Older, Some(index)) => Some(index - 1),
+            (Direction::Newer, Some(index)) => Some((index + 1) % matches.len()),
+        };
+
+        self.current = position.map(|index| matches[index]);
+        self.position = position;
+        self.matches = Some(matches.len());
+
+        self.current
+    }
+
+    pub fn view<'a>(&self) -> Element<'a, Message> {
+        let input = text_input("Search...", &self.query)
+            .id(self.input.clone())
+            .on_input(Message::SearchInput)
+            .on_submit(Message::Search(Direction::Older))
+            .padding(4)
+            .style(theme::text_input::primary);
+
+        let status = match (self.position, self.matches) {
+            (Some(position), Some(matches)) => format!("{}/{matches}", position + 1),
+            (None, Some(0)) => "No matches".to_string(),
+            _ => String::new(),
+        };
+
+        let control = |label: &'a str, message: Message| {
+            button(center(text(label)))
+                .padding(0)
+                .width(22)
+                .height(22)
+                .on_press(message)
+                .style(|theme, status| theme::button::secondary(theme, status, false))
+        };
+
+        container(
+            row![
+                input,
+                text(status).style(theme::text::secondary),
+                control("↑", Message::Search(Direction::Older)),
+                control("↓", Message::Search(Direction::Newer)),
+                button(center(icon::cancel()))
+                    .padding(5)
+                    .width(22)
+                    .height(22)
+                    .on_press(Message::CloseSearch)
+                    .style(|theme, status| theme::button::secondary(theme, status, false)),
+            ]
+            .spacing(4)
+            .align_y(iced::Alignment::Center),
+        )
+        .width(Length::Fill)
+        .padding([4, 8])
+        .into()
+    }
+}
@@ -1,4 +1,4 @@
-use data::{buffer, history, message, Config};
+use data::{buffer, command, history, message, Config};
 use iced::widget::{column, container, row, vertical_space};
 use iced::{Length, Task};
 
@@ -16,6 +16,7 @@ pub enum Event {
     UserContext(user_context::Event),
     OpenChannel(String),
     History(Task<history::manager::Message>),
+    Layout(command::Layout, Task<history::manager::Message>),
 }
 
 pub fn view<'a>(
@@ -29,6 +30,7 @@ pub fn view<'a>(
     let status = clients.status(&state.server);
     let buffer = &state.buffer;
     let input = history.input(buffer);
+    let render = config.buffer.formatting.render(buffer);
 
     let messages = container(
         scroll_view::view(
@@ -36,7 +38,8 @@ pub fn view<'a>(
             scroll_view::Kind::Server(&state.server),
             history,
             config,
-            move |message, _, _| {
+            true,
+            move |message, _, _, _| {
                 let timestamp =
                     config
                         .buffer
@@ -52,17 +55,52 @@ pub fn view<'a>(
                             theme,
                             scroll_view::Message::Link,
                             move |theme| theme::selectable_text::server(theme, server.as_ref()),
+                            render,
                             config,
                         );
 
                         Some(container(row![].push_maybe(timestamp).push(message)).into())
                     }
+                    // Direct messages received during a query flood
+                    message::Source::User(user) => {
+                        let nick = selectable_text(
+                            config.buffer.nickname.brackets.format(user.display(false)),
+                        )
+                        .style(|theme| {
+                            theme::selectable_text::nickname(
+                                theme,
+                                user.nick_color(theme.colors(), config.buffer.nickname.color),
+                                false,
+                            )
+                        });
+
+                        let message = message_content(
+                            &message.content,
+                            theme,
+                            scroll_view::Message::Link,
+                            theme::selectable_text::default,
+                            render,
+                            config,
+                        );
+
+                        Some(
+                            container(
+                                row![]
+                                    .push_maybe(timestamp)
+                                    .push(nick)
+                                    .push(selectable_text(" "))
+                                    .push(message),
+                            )
+                            .into(),
+                        )
+                    }
                     message::Source::Internal(message::source::Internal::Status(status)) => {
                         let message = message_content(
                             &message.content,
                             theme,
                             scroll_view::Message::Link,
                             move |theme| theme::selectable_text::status(theme, *status),
+                            render,
                             config,
                         );
 
@@ -84,8 +122,16 @@ pub fn view<'a>(
     let text_input = show_text_input.then(|| {
         column![
             vertical_space().height(4),
-            input_view::view(&state.input_view, input, is_focused, !status.connected())
-                .map(Message::InputView)
+            input_view::view(
+                &state.input_view,
+                input,
+                is_focused,
+                !status.connected(),
+                input_view::PLACEHOLDER,
+                config,
+                theme,
+            )
+            .map(Message::InputView)
         ]
         .width(Length::Fill)
     });
@@ -130,10 +176,31 @@ impl Server {
             Message::ScrollView(message) => {
                 let (command, event) = self.scroll_view.update(message);
 
+                if let Some(scroll_view::Event::Search(direction)) = event {
+                    let search = self.scroll_view.search(
+                        direction,
+                        scroll_view::Kind::Server(&self.server),
+                        history,
+                        config,
+                        true,
+                    );
+
+                    return (
+                        Task::batch(vec![command, search]).map(Message::ScrollView),
+                        None,
+                    );
+                }
+
                 let event = event.and_then(|event| match event {
                     scroll_view::Event::UserContext(event) => Some(Event::UserContext(event)),
                     scroll_view::Event::OpenChannel(channel) => Some(Event::OpenChannel(channel)),
-                    scroll_view::Event::GoToMessage(_, _, _) => None,
+                    scroll_view::Event::GoToMessage(..) | scroll_view::Event::Search(_) => None,
+                    scroll_view::Event::RequestOlder => {
+                        let task = history.load_older(&history::Kind::Server(self.server.clone()));
+
+                        Some(Event::History(Task::batch(task.map(Task::future))))
+                    }
+                    scroll_view::Event::RequestMissing(..) => None,
                 });
 
                 (command.map(Message::ScrollView), event)
@@ -152,6 +219,10 @@ impl Server {
                         ]),
                         Some(Event::History(history_task)),
                     ),
+                    Some(input_view::Event::Layout {
+                        layout,
+                        history_task,
+                    }) => (command, Some(Event::Layout(layout, history_task))),
                     None => (command, None),
                 }
             }
@@ -0,0 +1,146 @@
+use data::{buffer, history, Config};
+use iced::widget::{button, center, column, container, row, scrollable, text, Scrollable};
+use iced::{clipboard, Length, Task};
+
+use crate::widget::{Element, Text};
+use crate::{icon, theme};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Copy(String),
+    Open(String),
+    Filter(Option<buffer::Upstream>),
+}
+
+pub fn view<'a>(
+    state: &'a Urls,
+    history: &'a history::Manager,
+    config: &'a Config,
+) -> Element<'a, Message> {
+    let links = history
+        .links()
+        .filter(|link| {
+            state
+                .filter
+                .as_ref()
+                .is_none_or(|buffer| *buffer == link.buffer)
+        })
+        .collect::<Vec<_>>();
+
+    let filter = state.filter.as_ref().map(|buffer| {
+        container(
+            row![
+                text(format!("URLs from {}", buffer_name(buffer))).style(theme::text::secondary),
+                button(text("Show all"))
+                    .padding([2, 6])
+                    .on_press(Message::Filter(None))
+                    .style(|theme, status| theme::button::secondary(theme, status, false)),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        )
+        .padding([4, 8])
+    });
+
+    if links.is_empty() {
+        return column![]
+            .push_maybe(filter)
+            .push(center(text("No URLs found").style(theme::text::secondary)))
+            .into();
+    }
+
+    let rows = column(links.into_iter().enumerate().map(|(idx, link)| {
+        let url = link.url.to_string();
+
+        let timestamp = config
+            .buffer
+            .format_timestamp(&link.server_time)
+            .map(|timestamp| text(timestamp).style(theme::text::secondary));
+
+        container(
+            row![
+                column![
+                    row![]
+                        .push_maybe(timestamp)
+                        .push(
+                            button(text(buffer_name(&link.buffer)))
+                                .padding(0)
+                                .on_press(Message::Filter(Some(link.buffer.clone())))
+                                .style(theme::button::bare),
+                        )
+                        .push(text(format!(" {}", link.nick)).style(theme::text::secondary)),
+                    text(url.clone()).style(theme::text::primary),
+                ]
+                .width(Length::Fill),
+                row_button(icon::copy(), Message::Copy(url.clone())),
+                row_button(icon::popout(), Message::Open(url)),
+            ]
+            .spacing(4)
+            .align_y(iced::Alignment::Center),
+        )
+        .padding(6)
+        .style(move |theme| theme::container::table(theme, idx))
+        .into()
+    }))
+    .spacing(1)
+    .padding([0, 2]);
+
+    column![]
+        .push_maybe(filter)
+        .push(
+            container(
+                Scrollable::new(rows)
+                    .direction(scrollable::Direction::Vertical(
+                        scrollable::Scrollbar::new().width(1).scroller_width(1),
+                    ))
+                    .style(theme::scrollable::hidden),
+            )
+            .width(Length::Fill)
+            .height(Length::Fill),
+        )
+        .into()
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Urls {
+    /// Only list URLs sent to this buffer
+    filter: Option<buffer::Upstream>,
+}
+
+impl Urls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Copy(url) => return clipboard::write(url),
+            Message::Open(url) => {
+                let _ = open::that_detached(url);
+            }
+            Message::Filter(filter) => {
+                self.filter = filter;
+            }
+        }
+
+        Task::none()
+    }
+}
+
+fn buffer_name(buffer: &buffer::Upstream) -> String {
+    match buffer {
+        buffer::Upstream::Server(server) => server.to_string(),
+        buffer::Upstream::Channel(server, channel) => format!("{channel} ({server})"),
+        buffer::Upstream::Query(server, nick) => format!("{nick} ({server})"),
+    }
+}
+
+fn row_button(icon: Text, message: Message) -> Element<Message> {
+    button(center(icon))
+        .on_press(message)
+        .padding(5)
+        .width(22)
+        .height(22)
+        .style(|theme, status| theme::button::secondary(theme, status, false))
+        .into()
+}
@@ -1,5 +1,6 @@
+use data::buffer::ContextMenuEntry;
 use data::user::Nick;
-use data::{Server, User};
+use data::{ban, Config, Server, User};
 use iced::widget::{button, container, horizontal_rule, row, text, Space};
 use iced::{padding, Length, Padding};
 
@@ -14,13 +15,27 @@ pub enum Entry {
     ToggleAccessLevelVoice,
     SendFile,
     UserInfo,
+    Ban(ban::Mask),
     HorizontalRule,
+    /// Index into the configured context menu entries
+    Custom(usize),
 }
 
 impl Entry {
-    pub fn list(is_channel: bool, our_user: Option<&User>) -> Vec<Self> {
+    pub fn list(is_channel: bool, our_user: Option<&User>, custom: usize) -> Vec<Self> {
+        let mut entries = Self::built_in(is_channel, our_user);
+
+        if custom > 0 {
+            entries.push(Entry::HorizontalRule);
+            entries.extend((0..custom).map(Entry::Custom));
+        }
+
+        entries
+    }
+
+    fn built_in(is_channel: bool, our_user: Option<&User>) -> Vec<Self> {
         if is_channel {
-            if our_user.is_some_and(|u| u.has_access_level(data::user::AccessLevel::Oper)) {
+            if our_user.is_some_and(|u| u.has_access_mode('o')) {
                 vec![
                     Entry::UserInfo,
                     Entry::HorizontalRule,
@@ -29,7 +44,11 @@ impl Entry {
                     Entry::ToggleAccessLevelOp,
                     Entry::ToggleAccessLevelVoice,
                     Entry::SendFile,
+                    Entry::HorizontalRule,
                 ]
+                .into_iter()
+                .chain(ban::Mask::ALL.map(Entry::Ban))
+                .collect()
             } else {
                 vec![
                     Entry::UserInfo,
@@ -50,6 +69,7 @@ impl Entry {
         channel: Option<&str>,
         user: &User,
         current_user: Option<&User>,
+        custom: &'a [ContextMenuEntry],
         length: Length,
     ) -> Element<'a, Message> {
         let nickname = user.nickname().to_owned();
@@ -61,7 +81,7 @@ impl Entry {
             }
             Entry::ToggleAccessLevelOp => {
                 if let Some(channel) = channel {
-                    if user.has_access_level(data::user::AccessLevel::Oper) {
+                    if user.has_access_mode('o') {
                         menu_button(
                             "Take Op (-o)",
                             Message::ToggleAccessLevel(
@@ -90,7 +110,7 @@ impl Entry {
             }
             Entry::ToggleAccessLevelVoice => {
                 if let Some(channel) = channel {
-                    if user.has_access_level(data::user::AccessLevel::Voice) {
+                    if user.has_access_mode('v') {
                         menu_button(
                             "Take Voice (-v)",
                             Message::ToggleAccessLevel(
@@ -123,10 +143,42 @@ impl Entry {
                 length,
             ),
             Entry::UserInfo => user_info(current_user, length),
+            Entry::Ban(mask) => {
+                // Prefer what the channel knows of the user
+                let known = current_user.unwrap_or(user);
+                let label = match mask {
+                    ban::Mask::Account => known
+                        .accountname()
+                        .filter(|account| *account != "*")
+                        .map(|account| format!("Ban account {account}")),
+                    _ => mask.of(known, None).map(|mask| format!("Ban {mask}")),
+                };
+
+                match (label, channel) {
+                    (Some(label), Some(channel)) => menu_button(
+                        label,
+                        Message::Ban(server.clone(), channel.to_string(), nickname, mask),
+                        length,
+                    ),
+                    _ => row![].into(),
+                }
+            }
             Entry::HorizontalRule => match length {
                 Length::Fill => container(horizontal_rule(1)).padding([0, 6]).into(),
                 _ => Space::new(length, 1).into(),
             },
+            Entry::Custom(index) => match custom.get(index) {
+                Some(entry) => menu_button(
+                    &entry.label,
+                    Message::RunCommand(
+                        server.clone(),
+                        channel.map(ToString::to_string),
+                        entry.command(user, channel),
+                    ),
+                    length,
+                ),
+                None => row![].into(),
+            },
         }
     }
 }
@@ -136,8 +188,10 @@ pub enum Message {
     Whois(Server, Nick),
     Query(Server, Nick),
     ToggleAccessLevel(Server, String, Nick, String),
+    Ban(Server, String, Nick, ban::Mask),
     SendFile(Server, Nick),
     SingleClick(Nick),
+    RunCommand(Server, Option<String>, String),
 }
 
 #[derive(Debug, Clone)]
@@ -145,8 +199,10 @@ pub enum Event {
     SendWhois(Server, Nick),
     OpenQuery(Server, Nick),
     ToggleAccessLevel(Server, String, Nick, String),
+    Ban(Server, String, Nick, ban::Mask),
     SendFile(Server, Nick),
     SingleClick(Nick),
+    RunCommand(Server, Option<String>, String),
 }
 
 pub fn update(message: Message) -> Option<Event> {
@@ -156,8 +212,12 @@ pub fn update(message: Message) -> Option<Event> {
         Message::ToggleAccessLevel(server, target, nick, mode) => {
             Some(Event::ToggleAccessLevel(server, target, nick, mode))
         }
+        Message::Ban(server, channel, nick, mask) => Some(Event::Ban(server, channel, nick, mask)),
         Message::SendFile(server, nick) => Some(Event::SendFile(server, nick)),
         Message::SingleClick(nick) => Some(Event::SingleClick(nick)),
+        Message::RunCommand(server, channel, command) => {
+            Some(Event::RunCommand(server, channel, command))
+        }
     }
 }
 
@@ -168,8 +228,10 @@ pub fn view<'a>(
     user: &'a User,
     current_user: Option<&'a User>,
     our_user: Option<&'a User>,
+    config: &'a Config,
 ) -> Element<'a, Message> {
-    let entries = Entry::list(channel.is_some(), our_user);
+    let custom = &config.buffer.nickname.context_menu;
+    let entries = Entry::list(channel.is_some(), our_user, custom.len());
 
     let content = button(content)
         .padding(0)
@@ -180,12 +242,16 @@ pub fn view<'a>(
         Default::default(),
         content,
         entries,
-        move |entry, length| entry.view(server, channel, user, current_user, length),
+        move |entry, length| entry.view(server, channel, user, current_user, custom, length),
     )
     .into()
 }
 
-fn menu_button(content: &str, message: Message, length: Length) -> Element<'_, Message> {
+fn menu_button<'a>(
+    content: impl text::IntoFragment<'a>,
+    message: Message,
+    length: Length,
+) -> Element<'a, Message> {
     button(text(content).style(theme::text::primary))
         .padding(5)
         .width(length)
@@ -0,0 +1,70 @@
+//! Command line arguments, applied over the configuration file.
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: halloy [OPTIONS] [URL]
+
+Arguments:
+  [URL]             irc://, ircs:// or halloy:// url to open
+
+Options:
+      --config <PATH>  Read the configuration from PATH instead of config.toml
+      --connect <URL>  Connect to the server and channels of an irc:// or ircs:// url
+      --nick <NICK>    Nickname to use on every server
+      --check-config   Check the configuration for errors and exit
+  -V, --version        Print version
+  -h, --help           Print help";
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub version: bool,
+    pub help: bool,
+    pub config: Option<PathBuf>,
+    pub connect: Option<String>,
+    pub nick: Option<String>,
+    pub check_config: bool,
+    /// Url to open, asking before connecting to a server
+    pub url: Option<String>,
+}
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+
+        // Executable
+        args.next();
+
+        while let Some(arg) = args.next() {
+            // Both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{flag} requires a value"))
+            };
+
+            match flag {
+                "-V" | "--version" => parsed.version = true,
+                "-h" | "--help" => parsed.help = true,
+                "--config" => parsed.config = Some(PathBuf::from(value()?)),
+                "--connect" => parsed.connect = Some(value()?),
+                "--nick" => parsed.nick = Some(value()?),
+                "--check-config" => parsed.check_config = true,
+                flag if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+                // Anything else the platform passes along is ignored
+                _ => {
+                    if parsed.url.is_none() && arg.contains("://") {
+                        parsed.url = Some(arg);
+                    }
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+}
@@ -1,4 +1,4 @@
-use iced::{event, keyboard, window, Subscription};
+use iced::{event, keyboard, mouse, window, Subscription};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Event {
@@ -6,6 +6,8 @@ pub enum Event {
     Escape,
     Home,
     End,
+    /// Any other key press, click or scroll, telling the user isn't idle
+    Input,
 }
 
 pub fn events() -> Subscription<(window::Id, Event)> {
@@ -37,6 +39,9 @@ fn filtered_events(
             key: keyboard::Key::Named(keyboard::key::Named::End),
             ..
         }) if ignored(status) => Some(Event::End),
+        iced::Event::Keyboard(keyboard::Event::KeyPressed { .. })
+        | iced::Event::Mouse(mouse::Event::ButtonPressed(_))
+        | iced::Event::Mouse(mouse::Event::WheelScrolled { .. }) => Some(Event::Input),
         _ => None,
     };
 
@@ -44,15 +44,37 @@ pub fn setup(is_debug: bool) -> Result<ReceiverStream<Vec<Record>>, Error> {
         .level(log::LevelFilter::Off)
         .level_for("panic", log::LevelFilter::Error)
         .level_for("iced_wgpu", log::LevelFilter::Info)
-        .level_for("data", level_filter)
-        .level_for("halloy", level_filter)
+        // Narrowed down at runtime, see `data::log::set_filter`
+        .level_for("data", log::LevelFilter::Trace)
+        .level_for("halloy", log::LevelFilter::Trace)
+        .filter(data::log::enabled)
         .chain(io_sink)
         .chain(channel_sink)
         .apply()?;
 
+    data::log::configure(data::log::Filter {
+        verbosity: level_filter.into(),
+        ..Default::default()
+    });
+
     Ok(reciever)
 }
 
+/// Logs what the configuration sets, with `RUST_LOG` taking precedence over
+/// its level.
+pub fn configure(logs: &data::config::Logs) {
+    let mut filter = logs.filter();
+
+    if let Some(level) = env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse::<log::LevelFilter>().ok())
+    {
+        filter.verbosity = level.into();
+    }
+
+    data::log::configure(filter);
+}
+
 fn channel_logger() -> (Box<dyn Log>, ReceiverStream<Vec<Record>>) {
     let (log_sender, log_receiver) = mpsc::channel();
     let (async_sender, async_receiver) = tokio_mpsc::channel(1);
@@ -4,6 +4,7 @@
 mod appearance;
 mod audio;
 mod buffer;
+mod cli;
 mod event;
 mod font;
 mod icon;
@@ -17,7 +18,7 @@ mod widget;
 mod window;
 
 use std::collections::HashSet;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use std::{env, mem};
 
 use appearance::{theme, Theme};
@@ -25,7 +26,7 @@ use chrono::Utc;
 use data::config::{self, Config};
 use data::history::manager::Broadcast;
 use data::version::Version;
-use data::{environment, server, version, Url, User};
+use data::{dcc, environment, server, version, Url, User};
 use data::{history, Server};
 use iced::widget::{column, container};
 use iced::{padding, Length, Subscription, Task};
@@ -38,21 +39,81 @@ use self::modal::Modal;
 use self::widget::Element;
 use self::window::Window;
 
-pub fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut args = env::args();
-    args.next();
+/// Loads the configuration without starting Halloy, printing any problems
+/// found. Returns the exit code.
+fn check_config() -> Result<i32, Box<dyn std::error::Error>> {
+    let path = Config::path();
+
+    let rt = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+
+    match rt.block_on(Config::check()) {
+        Ok((_, unknown)) => {
+            for problem in &unknown {
+                println!("warning: {problem}");
+            }
+
+            println!("{} is valid", path.display());
+
+            Ok(0)
+        }
+        Err(error) => {
+            eprintln!("error: {error}");
+
+            Ok(1)
+        }
+    }
+}
 
-    let version = args
-        .next()
-        .map(|s| s == "--version" || s == "-V")
-        .unwrap_or_default();
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = match cli::Args::parse(env::args()) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    if version {
+    if args.version {
         println!("halloy {}", environment::formatted_version());
 
         return Ok(());
     }
 
+    if args.help {
+        println!("{}", cli::USAGE);
+
+        return Ok(());
+    }
+
+    // Connects right away, unlike urls opened with halloy
+    let connect = match args.connect.as_deref().map(str::parse) {
+        None => None,
+        Some(Ok(Url::ServerConnect {
+            server, mut config, ..
+        })) => {
+            if let Some(nick) = &args.nick {
+                config.nickname.clone_from(nick);
+            }
+
+            Some((server, config))
+        }
+        Some(_) => {
+            eprintln!("error: --connect expects an irc:// or ircs:// url");
+            std::process::exit(2);
+        }
+    };
+
+    config::set_overrides(config::Overrides {
+        path: args.config,
+        nickname: args.nick,
+    });
+
+    if args.check_config {
+        std::process::exit(check_config()?);
+    }
+
     let is_debug = cfg!(debug_assertions);
 
     // Prepare notifications.
@@ -70,32 +131,54 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
             .enable_all()
             .build()?;
 
-        rt.block_on(Config::load())
+        rt.block_on(async {
+            let config = Config::load().await;
+
+            // History can only be read once its key is derived, and mustn't be
+            // written unencrypted when it can't be
+            if let Some(passphrase) = config
+                .as_ref()
+                .ok()
+                .and_then(|config| config.history.passphrase.as_deref())
+            {
+                history::encryption::init(passphrase).await?;
+            }
+
+            Ok::<_, history::Error>(config)
+        })
+        .inspect_err(|error| log::error!("failed to set up history encryption: {error}"))?
     };
 
     // DANGER ZONE - font must be set using config
     // before we do any iced related stuff w/ it
     font::set(config_load.as_ref().ok());
 
-    let destination = data::Url::find_in(std::env::args());
+    let destination = args.url.and_then(|url| url.parse::<Url>().ok());
     if let Some(loc) = &destination {
         if ipc::connect_and_send(loc.to_string()) {
             return Ok(());
         }
     }
 
+    // A running instance asks before connecting instead
+    if let Some(url) = &args.connect {
+        if ipc::connect_and_send(url.clone()) {
+            return Ok(());
+        }
+    }
+
     // TODO: Renable persistant window position and size:
     // Winit currently has a bug with resize and move events.
     // Until it have been fixed, the persistant position and size has been disabled.
     //
     // let window_load = Window::load().unwrap_or_default();
 
-    iced::daemon("Halloy", Halloy::update, Halloy::view)
+    iced::daemon(Halloy::title, Halloy::update, Halloy::view)
         .theme(Halloy::theme)
         .scale_factor(Halloy::scale_factor)
         .subscription(Halloy::subscription)
         .settings(settings(&config_load))
-        .run_with(move || Halloy::new(config_load, destination, log_stream))
+        .run_with(move || Halloy::new(config_load, destination, connect, log_stream))
         .inspect_err(|err| log::error!("{}", err))?;
 
     Ok(())
@@ -132,6 +215,10 @@ struct Halloy {
     modal: Option<Modal>,
     main_window: Window,
     pending_logs: Vec<data::log::Record>,
+    /// Selected theme and when its file was last modified
+    theme_modified: Option<(String, SystemTime)>,
+    /// When the configuration file was last modified
+    config_modified: Option<SystemTime>,
 }
 
 impl Halloy {
@@ -152,6 +239,7 @@ impl Halloy {
 
         let (screen, config, command) = match config_load {
             Ok(config) => {
+                logger::configure(&config.logs);
                 let (screen, command) = load_dashboard(&config);
 
                 (
@@ -184,20 +272,28 @@ impl Halloy {
             ),
         };
 
-        (
-            Halloy {
-                version: Version::new(),
-                screen,
-                theme: appearance::theme(&config.appearance.selected).into(),
-                clients: Default::default(),
-                servers: config.servers.clone(),
-                config,
-                modal: None,
-                main_window,
-                pending_logs: vec![],
-            },
-            command,
-        )
+        let mut halloy = Halloy {
+            version: Version::new(),
+            screen,
+            theme: appearance::theme(&config.appearance.selected).into(),
+            clients: data::client::Map::new(
+                config.highlights.clone(),
+                config.buffer.repeats,
+                config.notifications.do_not_disturb.clone(),
+                config.away.clone(),
+            ),
+            servers: config.servers.clone(),
+            config,
+            modal: None,
+            main_window,
+            pending_logs: vec![],
+            theme_modified: None,
+            config_modified: Config::modified(),
+        };
+
+        let prompt = halloy.prompt_password(None);
+
+        (halloy, Task::batch(vec![command, prompt]))
     }
 }
 
@@ -212,9 +308,14 @@ pub enum Screen {
 #[derive(Debug)]
 pub enum Message {
     AppearanceReloaded(data::appearance::Appearance),
+    ThemeReloaded(
+        String,
+        Result<data::appearance::theme::Colors, data::appearance::theme::Error>,
+    ),
     ScreenConfigReloaded(Result<Config, config::Error>),
     Dashboard(dashboard::Message),
     Stream(stream::Update),
+    DccChat(dcc::chat::Update),
     Help(help::Message),
     Welcome(welcome::Message),
     Migration(migration::Message),
@@ -227,12 +328,19 @@ pub enum Message {
     Window(window::Id, window::Event),
     WindowSettingsSaved(Result<(), window::Error>),
     Logging(Vec<logger::Record>),
+    HistoryCompacted(Result<history::compaction::Compacted, history::Error>),
+    HistoryRotated(Result<Vec<history::Kind>, history::Error>),
+    ChannelKeysSaved(Result<(), data::channel::keys::Error>),
+    NotifyListsSaved(Result<(), data::notify::Error>),
+    WebhookSent(Result<(), data::webhook::Error>),
+    NotificationClicked(data::buffer::Upstream),
 }
 
 impl Halloy {
     fn new(
         config_load: Result<Config, config::Error>,
         url_received: Option<data::Url>,
+        connect: Option<(Server, config::Server)>,
         log_stream: ReceiverStream<Vec<logger::Record>>,
     ) -> (Halloy, Task<Message>) {
         let (main_window, open_main_window) = window::open(window::Settings {
@@ -246,11 +354,16 @@ impl Halloy {
         let (mut halloy, command) = Halloy::load_from_state(main_window, config_load);
         let latest_remote_version =
             Task::perform(version::latest_remote_version(), Message::Version);
+        let compact_history = Task::perform(
+            history::compaction::compact(halloy.config.history.max_size),
+            Message::HistoryCompacted,
+        );
 
         let mut commands = vec![
             open_main_window.then(|_| Task::none()),
             command,
             latest_remote_version,
+            compact_history,
             Task::stream(log_stream).map(Message::Logging),
         ];
 
@@ -258,9 +371,23 @@ impl Halloy {
             commands.push(halloy.handle_url(url));
         }
 
+        if let Some((server, config)) = connect {
+            halloy.add_server(server, config);
+        }
+
         (halloy, Task::batch(commands))
     }
 
+    /// Connects to `server`, only joining the channels of `config` if
+    /// already connected to it.
+    fn add_server(&mut self, server: Server, config: config::Server) {
+        if let Some(entry) = self.servers.find(&server, &config) {
+            self.clients.join(&entry.server, &config.channels);
+        } else {
+            self.servers.insert(server, config);
+        }
+    }
+
     fn handle_url(&mut self, url: Url) -> Task<Message> {
         match url {
             data::Url::ServerConnect {
@@ -268,6 +395,19 @@ impl Halloy {
                 server,
                 config,
             } => {
+                // Channels on a server already connected to are joined right away
+                if let Some(entry) = self.servers.find(&server, &config) {
+                    if self
+                        .clients
+                        .connected_servers()
+                        .any(|connected| *connected == entry.server)
+                    {
+                        self.clients.join(&entry.server, &config.channels);
+
+                        return Task::none();
+                    }
+                }
+
                 self.modal = Some(Modal::ServerConnect {
                     url,
                     server,
@@ -295,6 +435,23 @@ impl Halloy {
                 self.config.appearance = appearance;
                 Task::none()
             }
+            Message::ThemeReloaded(name, Ok(colors)) => {
+                let reloaded = data::Theme::new(name, colors);
+
+                self.config.appearance.replace(&reloaded);
+
+                if let Theme::Selected(selected) = &self.theme {
+                    if selected.name == reloaded.name {
+                        self.theme = reloaded.into();
+                    }
+                }
+
+                Task::none()
+            }
+            Message::ThemeReloaded(name, Err(error)) => {
+                log::warn!("failed to reload theme {name}: {error}");
+                Task::none()
+            }
             Message::ScreenConfigReloaded(updated) => {
                 let (halloy, command) = Halloy::load_from_state(self.main_window.id, updated);
                 *self = halloy;
@@ -318,30 +475,73 @@ impl Halloy {
                 let track = dashboard.track();
 
                 let event_task = match event {
-                    Some(dashboard::Event::ConfigReloaded(config)) => {
-                        match config {
-                            Ok(updated) => {
-                                let removed_servers = self
-                                    .servers
-                                    .keys()
-                                    .filter(|server| !updated.servers.contains(server))
+                    Some(dashboard::Event::ConfigReloaded(config)) => match config {
+                        Ok(updated) => {
+                            let removed_servers = self
+                                .servers
+                                .keys()
+                                .filter(|server| !updated.servers.contains(server))
+                                .cloned()
+                                .collect::<Vec<_>>();
+
+                            for entry in updated.servers.entries() {
+                                let Some(previous) = self.servers.get(&entry.server) else {
+                                    continue;
+                                };
+
+                                let joined = entry
+                                    .config
+                                    .channels
+                                    .iter()
+                                    .filter(|channel| !previous.channels.contains(channel))
+                                    .cloned()
+                                    .collect::<Vec<_>>();
+                                let parted = previous
+                                    .channels
+                                    .iter()
+                                    .filter(|channel| !entry.config.channels.contains(channel))
                                     .cloned()
                                     .collect::<Vec<_>>();
 
-                                self.servers = updated.servers.clone();
-                                self.theme = appearance::theme(&updated.appearance.selected).into();
-                                self.config = updated;
+                                self.clients.join(&entry.server, &joined);
+                                self.clients.part(&entry.server, &parted);
+                            }
 
-                                for server in removed_servers {
-                                    self.clients.quit(&server, None);
-                                }
+                            let restart_required = self.config.restart_required(&updated);
+
+                            if !restart_required.is_empty() {
+                                log::warn!(
+                                    "configuration reloaded, restart to apply changes to: {}",
+                                    restart_required.join(", ")
+                                );
                             }
-                            Err(error) => {
-                                self.modal = Some(Modal::ReloadConfigurationError(error));
+
+                            let mut servers = updated.servers.clone();
+                            servers.keep_prompted(&self.servers);
+                            self.servers = servers;
+                            self.theme = appearance::theme(&updated.appearance.selected).into();
+                            dashboard
+                                .set_file_transfer_rate_limit(updated.file_transfer.rate_limit);
+                            dashboard.update_channel_settings(&updated.servers);
+                            self.clients.set_highlights(updated.highlights.clone());
+                            self.clients.set_repeats(updated.buffer.repeats);
+                            self.clients
+                                .set_quiet_hours(updated.notifications.do_not_disturb.clone());
+                            self.clients.set_away(updated.away.clone());
+                            logger::configure(&updated.logs);
+                            self.config = updated;
+
+                            for server in removed_servers {
+                                self.clients.quit(&server, None);
                             }
-                        };
-                        Task::none()
-                    }
+
+                            self.prompt_password(None)
+                        }
+                        Err(error) => {
+                            self.modal = Some(Modal::ReloadConfigurationError(error));
+                            Task::none()
+                        }
+                    },
                     Some(dashboard::Event::ReloadThemes) => Task::future(Config::load())
                         .and_then(|config| Task::done(config.appearance))
                         .map(Message::AppearanceReloaded),
@@ -372,6 +572,72 @@ impl Halloy {
                     track.map(Message::Dashboard),
                 ])
             }
+            Message::HistoryCompacted(Ok(compacted)) => {
+                log::info!(
+                    "history uses {} ({} compressed)",
+                    compacted.usage,
+                    compacted.compressed
+                );
+
+                // History in use is kept, so without the dashboard nothing is removed
+                let Screen::Dashboard(dashboard) = &self.screen else {
+                    return Task::none();
+                };
+
+                if compacted.rotation.is_empty() {
+                    return Task::none();
+                }
+
+                let kinds = compacted
+                    .rotation
+                    .select(|kind| dashboard.history().is_in_use(kind));
+
+                Task::perform(history::compaction::remove(kinds), Message::HistoryRotated)
+            }
+            Message::HistoryRotated(Ok(kinds)) => {
+                let Screen::Dashboard(dashboard) = &mut self.screen else {
+                    return Task::none();
+                };
+
+                dashboard.history_rotated(kinds).map(Message::Dashboard)
+            }
+            Message::HistoryRotated(Err(error)) => {
+                log::warn!("failed to remove history over max_size: {error}");
+
+                Task::none()
+            }
+            Message::HistoryCompacted(Err(error)) => {
+                log::warn!("failed to compact history: {error}");
+
+                Task::none()
+            }
+            Message::ChannelKeysSaved(Ok(())) => Task::none(),
+            Message::ChannelKeysSaved(Err(error)) => {
+                log::warn!("failed to save channel keys: {error}");
+
+                Task::none()
+            }
+            Message::NotifyListsSaved(Ok(())) => Task::none(),
+            Message::NotifyListsSaved(Err(error)) => {
+                log::warn!("failed to save notify lists: {error}");
+
+                Task::none()
+            }
+            Message::NotificationClicked(buffer) => {
+                let Screen::Dashboard(dashboard) = &mut self.screen else {
+                    return Task::none();
+                };
+
+                dashboard
+                    .focus_buffer(&self.main_window, buffer, &self.config)
+                    .map(Message::Dashboard)
+            }
+            Message::WebhookSent(Ok(())) => Task::none(),
+            Message::WebhookSent(Err(error)) => {
+                log::warn!("failed to post notification to webhook: {error}");
+
+                Task::none()
+            }
             Message::Version(remote) => {
                 // Set latest known remote version
                 self.version.remote = remote;
@@ -433,7 +699,9 @@ impl Halloy {
                             .broadcast(&server, &self.config, sent_time, Broadcast::Connecting)
                             .map(Message::Dashboard)
                     } else {
-                        notification::disconnected(&self.config.notifications, &server);
+                        if !self.clients.is_do_not_disturb() {
+                            notification::disconnected(&self.config.notifications, &server);
+                        }
 
                         dashboard
                             .broadcast(
@@ -458,13 +726,17 @@ impl Halloy {
                     };
 
                     if is_initial {
-                        notification::connected(&self.config.notifications, &server);
+                        if !self.clients.is_do_not_disturb() {
+                            notification::connected(&self.config.notifications, &server);
+                        }
 
                         dashboard
                             .broadcast(&server, &self.config, sent_time, Broadcast::Connected)
                             .map(Message::Dashboard)
                     } else {
-                        notification::reconnected(&self.config.notifications, &server);
+                        if !self.clients.is_do_not_disturb() {
+                            notification::reconnected(&self.config.notifications, &server);
+                        }
 
                         dashboard
                             .broadcast(&server, &self.config, sent_time, Broadcast::Reconnected)
@@ -489,6 +761,11 @@ impl Halloy {
                         )
                         .map(Message::Dashboard)
                 }
+                stream::Update::Console(server, lines) => {
+                    self.clients.record_console(&server, lines);
+
+                    Task::none()
+                }
                 stream::Update::MessagesReceived(server, messages) => {
                     let Screen::Dashboard(dashboard) = &mut self.screen else {
                         return Task::none();
@@ -533,6 +810,11 @@ impl Halloy {
                                             chantypes,
                                             statusmsg,
                                         ) {
+                                            commands.push(
+                                                dashboard
+                                                    .request_previews(&message, &self.config)
+                                                    .map(Message::Dashboard),
+                                            );
                                             commands.push(
                                                 dashboard
                                                     .record_message(&server, message)
@@ -609,6 +891,7 @@ impl Halloy {
                                             inviter,
                                             channel,
                                             user_channels,
+                                            joining,
                                             sent_time,
                                         } => {
                                             let inviter = inviter.nickname();
@@ -623,6 +906,7 @@ impl Halloy {
                                                             inviter: inviter.to_owned(),
                                                             channel,
                                                             user_channels,
+                                                            joining,
                                                         },
                                                     )
                                                     .map(Message::Dashboard),
@@ -653,13 +937,45 @@ impl Halloy {
                                                     .map(Message::Dashboard),
                                             );
                                         }
+                                        data::client::Broadcast::Rejoin {
+                                            channel,
+                                            status,
+                                            sent_time,
+                                        } => {
+                                            commands.push(
+                                                dashboard
+                                                    .broadcast(
+                                                        &server,
+                                                        &self.config,
+                                                        sent_time,
+                                                        Broadcast::Rejoin { channel, status },
+                                                    )
+                                                    .map(Message::Dashboard),
+                                            );
+                                        }
+                                        data::client::Broadcast::CtcpSuppressed {
+                                            nick,
+                                            command,
+                                            sent_time,
+                                        } => {
+                                            commands.push(
+                                                dashboard
+                                                    .broadcast(
+                                                        &server,
+                                                        &self.config,
+                                                        sent_time,
+                                                        Broadcast::CtcpSuppressed { nick, command },
+                                                    )
+                                                    .map(Message::Dashboard),
+                                            );
+                                        }
                                     },
                                     data::client::Event::Notification(
                                         encoded,
                                         our_nick,
                                         notification,
                                     ) => {
-                                        if let Some(message) = data::Message::received(
+                                        let message = data::Message::received(
                                             encoded,
                                             our_nick,
                                             &self.config,
@@ -667,16 +983,29 @@ impl Halloy {
                                             channel_users,
                                             chantypes,
                                             statusmsg,
-                                        ) {
+                                        );
+                                        let text = message
+                                            .as_ref()
+                                            .map(|message| message.content.text().into_owned())
+                                            .unwrap_or_default();
+
+                                        if let Some(message) = message {
+                                            commands.push(
+                                                dashboard
+                                                    .request_previews(&message, &self.config)
+                                                    .map(Message::Dashboard),
+                                            );
                                             commands.push(
                                                 dashboard
                                                     .record_message(&server, message.clone())
                                                     .map(Message::Dashboard),
                                             );
 
+                                            // Direct messages are collected with highlights
                                             if matches!(
                                                 notification,
                                                 data::client::Notification::Highlight { .. }
+                                                    | data::client::Notification::DirectMessage(_)
                                             ) {
                                                 commands.extend(
                                                     message.into_highlight(server.clone()).map(
@@ -690,20 +1019,60 @@ impl Halloy {
                                             }
                                         }
 
+                                        // Highlights are still recorded above
+                                        if self.clients.is_do_not_disturb() {
+                                            continue;
+                                        }
+
                                         match notification {
                                             data::client::Notification::DirectMessage(user) => {
+                                                commands.push(
+                                                    notification::webhooks(
+                                                        &self.config.notifications,
+                                                        notification::WebhookEvent::DirectMessage,
+                                                        self.clients.is_away(&server),
+                                                        self.main_window.unfocused_for(),
+                                                        notification::Context {
+                                                            server: &server.to_string(),
+                                                            nick: user.nickname().as_ref(),
+                                                            message: &text,
+                                                            ..Default::default()
+                                                        },
+                                                    )
+                                                    .map(Message::WebhookSent),
+                                                );
+
+                                                let query = data::buffer::Upstream::Query(
+                                                    server.clone(),
+                                                    user.nickname().to_owned(),
+                                                );
+
                                                 // only send notification if query has unread
-                                                // or if window is not focused
-                                                if dashboard.history().has_unread(
-                                                    &history::Kind::Query(
-                                                        server.clone(),
-                                                        user.nickname().to_owned(),
-                                                    ),
-                                                ) || !self.main_window.focused
+                                                // or if window is not focused, and it's not
+                                                // the buffer being read
+                                                if !dashboard.is_window_focused(&self.main_window)
+                                                    || (dashboard.history().has_unread(
+                                                        &history::Kind::Query(
+                                                            server.clone(),
+                                                            user.nickname().to_owned(),
+                                                        ),
+                                                    ) && !dashboard.is_focused_buffer(
+                                                        &self.main_window,
+                                                        &query,
+                                                    ))
                                                 {
-                                                    notification::direct_message(
-                                                        &self.config.notifications,
-                                                        user.nickname(),
+                                                    commands.push(
+                                                        notification::direct_message(
+                                                            &self.config.notifications,
+                                                            user.nickname(),
+                                                            &server,
+                                                            &text,
+                                                        )
+                                                        .map(move |()| {
+                                                            Message::NotificationClicked(
+                                                                query.clone(),
+                                                            )
+                                                        }),
                                                     );
                                                 }
                                             }
@@ -712,12 +1081,54 @@ impl Halloy {
                                                 user,
                                                 channel,
                                             } => {
-                                                if enabled {
-                                                    notification::highlight(
-                                                        &self.config.notifications,
-                                                        user.nickname(),
-                                                        channel,
+                                                if enabled
+                                                    && self
+                                                        .config
+                                                        .servers
+                                                        .channel_settings(&server, &channel)
+                                                        .notifications
+                                                {
+                                                    commands.push(
+                                                        notification::webhooks(
+                                                            &self.config.notifications,
+                                                            notification::WebhookEvent::Highlight,
+                                                            self.clients.is_away(&server),
+                                                            self.main_window.unfocused_for(),
+                                                            notification::Context {
+                                                                server: &server.to_string(),
+                                                                channel: &channel,
+                                                                nick: user.nickname().as_ref(),
+                                                                message: &text,
+                                                            },
+                                                        )
+                                                        .map(Message::WebhookSent),
                                                     );
+
+                                                    let buffer = data::buffer::Upstream::Channel(
+                                                        server.clone(),
+                                                        channel.clone(),
+                                                    );
+
+                                                    // Skip the channel being read
+                                                    if !dashboard.is_focused_buffer(
+                                                        &self.main_window,
+                                                        &buffer,
+                                                    ) {
+                                                        commands.push(
+                                                            notification::highlight(
+                                                                &self.config.notifications,
+                                                                user.nickname(),
+                                                                channel,
+                                                                &server,
+                                                                &text,
+                                                            )
+                                                            .map(move |()| {
+                                                                Message::NotificationClicked(
+                                                                    buffer.clone(),
+                                                                )
+                                                            }),
+                                                        );
+                                                    }
                                                 }
                                             }
                                             data::client::Notification::MonitoredOnline(
@@ -749,10 +1160,42 @@ impl Halloy {
                                             &server,
                                             request,
                                             &self.config,
+                                            self.clients.is_do_not_disturb(),
                                         ) {
                                             commands.push(command.map(Message::Dashboard));
                                         }
                                     }
+                                    data::client::Event::DccChat(from, offer) => {
+                                        if self.clients.dcc_chats_mut().received(
+                                            &server,
+                                            from.clone(),
+                                            offer,
+                                        ) {
+                                            commands.push(
+                                                dashboard
+                                                    .record_message(
+                                                        &server,
+                                                        data::Message::dcc_chat_offer_received(
+                                                            &from,
+                                                        ),
+                                                    )
+                                                    .map(Message::Dashboard),
+                                            );
+                                        }
+                                    }
+                                    data::client::Event::FileTransferResume(from, resume) => {
+                                        dashboard.resume_file_transfer(&from, resume);
+                                    }
+                                    data::client::Event::FileTransferAccept(from, accept) => {
+                                        dashboard.accept_file_transfer(&from, accept);
+                                    }
+                                    data::client::Event::FileTransferChecksum(
+                                        from,
+                                        filename,
+                                        sha256,
+                                    ) => {
+                                        dashboard.verify_file_transfer(&from, &filename, sha256);
+                                    }
                                     data::client::Event::UpdateReadMarker(target, read_marker) => {
                                         commands.push(
                                             dashboard
@@ -774,6 +1217,35 @@ impl Halloy {
                                                 .map(Message::Dashboard),
                                         );
                                     }
+                                    data::client::Event::Backfill(target, encoded, our_nick) => {
+                                        let messages = encoded
+                                            .into_iter()
+                                            .filter_map(|encoded| {
+                                                data::Message::received(
+                                                    encoded,
+                                                    our_nick.clone(),
+                                                    &self.config,
+                                                    resolve_user_attributes,
+                                                    channel_users,
+                                                    chantypes,
+                                                    statusmsg,
+                                                )
+                                            })
+                                            .collect();
+
+                                        commands.push(
+                                            dashboard
+                                                .backfill(
+                                                    &history::Kind::from_target(
+                                                        server.clone(),
+                                                        target,
+                                                        chantypes,
+                                                    ),
+                                                    messages,
+                                                )
+                                                .map(Message::Dashboard),
+                                        );
+                                    }
                                 }
                             }
 
@@ -781,13 +1253,15 @@ impl Halloy {
                         })
                         .collect::<Vec<_>>();
 
-                    // Must be called after receiving message batches to ensure
-                    // user & channel lists are in sync
-                    self.clients.sync(&server);
-
                     Task::batch(commands)
                 }
                 stream::Update::Quit(server, reason) => match &mut self.screen {
+                    // Quit with `/reconnect` or `/disconnect`, keep the server around
+                    Screen::Dashboard(_) if self.clients.is_quitting(&server) => {
+                        self.clients.quit_sent(&server);
+
+                        Task::none()
+                    }
                     Screen::Dashboard(dashboard) => {
                         self.servers.remove(&server);
 
@@ -825,6 +1299,10 @@ impl Halloy {
                 },
             },
             Message::Event(window, event) => {
+                if let Err(e) = self.clients.input(Instant::now()) {
+                    handle_irc_error(e);
+                }
+
                 if let Screen::Dashboard(dashboard) = &mut self.screen {
                     return dashboard
                         .handle_event(
@@ -841,15 +1319,109 @@ impl Halloy {
 
                 Task::none()
             }
+            Message::DccChat(update) => {
+                let Screen::Dashboard(dashboard) = &mut self.screen else {
+                    return Task::none();
+                };
+
+                let chats = self.clients.dcc_chats_mut();
+
+                let (server, message) = match update {
+                    dcc::chat::Update::Ready(id, sender) => {
+                        chats.ready(id, sender);
+                        return Task::none();
+                    }
+                    dcc::chat::Update::Connected(id) => {
+                        let Some(chat) = chats.connected(id) else {
+                            return Task::none();
+                        };
+
+                        (
+                            chat.server.clone(),
+                            data::Message::dcc_chat_status(
+                                &chat.remote,
+                                format!("DCC chat with {} connected", chat.remote),
+                            ),
+                        )
+                    }
+                    dcc::chat::Update::Received(id, text) => {
+                        let Some(chat) = chats.get(id) else {
+                            return Task::none();
+                        };
+
+                        (
+                            chat.server.clone(),
+                            data::Message::dcc_chat_received(&chat.remote, &text),
+                        )
+                    }
+                    dcc::chat::Update::Closed(id, error) => {
+                        // Already gone if closed with `/dcc close`
+                        let Some(chat) = chats.remove(id) else {
+                            return Task::none();
+                        };
+
+                        let text = match error {
+                            Some(error) => format!("DCC chat with {} closed: {error}", chat.remote),
+                            None => format!("DCC chat with {} closed", chat.remote),
+                        };
+
+                        (
+                            chat.server.clone(),
+                            data::Message::dcc_chat_status(&chat.remote, text),
+                        )
+                    }
+                };
+
+                dashboard
+                    .record_message(&server, message)
+                    .map(Message::Dashboard)
+            }
             Message::Tick(now) => {
-                if let Err(e) = self.clients.tick(now) {
+                let reload_theme = self.reload_theme();
+                let reload_config = self.reload_config();
+
+                let task = if let Err(e) = self.clients.tick(now) {
                     handle_irc_error(e);
                     Task::none()
                 } else if let Screen::Dashboard(dashboard) = &mut self.screen {
-                    dashboard.tick(now).map(Message::Dashboard)
+                    let timers = self.clients.expired_timers(now);
+                    let failed_joins = self.clients.failed_joins(now);
+
+                    let mut tasks = failed_joins
+                        .into_iter()
+                        .map(|(server, channel, reason)| {
+                            dashboard
+                                .broadcast(
+                                    &server,
+                                    &self.config,
+                                    Utc::now(),
+                                    Broadcast::JoinFailed { channel, reason },
+                                )
+                                .map(Message::Dashboard)
+                        })
+                        .collect::<Vec<_>>();
+
+                    tasks.push(
+                        dashboard
+                            .run_timers(timers, &mut self.clients, &self.config)
+                            .map(Message::Dashboard),
+                    );
+                    tasks.push(dashboard.tick(now).map(Message::Dashboard));
+
+                    if let Some(save) = self.clients.save_channel_keys() {
+                        tasks.push(Task::perform(save, Message::ChannelKeysSaved));
+                    }
+
+                    if let Some(save) = self.clients.save_notify_lists() {
+                        tasks.push(Task::perform(save, Message::NotifyListsSaved));
+                    }
+
+                    Task::batch(tasks)
                 } else {
                     Task::none()
-                }
+                };
+
+                Task::batch(vec![reload_theme, reload_config, task])
             }
             Message::Modal(message) => {
                 let Some(modal) = &mut self.modal else {
@@ -859,22 +1431,31 @@ impl Halloy {
                 if let Some(event) = modal.update(message) {
                     match event {
                         modal::Event::CloseModal => {
-                            self.modal = None;
+                            // Skipped servers aren't connected to until reloading
+                            if let Some(Modal::PasswordPrompt { server, .. }) = self.modal.take() {
+                                return self.prompt_password(Some(&server));
+                            }
+                        }
+                        modal::Event::SubmitPassword => {
+                            if let Some(Modal::PasswordPrompt {
+                                server,
+                                prompt,
+                                input,
+                            }) = self.modal.take()
+                            {
+                                if let Some(mut config) = self.servers.get(&server).cloned() {
+                                    config.set_prompted(prompt, input);
+                                    self.servers.insert(server, config);
+                                }
+
+                                return self.prompt_password(None);
+                            }
                         }
                         modal::Event::AcceptNewServer => {
                             if let Some(Modal::ServerConnect { server, config, .. }) =
                                 self.modal.take()
                             {
-                                let existing_entry = self.servers.entries().find(|entry| {
-                                    entry.server == server || entry.config.server == config.server
-                                });
-
-                                // If server already exists, we only want to join the new channels
-                                if let Some(entry) = existing_entry {
-                                    self.clients.join(&entry.server, &config.channels);
-                                } else {
-                                    self.servers.insert(server, config);
-                                }
+                                self.add_server(server, config);
                             }
                         }
                     }
@@ -893,13 +1474,23 @@ impl Halloy {
             }
             Message::Window(id, event) => {
                 if id == self.main_window.id {
+                    let mut task = Task::none();
+
                     match event {
                         window::Event::Moved(position) => {
                             self.main_window.position = Some(position)
                         }
                         window::Event::Resized(size) => self.main_window.size = size,
-                        window::Event::Focused => self.main_window.focused = true,
-                        window::Event::Unfocused => self.main_window.focused = false,
+                        window::Event::Focused => {
+                            self.main_window.set_focused(true);
+
+                            if let Screen::Dashboard(dashboard) = &mut self.screen {
+                                task = dashboard
+                                    .main_window_focused(&self.main_window)
+                                    .map(Message::Dashboard);
+                            }
+                        }
+                        window::Event::Unfocused => self.main_window.set_focused(false),
                         window::Event::Opened { position, size } => {
                             self.main_window.opened(position, size)
                         }
@@ -912,13 +1503,16 @@ impl Halloy {
                         }
                     }
 
-                    Task::perform(
-                        data::Window::from(self.main_window).save(),
-                        Message::WindowSettingsSaved,
-                    )
+                    Task::batch(vec![
+                        task,
+                        Task::perform(
+                            data::Window::from(self.main_window).save(),
+                            Message::WindowSettingsSaved,
+                        ),
+                    ])
                 } else if let Screen::Dashboard(dashboard) = &mut self.screen {
                     dashboard
-                        .handle_window_event(id, event, &mut self.theme)
+                        .handle_window_event(id, event, &mut self.theme, &self.main_window)
                         .map(Message::Dashboard)
                 } else {
                     Task::none()
@@ -1025,6 +1619,19 @@ impl Halloy {
             .into()
     }
 
+    /// Shows unread messages and highlights, for the taskbar or dock to pick up.
+    fn title(&self, _window: window::Id) -> String {
+        let Screen::Dashboard(dashboard) = &self.screen else {
+            return "Halloy".to_string();
+        };
+
+        match dashboard.history().unread_counts() {
+            (0, _) => "Halloy".to_string(),
+            (messages, 0) => format!("Halloy ({messages})"),
+            (messages, highlights) => format!("Halloy ({messages}, {highlights} highlighted)"),
+        }
+    }
+
     fn theme(&self, _window: window::Id) -> Theme {
         self.theme.clone()
     }
@@ -1033,16 +1640,115 @@ impl Halloy {
         self.config.scale_factor.into()
     }
 
+    /// Asks for the next credential a server needs before connecting, of
+    /// servers ordered after `after` when set.
+    fn prompt_password(&mut self, after: Option<&Server>) -> Task<Message> {
+        if self.modal.is_some() {
+            return Task::none();
+        }
+
+        let next = self.servers.entries().find_map(|entry| {
+            let prompt = entry.config.prompt()?;
+
+            after
+                .is_none_or(|after| entry.server > *after)
+                .then_some((entry.server, prompt))
+        });
+
+        if let Some((server, prompt)) = next {
+            self.modal = Some(Modal::PasswordPrompt {
+                server,
+                prompt,
+                input: String::new(),
+            });
+
+            modal::password_prompt::focus()
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Reloads the configuration once its file is modified.
+    fn reload_config(&mut self) -> Task<Message> {
+        let modified = Config::modified();
+
+        if modified == self.config_modified {
+            return Task::none();
+        }
+
+        self.config_modified = modified;
+
+        if modified.is_some() && matches!(self.screen, Screen::Dashboard(_)) {
+            Task::perform(Config::load(), |config| {
+                Message::Dashboard(dashboard::Message::ConfigReloaded(config))
+            })
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Reloads the selected theme once its file is modified, so changes
+    /// made in an editor apply without restarting.
+    fn reload_theme(&mut self) -> Task<Message> {
+        // Unsaved changes from the theme editor are kept
+        let Theme::Selected(selected) = &self.theme else {
+            return Task::none();
+        };
+
+        let path = selected.path();
+
+        let Ok(modified) = std::fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+            self.theme_modified = None;
+            return Task::none();
+        };
+
+        let changed = self
+            .theme_modified
+            .as_ref()
+            .is_some_and(|(name, last)| *name == selected.name && *last != modified);
+
+        self.theme_modified = Some((selected.name.clone(), modified));
+
+        if changed {
+            let name = selected.name.clone();
+
+            Task::perform(data::appearance::theme::Colors::load(path), move |result| {
+                Message::ThemeReloaded(name.clone(), result)
+            })
+        } else {
+            Task::none()
+        }
+    }
+
     fn subscription(&self) -> Subscription<Message> {
         let tick = iced::time::every(Duration::from_secs(1)).map(Message::Tick);
 
         let streams = Subscription::batch(
             self.servers
                 .entries()
-                .map(|entry| stream::run(entry, self.config.proxy.clone())),
+                .filter(|entry| {
+                    !self.clients.is_stopped(&entry.server) && entry.config.prompt().is_none()
+                })
+                .map(|entry| {
+                    let connection = self.clients.connection(&entry.server);
+
+                    stream::run(entry, connection, self.config.proxy.clone())
+                }),
         )
         .map(Message::Stream);
 
+        let dcc_chats = Subscription::batch(self.clients.dcc_chats().list().map(|chat| {
+            Subscription::run_with_id(
+                chat.id,
+                dcc::chat::run(
+                    chat.clone(),
+                    self.config.file_transfer.clone(),
+                    self.config.proxy.clone(),
+                ),
+            )
+        }))
+        .map(Message::DccChat);
+
         Subscription::batch(vec![
             url::listen().map(Message::RouteReceived),
             events().map(|(window, event)| Message::Event(window, event)),
@@ -1052,6 +1758,7 @@ impl Halloy {
             // appearance::subscription().map(Message::AppearanceChange),
             tick,
             streams,
+            dcc_chats,
         ])
     }
 }
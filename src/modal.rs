@@ -2,6 +2,7 @@ use crate::widget::Element;
 use data::{config, Server};
 
 pub mod connect_to_server;
+pub mod password_prompt;
 pub mod reload_configuration_error;
 
 #[derive(Debug)]
@@ -12,18 +13,26 @@ pub enum Modal {
         server: Server,
         config: config::Server,
     },
+    PasswordPrompt {
+        server: Server,
+        prompt: config::server::Prompt,
+        input: String,
+    },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Message {
     Cancel,
     AcceptNewServer,
     DangerouslyAcceptInvalidCerts(bool),
+    PasswordInput(String),
+    SubmitPassword,
 }
 
 pub enum Event {
     CloseModal,
     AcceptNewServer,
+    SubmitPassword,
 }
 
 impl Modal {
@@ -38,6 +47,19 @@ impl Modal {
 
                 None
             }
+            Message::PasswordInput(password) => {
+                if let Modal::PasswordPrompt { input, .. } = self {
+                    *input = password;
+                }
+
+                None
+            }
+            Message::SubmitPassword => match self {
+                Modal::PasswordPrompt { input, .. } if !input.is_empty() => {
+                    Some(Event::SubmitPassword)
+                }
+                _ => None,
+            },
         }
     }
 
@@ -47,6 +69,11 @@ impl Modal {
             Modal::ServerConnect {
                 url: raw, config, ..
             } => connect_to_server::view(raw, config),
+            Modal::PasswordPrompt {
+                server,
+                prompt,
+                input,
+            } => password_prompt::view(server, *prompt, input),
         }
     }
 }
@@ -13,6 +13,9 @@ pub fn view<'a>(raw: &'a str, config: &config::Server) -> Element<'a, Message> {
         column![
             text("Connect to server?"),
             text(raw).style(theme::text::tertiary),
+            text("It isn't added to your configuration, and is forgotten once Halloy is closed.")
+                .style(theme::text::secondary)
+                .size(theme::TEXT_SIZE - 1.0),
         ]
         .push(
             checkbox(
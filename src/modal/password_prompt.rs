@@ -0,0 +1,57 @@
+use data::config::server::Prompt;
+use data::Server;
+use iced::widget::{button, column, container, text, text_input};
+use iced::{alignment, Length, Task};
+use once_cell::sync::Lazy;
+
+use super::Message;
+use crate::{theme, widget::Element};
+
+static INPUT: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
+
+pub fn view<'a>(server: &'a Server, prompt: Prompt, input: &'a str) -> Element<'a, Message> {
+    container(
+        column![
+            text(format!("Enter the {prompt} for {server}")),
+            text_input("Password", input)
+                .id(INPUT.clone())
+                .secure(true)
+                .on_input(Message::PasswordInput)
+                .on_submit(Message::SubmitPassword)
+                .padding(5)
+                .width(Length::Fixed(250.0))
+                .style(theme::text_input::primary),
+            column![
+                button(
+                    container(text("Connect"))
+                        .align_x(alignment::Horizontal::Center)
+                        .width(Length::Fill),
+                )
+                .padding(5)
+                .width(Length::Fixed(250.0))
+                .style(|theme, status| theme::button::secondary(theme, status, false))
+                .on_press(Message::SubmitPassword),
+                button(
+                    container(text("Skip"))
+                        .align_x(alignment::Horizontal::Center)
+                        .width(Length::Fill),
+                )
+                .padding(5)
+                .width(Length::Fixed(250.0))
+                .style(|theme, status| theme::button::secondary(theme, status, false))
+                .on_press(Message::Cancel),
+            ]
+            .spacing(4),
+        ]
+        .spacing(20)
+        .align_x(iced::Alignment::Center),
+    )
+    .width(Length::Shrink)
+    .style(theme::container::tooltip)
+    .padding(25)
+    .into()
+}
+
+pub fn focus<T>() -> Task<T> {
+    text_input::focus(INPUT.clone())
+}
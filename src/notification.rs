@@ -1,41 +1,100 @@
+use std::time::Duration;
+
 use data::{
     audio::Sound,
     config::{self, notification},
     user::{Nick, NickRef},
+    webhook,
 };
+use iced::Task;
 
 use crate::audio;
 
 pub use self::toast::prepare;
+pub use data::config::notification::{Context, WebhookEvent};
 
+mod command;
 mod toast;
 
 pub fn connected(config: &config::Notifications<Sound>, server: impl ToString) {
-    show_notification(&config.connected, "Connected", server);
+    let server = server.to_string();
+
+    show_notification(
+        &config.connected,
+        "Connected",
+        &server,
+        notification::Context {
+            server: &server,
+            ..Default::default()
+        },
+    );
 }
 
 pub fn reconnected(config: &config::Notifications<Sound>, server: impl ToString) {
-    show_notification(&config.reconnected, "Reconnected", server);
+    let server = server.to_string();
+
+    show_notification(
+        &config.reconnected,
+        "Reconnected",
+        &server,
+        notification::Context {
+            server: &server,
+            ..Default::default()
+        },
+    );
 }
 
 pub fn disconnected(config: &config::Notifications<Sound>, server: impl ToString) {
-    show_notification(&config.disconnected, "Disconnected", server);
-}
+    let server = server.to_string();
 
-pub fn direct_message(config: &config::Notifications<Sound>, nick: NickRef) {
     show_notification(
+        &config.disconnected,
+        "Disconnected",
+        &server,
+        notification::Context {
+            server: &server,
+            ..Default::default()
+        },
+    );
+}
+
+pub fn direct_message(
+    config: &config::Notifications<Sound>,
+    nick: NickRef,
+    server: impl ToString,
+    message: &str,
+) -> Task<()> {
+    show_clickable_notification(
         &config.direct_message,
         "Direct message",
         format!("{} sent you a direct message", nick),
-    );
+        notification::Context {
+            server: &server.to_string(),
+            nick: nick.as_ref(),
+            message,
+            ..Default::default()
+        },
+    )
 }
 
-pub fn highlight(config: &config::Notifications<Sound>, nick: NickRef, channel: String) {
-    show_notification(
+pub fn highlight(
+    config: &config::Notifications<Sound>,
+    nick: NickRef,
+    channel: String,
+    server: impl ToString,
+    message: &str,
+) -> Task<()> {
+    show_clickable_notification(
         &config.highlight,
         "Highlight",
         format!("{} highlighted you in {}", nick, channel),
-    );
+        notification::Context {
+            server: &server.to_string(),
+            channel: &channel,
+            nick: nick.as_ref(),
+            message,
+        },
+    )
 }
 
 pub fn file_transfer_request(
@@ -43,35 +102,106 @@ pub fn file_transfer_request(
     nick: Nick,
     server: impl ToString,
 ) {
+    let server = server.to_string();
+
     show_notification(
         &config.file_transfer_request,
         &format!("File transfer from {}", nick),
-        server,
+        &server,
+        notification::Context {
+            server: &server,
+            nick: nick.as_ref(),
+            ..Default::default()
+        },
     );
 }
 
 pub fn monitored_online(config: &config::Notifications<Sound>, nick: Nick, server: impl ToString) {
+    let server = server.to_string();
+
     show_notification(
         &config.monitored_online,
         &format!("{} is online", nick),
-        server,
+        &server,
+        notification::Context {
+            server: &server,
+            nick: nick.as_ref(),
+            ..Default::default()
+        },
     );
 }
 
 pub fn monitored_offline(config: &config::Notifications<Sound>, nick: Nick, server: impl ToString) {
+    let server = server.to_string();
+
     show_notification(
         &config.monitored_offline,
         &format!("{} is offline", nick),
-        server,
+        &server,
+        notification::Context {
+            server: &server,
+            nick: nick.as_ref(),
+            ..Default::default()
+        },
     );
 }
 
-fn show_notification(notification: &notification::Loaded, title: &str, body: impl ToString) {
+/// Posts to the webhooks subscribed to `event`, skipping those only posting
+/// while we're away or idle otherwise.
+pub fn webhooks(
+    config: &config::Notifications<Sound>,
+    event: notification::WebhookEvent,
+    away: bool,
+    unfocused: Option<Duration>,
+    context: notification::Context,
+) -> Task<Result<(), webhook::Error>> {
+    Task::batch(
+        config
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.is_enabled(event, away, unfocused))
+            .map(|webhook| Task::future(webhook::send(webhook, context))),
+    )
+}
+
+fn show_notification(
+    notification: &notification::Loaded,
+    title: &str,
+    body: impl ToString,
+    context: notification::Context,
+) {
     if notification.show_toast {
         toast::show(title, body);
     }
 
+    alert(notification, context);
+}
+
+/// Like [`show_notification`], but the returned task completes when the toast
+/// is clicked.
+fn show_clickable_notification(
+    notification: &notification::Loaded,
+    title: &str,
+    body: impl ToString,
+    context: notification::Context,
+) -> Task<()> {
+    let task = if notification.show_toast {
+        toast::show_clickable(title, body)
+    } else {
+        Task::none()
+    };
+
+    alert(notification, context);
+
+    task
+}
+
+fn alert(notification: &notification::Loaded, context: notification::Context) {
     if let Some(sound) = &notification.sound {
         audio::play(sound.clone());
     }
+
+    if let Some(command) = notification.command(context) {
+        command::run(command);
+    }
 }
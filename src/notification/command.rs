@@ -0,0 +1,27 @@
+use std::{process, thread};
+
+/// Runs the program with its arguments, without a shell so substituted
+/// messages can't inject commands.
+pub fn run(command: Vec<String>) {
+    let Some((program, args)) = command.split_first() else {
+        return;
+    };
+
+    match process::Command::new(program)
+        .args(args)
+        .stdin(process::Stdio::null())
+        .spawn()
+    {
+        Ok(mut child) => {
+            // Reap it once done
+            thread::spawn(move || {
+                if let Err(e) = child.wait() {
+                    log::error!("Failed to wait for notification command: {e}");
+                }
+            });
+        }
+        Err(e) => {
+            log::error!("Failed to run notification command {program}: {e}");
+        }
+    }
+}
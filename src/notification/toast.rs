@@ -1,3 +1,25 @@
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::thread;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::time::Duration;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use futures::channel::oneshot;
+use iced::Task;
+
+/// How long a clickable toast is shown, the server closing it afterwards.
+#[cfg(all(unix, not(target_os = "macos")))]
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Toasts waited on for a click at once, as servers which keep toasts
+/// around never close them.
+#[cfg(all(unix, not(target_os = "macos")))]
+const MAX_WAITING: usize = 4;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
 #[cfg(target_os = "macos")]
 pub fn prepare() {
     match notify_rust::set_application(data::environment::APPLICATION_ID) {
@@ -12,6 +34,57 @@ pub fn prepare() {
 pub fn prepare() {}
 
 pub fn show(title: &str, body: impl ToString) {
+    let _ = notification(title, body).show();
+}
+
+/// Shows a toast, completing once it's clicked where the platform supports it.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn show_clickable(title: &str, body: impl ToString) -> Task<()> {
+    if WAITING.fetch_add(1, Ordering::Relaxed) >= MAX_WAITING {
+        WAITING.fetch_sub(1, Ordering::Relaxed);
+        show(title, body);
+
+        return Task::none();
+    }
+
+    let mut notification = notification(title, body);
+
+    // Invoked by clicking the notification itself, rather than a button
+    notification.action("default", "Open");
+    // Closing it ends the wait for a click below
+    notification.timeout(notify_rust::Timeout::Milliseconds(
+        TIMEOUT.as_millis() as u32
+    ));
+
+    let (sender, receiver) = oneshot::channel();
+
+    // Waiting for the action blocks, so show it from its own thread
+    thread::spawn(move || {
+        if let Ok(handle) = notification.show() {
+            handle.wait_for_action(|action| {
+                if action == "default" {
+                    let _ = sender.send(());
+                }
+            });
+        }
+
+        WAITING.fetch_sub(1, Ordering::Relaxed);
+    });
+
+    Task::future(receiver).then(|clicked| match clicked {
+        Ok(()) => Task::done(()),
+        Err(_) => Task::none(),
+    })
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn show_clickable(title: &str, body: impl ToString) -> Task<()> {
+    show(title, body);
+
+    Task::none()
+}
+
+fn notification(title: &str, body: impl ToString) -> notify_rust::Notification {
     let mut notification = notify_rust::Notification::new();
 
     notification.summary(title);
@@ -27,5 +100,5 @@ pub fn show(title: &str, body: impl ToString) {
         notification.app_id(data::environment::APPLICATION_ID);
     }
 
-    let _ = notification.show();
+    notification
 }
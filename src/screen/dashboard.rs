@@ -2,7 +2,7 @@ use chrono::{DateTime, Utc};
 use data::dashboard::BufferAction;
 use data::environment::{RELEASE_WEBSITE, WIKI_WEBSITE};
 use data::history::ReadMarker;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use std::{convert, slice};
@@ -10,8 +10,9 @@ use std::{convert, slice};
 use data::config;
 use data::file_transfer;
 use data::history::manager::Broadcast;
+use data::message::formatting::Modifier;
 use data::user::Nick;
-use data::{client, environment, history, Config, Server, Version};
+use data::{client, command, dcc, environment, history, Config, Server, Version};
 use iced::widget::pane_grid::{self, PaneGrid};
 use iced::widget::{column, container, row, Space};
 use iced::{clipboard, Length, Task, Vector};
@@ -33,16 +34,27 @@ pub mod sidebar;
 mod theme_editor;
 
 const SAVE_AFTER: Duration = Duration::from_secs(3);
+/// Pixels scrolled per `j` / `k` in vim normal mode
+const SCROLL_STEP: f32 = 40.0;
 
 pub struct Dashboard {
     panes: Panes,
     focus: Option<(window::Id, pane_grid::Pane)>,
+    /// Pane last focused in the main window, restored when it regains focus
+    main_focus: Option<pane_grid::Pane>,
+    /// Popout window holding the OS focus, if any
+    focused_popout: Option<window::Id>,
     side_menu: Sidebar,
     history: history::Manager,
     last_changed: Option<Instant>,
     command_bar: Option<CommandBar>,
     file_transfers: file_transfer::Manager,
     theme_editor: Option<ThemeEditor>,
+    /// Named layouts of the main window
+    layouts: BTreeMap<String, data::Pane>,
+    zoom: HashMap<data::Buffer, data::buffer::Zoom>,
+    /// Mode of the vim-style keybindings, when enabled
+    vim_mode: data::shortcut::Mode,
 }
 
 #[derive(Debug)]
@@ -52,10 +64,12 @@ pub enum Message {
     SelectedText(Vec<(f32, String)>),
     History(history::manager::Message),
     DashboardSaved(Result<(), data::dashboard::Error>),
+    FileTransfersSaved(Result<(), file_transfer::saved::Error>),
     Task(command_bar::Message),
     Shortcut(shortcut::Command),
     FileTransfer(file_transfer::task::Update),
     SendFileSelected(Server, Nick, Option<PathBuf>),
+    ThemeImported(Option<Result<data::Theme, data::appearance::theme::Error>>),
     CloseContextMenu(window::Id, bool),
     ThemeEditor(theme_editor::Message),
     ConfigReloaded(Result<Config, config::Error>),
@@ -80,14 +94,22 @@ impl Dashboard {
                 popout: HashMap::new(),
             },
             focus: None,
+            main_focus: None,
+            focused_popout: None,
             side_menu: Sidebar::new(),
             history: history::Manager::default(),
             last_changed: None,
             command_bar: None,
-            file_transfers: file_transfer::Manager::new(config.file_transfer.clone()),
+            file_transfers: file_transfer::Manager::load(config.file_transfer.clone()),
             theme_editor: None,
+            layouts: BTreeMap::new(),
+            zoom: HashMap::new(),
+            vim_mode: data::shortcut::Mode::default(),
         };
 
+        dashboard.history.update_logging(&config.servers);
+        dashboard.history.update_filters(&config.servers);
+
         let command = dashboard.track();
 
         (dashboard, command)
@@ -138,6 +160,15 @@ impl Dashboard {
                         self.last_changed = Some(Instant::now());
                     }
                     pane::Message::PaneDragged(_) => {}
+                    pane::Message::Zoom(pane, zoom_in) => {
+                        self.zoom_pane(main_window, window, pane, |zoom| {
+                            if zoom_in {
+                                zoom.zoom_in()
+                            } else {
+                                zoom.zoom_out()
+                            }
+                        });
+                    }
                     pane::Message::ClosePane => {
                         if let Some((window, pane)) = self.focus.take() {
                             return (self.close_pane(main_window, window, pane), None);
@@ -153,6 +184,7 @@ impl Dashboard {
                                 clients,
                                 &mut self.history,
                                 &mut self.file_transfers,
+                                &pane.settings,
                                 config,
                             );
 
@@ -189,6 +221,23 @@ impl Dashboard {
                                                 clients.send(&input.buffer, encoded);
                                             }
                                         }
+                                        buffer::user_context::Event::Ban(
+                                            server,
+                                            channel,
+                                            nick,
+                                            mask,
+                                        ) => {
+                                            if let Err(error) = clients.ban(
+                                                &server,
+                                                &channel,
+                                                nick.as_ref(),
+                                                Some(mask),
+                                            ) {
+                                                log::warn!(
+                                                    "failed to ban {nick} from {channel}: {error}"
+                                                );
+                                            }
+                                        }
                                         buffer::user_context::Event::SendWhois(server, nick) => {
                                             let buffer =
                                                 pane.buffer.upstream().cloned().unwrap_or_else(
@@ -256,7 +305,7 @@ impl Dashboard {
                                                     self.open_buffer(
                                                         main_window,
                                                         data::Buffer::Upstream(buffer),
-                                                        config.buffer.clone().into(),
+                                                        config,
                                                     ),
                                                 ]),
                                                 None,
@@ -313,6 +362,42 @@ impl Dashboard {
                                                 None,
                                             );
                                         }
+                                        buffer::user_context::Event::RunCommand(
+                                            server,
+                                            channel,
+                                            command,
+                                        ) => {
+                                            let upstream = channel
+                                                .map(|channel| {
+                                                    buffer::Upstream::Channel(
+                                                        server.clone(),
+                                                        channel,
+                                                    )
+                                                })
+                                                .or_else(|| pane.buffer.upstream().cloned())
+                                                .unwrap_or(buffer::Upstream::Server(server));
+
+                                            let command_task = match data::input::parse(
+                                                upstream.clone(),
+                                                config.buffer.text_input.auto_format,
+                                                &command,
+                                                clients.get_line_len(upstream.server()),
+                                                clients.get_statusmsg(upstream.server()),
+                                            ) {
+                                                Ok(input) => buffer::input_view::send(
+                                                    input,
+                                                    clients,
+                                                    &mut self.history,
+                                                )
+                                                .map(Message::History),
+                                                Err(error) => self.record_feedback(
+                                                    history::Kind::from_input_buffer(upstream),
+                                                    error.to_string(),
+                                                ),
+                                            };
+
+                                            return (Task::batch(vec![task, command_task]), None);
+                                        }
                                     }
                                 }
                                 buffer::Event::OpenChannel(channel) => {
@@ -343,10 +428,48 @@ impl Dashboard {
                                         None,
                                     )
                                 }
-                                buffer::Event::GoToMessage(server, channel, message) => {
-                                    let buffer = data::Buffer::Upstream(buffer::Upstream::Channel(
-                                        server, channel,
-                                    ));
+                                buffer::Event::Layout(layout, history_task) => {
+                                    let Some(upstream) = pane.buffer.upstream().cloned() else {
+                                        return (task, None);
+                                    };
+
+                                    return (
+                                        Task::batch(vec![
+                                            task,
+                                            history_task.map(Message::History),
+                                            self.layout(layout, upstream, main_window),
+                                        ]),
+                                        None,
+                                    );
+                                }
+                                buffer::Event::ResendFile(id) => {
+                                    let Some(transfer) = self.file_transfers.get(&id).cloned()
+                                    else {
+                                        return (task, None);
+                                    };
+                                    let Some(path) = transfer.path else {
+                                        return (task, None);
+                                    };
+
+                                    // Replaced by the new transfer
+                                    self.file_transfers.remove(&id);
+
+                                    return (
+                                        Task::batch(vec![
+                                            task,
+                                            self.send_file(
+                                                transfer.server,
+                                                transfer.remote_user,
+                                                path,
+                                                clients,
+                                                config,
+                                            ),
+                                        ]),
+                                        None,
+                                    );
+                                }
+                                buffer::Event::GoToMessage(buffer, message) => {
+                                    let buffer = data::Buffer::Upstream(buffer);
 
                                     let mut tasks = vec![];
 
@@ -358,7 +481,7 @@ impl Dashboard {
                                         tasks.push(self.open_buffer(
                                             main_window,
                                             buffer.clone(),
-                                            config.buffer.clone().into(),
+                                            config,
                                         ));
                                     }
 
@@ -368,7 +491,12 @@ impl Dashboard {
                                         tasks.push(
                                             state
                                                 .buffer
-                                                .scroll_to_message(message, &self.history, config)
+                                                .scroll_to_message(
+                                                    message,
+                                                    &self.history,
+                                                    &state.settings,
+                                                    config,
+                                                )
                                                 .map(move |message| {
                                                     Message::Pane(
                                                         window,
@@ -401,6 +529,20 @@ impl Dashboard {
                             self.last_changed = Some(Instant::now());
                         }
                     }
+                    pane::Message::ToggleSmartFilter => {
+                        if let Some((_, _, pane)) = self.get_focused_mut(main_window) {
+                            pane.update_settings(|settings| settings.channel.smart_filter.toggle());
+                            self.last_changed = Some(Instant::now());
+                        }
+                    }
+                    pane::Message::ToggleFiltered => {
+                        if let Some(resource) = self
+                            .get_focused_mut(main_window)
+                            .and_then(|(_, _, pane)| pane.resource())
+                        {
+                            self.history.toggle_filtered(resource.kind);
+                        }
+                    }
                     pane::Message::MaximizePane => self.maximize_pane(),
                     pane::Message::Popout => return (self.popout_pane(main_window), None),
                     pane::Message::Merge => return (self.merge_pane(config, main_window), None),
@@ -427,11 +569,7 @@ impl Dashboard {
 
                 let (event_task, event) = match event {
                     sidebar::Event::Open(buffer) => (
-                        self.open_buffer(
-                            main_window,
-                            data::Buffer::Upstream(buffer),
-                            config.buffer.clone().into(),
-                        ),
+                        self.open_buffer(main_window, data::Buffer::Upstream(buffer), config),
                         None,
                     ),
                     sidebar::Event::Popout(buffer) => (
@@ -501,6 +639,13 @@ impl Dashboard {
                         self.toggle_internal_buffer(config, main_window, buffer),
                         None,
                     ),
+                    sidebar::Event::ToggleConsole(server) => {
+                        (self.toggle_console(config, main_window, server), None)
+                    }
+                    sidebar::Event::ToggleModeLists(server, channel) => (
+                        self.toggle_mode_lists(clients, config, main_window, server, channel),
+                        None,
+                    ),
                     sidebar::Event::ToggleCommandBar => (
                         self.toggle_command_bar(
                             &closed_buffers(self, main_window.id, clients),
@@ -521,6 +666,19 @@ impl Dashboard {
                     sidebar::Event::ToggleThemeEditor => {
                         (self.toggle_theme_editor(theme, main_window), None)
                     }
+                    sidebar::Event::NextHighlight => {
+                        let task = self
+                            .history
+                            .next_highlight()
+                            .cloned()
+                            .map(|kind| self.open_buffer(main_window, kind.into(), config));
+
+                        (task.unwrap_or_else(Task::none), None)
+                    }
+                    sidebar::Event::ToggleDoNotDisturb => {
+                        clients.do_not_disturb(data::command::DoNotDisturb::Toggle);
+                        (Task::none(), None)
+                    }
                     sidebar::Event::OpenDocumentation => {
                         let _ = open::that_detached(WIKI_WEBSITE);
                         (Task::none(), None)
@@ -563,14 +721,15 @@ impl Dashboard {
                                 self.panes.get_mut_by_buffer(main_window.id, &buffer)
                             {
                                 return (
-                                    state.buffer.scroll_to_backlog(&self.history, config).map(
-                                        move |message| {
+                                    state
+                                        .buffer
+                                        .scroll_to_backlog(&self.history, &state.settings, config)
+                                        .map(move |message| {
                                             Message::Pane(
                                                 window,
                                                 pane::Message::Buffer(pane, message),
                                             )
-                                        },
-                                    ),
+                                        }),
                                     None,
                                 );
                             }
@@ -584,6 +743,30 @@ impl Dashboard {
                                 };
                             }
                         }
+                        history::manager::Event::Exported(kind, result) => {
+                            let text = match result {
+                                Ok(path) => format!("exported to {}", path.display()),
+                                Err(error) => format!("failed to export: {error}"),
+                            };
+
+                            return (self.record_feedback(kind, text), None);
+                        }
+                        history::manager::Event::Imported(kind, result) => {
+                            let text = match result {
+                                Ok(count) if count.kept < count.new => format!(
+                                    "imported {} messages, {} older ones didn't fit in history",
+                                    count.kept,
+                                    count.new - count.kept
+                                ),
+                                Ok(count) => format!("imported {} messages", count.kept),
+                                Err(error) => format!("failed to import: {error}"),
+                            };
+
+                            return (self.record_feedback(kind, text), None);
+                        }
+                        history::manager::Event::Feedback(kind, text) => {
+                            return (self.record_feedback(kind, text), None);
+                        }
                         history::manager::Event::Exited(results) => {
                             for (kind, read_marker) in results {
                                 if let Some(((server, target), read_marker)) =
@@ -606,6 +789,12 @@ impl Dashboard {
             Message::DashboardSaved(Err(error)) => {
                 log::warn!("error saving dashboard: {error}");
             }
+            Message::FileTransfersSaved(Ok(_)) => {
+                log::debug!("file transfers saved");
+            }
+            Message::FileTransfersSaved(Err(error)) => {
+                log::warn!("error saving file transfers: {error}");
+            }
             Message::Task(message) => {
                 let Some(command_bar) = &mut self.command_bar else {
                     return (Task::none(), None);
@@ -684,6 +873,13 @@ impl Dashboard {
                                     (Task::perform(Config::load(), Message::ConfigReloaded), None)
                                 }
                             },
+                            command_bar::Command::Layout(command) => match command {
+                                command_bar::Layout::Load(name) => (
+                                    self.load_layout(&name, main_window)
+                                        .unwrap_or_else(Task::none),
+                                    None,
+                                ),
+                            },
                             command_bar::Command::UI(command) => match command {
                                 command_bar::Ui::ToggleSidebarVisibility => {
                                     self.side_menu.toggle_visibility();
@@ -706,6 +902,25 @@ impl Dashboard {
                                         (task.then(|_| Task::none()), None)
                                     }
                                 }
+                                command_bar::Theme::ImportBase16 => (
+                                    Task::perform(
+                                        async {
+                                            let handle = rfd::AsyncFileDialog::new()
+                                                .add_filter("base16", &["yaml", "yml"])
+                                                .pick_file()
+                                                .await?;
+
+                                            Some(
+                                                data::appearance::theme::base16::import(
+                                                    handle.path().to_path_buf(),
+                                                )
+                                                .await,
+                                            )
+                                        },
+                                        Message::ThemeImported,
+                                    ),
+                                    None,
+                                ),
                             },
                         };
 
@@ -756,6 +971,11 @@ impl Dashboard {
                     Task::none()
                 };
 
+                // Both focus a text input
+                if matches!(shortcut, Search | CommandBar) {
+                    self.vim_mode = data::shortcut::Mode::Insert;
+                }
+
                 match shortcut {
                     MoveUp => return (move_focus(pane_grid::Direction::Up), None),
                     MoveDown => return (move_focus(pane_grid::Direction::Down), None),
@@ -882,26 +1102,148 @@ impl Dashboard {
                             None,
                         );
                     }
+                    Search => {
+                        if let Some((window, pane, state)) = self.get_focused_mut(main_window) {
+                            return (
+                                state.buffer.toggle_search().map(move |message| {
+                                    Message::Pane(window, pane::Message::Buffer(pane, message))
+                                }),
+                                None,
+                            );
+                        }
+                    }
+                    ScrollUp | ScrollDown => {
+                        let delta = if matches!(shortcut, ScrollUp) {
+                            -SCROLL_STEP
+                        } else {
+                            SCROLL_STEP
+                        };
+
+                        if let Some((window, pane, state)) = self.get_focused_mut(main_window) {
+                            return (
+                                state.buffer.scroll_by(delta).map(move |message| {
+                                    Message::Pane(window, pane::Message::Buffer(pane, message))
+                                }),
+                                None,
+                            );
+                        }
+                    }
+                    ScrollToStart => {
+                        if let Some((window, pane, state)) = self.get_focused_mut(main_window) {
+                            return (
+                                state.buffer.scroll_to_start().map(move |message| {
+                                    Message::Pane(window, pane::Message::Buffer(pane, message))
+                                }),
+                                None,
+                            );
+                        }
+                    }
+                    ScrollToEnd => {
+                        if let Some((window, pane, state)) = self.get_focused_mut(main_window) {
+                            return (
+                                state.buffer.scroll_to_end().map(move |message| {
+                                    Message::Pane(window, pane::Message::Buffer(pane, message))
+                                }),
+                                None,
+                            );
+                        }
+                    }
+                    Yank => {
+                        let buffer = self
+                            .get_focused_mut(main_window)
+                            .and_then(|(_, _, state)| state.buffer.upstream().cloned());
+
+                        if let Some(buffer) = buffer {
+                            let kind = history::Kind::from_input_buffer(buffer);
+
+                            let yanked = self
+                                .history
+                                .get_messages(&kind, None, config, false)
+                                .and_then(|view| {
+                                    view.old_messages
+                                        .into_iter()
+                                        .chain(view.new_messages)
+                                        .last()
+                                        .map(yank)
+                                });
+
+                            if let Some(yanked) = yanked {
+                                return (clipboard::write(yanked), None);
+                            }
+                        }
+                    }
+                    InsertMode => {
+                        self.vim_mode = data::shortcut::Mode::Insert;
+
+                        if let Some((window, pane, state)) = self.get_focused_mut(main_window) {
+                            return (
+                                state.buffer.focus().map(move |message| {
+                                    Message::Pane(window, pane::Message::Buffer(pane, message))
+                                }),
+                                None,
+                            );
+                        }
+                    }
+                    NormalMode => {
+                        self.vim_mode = data::shortcut::Mode::Normal;
+                    }
+                    ZoomIn | ZoomOut | ZoomReset => {
+                        if let Some((window, pane)) = self.focus {
+                            self.zoom_pane(main_window, window, pane, |zoom| match shortcut {
+                                ZoomIn => zoom.zoom_in(),
+                                ZoomOut => zoom.zoom_out(),
+                                _ => data::buffer::Zoom::default(),
+                            });
+                        }
+                    }
+                    FormatBold | FormatItalics | FormatUnderline | FormatColor | FormatReset => {
+                        let modifier = match shortcut {
+                            FormatBold => Modifier::Bold,
+                            FormatItalics => Modifier::Italics,
+                            FormatUnderline => Modifier::Underline,
+                            FormatColor => Modifier::Color,
+                            _ => Modifier::Reset,
+                        };
+
+                        if let Some((window, _)) = self.focus {
+                            if let Some((pane, state, history)) =
+                                self.get_focused_with_history_mut(main_window)
+                            {
+                                return (
+                                    state
+                                        .buffer
+                                        .insert_formatting_to_input(
+                                            modifier.char().to_string(),
+                                            history,
+                                        )
+                                        .map(move |message| {
+                                            Message::Pane(
+                                                window,
+                                                pane::Message::Buffer(pane, message),
+                                            )
+                                        }),
+                                    None,
+                                );
+                            }
+                        }
+                    }
                 }
             }
             Message::FileTransfer(update) => {
                 self.file_transfers.update(update);
             }
+            Message::ThemeImported(imported) => match imported {
+                Some(Ok(imported)) => {
+                    *theme = Theme::from(imported);
+
+                    return (Task::none(), Some(Event::ReloadThemes));
+                }
+                Some(Err(error)) => log::warn!("failed to import base16 scheme: {error}"),
+                None => {}
+            },
             Message::SendFileSelected(server, to, path) => {
-                if let Some(server_handle) = clients.get_server_handle(&server) {
-                    if let Some(path) = path {
-                        if let Some(event) = self.file_transfers.send(
-                            file_transfer::SendRequest {
-                                to,
-                                path,
-                                server: server.clone(),
-                                server_handle: server_handle.clone(),
-                            },
-                            config.proxy.clone(),
-                        ) {
-                            return (self.handle_file_transfer_event(&server, event), None);
-                        }
-                    }
+                if let Some(path) = path {
+                    return (self.send_file(server, to, path, clients, config), None);
                 }
             }
             Message::CloseContextMenu(window, any_closed) => {
@@ -966,6 +1308,7 @@ impl Dashboard {
                         1,
                         is_focused,
                         false,
+                        self.zoom_level(&pane.buffer),
                         clients,
                         &self.file_transfers,
                         &self.history,
@@ -1011,6 +1354,7 @@ impl Dashboard {
                 panes,
                 is_focused,
                 maximized,
+                self.zoom_level(&pane.buffer),
                 clients,
                 &self.file_transfers,
                 &self.history,
@@ -1087,6 +1431,7 @@ impl Dashboard {
                         &all_buffers(clients, &self.history),
                         self.focus,
                         self.buffer_resize_action(),
+                        &self.layouts.keys().cloned().collect::<Vec<_>>(),
                         version,
                         config,
                         main_window.id,
@@ -1102,7 +1447,12 @@ impl Dashboard {
             column![column![base]].into()
         };
 
-        shortcut(base, config.keyboard.shortcuts(), Message::Shortcut)
+        shortcut(
+            base,
+            config.keyboard.shortcuts(),
+            config.keyboard.vim_mode.then_some(self.vim_mode),
+            Message::Shortcut,
+        )
     }
 
     pub fn handle_event(
@@ -1155,6 +1505,7 @@ impl Dashboard {
                     })
                 })
                 .unwrap_or_else(Task::none),
+            Input => Task::none(),
         }
     }
 
@@ -1190,11 +1541,9 @@ impl Dashboard {
         } else {
             match config.sidebar.buffer_action {
                 // Don't replace for internal buffers
-                BufferAction::NewPane | BufferAction::ReplacePane => self.open_buffer(
-                    main_window,
-                    data::Buffer::Internal(buffer),
-                    config.buffer.clone().into(),
-                ),
+                BufferAction::NewPane | BufferAction::ReplacePane => {
+                    self.open_buffer(main_window, data::Buffer::Internal(buffer), config)
+                }
                 BufferAction::NewWindow => self.open_popout_window(
                     main_window,
                     Pane::new(Buffer::from(data::Buffer::Internal(buffer)), config),
@@ -1203,7 +1552,220 @@ impl Dashboard {
         }
     }
 
+    fn zoom_level(&self, buffer: &Buffer) -> data::buffer::Zoom {
+        buffer
+            .data()
+            .and_then(|buffer| self.zoom.get(&buffer).copied())
+            .unwrap_or_default()
+    }
+
+    /// Zooms the buffer shown in a pane, remembering it for that buffer.
+    fn zoom_pane(
+        &mut self,
+        main_window: &Window,
+        window: window::Id,
+        pane: pane_grid::Pane,
+        f: impl FnOnce(data::buffer::Zoom) -> data::buffer::Zoom,
+    ) {
+        let Some(buffer) = self
+            .panes
+            .get(main_window.id, window, pane)
+            .and_then(|state| state.buffer.data())
+        else {
+            return;
+        };
+
+        let zoom = f(self.zoom.get(&buffer).copied().unwrap_or_default());
+
+        if zoom.is_default() {
+            self.zoom.remove(&buffer);
+        } else {
+            self.zoom.insert(buffer, zoom);
+        }
+
+        self.last_changed = Some(Instant::now());
+    }
+
+    /// Saves, loads or lists layouts of the main window, reporting back in `upstream`.
+    fn layout(
+        &mut self,
+        layout: command::Layout,
+        upstream: buffer::Upstream,
+        main_window: &Window,
+    ) -> Task<Message> {
+        let mut task = Task::none();
+
+        let feedback = match layout {
+            command::Layout::Save(name) => {
+                let pane = from_layout(&self.panes.main, self.panes.main.layout().clone());
+
+                self.layouts.insert(name.clone(), pane);
+                self.last_changed = Some(Instant::now());
+
+                format!("Saved layout {name}")
+            }
+            command::Layout::Load(name) => match self.load_layout(&name, main_window) {
+                Some(load) => {
+                    task = load;
+                    format!("Loaded layout {name}")
+                }
+                None => format!("No layout named {name}"),
+            },
+            command::Layout::Delete(name) => {
+                if self.layouts.remove(&name).is_some() {
+                    self.last_changed = Some(Instant::now());
+                    format!("Deleted layout {name}")
+                } else {
+                    format!("No layout named {name}")
+                }
+            }
+            command::Layout::List => {
+                if self.layouts.is_empty() {
+                    "No layouts saved".to_string()
+                } else {
+                    let names = self.layouts.keys().cloned().collect::<Vec<_>>();
+
+                    format!("Layouts: {}", names.join(", "))
+                }
+            }
+        };
+
+        Task::batch(vec![
+            task,
+            self.record_feedback(history::Kind::from_input_buffer(upstream), feedback),
+        ])
+    }
+
+    /// Replaces the panes of the main window with a saved layout.
+    fn load_layout(&mut self, name: &str, main_window: &Window) -> Option<Task<Message>> {
+        let pane = self.layouts.get(name).cloned()?;
+
+        self.panes.main = pane_grid::State::with_configuration(configuration(pane));
+        self.last_changed = Some(Instant::now());
+
+        // Panes of the previous layout no longer exist
+        if self
+            .focus
+            .is_some_and(|(window, _)| window == main_window.id)
+        {
+            self.focus = None;
+        }
+        self.main_focus = None;
+
+        let first = self.panes.main.iter().next().map(|(pane, _)| *pane);
+
+        Some(
+            first
+                .map(|pane| self.focus_pane(main_window, main_window.id, pane))
+                .unwrap_or_else(Task::none),
+        )
+    }
+
+    /// Shows the outcome of a command run in the background in its buffer.
+    fn record_feedback(&mut self, kind: history::Kind, text: String) -> Task<Message> {
+        let (Some(server), data::Buffer::Upstream(buffer)) =
+            (kind.server().cloned(), data::Buffer::from(kind))
+        else {
+            return Task::none();
+        };
+
+        self.history
+            .record_message(
+                &server,
+                data::Message::sent(
+                    buffer.server_message_target(None),
+                    data::message::plain(text),
+                ),
+            )
+            .map(|task| Task::future(task).map(Message::History))
+            .unwrap_or_else(Task::none)
+    }
+
+    /// Reports history removed to stay under `max_size` in the server buffer
+    /// it belonged to.
+    pub fn history_rotated(&mut self, kinds: Vec<history::Kind>) -> Task<Message> {
+        Task::batch(kinds.into_iter().map(|kind| {
+            log::warn!("removed history of {kind} to stay under max_size");
+
+            match kind.server() {
+                Some(server) => {
+                    let server = history::Kind::Server(server.clone());
+                    let text = format!("removed history of {kind} to stay under max_size");
+
+                    self.record_feedback(server, text)
+                }
+                None => Task::none(),
+            }
+        }))
+    }
+
+    fn toggle_console(
+        &mut self,
+        config: &Config,
+        main_window: &Window,
+        server: data::Server,
+    ) -> Task<Message> {
+        let panes = self.panes.clone();
+        let buffer = data::Buffer::Console { server };
+
+        let open = panes
+            .iter(main_window.id)
+            .find_map(|(window_id, pane, state)| {
+                (state.buffer.data().as_ref() == Some(&buffer)).then_some((window_id, pane))
+            });
+
+        if let Some((window, pane)) = open {
+            self.close_pane(main_window, window, pane)
+        } else {
+            self.open_buffer(main_window, buffer, config)
+        }
+    }
+
+    fn toggle_mode_lists(
+        &mut self,
+        clients: &mut data::client::Map,
+        config: &Config,
+        main_window: &Window,
+        server: data::Server,
+        channel: String,
+    ) -> Task<Message> {
+        let panes = self.panes.clone();
+        let buffer = data::Buffer::ModeLists { server, channel };
+
+        let open = panes
+            .iter(main_window.id)
+            .find_map(|(window_id, pane, state)| {
+                (state.buffer.data().as_ref() == Some(&buffer)).then_some((window_id, pane))
+            });
+
+        if let Some((window, pane)) = open {
+            return self.close_pane(main_window, window, pane);
+        }
+
+        let task = self.open_buffer(main_window, buffer.clone(), config);
+
+        // Fetch the list shown first right away
+        if let Some((_, _, pane)) = self.panes.get_mut_by_buffer(main_window.id, &buffer) {
+            if let Buffer::ModeLists(state) = &mut pane.buffer {
+                state.request(clients);
+            }
+        }
+
+        task
+    }
+
     fn open_buffer(
+        &mut self,
+        main_window: &Window,
+        buffer: data::Buffer,
+        config: &Config,
+    ) -> Task<Message> {
+        let settings = buffer::Settings::new(&buffer, config);
+
+        self.open_buffer_with_settings(main_window, buffer, settings)
+    }
+
+    fn open_buffer_with_settings(
         &mut self,
         main_window: &Window,
         buffer: data::Buffer,
@@ -1214,8 +1776,6 @@ impl Dashboard {
         // If buffer already is open, we focus it.
         for (window, id, pane) in panes.iter(main_window.id) {
             if pane.buffer.data().as_ref() == Some(&buffer) {
-                self.focus = Some((window, id));
-
                 return self.focus_pane(main_window, window, id);
             }
         }
@@ -1333,6 +1893,15 @@ impl Dashboard {
         }
     }
 
+    pub fn request_previews(&mut self, message: &data::Message, config: &Config) -> Task<Message> {
+        Task::batch(
+            self.history
+                .request_previews(message, &config.preview)
+                .into_iter()
+                .map(|task| Task::perform(task, Message::History)),
+        )
+    }
+
     pub fn record_log(&mut self, record: data::log::Record) -> Task<Message> {
         if let Some(task) = self.history.record_log(record) {
             Task::perform(task, Message::History)
@@ -1376,6 +1945,18 @@ impl Dashboard {
         }
     }
 
+    pub fn backfill(
+        &mut self,
+        kind: &history::Kind,
+        messages: Vec<data::Message>,
+    ) -> Task<Message> {
+        if let Some(task) = self.history.backfill(kind, messages) {
+            Task::perform(task, Message::History)
+        } else {
+            Task::none()
+        }
+    }
+
     pub fn channel_joined(&mut self, server: Server, channel: String) -> Task<Message> {
         if let Some(task) = self.history.channel_joined(server, channel) {
             Task::perform(task, Message::History)
@@ -1411,8 +1992,23 @@ impl Dashboard {
         pane: pane_grid::Pane,
     ) -> Task<Message> {
         if self.focus != Some((window, pane)) {
+            let unfocused = self
+                .focus
+                .and_then(|(window, pane)| self.panes.get(main_window.id, window, pane))
+                .and_then(|state| state.buffer.upstream())
+                .cloned();
+
+            if let Some(buffer) = unfocused {
+                self.history
+                    .unfocused(&history::Kind::from_input_buffer(buffer));
+            }
+
             self.focus = Some((window, pane));
 
+            if window == main_window.id {
+                self.main_focus = Some(pane);
+            }
+
             if let Some(task) = self.panes.iter(main_window.id).find_map(|(w, p, state)| {
                 (w == window && p == pane).then(|| {
                     state.buffer.focus().map(move |message| {
@@ -1552,7 +2148,9 @@ impl Dashboard {
                 .and_then(|panes| panes.get(pane).cloned())
             {
                 let task = match pane.buffer.data() {
-                    Some(buffer) => self.open_buffer(main_window, buffer, pane.settings),
+                    Some(buffer) => {
+                        self.open_buffer_with_settings(main_window, buffer, pane.settings)
+                    }
                     None => self.new_pane(pane_grid::Axis::Horizontal, config, main_window),
                 };
 
@@ -1575,12 +2173,46 @@ impl Dashboard {
         )
     }
 
+    pub fn run_timers(
+        &mut self,
+        timers: Vec<(buffer::Upstream, String)>,
+        clients: &mut client::Map,
+        config: &Config,
+    ) -> Task<Message> {
+        Task::batch(timers.into_iter().filter_map(|(upstream, command)| {
+            let line_len = clients.get_line_len(upstream.server());
+            let statusmsg = clients.get_statusmsg(upstream.server()).to_vec();
+
+            match data::input::parse(
+                upstream,
+                config.buffer.text_input.auto_format,
+                &command,
+                line_len,
+                &statusmsg,
+            ) {
+                Ok(input) => Some(
+                    buffer::input_view::send(input, clients, &mut self.history)
+                        .map(Message::History),
+                ),
+                Err(error) => {
+                    log::warn!("failed to run timer command {command:?}: {error}");
+                    None
+                }
+            }
+        }))
+    }
+
     pub fn tick(&mut self, now: Instant) -> Task<Message> {
         let history = Task::batch(
             self.history
                 .tick(now.into())
                 .into_iter()
                 .map(|task| Task::perform(task, Message::History))
+                .chain(
+                    self.file_transfers
+                        .tick(now)
+                        .map(|save| Task::perform(save, Message::FileTransfersSaved)),
+                )
                 .collect::<Vec<_>>(),
         );
 
@@ -1637,6 +2269,7 @@ impl Dashboard {
             config,
             self.focus,
             self.buffer_resize_action(),
+            &self.layouts.keys().cloned().collect::<Vec<_>>(),
             main_window.id,
         ));
     }
@@ -1655,12 +2288,15 @@ impl Dashboard {
         server: &Server,
         request: file_transfer::ReceiveRequest,
         config: &Config,
+        do_not_disturb: bool,
     ) -> Option<Task<Message>> {
         if let Some(event) = self
             .file_transfers
             .receive(request.clone(), config.proxy.as_ref())
         {
-            notification::file_transfer_request(&config.notifications, request.from, server);
+            if !do_not_disturb {
+                notification::file_transfer_request(&config.notifications, request.from, server);
+            }
 
             return Some(self.handle_file_transfer_event(server, event));
         }
@@ -1668,6 +2304,55 @@ impl Dashboard {
         None
     }
 
+    pub fn resume_file_transfer(&mut self, from: &Nick, resume: dcc::Resume) {
+        self.file_transfers.resume(from, resume);
+    }
+
+    pub fn accept_file_transfer(&mut self, from: &Nick, accept: dcc::Resume) {
+        self.file_transfers.accept(from, accept);
+    }
+
+    pub fn verify_file_transfer(&mut self, from: &Nick, filename: &str, sha256: String) {
+        self.file_transfers.checksum(from, filename, sha256);
+    }
+
+    pub fn set_file_transfer_rate_limit(&mut self, rate_limit: config::file_transfer::RateLimit) {
+        self.file_transfers.set_rate_limit(rate_limit);
+    }
+
+    pub fn update_channel_settings(&mut self, servers: &data::server::Map) {
+        self.history.update_logging(servers);
+        self.history.update_filters(servers);
+    }
+
+    fn send_file(
+        &mut self,
+        server: Server,
+        to: Nick,
+        path: PathBuf,
+        clients: &client::Map,
+        config: &Config,
+    ) -> Task<Message> {
+        let Some(server_handle) = clients.get_server_handle(&server) else {
+            return Task::none();
+        };
+
+        if let Some(event) = self.file_transfers.send(
+            file_transfer::SendRequest {
+                to,
+                path,
+                server: server.clone(),
+                server_handle: server_handle.clone(),
+                reported_host: clients.get_reported_host(&server).map(ToString::to_string),
+            },
+            config.proxy.clone(),
+        ) {
+            self.handle_file_transfer_event(&server, event)
+        } else {
+            Task::none()
+        }
+    }
+
     pub fn handle_file_transfer_event(
         &mut self,
         server: &Server,
@@ -1710,48 +2395,33 @@ impl Dashboard {
         config: &Config,
         main_window: &Window,
     ) -> (Self, Task<Message>) {
-        use pane_grid::Configuration;
-
-        fn configuration(pane: data::Pane) -> Configuration<Pane> {
-            match pane {
-                data::Pane::Split { axis, ratio, a, b } => Configuration::Split {
-                    axis: match axis {
-                        data::pane::Axis::Horizontal => pane_grid::Axis::Horizontal,
-                        data::pane::Axis::Vertical => pane_grid::Axis::Vertical,
-                    },
-                    ratio,
-                    a: Box::new(configuration(*a)),
-                    b: Box::new(configuration(*b)),
-                },
-                data::Pane::Buffer { buffer, settings } => {
-                    Configuration::Pane(Pane::with_settings(Buffer::from(buffer), settings))
-                }
-                data::Pane::Empty => Configuration::Pane(Pane::with_settings(
-                    Buffer::empty(),
-                    buffer::Settings::default(),
-                )),
-            }
-        }
-
         let mut dashboard = Self {
             panes: Panes {
                 main: pane_grid::State::with_configuration(configuration(data.pane)),
                 popout: HashMap::new(),
             },
             focus: None,
+            main_focus: None,
+            focused_popout: None,
             side_menu: Sidebar::new(),
             history: history::Manager::default(),
             last_changed: None,
             command_bar: None,
-            file_transfers: file_transfer::Manager::new(config.file_transfer.clone()),
+            file_transfers: file_transfer::Manager::load(config.file_transfer.clone()),
             theme_editor: None,
+            layouts: data.layouts,
+            zoom: data.zoom.into_iter().collect(),
+            vim_mode: data::shortcut::Mode::default(),
         };
 
+        dashboard.history.update_logging(&config.servers);
+        dashboard.history.update_filters(&config.servers);
+
         let mut tasks = vec![];
 
         for pane in data.popout_panes {
             // Popouts are only a single pane
-            let Configuration::Pane(pane) = configuration(pane) else {
+            let pane_grid::Configuration::Pane(pane) = configuration(pane) else {
                 continue;
             };
 
@@ -1765,22 +2435,107 @@ impl Dashboard {
         &self.history
     }
 
+    /// Whether `buffer` is open in the focused pane of the main window.
+    /// Opens or focuses the buffer, raising the window it's shown in.
+    pub fn focus_buffer(
+        &mut self,
+        main_window: &Window,
+        buffer: buffer::Upstream,
+        config: &Config,
+    ) -> Task<Message> {
+        let task = self.open_buffer(main_window, data::Buffer::Upstream(buffer), config);
+        let window = self.focus.map_or(main_window.id, |(window, _)| window);
+
+        Task::batch(vec![task, window::gain_focus(window)])
+    }
+
+    /// Whether any of our windows holds the OS focus.
+    pub fn is_window_focused(&self, main_window: &Window) -> bool {
+        main_window.focused
+            || self
+                .focused_popout
+                .is_some_and(|window| self.panes.popout.contains_key(&window))
+    }
+
+    /// Whether `buffer` is focused in the window holding the OS focus.
+    pub fn is_focused_buffer(&self, main_window: &Window, buffer: &buffer::Upstream) -> bool {
+        let focused_window = if main_window.focused {
+            main_window.id
+        } else if let Some(window) = self.focused_popout {
+            window
+        } else {
+            return false;
+        };
+
+        self.focus.is_some_and(|(window, pane)| {
+            window == focused_window
+                && self
+                    .panes
+                    .get(main_window.id, window, pane)
+                    .and_then(|state| state.buffer.upstream())
+                    == Some(buffer)
+        })
+    }
+
+    /// Moves focus back to the main window's last focused pane when the main
+    /// window regains OS focus from a popout.
+    pub fn main_window_focused(&mut self, main_window: &Window) -> Task<Message> {
+        self.focused_popout = None;
+
+        if !self
+            .focus
+            .is_some_and(|(window, _)| window != main_window.id)
+        {
+            return Task::none();
+        }
+
+        let pane = self
+            .main_focus
+            .filter(|pane| self.panes.main.get(*pane).is_some())
+            .or_else(|| self.panes.main.iter().next().map(|(pane, _)| *pane));
+
+        if let Some(pane) = pane {
+            self.focus_pane(main_window, main_window.id, pane)
+        } else {
+            Task::none()
+        }
+    }
+
     pub fn handle_window_event(
         &mut self,
         id: window::Id,
         event: window::Event,
         theme: &mut Theme,
+        main_window: &Window,
     ) -> Task<Message> {
-        if self.panes.popout.contains_key(&id) {
+        if let Some(panes) = self.panes.popout.get(&id) {
             match event {
                 window::Event::CloseRequested => {
                     self.panes.popout.remove(&id);
+
+                    if self.focused_popout == Some(id) {
+                        self.focused_popout = None;
+                    }
+
                     return window::close(id);
                 }
+                window::Event::Focused => {
+                    self.focused_popout = Some(id);
+
+                    // Popouts are only a single pane
+                    let pane = panes.iter().next().map(|(pane, _)| *pane);
+
+                    if let Some(pane) = pane {
+                        return self.focus_pane(main_window, id, pane);
+                    }
+                }
+                window::Event::Unfocused => {
+                    if self.focused_popout == Some(id) {
+                        self.focused_popout = None;
+                    }
+                }
                 window::Event::Moved(_)
                 | window::Event::Resized(_)
-                | window::Event::Focused
-                | window::Event::Unfocused
                 | window::Event::Opened { .. } => {}
             }
         } else if self
@@ -1830,6 +2585,7 @@ impl Dashboard {
         let history = self.history.exit();
         let last_changed = self.last_changed.take();
         let dashboard = data::Dashboard::from(&*self);
+        let file_transfers = self.file_transfers.exit();
 
         Task::perform(
             async move {
@@ -1844,6 +2600,12 @@ impl Dashboard {
                     }
                 }
 
+                if let Some(file_transfers) = file_transfers {
+                    if let Err(error) = file_transfers.await {
+                        log::warn!("error saving file transfers: {error}");
+                    }
+                }
+
                 history.await
             },
             Message::History,
@@ -1902,40 +2664,13 @@ impl Dashboard {
         if let Some((window, pane)) = matching_pane {
             self.focus_pane(main_window, window, pane)
         } else {
-            self.open_buffer(
-                main_window,
-                data::Buffer::Upstream(buffer),
-                config.buffer.clone().into(),
-            )
+            self.open_buffer(main_window, data::Buffer::Upstream(buffer), config)
         }
     }
 }
 
 impl<'a> From<&'a Dashboard> for data::Dashboard {
     fn from(dashboard: &'a Dashboard) -> Self {
-        use pane_grid::Node;
-
-        fn from_layout(panes: &pane_grid::State<Pane>, node: pane_grid::Node) -> data::Pane {
-            match node {
-                Node::Split {
-                    axis, ratio, a, b, ..
-                } => data::Pane::Split {
-                    axis: match axis {
-                        pane_grid::Axis::Horizontal => data::pane::Axis::Horizontal,
-                        pane_grid::Axis::Vertical => data::pane::Axis::Vertical,
-                    },
-                    ratio,
-                    a: Box::new(from_layout(panes, *a)),
-                    b: Box::new(from_layout(panes, *b)),
-                },
-                Node::Pane(pane) => panes
-                    .get(pane)
-                    .cloned()
-                    .map(data::Pane::from)
-                    .unwrap_or(data::Pane::Empty),
-            }
-        }
-
         let layout = dashboard.panes.main.layout().clone();
 
         data::Dashboard {
@@ -1946,10 +2681,62 @@ impl<'a> From<&'a Dashboard> for data::Dashboard {
                 .values()
                 .map(|state| from_layout(state, state.layout().clone()))
                 .collect(),
+            layouts: dashboard.layouts.clone(),
+            zoom: dashboard
+                .zoom
+                .iter()
+                .map(|(buffer, zoom)| (buffer.clone(), *zoom))
+                .collect(),
         }
     }
 }
 
+fn configuration(pane: data::Pane) -> pane_grid::Configuration<Pane> {
+    use pane_grid::Configuration;
+
+    match pane {
+        data::Pane::Split { axis, ratio, a, b } => Configuration::Split {
+            axis: match axis {
+                data::pane::Axis::Horizontal => pane_grid::Axis::Horizontal,
+                data::pane::Axis::Vertical => pane_grid::Axis::Vertical,
+            },
+            ratio,
+            a: Box::new(configuration(*a)),
+            b: Box::new(configuration(*b)),
+        },
+        data::Pane::Buffer { buffer, settings } => {
+            Configuration::Pane(Pane::with_settings(Buffer::from(buffer), settings))
+        }
+        data::Pane::Empty => Configuration::Pane(Pane::with_settings(
+            Buffer::empty(),
+            buffer::Settings::default(),
+        )),
+    }
+}
+
+fn from_layout(panes: &pane_grid::State<Pane>, node: pane_grid::Node) -> data::Pane {
+    use pane_grid::Node;
+
+    match node {
+        Node::Split {
+            axis, ratio, a, b, ..
+        } => data::Pane::Split {
+            axis: match axis {
+                pane_grid::Axis::Horizontal => data::pane::Axis::Horizontal,
+                pane_grid::Axis::Vertical => data::pane::Axis::Vertical,
+            },
+            ratio,
+            a: Box::new(from_layout(panes, *a)),
+            b: Box::new(from_layout(panes, *b)),
+        },
+        Node::Pane(pane) => panes
+            .get(pane)
+            .cloned()
+            .map(data::Pane::from)
+            .unwrap_or(data::Pane::Empty),
+    }
+}
+
 #[derive(Clone)]
 pub struct Panes {
     main: pane_grid::State<Pane>,
@@ -2033,6 +2820,16 @@ impl Panes {
     }
 }
 
+/// Text of a message as copied by yanking it.
+fn yank(message: &data::Message) -> String {
+    match message.target.source() {
+        data::message::Source::User(user) => {
+            format!("<{}> {}", user.nickname(), message.content.text())
+        }
+        _ => message.content.text().into_owned(),
+    }
+}
+
 fn all_buffers(clients: &client::Map, history: &history::Manager) -> Vec<buffer::Upstream> {
     clients
         .connected_servers()
@@ -25,6 +25,7 @@ impl CommandBar {
         config: &Config,
         focus: Option<(window::Id, pane_grid::Pane)>,
         resize_buffer: data::buffer::Resize,
+        layouts: &[String],
         main_window: window::Id,
     ) -> Self {
         let state = combo_box::State::new(Command::list(
@@ -32,6 +33,7 @@ impl CommandBar {
             config,
             focus,
             resize_buffer,
+            layouts,
             version,
             main_window,
         ));
@@ -57,6 +59,7 @@ impl CommandBar {
         buffers: &[buffer::Upstream],
         focus: Option<(window::Id, pane_grid::Pane)>,
         resize_buffer: data::buffer::Resize,
+        layouts: &[String],
         version: &data::Version,
         config: &'a Config,
         main_window: window::Id,
@@ -84,9 +87,17 @@ impl CommandBar {
             column(
                 std::iter::once(text("Type a command...").size(font_size))
                     .chain(
-                        Command::list(buffers, config, focus, resize_buffer, version, main_window)
-                            .iter()
-                            .map(|command| text(command.to_string()).size(font_size)),
+                        Command::list(
+                            buffers,
+                            config,
+                            focus,
+                            resize_buffer,
+                            layouts,
+                            version,
+                            main_window,
+                        )
+                        .iter()
+                        .map(|command| text(command.to_string()).size(font_size)),
                     )
                     .map(Element::from),
             )
@@ -113,6 +124,7 @@ pub enum Command {
     Configuration(Configuration),
     UI(Ui),
     Theme(Theme),
+    Layout(Layout),
 }
 
 #[derive(Debug, Clone)]
@@ -138,6 +150,11 @@ pub enum Configuration {
     OpenWebsite,
 }
 
+#[derive(Debug, Clone)]
+pub enum Layout {
+    Load(String),
+}
+
 #[derive(Debug, Clone)]
 pub enum Ui {
     ToggleSidebarVisibility,
@@ -147,6 +164,7 @@ pub enum Ui {
 pub enum Theme {
     Switch(data::Theme),
     OpenEditor,
+    ImportBase16,
 }
 
 impl Command {
@@ -155,6 +173,7 @@ impl Command {
         config: &Config,
         focus: Option<(window::Id, pane_grid::Pane)>,
         resize_buffer: data::buffer::Resize,
+        layouts: &[String],
         version: &data::Version,
         main_window: window::Id,
     ) -> Vec<Self> {
@@ -166,6 +185,8 @@ impl Command {
             .into_iter()
             .map(Command::Configuration);
 
+        let layouts = Layout::list(layouts).into_iter().map(Command::Layout);
+
         let uis = Ui::list().into_iter().map(Command::UI);
 
         let themes = Theme::list(config).into_iter().map(Command::Theme);
@@ -174,6 +195,7 @@ impl Command {
 
         version
             .chain(buffers)
+            .chain(layouts)
             .chain(configs)
             .chain(themes)
             .chain(uis)
@@ -189,6 +211,7 @@ impl std::fmt::Display for Command {
             Command::UI(ui) => write!(f, "UI: {}", ui),
             Command::Theme(theme) => write!(f, "Theme: {}", theme),
             Command::Version(application) => write!(f, "Version: {}", application),
+            Command::Layout(layout) => write!(f, "Layout: {}", layout),
         }
     }
 }
@@ -246,6 +269,12 @@ impl Configuration {
     }
 }
 
+impl Layout {
+    fn list(layouts: &[String]) -> Vec<Self> {
+        layouts.iter().cloned().map(Layout::Load).collect()
+    }
+}
+
 impl Ui {
     fn list() -> Vec<Self> {
         vec![Ui::ToggleSidebarVisibility]
@@ -254,7 +283,7 @@ impl Ui {
 
 impl Theme {
     fn list(config: &Config) -> Vec<Self> {
-        Some(Self::OpenEditor)
+        [Self::OpenEditor, Self::ImportBase16]
             .into_iter()
             .chain(config.appearance.all.iter().cloned().map(Self::Switch))
             .collect()
@@ -318,6 +347,14 @@ impl std::fmt::Display for Configuration {
     }
 }
 
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layout::Load(name) => write!(f, "Load {name}"),
+        }
+    }
+}
+
 impl std::fmt::Display for Ui {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -331,6 +368,7 @@ impl std::fmt::Display for Theme {
         match self {
             Theme::Switch(theme) => write!(f, "Switch to {}", theme.name),
             Theme::OpenEditor => write!(f, "Open editor"),
+            Theme::ImportBase16 => write!(f, "Import base16 scheme"),
         }
     }
 }
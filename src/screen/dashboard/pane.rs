@@ -19,9 +19,12 @@ pub enum Message {
     MaximizePane,
     ToggleShowUserList,
     ToggleShowTopic,
+    ToggleSmartFilter,
+    ToggleFiltered,
     Popout,
     Merge,
     ScrollToBottom,
+    Zoom(pane_grid::Pane, bool),
 }
 
 #[derive(Clone)]
@@ -36,7 +39,12 @@ pub struct TitleBar {}
 
 impl Pane {
     pub fn new(buffer: Buffer, config: &Config) -> Self {
-        Self::with_settings(buffer, buffer::Settings::from(config.buffer.clone()))
+        let settings = match buffer.data() {
+            Some(buffer) => buffer::Settings::new(&buffer, config),
+            None => buffer::Settings::from(config.buffer.clone()),
+        };
+
+        Self::with_settings(buffer, settings)
     }
 
     pub fn with_settings(buffer: Buffer, settings: buffer::Settings) -> Self {
@@ -54,6 +62,7 @@ impl Pane {
         panes: usize,
         is_focused: bool,
         maximized: bool,
+        zoom: data::buffer::Zoom,
         clients: &'a data::client::Map,
         file_transfers: &'a file_transfer::Manager,
         history: &'a history::Manager,
@@ -85,6 +94,11 @@ impl Pane {
             Buffer::FileTransfers(_) => "File Transfers".to_string(),
             Buffer::Logs(_) => "Logs".to_string(),
             Buffer::Highlights(_) => "Highlights".to_string(),
+            Buffer::Urls(_) => "URLs".to_string(),
+            Buffer::ModeLists(state) => {
+                format!("Mode lists @ {} ({})", state.channel, state.server)
+            }
+            Buffer::Console(state) => format!("Console @ {}", state.server),
         };
 
         let title_bar = self.title_bar.view(
@@ -96,23 +110,30 @@ impl Pane {
             maximized,
             clients,
             &self.settings,
+            config.buffer.server_messages.has_smart(),
+            self.resource()
+                .map(|resource| history.filtered(&resource.kind))
+                .unwrap_or_default(),
             config.tooltips,
             is_popout,
         );
 
-        let content = self
-            .buffer
-            .view(
-                clients,
-                file_transfers,
-                history,
-                &self.settings,
-                config,
-                theme,
-                is_focused,
-                sidebar,
-            )
-            .map(move |msg| Message::Buffer(id, msg));
+        let content = widget::zoom(
+            self.buffer
+                .view(
+                    clients,
+                    file_transfers,
+                    history,
+                    &self.settings,
+                    config,
+                    theme,
+                    is_focused,
+                    sidebar,
+                )
+                .map(move |msg| Message::Buffer(id, msg)),
+            zoom.scale(),
+        )
+        .on_scroll(move |zoom_in| Message::Zoom(id, zoom_in));
 
         widget::Content::new(content)
             .style(move |theme| theme::container::buffer(theme, is_focused))
@@ -134,6 +155,9 @@ impl Pane {
             Buffer::FileTransfers(_) => None,
             Buffer::Logs(_) => Some(history::Resource::logs()),
             Buffer::Highlights(_) => Some(history::Resource::highlights()),
+            Buffer::Urls(_) => None,
+            Buffer::ModeLists(_) => None,
+            Buffer::Console(_) => None,
         }
     }
 
@@ -153,6 +177,8 @@ impl TitleBar {
         maximized: bool,
         clients: &'a data::client::Map,
         settings: &'a buffer::Settings,
+        has_smart_filter: bool,
+        (filtered, revealed): (usize, bool),
         show_tooltips: bool,
         is_popout: bool,
     ) -> widget::TitleBar<'a, Message> {
@@ -200,6 +226,51 @@ impl TitleBar {
                 }
             }
 
+            // Show smart filter button only if any server message is filtered
+            if has_smart_filter {
+                let smart_filter_button = button(center(icon::megaphone()))
+                    .padding(5)
+                    .width(22)
+                    .height(22)
+                    .on_press(Message::ToggleSmartFilter)
+                    .style(|theme, status| {
+                        theme::button::secondary(
+                            theme,
+                            status,
+                            settings.channel.smart_filter.enabled,
+                        )
+                    });
+
+                let smart_filter_button_with_tooltip = tooltip(
+                    smart_filter_button,
+                    show_tooltips.then_some("Smart Filter"),
+                    tooltip::Position::Bottom,
+                );
+
+                controls = controls.push(smart_filter_button_with_tooltip);
+            }
+
+            // Show how many messages the channel's filters hide, if any
+            if filtered > 0 {
+                let filtered_button = button(center(text(filtered.to_string()).size(10)))
+                    .padding(5)
+                    .height(22)
+                    .on_press(Message::ToggleFiltered)
+                    .style(move |theme, status| theme::button::secondary(theme, status, revealed));
+
+                let filtered_button_with_tooltip = tooltip(
+                    filtered_button,
+                    show_tooltips.then_some(if revealed {
+                        "Hide Filtered Messages"
+                    } else {
+                        "Show Filtered Messages"
+                    }),
+                    tooltip::Position::Bottom,
+                );
+
+                controls = controls.push(filtered_button_with_tooltip);
+            }
+
             let nicklist_button = button(center(icon::people()))
                 .padding(5)
                 .width(22)
@@ -328,6 +399,14 @@ impl From<Pane> for data::Pane {
             Buffer::FileTransfers(_) => data::Buffer::Internal(buffer::Internal::FileTransfers),
             Buffer::Logs(_) => data::Buffer::Internal(buffer::Internal::Logs),
             Buffer::Highlights(_) => data::Buffer::Internal(buffer::Internal::Highlights),
+            Buffer::Urls(_) => data::Buffer::Internal(buffer::Internal::Urls),
+            Buffer::ModeLists(state) => data::Buffer::ModeLists {
+                server: state.server,
+                channel: state.channel,
+            },
+            Buffer::Console(state) => data::Buffer::Console {
+                server: state.server,
+            },
         };
 
         data::Pane::Buffer {
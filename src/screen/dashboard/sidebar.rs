@@ -26,8 +26,12 @@ pub enum Message {
     Swap(window::Id, pane_grid::Pane, window::Id, pane_grid::Pane),
     Leave(buffer::Upstream),
     ToggleInternalBuffer(buffer::Internal),
+    ToggleConsole(data::Server),
+    ToggleModeLists(data::Server, String),
     ToggleCommandBar,
     ToggleThemeEditor,
+    ToggleDoNotDisturb,
+    NextHighlight,
     ReloadingConfigFile,
     ConfigReloaded(Result<Config, config::Error>),
     OpenReleaseWebsite,
@@ -46,8 +50,12 @@ pub enum Event {
     Swap(window::Id, pane_grid::Pane, window::Id, pane_grid::Pane),
     Leave(buffer::Upstream),
     ToggleInternalBuffer(buffer::Internal),
+    ToggleConsole(data::Server),
+    ToggleModeLists(data::Server, String),
     ToggleCommandBar,
     ToggleThemeEditor,
+    ToggleDoNotDisturb,
+    NextHighlight,
     OpenReleaseWebsite,
     OpenDocumentation,
     ConfigReloaded(Result<Config, config::Error>),
@@ -94,8 +102,14 @@ impl Sidebar {
             Message::ToggleInternalBuffer(buffer) => {
                 (Task::none(), Some(Event::ToggleInternalBuffer(buffer)))
             }
+            Message::ToggleConsole(server) => (Task::none(), Some(Event::ToggleConsole(server))),
+            Message::ToggleModeLists(server, channel) => {
+                (Task::none(), Some(Event::ToggleModeLists(server, channel)))
+            }
             Message::ToggleCommandBar => (Task::none(), Some(Event::ToggleCommandBar)),
             Message::ToggleThemeEditor => (Task::none(), Some(Event::ToggleThemeEditor)),
+            Message::ToggleDoNotDisturb => (Task::none(), Some(Event::ToggleDoNotDisturb)),
+            Message::NextHighlight => (Task::none(), Some(Event::NextHighlight)),
             Message::ReloadingConfigFile => {
                 self.reloading_config = true;
                 (Task::perform(Config::load(), Message::ConfigReloaded), None)
@@ -121,8 +135,18 @@ impl Sidebar {
         keyboard: &'a data::config::Keyboard,
         file_transfers: &'a file_transfer::Manager,
         version: &'a Version,
+        do_not_disturb: bool,
+        unread_highlights: usize,
+        unread_log_warnings: usize,
     ) -> Element<'a, Message> {
-        let base = button(icon::menu())
+        // Stands out until warnings and errors in the logs are read
+        let icon = icon::menu().style(if unread_log_warnings == 0 {
+            theme::text::primary
+        } else {
+            theme::text::tertiary
+        });
+
+        let base = button(icon)
             .padding(5)
             .width(Length::Shrink)
             .on_press(Message::Noop);
@@ -192,8 +216,41 @@ impl Sidebar {
                             icon::highlights(),
                             Message::ToggleInternalBuffer(buffer::Internal::Highlights),
                         ),
+                        Menu::Urls => context_button(
+                            text("URLs"),
+                            None,
+                            icon::copy(),
+                            Message::ToggleInternalBuffer(buffer::Internal::Urls),
+                        ),
+                        Menu::DoNotDisturb => context_button(
+                            text("Do Not Disturb"),
+                            None,
+                            if do_not_disturb {
+                                icon::checkmark()
+                            } else {
+                                text("")
+                            },
+                            Message::ToggleDoNotDisturb,
+                        ),
+                        Menu::NextHighlight => context_button(
+                            text(format!("Next Highlight ({unread_highlights})")).style(
+                                if unread_highlights == 0 {
+                                    theme::text::primary
+                                } else {
+                                    theme::text::tertiary
+                                },
+                            ),
+                            None,
+                            icon::highlights(),
+                            Message::NextHighlight,
+                        ),
                         Menu::Logs => context_button(
-                            text("Logs"),
+                            if unread_log_warnings == 0 {
+                                text("Logs")
+                            } else {
+                                text(format!("Logs ({unread_log_warnings})"))
+                                    .style(theme::text::tertiary)
+                            },
                             Some(&keyboard.logs),
                             icon::logs(),
                             Message::ToggleInternalBuffer(buffer::Internal::Logs),
@@ -251,15 +308,24 @@ impl Sidebar {
             return None;
         }
 
-        let user_menu_button = config
-            .show_user_menu
-            .then(|| self.user_menu_button(keyboard, file_transfers, version));
+        let user_menu_button = config.show_user_menu.then(|| {
+            self.user_menu_button(
+                keyboard,
+                file_transfers,
+                version,
+                clients.is_do_not_disturb(),
+                history.unread_counts().1,
+                history.unread_log_warnings(),
+            )
+        });
 
         let mut buffers = vec![];
 
         for (i, (server, state)) in clients.iter().enumerate() {
             match state {
-                data::client::State::Disconnected => {
+                data::client::State::Disconnected
+                | data::client::State::Stopped
+                | data::client::State::Quitting { .. } => {
                     buffers.push(upstream_buffer_button(
                         main_window,
                         panes,
@@ -401,7 +467,10 @@ enum Menu {
     ThemeEditor,
     Highlights,
     Logs,
+    Urls,
     FileTransfers,
+    NextHighlight,
+    DoNotDisturb,
     Version,
     HorizontalRule,
     Documentation,
@@ -415,7 +484,10 @@ impl Menu {
             Menu::CommandBar,
             Menu::FileTransfers,
             Menu::Highlights,
+            Menu::NextHighlight,
+            Menu::Urls,
             Menu::Logs,
+            Menu::DoNotDisturb,
             Menu::RefreshConfig,
             Menu::ThemeEditor,
             Menu::Documentation,
@@ -430,6 +502,8 @@ enum Entry {
     Replace(window::Id, pane_grid::Pane),
     Close(window::Id, pane_grid::Pane),
     Swap(window::Id, pane_grid::Pane, window::Id, pane_grid::Pane),
+    Console { open: bool },
+    ModeLists { open: bool },
     Leave,
 }
 
@@ -604,7 +678,31 @@ fn upstream_buffer_button(
             }
         });
 
-    let entries = Entry::list(panes.len(), open, focus);
+    let mut entries = Entry::list(panes.len(), open, focus);
+
+    if let buffer::Upstream::Server(server) = &buffer {
+        let console = Some(data::Buffer::Console {
+            server: server.clone(),
+        });
+        let open = panes
+            .iter(main_window)
+            .any(|(_, _, state)| state.buffer.data() == console);
+
+        // Before leave, which is always last
+        entries.insert(entries.len().saturating_sub(1), Entry::Console { open });
+    }
+
+    if let buffer::Upstream::Channel(server, channel) = &buffer {
+        let mode_lists = Some(data::Buffer::ModeLists {
+            server: server.clone(),
+            channel: channel.clone(),
+        });
+        let open = panes
+            .iter(main_window)
+            .any(|(_, _, state)| state.buffer.data() == mode_lists);
+
+        entries.insert(entries.len().saturating_sub(1), Entry::ModeLists { open });
+    }
 
     if entries.is_empty() || !connected {
         base.into()
@@ -622,6 +720,25 @@ fn upstream_buffer_button(
                     "Swap with current pane",
                     Message::Swap(from_window, from_pane, to_window, to_pane),
                 ),
+                Entry::Console { open } => (
+                    if open {
+                        "Close console"
+                    } else {
+                        "Open console"
+                    },
+                    Message::ToggleConsole(buffer.server().clone()),
+                ),
+                Entry::ModeLists { open } => (
+                    if open {
+                        "Close mode lists"
+                    } else {
+                        "Open mode lists"
+                    },
+                    Message::ToggleModeLists(
+                        buffer.server().clone(),
+                        buffer.channel().unwrap_or_default().to_string(),
+                    ),
+                ),
                 Entry::Leave => (
                     match &buffer {
                         buffer::Upstream::Server(_) => "Leave server",
@@ -12,10 +12,12 @@ pub use self::double_pass::double_pass;
 pub use self::key_press::key_press;
 pub use self::message_content::message_content;
 pub use self::modal::modal;
+pub use self::paste::paste;
 pub use self::selectable_rich_text::selectable_rich_text;
 pub use self::selectable_text::selectable_text;
 pub use self::shortcut::shortcut;
 pub use self::tooltip::tooltip;
+pub use self::zoom::zoom;
 
 pub mod anchored_overlay;
 pub mod collection;
@@ -28,10 +30,12 @@ pub mod double_pass;
 pub mod key_press;
 pub mod message_content;
 pub mod modal;
+pub mod paste;
 pub mod selectable_rich_text;
 pub mod selectable_text;
 pub mod shortcut;
 pub mod tooltip;
+pub mod zoom;
 
 pub type Renderer = iced::Renderer;
 pub type Element<'a, Message> = iced::Element<'a, Message, Theme, Renderer>;
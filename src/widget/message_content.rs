@@ -1,8 +1,9 @@
 use data::appearance::theme::randomize_color;
+use data::config::buffer::Render;
 use data::user::NickColor;
 use data::{message, Config};
 use iced::widget::span;
-use iced::widget::text::Span;
+use iced::widget::text::{self, Span};
 use iced::{border, Length};
 
 use crate::{font, Theme};
@@ -14,6 +15,7 @@ pub fn message_content<'a, M: 'a>(
     theme: &'a Theme,
     on_link: impl Fn(message::Link) -> M + 'a,
     style: impl Fn(&Theme) -> selectable_text::Style + 'a,
+    render: Render,
     config: &Config,
 ) -> Element<'a, M> {
     message_content_impl::<(), M>(
@@ -22,6 +24,7 @@ pub fn message_content<'a, M: 'a>(
         on_link,
         style,
         Option::<(fn(&message::Link) -> _, fn(&message::Link, _, _) -> _)>::None,
+        render,
         config,
     )
 }
@@ -33,6 +36,7 @@ pub fn with_context<'a, T: Copy + 'a, M: 'a>(
     style: impl Fn(&Theme) -> selectable_text::Style + 'a,
     link_entries: impl Fn(&message::Link) -> Vec<T> + 'a,
     entry: impl Fn(&message::Link, T, Length) -> Element<'a, M> + 'a,
+    render: Render,
     config: &Config,
 ) -> Element<'a, M> {
     message_content_impl(
@@ -41,6 +45,7 @@ pub fn with_context<'a, T: Copy + 'a, M: 'a>(
         on_link,
         style,
         Some((link_entries, entry)),
+        render,
         config,
     )
 }
@@ -55,6 +60,7 @@ fn message_content_impl<'a, T: Copy + 'a, M: 'a>(
         impl Fn(&message::Link) -> Vec<T> + 'a,
         impl Fn(&message::Link, T, Length) -> Element<'a, M> + 'a,
     )>,
+    render: Render,
     config: &Config,
 ) -> Element<'a, M> {
     match content {
@@ -87,45 +93,10 @@ fn message_content_impl<'a, T: Copy + 'a, M: 'a>(
                             .color(theme.colors().buffer.url)
                             .link(message::Link::Url(s.as_str().to_string())),
                         data::message::Fragment::Formatted { text, formatting } => {
-                            let mut span = span(text)
-                                .color_maybe(
-                                    formatting
-                                        .fg
-                                        .and_then(|color| color.into_iced(theme.colors())),
-                                )
-                                .background_maybe(
-                                    formatting
-                                        .bg
-                                        .and_then(|color| color.into_iced(theme.colors())),
-                                )
-                                .underline(formatting.underline)
-                                .strikethrough(formatting.strikethrough);
-
-                            if formatting.monospace {
-                                span = span
-                                    .padding([0, 4])
-                                    .color(theme.colors().buffer.code)
-                                    .border(
-                                        border::rounded(3)
-                                            .color(theme.colors().general.border)
-                                            .width(1),
-                                    );
+                            match render.apply(*formatting) {
+                                Some(formatting) => formatted_span(text, &formatting, theme),
+                                None => span(text),
                             }
-
-                            match (formatting.bold, formatting.italics) {
-                                (true, true) => {
-                                    span = span.font(font::MONO_BOLD_ITALICS.clone());
-                                }
-                                (true, false) => {
-                                    span = span.font(font::MONO_BOLD.clone());
-                                }
-                                (false, true) => {
-                                    span = span.font(font::MONO_ITALICS.clone());
-                                }
-                                (false, false) => {}
-                            }
-
-                            span
                         }
                     })
                     .collect::<Vec<_>>(),
@@ -161,3 +132,42 @@ fn message_content_impl<'a, T: Copy + 'a, M: 'a>(
         }
     }
 }
+
+/// A span of `text` styled with its IRC `formatting`.
+pub fn formatted_span<'a, Link>(
+    text: impl text::IntoFragment<'a>,
+    formatting: &message::Formatting,
+    theme: &Theme,
+) -> Span<'a, Link> {
+    let mut span = span(text)
+        .color_maybe(
+            formatting
+                .fg
+                .and_then(|color| color.into_iced(theme.colors())),
+        )
+        .background_maybe(
+            formatting
+                .bg
+                .and_then(|color| color.into_iced(theme.colors())),
+        )
+        .underline(formatting.underline)
+        .strikethrough(formatting.strikethrough);
+
+    if formatting.monospace {
+        span = span
+            .padding([0, 4])
+            .color(theme.colors().buffer.code)
+            .border(
+                border::rounded(3)
+                    .color(theme.colors().general.border)
+                    .width(1),
+            );
+    }
+
+    match (formatting.bold, formatting.italics) {
+        (true, true) => span.font(font::MONO_BOLD_ITALICS.clone()),
+        (true, false) => span.font(font::MONO_BOLD.clone()),
+        (false, true) => span.font(font::MONO_ITALICS.clone()),
+        (false, false) => span,
+    }
+}
@@ -0,0 +1,51 @@
+use iced::advanced::{clipboard, widget, Clipboard, Layout, Shell};
+use iced::keyboard::{self, Key};
+use iced::{event, mouse, Event, Rectangle};
+
+use super::{decorate, Element, Renderer};
+
+/// Intercepts pastes into `base` that `on_paste` turns into a message, such
+/// as pastes of many lines. Other pastes reach `base` as usual.
+pub fn paste<'a, Message>(
+    base: impl Into<Element<'a, Message>>,
+    on_paste: impl Fn(String) -> Option<Message> + 'a,
+) -> Element<'a, Message>
+where
+    Message: 'a,
+{
+    decorate(base)
+        .on_event(
+            move |_state: &mut (),
+                  inner: &mut Element<'a, Message>,
+                  tree: &mut widget::Tree,
+                  event: Event,
+                  layout: Layout<'_>,
+                  cursor: mouse::Cursor,
+                  renderer: &Renderer,
+                  clipboard: &mut dyn Clipboard,
+                  shell: &mut Shell<'_, Message>,
+                  viewport: &Rectangle| {
+                if let Event::Keyboard(keyboard::Event::KeyPressed {
+                    key: Key::Character(c),
+                    modifiers,
+                    ..
+                }) = &event
+                {
+                    if modifiers.command() && c.as_str() == "v" {
+                        if let Some(message) = clipboard
+                            .read(clipboard::Kind::Standard)
+                            .and_then(&on_paste)
+                        {
+                            shell.publish(message);
+                            return event::Status::Captured;
+                        }
+                    }
+                }
+
+                inner.as_widget_mut().on_event(
+                    tree, event, layout, cursor, renderer, clipboard, shell, viewport,
+                )
+            },
+        )
+        .into()
+}
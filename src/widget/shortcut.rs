@@ -1,4 +1,4 @@
-use data::shortcut;
+use data::shortcut::{self, Mode, Normal};
 use iced::advanced::widget::Tree;
 use iced::advanced::{Clipboard, Layout, Shell};
 use iced::{event, keyboard, mouse, Event};
@@ -7,9 +7,17 @@ pub use data::shortcut::Command;
 
 use super::{decorate, Element, Renderer};
 
+#[derive(Debug, Default)]
+struct State {
+    /// First key of a normal mode sequence
+    pending: Option<char>,
+}
+
+/// `mode` is the current vim mode, `None` when vim mode is disabled.
 pub fn shortcut<'a, Message>(
     base: impl Into<Element<'a, Message>>,
     shortcuts: Vec<data::Shortcut>,
+    mode: Option<Mode>,
     on_press: impl Fn(Command) -> Message + 'a,
 ) -> Element<'a, Message>
 where
@@ -17,7 +25,7 @@ where
 {
     decorate(base)
         .on_event(
-            move |modifiers: &mut shortcut::Modifiers,
+            move |state: &mut State,
                   inner: &mut Element<'a, Message>,
                   tree: &mut Tree,
                   event: iced::Event,
@@ -27,22 +35,50 @@ where
                   clipboard: &mut dyn Clipboard,
                   shell: &mut Shell<'_, Message>,
                   viewport: &iced::Rectangle| {
-                match &event {
-                    Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
-                        let key_bind = shortcut::KeyBind::from((key.clone(), *modifiers));
+                if let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = &event
+                {
+                    let key_bind = shortcut::KeyBind::from((key.clone(), *modifiers));
 
-                        if let Some(command) = shortcuts
-                            .iter()
-                            .find_map(|shortcut| shortcut.execute(&key_bind))
+                    if let Some(command) = shortcuts
+                        .iter()
+                        .find_map(|shortcut| shortcut.execute(&key_bind))
+                    {
+                        state.pending = None;
+                        shell.publish((on_press)(command));
+                        return event::Status::Captured;
+                    }
+
+                    match (mode, key) {
+                        (
+                            Some(Mode::Insert),
+                            keyboard::Key::Named(keyboard::key::Named::Escape),
+                        ) => {
+                            // Escape still reaches the input
+                            shell.publish((on_press)(Command::NormalMode));
+                        }
+                        (Some(Mode::Normal), keyboard::Key::Character(c))
+                            if !(modifiers.command() || modifiers.alt()) =>
                         {
-                            shell.publish((on_press)(command));
+                            match shortcut::normal(state.pending.take(), c.as_str()) {
+                                Normal::Command(command) => {
+                                    shell.publish((on_press)(command));
+                                }
+                                Normal::Pending(key) => {
+                                    state.pending = Some(key);
+                                }
+                                Normal::Ignored => {}
+                            }
+
+                            // Never typed into the input in normal mode
                             return event::Status::Captured;
                         }
+                        (Some(Mode::Normal), keyboard::Key::Named(keyboard::key::Named::Enter)) => {
+                            state.pending = None;
+                            shell.publish((on_press)(Command::InsertMode));
+                            return event::Status::Captured;
+                        }
+                        _ => {}
                     }
-                    Event::Keyboard(keyboard::Event::ModifiersChanged(new_modifiers)) => {
-                        *modifiers = (*new_modifiers).into();
-                    }
-                    _ => {}
                 }
 
                 inner.as_widget_mut().on_event(
@@ -0,0 +1,319 @@
+//! Scales its content, text and all, independently of the rest of the UI.
+use std::slice;
+
+use iced::advanced::widget::{self, tree, Tree};
+use iced::advanced::{self, layout, overlay, renderer, Clipboard, Layout, Shell, Widget};
+use iced::{
+    event, keyboard, mouse, Element, Event, Length, Point, Rectangle, Size, Transformation, Vector,
+};
+
+pub fn zoom<'a, Message, Theme, Renderer>(
+    content: impl Into<Element<'a, Message, Theme, Renderer>>,
+    scale: f32,
+) -> Zoom<'a, Message, Theme, Renderer> {
+    Zoom {
+        content: content.into(),
+        scale,
+        on_scroll: None,
+    }
+}
+
+pub struct Zoom<'a, Message, Theme, Renderer> {
+    content: Element<'a, Message, Theme, Renderer>,
+    scale: f32,
+    on_scroll: Option<Box<dyn Fn(bool) -> Message + 'a>>,
+}
+
+impl<'a, Message, Theme, Renderer> Zoom<'a, Message, Theme, Renderer> {
+    /// Sets the message produced when scrolling with the command key held,
+    /// `true` when zooming in.
+    pub fn on_scroll(mut self, on_scroll: impl Fn(bool) -> Message + 'a) -> Self {
+        self.on_scroll = Some(Box::new(on_scroll));
+        self
+    }
+
+    /// Maps the content's layout onto the screen, scaling from `origin`.
+    fn transformation(&self, origin: Point) -> Transformation {
+        Transformation::translate(origin.x, origin.y)
+            * Transformation::scale(self.scale)
+            * Transformation::translate(-origin.x, -origin.y)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    modifiers: keyboard::Modifiers,
+}
+
+impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for Zoom<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Theme: 'a,
+    Renderer: advanced::Renderer + 'a,
+{
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn size_hint(&self) -> Size<Length> {
+        self.content.as_widget().size_hint()
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(slice::from_ref(&self.content));
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        // Content is laid out in the space it will have once scaled
+        let limits = layout::Limits::new(
+            limits.min() * (1.0 / self.scale),
+            limits.max() * (1.0 / self.scale),
+        );
+
+        let content = self
+            .content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, &limits);
+
+        layout::Node::with_children(content.size() * self.scale, vec![content])
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let transformation = self.transformation(layout.position());
+        let content = layout.children().next().unwrap();
+
+        renderer.with_transformation(transformation, |renderer| {
+            self.content.as_widget().draw(
+                &tree.children[0],
+                renderer,
+                theme,
+                style,
+                content,
+                cursor * transformation.inverse(),
+                &(*viewport * transformation.inverse()),
+            );
+        });
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<()>,
+    ) {
+        self.content.as_widget().operate(
+            &mut tree.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            operation,
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+
+        match &event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = *modifiers;
+            }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) if state.modifiers.command() => {
+                if let Some(on_scroll) = &self.on_scroll {
+                    if cursor.is_over(layout.bounds()) {
+                        let y = match delta {
+                            mouse::ScrollDelta::Lines { y, .. }
+                            | mouse::ScrollDelta::Pixels { y, .. } => *y,
+                        };
+
+                        if y != 0.0 {
+                            shell.publish(on_scroll(y > 0.0));
+                        }
+
+                        return event::Status::Captured;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let transformation = self.transformation(layout.position());
+
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout.children().next().unwrap(),
+            cursor * transformation.inverse(),
+            renderer,
+            clipboard,
+            shell,
+            &(*viewport * transformation.inverse()),
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let transformation = self.transformation(layout.position());
+
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout.children().next().unwrap(),
+            cursor * transformation.inverse(),
+            &(*viewport * transformation.inverse()),
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        let content = self.content.as_widget_mut().overlay(
+            &mut tree.children[0],
+            layout.children().next().unwrap(),
+            renderer,
+            translation,
+        )?;
+
+        Some(overlay::Element::new(Box::new(Overlay {
+            content,
+            origin: layout.position() + translation,
+            scale: self.scale,
+        })))
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<Zoom<'a, Message, Theme, Renderer>>
+    for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Theme: 'a,
+    Renderer: advanced::Renderer + 'a,
+{
+    fn from(zoom: Zoom<'a, Message, Theme, Renderer>) -> Self {
+        Element::new(zoom)
+    }
+}
+
+/// Overlays of the content keep their size, but are moved to where their
+/// position is once scaled.
+struct Overlay<'a, Message, Theme, Renderer> {
+    content: overlay::Element<'a, Message, Theme, Renderer>,
+    origin: Point,
+    scale: f32,
+}
+
+impl<'a, Message, Theme, Renderer> overlay::Overlay<Message, Theme, Renderer>
+    for Overlay<'a, Message, Theme, Renderer>
+where
+    Renderer: advanced::Renderer,
+{
+    fn layout(&mut self, renderer: &Renderer, bounds: Size) -> layout::Node {
+        let node = self.content.layout(renderer, bounds);
+        let position = node.bounds().position();
+        let scaled = self.origin + (position - self.origin) * self.scale;
+
+        node.translate(scaled - position)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+    ) {
+        self.content.draw(renderer, theme, style, layout, cursor);
+    }
+
+    fn operate(
+        &mut self,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<()>,
+    ) {
+        self.content.operate(layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        self.content
+            .on_event(event, layout, cursor, renderer, clipboard, shell)
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content
+            .mouse_interaction(layout, cursor, viewport, renderer)
+    }
+
+    fn is_over(&self, layout: Layout<'_>, renderer: &Renderer, cursor_position: Point) -> bool {
+        self.content.is_over(layout, renderer, cursor_position)
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.content.overlay(layout, renderer)
+    }
+}
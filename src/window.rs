@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use futures::{stream::BoxStream, Stream, StreamExt};
 use iced::{advanced::graphics::futures::subscription, Point, Size, Subscription};
 
@@ -10,6 +12,7 @@ pub struct Window {
     pub position: Option<Point>,
     pub size: Size,
     pub focused: bool,
+    unfocused_at: Option<Instant>,
 }
 
 impl Window {
@@ -19,13 +22,31 @@ impl Window {
             position: None,
             size: Size::default(),
             focused: false,
+            unfocused_at: None,
+        }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused {
+            self.unfocused_at = None;
+        } else if self.focused || self.unfocused_at.is_none() {
+            self.unfocused_at = Some(Instant::now());
         }
+
+        self.focused = focused;
+    }
+
+    /// How long the window has been unfocused for, if it is.
+    pub fn unfocused_for(&self) -> Option<Duration> {
+        self.unfocused_at
+            .filter(|_| !self.focused)
+            .map(|unfocused_at| unfocused_at.elapsed())
     }
 
     pub fn opened(&mut self, position: Option<Point>, size: Size) {
         self.position = position;
         self.size = size;
-        self.focused = true;
+        self.set_focused(true);
     }
 }
 